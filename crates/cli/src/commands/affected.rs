@@ -0,0 +1,44 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::workspace_manager::{RunOptions, WorkspaceManager};
+
+use crate::events::JsonLog;
+
+pub async fn execute(
+    manager: &WorkspaceManager,
+    task: &str,
+    base: Option<&str>,
+    dry_run: bool,
+    log: Option<&JsonLog>,
+) -> Result<()> {
+    if dry_run {
+        let affected = manager
+            .affected_projects(base)
+            .context("Failed to detect affected projects")?;
+        let mut plan = manager
+            .get_execution_plan(task)
+            .context("Failed to get execution plan")?;
+        plan.compatible_projects
+            .retain(|project| affected.contains(project));
+
+        let heading = match base {
+            Some(base) => format!("Projects affected since {} with task", base),
+            None => "Projects affected with task".to_string(),
+        };
+        println!("{} {}", heading.bold(), plan.task_name.cyan());
+        if plan.compatible_projects.is_empty() {
+            println!("  {}", "(none)".dimmed());
+        }
+        for project in &plan.compatible_projects {
+            println!("  {}", project);
+        }
+        return Ok(());
+    }
+
+    let options = RunOptions {
+        affected: true,
+        affected_since: base.map(str::to_string),
+        ..RunOptions::default()
+    };
+    super::run::execute(manager, task, &options, log).await
+}
@@ -0,0 +1,21 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub async fn execute(manager: &WorkspaceManager) -> Result<()> {
+    println!("{}", "Bootstrapping workspace".bold());
+
+    manager
+        .bootstrap()
+        .await
+        .context("Failed to bootstrap workspace")?;
+
+    println!();
+    println!(
+        "{} {}",
+        "✓".green().bold(),
+        "Workspace bootstrapped successfully!".green().bold()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::bundle::{self, BundleFormat, LOCKFILE};
+use marty_core::platform::PlatformInfo;
+use marty_core::plugin_cache::PluginCache;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub async fn execute(
+    manager: &WorkspaceManager,
+    output: &Path,
+    targets: &[String],
+    oci: bool,
+) -> Result<()> {
+    let platforms = if targets.is_empty() {
+        vec![PlatformInfo::current()]
+    } else {
+        targets
+            .iter()
+            .map(|target| {
+                PlatformInfo::from_target(target).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Plugins aren't published for '{}'. Supported targets: {}",
+                        target,
+                        PlatformInfo::supported_targets().join(", ")
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+    let format = if oci {
+        BundleFormat::Oci
+    } else {
+        BundleFormat::Directory
+    };
+
+    let cache = PluginCache::new(&manager.workspace.root).with_signing(
+        manager
+            .workspace_config
+            .plugin_signing
+            .clone()
+            .unwrap_or_default(),
+    );
+    let plugin_configs = manager
+        .workspace_config
+        .plugins
+        .as_ref()
+        .cloned()
+        .unwrap_or_default();
+    let lock = bundle::create(&cache, &plugin_configs, &platforms, output, format)
+        .await
+        .context("Failed to create bundle")?;
+
+    println!();
+    println!("{}", "Bundled plugins:".bold());
+    if lock.plugins.is_empty() {
+        println!("  {}", "none".dimmed());
+    }
+    for plugin in &lock.plugins {
+        println!("  {}", plugin.source);
+        for binary in &plugin.binaries {
+            println!(
+                "    {} {}",
+                binary.target.as_deref().unwrap_or("as configured").cyan(),
+                binary.file.dimmed()
+            );
+        }
+    }
+
+    println!();
+    let what = if oci { "OCI image layout" } else { "Bundle" };
+    println!(
+        "{} {} written to {}",
+        "✓".green().bold(),
+        what,
+        output.display()
+    );
+    if oci {
+        println!(
+            "  The image holds the bundle under /marty; copy it into a CI image or run /marty/bin/marty."
+        );
+    } else {
+        println!(
+            "  Run {} there; it loads plugins only from the bundle, as pinned in {}.",
+            output.join("bin").join("marty").display(),
+            LOCKFILE
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::workspace_manager::WorkspaceManager;
+
+use crate::CacheCommands;
+
+pub fn execute(manager: &WorkspaceManager, command: CacheCommands) -> Result<()> {
+    match command {
+        CacheCommands::Stats => show_stats(manager),
+        CacheCommands::Clear => {
+            manager
+                .clear_cache()
+                .context("Failed to clear task cache")?;
+            println!("Task cache cleared successfully.");
+            Ok(())
+        }
+    }
+}
+
+fn show_stats(manager: &WorkspaceManager) -> Result<()> {
+    let stats = manager
+        .get_cache_stats()
+        .context("Failed to read task cache")?;
+
+    println!("{}", "Task Cache:".bold().underline());
+    if stats.enabled {
+        println!("  {} {}", "Status:".bright_black(), "enabled".green());
+    } else {
+        println!("  {} {}", "Status:".bright_black(), "disabled".yellow());
+    }
+    println!(
+        "  {} {} {}",
+        "Current namespace:".bright_black(),
+        stats.current_namespace.id().cyan(),
+        format_dimensions(&stats.current_namespace.dimensions).dimmed()
+    );
+    println!();
+
+    if stats.namespaces.is_empty() {
+        println!("No cached task results found.");
+        return Ok(());
+    }
+
+    for namespace in &stats.namespaces {
+        let marker = if namespace.id == stats.current_namespace.id() {
+            " (current)".green().to_string()
+        } else {
+            String::new()
+        };
+        println!("{}{}", namespace.id.blue().bold(), marker);
+        println!(
+            "  {} {}",
+            "dimensions:".dimmed(),
+            format_dimensions(&namespace.dimensions)
+        );
+        println!(
+            "  {} {} ({} bytes)",
+            "entries:".dimmed(),
+            namespace.entries,
+            namespace.size_bytes
+        );
+    }
+
+    Ok(())
+}
+
+fn format_dimensions(dimensions: &BTreeMap<String, String>) -> String {
+    if dimensions.is_empty() {
+        return "none".to_string();
+    }
+    dimensions
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
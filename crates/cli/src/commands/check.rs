@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::messages::Message;
+use marty_core::results::CheckStatus;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub fn execute(manager: &WorkspaceManager, tasks: &[String], json: bool) -> Result<()> {
+    let result = manager.check(tasks).context("Failed to check workspace")?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        for check in &result.checks {
+            let status = match check.status {
+                CheckStatus::Pass => "PASS".green(),
+                CheckStatus::Fail => "FAIL".red(),
+            };
+            match check.details.as_slice() {
+                [detail] => println!("  [{}] {}: {}", status, check.name.bold(), detail),
+                details => {
+                    println!("  [{}] {}", status, check.name.bold());
+                    for detail in details {
+                        println!("      {}", detail);
+                    }
+                }
+            }
+        }
+        for hint in &result.hints {
+            println!("  {} {}", "hint:".yellow(), hint);
+        }
+        println!();
+    }
+
+    let failed = result
+        .checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{}",
+            Message::new("check.failed")
+                .with("failed", failed)
+                .with("count", result.checks.len())
+        );
+    }
+    if !json {
+        println!(
+            "{} {}",
+            "✓".green().bold(),
+            Message::new("check.passed")
+                .with("count", result.checks.len())
+                .to_string()
+                .green()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::plan_diff::{diff_plans, PlanSide};
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub async fn execute(
+    manager: &WorkspaceManager,
+    target: &str,
+    base: &PlanSide,
+    head: &PlanSide,
+) -> Result<()> {
+    if base.git_ref == head.git_ref && base.config_file == head.config_file {
+        return Err(anyhow::anyhow!(
+            "Nothing to compare: pass --base and/or --base-config (or the --head equivalents)"
+        ));
+    }
+
+    let before = manager
+        .plan_snapshot(target, base)
+        .await
+        .with_context(|| format!("Failed to plan {}", base.label()))?;
+    let after = manager
+        .plan_snapshot(target, head)
+        .await
+        .with_context(|| format!("Failed to plan {}", head.label()))?;
+    let diff = diff_plans(&before, &after);
+
+    println!(
+        "{} {}: {} → {}",
+        "Plan for".bold(),
+        target.cyan(),
+        before.label,
+        after.label
+    );
+
+    if diff.added.is_empty() && diff.removed.is_empty() && diff.moved.is_empty() {
+        println!("  {}", "Same projects in the same order".dimmed());
+    }
+    for (project, level) in &diff.added {
+        println!("  {} {} (level {})", "+".green().bold(), project, level + 1);
+    }
+    for (project, level) in &diff.removed {
+        println!("  {} {} (level {})", "-".red().bold(), project, level + 1);
+    }
+    for (project, from, to) in &diff.moved {
+        println!(
+            "  {} {}: level {} → {}",
+            "~".yellow().bold(),
+            project,
+            from + 1,
+            to + 1
+        );
+    }
+
+    if let (Some(before_hits), Some(after_hits)) = (&before.cache_hits, &after.cache_hits) {
+        println!();
+        println!("{}", "Cache predictions:".bold());
+        for (project, was, now) in &diff.cache_changes {
+            println!("  {}: {} → {}", project, hit_label(*was), hit_label(*now));
+        }
+        let count = |hits: &std::collections::BTreeMap<String, bool>| {
+            hits.values().filter(|hit| **hit).count()
+        };
+        println!(
+            "  {}",
+            format!(
+                "{} of {} cached before, {} of {} after",
+                count(before_hits),
+                before_hits.len(),
+                count(after_hits),
+                after_hits.len()
+            )
+            .dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn hit_label(hit: bool) -> ColoredString {
+    if hit {
+        "cached".green()
+    } else {
+        "runs".yellow()
+    }
+}
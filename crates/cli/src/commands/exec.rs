@@ -0,0 +1,27 @@
+use anyhow::Result;
+use colored::*;
+use marty_core::workspace_manager::{RunOptions, WorkspaceManager};
+
+use super::run::{self, SummaryCollector};
+use crate::events::JsonLog;
+
+pub async fn execute(
+    manager: &WorkspaceManager,
+    command: &str,
+    projects: &[String],
+    options: &RunOptions,
+    log: Option<&JsonLog>,
+) -> Result<()> {
+    if options.dry_run {
+        println!("{} {}", "Dry run of".bold(), command.cyan());
+    } else {
+        println!("{} {}", "Running".bold(), command.cyan());
+    }
+    println!();
+
+    let collector = SummaryCollector::default();
+    let outcome = manager
+        .exec(command, projects, options, run::hooks(log, &collector))
+        .await;
+    run::report(outcome, &collector, options.dry_run)
+}
@@ -0,0 +1,36 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use marty_core::workspace_manager::WorkspaceManager;
+
+use crate::ExportCommands;
+
+/// How exported records are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per line, as BigQuery and most log pipelines ingest
+    Ndjson,
+    /// A single JSON array
+    Json,
+}
+
+pub fn execute(manager: &WorkspaceManager, command: ExportCommands) -> Result<()> {
+    match command {
+        ExportCommands::Metadata { format } => metadata(manager, format),
+    }
+}
+
+fn metadata(manager: &WorkspaceManager, format: ExportFormat) -> Result<()> {
+    let records = manager
+        .project_metadata()
+        .context("Failed to collect project metadata")?;
+
+    match format {
+        ExportFormat::Ndjson => {
+            for record in &records {
+                println!("{}", serde_json::to_string(record)?);
+            }
+        }
+        ExportFormat::Json => println!("{}", serde_json::to_string_pretty(&records)?),
+    }
+    Ok(())
+}
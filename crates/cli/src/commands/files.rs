@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::colors::project_color;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub fn execute(manager: &WorkspaceManager, project: Option<&str>, json: bool) -> Result<()> {
+    match project {
+        Some(project) => list_project_files(manager, project, json),
+        None => list_ownership(manager, json),
+    }
+}
+
+fn list_project_files(manager: &WorkspaceManager, project: &str, json: bool) -> Result<()> {
+    let files = manager
+        .get_project_files(project)
+        .context("Failed to list project files")?;
+
+    if json {
+        let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        println!("{}", serde_json::to_string_pretty(&files)?);
+        return Ok(());
+    }
+
+    for file in files {
+        println!("{}", file.display());
+    }
+    Ok(())
+}
+
+fn list_ownership(manager: &WorkspaceManager, json: bool) -> Result<()> {
+    let result = manager
+        .get_file_ownership()
+        .context("Failed to map file ownership")?;
+
+    if json {
+        let map: serde_json::Map<String, serde_json::Value> = result
+            .files
+            .iter()
+            .map(|(file, owner)| {
+                let owner = owner
+                    .clone()
+                    .map(serde_json::Value::String)
+                    .unwrap_or(serde_json::Value::Null);
+                (file.display().to_string(), owner)
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&map)?);
+        return Ok(());
+    }
+
+    for (file, owner) in &result.files {
+        match owner {
+            Some(owner) => println!("{} {}", owner.color(project_color(owner)), file.display()),
+            None => println!("{} {}", "-".dimmed(), file.display()),
+        }
+    }
+    Ok(())
+}
@@ -1,13 +1,23 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
+use marty_core::colors::project_color;
+use marty_core::workspace::ProjectKind;
 use marty_core::workspace_manager::WorkspaceManager;
 
-pub fn execute(manager: &WorkspaceManager) -> Result<()> {
+pub fn execute(
+    manager: &WorkspaceManager,
+    group_by_dir: Option<usize>,
+    kind: Option<ProjectKind>,
+) -> Result<()> {
+    if let Some(depth) = group_by_dir {
+        return execute_grouped(manager, depth);
+    }
+
     println!("{}", "Project Dependency Graph:".bold().underline());
 
     let result = manager
         .get_dependency_graph()
-        .map_err(|e| anyhow::anyhow!("Failed to get dependency graph: {}", e))?;
+        .context("Failed to get dependency graph")?;
 
     if result.graph.is_none() {
         println!("No dependency graph available");
@@ -36,8 +46,26 @@ pub fn execute(manager: &WorkspaceManager) -> Result<()> {
             format!("Circular dependencies detected: {}", cycles_description).yellow()
         );
     }
+    for plan in manager.suggest_cycle_breaks() {
+        for cut in &plan.breaks {
+            println!(
+                "  {} moving what `{}` uses out of `{}` ({} dependency) would break {} of {} cycle(s)",
+                "Suggestion:".cyan(),
+                cut.project,
+                cut.dependency,
+                cut.source.as_deref().unwrap_or("unknown"),
+                cut.cycles,
+                plan.cycles
+            );
+        }
+    }
 
     for (node_index, node_weight) in graph.node_indices().zip(graph.node_weights()) {
+        if let Some(kind) = kind {
+            if manager.project_kind(node_weight)? != Some(kind) {
+                continue;
+            }
+        }
         println!("{}", node_weight.blue().bold());
 
         let mut deps = Vec::new();
@@ -57,3 +85,81 @@ pub fn execute(manager: &WorkspaceManager) -> Result<()> {
 
     Ok(())
 }
+
+/// Print the chains of dependencies through which `from` depends on `to`, with the plugin
+/// that reported each edge
+pub fn execute_why(manager: &WorkspaceManager, from: &str, to: &str, all: bool) -> Result<()> {
+    let result = manager
+        .dependency_paths(from, to, all)
+        .context("Failed to find dependency paths")?;
+
+    if result.paths.is_empty() {
+        println!(
+            "{} does not depend on {}",
+            from.color(project_color(from)).bold(),
+            to.color(project_color(to)).bold()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("Why {} depends on {}:", from, to)
+            .bold()
+            .underline()
+    );
+    for path in &result.paths {
+        let mut line = path[0].color(project_color(&path[0])).bold().to_string();
+        for edge in path.windows(2) {
+            let source = result
+                .edge_sources
+                .get(&(edge[0].clone(), edge[1].clone()))
+                .map(String::as_str)
+                .unwrap_or("unknown");
+            line.push_str(&format!(
+                " {} {}",
+                format!("-[{}]->", source).dimmed(),
+                edge[1].color(project_color(&edge[1]))
+            ));
+        }
+        println!("  {}", line);
+    }
+    if all {
+        println!();
+        println!("{}", format!("{} chain(s)", result.paths.len()).dimmed());
+    }
+
+    Ok(())
+}
+
+fn execute_grouped(manager: &WorkspaceManager, depth: usize) -> Result<()> {
+    println!(
+        "{}",
+        format!("Directory Dependency Graph (depth {}):", depth)
+            .bold()
+            .underline()
+    );
+
+    let result = manager
+        .get_grouped_dependency_graph(depth)
+        .context("Failed to get dependency graph")?;
+
+    for (group, members) in &result.groups {
+        println!(
+            "{} {}",
+            group.blue().bold(),
+            format!("({} projects)", members.len()).dimmed()
+        );
+
+        match result.edges.get(group) {
+            Some(deps) => {
+                let deps = deps.iter().cloned().collect::<Vec<_>>();
+                println!("  {} {}", "depends on:".dimmed(), deps.join(", "));
+            }
+            None => println!("  {}", "no dependencies".dimmed()),
+        }
+        println!();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::dependency_rules::ViolationKind;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub fn execute(manager: &WorkspaceManager) -> Result<()> {
+    let result = manager
+        .lint_dependencies()
+        .context("Failed to check dependency rules")?;
+
+    if result.checked_projects == 0 {
+        println!("No projects declare allowedDependencies or deniedDependencies.");
+        return Ok(());
+    }
+
+    if result.violations.is_empty() {
+        println!(
+            "{} {}",
+            "✓".green().bold(),
+            format!(
+                "Dependency rules satisfied for {} project(s)",
+                result.checked_projects
+            )
+            .green()
+        );
+        return Ok(());
+    }
+
+    println!("{}", "Dependency rule violations:".red().bold());
+    for violation in &result.violations {
+        let reason = match &violation.kind {
+            ViolationKind::Denied(pattern) => format!("matches denied pattern '{}'", pattern),
+            ViolationKind::NotAllowed => "is not in allowedDependencies".to_string(),
+        };
+        println!(
+            "  {} -> {} {}",
+            violation.project.bold(),
+            violation.dependency.yellow(),
+            reason.dimmed()
+        );
+    }
+
+    Err(anyhow::anyhow!(
+        "{} dependency rule violation(s) found",
+        result.violations.len()
+    ))
+}
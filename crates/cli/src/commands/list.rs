@@ -1,9 +1,79 @@
-use anyhow::Result;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
 use colored::*;
+use marty_core::colors::project_color;
+use marty_core::results::ProjectListResult;
+use marty_core::workspace::ProjectKind;
 use marty_core::workspace_manager::WorkspaceManager;
+use serde::Serialize;
+
+use crate::events::{Event, JsonLog};
+
+/// How `marty list` prints projects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListOutput {
+    /// Human-readable listing
+    Pretty,
+    /// One JSON object per project and line
+    Json,
+    /// One project name per entry
+    Names,
+    /// One project directory per entry
+    Paths,
+}
+
+/// A project as written by `marty list --json`
+#[derive(Serialize)]
+struct ProjectLine<'a> {
+    name: &'a str,
+    path: &'a Path,
+    tracked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    discovered_by: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<ProjectKind>,
+    /// `#rrggbb` of the color the project is shown in
+    color: Option<String>,
+}
+
+pub fn execute(
+    manager: &WorkspaceManager,
+    inferred: bool,
+    kind: Option<ProjectKind>,
+    output: ListOutput,
+    null_terminated: bool,
+    log: Option<&JsonLog>,
+) -> Result<()> {
+    let mut result = manager.list_projects(inferred)?;
+    if let Some(kind) = kind {
+        result.explicit_projects.retain(|p| p.kind == Some(kind));
+        result.inferred_projects.retain(|p| p.kind == Some(kind));
+    }
 
-pub fn execute(manager: &WorkspaceManager, inferred: bool) -> Result<()> {
-    let result = manager.list_projects(inferred)?;
+    if let Some(log) = log {
+        for line in project_lines(&result, inferred) {
+            log.emit(&Event::Project {
+                name: line.name,
+                path: line.path,
+                tracked: line.tracked,
+                discovered_by: line.discovered_by,
+                kind: line.kind,
+                color: line.color.as_deref(),
+            });
+        }
+        return Ok(());
+    }
+
+    if output != ListOutput::Pretty {
+        let lines = project_lines(&result, inferred);
+        return match write_lines(&lines, output, null_terminated) {
+            // The reader (e.g. `head`) stopped early, which is not an error
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            outcome => outcome.context("Failed to write project list"),
+        };
+    }
 
     let heading = if inferred {
         "Projects (inferred)"
@@ -28,14 +98,22 @@ pub fn execute(manager: &WorkspaceManager, inferred: bool) -> Result<()> {
                 .any(|tracked| tracked.name == project.name);
 
             if is_tracked {
-                println!("{} {}", project.name.blue().bold(), "[marty.yml]".green());
+                print!(
+                    "{} {}",
+                    project.name.color(project_color(&project.name)).bold(),
+                    "[marty.yml]".green()
+                );
             } else {
-                println!(
+                print!(
                     "{} {}",
-                    project.name.cyan(),
+                    project.name.color(project_color(&project.name)),
                     format!("Inferred Project ({} plugin)", project.discovered_by).dimmed()
                 );
             }
+            match project.kind {
+                Some(kind) => println!(" {}", kind.to_string().magenta()),
+                None => println!(),
+            }
         }
     } else {
         let mut tracked_projects: Vec<_> = result.explicit_projects.iter().collect();
@@ -47,9 +125,87 @@ pub fn execute(manager: &WorkspaceManager, inferred: bool) -> Result<()> {
         }
 
         for project in tracked_projects {
-            println!("{}", project.name.blue().bold());
+            match project.kind {
+                Some(kind) => println!(
+                    "{} {}",
+                    project.name.color(project_color(&project.name)).bold(),
+                    kind.to_string().magenta()
+                ),
+                None => println!(
+                    "{}",
+                    project.name.color(project_color(&project.name)).bold()
+                ),
+            }
         }
     }
 
     Ok(())
 }
+
+/// Projects sorted by name; with `inferred`, every inference of a name is kept
+fn project_lines(result: &ProjectListResult, inferred: bool) -> Vec<ProjectLine<'_>> {
+    let color = |name: &str| result.project_colors.get(name).map(|color| color.hex());
+    let mut lines: Vec<ProjectLine> = if inferred {
+        result
+            .inferred_projects
+            .iter()
+            .map(|project| ProjectLine {
+                name: &project.name,
+                path: &project.path,
+                tracked: project.is_tracked,
+                discovered_by: Some(&project.discovered_by),
+                kind: project.kind,
+                color: color(&project.name),
+            })
+            .collect()
+    } else {
+        result
+            .explicit_projects
+            .iter()
+            .map(|project| ProjectLine {
+                name: &project.name,
+                path: &project.path,
+                tracked: true,
+                discovered_by: None,
+                kind: project.kind,
+                color: color(&project.name),
+            })
+            .collect()
+    };
+    lines.sort_by(|a, b| a.name.cmp(b.name));
+    lines
+}
+
+/// Stream the projects to stdout, one entry at a time
+fn write_lines(lines: &[ProjectLine], output: ListOutput, null_terminated: bool) -> io::Result<()> {
+    let terminator: &[u8] = if null_terminated { b"\0" } else { b"\n" };
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut previous: Option<&[u8]> = None;
+    for line in lines {
+        match output {
+            ListOutput::Json => {
+                serde_json::to_writer(&mut out, line)?;
+                out.write_all(b"\n")?;
+                continue;
+            }
+            ListOutput::Names | ListOutput::Pretty => {
+                let entry = line.name.as_bytes();
+                // A project inferred by several plugins is listed once
+                if previous != Some(entry) {
+                    out.write_all(entry)?;
+                    out.write_all(terminator)?;
+                }
+                previous = Some(entry);
+            }
+            ListOutput::Paths => {
+                let entry = line.path.as_os_str().as_encoded_bytes();
+                if previous != Some(entry) {
+                    out.write_all(entry)?;
+                    out.write_all(terminator)?;
+                }
+                previous = Some(entry);
+            }
+        }
+    }
+    out.flush()
+}
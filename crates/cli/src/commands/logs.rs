@@ -0,0 +1,34 @@
+use std::io::{self, Write};
+
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::task_logs;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub fn execute(
+    manager: &WorkspaceManager,
+    project: &str,
+    task: Option<&str>,
+    path_only: bool,
+) -> Result<()> {
+    if manager.workspace.project(project).is_none() {
+        anyhow::bail!("Project '{}' not found", project);
+    }
+    let path =
+        task_logs::latest(&manager.workspace.root, project, task).context("Failed to find log")?;
+
+    if path_only {
+        println!("{}", path.display());
+        return Ok(());
+    }
+
+    let content = std::fs::read(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    eprintln!("{}", format!("==> {} <==", path.display()).bright_black());
+    let mut stdout = io::stdout().lock();
+    match stdout.write_all(&content).and_then(|_| stdout.flush()) {
+        // The reader (e.g. `head`) stopping early is not an error
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
@@ -1,5 +1,21 @@
+pub mod affected;
+pub mod bootstrap;
+pub mod bundle;
+pub mod cache;
+pub mod check;
+pub mod diff_plan;
+pub mod exec;
+pub mod export;
+pub mod files;
 pub mod graph;
+pub mod lint_deps;
 pub mod list;
+pub mod logs;
 pub mod plan;
 pub mod plugin;
+pub mod project;
+pub mod repro;
 pub mod run;
+pub mod snapshot;
+pub mod sparse;
+pub mod tasks;
@@ -1,18 +1,51 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
 use marty_core::workspace_manager::WorkspaceManager;
 
-pub async fn execute(manager: &WorkspaceManager, target: &str) -> Result<()> {
+use crate::events::{Event, JsonLog};
+
+pub async fn execute(
+    manager: &WorkspaceManager,
+    target: &str,
+    log: Option<&JsonLog>,
+) -> Result<()> {
     println!("{} {}", "Execution plan for".bold(), target.cyan());
 
     // Get execution plan from workspace manager
     let execution_plan = manager
         .get_execution_plan(target)
-        .map_err(|e| anyhow::anyhow!("Failed to get execution plan: {}", e))?;
+        .context("Failed to get execution plan")?;
+
+    let task_configs = manager
+        .plan_task_configs(&execution_plan)
+        .context("Failed to resolve tasks")?;
 
     println!("\n{}:", "Execution order".bold());
     for (i, project) in execution_plan.compatible_projects.iter().enumerate() {
         println!("  {}. {}:{}", i + 1, project, execution_plan.task_name);
+        let task_config = task_configs.get(project);
+        if let Some(log) = log {
+            log.emit(&Event::PlannedTask {
+                position: i + 1,
+                project,
+                task: &execution_plan.task_name,
+                inputs: task_config.and_then(|config| config.inputs.as_deref()),
+                outputs: task_config.and_then(|config| config.outputs.as_deref()),
+            });
+        }
+        let Some(task_config) = task_config else {
+            continue;
+        };
+        if let Some(inputs) = &task_config.inputs {
+            println!("       {} {}", "inputs:".bright_black(), inputs.join(", "));
+        }
+        if let Some(outputs) = &task_config.outputs {
+            println!(
+                "       {} {}",
+                "outputs:".bright_black(),
+                outputs.join(", ")
+            );
+        }
     }
 
     Ok(())
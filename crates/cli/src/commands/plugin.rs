@@ -1,25 +1,46 @@
 use anyhow::{Context, Result};
+use colored::Colorize;
 use marty_core::{
-    platform::PlatformInfo, plugin_cache::PluginCache, workspace_manager::WorkspaceManager,
+    builtin_plugins,
+    bundle::Bundle,
+    platform::PlatformInfo,
+    plugin_cache::{PluginCache, PluginFileInfo, PluginStatus},
+    plugin_conformance::{run_conformance, ConformanceStatus},
+    plugin_options::{describe_options, example_yaml, suggested_tasks_yaml},
+    plugin_registry::{add_plugin_entry, RegistryIndex, DEFAULT_REGISTRY_URL},
+    plugin_runtime::load_plugin,
+    workspace_manager::WorkspaceManager,
 };
+use marty_plugin_protocol::MartyPlugin;
 use std::path::Path;
+use std::time::SystemTime;
 
 use crate::PluginCommands;
 
 pub async fn execute(manager: &WorkspaceManager, command: PluginCommands) -> Result<()> {
-    let cache = PluginCache::new(&manager.workspace.root);
+    let cache = PluginCache::new(&manager.workspace.root)
+        .with_signing(
+            manager
+                .workspace_config
+                .plugin_signing
+                .clone()
+                .unwrap_or_default(),
+        )
+        .with_bundle(Bundle::locate()?)
+        .with_limits(
+            manager
+                .workspace_config
+                .plugin_cache
+                .clone()
+                .unwrap_or_default(),
+        );
 
     match command {
         PluginCommands::List => {
-            let cached_plugins = cache.list_cached_plugins()?;
-            if cached_plugins.is_empty() {
-                println!("No cached plugins found.");
-            } else {
-                println!("Cached plugins:");
-                for (name, path) in cached_plugins {
-                    println!("  {} -> {}", name, path.display());
-                }
-            }
+            list_plugins(manager, &cache)?;
+        }
+        PluginCommands::Prune { dry_run } => {
+            prune_plugins(manager, &cache, dry_run)?;
         }
         PluginCommands::Clear => {
             cache.clear_cache().await?;
@@ -50,9 +71,27 @@ pub async fn execute(manager: &WorkspaceManager, command: PluginCommands) -> Res
                 }
             }
         }
+        PluginCommands::Search { term } => {
+            search_registry(manager, &term).await?;
+        }
+        PluginCommands::Add { name, version } => {
+            add_plugin(manager, &name, version.as_deref()).await?;
+        }
+        PluginCommands::Info { key } => {
+            let plugin = find_plugin(manager, &cache, &key).await?;
+            print_plugin_header(plugin.as_ref());
+            print_display_metadata(plugin.as_ref(), "");
+        }
+        PluginCommands::Options { key } => {
+            let plugin = find_plugin(manager, &cache, &key).await?;
+            print_plugin_details(plugin.as_ref())?;
+        }
         PluginCommands::Validate { path, name } => {
             validate_plugin(&path, name.as_deref()).await?;
         }
+        PluginCommands::Conformance { path } => {
+            conformance(&path)?;
+        }
         PluginCommands::CheckRelease {
             github_repo,
             plugin,
@@ -73,6 +112,386 @@ pub async fn execute(manager: &WorkspaceManager, command: PluginCommands) -> Res
     Ok(())
 }
 
+fn list_plugins(manager: &WorkspaceManager, cache: &PluginCache) -> Result<()> {
+    let plugin_configs = manager
+        .workspace_config
+        .plugins
+        .as_ref()
+        .cloned()
+        .unwrap_or_default();
+    let listing = cache.plugin_listing(&plugin_configs)?;
+
+    if listing.plugins.is_empty() {
+        println!("No plugins configured in workspace.yml.");
+    } else {
+        println!("{}", "Configured plugins:".bold().underline());
+    }
+    for (config, plugin) in plugin_configs.iter().zip(&listing.plugins) {
+        let loaded = if builtin_plugins::is_builtin(config) {
+            builtin_plugins::resolve(config).ok()
+        } else {
+            plugin
+                .file
+                .as_ref()
+                .and(plugin.path.as_deref())
+                .and_then(|path| load_plugin(path, &manager.workspace.root).ok())
+        };
+        let status = match plugin.status {
+            PluginStatus::Cached => "cached".green(),
+            PluginStatus::NeedsDownload => "needs download".yellow(),
+            PluginStatus::Local => "local".cyan(),
+            PluginStatus::Builtin => "built in".cyan(),
+            PluginStatus::Missing => "missing".red(),
+            PluginStatus::Disabled => "DISABLED".red().bold(),
+        };
+        println!("  {} [{}]", plugin.source.bold(), status);
+        let reported = loaded.as_deref().and_then(|loaded| loaded.version());
+        match (&plugin.version, reported) {
+            (Some(version), Some(reported)) if version.trim_start_matches('v') != reported => {
+                println!(
+                    "    {} {} (plugin reports {})",
+                    "version:".dimmed(),
+                    version,
+                    reported
+                )
+            }
+            (Some(version), _) => println!("    {} {}", "version:".dimmed(), version),
+            (None, Some(reported)) => println!("    {} {}", "version:".dimmed(), reported),
+            (None, None) => {}
+        }
+        if let Some(loaded) = &loaded {
+            print_display_metadata(loaded.as_ref(), "    ");
+        }
+        if plugin.status == PluginStatus::Disabled {
+            let selector = loaded.as_deref().map_or("<key>", |loaded| loaded.key());
+            println!(
+                "    {}",
+                format!(
+                    "enabled: false in workspace.yml; load it for one run with --enable-plugin {}",
+                    selector
+                )
+                .dimmed()
+            );
+        }
+        if let Some(path) = &plugin.path {
+            println!("    {} {}", "path:".dimmed(), path.display());
+        }
+        if let Some(file) = &plugin.file {
+            println!("    {}", format_file_info(file));
+        }
+    }
+
+    if !listing.prunable.is_empty() {
+        println!();
+        println!(
+            "{}",
+            "Prunable cache entries (not referenced by workspace.yml):"
+                .bold()
+                .underline()
+        );
+        for (path, file) in &listing.prunable {
+            println!("  {}", path.display());
+            println!("    {}", format_file_info(file));
+        }
+        println!("  {}", "Run `marty plugin prune` to remove them.".dimmed());
+    }
+
+    Ok(())
+}
+
+fn prune_plugins(manager: &WorkspaceManager, cache: &PluginCache, dry_run: bool) -> Result<()> {
+    let plugin_configs = manager
+        .workspace_config
+        .plugins
+        .as_ref()
+        .cloned()
+        .unwrap_or_default();
+    let pruned = cache.prune(&plugin_configs, dry_run)?;
+
+    if pruned.is_empty() {
+        println!("No unreferenced plugin cache entries found.");
+        return Ok(());
+    }
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    for (path, file) in &pruned {
+        println!("  {} {} ({})", verb, path.display(), format_size(file.size));
+    }
+    let total = pruned.iter().map(|(_, file)| file.size).sum();
+    println!(
+        "{} {} cache entr{}, freeing {}",
+        verb,
+        pruned.len(),
+        if pruned.len() == 1 { "y" } else { "ies" },
+        format_size(total)
+    );
+
+    Ok(())
+}
+
+async fn fetch_registry(manager: &WorkspaceManager) -> Result<RegistryIndex> {
+    let url = manager
+        .workspace_config
+        .plugin_registry
+        .as_deref()
+        .unwrap_or(DEFAULT_REGISTRY_URL);
+    RegistryIndex::fetch(&reqwest::Client::new(), url).await
+}
+
+async fn search_registry(manager: &WorkspaceManager, term: &str) -> Result<()> {
+    let index = fetch_registry(manager).await?;
+    let found = index.search(term);
+    if found.is_empty() {
+        println!("No plugins in the registry match '{}'.", term);
+        return Ok(());
+    }
+
+    for plugin in found {
+        let version = plugin.latest_version().unwrap_or("no releases");
+        println!("{} {}", plugin.name.bold(), version.dimmed());
+        if !plugin.description.is_empty() {
+            println!("    {}", plugin.description);
+        }
+    }
+    println!();
+    println!(
+        "{}",
+        "Run `marty plugin add <name>` to add one to workspace.yml.".dimmed()
+    );
+
+    Ok(())
+}
+
+async fn add_plugin(manager: &WorkspaceManager, name: &str, version: Option<&str>) -> Result<()> {
+    let index = fetch_registry(manager).await?;
+    let plugin = index.find(name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Plugin '{}' is not in the registry (see `marty plugin search`)",
+            name
+        )
+    })?;
+    let version = match version {
+        Some(version) if plugin.versions.iter().any(|v| v == version) => version,
+        Some(version) => anyhow::bail!(
+            "Plugin '{}' has no version {} in the registry (available: {})",
+            name,
+            version,
+            plugin.versions.join(", ")
+        ),
+        None => plugin
+            .latest_version()
+            .ok_or_else(|| anyhow::anyhow!("Plugin '{}' has no releases yet", name))?,
+    };
+
+    let config_path = manager.workspace.root.join(".marty").join("workspace.yml");
+    let content = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read {}", config_path.display()))?;
+    let updated = add_plugin_entry(&content, &plugin.plugin_config(version))?;
+    std::fs::write(&config_path, updated)
+        .with_context(|| format!("Failed to write {}", config_path.display()))?;
+
+    println!(
+        "Added {} {} to {}",
+        plugin.name.bold(),
+        version,
+        config_path.display()
+    );
+    println!(
+        "{}",
+        format!(
+            "Run `marty plugin options {}` to see what it can be configured with.",
+            plugin.name
+        )
+        .dimmed()
+    );
+
+    Ok(())
+}
+
+/// The configured plugin with the given key or name, loading it from the cache if needed
+async fn find_plugin(
+    manager: &WorkspaceManager,
+    cache: &PluginCache,
+    key: &str,
+) -> Result<Box<dyn MartyPlugin>> {
+    let plugin_configs = manager
+        .workspace_config
+        .plugins
+        .as_ref()
+        .cloned()
+        .unwrap_or_default();
+    let builtin = plugin_configs
+        .iter()
+        .filter(|config| builtin_plugins::is_builtin(config))
+        .filter_map(|config| builtin_plugins::resolve(config).ok())
+        .find(|plugin| plugin.key() == key || plugin.name() == key);
+    if let Some(plugin) = builtin {
+        return Ok(plugin);
+    }
+
+    let cached_plugins = cache
+        .resolve_plugins(&plugin_configs)
+        .await
+        .context("Failed to resolve plugins")?;
+
+    let mut found = None;
+    for cached_plugin in cached_plugins {
+        let provider = load_plugin(&cached_plugin.path, &manager.workspace.root)
+            .with_context(|| format!("Failed to load plugin '{}'", cached_plugin.name))?;
+        if provider.key() == key || provider.name() == key || cached_plugin.name == key {
+            found = Some(provider);
+            break;
+        }
+    }
+    found.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Plugin '{}' is not configured in workspace.yml (see `marty plugin list`)",
+            key
+        )
+    })
+}
+
+fn print_plugin_details(plugin: &dyn MartyPlugin) -> Result<()> {
+    let key = plugin.key();
+    print_plugin_header(plugin);
+    print_display_metadata(plugin, "");
+    let suggested = plugin.suggested_tasks();
+    match plugin.configuration_options() {
+        Some(schema) if !describe_options(&schema).is_empty() => print_options(&schema)?,
+        _ => println!("This plugin does not accept any options."),
+    }
+
+    if !suggested.is_empty() {
+        let file =
+            suggested_tasks_yaml(key, &suggested).context("Failed to build suggested tasks")?;
+        println!();
+        println!(
+            "{}",
+            format!(
+                "Suggested tasks (save as .marty/tasks/{}.yml and tag the projects `{}`):",
+                key, key
+            )
+            .bold()
+            .underline()
+        );
+        for line in file.lines() {
+            println!("    {}", line);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the description and homepage a plugin reports about itself
+/// The plugin's name, with its version if it reports one, and its key
+fn print_plugin_header(plugin: &dyn MartyPlugin) {
+    let key = format!("(key: {})", plugin.key()).dimmed();
+    match plugin.version() {
+        Some(version) => println!("{} {} {}", plugin.name().bold(), version, key),
+        None => println!("{} {}", plugin.name().bold(), key),
+    }
+}
+
+fn print_display_metadata(plugin: &dyn MartyPlugin, indent: &str) {
+    if let Some(description) = plugin.description() {
+        println!("{}{} {}", indent, "description:".dimmed(), description);
+    }
+    if let Some(homepage) = plugin.homepage() {
+        println!("{}{} {}", indent, "homepage:".dimmed(), homepage);
+    }
+}
+
+fn print_options(schema: &serde_json::Value) -> Result<()> {
+    let options = describe_options(schema);
+
+    println!();
+    println!("{}", "Options:".bold().underline());
+    for option in &options {
+        let required = if option.required {
+            format!(" {}", "required".red())
+        } else {
+            String::new()
+        };
+        println!(
+            "  {} {}{}",
+            option.path.cyan().bold(),
+            format!("<{}>", option.type_name).dimmed(),
+            required
+        );
+        if let Some(description) = &option.description {
+            println!("    {}", description);
+        }
+        if !option.allowed.is_empty() {
+            let allowed: Vec<String> = option.allowed.iter().map(|v| v.to_string()).collect();
+            println!("    {} {}", "allowed:".dimmed(), allowed.join(", "));
+        }
+        if let Some(default) = &option.default {
+            println!("    {} {}", "default:".dimmed(), default);
+        }
+    }
+
+    let example = example_yaml(schema).context("Failed to build example")?;
+    println!();
+    println!(
+        "{}",
+        "Example (add to the plugin's entry in workspace.yml):"
+            .bold()
+            .underline()
+    );
+    for line in example.lines() {
+        println!("    {}", line);
+    }
+
+    Ok(())
+}
+
+fn format_file_info(file: &PluginFileInfo) -> String {
+    let updated = file
+        .modified
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|age| format_age(age.as_secs()))
+        .unwrap_or_else(|| "unknown".to_string());
+    let mut info = format!(
+        "{} {}  {} {}  {} {}",
+        "size:".dimmed(),
+        format_size(file.size),
+        "sha256:".dimmed(),
+        &file.sha256[..12.min(file.sha256.len())],
+        "updated:".dimmed(),
+        updated
+    );
+    if let Some(age) = file
+        .last_used
+        .and_then(|used| SystemTime::now().duration_since(used).ok())
+    {
+        info.push_str(&format!(
+            "  {} {}",
+            "used:".dimmed(),
+            format_age(age.as_secs())
+        ));
+    }
+    info
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
 async fn validate_plugin(path: &Path, expected_name: Option<&str>) -> Result<()> {
     println!("🔍 Validating plugin: {}", path.display());
     println!();
@@ -86,7 +505,9 @@ async fn validate_plugin(path: &Path, expected_name: Option<&str>) -> Result<()>
     let platform = PlatformInfo::current();
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
 
-    if ext != platform.extension {
+    if ext == "wasm" {
+        println!("✅ WebAssembly component, which loads on every platform");
+    } else if ext != platform.extension {
         println!(
             "⚠️  Warning: File extension '{}' doesn't match current platform extension '{}'",
             ext, platform.extension
@@ -99,20 +520,28 @@ async fn validate_plugin(path: &Path, expected_name: Option<&str>) -> Result<()>
         println!("✅ File extension matches platform: .{}", ext);
     }
 
-    // Try to load the plugin and get its name
+    // Load the plugin and read its name and key
     println!();
     println!("🔌 Loading plugin to check name...");
 
-    // We can't directly load the plugin here without FFI, so provide guidance
-    println!("   To fully validate, ensure your plugin's MartyPlugin::name() method returns the expected name.");
+    let sandbox_root = path.parent().unwrap_or(Path::new("."));
+    let plugin = load_plugin(path, sandbox_root)
+        .with_context(|| format!("❌ Failed to load plugin: {}", path.display()))?;
+    println!("✅ Loaded '{}' (key: {})", plugin.name(), plugin.key());
 
     if let Some(expected) = expected_name {
         println!();
         println!("📝 Expected plugin name: {}", expected);
-        println!(
-            "   ⚠️  Make sure MartyPlugin::name() returns exactly: \"{}\"",
-            expected
-        );
+        if plugin.key() == expected || plugin.name() == expected {
+            println!("✅ Plugin reports the expected name");
+        } else {
+            println!(
+                "⚠️  Plugin reports name '{}' and key '{}', expected: \"{}\"",
+                plugin.name(),
+                plugin.key(),
+                expected
+            );
+        }
 
         // Check if filename follows convention
         let filename = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
@@ -136,7 +565,12 @@ async fn validate_plugin(path: &Path, expected_name: Option<&str>) -> Result<()>
     println!();
     println!("📋 Binary Naming Convention Checklist:");
     println!("   For release, your binary should be named:");
-    if let Some(name) = expected_name {
+    if ext == "wasm" {
+        println!(
+            "   marty-plugin-{}-v{{VERSION}}.wasm, referenced with url: in workspace.yml",
+            expected_name.unwrap_or("{NAME}")
+        );
+    } else if let Some(name) = expected_name {
         println!(
             "   marty-plugin-{}-v{{VERSION}}-{}.{}",
             name, platform.target, platform.extension
@@ -152,13 +586,53 @@ async fn validate_plugin(path: &Path, expected_name: Option<&str>) -> Result<()>
     println!("✅ Basic validation complete!");
     println!();
     println!("Next steps:");
-    println!("1. Test the plugin with: marty plugin list (after configuring it)");
+    println!(
+        "1. Check runtime behavior with: marty plugin conformance {}",
+        path.display()
+    );
     println!("2. Build for all platforms before release");
     println!("3. Use 'marty plugin release-guide' to see release instructions");
 
     Ok(())
 }
 
+fn conformance(path: &Path) -> Result<()> {
+    println!("🔍 Running conformance checks: {}", path.display());
+    println!();
+
+    if path.extension().is_some_and(|ext| ext == "wasm") {
+        anyhow::bail!(
+            "Conformance checks exercise the C ABI of dynamic library plugins; check \
+             WebAssembly plugins with `marty plugin validate`"
+        );
+    }
+    let report = run_conformance(path).context("Failed to run conformance checks")?;
+
+    for check in &report.checks {
+        let status = match check.status {
+            ConformanceStatus::Pass => "PASS".green(),
+            ConformanceStatus::Warn => "WARN".yellow(),
+            ConformanceStatus::Fail => "FAIL".red(),
+        };
+        println!("  [{}] {}: {}", status, check.name.bold(), check.detail);
+    }
+
+    let count = |status| report.checks.iter().filter(|c| c.status == status).count();
+    println!();
+    println!(
+        "📊 {} passed, {} warnings, {} failed",
+        count(ConformanceStatus::Pass),
+        count(ConformanceStatus::Warn),
+        count(ConformanceStatus::Fail)
+    );
+
+    if !report.passed() {
+        anyhow::bail!("Plugin failed conformance checks");
+    }
+    println!("✅ Plugin conforms to the Marty plugin ABI");
+    Ok(())
+}
+
 async fn check_release(github_repo: &str, plugin_name: Option<&str>, version: &str) -> Result<()> {
     println!("🔍 Checking GitHub release...");
     println!();
@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::workspace_manager::WorkspaceManager;
+
+use crate::ProjectCommands;
+
+pub async fn execute(manager: &WorkspaceManager, command: ProjectCommands) -> Result<()> {
+    match command {
+        ProjectCommands::Created { dir } => created(manager, &dir).await,
+    }
+}
+
+async fn created(manager: &WorkspaceManager, dir: &Path) -> Result<()> {
+    // The directory is given relative to where marty runs, not to the workspace root
+    let dir = std::env::current_dir()
+        .context("Failed to read the current directory")?
+        .join(dir);
+    let notes = manager
+        .project_created(&dir)
+        .await
+        .context("Failed to register the project")?;
+
+    if notes.is_empty() {
+        println!("{}", "No plugin had to register the project".dimmed());
+        return Ok(());
+    }
+    for (plugin, note) in notes {
+        println!(
+            "{} {} {}",
+            "✓".green().bold(),
+            format!("{}:", plugin).cyan(),
+            note
+        );
+    }
+    Ok(())
+}
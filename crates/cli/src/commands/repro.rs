@@ -0,0 +1,51 @@
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::run_history::RunRecord;
+use marty_core::workspace_manager::WorkspaceManager;
+
+use crate::exit::TaskFailure;
+
+pub fn execute(manager: &WorkspaceManager, run_id: &str, target: &str, exec: bool) -> Result<()> {
+    let (project, task) = target
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Target must be in the form project:task"))?;
+
+    let root = &manager.workspace.root;
+    let record = RunRecord::load(root, run_id).context("Failed to load run")?;
+    let script = record
+        .repro_script(project, task)
+        .context("Failed to build reproduction script")?;
+
+    // Differences are informational; the script still reproduces the recorded commands
+    for difference in record.drift(root, manager.shell(), project, task) {
+        eprintln!("{} {}", "Warning:".yellow(), difference);
+    }
+
+    if !exec {
+        print!("{}", script);
+        return Ok(());
+    }
+
+    eprintln!(
+        "{} {} {}",
+        "Reproducing".bold(),
+        target.cyan(),
+        format!("from run {}", record.id).bright_black()
+    );
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(&script)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run reproduction script: {}", e))?;
+    if !status.success() {
+        return Err(TaskFailure(format!(
+            "Reproduction failed with exit code {}",
+            status.code().unwrap_or(-1)
+        ))
+        .into());
+    }
+
+    Ok(())
+}
@@ -1,23 +1,330 @@
-use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
 use colored::*;
-use marty_core::workspace_manager::WorkspaceManager;
+use marty_core::execution::runner::RunHookFuture;
+use marty_core::execution::{ProjectRunStatus, RunHook, RunSummary};
+use marty_core::messages::text;
+use marty_core::results::TaskRunResult;
+use marty_core::types::MartyResult;
+use marty_core::workspace_manager::{RunOptions, WorkspaceManager};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{self, JsonLog};
+use crate::exit::{BlockedRun, TaskFailure};
 
-pub async fn execute(manager: &WorkspaceManager, target: &str) -> Result<()> {
-    println!("{} {}", "Running task".bold(), target.cyan());
+pub async fn execute(
+    manager: &WorkspaceManager,
+    target: &str,
+    options: &RunOptions,
+    log: Option<&JsonLog>,
+) -> Result<()> {
+    if options.dry_run {
+        println!("{} {}", "Dry run of task".bold(), target.cyan());
+    } else {
+        println!("{} {}", "Running task".bold(), target.cyan());
+    }
     println!();
 
     // Execute task using workspace manager
-    manager
-        .run_task(target)
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to run task: {}", e))?;
+    let collector = SummaryCollector::default();
+    let outcome = manager
+        .run_task_with_hooks(target, options, hooks(log, &collector))
+        .await;
+    report(outcome, &collector, options.dry_run)
+}
+
+/// Hooks for a run started from the command line: the JSON log, if any, and `collector`
+pub(crate) fn hooks(log: Option<&JsonLog>, collector: &SummaryCollector) -> Vec<Box<dyn RunHook>> {
+    log.map(|log| Box::new(log.clone()) as Box<dyn RunHook>)
+        .into_iter()
+        .chain([Box::new(collector.clone()) as Box<dyn RunHook>])
+        .collect()
+}
+
+/// Print the summary of a finished run, turning a failure into the matching error
+pub(crate) fn report(
+    outcome: MartyResult<TaskRunResult>,
+    collector: &SummaryCollector,
+    dry_run: bool,
+) -> Result<()> {
+    let result = match outcome {
+        Ok(result) => result,
+        Err(e) => {
+            // Only runs that got to run tasks report a summary; anything failing earlier,
+            // like an unknown task, is a configuration error
+            let Some(summary) = collector.0.lock().ok().and_then(|mut slot| slot.take()) else {
+                return Err(e).context("Failed to run task");
+            };
+            let blocked = !summary.blocked.is_empty();
+            println!();
+            print_summary(&TaskRunResult::from(summary));
+            let message = "Failed to run task".to_string();
+            return if blocked {
+                Err(e).context(BlockedRun(message))
+            } else {
+                Err(e).context(TaskFailure(message))
+            };
+        }
+    };
 
     println!();
+    if dry_run {
+        println!("{}", "Dry run complete, nothing was executed".bold());
+        return Ok(());
+    }
+    print_summary(&result);
     println!(
         "{} {}",
         "✓".green().bold(),
-        "All tasks completed successfully!".green().bold()
+        text("summary.success").green().bold()
+    );
+
+    Ok(())
+}
+
+/// Print a table with the duration, resource usage, outcome and cache use of every
+/// project of the run
+fn print_summary(result: &TaskRunResult) {
+    if result.projects.is_empty() {
+        return;
+    }
+
+    let [project, task, duration, cpu, memory, status, cache] = [
+        "summary.project",
+        "summary.task",
+        "summary.duration",
+        "summary.cpu",
+        "summary.memory",
+        "summary.status",
+        "summary.cache",
+    ]
+    .map(text);
+    let statuses = [
+        "summary.status.succeeded",
+        "summary.status.cached",
+        "summary.status.failed",
+        "summary.status.not_run",
+        "summary.status.blocked",
+    ]
+    .map(text);
+    // Translated text may contain non-ASCII characters, so widths count characters
+    let width = |text: &str| text.chars().count();
+    let project_width = result
+        .projects
+        .iter()
+        .map(|report| width(&report.project))
+        .chain([width(&project)])
+        .max()
+        .unwrap_or_default();
+    let task_width = result
+        .projects
+        .iter()
+        .map(|report| width(&report.task))
+        .chain([width(&task)])
+        .max()
+        .unwrap_or_default();
+    let duration_width = width(&duration).max(9);
+    let cpu_width = width(&cpu).max(9);
+    let memory_width = width(&memory).max(9);
+    let status_width = statuses
+        .iter()
+        .chain([&status])
+        .map(|text| width(text))
+        .max()
+        .unwrap_or_default();
+
+    println!(
+        "{}",
+        format!(
+            "{:<project_width$}  {:<task_width$}  {:>duration_width$}  {:>cpu_width$}  {:>memory_width$}  {:<status_width$}  {}",
+            project, task, duration, cpu, memory, status, cache
+        )
+        .bold()
+    );
+    for report in &result.projects {
+        let [succeeded, cached, failed, not_run, blocked] = &statuses;
+        let status = match report.status {
+            ProjectRunStatus::Succeeded => format!("{:<status_width$}", succeeded).green(),
+            ProjectRunStatus::Cached => format!("{:<status_width$}", cached).cyan(),
+            ProjectRunStatus::Failed => format!("{:<status_width$}", failed).red(),
+            ProjectRunStatus::NotRun => format!("{:<status_width$}", not_run).bright_black(),
+            ProjectRunStatus::Blocked => format!("{:<status_width$}", blocked).yellow(),
+        };
+        let cache = match report.cache_hit {
+            Some(true) => text("summary.cache.hit").green(),
+            Some(false) => text("summary.cache.miss").yellow(),
+            None => "-".bright_black(),
+        };
+        let (cpu, memory) = match report.resources {
+            Some(usage) => (
+                format_duration(usage.cpu_time),
+                format_bytes(usage.peak_rss_bytes),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        println!(
+            "{:<project_width$}  {:<task_width$}  {:>duration_width$}  {:>cpu_width$}  {:>memory_width$}  {}  {}",
+            report.project,
+            report.task,
+            format_duration(report.duration),
+            cpu,
+            memory,
+            status,
+            cache
+        );
+    }
+    println!(
+        "{} {}",
+        text("summary.total").bright_black(),
+        format_duration(result.duration)
     );
+    println!();
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= 1024.0 * MIB {
+        format!("{:.1} GiB", bytes as f64 / (1024.0 * MIB))
+    } else {
+        format!("{:.1} MiB", bytes as f64 / MIB)
+    }
+}
+
+/// A run requested by an external orchestrator, one JSON object per input line
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchRequest {
+    /// Opaque value echoed back in the result, for matching results to requests
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    target: String,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+/// Result of one batch request, written as a single JSON line
+#[derive(Serialize)]
+struct BatchResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<serde_json::Value>,
+    /// 1-based line number of the request in the input
+    line: usize,
+    target: Option<String>,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Per-project outcome, absent when nothing was run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<RunSummary>,
+}
+
+/// Captures the summary of a run so it can be reported back
+#[derive(Clone, Default)]
+pub(crate) struct SummaryCollector(Arc<Mutex<Option<RunSummary>>>);
+
+impl RunHook for SummaryCollector {
+    fn name(&self) -> &str {
+        "batch"
+    }
+
+    fn on_run_complete<'a>(&'a self, summary: &'a RunSummary) -> RunHookFuture<'a> {
+        Box::pin(async move {
+            if let Ok(mut slot) = self.0.lock() {
+                *slot = Some(summary.clone());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Run requests read from `input` ("-" for stdin) one after another.
+///
+/// Results are streamed as JSON lines on stdout. Everything else Marty and the tasks
+/// print goes to stderr so the result stream stays machine-readable.
+pub async fn execute_batch(
+    manager: &WorkspaceManager,
+    input: &Path,
+    options: &RunOptions,
+) -> Result<()> {
+    let reader: Box<dyn BufRead> = if input == Path::new("-") {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        let file = File::open(input)
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", input.display(), e))?;
+        Box::new(BufReader::new(file))
+    };
+    let mut results = events::take_stdout().context("Failed to redirect output for batch mode")?;
+
+    let mut failed = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| anyhow::anyhow!("Failed to read batch input: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = run_request(manager, &line, i + 1, options).await;
+        if !result.success {
+            failed += 1;
+        }
+
+        serde_json::to_writer(&mut results, &result).context("Failed to write batch result")?;
+        writeln!(results)
+            .and_then(|_| results.flush())
+            .context("Failed to write batch result")?;
+    }
 
+    if failed > 0 {
+        return Err(TaskFailure(format!("{} batch request(s) failed", failed)).into());
+    }
     Ok(())
 }
+
+async fn run_request(
+    manager: &WorkspaceManager,
+    line: &str,
+    line_number: usize,
+    options: &RunOptions,
+) -> BatchResult {
+    let request: BatchRequest = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return BatchResult {
+                id: None,
+                line: line_number,
+                target: None,
+                success: false,
+                error: Some(format!("Invalid request: {}", e)),
+                summary: None,
+            }
+        }
+    };
+
+    let mut options = options.clone();
+    options.env.extend(request.env);
+
+    eprintln!("{} {}", "Running task".bold(), request.target.cyan());
+    let collector = SummaryCollector::default();
+    let outcome = manager
+        .run_task_with_hooks(&request.target, &options, vec![Box::new(collector.clone())])
+        .await;
+    let summary = collector.0.lock().ok().and_then(|mut slot| slot.take());
+
+    BatchResult {
+        id: request.id,
+        line: line_number,
+        target: Some(request.target),
+        success: outcome.is_ok(),
+        error: outcome.err().map(|e| e.to_string()),
+        summary,
+    }
+}
@@ -0,0 +1,25 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub fn execute(manager: &WorkspaceManager, name: &str) -> Result<()> {
+    let files = manager
+        .save_snapshot(name)
+        .context("Failed to take snapshot")?;
+
+    println!(
+        "{} Recorded {} files as snapshot '{}'",
+        "✓".green().bold(),
+        files,
+        name
+    );
+    println!(
+        "  {}",
+        format!(
+            "Compare with it using `marty --change-detection snapshot affected <task> --base {}`",
+            name
+        )
+        .dimmed()
+    );
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::workspace_manager::WorkspaceManager;
+
+pub fn execute(manager: &WorkspaceManager, projects: &[String], dry_run: bool) -> Result<()> {
+    let dirs = if dry_run {
+        manager.get_sparse_checkout_dirs(projects)
+    } else {
+        manager.apply_sparse_checkout(projects)
+    }
+    .context("Failed to configure sparse checkout")?;
+
+    let root = &manager.workspace.root;
+    println!("{}", "Sparse checkout directories:".bold());
+    for dir in &dirs {
+        let relative = dir.strip_prefix(root).unwrap_or(dir);
+        println!("  {}", relative.display());
+    }
+
+    if dry_run {
+        println!();
+        println!(
+            "{}",
+            "Dry run: git sparse-checkout was not changed".dimmed()
+        );
+    } else {
+        println!();
+        println!(
+            "{} {}",
+            "✓".green().bold(),
+            "Sparse checkout configured. Run 'git sparse-checkout disable' to restore the full tree."
+                .green()
+        );
+    }
+
+    Ok(())
+}
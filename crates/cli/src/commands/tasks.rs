@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use colored::*;
+use marty_core::colors::project_color;
+use marty_core::configs::tasks::{Command, TaskConfig};
+use marty_core::workspace_manager::WorkspaceManager;
+
+use crate::TaskCommands;
+
+pub fn execute(manager: &WorkspaceManager, command: TaskCommands) -> Result<()> {
+    match command {
+        TaskCommands::Describe { task } => describe(manager, &task),
+    }
+}
+
+fn describe(manager: &WorkspaceManager, task_name: &str) -> Result<()> {
+    let result = manager
+        .describe_task(task_name)
+        .context("Failed to describe task")?;
+
+    println!("{} {}", "Task".bold(), result.name.cyan().bold());
+
+    match &result.workspace_task {
+        Some(task) => {
+            print_summary(task, "  ");
+            if let Some(docs) = &task.docs {
+                println!();
+                print_markdown(docs);
+            }
+        }
+        None => println!(
+            "  {}",
+            "Not defined at workspace level (project overrides only)".dimmed()
+        ),
+    }
+
+    println!();
+    if result.project_overrides.is_empty() {
+        println!("{}", "No project overrides".dimmed());
+    } else {
+        println!("{}", "Project overrides:".bold());
+        for (project, task) in &result.project_overrides {
+            println!("  {}", project.color(project_color(project)).bold());
+            print_summary(task, "    ");
+            if let Some(docs) = &task.docs {
+                print_markdown(docs);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_summary(task: &TaskConfig, indent: &str) {
+    if let Some(description) = &task.description {
+        println!("{}{}", indent, description);
+    }
+    if let Some(script) = &task.script {
+        println!("{}{} {}", indent, "Script:".bright_black(), script);
+    }
+    if let Some(command) = &task.command {
+        let command = match command {
+            Command::Single(cmd) => cmd.clone(),
+            Command::Multiple(cmds) => cmds.join(" "),
+        };
+        println!("{}{} {}", indent, "Command:".bright_black(), command);
+    }
+    if let Some(plugin) = &task.plugin {
+        println!("{}{} {}", indent, "Run by plugin:".bright_black(), plugin);
+    }
+    if let Some(deps) = task.dependencies.as_ref().filter(|d| !d.is_empty()) {
+        println!(
+            "{}{} {}",
+            indent,
+            "Depends on:".bright_black(),
+            deps.join(", ")
+        );
+    }
+}
+
+/// Render a small subset of Markdown (headings, lists, code blocks, inline code) for the terminal
+fn print_markdown(markdown: &str) {
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+
+        if in_code_block {
+            println!("    {}", line.cyan());
+        } else if let Some(heading) = trimmed.strip_prefix('#') {
+            println!(
+                "{}",
+                heading.trim_start_matches('#').trim().bold().underline()
+            );
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            println!("  • {}", render_inline(item));
+        } else {
+            println!("{}", render_inline(line));
+        }
+    }
+}
+
+/// Highlight `inline code` spans
+fn render_inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                part.cyan().to_string()
+            } else {
+                part.to_string()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+//! Newline-delimited JSON output for `--log-format json`
+//!
+//! In JSON mode stdout carries nothing but events, one JSON object per line. Everything
+//! else Marty and the tasks print is sent to stderr instead.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use clap::ValueEnum;
+use marty_core::execution::runner::RunHookFuture;
+use marty_core::execution::{RunHook, RunSummary};
+use marty_core::progress::ProgressSink;
+use marty_core::timings::PhaseTiming;
+use marty_core::workspace::ProjectKind;
+use serde::Serialize;
+
+/// How Marty reports what it does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable output
+    Text,
+    /// One JSON event per line on stdout
+    Json,
+}
+
+/// Events written by the CLI itself, next to the core's progress events
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Project {
+        name: &'a str,
+        path: &'a Path,
+        tracked: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        discovered_by: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        kind: Option<ProjectKind>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        color: Option<&'a str>,
+    },
+    PlannedTask {
+        /// 1-based position in the execution order
+        position: usize,
+        project: &'a str,
+        task: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inputs: Option<&'a [String]>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        outputs: Option<&'a [String]>,
+    },
+    RunFinished(&'a RunSummary),
+    /// Where the command spent its time, with `--timings`
+    Timings {
+        phases: &'a [PhaseTiming],
+        total_ms: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Writer of the JSON event stream
+#[derive(Clone)]
+pub struct JsonLog {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl JsonLog {
+    /// Take over stdout for the event stream
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            out: Arc::new(Mutex::new(take_stdout()?)),
+        })
+    }
+
+    /// Write one event line. Failing to log never fails the command.
+    pub fn emit(&self, event: &impl Serialize) {
+        let Ok(mut out) = self.out.lock() else {
+            return;
+        };
+        if serde_json::to_writer(&mut *out, event).is_ok() {
+            let _ = writeln!(out).and_then(|_| out.flush());
+        }
+    }
+
+    /// Sink writing discovery and task run progress to this log
+    pub fn progress(&self) -> ProgressSink {
+        let log = self.clone();
+        ProgressSink::new(move |event| log.emit(event))
+    }
+}
+
+impl RunHook for JsonLog {
+    fn name(&self) -> &str {
+        "json log"
+    }
+
+    fn on_run_complete<'a>(&'a self, summary: &'a RunSummary) -> RunHookFuture<'a> {
+        Box::pin(async move {
+            self.emit(&Event::RunFinished(summary));
+            Ok(())
+        })
+    }
+}
+
+/// Point the process's stdout at stderr, returning a handle to the original stdout.
+///
+/// Task commands inherit the process's stdout, so swapping the file descriptor is the
+/// only way to keep their output out of a machine-readable stream.
+#[cfg(unix)]
+pub fn take_stdout() -> io::Result<Box<dyn Write + Send>> {
+    use std::os::fd::AsFd;
+
+    io::stdout().flush()?;
+    let original = io::stdout().as_fd().try_clone_to_owned()?;
+    // SAFETY: dup2 only replaces the descriptor number 1, which stays valid
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Box::new(File::from(original)))
+}
+
+#[cfg(not(unix))]
+pub fn take_stdout() -> io::Result<Box<dyn Write + Send>> {
+    Ok(Box::new(io::stdout()))
+}
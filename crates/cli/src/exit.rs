@@ -0,0 +1,85 @@
+//! Exit codes of the `marty` binary
+//!
+//! | Code | Meaning                                                              |
+//! |------|----------------------------------------------------------------------|
+//! | 0    | Success                                                              |
+//! | 1    | A task failed on at least one project                                |
+//! | 2    | Invalid configuration, arguments or workspace state                  |
+//! | 3    | Internal error: an I/O failure Marty did not expect, or a bug        |
+//! | 4    | A task failed and projects depending on it were blocked (`-k`)       |
+
+use std::fmt;
+use std::process::ExitCode;
+
+use marty_core::types::MartyError;
+
+pub const TASK_FAILURE: u8 = 1;
+pub const CONFIG_ERROR: u8 = 2;
+pub const INTERNAL_ERROR: u8 = 3;
+pub const BLOCKED: u8 = 4;
+
+/// Error, or context of an error, saying that tasks ran and some of them failed
+#[derive(Debug)]
+pub struct TaskFailure(pub String);
+
+impl fmt::Display for TaskFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TaskFailure {}
+
+/// Like [`TaskFailure`], for runs that kept going and left projects depending on a failed
+/// one blocked, so CI can tell an incomplete run from one where every project was attempted
+#[derive(Debug)]
+pub struct BlockedRun(pub String);
+
+impl fmt::Display for BlockedRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BlockedRun {}
+
+/// The exit code for a command that failed with `error`. Errors Marty reports itself
+/// without a more specific cause, such as an unknown project, count as usage errors.
+pub fn code(error: &anyhow::Error) -> ExitCode {
+    if error.downcast_ref::<BlockedRun>().is_some() {
+        return ExitCode::from(BLOCKED);
+    }
+    if error.downcast_ref::<TaskFailure>().is_some() {
+        return ExitCode::from(TASK_FAILURE);
+    }
+    for cause in error.chain() {
+        if cause.is::<BlockedRun>() {
+            return ExitCode::from(BLOCKED);
+        }
+        if cause.is::<TaskFailure>() {
+            return ExitCode::from(TASK_FAILURE);
+        }
+        if let Some(error) = cause.downcast_ref::<MartyError>() {
+            return ExitCode::from(match error {
+                MartyError::Io(_) => INTERNAL_ERROR,
+                _ => CONFIG_ERROR,
+            });
+        }
+        if cause.is::<std::io::Error>() {
+            return ExitCode::from(INTERNAL_ERROR);
+        }
+    }
+    ExitCode::from(CONFIG_ERROR)
+}
+
+/// Exit with [`INTERNAL_ERROR`] instead of Rust's default code when the main thread
+/// panics. Panics of worker threads are still handled where they are joined.
+pub fn on_panic() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default(info);
+        if std::thread::current().name() == Some("main") {
+            std::process::exit(i32::from(INTERNAL_ERROR));
+        }
+    }));
+}
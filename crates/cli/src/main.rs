@@ -1,10 +1,25 @@
 use std::path::PathBuf;
+use std::process::ExitCode;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use marty_core::workspace_manager::{WorkspaceManager, WorkspaceManagerConfig};
+use marty_core::configs::workspace::ChangeDetection;
+use marty_core::plan_diff::PlanSide;
+use marty_core::timings::Timings;
+use marty_core::workspace::ProjectKind;
+use marty_core::workspace_manager::{
+    PluginSelection, RunOptions, WorkspaceManager, WorkspaceManagerConfig,
+};
 
 mod commands;
+mod events;
+mod exit;
+mod picker;
+mod timings;
+
+use commands::export::ExportFormat;
+use commands::list::ListOutput;
+use events::{Event, JsonLog, LogFormat};
 
 /// Marty - A monorepo management tool
 #[derive(Parser)]
@@ -16,6 +31,38 @@ struct Cli {
     #[arg(short, long, default_value = ".")]
     workspace: PathBuf,
 
+    /// Skip a configured plugin for this invocation (by key, e.g. "typescript")
+    #[arg(long = "disable-plugin", value_name = "PLUGIN", global = true)]
+    disable_plugins: Vec<String>,
+
+    /// Only load the given plugin(s) for this invocation (by key, e.g. "cargo")
+    #[arg(long = "only-plugin", value_name = "PLUGIN", global = true)]
+    only_plugins: Vec<String>,
+
+    /// Load a plugin turned off with `enabled: false` in workspace.yml for this invocation
+    #[arg(long = "enable-plugin", value_name = "PLUGIN", global = true)]
+    enable_plugins: Vec<String>,
+
+    /// Where the changed files for `affected` and `--changed-only-within` come from: git,
+    /// mercurial, snapshot or stdin (one path per line). Overrides `changeDetection` in
+    /// workspace.yml.
+    #[arg(long, value_name = "KIND", global = true)]
+    change_detection: Option<ChangeDetection>,
+
+    /// Only work with the projects of a view defined under `views` in workspace.yml
+    #[arg(long, value_name = "VIEW", global = true)]
+    view: Option<String>,
+
+    /// Output format; `json` writes newline-delimited events to stdout and everything
+    /// else to stderr
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
+    /// Print how long config loading, plugin resolution, traversal, graph building,
+    /// planning and execution took once the command finishes
+    #[arg(long, global = true)]
+    timings: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -27,19 +74,229 @@ enum Commands {
         /// Include projects inferred from workspace providers even without a marty.yml
         #[arg(long)]
         inferred: bool,
+        /// Print one JSON object per project and line
+        #[arg(long, conflicts_with_all = ["names_only", "paths_only", "null"])]
+        json: bool,
+        /// Print only project names, one per line
+        #[arg(long, conflicts_with = "paths_only")]
+        names_only: bool,
+        /// Print only project directories, one per line
+        #[arg(long)]
+        paths_only: bool,
+        /// Separate entries with NUL instead of newlines (for `xargs -0`); prints names
+        /// unless --paths-only is given
+        #[arg(short = '0', long)]
+        null: bool,
+        /// Only list projects of this kind: application, library, tool or test
+        #[arg(long)]
+        kind: Option<ProjectKind>,
     },
     /// Show execution plan for a task without running it
     Plan {
         /// Target in format "project:task" or just "task" for all projects
         target: String,
     },
+    /// Compare the execution plan of a target between two git refs or workspace configs
+    DiffPlan {
+        /// Target in format "project:task" or just "task" for all projects
+        target: String,
+        /// Git ref to plan against first (defaults to the working tree)
+        #[arg(long, value_name = "REF")]
+        base: Option<String>,
+        /// workspace.yml to use for the first plan
+        #[arg(long, value_name = "FILE")]
+        base_config: Option<PathBuf>,
+        /// Git ref to compare with (defaults to the working tree)
+        #[arg(long, value_name = "REF")]
+        head: Option<String>,
+        /// workspace.yml to use for the second plan
+        #[arg(long, value_name = "FILE")]
+        head_config: Option<PathBuf>,
+    },
     /// Run a task
+    #[command(
+        after_help = "Exit codes: 0 success, 1 task failures, 2 configuration or usage errors, 3 internal errors, 4 task failures that left dependent projects blocked (--keep-going)"
+    )]
     Run {
         /// Target in format "project:task" or just "task" for all projects
+        #[arg(required_unless_present = "batch")]
+        target: Option<String>,
+        /// Read JSON lines of {"target", "env"} requests from FILE ("-" for stdin) and print one JSON result line per request
+        #[arg(long, value_name = "FILE", conflicts_with = "target")]
+        batch: Option<PathBuf>,
+        /// Only run projects changed since the last fully successful run of this task on the current branch
+        #[arg(long)]
+        since_last_success: bool,
+        /// Only run projects that failed, were blocked or never started the last time the task ran on them, plus their dependents
+        #[arg(long)]
+        failed: bool,
+        /// Only run if PROJECT has changed since --base (uncommitted changes by default), and then only on PROJECT and the projects depending on it
+        #[arg(long, value_name = "PROJECT")]
+        changed_only_within: Option<String>,
+        /// With --changed-only-within, the revision to compare the working tree against: a git ref, a Mercurial revision or a snapshot name, depending on the change detection
+        #[arg(long, value_name = "REV", requires = "changed_only_within")]
+        base: Option<String>,
+        /// Keep running projects that don't depend on a failed one and report all failures at the end
+        #[arg(long, short = 'k')]
+        keep_going: bool,
+        /// With --keep-going, also attempt projects whose dependencies failed instead of blocking them
+        #[arg(long, requires = "keep_going")]
+        run_blocked: bool,
+        /// Keep going until N projects failed, then stop starting new ones (implies --keep-going)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        max_failures: Option<u32>,
+        /// Print the resolved commands of every project in execution order without running them
+        #[arg(long, conflicts_with = "batch")]
+        dry_run: bool,
+        /// Run up to N independent projects at the same time
+        #[arg(long, short = 'j', value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        jobs: Option<u32>,
+        /// Pick which of the compatible projects to run from a checkbox list first; their dependencies still run
+        #[arg(long, short = 'i', conflicts_with = "batch")]
+        interactive: bool,
+        /// Arguments appended to the task's command, e.g. `marty run build -- --release`
+        #[arg(last = true, value_name = "ARGS")]
+        args: Vec<String>,
+    },
+    /// Run a shell command in every project's directory in dependency order, without a task
+    Exec {
+        /// Only run in this project (repeatable)
+        #[arg(long = "project", short = 'p', value_name = "PROJECT")]
+        projects: Vec<String>,
+        /// Keep running projects that don't depend on a failed one and report all failures at the end
+        #[arg(long, short = 'k')]
+        keep_going: bool,
+        /// Print the resolved commands in execution order without running them
+        #[arg(long)]
+        dry_run: bool,
+        /// Run up to N independent projects at the same time
+        #[arg(long, short = 'j', value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        jobs: Option<u32>,
+        /// Command to run, joined with spaces and run by the workspace's shell (e.g. `marty exec -- git status`)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Run a task only on the projects affected by changes since a git ref
+    Affected {
+        /// Task to run on the affected projects
+        task: String,
+        /// Revision to compare the working tree against: a git ref (e.g. origin/main), a
+        /// Mercurial revision or a snapshot name, depending on the change detection.
+        /// Defaults to the checked-out revision, or the "latest" snapshot.
+        #[arg(long)]
+        base: Option<String>,
+        /// Only list the affected projects without running the task
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print (or run) a shell script reproducing the environment of a task from a past run
+    Repro {
+        /// Run id from `.marty/runs`, or "latest"
+        run_id: String,
+        /// Target in format "project:task"
         target: String,
+        /// Run the script instead of printing it
+        #[arg(long)]
+        exec: bool,
+    },
+    /// Show the most recent saved output of a project's task (needs `logs.enabled`)
+    Logs {
+        /// Project whose log to show
+        project: String,
+        /// Only consider runs of this task
+        task: Option<String>,
+        /// Print the log file's path instead of its content
+        #[arg(long)]
+        path: bool,
     },
+    /// Set up a fresh checkout by running each project's setup task in dependency order
+    Bootstrap,
     /// Show the project dependency graph
-    Graph,
+    Graph {
+        /// Collapse projects into directory groups this many levels deep (e.g. 1 for apps/, libs/)
+        #[arg(long, value_name = "DEPTH")]
+        group_by_dir: Option<usize>,
+        /// Only show projects of this kind: application, library, tool or test
+        #[arg(long, conflicts_with = "group_by_dir")]
+        kind: Option<ProjectKind>,
+        /// Explain how FROM ends up depending on TO with a shortest chain of dependencies
+        #[arg(
+            long,
+            num_args = 2,
+            value_names = ["FROM", "TO"],
+            conflicts_with_all = ["group_by_dir", "kind"]
+        )]
+        why: Option<Vec<String>>,
+        /// With --why, print every chain instead of a shortest one
+        #[arg(long, requires = "why")]
+        all: bool,
+    },
+    /// List the files owned by a project, or map every workspace file to its owning project
+    Files {
+        /// Only list files owned by this project
+        project: Option<String>,
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check projects' allowedDependencies/deniedDependencies rules
+    LintDeps,
+    /// Validate configuration, dependency rules and cycles, and plan tasks, for CI gates
+    Check {
+        /// Tasks to plan (defaults to every workspace-level task)
+        tasks: Vec<String>,
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Record the hashes of the workspace files, as a base for `--change-detection snapshot`
+    Snapshot {
+        /// Name to save the snapshot under, and to pass to `affected --base` later
+        #[arg(default_value = marty_core::change_detection::DEFAULT_SNAPSHOT)]
+        name: String,
+    },
+    /// Configure git sparse checkout to only the directories needed for the given projects
+    Sparse {
+        /// Projects to work on (their dependencies are included automatically)
+        #[arg(required = true)]
+        projects: Vec<String>,
+        /// Only print the directories without changing the checkout
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Package the marty binary, the workspace's plugins and a lockfile pinning them into a
+    /// self-contained directory for CI runners without network access
+    Bundle {
+        /// Directory to create
+        output: PathBuf,
+        /// Target to vendor plugins for (repeatable, defaults to this machine's), e.g.
+        /// "aarch64-unknown-linux-gnu"
+        #[arg(long = "platform", value_name = "TARGET")]
+        platforms: Vec<String>,
+        /// Write an OCI image layout with the bundle under /marty instead of a plain directory
+        #[arg(long)]
+        oci: bool,
+    },
+    /// Export workspace data for tools outside Marty
+    Export {
+        #[command(subcommand)]
+        export_command: ExportCommands,
+    },
+    /// Inspect task definitions
+    Tasks {
+        #[command(subcommand)]
+        task_command: TaskCommands,
+    },
+    /// Tell the plugins about projects
+    Project {
+        #[command(subcommand)]
+        project_command: ProjectCommands,
+    },
+    /// Inspect and manage the task result cache
+    Cache {
+        #[command(subcommand)]
+        cache_command: CacheCommands,
+    },
     /// Manage plugins
     Plugin {
         #[command(subcommand)]
@@ -47,14 +304,81 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ExportCommands {
+    /// One record per project with its tags, owners, plugin, dependencies and how its tasks
+    /// last ended, for monorepo dashboards
+    Metadata {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Ndjson)]
+        format: ExportFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum TaskCommands {
+    /// Show a task's documentation, command, and project overrides
+    Describe {
+        /// Task name
+        task: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectCommands {
+    /// Let the plugins register a project just created in DIR with their tools, e.g. add it
+    /// to the Cargo workspace members or the pnpm workspace packages
+    Created {
+        /// Directory of the new project
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Show cached task results grouped by namespace
+    Stats,
+    /// Remove all cached task results
+    Clear,
+}
+
 #[derive(Subcommand)]
 enum PluginCommands {
-    /// List cached plugins
+    /// List configured plugins with their status, and cache entries that can be pruned
     List,
+    /// Remove cached plugins that no entry in workspace.yml refers to
+    Prune {
+        /// Show what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Clear plugin cache
     Clear,
     /// Update all plugins from their URLs
     Update,
+    /// Search the plugin registry by name or description
+    Search {
+        /// Text to look for
+        term: String,
+    },
+    /// Add a plugin from the registry to workspace.yml
+    Add {
+        /// Plugin name in the registry (e.g. "helm")
+        name: String,
+        /// Version to add instead of the latest one
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// Show what a configured plugin reports about itself: version, description and homepage
+    Info {
+        /// Plugin key or name (e.g. "cargo")
+        key: String,
+    },
+    /// Show the options a configured plugin accepts, with an example workspace.yml entry, and
+    /// the tasks it suggests
+    Options {
+        /// Plugin key or name (e.g. "cargo")
+        key: String,
+    },
     /// Validate a plugin for publication (for plugin developers)
     Validate {
         /// Path to the plugin binary to validate
@@ -63,6 +387,11 @@ enum PluginCommands {
         #[arg(short, long)]
         name: Option<String>,
     },
+    /// Run ABI conformance checks against a plugin binary (for plugin developers)
+    Conformance {
+        /// Path to the plugin binary to check
+        path: PathBuf,
+    },
     /// Check if a plugin release exists on GitHub
     CheckRelease {
         /// GitHub repository (e.g., "owner/repo")
@@ -91,22 +420,215 @@ enum PluginCommands {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    exit::on_panic();
     let cli = Cli::parse();
 
+    let log = match cli.log_format {
+        LogFormat::Text => None,
+        LogFormat::Json => match JsonLog::new() {
+            Ok(log) => Some(log),
+            Err(e) => {
+                eprintln!("Error: Failed to set up JSON logging: {}", e);
+                return ExitCode::from(exit::INTERNAL_ERROR);
+            }
+        },
+    };
+    let started = std::time::Instant::now();
+    let timings = if cli.timings {
+        Timings::enabled()
+    } else {
+        Timings::default()
+    };
+    let result = run(cli, log.as_ref(), timings.clone()).await;
+    if timings.is_enabled() {
+        timings::report(&timings, started.elapsed(), log.as_ref());
+    }
+    let Err(e) = result else {
+        return ExitCode::SUCCESS;
+    };
+    // The alternate format includes the causes, e.g. "Failed to run task: Task error: ..."
+    if let Some(log) = &log {
+        log.emit(&Event::Error {
+            message: format!("{:#}", e),
+        });
+    }
+    eprintln!("Error: {:#}", e);
+    exit::code(&e)
+}
+
+async fn run(cli: Cli, log: Option<&JsonLog>, timings: Timings) -> Result<()> {
     // Initialize workspace manager with all business logic
-    let manager = WorkspaceManager::new(WorkspaceManagerConfig {
-        workspace_root: cli.workspace,
-    })
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to initialize workspace: {}", e))?;
+    let manager = match &cli.command {
+        // Plugin management only reads workspace.yml, so skip loading (and downloading) plugins
+        Commands::Plugin { .. } | Commands::Bundle { .. } => {
+            WorkspaceManager::with_providers(cli.workspace, Vec::new())
+        }
+        _ => {
+            WorkspaceManager::new(WorkspaceManagerConfig {
+                workspace_root: cli.workspace,
+                plugins: PluginSelection {
+                    disabled: cli.disable_plugins,
+                    only: cli.only_plugins,
+                    enabled: cli.enable_plugins,
+                },
+                progress: log.map(JsonLog::progress).unwrap_or_default(),
+                view: cli.view,
+                timings,
+                change_detection: cli.change_detection,
+            })
+            .await
+        }
+    }
+    .context("Failed to initialize workspace")?;
 
     // Execute command (CLI layer only handles presentation)
     match cli.command {
-        Commands::List { inferred } => commands::list::execute(&manager, inferred),
-        Commands::Plan { target } => commands::plan::execute(&manager, &target).await,
-        Commands::Run { target } => commands::run::execute(&manager, &target).await,
-        Commands::Graph => commands::graph::execute(&manager),
+        Commands::List {
+            inferred,
+            json,
+            names_only,
+            paths_only,
+            null,
+            kind,
+        } => {
+            let output = if json {
+                ListOutput::Json
+            } else if paths_only {
+                ListOutput::Paths
+            } else if names_only || null {
+                ListOutput::Names
+            } else {
+                ListOutput::Pretty
+            };
+            commands::list::execute(&manager, inferred, kind, output, null, log)
+        }
+        Commands::Plan { target } => commands::plan::execute(&manager, &target, log).await,
+        Commands::DiffPlan {
+            target,
+            base,
+            base_config,
+            head,
+            head_config,
+        } => {
+            let base = PlanSide {
+                git_ref: base,
+                config_file: base_config,
+            };
+            let head = PlanSide {
+                git_ref: head,
+                config_file: head_config,
+            };
+            commands::diff_plan::execute(&manager, &target, &base, &head).await
+        }
+        Commands::Run {
+            target,
+            batch,
+            since_last_success,
+            failed,
+            changed_only_within,
+            base,
+            keep_going,
+            run_blocked,
+            max_failures,
+            dry_run,
+            jobs,
+            interactive,
+            args,
+        } => {
+            let mut options = RunOptions {
+                since_last_success,
+                failed,
+                changed_only_within,
+                affected_since: base,
+                keep_going: keep_going || max_failures.is_some(),
+                run_blocked,
+                max_failures: max_failures.map(|max| max as usize),
+                args,
+                dry_run,
+                jobs: jobs.map(|jobs| jobs as usize),
+                ..RunOptions::default()
+            };
+            match (batch, target) {
+                (Some(input), _) => commands::run::execute_batch(&manager, &input, &options).await,
+                (None, Some(target)) => {
+                    if interactive {
+                        let plan = manager.get_execution_plan(&target)?;
+                        if plan.compatible_projects.len() > 1 {
+                            let title = format!("Projects to run '{}' on:", plan.task_name);
+                            let Some(projects) = picker::pick(&title, &plan.compatible_projects)?
+                            else {
+                                println!("Nothing selected, not running '{}'", target);
+                                return Ok(());
+                            };
+                            options.projects = Some(projects);
+                        }
+                    }
+                    commands::run::execute(&manager, &target, &options, log).await
+                }
+                (None, None) => unreachable!("clap requires a target without --batch"),
+            }
+        }
+        Commands::Exec {
+            projects,
+            keep_going,
+            dry_run,
+            jobs,
+            command,
+        } => {
+            let options = RunOptions {
+                keep_going,
+                dry_run,
+                jobs: jobs.map(|jobs| jobs as usize),
+                ..RunOptions::default()
+            };
+            commands::exec::execute(&manager, &command.join(" "), &projects, &options, log).await
+        }
+        Commands::Affected {
+            task,
+            base,
+            dry_run,
+        } => commands::affected::execute(&manager, &task, base.as_deref(), dry_run, log).await,
+        Commands::Repro {
+            run_id,
+            target,
+            exec,
+        } => commands::repro::execute(&manager, &run_id, &target, exec),
+        Commands::Logs {
+            project,
+            task,
+            path,
+        } => commands::logs::execute(&manager, &project, task.as_deref(), path),
+        Commands::Bootstrap => commands::bootstrap::execute(&manager).await,
+        Commands::Graph {
+            group_by_dir,
+            kind,
+            why,
+            all,
+        } => match why.as_deref() {
+            Some([from, to]) => commands::graph::execute_why(&manager, from, to, all),
+            _ => commands::graph::execute(&manager, group_by_dir, kind),
+        },
+        Commands::Files { project, json } => {
+            commands::files::execute(&manager, project.as_deref(), json)
+        }
+        Commands::LintDeps => commands::lint_deps::execute(&manager),
+        Commands::Check { tasks, json } => commands::check::execute(&manager, &tasks, json),
+        Commands::Snapshot { name } => commands::snapshot::execute(&manager, &name),
+        Commands::Sparse { projects, dry_run } => {
+            commands::sparse::execute(&manager, &projects, dry_run)
+        }
+        Commands::Bundle {
+            output,
+            platforms,
+            oci,
+        } => commands::bundle::execute(&manager, &output, &platforms, oci).await,
+        Commands::Export { export_command } => commands::export::execute(&manager, export_command),
+        Commands::Tasks { task_command } => commands::tasks::execute(&manager, task_command),
+        Commands::Project { project_command } => {
+            commands::project::execute(&manager, project_command).await
+        }
+        Commands::Cache { cache_command } => commands::cache::execute(&manager, cache_command),
         Commands::Plugin { plugin_command } => {
             commands::plugin::execute(&manager, plugin_command).await
         }
@@ -0,0 +1,71 @@
+//! A checkbox list on the terminal for picking some of a task's projects
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::{bail, Context, Result};
+use colored::*;
+
+/// Let the user pick a subset of `projects` on the terminal, all of them selected at
+/// first. Returns `None` when the user quits without running anything.
+pub fn pick(title: &str, projects: &[String]) -> Result<Option<Vec<String>>> {
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        bail!("--interactive needs a terminal to pick projects on");
+    }
+
+    let mut selected = vec![true; projects.len()];
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        eprintln!("{}", title.bold());
+        for (i, (project, on)) in projects.iter().zip(&selected).enumerate() {
+            let mark = if *on { "[x]".green() } else { "[ ]".normal() };
+            eprintln!("  {} {:>3} {}", mark, i + 1, project.cyan());
+        }
+        eprint!(
+            "Toggle numbers or ranges (e.g. `1 3-5`), `a` all, `n` none, Enter to run, `q` to quit: "
+        );
+        io::stderr().flush().context("Failed to write prompt")?;
+
+        let Some(line) = lines.next() else {
+            return Ok(None);
+        };
+        let line = line.context("Failed to read selection")?;
+        match line.trim() {
+            "" => {
+                let picked: Vec<String> = projects
+                    .iter()
+                    .zip(&selected)
+                    .filter(|(_, on)| **on)
+                    .map(|(project, _)| project.clone())
+                    .collect();
+                if !picked.is_empty() {
+                    return Ok(Some(picked));
+                }
+                eprintln!("{}", "Select at least one project, or `q` to quit".yellow());
+            }
+            "q" => return Ok(None),
+            "a" => selected.fill(true),
+            "n" => selected.fill(false),
+            input => match parse_numbers(input, projects.len()) {
+                Ok(numbers) => numbers.into_iter().for_each(|i| selected[i] = !selected[i]),
+                Err(message) => eprintln!("{}", message.yellow()),
+            },
+        }
+        eprintln!();
+    }
+}
+
+/// Zero-based indices of the numbers and ranges in `input`, e.g. `1 3-5`
+fn parse_numbers(input: &str, len: usize) -> Result<Vec<usize>, String> {
+    let number = |s: &str| match s.trim().parse::<usize>() {
+        Ok(n) if (1..=len).contains(&n) => Ok(n - 1),
+        _ => Err(format!("'{}' is not a number between 1 and {}", s, len)),
+    };
+    let mut indices = Vec::new();
+    for part in input.split([',', ' ']).filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => indices.extend(number(start)?..=number(end)?),
+            None => indices.push(number(part)?),
+        }
+    }
+    Ok(indices)
+}
@@ -0,0 +1,52 @@
+//! Output of `--timings`
+
+use std::time::Duration;
+
+use colored::Colorize;
+use marty_core::timings::Timings;
+
+use crate::events::{Event, JsonLog};
+
+/// Print where the command spent its time: on stderr as a table, or as an event in JSON
+/// mode. Time outside the measured phases, such as printing results, is listed as "Other".
+pub fn report(timings: &Timings, total: Duration, log: Option<&JsonLog>) {
+    let phases = timings.phases();
+    if let Some(log) = log {
+        log.emit(&Event::Timings {
+            phases: &phases,
+            total_ms: total.as_millis() as u64,
+        });
+        return;
+    }
+
+    let measured: Duration = phases.iter().map(|timing| timing.duration).sum();
+    let rows = phases
+        .iter()
+        .map(|timing| (timing.phase.label(), timing.duration))
+        .chain([("Other", total.saturating_sub(measured))]);
+
+    eprintln!();
+    eprintln!("{}", "Timings:".bold());
+    for (label, duration) in rows {
+        let share = if total.is_zero() {
+            0.0
+        } else {
+            duration.as_secs_f64() / total.as_secs_f64() * 100.0
+        };
+        eprintln!(
+            "  {:<18} {:>10} {}",
+            label,
+            format_duration(duration),
+            format!("{:>5.1}%", share).dimmed()
+        );
+    }
+    eprintln!("  {:<18} {:>10}", "Total".bold(), format_duration(total));
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
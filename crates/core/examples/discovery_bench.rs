@@ -0,0 +1,118 @@
+//! Compare rebuilding the project graph with loading it from the discovery cache.
+//!
+//! Generates a synthetic workspace of chained projects and measures the time from creating
+//! a `WorkspaceManager` to having an execution plan, with and without a stored graph:
+//!
+//! ```text
+//! cargo run --release -p marty_core --example discovery_bench -- 10000
+//! ```
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use marty_core::WorkspaceManager;
+use marty_plugin_protocol::{
+    InferredProject, MartyPlugin, PluginType, Workspace, WorkspaceProvider,
+};
+
+/// Discovers a project for every `project.txt`, which lists its dependencies one per line
+struct ListPlugin;
+
+impl MartyPlugin for ListPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "List"
+    }
+
+    fn key(&self) -> &str {
+        "list"
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        self
+    }
+}
+
+impl WorkspaceProvider for ListPlugin {
+    fn include_path_globs(&self) -> Vec<String> {
+        vec!["**/project.txt".to_string()]
+    }
+
+    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let project_dir = path.parent()?.to_path_buf();
+        let contents = std::fs::read_to_string(path).ok()?;
+        Some(InferredProject {
+            name: project_dir.file_name()?.to_string_lossy().to_string(),
+            project_dir,
+            discovered_by: "list".to_string(),
+            workspace_dependencies: contents.lines().map(str::to_string).collect(),
+            kind: None,
+        })
+    }
+}
+
+fn generate(root: &Path, projects: usize) -> std::io::Result<()> {
+    std::fs::create_dir_all(root.join(".marty"))?;
+    std::fs::write(
+        root.join(".marty/workspace.yml"),
+        "name: bench\ndiscovery:\n  cache: true\ntasks:\n  - name: build\n    command: \"true\"\n",
+    )?;
+    for i in 0..projects {
+        // Spread projects over directories like a real monorepo
+        let dir = root.join(format!("group{}/p{}", i % 100, i));
+        std::fs::create_dir_all(&dir)?;
+        let dependencies = if i == 0 {
+            String::new()
+        } else {
+            format!("p{}\n", i / 2)
+        };
+        std::fs::write(dir.join("project.txt"), dependencies)?;
+        std::fs::write(dir.join("README.md"), "docs")?;
+    }
+    Ok(())
+}
+
+async fn time_to_plan(root: &Path) -> Duration {
+    let start = Instant::now();
+    let manager = WorkspaceManager::builder(root)
+        .register_plugin(Box::new(ListPlugin))
+        .build()
+        .await
+        .expect("failed to load workspace");
+    manager.get_execution_plan("build").expect("failed to plan");
+    start.elapsed()
+}
+
+#[tokio::main]
+async fn main() {
+    let projects = std::env::args()
+        .nth(1)
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(10_000);
+
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let root = temp
+        .path()
+        .canonicalize()
+        .expect("failed to resolve temp dir");
+    generate(&root, projects).expect("failed to generate workspace");
+    println!("{} projects in {}", projects, root.display());
+
+    let rebuild = time_to_plan(&root).await;
+    let loads = [
+        time_to_plan(&root).await,
+        time_to_plan(&root).await,
+        time_to_plan(&root).await,
+    ];
+    let load = loads.iter().min().copied().unwrap_or_default();
+
+    println!("rebuild (scan, graph, store): {:?}", rebuild);
+    println!("load from discovery cache:    {:?}", load);
+    println!(
+        "speed-up:                     {:.1}x",
+        rebuild.as_secs_f64() / load.as_secs_f64()
+    );
+}
@@ -0,0 +1,129 @@
+//! Compare planning with project configs read one after the other and on several threads.
+//!
+//! Generates a synthetic workspace where every project has a marty.yml and the task is
+//! bound to a tag, so planning reads every config, and measures planning the task and
+//! resolving each project's task definition with `planning.parallelConfigLoading` off
+//! and on:
+//!
+//! ```text
+//! cargo run --release -p marty_core --example planning_bench -- 2000
+//! ```
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use marty_core::WorkspaceManager;
+use marty_plugin_protocol::{
+    InferredProject, MartyPlugin, PluginType, Workspace, WorkspaceProvider,
+};
+
+/// Discovers a project for every `marty.yml`
+struct ConfigPlugin;
+
+impl MartyPlugin for ConfigPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "Config"
+    }
+
+    fn key(&self) -> &str {
+        "config"
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        self
+    }
+}
+
+impl WorkspaceProvider for ConfigPlugin {
+    fn include_path_globs(&self) -> Vec<String> {
+        vec!["**/marty.yml".to_string()]
+    }
+
+    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let project_dir = path.parent()?.to_path_buf();
+        Some(InferredProject {
+            name: project_dir.file_name()?.to_string_lossy().to_string(),
+            project_dir,
+            discovered_by: "config".to_string(),
+            workspace_dependencies: Vec::new(),
+            kind: None,
+        })
+    }
+}
+
+fn generate(root: &Path, projects: usize) -> std::io::Result<()> {
+    std::fs::create_dir_all(root.join(".marty/tasks"))?;
+    std::fs::write(
+        root.join(".marty/tasks/build.yml"),
+        "tags: [bench]\ntasks:\n  - name: build\n    command: \"true\"\n",
+    )?;
+    for i in 0..projects {
+        let dir = root.join(format!("group{}/p{}", i % 100, i));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(
+            dir.join("marty.yml"),
+            format!(
+                "name: p{}\ndescription: Project {}\ntags: [bench, group{}]\ntasks:\n  - name: test\n    command: [\"echo\", \"test\"]\n    inputs: [\"src/**\"]\n",
+                i, i, i % 100
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+async fn time_to_plan(root: &Path, parallel: bool) -> Duration {
+    std::fs::write(
+        root.join(".marty/workspace.yml"),
+        format!(
+            "name: bench\nplanning:\n  parallelConfigLoading: {}\n",
+            parallel
+        ),
+    )
+    .expect("failed to write workspace.yml");
+    let manager = WorkspaceManager::builder(root)
+        .register_plugin(Box::new(ConfigPlugin))
+        .build()
+        .await
+        .expect("failed to load workspace");
+
+    let start = Instant::now();
+    let plan = manager.get_execution_plan("build").expect("failed to plan");
+    manager
+        .plan_task_configs(&plan)
+        .expect("failed to resolve tasks");
+    start.elapsed()
+}
+
+#[tokio::main]
+async fn main() {
+    let projects = std::env::args()
+        .nth(1)
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(2_000);
+
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let root = temp
+        .path()
+        .canonicalize()
+        .expect("failed to resolve temp dir");
+    generate(&root, projects).expect("failed to generate workspace");
+    println!("{} projects in {}", projects, root.display());
+
+    let mut sequential = Duration::MAX;
+    let mut parallel = Duration::MAX;
+    for _ in 0..3 {
+        sequential = sequential.min(time_to_plan(&root, false).await);
+        parallel = parallel.min(time_to_plan(&root, true).await);
+    }
+
+    println!("sequential config loading: {:?}", sequential);
+    println!("parallel config loading:   {:?}", parallel);
+    println!(
+        "speed-up:                  {:.1}x",
+        sequential.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -0,0 +1,108 @@
+//! Plugins compiled into marty
+//!
+//! With the `builtin-cargo`, `builtin-pnpm` and `builtin-typescript` features, those
+//! plugins are linked into the binary and run in-process, so a fresh workspace needs no
+//! download. workspace.yml selects one with `path: "builtin"` and the plugin's name:
+//!
+//! ```yaml
+//! plugins:
+//!   - path: "builtin"
+//!     plugin: "cargo"
+//! ```
+
+use marty_plugin_protocol::MartyPlugin;
+
+use crate::configs::workspace::PluginConfig;
+use crate::types::{MartyError, MartyResult};
+
+/// `path` value of a workspace.yml entry that refers to a compiled-in plugin
+pub const BUILTIN_PATH: &str = "builtin";
+
+/// Whether a workspace.yml entry refers to a compiled-in plugin
+pub fn is_builtin(config: &PluginConfig) -> bool {
+    config.path.as_deref() == Some(BUILTIN_PATH)
+}
+
+/// Names of the plugins compiled into this binary
+pub fn available() -> Vec<&'static str> {
+    [
+        cfg!(feature = "builtin-cargo").then_some("cargo"),
+        cfg!(feature = "builtin-pnpm").then_some("pnpm"),
+        cfg!(feature = "builtin-typescript").then_some("typescript"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// A new instance of a compiled-in plugin
+pub fn create(name: &str) -> Option<Box<dyn MartyPlugin>> {
+    match name {
+        #[cfg(feature = "builtin-cargo")]
+        "cargo" => Some(Box::new(marty_plugin_cargo::CargoPlugin::new())),
+        #[cfg(feature = "builtin-pnpm")]
+        "pnpm" => Some(Box::new(marty_plugin_pnpm::PnpmPlugin::new())),
+        #[cfg(feature = "builtin-typescript")]
+        "typescript" => Some(Box::new(marty_plugin_typescript::TypeScriptPlugin::new())),
+        _ => None,
+    }
+}
+
+/// The compiled-in plugin a `path: "builtin"` entry names
+pub fn resolve(config: &PluginConfig) -> MartyResult<Box<dyn MartyPlugin>> {
+    let available = available();
+    let listed = if available.is_empty() {
+        "none".to_string()
+    } else {
+        available.join(", ")
+    };
+    let Some(name) = &config.plugin else {
+        return Err(MartyError::Config(format!(
+            "A plugin with path \"{}\" must name the plugin with `plugin` (built in: {})",
+            BUILTIN_PATH, listed
+        )));
+    };
+    create(name).ok_or_else(|| {
+        MartyError::Config(format!(
+            "Plugin '{}' is not built into this marty binary (built in: {})",
+            name, listed
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(plugin: Option<&str>) -> PluginConfig {
+        PluginConfig {
+            github_repo: None,
+            plugin: plugin.map(String::from),
+            version: None,
+            url: None,
+            path: Some(BUILTIN_PATH.to_string()),
+            public_key: None,
+            writable: None,
+            enabled: None,
+            options: None,
+        }
+    }
+
+    #[test]
+    fn test_builtin_entries_resolve_to_compiled_in_plugins() {
+        assert!(is_builtin(&entry(Some("cargo"))));
+        for name in available() {
+            let plugin = resolve(&entry(Some(name))).unwrap();
+            assert_eq!(plugin.key(), name);
+        }
+
+        let missing = resolve(&entry(None)).err().unwrap().to_string();
+        assert!(missing.contains("must name the plugin"), "{}", missing);
+        let unknown = resolve(&entry(Some("gradle"))).err().unwrap().to_string();
+        assert!(
+            unknown.contains("'gradle' is not built into"),
+            "{}",
+            unknown
+        );
+    }
+}
@@ -0,0 +1,412 @@
+//! Self-contained bundles for offline CI
+//!
+//! `marty bundle` copies the running marty binary, the plugins workspace.yml downloads
+//! (for each selected platform) and a lockfile pinning them into one directory, or into an
+//! OCI image layout. A marty binary run from a bundle's `bin/` directory, or with
+//! `MARTY_BUNDLE` pointing at a bundle, loads plugins only from the bundle and never
+//! downloads anything.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::configs::workspace::PluginConfig;
+use crate::platform::PlatformInfo;
+use crate::plugin_cache::{file_digest, PluginCache};
+
+/// Name of the lockfile at the root of a bundle
+pub const LOCKFILE: &str = "plugins.lock.json";
+
+/// Environment variable pointing marty at a bundle directory
+pub const BUNDLE_ENV: &str = "MARTY_BUNDLE";
+
+/// Directory the bundle is placed in inside an OCI image
+const IMAGE_ROOT: &str = "marty";
+
+/// Contents of a bundle's lockfile
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleLock {
+    /// Version of the marty binary in the bundle
+    pub marty_version: String,
+    /// Targets plugins were vendored for
+    pub platforms: Vec<String>,
+    pub plugins: Vec<BundledPlugin>,
+}
+
+/// A plugin entry from workspace.yml and its vendored binaries
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledPlugin {
+    /// Description of the workspace.yml entry
+    pub source: String,
+    pub version: Option<String>,
+    pub binaries: Vec<BundledBinary>,
+}
+
+/// A vendored plugin binary
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledBinary {
+    /// Target the binary was built for; `None` for entries with a `url`, which names the
+    /// same binary on every platform
+    pub target: Option<String>,
+    /// Where marty downloads the plugin from outside a bundle
+    pub url: String,
+    /// Path of the binary relative to the bundle root
+    pub file: String,
+    pub sha256: String,
+}
+
+/// How `marty bundle` writes its output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleFormat {
+    Directory,
+    /// An OCI image layout with the bundle as its only layer, under `/marty`
+    Oci,
+}
+
+/// A bundle on disk
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub root: PathBuf,
+    pub lock: BundleLock,
+}
+
+impl Bundle {
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(LOCKFILE);
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read bundle lockfile {}", path.display()))?;
+        let lock = serde_json::from_str(&json)
+            .with_context(|| format!("Invalid bundle lockfile {}", path.display()))?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            lock,
+        })
+    }
+
+    /// The bundle named by `MARTY_BUNDLE`, or else the one the running binary belongs to
+    pub fn locate() -> Result<Option<Self>> {
+        if let Some(root) = std::env::var_os(BUNDLE_ENV).filter(|root| !root.is_empty()) {
+            return Self::load(Path::new(&root)).map(Some);
+        }
+        let root = std::env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.parent()?.parent()?.to_path_buf()));
+        match root {
+            Some(root) if root.join(LOCKFILE).is_file() => Self::load(&root).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The vendored binary of the plugin downloaded from `url`
+    pub fn binary(&self, url: &str) -> Option<&BundledBinary> {
+        self.lock
+            .plugins
+            .iter()
+            .flat_map(|plugin| &plugin.binaries)
+            .find(|binary| binary.url == url)
+    }
+
+    /// Path of the vendored binary of the plugin downloaded from `url`, after checking it
+    /// against the lockfile
+    pub fn plugin_file(&self, url: &str) -> Result<PathBuf> {
+        let binary = self.binary(url).ok_or_else(|| {
+            anyhow!(
+                "Plugin {} is not in the bundle at {}; create the bundle again with this workspace.yml and platform",
+                url,
+                self.root.display()
+            )
+        })?;
+        let path = self.root.join(&binary.file);
+        let actual = file_digest(&path)?;
+        if actual != binary.sha256 {
+            bail!(
+                "Bundled plugin {} doesn't match the lockfile (sha256 {} instead of {})",
+                path.display(),
+                actual,
+                binary.sha256
+            );
+        }
+        Ok(path)
+    }
+}
+
+/// File name of the marty binary on this platform
+fn binary_name() -> String {
+    format!("marty{}", std::env::consts::EXE_SUFFIX)
+}
+
+/// Bundle the running marty binary with the plugins of `plugin_configs` for each of
+/// `platforms`, downloading them through `cache`
+pub async fn create(
+    cache: &PluginCache,
+    plugin_configs: &[PluginConfig],
+    platforms: &[PlatformInfo],
+    output: &Path,
+    format: BundleFormat,
+) -> Result<BundleLock> {
+    let occupied = fs::read_dir(output)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(output.exists());
+    if occupied {
+        bail!("{} already exists and is not empty", output.display());
+    }
+
+    let staging = match format {
+        BundleFormat::Directory => None,
+        BundleFormat::Oci => {
+            Some(tempfile::tempdir().context("Failed to create staging directory")?)
+        }
+    };
+    let dir = staging.as_ref().map_or(output, |staging| staging.path());
+
+    let plugins = cache.vendor_plugins(plugin_configs, platforms, dir).await?;
+
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin).with_context(|| format!("Failed to create {}", bin.display()))?;
+    let exe = std::env::current_exe().context("Failed to locate the marty binary")?;
+    fs::copy(&exe, bin.join(binary_name()))
+        .with_context(|| format!("Failed to copy {}", exe.display()))?;
+
+    let lock = BundleLock {
+        marty_version: env!("CARGO_PKG_VERSION").to_string(),
+        platforms: platforms
+            .iter()
+            .map(|platform| platform.target.to_string())
+            .collect(),
+        plugins,
+    };
+    fs::write(
+        dir.join(LOCKFILE),
+        serde_json::to_string_pretty(&lock)? + "\n",
+    )
+    .context("Failed to write the bundle lockfile")?;
+
+    if format == BundleFormat::Oci {
+        write_oci_layout(dir, output)?;
+    }
+    Ok(lock)
+}
+
+/// Write `bundle_dir` as an OCI image layout at `output`: one layer holding the bundle
+/// under `/marty`, with its marty binary as the entrypoint
+pub fn write_oci_layout(bundle_dir: &Path, output: &Path) -> Result<()> {
+    let blobs = output.join("blobs").join("sha256");
+    fs::create_dir_all(&blobs).with_context(|| format!("Failed to create {}", blobs.display()))?;
+
+    let layer = write_blob(
+        &blobs,
+        "application/vnd.oci.image.layer.v1.tar",
+        &layer_tar(bundle_dir)?,
+    )?;
+    let (os, architecture) = image_platform();
+    let config = json!({
+        "architecture": architecture,
+        "os": os,
+        "config": {
+            "Entrypoint": [format!("/{}/bin/{}", IMAGE_ROOT, binary_name())],
+        },
+        "rootfs": { "type": "layers", "diff_ids": [layer["digest"]] },
+    });
+    let config = write_blob(
+        &blobs,
+        "application/vnd.oci.image.config.v1+json",
+        &serde_json::to_vec(&config)?,
+    )?;
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": config,
+        "layers": [layer],
+    });
+    let mut manifest = write_blob(
+        &blobs,
+        "application/vnd.oci.image.manifest.v1+json",
+        &serde_json::to_vec(&manifest)?,
+    )?;
+    manifest["annotations"] = json!({
+        "org.opencontainers.image.ref.name": env!("CARGO_PKG_VERSION"),
+    });
+
+    fs::write(
+        output.join("oci-layout"),
+        json!({ "imageLayoutVersion": "1.0.0" }).to_string(),
+    )?;
+    fs::write(
+        output.join("index.json"),
+        serde_json::to_vec(&json!({ "schemaVersion": 2, "manifests": [manifest] }))?,
+    )?;
+    Ok(())
+}
+
+/// Store `data` under its digest and describe it
+fn write_blob(blobs: &Path, media_type: &str, data: &[u8]) -> Result<serde_json::Value> {
+    let digest = format!("{:x}", Sha256::digest(data));
+    let path = blobs.join(&digest);
+    fs::write(&path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(json!({
+        "mediaType": media_type,
+        "digest": format!("sha256:{}", digest),
+        "size": data.len(),
+    }))
+}
+
+/// The bundle as a tar archive under `marty/`, with fixed owners and timestamps so the
+/// same bundle always gives the same layer
+fn layer_tar(bundle_dir: &Path) -> Result<Vec<u8>> {
+    let mut files = Vec::new();
+    collect_files(bundle_dir, Path::new(""), &mut files)?;
+    files.sort();
+
+    let mut archive = tar::Builder::new(Vec::new());
+    for relative in files {
+        let data = fs::read(bundle_dir.join(&relative))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(data.len() as u64);
+        header.set_mode(if relative.starts_with("bin") {
+            0o755
+        } else {
+            0o644
+        });
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        archive.append_data(
+            &mut header,
+            Path::new(IMAGE_ROOT).join(&relative),
+            data.as_slice(),
+        )?;
+    }
+    Ok(archive.into_inner()?)
+}
+
+/// Paths of the files under `dir`, relative to the directory the walk started in
+fn collect_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &relative, files)?;
+        } else {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// OCI names of the operating system and architecture of the running binary
+fn image_platform() -> (&'static str, &'static str) {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    let architecture = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        arch => arch,
+    };
+    (os, architecture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle_with(root: &Path, contents: &[u8]) -> Bundle {
+        fs::create_dir_all(root.join("plugins")).unwrap();
+        fs::write(root.join("plugins/foo_12345678.so"), contents).unwrap();
+        Bundle {
+            root: root.to_path_buf(),
+            lock: BundleLock {
+                marty_version: "0.3.3".to_string(),
+                platforms: vec!["x86_64-unknown-linux-gnu".to_string()],
+                plugins: vec![BundledPlugin {
+                    source: "url https://example.com/foo.so".to_string(),
+                    version: None,
+                    binaries: vec![BundledBinary {
+                        target: None,
+                        url: "https://example.com/foo.so".to_string(),
+                        file: "plugins/foo_12345678.so".to_string(),
+                        sha256: format!("{:x}", Sha256::digest(b"foo")),
+                    }],
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn test_bundled_plugins_are_checked_against_the_lockfile() {
+        let root = tempfile::tempdir().unwrap();
+        let bundle = bundle_with(root.path(), b"foo");
+        assert_eq!(
+            bundle.plugin_file("https://example.com/foo.so").unwrap(),
+            root.path().join("plugins/foo_12345678.so")
+        );
+        let missing = bundle
+            .plugin_file("https://example.com/bar.so")
+            .unwrap_err();
+        assert!(missing.to_string().contains("is not in the bundle"));
+
+        let tampered = bundle_with(root.path(), b"bar");
+        let error = tampered
+            .plugin_file("https://example.com/foo.so")
+            .unwrap_err();
+        assert!(error.to_string().contains("doesn't match the lockfile"));
+    }
+
+    #[test]
+    fn test_oci_layout_refers_to_its_blobs() {
+        let root = tempfile::tempdir().unwrap();
+        let bundle_dir = root.path().join("bundle");
+        let bundle = bundle_with(&bundle_dir, b"foo");
+        fs::write(
+            bundle_dir.join(LOCKFILE),
+            serde_json::to_string(&bundle.lock).unwrap(),
+        )
+        .unwrap();
+        let output = root.path().join("image");
+        write_oci_layout(&bundle_dir, &output).unwrap();
+
+        let read_json = |path: PathBuf| -> serde_json::Value {
+            serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+        };
+        let blob = |descriptor: &serde_json::Value| {
+            let digest = descriptor["digest"].as_str().unwrap();
+            let path = output
+                .join("blobs/sha256")
+                .join(digest.trim_start_matches("sha256:"));
+            let data = fs::read(&path).unwrap();
+            assert_eq!(descriptor["size"], data.len());
+            assert_eq!(digest, format!("sha256:{:x}", Sha256::digest(&data)));
+            path
+        };
+
+        let index = read_json(output.join("index.json"));
+        let manifest = read_json(blob(&index["manifests"][0]));
+        let config = read_json(blob(&manifest["config"]));
+        let layer = blob(&manifest["layers"][0]);
+        assert_eq!(
+            config["rootfs"]["diff_ids"][0],
+            manifest["layers"][0]["digest"]
+        );
+
+        let mut archive = tar::Archive::new(fs::File::open(layer).unwrap());
+        let mut names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["marty/plugins.lock.json", "marty/plugins/foo_12345678.so"]
+        );
+    }
+}
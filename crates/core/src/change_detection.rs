@@ -0,0 +1,332 @@
+//! Change detection
+//!
+//! The affected planner starts from the files changed since a base: the projects owning
+//! them are affected, and so is every project depending on those. Where that list comes
+//! from is up to a [`ChangeDetector`], selected with `changeDetection` in workspace.yml or
+//! `--change-detection`:
+//!
+//! - [`GitChangeDetector`] diffs the working tree against a git ref
+//! - [`MercurialChangeDetector`] asks `hg status` for the changes since a revision
+//! - [`SnapshotChangeDetector`] compares file hashes with a snapshot taken by
+//!   `marty snapshot`, for checkouts without version control
+//! - [`FileListChangeDetector`] takes the list as it is, e.g. from a CI system that
+//!   already knows which files a change touches and pipes them to stdin
+//!
+//! Without a base, detectors compare with the revision the working tree is based on, or
+//! for snapshots with the one named [`DEFAULT_SNAPSHOT`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::configs::workspace::ChangeDetection;
+use crate::git;
+use crate::types::{MartyError, MartyResult};
+use crate::workspace::{list_workspace_files, Workspace};
+
+/// Snapshot compared with when no base is given, and taken by `marty snapshot` without a name
+pub const DEFAULT_SNAPSHOT: &str = "latest";
+
+/// Files that change on every run without changing any project, left out of snapshots
+const SNAPSHOT_EXCLUDES: &[&str] = &[
+    "**/.hg/**",
+    ".marty/state.json",
+    ".marty/logs/**",
+    ".marty/runs/**",
+    ".marty/snapshots/**",
+];
+
+/// Finds the files changed since a base
+pub trait ChangeDetector: fmt::Debug + Send + Sync {
+    /// Files changed since `base` (or the detector's default base), relative to the
+    /// workspace root
+    fn changed_files(&self, workspace: &Workspace, base: Option<&str>)
+        -> MartyResult<Vec<PathBuf>>;
+}
+
+/// The detector for a `changeDetection` setting. `excludes` are the workspace excludes,
+/// which snapshots leave out.
+pub fn change_detector(kind: ChangeDetection, excludes: &[String]) -> Arc<dyn ChangeDetector> {
+    match kind {
+        ChangeDetection::Git => Arc::new(GitChangeDetector),
+        ChangeDetection::Mercurial => Arc::new(MercurialChangeDetector),
+        ChangeDetection::Snapshot => Arc::new(SnapshotChangeDetector::new(excludes.to_vec())),
+        ChangeDetection::Stdin => Arc::new(FileListChangeDetector::stdin()),
+    }
+}
+
+/// Committed, uncommitted and untracked changes in a git repository. The base is a git ref
+/// and defaults to `HEAD`.
+#[derive(Debug, Default)]
+pub struct GitChangeDetector;
+
+impl ChangeDetector for GitChangeDetector {
+    fn changed_files(
+        &self,
+        workspace: &Workspace,
+        base: Option<&str>,
+    ) -> MartyResult<Vec<PathBuf>> {
+        git::changed_files_since(&workspace.root, base.unwrap_or("HEAD"))
+    }
+}
+
+/// Changes in a Mercurial repository, untracked files included. The base is a revision
+/// and defaults to `.`, the parent of the working directory.
+#[derive(Debug, Default)]
+pub struct MercurialChangeDetector;
+
+impl ChangeDetector for MercurialChangeDetector {
+    fn changed_files(
+        &self,
+        workspace: &Workspace,
+        base: Option<&str>,
+    ) -> MartyResult<Vec<PathBuf>> {
+        let base = base.unwrap_or(".");
+        // With a pattern, hg prints paths relative to the current directory
+        let args = ["status", "--rev", base, "-mardu", "--no-status", "."];
+        let output = Command::new("hg")
+            .args(args)
+            .current_dir(&workspace.root)
+            .output()
+            .map_err(|e| MartyError::Workspace(format!("Failed to execute hg: {}", e)))?;
+        if !output.status.success() {
+            return Err(MartyError::Workspace(format!(
+                "hg {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(file_list(String::from_utf8_lossy(&output.stdout).lines()))
+    }
+}
+
+/// Hashes of every workspace file, as recorded by [`SnapshotChangeDetector::save`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    files: BTreeMap<PathBuf, String>,
+}
+
+/// Changes since a snapshot of the workspace's file hashes, for checkouts without version
+/// control. The base names a snapshot in `.marty/snapshots` and defaults to
+/// [`DEFAULT_SNAPSHOT`]. Files added, removed or modified since count as changed.
+#[derive(Debug, Default)]
+pub struct SnapshotChangeDetector {
+    excludes: Vec<String>,
+}
+
+impl SnapshotChangeDetector {
+    /// A detector leaving out files matching `excludes`, besides the usual ones
+    pub fn new(excludes: Vec<String>) -> Self {
+        Self { excludes }
+    }
+
+    /// Location of a named snapshot
+    pub fn path(workspace_root: &Path, name: &str) -> PathBuf {
+        workspace_root
+            .join(".marty")
+            .join("snapshots")
+            .join(format!("{}.json", name))
+    }
+
+    /// Record the hashes of the workspace files as snapshot `name`, replacing any earlier
+    /// one. Returns the number of files recorded.
+    pub fn save(&self, workspace: &Workspace, name: &str) -> MartyResult<usize> {
+        let snapshot = self.take(workspace)?;
+        let path = Self::path(&workspace.root, name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| MartyError::Workspace(format!("Failed to serialize snapshot: {}", e)))?;
+        std::fs::write(&path, content)?;
+        Ok(snapshot.files.len())
+    }
+
+    fn take(&self, workspace: &Workspace) -> MartyResult<Snapshot> {
+        let excludes: Vec<String> = SNAPSHOT_EXCLUDES
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .chain(self.excludes.iter().cloned())
+            .collect();
+        let mut files = BTreeMap::new();
+        for file in list_workspace_files(workspace, &excludes) {
+            let bytes = std::fs::read(workspace.root.join(&file))?;
+            files.insert(file, format!("{:x}", Sha256::digest(&bytes)));
+        }
+        Ok(Snapshot { files })
+    }
+
+    fn load(workspace_root: &Path, name: &str) -> MartyResult<Snapshot> {
+        let path = Self::path(workspace_root, name);
+        let content = std::fs::read_to_string(&path).map_err(|_| {
+            MartyError::Workspace(format!(
+                "No snapshot named '{}', take one with `marty snapshot {}`",
+                name, name
+            ))
+        })?;
+        serde_json::from_str(&content).map_err(|e| {
+            MartyError::Workspace(format!(
+                "Failed to parse snapshot {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+impl ChangeDetector for SnapshotChangeDetector {
+    fn changed_files(
+        &self,
+        workspace: &Workspace,
+        base: Option<&str>,
+    ) -> MartyResult<Vec<PathBuf>> {
+        let before = Self::load(&workspace.root, base.unwrap_or(DEFAULT_SNAPSHOT))?;
+        let now = self.take(workspace)?;
+
+        let mut changed: Vec<PathBuf> = now
+            .files
+            .iter()
+            .filter(|(file, hash)| before.files.get(*file) != Some(*hash))
+            .chain(
+                before
+                    .files
+                    .iter()
+                    .filter(|(file, _)| !now.files.contains_key(*file)),
+            )
+            .map(|(file, _)| file.clone())
+            .collect();
+        changed.sort();
+        Ok(changed)
+    }
+}
+
+/// A list of changed files handed to Marty, one path relative to the workspace root per
+/// line. The base is ignored: whoever made the list already compared with theirs.
+#[derive(Debug)]
+pub struct FileListChangeDetector {
+    /// `None` until stdin is read, so commands that never ask for changes leave it alone
+    files: Mutex<Option<Vec<PathBuf>>>,
+}
+
+impl FileListChangeDetector {
+    /// Changes from a list of paths
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        Self {
+            files: Mutex::new(Some(files)),
+        }
+    }
+
+    /// Changes read from stdin the first time they are needed
+    pub fn stdin() -> Self {
+        Self {
+            files: Mutex::new(None),
+        }
+    }
+
+    /// Changes from a reader with one path per line. Blank lines are skipped, and so is
+    /// a leading `./`.
+    pub fn from_reader(reader: impl Read) -> MartyResult<Self> {
+        read_file_list(reader).map(Self::new)
+    }
+}
+
+impl ChangeDetector for FileListChangeDetector {
+    fn changed_files(
+        &self,
+        _workspace: &Workspace,
+        _base: Option<&str>,
+    ) -> MartyResult<Vec<PathBuf>> {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        if files.is_none() {
+            *files = Some(read_file_list(std::io::stdin().lock())?);
+        }
+        Ok(files.clone().unwrap_or_default())
+    }
+}
+
+fn read_file_list(reader: impl Read) -> MartyResult<Vec<PathBuf>> {
+    let lines = std::io::BufReader::new(reader)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(file_list(lines.iter().map(String::as_str)))
+}
+
+/// Sorted, deduplicated paths from lines of output
+fn file_list<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = lines
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(line.strip_prefix("./").unwrap_or(line)))
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::ProjectIndex;
+
+    fn workspace(root: &Path) -> Workspace {
+        Workspace {
+            root: root.to_path_buf(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_reports_added_removed_and_modified_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("api/src")).unwrap();
+        std::fs::write(root.join("api/src/lib.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("api/README.md"), "api").unwrap();
+        std::fs::write(root.join("notes.txt"), "ignored").unwrap();
+        let workspace = workspace(root);
+        let detector = SnapshotChangeDetector::new(vec!["notes.txt".to_string()]);
+
+        assert!(detector.changed_files(&workspace, None).is_err());
+        assert_eq!(detector.save(&workspace, DEFAULT_SNAPSHOT).unwrap(), 2);
+        assert!(detector.changed_files(&workspace, None).unwrap().is_empty());
+
+        std::fs::write(root.join("api/src/lib.rs"), "fn b() {}").unwrap();
+        std::fs::remove_file(root.join("api/README.md")).unwrap();
+        std::fs::write(root.join("api/src/new.rs"), "").unwrap();
+        std::fs::write(root.join("notes.txt"), "still ignored").unwrap();
+        assert_eq!(
+            detector.changed_files(&workspace, Some("latest")).unwrap(),
+            vec![
+                PathBuf::from("api/README.md"),
+                PathBuf::from("api/src/lib.rs"),
+                PathBuf::from("api/src/new.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_list_skips_blank_lines_and_leading_dot() {
+        let input = "./web/index.ts\n\napi/src/lib.rs\r\nweb/index.ts\n";
+        let detector = FileListChangeDetector::from_reader(input.as_bytes()).unwrap();
+        let workspace = workspace(Path::new("/ws"));
+        assert_eq!(
+            detector.changed_files(&workspace, Some("main")).unwrap(),
+            vec![
+                PathBuf::from("api/src/lib.rs"),
+                PathBuf::from("web/index.ts")
+            ]
+        );
+    }
+}
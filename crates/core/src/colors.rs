@@ -0,0 +1,111 @@
+//! Project label colors
+//!
+//! Every command labels a project with the same color: it is picked from a fixed palette
+//! by a hash of the project name, so it doesn't change between commands, between runs or
+//! when other projects are added. [`ProjectColor`] is plain RGB, so other frontends can
+//! match the CLI's colors.
+
+use std::collections::BTreeMap;
+
+use colored::Color;
+use serde::Serialize;
+
+/// The color a project's name and output prefix are shown in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct ProjectColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl ProjectColor {
+    /// The color as `#rrggbb`
+    pub fn hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl From<ProjectColor> for Color {
+    fn from(color: ProjectColor) -> Self {
+        Color::TrueColor {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+        }
+    }
+}
+
+/// Vibrant jewel tones that read as labels, avoiding the conventional log colors
+/// (red/yellow/green/blue)
+pub const PALETTE: [ProjectColor; 6] = [
+    // Medium slate blue
+    ProjectColor {
+        r: 147,
+        g: 112,
+        b: 219,
+    },
+    // Turquoise
+    ProjectColor {
+        r: 64,
+        g: 224,
+        b: 208,
+    },
+    // Dark orange
+    ProjectColor {
+        r: 255,
+        g: 140,
+        b: 0,
+    },
+    // Medium violet red
+    ProjectColor {
+        r: 199,
+        g: 21,
+        b: 133,
+    },
+    // Medium turquoise
+    ProjectColor {
+        r: 72,
+        g: 209,
+        b: 204,
+    },
+    // Blue violet
+    ProjectColor {
+        r: 138,
+        g: 43,
+        b: 226,
+    },
+];
+
+/// The color of a project
+pub fn project_color(project_name: &str) -> ProjectColor {
+    let hash = project_name
+        .bytes()
+        .fold(0u64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u64));
+    PALETTE[(hash % PALETTE.len() as u64) as usize]
+}
+
+/// The colors of several projects, ordered by name
+pub fn project_colors<'a>(
+    project_names: impl IntoIterator<Item = &'a str>,
+) -> BTreeMap<String, ProjectColor> {
+    project_names
+        .into_iter()
+        .map(|name| (name.to_string(), project_color(name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_depend_only_on_the_name() {
+        // Pinned, since external frontends rely on the assignment
+        assert_eq!(project_color("app").hex(), "#c71585");
+        assert_eq!(project_color("lib").hex(), "#8a2be2");
+
+        let colors = project_colors(["lib", "app", "web"]);
+        assert_eq!(colors.keys().collect::<Vec<_>>(), vec!["app", "lib", "web"]);
+        assert_eq!(colors["lib"], project_color("lib"));
+    }
+}
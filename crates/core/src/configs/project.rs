@@ -1,7 +1,8 @@
+use marty_plugin_protocol::ProjectKind;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::configs::tasks::TaskConfig;
+use crate::configs::tasks::{expand_document, TaskConfig};
 use crate::types::MartyResult;
 
 #[derive(Deserialize, Serialize, JsonSchema, Clone)]
@@ -10,11 +11,28 @@ pub struct ProjectConfig {
     pub name: Option<String>,
     pub description: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Teams or people responsible for the project, as exported by `marty export metadata`
+    pub owners: Option<Vec<String>>,
+    /// application, library, tool or test; overrides the kind inferred by plugins
+    #[schemars(with = "Option<String>")]
+    pub kind: Option<ProjectKind>,
     pub dependencies: Option<Vec<String>>,
     pub tasks: Option<Vec<TaskConfig>>,
+    /// Glob patterns of projects this project may depend on, or `kind:<kind>` to match projects
+    /// of a kind. If set, any other dependency is a violation.
+    pub allowed_dependencies: Option<Vec<String>>,
+    /// Glob patterns, or `kind:<kind>`, of projects this project must not depend on
+    pub denied_dependencies: Option<Vec<String>>,
 }
 
 pub fn parse_project_config(yaml_str: &str) -> MartyResult<ProjectConfig> {
-    let config: ProjectConfig = serde_yaml::from_str(yaml_str)?;
+    let value: serde_yaml::Value = serde_yaml::from_str(yaml_str)?;
+    let expanded = expand_document(value.clone())?;
+    // Deserialize the text directly when possible, so errors keep their line number
+    let config: ProjectConfig = if expanded == value {
+        serde_yaml::from_str(yaml_str)?
+    } else {
+        serde_yaml::from_value(expanded)?
+    };
     Ok(config)
 }
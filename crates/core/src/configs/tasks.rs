@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
+
+use marty_plugin_protocol::ProjectKind;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::types::MartyResult;
+use crate::types::{MartyError, MartyResult};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 #[serde(untagged)]
@@ -10,15 +13,146 @@ pub enum Command {
     Multiple(Vec<String>),
 }
 
-#[derive(Deserialize, Serialize, JsonSchema, Clone)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Default)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct TaskConfig {
     pub name: String,
     pub description: Option<String>,
+    /// Long-form Markdown documentation (usage, examples, flags) shown by `marty tasks describe`
+    pub docs: Option<String>,
     pub script: Option<String>,
     pub command: Option<Command>,
+    /// Key of a plugin that runs the task itself, instead of a command or script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+    /// Tasks to run first: `task` on the same targets, `^task` on every workspace
+    /// dependency of the project, or `project:task` on one specific project
     pub dependencies: Option<Vec<String>>,
     pub override_targets: Option<Vec<String>>,
+    /// Backend the task runs on (defaults to the local shell)
+    pub executor: Option<ExecutorConfig>,
+    /// Commands whose output identifies the tool versions the task depends on
+    /// (e.g. "node --version"). Their output is part of the task's cache key.
+    pub env_probe: Option<Vec<String>>,
+    /// How many times a failing or timed out command is retried (defaults to 0)
+    pub retries: Option<u32>,
+    /// Seconds to wait before the first retry, doubled for each further retry (defaults to 1)
+    pub retry_delay: Option<u64>,
+    /// Seconds a command may run before it is killed
+    pub timeout: Option<u64>,
+    /// The command keeps running until stopped, like a dev server. Every project's command
+    /// starts at the same time, after the task's dependencies, and Ctrl-C stops them all.
+    /// Persistent tasks are never cached, retried or timed out.
+    pub persistent: Option<bool>,
+    /// Environment variables for the task's commands. `${VAR}` in values is expanded,
+    /// including `${MARTY_PROJECT_DIR}` and `${MARTY_WORKSPACE_ROOT}`.
+    pub env: Option<BTreeMap<String, String>>,
+    /// Globs, relative to the project directory, of the files the task reads. When set, only
+    /// matching files are part of the cache key instead of the whole project directory.
+    pub inputs: Option<Vec<String>>,
+    /// Globs, relative to the project directory, of the files the task produces. They are
+    /// never part of the cache key, and are stored with the cache entry and restored on a hit.
+    pub outputs: Option<Vec<String>>,
+    /// What a successful run does when one of the `outputs` globs matches no non-empty file
+    /// (defaults to failing the task)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing_outputs: Option<MissingOutputs>,
+    /// Globs, relative to the project directory, of files copied to
+    /// `.marty/artifacts/<project>/<task>/` after the task succeeds, for CI to pick up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifacts: Option<Vec<String>>,
+    /// What a run of the task does when it fails on a project (defaults to the task file's)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_error: Option<ErrorPolicy>,
+    /// Shell commands run in the project's context before the task's command. The first one
+    /// failing fails the task without running its command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Vec<String>>,
+    /// Shell commands run in the project's context after the task's command, even if the
+    /// task failed, like a `finally` block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Vec<String>>,
+}
+
+/// What a run does once the task failed on a project. Flags given on the command line,
+/// such as `--keep-going`, take precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorPolicy {
+    /// Start no further projects and stop the commands still running on other projects
+    FailFast,
+    /// Keep running projects that don't depend on a failed one
+    Continue,
+    /// Run every project, including those depending on a failed one
+    Isolate,
+}
+
+/// How a task that succeeded without producing its declared outputs is treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MissingOutputs {
+    /// Fail the task, so no cache entry is recorded for the run
+    #[default]
+    Fail,
+    /// Print a warning and treat the task as successful
+    Warn,
+    /// Don't check the outputs
+    Ignore,
+}
+
+impl TaskConfig {
+    pub fn is_persistent(&self) -> bool {
+        self.persistent.unwrap_or(false)
+    }
+}
+
+/// An entry of a task's `dependencies`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskDependency<'a> {
+    /// `task`: another task on the same targets
+    Task(&'a str),
+    /// `^task`: the task on every workspace dependency of the project first
+    Dependencies(&'a str),
+    /// `project:task`: the task on one specific project
+    Project { project: &'a str, task: &'a str },
+}
+
+impl<'a> TaskDependency<'a> {
+    pub fn parse(dependency: &'a str) -> Self {
+        if let Some(task) = dependency.strip_prefix('^') {
+            return Self::Dependencies(task);
+        }
+        match dependency.rsplit_once(':') {
+            Some((project, task)) => Self::Project { project, task },
+            None => Self::Task(dependency),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
+pub enum ExecutorConfig {
+    /// Run directly on the host
+    Local,
+    /// Run inside a Docker container with the workspace mounted at the same path
+    Docker {
+        image: String,
+        /// Extra arguments passed to `docker run`
+        options: Option<Vec<String>>,
+    },
+    /// Run on another machine over SSH
+    Remote {
+        host: String,
+        /// Directory of the workspace on the remote host (defaults to the local workspace path)
+        workdir: Option<String>,
+    },
+    /// Run the command as a WASI module, sandboxed to the project directory
+    Wasm {
+        /// Program running the module, invoked like `wasmtime run` (defaults to `wasmtime`)
+        runtime: Option<String>,
+        /// Further directories the module may access, relative to the workspace root
+        dirs: Option<Vec<String>>,
+    },
 }
 
 #[derive(Deserialize, Serialize, JsonSchema, Clone)]
@@ -28,10 +162,218 @@ pub struct TasksFileConfig {
     pub description: Option<String>,
     pub tasks: Vec<TaskConfig>,
     pub tags: Option<Vec<String>>,
+    /// Only bind the tasks to projects of these kinds
+    #[schemars(with = "Option<Vec<String>>")]
+    pub kinds: Option<Vec<ProjectKind>>,
     pub targets: Option<Vec<String>>,
+    /// Environment variables for every task in the file; a task's own `env` takes precedence
+    pub env: Option<BTreeMap<String, String>>,
+    /// Error policy of every task in the file that doesn't set its own `onError`
+    pub on_error: Option<ErrorPolicy>,
 }
 
+/// Parse a task file.
+///
+/// A file may contain several YAML documents separated by `---`; their tasks are combined
+/// in order. The name and description come from the first document that sets them, and
+/// tags, kinds and targets are merged. Anchors, aliases and `<<` merge keys are expanded, and
+/// top-level keys starting with `x-` are ignored so they can hold shared anchored defaults.
 pub fn parse_tasks_config(yaml_str: &str) -> MartyResult<TasksFileConfig> {
-    let config: TasksFileConfig = serde_yaml::from_str(yaml_str)?;
-    Ok(config)
+    let mut combined: Option<TasksFileConfig> = None;
+
+    // Documents are read twice: once as plain values to expand them, and once directly when
+    // expansion changed nothing, so errors keep their field path and line number
+    let documents = serde_yaml::Deserializer::from_str(yaml_str)
+        .zip(serde_yaml::Deserializer::from_str(yaml_str));
+    for (document, original) in documents {
+        let value = serde_yaml::Value::deserialize(document)?;
+        if value.is_null() {
+            continue;
+        }
+        let expanded = expand_document(value.clone())?;
+        let mut config: TasksFileConfig = if expanded == value {
+            TasksFileConfig::deserialize(original)?
+        } else {
+            serde_yaml::from_value(expanded)?
+        };
+        // File-level variables only apply to the tasks of their own document
+        if let Some(file_env) = config.env.take() {
+            for task in &mut config.tasks {
+                let mut env = file_env.clone();
+                env.extend(task.env.take().unwrap_or_default());
+                task.env = Some(env);
+            }
+        }
+        if let Some(policy) = config.on_error.take() {
+            for task in &mut config.tasks {
+                task.on_error.get_or_insert(policy);
+            }
+        }
+
+        combined = Some(match combined {
+            None => config,
+            Some(mut combined) => {
+                combined.name = combined.name.or(config.name);
+                combined.description = combined.description.or(config.description);
+                combined.tasks.extend(config.tasks);
+                combined.tags = merge_lists(combined.tags, config.tags);
+                combined.kinds = merge_lists(combined.kinds, config.kinds);
+                combined.targets = merge_lists(combined.targets, config.targets);
+                combined
+            }
+        });
+    }
+
+    combined.ok_or_else(|| MartyError::Config("Task file contains no documents".to_string()))
+}
+
+/// Expand `<<` merge keys and drop top-level `x-` extension keys from a config document
+pub(crate) fn expand_document(mut value: serde_yaml::Value) -> MartyResult<serde_yaml::Value> {
+    value.apply_merge()?;
+    if let serde_yaml::Value::Mapping(mapping) = &mut value {
+        mapping.retain(|key, _| !key.as_str().is_some_and(|key| key.starts_with("x-")));
+    }
+    Ok(value)
+}
+
+fn merge_lists<T: PartialEq>(first: Option<Vec<T>>, second: Option<Vec<T>>) -> Option<Vec<T>> {
+    match (first, second) {
+        (Some(mut first), Some(second)) => {
+            for item in second {
+                if !first.contains(&item) {
+                    first.push(item);
+                }
+            }
+            Some(first)
+        }
+        (first, second) => first.or(second),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchored_defaults_are_expanded() {
+        let config = parse_tasks_config(
+            r#"
+x-defaults: &defaults
+  retries: 2
+  timeout: 60
+  dependencies: ["build"]
+tasks:
+  - <<: *defaults
+    name: test
+    command: cargo test
+  - <<: *defaults
+    name: lint
+    command: cargo clippy
+    retries: 0
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.tasks.len(), 2);
+        assert_eq!(config.tasks[0].retries, Some(2));
+        assert_eq!(config.tasks[0].timeout, Some(60));
+        assert_eq!(
+            config.tasks[1].dependencies,
+            Some(vec!["build".to_string()])
+        );
+        // Keys set on the task take precedence over the merged defaults
+        assert_eq!(config.tasks[1].retries, Some(0));
+    }
+
+    #[test]
+    fn test_task_dependency_syntax() {
+        assert_eq!(
+            TaskDependency::parse("build"),
+            TaskDependency::Task("build")
+        );
+        assert_eq!(
+            TaskDependency::parse("^build"),
+            TaskDependency::Dependencies("build")
+        );
+        assert_eq!(
+            TaskDependency::parse("@acme/ui:build"),
+            TaskDependency::Project {
+                project: "@acme/ui",
+                task: "build"
+            }
+        );
+    }
+
+    #[test]
+    fn test_file_env_applies_to_its_tasks() {
+        let config = parse_tasks_config(
+            "env:\n  MODE: ci\n  LEVEL: '1'\ntasks:\n  - name: build\n    command: make\n    env:\n      LEVEL: '2'\n---\ntasks:\n  - name: test\n    command: make test\n",
+        )
+        .unwrap();
+
+        let env = config.tasks[0].env.as_ref().unwrap();
+        assert_eq!(env["MODE"], "ci");
+        assert_eq!(env["LEVEL"], "2");
+        assert!(config.tasks[1].env.is_none());
+    }
+
+    #[test]
+    fn test_file_error_policy_applies_to_its_tasks() {
+        let config = parse_tasks_config(
+            "onError: isolate\ntasks:\n  - name: build\n    command: make\n  - name: lint\n    command: make lint\n    onError: failFast\n---\ntasks:\n  - name: test\n    command: make test\n",
+        )
+        .unwrap();
+
+        let policies: Vec<_> = config.tasks.iter().map(|t| t.on_error).collect();
+        assert_eq!(
+            policies,
+            [
+                Some(ErrorPolicy::Isolate),
+                Some(ErrorPolicy::FailFast),
+                None
+            ]
+        );
+        assert!(parse_tasks_config("onError: retry\ntasks: []\n").is_err());
+    }
+
+    #[test]
+    fn test_multiple_documents_are_combined() {
+        let config = parse_tasks_config(
+            r#"
+name: ci
+tags: ["rust"]
+tasks:
+  - &build
+    name: build
+    command: make
+---
+tags: ["rust", "ci"]
+tasks:
+  - <<: *build
+    name: release
+---
+"#,
+        );
+        // Anchors do not cross document boundaries
+        assert!(config.is_err());
+
+        let config = parse_tasks_config(
+            "name: ci\ntags: [rust]\ntasks:\n  - name: build\n    command: make\n---\nname: other\ntags: [rust, ci]\ntasks:\n  - name: test\n    command: make test\n---\n",
+        )
+        .unwrap();
+        let names: Vec<&str> = config.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["build", "test"]);
+        assert_eq!(config.name.as_deref(), Some("ci"));
+        assert_eq!(
+            config.tags,
+            Some(vec!["rust".to_string(), "ci".to_string()])
+        );
+
+        assert!(parse_tasks_config("---\n").is_err());
+
+        let error = parse_tasks_config("tasks: []\n---\ntasks:\n  - command: make\n")
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("line 4"), "{}", error);
+    }
 }
@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::configs::tasks::TaskConfig;
 use crate::types::MartyResult;
 
 #[derive(Deserialize, Serialize, JsonSchema, Clone)]
@@ -13,6 +16,251 @@ pub struct WorkspaceConfig {
     pub includes: Option<Vec<String>>,
     /// Glob patterns for paths to exclude from workspace traversal.
     pub excludes: Option<Vec<String>>,
+    /// Task result cache configuration
+    pub cache: Option<CacheConfig>,
+    /// How `marty bootstrap` sets up a fresh checkout
+    pub bootstrap: Option<BootstrapConfig>,
+    /// Notifications sent when a task run finishes
+    pub notifications: Option<Vec<NotificationConfig>>,
+    /// Tasks defined inline, merged with the task files in `.marty/tasks/`
+    pub tasks: Option<Vec<TaskConfig>>,
+    /// Project discovery settings
+    pub discovery: Option<DiscoveryConfig>,
+    /// Task planning settings
+    pub planning: Option<PlanningConfig>,
+    /// Known dependency cycles that only warn instead of failing planning, each listed by
+    /// its member projects. A cycle must have exactly the listed members, so new cycles,
+    /// and projects joining a listed one, still fail.
+    pub allowed_cycles: Option<Vec<Vec<String>>>,
+    /// Shell that runs string commands locally (defaults to `cmd` on Windows, `sh` elsewhere)
+    pub shell: Option<Shell>,
+    /// Saving task output to `.marty/logs`
+    pub logs: Option<LogsConfig>,
+    /// Named subsets of the workspace selected with `--view`: globs of the project
+    /// directories, relative to the workspace root, that belong to each view
+    pub views: Option<BTreeMap<String, Vec<String>>>,
+    /// Verification of downloaded plugins against their publishers' signatures
+    pub plugin_signing: Option<PluginSigningConfig>,
+    /// URL of the plugin registry index used by `marty plugin search` and `marty plugin add`
+    /// (defaults to the index of the plugins maintained in the Marty repository)
+    pub plugin_registry: Option<String>,
+    /// Limits on the downloaded plugins kept in `.marty/cache/plugins`
+    pub plugin_cache: Option<PluginCacheConfig>,
+    /// Where the affected planner gets the changed files from (defaults to git)
+    pub change_detection: Option<ChangeDetection>,
+}
+
+/// Limits on the plugin cache. Only entries workspace.yml no longer refers to, such as
+/// older versions of a plugin, are ever removed to stay within them.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PluginCacheConfig {
+    /// Total size in bytes of the cached plugins past which the least recently used
+    /// unreferenced entries are removed
+    pub max_size: Option<u64>,
+    /// Days after which unreferenced entries that haven't been used since are removed
+    pub max_age_days: Option<u64>,
+}
+
+/// Minisign verification of downloaded plugins. The signature is expected next to the
+/// release asset, at its URL with `.minisig` appended.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PluginSigningConfig {
+    /// Minisign public key (the base64 line of the publisher's `.pub` file) for downloaded
+    /// plugins that don't set their own `publicKey`
+    pub public_key: Option<String>,
+    /// Refuse downloaded plugins that have no public key. Plugins with a key always need a
+    /// valid signature.
+    pub require_signed: Option<bool>,
+}
+
+/// Shell used for string commands on the local machine
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Sh,
+    Bash,
+    Cmd,
+    /// Windows PowerShell
+    Powershell,
+    /// PowerShell 7+
+    Pwsh,
+}
+
+impl Default for Shell {
+    /// The shell every installation of the current platform has
+    fn default() -> Self {
+        if cfg!(windows) {
+            Shell::Cmd
+        } else {
+            Shell::Sh
+        }
+    }
+}
+
+/// How the files changed since a base are found
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeDetection {
+    /// Diff the working tree against a git ref
+    #[default]
+    Git,
+    /// Ask `hg status` for the changes since a Mercurial revision
+    Mercurial,
+    /// Compare file hashes with a snapshot taken by `marty snapshot`
+    Snapshot,
+    /// Read the changed files from stdin, one per line
+    Stdin,
+}
+
+impl ChangeDetection {
+    pub const ALL: [Self; 4] = [Self::Git, Self::Mercurial, Self::Snapshot, Self::Stdin];
+
+    /// The lowercase name used in workspace.yml
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Git => "git",
+            Self::Mercurial => "mercurial",
+            Self::Snapshot => "snapshot",
+            Self::Stdin => "stdin",
+        }
+    }
+}
+
+impl std::str::FromStr for ChangeDetection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown change detection '{}', expected one of: git, mercurial, snapshot, stdin",
+                    s
+                )
+            })
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    /// Reuse the projects and dependency graph found by the previous invocation while the
+    /// configuration, plugins and scanned files are unchanged
+    pub cache: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PlanningConfig {
+    /// Read and parse the projects' marty.yml files on several threads, which speeds up
+    /// planning in workspaces with thousands of projects
+    pub parallel_config_loading: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LogsConfig {
+    /// Save the output of every project's task to `.marty/logs/<project>/<task>-<timestamp>.log`
+    pub enabled: Option<bool>,
+    /// Logs kept per project and task (defaults to 10)
+    pub keep: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase", deny_unknown_fields)]
+pub enum NotificationConfig {
+    /// Post a message to a Slack incoming webhook
+    Slack {
+        /// Webhook URL. `$VAR` and `${VAR}` are read from the environment.
+        url: String,
+        on: Option<NotifyOn>,
+    },
+    /// POST the run summary as JSON
+    Webhook {
+        /// Endpoint URL. `$VAR` and `${VAR}` are read from the environment.
+        url: String,
+        /// Extra request headers, with values expanded like `url`
+        headers: Option<BTreeMap<String, String>>,
+        on: Option<NotifyOn>,
+    },
+}
+
+/// Which run outcomes trigger a notification
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum NotifyOn {
+    #[default]
+    Always,
+    Success,
+    Failure,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BootstrapConfig {
+    /// Name of the per-project setup task (defaults to "setup")
+    pub task: Option<String>,
+    /// Shell commands run once in the workspace root before any project is set up
+    pub before: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Skip tasks whose project files and definition are unchanged since their last successful run
+    pub enabled: Option<bool>,
+    /// Dimensions used to separate cache entries into namespaces
+    pub namespace: Option<CacheNamespaceConfig>,
+    /// Remote cache that task results are uploaded to after successful runs
+    pub remote: Option<RemoteCacheConfig>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RemoteCacheConfig {
+    /// Base URL of an HTTP remote cache. Entries are read with `GET {url}/{namespace}/{key}`
+    /// and uploaded with `PUT` to the same URL. Captured outputs are stored as a tar archive
+    /// at `{key}.outputs`.
+    pub url: Option<String>,
+    /// S3 (or S3-compatible) bucket used instead of an HTTP cache
+    pub s3: Option<S3CacheConfig>,
+    pub enabled: Option<bool>,
+    /// Upload results after successful runs (defaults to true). Turn off for machines that
+    /// should only read from the shared cache.
+    pub upload: Option<bool>,
+    /// Maximum number of uploads in flight at once (defaults to 4)
+    pub upload_concurrency: Option<usize>,
+    /// Artifacts larger than this many bytes are not uploaded
+    pub max_artifact_size: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct S3CacheConfig {
+    pub bucket: String,
+    pub region: String,
+    /// Key prefix for all entries (e.g. "marty/")
+    pub prefix: Option<String>,
+    /// Custom endpoint for S3-compatible stores such as MinIO or R2. Requests use path-style
+    /// addressing: `{endpoint}/{bucket}/{prefix}{namespace}/{key}`.
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CacheNamespaceConfig {
+    /// Keep a separate namespace per git branch
+    pub branch: Option<bool>,
+    /// Glob patterns of branches that share a namespace (e.g. "release/*").
+    /// Branches not matching any pattern get their own namespace when `branch` is enabled.
+    pub branch_groups: Option<Vec<String>>,
+    /// Build profile name (e.g. "ci", "release"). Can be overridden with `MARTY_CACHE_PROFILE`.
+    pub profile: Option<String>,
+    /// Commands whose output identifies a toolchain version (e.g. "rustc --version")
+    pub toolchains: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema, Clone)]
@@ -29,10 +277,35 @@ pub struct PluginConfig {
     pub url: Option<String>,
     /// Local path to plugin binary
     pub path: Option<String>,
+    /// Minisign public key of the plugin's publisher, used instead of
+    /// `pluginSigning.publicKey`
+    pub public_key: Option<String>,
+    /// Let a WebAssembly plugin write files in the workspace; it may only read them otherwise
+    pub writable: Option<bool>,
     pub enabled: Option<bool>,
     pub options: Option<serde_json::Value>,
 }
 
+impl PluginConfig {
+    /// Human-readable description of where the plugin comes from, used in diagnostics
+    pub fn source_description(&self) -> String {
+        if let (Some(repo), Some(version)) = (&self.github_repo, &self.version) {
+            match &self.plugin {
+                Some(plugin) => format!("githubRepo {} (plugin {}) v{}", repo, plugin, version),
+                None => format!("githubRepo {} v{}", repo, version),
+            }
+        } else if let (Some("builtin"), Some(plugin)) = (self.path.as_deref(), &self.plugin) {
+            format!("builtin {}", plugin)
+        } else if let Some(url) = &self.url {
+            format!("url {}", url)
+        } else if let Some(path) = &self.path {
+            format!("path {}", path)
+        } else {
+            "empty plugin entry".to_string()
+        }
+    }
+}
+
 pub fn parse_workspace_config(yaml_str: &str) -> MartyResult<WorkspaceConfig> {
     let config: WorkspaceConfig = serde_yaml::from_str(yaml_str)?;
     Ok(config)
@@ -0,0 +1,329 @@
+//! Suggested dependencies to remove to break dependency cycles
+//!
+//! A dependency cycle is reported as the strongly connected group of projects it spans,
+//! which tells what is tangled but not where to cut. For each group, Marty enumerates the
+//! elementary cycles through it and searches for the fewest dependencies that appear in
+//! all of them (a minimum feedback edge set). Each suggestion names the plugin that reported
+//! the dependency and how many of the group's cycles go through it, so the usual fix —
+//! moving what `project` uses from `dependency` into a project both can depend on — can
+//! start where it pays off most.
+//!
+//! Groups with a very large number of cycles are searched with a budget; past it the
+//! suggestion still breaks every cycle but may name more dependencies than necessary.
+
+use std::collections::BTreeSet;
+
+use crate::workspace::{dependency_source, ProjectId, Workspace};
+
+/// Most elementary cycles enumerated per round of the search
+const MAX_CYCLES: usize = 10_000;
+
+/// Most search steps spent looking for a minimum set before settling for a greedy one
+const SEARCH_BUDGET: usize = 100_000;
+
+/// A dependency whose removal breaks dependency cycles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleBreak {
+    pub project: String,
+    pub dependency: String,
+    /// Plugin that reported the dependency
+    pub source: Option<String>,
+    /// Number of the group's elementary cycles that go through the dependency
+    pub cycles: usize,
+}
+
+/// The dependencies to remove to make one group of cyclic projects acyclic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleBreakPlan {
+    /// Projects in the group, as in [`Workspace::dependency_cycles`] and
+    /// [`Workspace::allowed_cycles`]
+    pub projects: Vec<String>,
+    /// Number of elementary cycles through the group
+    pub cycles: usize,
+    /// Dependencies to remove, the ones breaking most cycles first
+    pub breaks: Vec<CycleBreak>,
+}
+
+/// Suggest how to break each of the workspace's dependency cycles, allowed ones included
+pub fn suggest_cycle_breaks(workspace: &Workspace) -> Vec<CycleBreakPlan> {
+    let Some(graph) = &workspace.dep_graph else {
+        return Vec::new();
+    };
+
+    workspace
+        .dependency_cycles
+        .iter()
+        .chain(&workspace.allowed_cycles)
+        .map(|projects| {
+            let ids: Vec<ProjectId> = projects
+                .iter()
+                .filter_map(|name| workspace.project_id(name))
+                .collect();
+            // Dependencies between the group's projects, by position in `projects`
+            let edges: Vec<(usize, usize)> = ids
+                .iter()
+                .enumerate()
+                .flat_map(|(from, id)| {
+                    let mut targets: Vec<usize> = graph
+                        .neighbors(id.node())
+                        .filter_map(|node| ids.iter().position(|id| id.node() == node))
+                        .collect();
+                    targets.sort_unstable();
+                    targets.dedup();
+                    targets.into_iter().map(move |to| (from, to))
+                })
+                .collect();
+
+            let (cycles, removed) = feedback_edges(projects.len(), &edges);
+            let mut breaks: Vec<CycleBreak> = removed
+                .into_iter()
+                .map(|(edge, cycles)| {
+                    let (from, to) = edges[edge];
+                    CycleBreak {
+                        project: projects[from].clone(),
+                        dependency: projects[to].clone(),
+                        source: dependency_source(workspace, &projects[from], &projects[to])
+                            .map(str::to_string),
+                        cycles,
+                    }
+                })
+                .collect();
+            breaks.sort_by(|a, b| {
+                b.cycles
+                    .cmp(&a.cycles)
+                    .then_with(|| (&a.project, &a.dependency).cmp(&(&b.project, &b.dependency)))
+            });
+
+            CycleBreakPlan {
+                projects: projects.clone(),
+                cycles,
+                breaks,
+            }
+        })
+        .collect()
+}
+
+/// Find few edges whose removal leaves the graph of `nodes` and `edges` acyclic. Returns the
+/// number of elementary cycles found, and each chosen edge with the number of cycles through
+/// it.
+fn feedback_edges(nodes: usize, edges: &[(usize, usize)]) -> (usize, Vec<(usize, usize)>) {
+    let mut removed: Vec<(usize, usize)> = Vec::new();
+    let mut total = 0;
+    loop {
+        let active: Vec<bool> = (0..edges.len())
+            .map(|edge| !removed.iter().any(|(e, _)| *e == edge))
+            .collect();
+        let cycles = elementary_cycles(nodes, edges, &active);
+        if cycles.is_empty() {
+            return (total, removed);
+        }
+        if removed.is_empty() {
+            total = cycles.len();
+        }
+
+        let chosen = minimum_hitting_set(&cycles).unwrap_or_else(|| greedy_hitting_set(&cycles));
+        removed.extend(chosen.into_iter().map(|edge| {
+            let through = cycles.iter().filter(|cycle| cycle.contains(&edge)).count();
+            (edge, through)
+        }));
+    }
+}
+
+/// Elementary cycles through the active edges, as edge lists, up to [`MAX_CYCLES`]. Each
+/// cycle is found once, from its lowest node.
+fn elementary_cycles(nodes: usize, edges: &[(usize, usize)], active: &[bool]) -> Vec<Vec<usize>> {
+    fn visit(
+        start: usize,
+        node: usize,
+        edges: &[(usize, usize)],
+        active: &[bool],
+        on_path: &mut [bool],
+        path: &mut Vec<usize>,
+        cycles: &mut Vec<Vec<usize>>,
+    ) {
+        for (edge, &(from, to)) in edges.iter().enumerate() {
+            if cycles.len() >= MAX_CYCLES {
+                return;
+            }
+            if from != node || !active[edge] || to < start {
+                continue;
+            }
+            path.push(edge);
+            if to == start {
+                cycles.push(path.clone());
+            } else if !on_path[to] {
+                on_path[to] = true;
+                visit(start, to, edges, active, on_path, path, cycles);
+                on_path[to] = false;
+            }
+            path.pop();
+        }
+    }
+
+    let mut cycles = Vec::new();
+    let mut on_path = vec![false; nodes];
+    for start in 0..nodes {
+        on_path[start] = true;
+        visit(
+            start,
+            start,
+            edges,
+            active,
+            &mut on_path,
+            &mut Vec::new(),
+            &mut cycles,
+        );
+        on_path[start] = false;
+    }
+    cycles
+}
+
+/// The fewest edges meeting every cycle, or `None` if the search ran out of budget
+fn minimum_hitting_set(cycles: &[Vec<usize>]) -> Option<Vec<usize>> {
+    fn search(
+        cycles: &[Vec<usize>],
+        limit: usize,
+        chosen: &mut Vec<usize>,
+        budget: &mut usize,
+    ) -> bool {
+        if *budget == 0 {
+            return false;
+        }
+        *budget -= 1;
+        let Some(uncovered) = cycles
+            .iter()
+            .find(|cycle| !cycle.iter().any(|edge| chosen.contains(edge)))
+        else {
+            return true;
+        };
+        if chosen.len() == limit {
+            return false;
+        }
+        for &edge in uncovered {
+            chosen.push(edge);
+            if search(cycles, limit, chosen, budget) {
+                return true;
+            }
+            chosen.pop();
+        }
+        false
+    }
+
+    let edges: BTreeSet<usize> = cycles.iter().flatten().copied().collect();
+    let mut budget = SEARCH_BUDGET;
+    for limit in 1..=edges.len() {
+        let mut chosen = Vec::new();
+        if search(cycles, limit, &mut chosen, &mut budget) {
+            return Some(chosen);
+        }
+        if budget == 0 {
+            return None;
+        }
+    }
+    None
+}
+
+/// Edges meeting every cycle, picking the edge on most remaining cycles each time
+fn greedy_hitting_set(cycles: &[Vec<usize>]) -> Vec<usize> {
+    let mut remaining: Vec<&Vec<usize>> = cycles.iter().collect();
+    let mut chosen = Vec::new();
+    while !remaining.is_empty() {
+        let edges: BTreeSet<usize> = remaining.iter().copied().flatten().copied().collect();
+        let best = edges
+            .into_iter()
+            .max_by_key(|edge| {
+                let count = remaining
+                    .iter()
+                    .filter(|cycle| cycle.contains(edge))
+                    .count();
+                (count, std::cmp::Reverse(*edge))
+            })
+            .expect("remaining cycles have edges");
+        remaining.retain(|cycle| !cycle.contains(&best));
+        chosen.push(best);
+    }
+    chosen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::{build_dependency_graph, InferredProject, Project, ProjectIndex};
+    use std::path::PathBuf;
+
+    fn workspace(dependencies: &[(&str, &[&str])]) -> Workspace {
+        let root = PathBuf::from("/ws");
+        let inferred_projects = dependencies
+            .iter()
+            .map(|(name, deps)| InferredProject {
+                name: name.to_string(),
+                project_dir: root.join(name),
+                discovered_by: "cargo".to_string(),
+                workspace_dependencies: deps.iter().map(|d| d.to_string()).collect(),
+                kind: None,
+            })
+            .collect();
+        let projects = dependencies
+            .iter()
+            .map(|(name, _)| Project {
+                name: name.to_string(),
+                project_dir: root.join(name),
+                file_path: None,
+                dependencies: Vec::new(),
+            })
+            .collect();
+        let mut workspace = Workspace {
+            root,
+            projects,
+            inferred_projects,
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        build_dependency_graph(&mut workspace).unwrap();
+        workspace
+    }
+
+    fn summary(plan: &CycleBreakPlan) -> Vec<(&str, &str, usize)> {
+        plan.breaks
+            .iter()
+            .map(|b| (b.project.as_str(), b.dependency.as_str(), b.cycles))
+            .collect()
+    }
+
+    #[test]
+    fn test_suggests_the_dependency_shared_by_most_cycles() {
+        let workspace = workspace(&[
+            ("api", &["types", "util"]),
+            ("types", &["api", "db", "util"]),
+            ("db", &["api"]),
+            ("util", &["api"]),
+            ("cli", &["api"]),
+            ("codegen", &["codegen"]),
+        ]);
+        let plans = suggest_cycle_breaks(&workspace);
+
+        assert_eq!(plans.len(), 2);
+        assert_eq!(plans[0].projects, vec!["api", "db", "types", "util"]);
+        // api -> types sits on three of the four cycles; only api <-> util is left after it
+        assert_eq!(plans[0].cycles, 4);
+        assert_eq!(
+            summary(&plans[0]),
+            vec![("api", "types", 3), ("api", "util", 1)]
+        );
+        assert_eq!(plans[0].breaks[0].source.as_deref(), Some("cargo"));
+        assert_eq!(plans[1].cycles, 1);
+        assert_eq!(summary(&plans[1]), vec![("codegen", "codegen", 1)]);
+    }
+
+    #[test]
+    fn test_greedy_set_breaks_every_cycle() {
+        let cycles = vec![vec![0, 1], vec![1, 2], vec![3, 4], vec![2, 4]];
+        let chosen = greedy_hitting_set(&cycles);
+        assert!(cycles
+            .iter()
+            .all(|cycle| cycle.iter().any(|edge| chosen.contains(edge))));
+        assert_eq!(minimum_hitting_set(&cycles).map(|set| set.len()), Some(2));
+    }
+}
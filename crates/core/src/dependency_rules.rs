@@ -0,0 +1,199 @@
+//! Per-project dependency rules
+//!
+//! Projects can restrict which other projects they may depend on with
+//! `allowedDependencies` and `deniedDependencies` glob patterns in their marty.yml.
+//! This supports gradual migrations where new code must not depend on legacy projects.
+//! A `kind:<kind>` pattern matches projects of that kind instead of a name, e.g.
+//! `kind:application` to keep libraries from depending on applications.
+
+use globset::{Glob, GlobMatcher};
+use marty_plugin_protocol::ProjectKind;
+
+use crate::configs::project::ProjectConfig;
+use crate::types::{MartyError, MartyResult};
+
+/// Why a dependency violates a project's rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The dependency matches a `deniedDependencies` pattern
+    Denied(String),
+    /// The project has `allowedDependencies` and the dependency matches none of them
+    NotAllowed,
+}
+
+/// A dependency edge that breaks a project's dependency rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyViolation {
+    pub project: String,
+    pub dependency: String,
+    pub kind: ViolationKind,
+}
+
+/// Compiled dependency rules for a single project
+pub struct DependencyRules {
+    allowed: Option<Vec<Pattern>>,
+    denied: Vec<(String, Pattern)>,
+}
+
+enum Pattern {
+    Name(GlobMatcher),
+    Kind(ProjectKind),
+}
+
+impl Pattern {
+    fn matches(&self, dependency: &str, kind: Option<ProjectKind>) -> bool {
+        match self {
+            Pattern::Name(glob) => glob.is_match(dependency),
+            Pattern::Kind(expected) => kind == Some(*expected),
+        }
+    }
+}
+
+impl DependencyRules {
+    /// Build the rules declared in a project config, or `None` if it declares none
+    pub fn from_config(config: &ProjectConfig) -> MartyResult<Option<Self>> {
+        if config.allowed_dependencies.is_none() && config.denied_dependencies.is_none() {
+            return Ok(None);
+        }
+
+        let allowed = config
+            .allowed_dependencies
+            .as_ref()
+            .map(|patterns| patterns.iter().map(|p| compile(p)).collect())
+            .transpose()?;
+
+        let denied = config
+            .denied_dependencies
+            .iter()
+            .flatten()
+            .map(|pattern| Ok((pattern.clone(), compile(pattern)?)))
+            .collect::<MartyResult<Vec<_>>>()?;
+
+        Ok(Some(Self { allowed, denied }))
+    }
+
+    /// Check a project's direct dependencies against the rules, looking up the kind of each
+    /// dependency with `kind_of`
+    pub fn check(
+        &self,
+        project: &str,
+        dependencies: &[String],
+        kind_of: impl Fn(&str) -> Option<ProjectKind>,
+    ) -> Vec<DependencyViolation> {
+        dependencies
+            .iter()
+            .filter_map(|dependency| {
+                let dependency_kind = kind_of(dependency);
+                let denied_by = self
+                    .denied
+                    .iter()
+                    .find(|(_, pattern)| pattern.matches(dependency, dependency_kind));
+
+                let kind = if let Some((pattern, _)) = denied_by {
+                    ViolationKind::Denied(pattern.clone())
+                } else if self.allowed.as_ref().is_some_and(|allowed| {
+                    !allowed
+                        .iter()
+                        .any(|pattern| pattern.matches(dependency, dependency_kind))
+                }) {
+                    ViolationKind::NotAllowed
+                } else {
+                    return None;
+                };
+
+                Some(DependencyViolation {
+                    project: project.to_string(),
+                    dependency: dependency.clone(),
+                    kind,
+                })
+            })
+            .collect()
+    }
+}
+
+fn compile(pattern: &str) -> MartyResult<Pattern> {
+    let invalid =
+        |e: String| MartyError::Config(format!("Invalid dependency pattern '{}': {}", pattern, e));
+    match pattern.strip_prefix("kind:") {
+        Some(kind) => kind.parse().map(Pattern::Kind).map_err(invalid),
+        None => Glob::new(pattern)
+            .map(|glob| Pattern::Name(glob.compile_matcher()))
+            .map_err(|e| invalid(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::project::parse_project_config;
+
+    fn deps(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_no_rules() {
+        let config = parse_project_config("name: app\n").unwrap();
+        assert!(DependencyRules::from_config(&config).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_denied_takes_precedence_over_allowed() {
+        let config = parse_project_config(
+            "allowedDependencies: [\"lib-*\"]\ndeniedDependencies: [\"lib-legacy*\"]\n",
+        )
+        .unwrap();
+        let rules = DependencyRules::from_config(&config).unwrap().unwrap();
+
+        let violations = rules.check(
+            "app",
+            &deps(&["lib-ui", "lib-legacy-auth", "tools"]),
+            |_| None,
+        );
+        assert_eq!(
+            violations,
+            vec![
+                DependencyViolation {
+                    project: "app".to_string(),
+                    dependency: "lib-legacy-auth".to_string(),
+                    kind: ViolationKind::Denied("lib-legacy*".to_string()),
+                },
+                DependencyViolation {
+                    project: "app".to_string(),
+                    dependency: "tools".to_string(),
+                    kind: ViolationKind::NotAllowed,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kind_patterns() {
+        let config = parse_project_config(
+            "allowedDependencies: [\"kind:library\", \"tools\"]\ndeniedDependencies: [\"kind:application\"]\n",
+        )
+        .unwrap();
+        let rules = DependencyRules::from_config(&config).unwrap().unwrap();
+
+        let kind_of = |name: &str| match name {
+            "ui" => Some(ProjectKind::Library),
+            "web" => Some(ProjectKind::Application),
+            _ => None,
+        };
+        let violations = rules.check("lib", &deps(&["ui", "web", "tools", "gen"]), kind_of);
+        let kinds: Vec<_> = violations
+            .iter()
+            .map(|v| (v.dependency.as_str(), v.kind.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("web", ViolationKind::Denied("kind:application".to_string())),
+                ("gen", ViolationKind::NotAllowed),
+            ]
+        );
+
+        let config = parse_project_config("deniedDependencies: [\"kind:service\"]\n").unwrap();
+        assert!(DependencyRules::from_config(&config).is_err());
+    }
+}
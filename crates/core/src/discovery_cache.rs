@@ -0,0 +1,292 @@
+//! Persisted discovery results
+//!
+//! Scanning a large workspace and asking every plugin about every matching file dominates
+//! the start-up time of each command. When enabled with `discovery.cache` in workspace.yml,
+//! the discovered projects, the dependency graph and the plugins' default tasks are stored in a compact binary file,
+//! `.marty/cache/discovery/graph.bin`, together with the modification time and size of
+//! every directory scanned and every file offered to a plugin.
+//!
+//! A later invocation reuses the stored graph when the configuration and plugins are the
+//! same and none of the recorded paths changed. Adding or removing a file changes the
+//! modification time of its directory, so new projects are still picked up. Files a plugin
+//! reads on its own, without being offered them by the scan, are not tracked.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use marty_plugin_protocol::SuggestedTask;
+use petgraph::graph::{DiGraph, NodeIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{MartyError, MartyResult};
+use crate::workspace::{InferredProject, Project, ProjectIndex, Workspace};
+
+/// Bumped whenever the layout of [`Snapshot`] changes
+const FORMAT_VERSION: u32 = 3;
+
+/// Modification time and size of a path when it was scanned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    /// Nanoseconds since the Unix epoch
+    modified: u128,
+    len: u64,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_nanos();
+        Some(Self {
+            modified,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Paths a discovery depended on
+#[derive(Debug, Default)]
+pub struct ScanStamps {
+    stamps: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl ScanStamps {
+    /// Remember the current state of `path`. Missing paths are skipped.
+    pub fn record(&mut self, path: &Path) {
+        if self.stamps.contains_key(path) {
+            return;
+        }
+        if let Some(stamp) = FileStamp::read(path) {
+            self.stamps.insert(path.to_path_buf(), stamp);
+        }
+    }
+}
+
+/// Everything stored in the discovery cache file
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    format: u32,
+    key: String,
+    stamps: Vec<(PathBuf, FileStamp)>,
+    projects: Vec<Project>,
+    inferred_projects: Vec<InferredProject>,
+    /// Dependency graph nodes, in index order
+    nodes: Vec<String>,
+    /// Dependency graph edges as (from, to) node indices
+    edges: Vec<(u32, u32)>,
+    dependency_cycles: Vec<Vec<String>>,
+    /// Default tasks by plugin key
+    plugin_tasks: BTreeMap<String, Vec<SuggestedTask>>,
+}
+
+/// Discovery results of a workspace, valid for one configuration
+pub struct DiscoveryCache {
+    path: PathBuf,
+    /// Identifies the configuration and plugins the results were discovered with
+    key: String,
+}
+
+impl DiscoveryCache {
+    pub fn new(workspace_root: &Path, key: String) -> Self {
+        Self {
+            path: Self::file(workspace_root),
+            key,
+        }
+    }
+
+    /// File holding the persisted discovery results
+    pub fn file(workspace_root: &Path) -> PathBuf {
+        workspace_root
+            .join(".marty")
+            .join("cache")
+            .join("discovery")
+            .join("graph.bin")
+    }
+
+    /// The stored workspace and plugin default tasks, if they were discovered with the same
+    /// key and none of the paths they depended on changed since
+    pub fn load(
+        &self,
+        workspace_root: &Path,
+    ) -> Option<(Workspace, BTreeMap<String, Vec<SuggestedTask>>)> {
+        let bytes = std::fs::read(&self.path).ok()?;
+        let snapshot: Snapshot = bincode::deserialize(&bytes).ok()?;
+        if snapshot.format != FORMAT_VERSION || snapshot.key != self.key {
+            return None;
+        }
+        if snapshot
+            .stamps
+            .iter()
+            .any(|(path, stamp)| FileStamp::read(path) != Some(*stamp))
+        {
+            return None;
+        }
+
+        let mut graph =
+            DiGraph::<String, ()>::with_capacity(snapshot.nodes.len(), snapshot.edges.len());
+        for node in snapshot.nodes {
+            graph.add_node(node);
+        }
+        let node_count = graph.node_count();
+        for (from, to) in snapshot.edges {
+            if from as usize >= node_count || to as usize >= node_count {
+                return None;
+            }
+            graph.add_edge(
+                NodeIndex::new(from as usize),
+                NodeIndex::new(to as usize),
+                (),
+            );
+        }
+
+        let mut workspace = Workspace {
+            root: workspace_root.to_path_buf(),
+            projects: snapshot.projects,
+            inferred_projects: snapshot.inferred_projects,
+            dep_graph: Some(graph),
+            dependency_cycles: snapshot.dependency_cycles,
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        workspace.reindex();
+        Some((workspace, snapshot.plugin_tasks))
+    }
+
+    /// Persist a freshly discovered workspace and the plugins' default tasks along with the
+    /// paths they depended on
+    pub fn store(
+        &self,
+        workspace: &Workspace,
+        plugin_tasks: &BTreeMap<String, Vec<SuggestedTask>>,
+        stamps: ScanStamps,
+    ) -> MartyResult<()> {
+        let (nodes, edges) = match &workspace.dep_graph {
+            Some(graph) => (
+                graph.node_weights().cloned().collect(),
+                graph
+                    .raw_edges()
+                    .iter()
+                    .map(|edge| (edge.source().index() as u32, edge.target().index() as u32))
+                    .collect(),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        let snapshot = Snapshot {
+            format: FORMAT_VERSION,
+            key: self.key.clone(),
+            stamps: stamps.stamps.into_iter().collect(),
+            projects: workspace.projects.clone(),
+            inferred_projects: workspace.inferred_projects.clone(),
+            nodes,
+            edges,
+            dependency_cycles: workspace.dependency_cycles.clone(),
+            plugin_tasks: plugin_tasks.clone(),
+        };
+        let bytes = bincode::serialize(&snapshot).map_err(|e| {
+            MartyError::Workspace(format!("Failed to serialize discovery cache: {}", e))
+        })?;
+
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        // Write to a temporary file first so concurrent invocations never read a partial file
+        let temp = tempfile::NamedTempFile::new_in(dir)?;
+        std::fs::write(temp.path(), bytes)?;
+        temp.persist(&self.path)
+            .map_err(|e| MartyError::Io(e.error))?;
+        Ok(())
+    }
+
+    /// Remove the persisted discovery results
+    pub fn clear(workspace_root: &Path) -> MartyResult<()> {
+        let path = Self::file(workspace_root);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::build_dependency_graph;
+
+    fn workspace(root: &Path) -> Workspace {
+        let project = |name: &str, deps: &[&str]| InferredProject {
+            name: name.to_string(),
+            project_dir: root.join(name),
+            discovered_by: "test".to_string(),
+            workspace_dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            kind: None,
+        };
+        let mut workspace = Workspace {
+            root: root.to_path_buf(),
+            projects: ["app", "lib"]
+                .iter()
+                .map(|name| Project {
+                    name: name.to_string(),
+                    project_dir: root.join(name),
+                    file_path: None,
+                    dependencies: Vec::new(),
+                })
+                .collect(),
+            inferred_projects: vec![project("app", &["lib"]), project("lib", &[])],
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        build_dependency_graph(&mut workspace).unwrap();
+        workspace
+    }
+
+    #[test]
+    fn test_round_trip_and_invalidation() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        std::fs::write(root.join("app").join("package.json"), "{}").unwrap();
+
+        let mut stamps = ScanStamps::default();
+        stamps.record(&root.join("app"));
+        stamps.record(&root.join("app").join("package.json"));
+        let cache = DiscoveryCache::new(root, "key".to_string());
+        let plugin_tasks = BTreeMap::from([(
+            "test".to_string(),
+            vec![SuggestedTask {
+                name: "build".to_string(),
+                command: "make".to_string(),
+                description: None,
+                dependencies: vec![],
+            }],
+        )]);
+        cache
+            .store(&workspace(root), &plugin_tasks, stamps)
+            .unwrap();
+
+        let (loaded, loaded_tasks) = cache.load(root).unwrap();
+        assert_eq!(loaded_tasks, plugin_tasks);
+        assert_eq!(loaded.project("lib").unwrap().name, "lib");
+        let graph = loaded.dep_graph.unwrap();
+        assert_eq!(loaded.inferred_projects.len(), 2);
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+
+        // Another configuration never sees these results
+        assert!(DiscoveryCache::new(root, "other".to_string())
+            .load(root)
+            .is_none());
+
+        // Editing a scanned file invalidates them
+        std::fs::write(root.join("app").join("package.json"), "{\"name\":\"app\"}").unwrap();
+        assert!(cache.load(root).is_none());
+
+        DiscoveryCache::clear(root).unwrap();
+        assert!(!DiscoveryCache::file(root).exists());
+    }
+}
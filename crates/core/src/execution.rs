@@ -3,10 +3,25 @@
 //! This module handles the actual execution of tasks including command execution,
 //! dependency management, and result reporting.
 
+pub mod cache;
 pub mod command;
 pub mod dependencies;
+pub mod executor;
+pub mod notify;
+pub mod remote_cache;
+pub mod resources;
 pub mod runner;
+pub mod upload;
 
+pub use cache::{CacheNamespace, TaskCache};
 pub use command::CommandExecutor;
 pub use dependencies::group_by_dependency_levels;
-pub use runner::{TaskRunner, TaskRunnerConfig};
+pub use executor::{ExecutionHook, Executor};
+pub use notify::WebhookNotifier;
+pub use remote_cache::RemoteCache;
+pub use resources::{ResourceMeter, ResourceUsage};
+pub use runner::{
+    ProjectRunReport, ProjectRunStatus, RunEvent, RunHook, RunSummary, TaskPlugins, TaskRunner,
+    TaskRunnerConfig,
+};
+pub use upload::{UploadQueue, UploadReport};
@@ -0,0 +1,1105 @@
+//! Local task result cache
+//!
+//! When caching is enabled, the runner fingerprints a project's files, and those of the
+//! workspace projects it depends on, together with the task definition before running it.
+//! If a successful run with the same fingerprint has been recorded in the current
+//! namespace, the task is skipped.
+//!
+//! Entries are separated into namespaces derived from configurable dimensions (branch,
+//! profile, toolchain versions) so that switching branches or toolchains can never produce
+//! a stale-but-matching hit. Entries live under `.marty/cache/tasks/<namespace>/`.
+//!
+//! Tasks can narrow the fingerprint to their `inputs` globs. Files matching their `outputs`
+//! globs are copied next to the entry (`<key>.outputs/`) and restored into the project on
+//! a hit. A remote cache stores them as a tar archive under `<key>.outputs`, next to the
+//! entry metadata.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::configs::tasks::TaskConfig;
+use crate::configs::workspace::{CacheNamespaceConfig, Shell};
+use crate::execution::executor::local::shell_command;
+use crate::execution::remote_cache::RemoteCache;
+use crate::execution::upload::{CacheArtifact, UploadQueue};
+use crate::git;
+use crate::types::{MartyError, MartyResult};
+
+/// Directory names never included in a project fingerprint
+const IGNORED_DIRS: &[&str] = &[".git", ".marty", "target", "node_modules"];
+
+/// Environment variable overriding the configured cache profile
+pub const PROFILE_ENV_VAR: &str = "MARTY_CACHE_PROFILE";
+
+/// The set of dimensions that scope cache entries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheNamespace {
+    pub dimensions: BTreeMap<String, String>,
+}
+
+impl CacheNamespace {
+    /// Resolve the namespace for the current environment from configuration
+    pub fn resolve(
+        workspace_root: &Path,
+        config: &CacheNamespaceConfig,
+        shell: Shell,
+    ) -> MartyResult<Self> {
+        let mut dimensions = BTreeMap::new();
+
+        let groups = config.branch_groups.as_deref().unwrap_or_default();
+        if config.branch.unwrap_or(false) || !groups.is_empty() {
+            let branch =
+                git::current_branch(workspace_root).unwrap_or_else(|_| "unknown".to_string());
+            let value = match Self::match_branch_group(&branch, groups)? {
+                Some(group) => group,
+                None if config.branch.unwrap_or(false) => branch,
+                None => "other".to_string(),
+            };
+            dimensions.insert("branch".to_string(), value);
+        }
+
+        let profile = std::env::var(PROFILE_ENV_VAR)
+            .ok()
+            .filter(|p| !p.is_empty())
+            .or_else(|| config.profile.clone());
+        if let Some(profile) = profile {
+            dimensions.insert("profile".to_string(), profile);
+        }
+
+        for command in config.toolchains.iter().flatten() {
+            dimensions.insert(
+                format!("toolchain:{}", command),
+                run_probe(workspace_root, shell, command),
+            );
+        }
+
+        Ok(Self { dimensions })
+    }
+
+    /// Short stable identifier used as the namespace directory name
+    pub fn id(&self) -> String {
+        if self.dimensions.is_empty() {
+            return "default".to_string();
+        }
+
+        let mut hasher = Sha256::new();
+        for (key, value) in &self.dimensions {
+            hasher.update(key.as_bytes());
+            hasher.update([0]);
+            hasher.update(value.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+
+    fn match_branch_group(branch: &str, groups: &[String]) -> MartyResult<Option<String>> {
+        for pattern in groups {
+            let glob = Glob::new(pattern).map_err(|e| {
+                MartyError::Config(format!("Invalid branch group '{}': {}", pattern, e))
+            })?;
+            if glob.compile_matcher().is_match(branch) {
+                return Ok(Some(pattern.clone()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Run a probe command in `shell` and return its trimmed output, or "unavailable" if it fails
+pub(crate) fn run_probe(workspace_root: &Path, shell: Shell, command: &str) -> String {
+    shell_command(shell, command)
+        .current_dir(workspace_root)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "unavailable".to_string())
+}
+
+/// Metadata stored alongside each cache entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntryMetadata {
+    pub project: String,
+    pub task: String,
+    pub key: String,
+    pub namespace: BTreeMap<String, String>,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Captured output files, relative to the project directory
+    #[serde(default)]
+    pub outputs: Vec<String>,
+}
+
+/// Summary of the entries stored in one namespace
+#[derive(Debug, Clone)]
+pub struct CacheNamespaceStats {
+    pub id: String,
+    pub dimensions: BTreeMap<String, String>,
+    pub entries: usize,
+    pub size_bytes: u64,
+}
+
+/// Task result cache scoped to a single namespace
+pub struct TaskCache {
+    root: PathBuf,
+    workspace_root: PathBuf,
+    namespace: CacheNamespace,
+    remote: Option<Arc<dyn RemoteCache>>,
+    uploads: Option<UploadQueue>,
+    /// Environment variables passed to every command of the run, part of every key
+    extra_env: BTreeMap<String, String>,
+    /// Arguments appended to the run's commands, part of every key
+    extra_args: Vec<String>,
+    /// Shell running `envProbe` commands
+    shell: Shell,
+    /// Output of `envProbe` commands, probed once per run
+    probes: Mutex<HashMap<String, String>>,
+}
+
+impl TaskCache {
+    pub fn new(workspace_root: &Path, namespace: CacheNamespace) -> Self {
+        Self {
+            root: Self::cache_root(workspace_root),
+            workspace_root: workspace_root.to_path_buf(),
+            namespace,
+            remote: None,
+            uploads: None,
+            extra_env: BTreeMap::new(),
+            extra_args: Vec::new(),
+            shell: Shell::default(),
+            probes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run `envProbe` commands in the workspace's configured shell
+    pub fn with_shell(mut self, shell: Shell) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Include environment variables passed to the run's commands in every key
+    pub fn with_extra_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.extra_env = env;
+        self
+    }
+
+    /// Include arguments appended to the run's commands in every key
+    pub fn with_extra_args(mut self, args: Vec<String>) -> Self {
+        self.extra_args = args;
+        self
+    }
+
+    /// Fetch entries missing locally from a remote cache
+    pub fn with_remote(mut self, remote: Arc<dyn RemoteCache>) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// Upload recorded entries to a remote cache in the background
+    pub fn with_uploads(mut self, uploads: UploadQueue) -> Self {
+        self.uploads = Some(uploads);
+        self
+    }
+
+    pub fn uploads(&self) -> Option<&UploadQueue> {
+        self.uploads.as_ref()
+    }
+
+    /// Directory holding all task cache namespaces
+    pub fn cache_root(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".marty").join("cache").join("tasks")
+    }
+
+    pub fn namespace(&self) -> &CacheNamespace {
+        &self.namespace
+    }
+
+    fn namespace_dir(&self) -> PathBuf {
+        self.root.join(self.namespace.id())
+    }
+
+    /// Compute the cache key for running a task on a project.
+    ///
+    /// `dependency_dirs` are the directories of the workspace projects the project depends
+    /// on, directly or not. All their files are part of the key, so changing a library
+    /// invalidates the results of its dependents.
+    pub fn compute_key(
+        &self,
+        project_name: &str,
+        project_dir: &Path,
+        dependency_dirs: &[&Path],
+        task_config: &TaskConfig,
+    ) -> MartyResult<String> {
+        // Documentation does not change what the task does
+        let keyed = TaskConfig {
+            docs: None,
+            ..task_config.clone()
+        };
+        let task_json = serde_json::to_string(&keyed).map_err(|e| {
+            MartyError::Task(format!(
+                "Failed to serialize task '{}': {}",
+                task_config.name, e
+            ))
+        })?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(project_name.as_bytes());
+        hasher.update([0]);
+        hasher.update(task_json.as_bytes());
+        hasher.update([0]);
+        hasher.update(self.namespace.id().as_bytes());
+        hasher.update([0]);
+        for probe in task_config.env_probe.iter().flatten() {
+            hasher.update(probe.as_bytes());
+            hasher.update([0]);
+            hasher.update(self.probe(probe).as_bytes());
+            hasher.update([0]);
+        }
+        for (name, value) in &self.extra_env {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+            hasher.update(value.as_bytes());
+            hasher.update([0]);
+        }
+        for arg in &self.extra_args {
+            hasher.update(arg.as_bytes());
+            hasher.update([0]);
+        }
+        let files = FileFilter {
+            inputs: task_config
+                .inputs
+                .as_deref()
+                .map(|globs| build_glob_set("input", globs))
+                .transpose()?,
+            outputs: build_glob_set("output", task_config.outputs.as_deref().unwrap_or_default())?,
+        };
+        hash_directory(&mut hasher, project_dir, project_dir, &files)?;
+
+        let all_files = FileFilter {
+            inputs: None,
+            outputs: GlobSet::empty(),
+        };
+        for dir in dependency_dirs {
+            let relative = dir.strip_prefix(&self.workspace_root).unwrap_or(dir);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hash_directory(&mut hasher, dir, dir, &all_files)?;
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Output of an environment probe command, run at most once per cache instance
+    fn probe(&self, command: &str) -> String {
+        if let Some(output) = self
+            .probes
+            .lock()
+            .ok()
+            .and_then(|p| p.get(command).cloned())
+        {
+            return output;
+        }
+
+        let output = run_probe(&self.workspace_root, self.shell, command);
+        if let Ok(mut probes) = self.probes.lock() {
+            probes.insert(command.to_string(), output.clone());
+        }
+        output
+    }
+
+    /// Whether a successful run with this key has been recorded
+    pub fn contains(&self, key: &str) -> bool {
+        self.namespace_dir().join(format!("{}.json", key)).exists()
+    }
+
+    /// Try to fetch an entry from the remote cache into the local cache.
+    ///
+    /// Returns whether the entry is now available locally. Remote failures are reported as
+    /// warnings and treated as a miss.
+    pub async fn fetch(&self, key: &str) -> bool {
+        let Some(remote) = &self.remote else {
+            return false;
+        };
+
+        let namespace = self.namespace.id();
+        let body = match remote.get(&namespace, key).await {
+            Ok(Some(body)) => body,
+            Ok(None) => return false,
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to fetch {} from {} remote cache: {}",
+                    key,
+                    remote.name(),
+                    e
+                );
+                return false;
+            }
+        };
+
+        let metadata = match serde_json::from_slice::<CacheEntryMetadata>(&body) {
+            Ok(metadata) if metadata.outputs.iter().all(|o| is_contained(o)) => metadata,
+            _ => {
+                eprintln!(
+                    "Warning: Ignoring malformed entry {} from {} remote cache",
+                    key,
+                    remote.name()
+                );
+                return false;
+            }
+        };
+
+        let outputs_key = format!("{}.outputs", key);
+        let outputs = if metadata.outputs.is_empty() {
+            None
+        } else {
+            match remote.get(&namespace, &outputs_key).await {
+                Ok(Some(archive)) => Some(archive),
+                // Skipped by the upload size limit, or not uploaded yet
+                Ok(None) => return false,
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Failed to fetch {} from {} remote cache: {}",
+                        outputs_key,
+                        remote.name(),
+                        e
+                    );
+                    return false;
+                }
+            }
+        };
+
+        let result = self.ensure_namespace_dir().and_then(|dir| {
+            if let Some(archive) = outputs {
+                let stored = dir.join(&outputs_key);
+                if stored.exists() {
+                    std::fs::remove_dir_all(&stored)?;
+                }
+                tar::Archive::new(archive.as_slice()).unpack(&stored)?;
+            }
+            Ok(std::fs::write(dir.join(format!("{}.json", key)), body)?)
+        });
+        if let Err(e) = result {
+            eprintln!("Warning: Failed to store remote cache entry {}: {}", key, e);
+            return false;
+        }
+        true
+    }
+
+    /// Create the directory for the current namespace, recording its dimensions
+    fn ensure_namespace_dir(&self) -> MartyResult<PathBuf> {
+        let dir = self.namespace_dir();
+        std::fs::create_dir_all(&dir)?;
+
+        let namespace_file = dir.join("namespace.json");
+        if !namespace_file.exists() {
+            std::fs::write(&namespace_file, to_json(&self.namespace)?)?;
+        }
+        Ok(dir)
+    }
+
+    /// Record a successful run, capturing the task's outputs from the project directory,
+    /// and return what was written: the captured outputs, if any, then the entry
+    pub fn record(
+        &self,
+        project_name: &str,
+        project_dir: &Path,
+        task_config: &TaskConfig,
+        key: &str,
+    ) -> MartyResult<Vec<CacheArtifact>> {
+        let dir = self.ensure_namespace_dir()?;
+        let outputs = match task_config.outputs.as_deref() {
+            Some(globs) if !globs.is_empty() => {
+                capture_outputs(project_dir, &dir.join(format!("{}.outputs", key)), globs)?
+            }
+            _ => Vec::new(),
+        };
+
+        let metadata = CacheEntryMetadata {
+            project: project_name.to_string(),
+            task: task_config.name.clone(),
+            key: key.to_string(),
+            namespace: self.namespace.dimensions.clone(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            outputs,
+        };
+        let path = dir.join(format!("{}.json", key));
+        std::fs::write(&path, to_json(&metadata)?)?;
+
+        let mut artifacts = Vec::new();
+        if !metadata.outputs.is_empty() {
+            artifacts.push(CacheArtifact {
+                namespace: self.namespace.id(),
+                key: format!("{}.outputs", key),
+                path: dir.join(format!("{}.outputs", key)),
+            });
+        }
+        artifacts.push(CacheArtifact {
+            namespace: self.namespace.id(),
+            key: key.to_string(),
+            path,
+        });
+        Ok(artifacts)
+    }
+
+    /// Copy the outputs captured with an entry back into the project directory.
+    ///
+    /// Returns false when the entry recorded outputs that are not available locally, e.g.
+    /// because they were deleted, so the task has to run again.
+    pub fn restore(&self, key: &str, project_dir: &Path) -> MartyResult<bool> {
+        let dir = self.namespace_dir();
+        let content = std::fs::read_to_string(dir.join(format!("{}.json", key)))?;
+        let metadata: CacheEntryMetadata = serde_json::from_str(&content)
+            .map_err(|e| MartyError::Task(format!("Failed to read cache entry {}: {}", key, e)))?;
+
+        let stored = dir.join(format!("{}.outputs", key));
+        if metadata
+            .outputs
+            .iter()
+            .any(|output| !is_contained(output) || !stored.join(output).is_file())
+        {
+            return Ok(false);
+        }
+        for output in &metadata.outputs {
+            let target = project_dir.join(output);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(stored.join(output), target)?;
+        }
+        Ok(true)
+    }
+
+    /// Collect statistics for every namespace in the workspace cache
+    pub fn stats(workspace_root: &Path) -> MartyResult<Vec<CacheNamespaceStats>> {
+        let root = Self::cache_root(workspace_root);
+        if !root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut namespaces = Vec::new();
+        for entry in std::fs::read_dir(&root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let dir = entry.path();
+            let dimensions = std::fs::read_to_string(dir.join("namespace.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str::<CacheNamespace>(&content).ok())
+                .map(|ns| ns.dimensions)
+                .unwrap_or_default();
+
+            let mut entries = 0;
+            let mut size_bytes = 0;
+            for file in std::fs::read_dir(&dir)? {
+                let file = file?;
+                if file.file_name() == "namespace.json" {
+                    continue;
+                }
+                if file.file_type()?.is_dir() {
+                    // Captured outputs of an entry
+                    size_bytes += directory_size(&file.path())?;
+                    continue;
+                }
+                entries += 1;
+                size_bytes += file.metadata()?.len();
+            }
+
+            namespaces.push(CacheNamespaceStats {
+                id: entry.file_name().to_string_lossy().to_string(),
+                dimensions,
+                entries,
+                size_bytes,
+            });
+        }
+
+        namespaces.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(namespaces)
+    }
+
+    /// Remove all cached task results
+    pub fn clear(workspace_root: &Path) -> MartyResult<()> {
+        let root = Self::cache_root(workspace_root);
+        if root.exists() {
+            std::fs::remove_dir_all(&root)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a recorded output path stays inside the directory it is joined to: relative,
+/// and without `..` components
+fn is_contained(output: &str) -> bool {
+    Path::new(output)
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> MartyResult<String> {
+    serde_json::to_string_pretty(value)
+        .map_err(|e| MartyError::Task(format!("Failed to serialize cache metadata: {}", e)))
+}
+
+/// Which files of a project are part of its fingerprint
+struct FileFilter {
+    /// Only files matching these globs, when the task declares inputs
+    inputs: Option<GlobSet>,
+    /// Never files matching these globs
+    outputs: GlobSet,
+}
+
+impl FileFilter {
+    fn includes(&self, relative: &Path) -> bool {
+        self.inputs
+            .as_ref()
+            .is_none_or(|inputs| inputs.is_match(relative))
+            && !self.outputs.is_match(relative)
+    }
+}
+
+fn build_glob_set(kind: &str, globs: &[String]) -> MartyResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in globs {
+        let glob = Glob::new(pattern).map_err(|e| {
+            MartyError::Config(format!("Invalid {} glob '{}': {}", kind, pattern, e))
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| MartyError::Config(format!("Invalid {} globs: {}", kind, e)))
+}
+
+/// Relative paths of all files under `dir` that could be task outputs. Unlike the
+/// fingerprint, this includes build directories such as `target` and `node_modules`.
+fn project_files(base: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> MartyResult<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if name == ".git" || name == ".marty" {
+                continue;
+            }
+            project_files(base, &path, files)?;
+        } else if file_type.is_file() {
+            files.push(path.strip_prefix(base).unwrap_or(&path).to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// The `globs` that match no non-empty file in the project directory
+pub fn missing_outputs(project_dir: &Path, globs: &[String]) -> MartyResult<Vec<String>> {
+    let matcher = build_glob_set("output", globs)?;
+    let mut files = Vec::new();
+    project_files(project_dir, project_dir, &mut files)?;
+
+    let mut produced = vec![false; globs.len()];
+    for relative in files {
+        let matches = matcher.matches(&relative);
+        if matches.is_empty() || std::fs::metadata(project_dir.join(&relative))?.len() == 0 {
+            continue;
+        }
+        for index in matches {
+            produced[index] = true;
+        }
+    }
+    Ok(globs
+        .iter()
+        .zip(produced)
+        .filter(|(_, produced)| !produced)
+        .map(|(glob, _)| glob.clone())
+        .collect())
+}
+
+/// Copy the project files matching `globs` into `target`, returning their relative paths
+fn capture_outputs(
+    project_dir: &Path,
+    target: &Path,
+    globs: &[String],
+) -> MartyResult<Vec<String>> {
+    if target.exists() {
+        std::fs::remove_dir_all(target)?;
+    }
+    copy_matching(project_dir, target, globs)
+}
+
+/// Copy the project files matching `globs` into `target`, next to what it already holds,
+/// returning their relative paths
+pub(crate) fn copy_matching(
+    project_dir: &Path,
+    target: &Path,
+    globs: &[String],
+) -> MartyResult<Vec<String>> {
+    let matcher = build_glob_set("output", globs)?;
+    let mut files = Vec::new();
+    project_files(project_dir, project_dir, &mut files)?;
+
+    let mut captured = Vec::new();
+    for relative in files.into_iter().filter(|f| matcher.is_match(f)) {
+        let destination = target.join(&relative);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(project_dir.join(&relative), destination)?;
+        captured.push(relative.to_string_lossy().replace('\\', "/"));
+    }
+    Ok(captured)
+}
+
+fn directory_size(dir: &Path) -> MartyResult<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        size += if entry.file_type()?.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            entry.metadata()?.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Feed the relative paths and contents of the fingerprinted files under `dir` into the hasher
+fn hash_directory(
+    hasher: &mut Sha256,
+    base: &Path,
+    dir: &Path,
+    filter: &FileFilter,
+) -> MartyResult<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if IGNORED_DIRS.iter().any(|ignored| name == *ignored) {
+                continue;
+            }
+            hash_directory(hasher, base, &path, filter)?;
+        } else if file_type.is_file() {
+            let relative = path.strip_prefix(base).unwrap_or(&path);
+            if !filter.includes(relative) {
+                continue;
+            }
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update(std::fs::read(&path)?);
+            hasher.update([0]);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(command: &str) -> TaskConfig {
+        TaskConfig {
+            name: "build".to_string(),
+            description: None,
+            docs: None,
+            script: None,
+            command: Some(crate::configs::tasks::Command::Single(command.to_string())),
+            plugin: None,
+            dependencies: None,
+            override_targets: None,
+            executor: None,
+            env_probe: None,
+            retries: None,
+            retry_delay: None,
+            timeout: None,
+            persistent: None,
+            env: None,
+            inputs: None,
+            outputs: None,
+            missing_outputs: None,
+            artifacts: None,
+            on_error: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    #[test]
+    fn test_key_changes_with_files_task_and_namespace() {
+        let temp = tempfile::tempdir().unwrap();
+        let project_dir = temp.path().join("app");
+        std::fs::create_dir_all(project_dir.join("target")).unwrap();
+        std::fs::write(project_dir.join("main.rs"), "fn main() {}").unwrap();
+
+        let cache = TaskCache::new(temp.path(), CacheNamespace::default());
+        let key = cache
+            .compute_key("app", &project_dir, &[], &task("make"))
+            .unwrap();
+
+        // Build output does not affect the key
+        std::fs::write(project_dir.join("target").join("out"), "binary").unwrap();
+        assert_eq!(
+            key,
+            cache
+                .compute_key("app", &project_dir, &[], &task("make"))
+                .unwrap()
+        );
+
+        assert_ne!(
+            key,
+            cache
+                .compute_key("app", &project_dir, &[], &task("make all"))
+                .unwrap()
+        );
+
+        let mut dimensions = BTreeMap::new();
+        dimensions.insert("profile".to_string(), "release".to_string());
+        let release = TaskCache::new(temp.path(), CacheNamespace { dimensions });
+        assert_ne!(
+            key,
+            release
+                .compute_key("app", &project_dir, &[], &task("make"))
+                .unwrap()
+        );
+
+        let mut env = BTreeMap::new();
+        env.insert("RELEASE".to_string(), "1".to_string());
+        let with_env = TaskCache::new(temp.path(), CacheNamespace::default()).with_extra_env(env);
+        assert_ne!(
+            key,
+            with_env
+                .compute_key("app", &project_dir, &[], &task("make"))
+                .unwrap()
+        );
+        let with_args = TaskCache::new(temp.path(), CacheNamespace::default())
+            .with_extra_args(vec!["--release".to_string()]);
+        assert_ne!(
+            key,
+            with_args
+                .compute_key("app", &project_dir, &[], &task("make"))
+                .unwrap()
+        );
+
+        let documented = TaskConfig {
+            docs: Some("Builds the app.".to_string()),
+            ..task("make")
+        };
+        assert_eq!(
+            key,
+            cache
+                .compute_key("app", &project_dir, &[], &documented)
+                .unwrap()
+        );
+
+        std::fs::write(project_dir.join("main.rs"), "fn main() { }").unwrap();
+        assert_ne!(
+            key,
+            cache
+                .compute_key("app", &project_dir, &[], &task("make"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_changes_with_dependency_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let project_dir = temp.path().join("app");
+        let lib_dir = temp.path().join("lib");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        std::fs::create_dir_all(lib_dir.join("target")).unwrap();
+        std::fs::write(project_dir.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(lib_dir.join("lib.rs"), "pub fn f() {}").unwrap();
+
+        let cache = TaskCache::new(temp.path(), CacheNamespace::default());
+        let key = |dependencies: &[&Path]| {
+            cache
+                .compute_key("app", &project_dir, dependencies, &task("make"))
+                .unwrap()
+        };
+        let with_lib = key(&[&lib_dir]);
+        assert_ne!(with_lib, key(&[]));
+
+        std::fs::write(lib_dir.join("target").join("out"), "binary").unwrap();
+        assert_eq!(with_lib, key(&[&lib_dir]));
+
+        std::fs::write(lib_dir.join("lib.rs"), "pub fn f() { }").unwrap();
+        assert_ne!(with_lib, key(&[&lib_dir]));
+    }
+
+    #[test]
+    fn test_env_probe_output_is_part_of_key() {
+        let temp = tempfile::tempdir().unwrap();
+        let project_dir = temp.path().join("app");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        std::fs::write(temp.path().join("version"), "1.0").unwrap();
+
+        let mut probed = task("make");
+        probed.env_probe = Some(vec!["cat version".to_string()]);
+
+        let key = TaskCache::new(temp.path(), CacheNamespace::default())
+            .compute_key("app", &project_dir, &[], &probed)
+            .unwrap();
+
+        // A new cache instance (a later run) sees the upgraded tool
+        std::fs::write(temp.path().join("version"), "2.0").unwrap();
+        let upgraded = TaskCache::new(temp.path(), CacheNamespace::default())
+            .compute_key("app", &project_dir, &[], &probed)
+            .unwrap();
+
+        assert_ne!(key, upgraded);
+    }
+
+    #[test]
+    fn test_record_and_stats_per_namespace() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut dimensions = BTreeMap::new();
+        dimensions.insert("branch".to_string(), "release/*".to_string());
+        let namespace = CacheNamespace { dimensions };
+
+        let cache = TaskCache::new(temp.path(), namespace.clone());
+        assert!(!cache.contains("abc"));
+        cache
+            .record("app", temp.path(), &task("make"), "abc")
+            .unwrap();
+        assert!(cache.contains("abc"));
+
+        // Entries from one namespace are not visible in another
+        let other = TaskCache::new(temp.path(), CacheNamespace::default());
+        assert!(!other.contains("abc"));
+
+        let stats = TaskCache::stats(temp.path()).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].id, namespace.id());
+        assert_eq!(stats[0].dimensions, namespace.dimensions);
+        assert_eq!(stats[0].entries, 1);
+
+        TaskCache::clear(temp.path()).unwrap();
+        assert!(TaskCache::stats(temp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_inputs_narrow_key_and_outputs_are_restored() {
+        let temp = tempfile::tempdir().unwrap();
+        let project_dir = temp.path().join("app");
+        std::fs::create_dir_all(project_dir.join("src")).unwrap();
+        std::fs::write(project_dir.join("src").join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(project_dir.join("README.md"), "docs").unwrap();
+
+        let mut build = task("make");
+        build.inputs = Some(vec!["src/**".to_string()]);
+        build.outputs = Some(vec!["dist/**".to_string()]);
+
+        let cache = TaskCache::new(temp.path(), CacheNamespace::default());
+        let key = cache.compute_key("app", &project_dir, &[], &build).unwrap();
+
+        // Neither files outside the inputs nor produced outputs affect the key
+        std::fs::write(project_dir.join("README.md"), "more docs").unwrap();
+        std::fs::create_dir_all(project_dir.join("dist")).unwrap();
+        std::fs::write(project_dir.join("dist").join("app.js"), "bundle").unwrap();
+        assert_eq!(
+            key,
+            cache.compute_key("app", &project_dir, &[], &build).unwrap()
+        );
+
+        cache.record("app", &project_dir, &build, &key).unwrap();
+        std::fs::remove_dir_all(project_dir.join("dist")).unwrap();
+        assert!(cache.restore(&key, &project_dir).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(project_dir.join("dist").join("app.js")).unwrap(),
+            "bundle"
+        );
+
+        // Without its captured outputs (e.g. fetched from a remote) the entry can't be used
+        let stored = TaskCache::cache_root(temp.path())
+            .join(CacheNamespace::default().id())
+            .join(format!("{}.outputs", key));
+        std::fs::remove_dir_all(stored).unwrap();
+        assert!(!cache.restore(&key, &project_dir).unwrap());
+
+        std::fs::write(project_dir.join("src").join("main.rs"), "fn main() { }").unwrap();
+        assert_ne!(
+            key,
+            cache.compute_key("app", &project_dir, &[], &build).unwrap()
+        );
+    }
+
+    /// Remote cache keeping entries in memory
+    #[derive(Default)]
+    struct MemoryRemote(Mutex<HashMap<String, Vec<u8>>>);
+
+    impl RemoteCache for MemoryRemote {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn get<'a>(
+            &'a self,
+            namespace: &'a str,
+            key: &'a str,
+        ) -> crate::execution::remote_cache::RemoteCacheFuture<'a, Option<Vec<u8>>> {
+            let body = self
+                .0
+                .lock()
+                .unwrap()
+                .get(&format!("{}/{}", namespace, key))
+                .cloned();
+            Box::pin(async move { Ok(body) })
+        }
+
+        fn put<'a>(
+            &'a self,
+            namespace: &'a str,
+            key: &'a str,
+            body: Vec<u8>,
+        ) -> crate::execution::remote_cache::RemoteCacheFuture<'a, ()> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(format!("{}/{}", namespace, key), body);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outputs_are_restored_from_a_remote_cache() {
+        let remote = Arc::new(MemoryRemote::default());
+        let config = crate::configs::workspace::RemoteCacheConfig {
+            url: Some("memory".to_string()),
+            s3: None,
+            enabled: None,
+            upload: None,
+            upload_concurrency: None,
+            max_artifact_size: None,
+        };
+        let mut build = task("make");
+        build.outputs = Some(vec!["dist/**".to_string()]);
+
+        let first = tempfile::tempdir().unwrap();
+        let project_dir = first.path().join("app");
+        std::fs::create_dir_all(project_dir.join("dist/js")).unwrap();
+        std::fs::write(project_dir.join("dist/js/app.js"), "bundle").unwrap();
+        let uploads = UploadQueue::new(remote.clone(), &config);
+        let cache = TaskCache::new(first.path(), CacheNamespace::default());
+        for artifact in cache.record("app", &project_dir, &build, "abc").unwrap() {
+            uploads.enqueue(artifact).await;
+        }
+        assert_eq!(uploads.finish().await.uploaded, 2);
+
+        let second = tempfile::tempdir().unwrap();
+        let project_dir = second.path().join("app");
+        let cache =
+            TaskCache::new(second.path(), CacheNamespace::default()).with_remote(remote.clone());
+        assert!(cache.fetch("abc").await);
+        assert!(cache.restore("abc", &project_dir).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(project_dir.join("dist/js/app.js")).unwrap(),
+            "bundle"
+        );
+
+        // Without its outputs archive, e.g. over the upload size limit, the entry is a miss
+        let namespace = CacheNamespace::default().id();
+        remote
+            .0
+            .lock()
+            .unwrap()
+            .remove(&format!("{}/abc.outputs", namespace));
+        let third = tempfile::tempdir().unwrap();
+        let cache = TaskCache::new(third.path(), CacheNamespace::default()).with_remote(remote);
+        assert!(!cache.fetch("abc").await);
+        assert!(!cache.contains("abc"));
+    }
+
+    #[tokio::test]
+    async fn test_entries_with_outputs_outside_the_project_are_a_miss() {
+        let remote = Arc::new(MemoryRemote::default());
+        let namespace = CacheNamespace::default().id();
+        let hostile = CacheEntryMetadata {
+            project: "app".to_string(),
+            task: "build".to_string(),
+            key: "abc".to_string(),
+            namespace: BTreeMap::new(),
+            created_at: 0,
+            outputs: vec!["../../escaped.txt".to_string()],
+        };
+        remote.0.lock().unwrap().insert(
+            format!("{}/abc", namespace),
+            serde_json::to_vec(&hostile).unwrap(),
+        );
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        archive
+            .append_data(&mut header, "payload.txt", "evil".as_bytes())
+            .unwrap();
+        remote.0.lock().unwrap().insert(
+            format!("{}/abc.outputs", namespace),
+            archive.into_inner().unwrap(),
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        let project_dir = temp.path().join("workspace").join("app");
+        let cache = TaskCache::new(&temp.path().join("workspace"), CacheNamespace::default())
+            .with_remote(remote);
+        assert!(!cache.fetch("abc").await);
+        assert!(!cache.contains("abc"));
+
+        // An entry written locally with such outputs is not restored either
+        let dir = cache.ensure_namespace_dir().unwrap();
+        std::fs::write(dir.join("abc.json"), serde_json::to_vec(&hostile).unwrap()).unwrap();
+        std::fs::create_dir_all(dir.join("abc.outputs")).unwrap();
+        std::fs::write(dir.join("abc.outputs/../../escaped.txt"), "evil").unwrap();
+        std::fs::write(temp.path().join("escaped.txt"), "original").unwrap();
+        assert!(!cache.restore("abc", &project_dir).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("escaped.txt")).unwrap(),
+            "original"
+        );
+    }
+
+    #[test]
+    fn test_missing_outputs() {
+        let temp = tempfile::tempdir().unwrap();
+        let project_dir = temp.path();
+        std::fs::create_dir_all(project_dir.join("dist")).unwrap();
+        std::fs::write(project_dir.join("dist").join("app.js"), "bundle").unwrap();
+        std::fs::write(project_dir.join("dist").join("app.d.ts"), "").unwrap();
+
+        let globs = ["dist/*.js", "dist/*.d.ts", "coverage/**"].map(String::from);
+        assert_eq!(
+            missing_outputs(project_dir, &globs).unwrap(),
+            vec!["dist/*.d.ts".to_string(), "coverage/**".to_string()]
+        );
+        assert!(missing_outputs(project_dir, &["dist/**".to_string()])
+            .unwrap()
+            .is_empty());
+        assert!(missing_outputs(project_dir, &["dist/[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_branch_groups() {
+        let groups = vec!["release/*".to_string()];
+        assert_eq!(
+            CacheNamespace::match_branch_group("release/1.2", &groups).unwrap(),
+            Some("release/*".to_string())
+        );
+        assert_eq!(
+            CacheNamespace::match_branch_group("main", &groups).unwrap(),
+            None
+        );
+    }
+}
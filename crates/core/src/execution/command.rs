@@ -1,48 +1,278 @@
 //! Command execution utilities
-//! 
+//!
 //! This module provides a unified interface for executing different types of commands
 //! (shell commands, scripts, executable with args) with consistent error handling and logging.
+//! Where commands run is delegated to an [`Executor`] backend, while hooks and exit status
+//! handling are shared by all backends.
 
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use colored::*;
+use marty_plugin_protocol::MartyPlugin;
 
-use crate::tasks::get_project_color;
+use crate::colors::project_color;
+use crate::configs::tasks::TaskConfig;
+use crate::execution::executor::{
+    interpolate, quote_for, shell_quote, ExecutionContext, ExecutionHook, Executor, Invocation,
+    LocalExecutor, LogCaptureHook, TargetEnvHook,
+};
+use crate::execution::resources::{self, wait_child, ResourceMeter};
+use crate::progress::{OutputStream, ProgressEvent, ProgressSink};
+use crate::task_logs::LogFile;
 use crate::types::{MartyError, MartyResult};
 use crate::workspace::Workspace;
 
+/// How often a failing command is retried and how long each attempt may run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each further retry
+    pub retry_delay: Duration,
+    pub timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+    pub fn from_task(task_config: &TaskConfig) -> Self {
+        Self {
+            retries: task_config.retries.unwrap_or(0),
+            retry_delay: Duration::from_secs(task_config.retry_delay.unwrap_or(1)),
+            timeout: task_config.timeout.map(Duration::from_secs),
+        }
+    }
+}
+
+/// Which of a task's commands an invocation is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// One of the task's `before` commands
+    Before,
+    /// The task's command or script
+    Main,
+    /// One of the task's `after` commands, run even if the task failed or was cancelled
+    After,
+}
+
 /// Unified command executor that handles common setup and execution patterns
 pub struct CommandExecutor<'a> {
     workspace: &'a Workspace,
     targets: &'a [String],
+    backend: Box<dyn Executor>,
+    hooks: Vec<Arc<dyn ExecutionHook>>,
+    policy: RetryPolicy,
+    /// Print resolved commands instead of running them
+    dry_run: bool,
+    /// Run commands until they exit or Marty is interrupted, ignoring the policy
+    persistent: bool,
+    progress: ProgressSink,
+    /// Task the commands belong to, for progress events
+    task: Option<String>,
+    /// Arguments appended to every command
+    extra_args: Vec<String>,
+    /// Once set, running commands are stopped and no further ones start
+    cancel: Option<Arc<AtomicBool>>,
+    /// Receives the resource usage of every command that ran to completion
+    meter: Option<ResourceMeter>,
 }
 
 impl<'a> CommandExecutor<'a> {
     pub fn new(workspace: &'a Workspace, targets: &'a [String]) -> Self {
-        Self { workspace, targets }
+        Self {
+            workspace,
+            targets,
+            backend: Box::new(LocalExecutor::default()),
+            hooks: vec![Arc::new(TargetEnvHook)],
+            policy: RetryPolicy::default(),
+            dry_run: false,
+            persistent: false,
+            progress: ProgressSink::default(),
+            task: None,
+            extra_args: Vec::new(),
+            cancel: None,
+            meter: None,
+        }
+    }
+
+    /// Print each resolved command instead of running it
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Retry failing commands and enforce a timeout
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Run commands on a different backend than the local shell
+    pub fn with_backend(mut self, backend: Box<dyn Executor>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Treat commands as long-running processes that Ctrl-C stops
+    pub fn with_persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    /// Report every line the commands of `task` write to `progress`
+    pub fn with_progress(mut self, progress: ProgressSink, task: &str) -> Self {
+        self.progress = progress;
+        self.task = Some(task.to_string());
+        self
+    }
+
+    /// Copy everything the commands write to `log`
+    pub fn with_log(self, log: LogFile) -> Self {
+        self.with_hook(Box::new(LogCaptureHook(log)))
+    }
+
+    /// Append `args` to every command, e.g. those given after `--` on the command line.
+    /// Shell commands get them quoted for the backend's shell.
+    pub fn with_extra_args(mut self, args: Vec<String>) -> Self {
+        self.extra_args = args;
+        self
+    }
+
+    /// Stop the commands, and everything they started, once `cancel` is set
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Add the resource usage of every command to `meter`
+    pub fn with_meter(mut self, meter: ResourceMeter) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// Add a hook that runs before and after every command
+    pub fn with_hook(mut self, hook: Box<dyn ExecutionHook>) -> Self {
+        self.hooks.push(Arc::from(hook));
+        self
     }
 
-    /// Execute a command with common setup and error handling
-    pub fn execute_command(
+    /// Execute an invocation with common setup and error handling
+    pub fn execute(
         &self,
-        command: &mut Command,
+        invocation: &Invocation,
+        execution_error_message: &str,
+        failure_error_message: &str,
+    ) -> MartyResult<()> {
+        self.execute_step(
+            invocation,
+            Step::Main,
+            execution_error_message,
+            failure_error_message,
+        )
+    }
+
+    /// Execute an invocation as the given step of the task. Only the main step gets the
+    /// extra arguments and runs persistently.
+    fn execute_step(
+        &self,
+        invocation: &Invocation,
+        step: Step,
         execution_error_message: &str,
         failure_error_message: &str,
     ) -> MartyResult<()> {
         // Common setup
-        command.current_dir(&self.workspace.root);
-        
-        // Set environment variables for targets
-        for (i, target) in self.targets.iter().enumerate() {
-            command.env(format!("MARTY_TARGET_{}", i), target);
+        let mut context = ExecutionContext {
+            workspace_root: self.workspace.root.clone(),
+            working_dir: self.workspace.root.clone(),
+            targets: self.targets.to_vec(),
+            env: self.well_known_env(),
+        };
+        for hook in &self.hooks {
+            hook.before(&mut context)?;
+        }
+
+        // Commands run through a shell expand variables themselves; programs run directly
+        // get `${VAR}` expanded here so they can refer to paths like the project directory
+        let extra_args: &[String] = if step == Step::Main {
+            &self.extra_args
+        } else {
+            &[]
+        };
+        let persistent = self.persistent && step == Step::Main;
+        // `after` commands clean up, so they run even once the run was cancelled
+        let cancel = self.cancel.as_deref().filter(|_| step != Step::After);
+        let invocation = match invocation {
+            Invocation::Shell(cmd) if !extra_args.is_empty() => Invocation::Shell(format!(
+                "{} {}",
+                cmd,
+                extra_args
+                    .iter()
+                    .map(|arg| quote_for(self.backend.shell(), arg))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )),
+            Invocation::Shell(_) => invocation.clone(),
+            Invocation::Program { program, args } => Invocation::Program {
+                program: interpolate(program, &context.env)?,
+                args: args
+                    .iter()
+                    .map(|arg| interpolate(arg, &context.env))
+                    .chain(extra_args.iter().cloned().map(Ok))
+                    .collect::<MartyResult<_>>()?,
+            },
+        };
+        let invocation = &invocation;
+
+        let mut command = self.backend.command(invocation, &context)?;
+        if self.dry_run {
+            print_resolved(invocation, &command, &context, self.backend.as_ref());
+            return Ok(());
+        }
+
+        if is_set(cancel) {
+            return Err(self.cancelled_error(execution_error_message));
+        }
+
+        let output_tap = self.output_tap();
+        let status = if persistent {
+            run_until_interrupted(&mut command, self.targets.first(), output_tap.as_ref())
+        } else {
+            self.run_with_retries(&mut command, cancel, output_tap.as_ref())
         }
+        .map_err(|e| {
+            MartyError::Task(format!(
+                "{} ({} executor): {}",
+                execution_error_message,
+                self.backend.name(),
+                e
+            ))
+        });
 
-        // Execute command
-        let status = command
-            .status()
-            .map_err(|e| MartyError::Task(format!("{}: {}", execution_error_message, e)))?;
+        // A persistent command stopped by Ctrl-C did its job
+        let success = matches!(&status, Ok(Some(status)) if status.success())
+            || (persistent && matches!(&status, Ok(None)));
+        for hook in &self.hooks {
+            hook.after(&command, &context, success)?;
+        }
 
+        let Some(status) = status? else {
+            if persistent {
+                println!("{}", "■ Stopped".bright_black());
+                return Ok(());
+            }
+            if is_set(cancel) {
+                return Err(self.cancelled_error(execution_error_message));
+            }
+            return Err(MartyError::Task(format!(
+                "{}: timed out after {}s",
+                execution_error_message,
+                self.policy.timeout.unwrap_or_default().as_secs()
+            )));
+        };
         if !status.success() {
             return Err(MartyError::Task(format!(
                 "{}: {}",
@@ -51,10 +281,98 @@ impl<'a> CommandExecutor<'a> {
             )));
         }
 
-        self.show_completion_message();
+        if step == Step::Main {
+            self.show_completion_message();
+        }
         Ok(())
     }
 
+    /// Execute a command, retrying failed and timed out attempts. Failing to start the
+    /// command at all is not transient, so it is never retried.
+    fn run_with_retries(
+        &self,
+        command: &mut Command,
+        cancel: Option<&AtomicBool>,
+        output_tap: Option<&OutputTap>,
+    ) -> io::Result<Option<ExitStatus>> {
+        let mut attempt = 0;
+        loop {
+            let status = run_to_completion(
+                command,
+                self.policy.timeout,
+                cancel,
+                self.targets.first(),
+                output_tap,
+                self.meter.as_ref(),
+            );
+            let failed =
+                matches!(&status, Ok(None)) || matches!(&status, Ok(Some(s)) if !s.success());
+            if !failed || attempt >= self.policy.retries || is_set(cancel) {
+                return status;
+            }
+
+            attempt += 1;
+            let delay = self.policy.retry_delay * 2u32.saturating_pow(attempt - 1);
+            println!(
+                "{} {}",
+                "↻".yellow().bold(),
+                format!(
+                    "Attempt {}/{} failed, retrying in {}s",
+                    attempt,
+                    self.policy.retries + 1,
+                    delay.as_secs_f32()
+                )
+                .yellow()
+            );
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn cancelled_error(&self, execution_error_message: &str) -> MartyError {
+        MartyError::Task(format!(
+            "{}: stopped because the task failed on another project",
+            execution_error_message
+        ))
+    }
+
+    /// Where to copy output lines besides our own output
+    fn output_tap(&self) -> Option<OutputTap> {
+        Some(OutputTap {
+            progress: self.progress.clone(),
+            hooks: self.hooks.clone(),
+            project: self.targets.first()?.clone(),
+            task: self.task.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Variables every command gets: `MARTY_WORKSPACE_ROOT`, and `MARTY_PROJECT_DIR` when
+    /// the first target is a project
+    fn well_known_env(&self) -> BTreeMap<String, String> {
+        let absolute = |path: &std::path::Path| {
+            path.canonicalize()
+                .unwrap_or_else(|_| path.to_path_buf())
+                .display()
+                .to_string()
+        };
+
+        let mut env = BTreeMap::new();
+        env.insert(
+            "MARTY_WORKSPACE_ROOT".to_string(),
+            absolute(&self.workspace.root),
+        );
+        if let Some(project) = self
+            .targets
+            .first()
+            .and_then(|target| self.workspace.project(target))
+        {
+            env.insert(
+                "MARTY_PROJECT_DIR".to_string(),
+                absolute(&project.project_dir),
+            );
+        }
+        env
+    }
+
     /// Execute a script file
     pub fn execute_script(&self, script_path: &str) -> MartyResult<()> {
         let script_path_buf = PathBuf::from(script_path);
@@ -73,20 +391,80 @@ impl<'a> CommandExecutor<'a> {
             )));
         }
 
-        let mut command = Command::new(&full_script_path);
-        self.execute_command(
-            &mut command,
+        let invocation = Invocation::Program {
+            program: full_script_path.display().to_string(),
+            args: Vec::new(),
+        };
+        self.execute(
+            &invocation,
             &format!("Failed to execute script: {}", full_script_path.display()),
             "Script execution failed with exit code",
         )
     }
 
+    /// Have `plugin` run `task` on the first target itself. The lines the plugin reports
+    /// are printed like a command's output.
+    pub fn execute_plugin_task(&self, plugin: &dyn MartyPlugin, task: &str) -> MartyResult<()> {
+        let Some(project) = self
+            .targets
+            .first()
+            .and_then(|target| self.workspace.project(target))
+        else {
+            return Err(MartyError::Task(format!(
+                "Task '{}' runs on plugin '{}' and needs a project to run on",
+                task,
+                plugin.key()
+            )));
+        };
+        if self.dry_run {
+            println!("  {} {} {}", "plugin".bright_black(), plugin.key(), task);
+            return Ok(());
+        }
+        let execution_error_message =
+            format!("Failed to run task '{}' on plugin '{}'", task, plugin.key());
+        if is_set(self.cancel.as_deref()) {
+            return Err(self.cancelled_error(&execution_error_message));
+        }
+
+        let workspace = marty_plugin_protocol::Workspace::from(self.workspace);
+        let outcome = plugin.run_task(task, project, &workspace);
+        let prefix = format!("{} │", project.name)
+            .color(project_color(&project.name))
+            .to_string();
+        let tap = self.output_tap();
+        for line in &outcome.output {
+            if outcome.success {
+                if let Some(tap) = &tap {
+                    tap.line(OutputStream::Stdout, line);
+                }
+                println!("{} {}", prefix, line);
+            } else {
+                if let Some(tap) = &tap {
+                    tap.line(OutputStream::Stderr, line);
+                }
+                eprintln!("{} {}", prefix, line);
+            }
+        }
+        if !outcome.success {
+            return Err(MartyError::Task(execution_error_message));
+        }
+
+        self.show_completion_message();
+        Ok(())
+    }
+
     /// Execute a command with arguments
-    pub fn execute_command_with_args(&self, command_path: &str, args: &[String]) -> MartyResult<()> {
-        let mut command = Command::new(command_path);
-        command.args(args);
-        self.execute_command(
-            &mut command,
+    pub fn execute_command_with_args(
+        &self,
+        command_path: &str,
+        args: &[String],
+    ) -> MartyResult<()> {
+        let invocation = Invocation::Program {
+            program: command_path.to_string(),
+            args: args.to_vec(),
+        };
+        self.execute(
+            &invocation,
             &format!("Failed to execute command '{}'", command_path),
             &format!("Command '{}' failed with exit code", command_path),
         )
@@ -94,19 +472,37 @@ impl<'a> CommandExecutor<'a> {
 
     /// Execute a single shell command
     pub fn execute_shell_command(&self, cmd: &str) -> MartyResult<()> {
-        let mut command = Command::new("sh");
-        command.arg("-c").arg(cmd);
-        self.execute_command(
-            &mut command,
+        self.execute(
+            &Invocation::Shell(cmd.to_string()),
             &format!("Failed to execute command '{}'", cmd),
             &format!("Command '{}' failed with exit code", cmd),
         )
     }
 
+    /// Execute one of the task's `before` shell commands
+    pub fn execute_before_command(&self, cmd: &str) -> MartyResult<()> {
+        self.execute_step(
+            &Invocation::Shell(cmd.to_string()),
+            Step::Before,
+            &format!("Failed to execute before command '{}'", cmd),
+            &format!("Before command '{}' failed with exit code", cmd),
+        )
+    }
+
+    /// Execute one of the task's `after` shell commands
+    pub fn execute_after_command(&self, cmd: &str) -> MartyResult<()> {
+        self.execute_step(
+            &Invocation::Shell(cmd.to_string()),
+            Step::After,
+            &format!("Failed to execute after command '{}'", cmd),
+            &format!("After command '{}' failed with exit code", cmd),
+        )
+    }
+
     /// Show completion message for the first target
     fn show_completion_message(&self) {
         if let Some(target) = self.targets.first() {
-            let project_color = get_project_color(target);
+            let project_color = project_color(target);
             println!(
                 "{} {}",
                 "✓".green().bold(),
@@ -114,4 +510,545 @@ impl<'a> CommandExecutor<'a> {
             );
         }
     }
-}
\ No newline at end of file
+}
+
+/// Print what a dry run would execute: the command line, the variables Marty sets for it
+/// and, for backends other than the local shell, the process that would be spawned
+fn print_resolved(
+    invocation: &Invocation,
+    command: &Command,
+    context: &ExecutionContext,
+    backend: &dyn Executor,
+) {
+    println!(
+        "  {} {}",
+        "$".bright_black(),
+        invocation.to_shell_line(backend.shell())
+    );
+    if !context.env.is_empty() {
+        let env: Vec<String> = context
+            .env
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, shell_quote(value)))
+            .collect();
+        println!("    {} {}", "env:".bright_black(), env.join(" "));
+    }
+    if backend.name() != "local" {
+        let spawned: Vec<String> = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| shell_quote(&part.to_string_lossy()))
+            .collect();
+        println!(
+            "    {} {}",
+            format!("{}:", backend.name()).bright_black(),
+            spawned.join(" ")
+        );
+    }
+}
+
+/// Whether a run's cancel flag, if any, is set
+fn is_set(cancel: Option<&AtomicBool>) -> bool {
+    cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+}
+
+/// Run a command to completion, killing it once `timeout` has elapsed and terminating it
+/// once `cancel` is set.
+///
+/// Returns `None` if the command was stopped. With a timeout or `cancel` the command runs
+/// in its own process group so that everything it started is stopped along with it.
+///
+/// When a `project` is given, the command's stdout and stderr are captured and every
+/// line is prefixed with the project's name, so output of tasks running side by side
+/// stays attributable. Captured lines are also copied to `tap`, and the command's resource
+/// usage is added to `meter`.
+fn run_to_completion(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    cancel: Option<&AtomicBool>,
+    project: Option<&String>,
+    tap: Option<&OutputTap>,
+    meter: Option<&ResourceMeter>,
+) -> io::Result<Option<ExitStatus>> {
+    #[cfg(unix)]
+    if timeout.is_some() || cancel.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let (mut child, forwarders) = spawn(command, project, tap)?;
+    let status = wait(&mut child, timeout, cancel, meter)?;
+    drain(forwarders);
+    Ok(status)
+}
+
+/// Set by Ctrl-C while an [`InterruptGuard`] is alive
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Number of live [`InterruptGuard`]s
+static INTERRUPT_GUARDS: Mutex<usize> = Mutex::new(0);
+
+/// Makes Ctrl-C stop persistent commands instead of terminating Marty right away.
+///
+/// Persistent commands of several projects share the handler. Once the last guard is
+/// dropped, the previous handler is back and the interrupt is forgotten, so Ctrl-C stops
+/// Marty again and a later persistent run starts afresh.
+struct InterruptGuard;
+
+impl InterruptGuard {
+    fn new() -> Self {
+        let mut guards = INTERRUPT_GUARDS.lock().unwrap_or_else(|e| e.into_inner());
+        if *guards == 0 {
+            interrupt_handler::install();
+        }
+        *guards += 1;
+        InterruptGuard
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        let mut guards = INTERRUPT_GUARDS.lock().unwrap_or_else(|e| e.into_inner());
+        *guards -= 1;
+        if *guards == 0 {
+            interrupt_handler::restore();
+            INTERRUPTED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod interrupt_handler {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::INTERRUPTED;
+
+    /// The SIGINT handler in place before [`install`]
+    static PREVIOUS: AtomicUsize = AtomicUsize::new(libc::SIG_DFL);
+
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    pub(super) fn install() {
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        let previous = unsafe {
+            libc::signal(
+                libc::SIGINT,
+                on_interrupt as *const () as libc::sighandler_t,
+            )
+        };
+        if previous != libc::SIG_ERR {
+            PREVIOUS.store(previous, Ordering::SeqCst);
+        }
+    }
+
+    pub(super) fn restore() {
+        // SAFETY: the handler being put back was installed before ours
+        unsafe {
+            libc::signal(libc::SIGINT, PREVIOUS.load(Ordering::SeqCst));
+        }
+    }
+}
+
+#[cfg(windows)]
+mod interrupt_handler {
+    use std::sync::atomic::Ordering;
+
+    use windows_sys::Win32::Foundation::{BOOL, FALSE, TRUE};
+    use windows_sys::Win32::System::Console::{
+        SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_C_EVENT,
+    };
+
+    use super::INTERRUPTED;
+
+    unsafe extern "system" fn on_interrupt(event: u32) -> BOOL {
+        if event == CTRL_C_EVENT || event == CTRL_BREAK_EVENT {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+            TRUE
+        } else {
+            FALSE
+        }
+    }
+
+    pub(super) fn install() {
+        // SAFETY: the handler only stores to an atomic
+        unsafe { SetConsoleCtrlHandler(Some(on_interrupt), TRUE) };
+    }
+
+    pub(super) fn restore() {
+        // SAFETY: removing a handler that was added by `install`
+        unsafe { SetConsoleCtrlHandler(Some(on_interrupt), FALSE) };
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod interrupt_handler {
+    pub(super) fn install() {}
+
+    pub(super) fn restore() {}
+}
+
+/// How long a persistent command may take to shut down before it is killed
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Run a long-running command until it exits by itself or Marty is interrupted.
+///
+/// The command runs in its own process group, so Ctrl-C reaches Marty only. Marty then
+/// asks the group to terminate and kills it if it is still running after a grace period.
+/// Returns `None` if the command was stopped that way.
+fn run_until_interrupted(
+    command: &mut Command,
+    project: Option<&String>,
+    tap: Option<&OutputTap>,
+) -> io::Result<Option<ExitStatus>> {
+    let _guard = InterruptGuard::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let (mut child, forwarders) = spawn(command, project, tap)?;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            terminate(&mut child)?;
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    drain(forwarders);
+    Ok(status)
+}
+
+/// Ask a command and everything it started to exit, killing them after [`SHUTDOWN_GRACE`]
+fn terminate(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    // SAFETY: signalling a process group has no memory safety requirements
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGTERM);
+    }
+    #[cfg(not(unix))]
+    kill(child);
+    // Whatever is still running once the grace period is over gets killed
+    wait(child, Some(SHUTDOWN_GRACE), None, None).map(drop)
+}
+
+/// Start a command. With a `project`, its output is captured and forwarded line by line.
+fn spawn(
+    command: &mut Command,
+    project: Option<&String>,
+    tap: Option<&OutputTap>,
+) -> io::Result<(Child, Vec<Option<JoinHandle<()>>>)> {
+    if project.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child = command.spawn()?;
+    resources::track(&child);
+    let forwarders = match project {
+        Some(project) => {
+            let prefix = format!("{} │", project)
+                .color(project_color(project))
+                .to_string();
+            vec![
+                child.stdout.take().map(|out| {
+                    forward_lines(out, prefix.clone(), OutputStream::Stdout, tap.cloned())
+                }),
+                child
+                    .stderr
+                    .take()
+                    .map(|err| forward_lines(err, prefix, OutputStream::Stderr, tap.cloned())),
+            ]
+        }
+        None => Vec::new(),
+    };
+    Ok((child, forwarders))
+}
+
+/// Let the remaining output drain, but don't hang on background processes the command
+/// left behind that still hold the pipes open
+fn drain(forwarders: Vec<Option<JoinHandle<()>>>) {
+    let deadline = Instant::now() + Duration::from_millis(500);
+    for forwarder in forwarders.into_iter().flatten() {
+        while !forwarder.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        if forwarder.is_finished() {
+            let _ = forwarder.join();
+        }
+    }
+}
+
+/// Wait for a child to exit, killing it once `timeout` has elapsed and terminating it once
+/// `cancel` is set
+fn wait(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    cancel: Option<&AtomicBool>,
+    meter: Option<&ResourceMeter>,
+) -> io::Result<Option<ExitStatus>> {
+    if timeout.is_none() && cancel.is_none() {
+        return reap(child, true, meter);
+    }
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if let Some(status) = reap(child, false, meter)? {
+            return Ok(Some(status));
+        }
+        if is_set(cancel) {
+            terminate(child)?;
+            return Ok(None);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            kill(child);
+            reap(child, true, meter)?;
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Reap a child once it exited (without `block`, only if it already has), adding its
+/// resource usage to `meter`
+fn reap(
+    child: &mut Child,
+    block: bool,
+    meter: Option<&ResourceMeter>,
+) -> io::Result<Option<ExitStatus>> {
+    let Some((status, usage)) = wait_child(child, block)? else {
+        return Ok(None);
+    };
+    if let (Some(meter), Some(usage)) = (meter, usage) {
+        meter.add(usage);
+    }
+    Ok(Some(status))
+}
+
+/// Destinations of a command's output lines besides our own output
+#[derive(Clone)]
+struct OutputTap {
+    progress: ProgressSink,
+    hooks: Vec<Arc<dyn ExecutionHook>>,
+    project: String,
+    task: String,
+}
+
+impl OutputTap {
+    fn line(&self, stream: OutputStream, text: &str) {
+        if self.progress.is_enabled() {
+            self.progress.emit(ProgressEvent::TaskOutputChunk {
+                project: self.project.clone(),
+                task: self.task.clone(),
+                stream,
+                text: text.to_string(),
+            });
+        }
+        for hook in &self.hooks {
+            hook.output(stream, text);
+        }
+    }
+}
+
+/// Copy lines from a child's output to ours, each prefixed with `prefix`
+fn forward_lines(
+    output: impl Read + Send + 'static,
+    prefix: String,
+    stream: OutputStream,
+    tap: Option<OutputTap>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\n', '\r']);
+            if let Some(tap) = &tap {
+                tap.line(stream, text);
+            }
+            // Lock per line so lines from concurrent tasks never mix
+            let _ = if stream == OutputStream::Stderr {
+                writeln!(io::stderr().lock(), "{} {}", prefix, text)
+            } else {
+                writeln!(io::stdout().lock(), "{} {}", prefix, text)
+            };
+            line.clear();
+        }
+    })
+}
+
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: signalling a process group has no memory safety requirements
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    // Elsewhere the command's job object holds everything it started
+    #[cfg(not(unix))]
+    resources::kill_tree(child);
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_kills_the_command() {
+        let started = Instant::now();
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("sleep 5; sleep 5");
+        let status = run_to_completion(
+            &mut command,
+            Some(Duration::from_millis(200)),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(status.is_none());
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("exit 3");
+        let project = "app".to_string();
+        let status = run_to_completion(
+            &mut command,
+            Some(Duration::from_secs(5)),
+            None,
+            Some(&project),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(status.and_then(|s| s.code()), Some(3));
+    }
+
+    #[test]
+    fn test_cancel_stops_the_command() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let setter = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(200));
+                cancel.store(true, Ordering::SeqCst);
+            })
+        };
+        let started = Instant::now();
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("sleep 5; sleep 5");
+        let status =
+            run_to_completion(&mut command, None, Some(&cancel), None, None, None).unwrap();
+        setter.join().unwrap();
+        assert!(status.is_none());
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ctrl_c_stops_a_persistent_command_once() {
+        let interrupter = std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(200));
+            // SAFETY: sending a signal to our own process
+            unsafe { libc::kill(libc::getpid(), libc::SIGINT) };
+        });
+        let started = Instant::now();
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("sleep 5; sleep 5");
+        let status = run_until_interrupted(&mut command, None, None).unwrap();
+        interrupter.join().unwrap();
+        assert!(status.is_none());
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        // The default handler is back and the interrupt doesn't stop the next run
+        assert!(!INTERRUPTED.load(Ordering::SeqCst));
+        // SAFETY: reading the handler by putting the default one in place
+        let handler = unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL) };
+        assert_eq!(handler, libc::SIG_DFL);
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("sleep 0.2");
+        let status = run_until_interrupted(&mut command, None, None).unwrap();
+        assert!(status.is_some_and(|status| status.success()));
+    }
+
+    #[test]
+    fn test_before_and_after_commands_skip_extra_args_and_only_after_runs_cancelled() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = Workspace {
+            root: temp.path().to_path_buf(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: Default::default(),
+        };
+        let targets = Vec::new();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let executor = CommandExecutor::new(&workspace, &targets)
+            .with_extra_args(vec!["--release".to_string()])
+            .with_cancel(cancel.clone());
+
+        // printf prints every argument it gets, so appended arguments would show up
+        executor
+            .execute_shell_command("printf '%s|' main > main.txt")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("main.txt")).unwrap(),
+            "main|--release|"
+        );
+        executor
+            .execute_before_command("printf '%s|' before > before.txt")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("before.txt")).unwrap(),
+            "before|"
+        );
+
+        cancel.store(true, Ordering::SeqCst);
+        assert!(executor.execute_before_command("true").is_err());
+        executor
+            .execute_after_command("printf '%s|' after > after.txt")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("after.txt")).unwrap(),
+            "after|"
+        );
+    }
+
+    #[test]
+    fn test_output_lines_are_reported_as_events() {
+        let (progress, receiver) = ProgressSink::channel();
+        let tap = OutputTap {
+            progress,
+            hooks: Vec::new(),
+            project: "app".to_string(),
+            task: "build".to_string(),
+        };
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo compiled; echo warned >&2");
+        let project = "app".to_string();
+        run_to_completion(&mut command, None, None, Some(&project), Some(&tap), None).unwrap();
+        drop(tap);
+
+        let mut chunks: Vec<(OutputStream, String)> = receiver
+            .iter()
+            .filter_map(|event| match event {
+                ProgressEvent::TaskOutputChunk { stream, text, .. } => Some((stream, text)),
+                _ => None,
+            })
+            .collect();
+        chunks.sort_by_key(|(stream, _)| *stream == OutputStream::Stderr);
+        assert_eq!(
+            chunks,
+            [
+                (OutputStream::Stdout, "compiled".to_string()),
+                (OutputStream::Stderr, "warned".to_string()),
+            ]
+        );
+    }
+}
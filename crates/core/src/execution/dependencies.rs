@@ -3,71 +3,134 @@
 //! This module handles the resolution of task dependencies, including topological sorting
 //! and dependency level grouping for parallel execution.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
 
 use crate::types::{MartyError, MartyResult};
-use crate::workspace::Workspace;
+use crate::workspace::{ProjectId, Workspace};
 
 /// Group projects by their dependency levels (topological levels)
+///
+/// Every project lands in the first level after all of its dependencies, so the levels
+/// can be run in order. Projects within a level are sorted by name.
 pub fn group_by_dependency_levels(
     workspace: &Workspace,
     projects: &[String],
 ) -> MartyResult<Vec<Vec<String>>> {
-    if workspace.dep_graph.is_none() {
-        return Err(MartyError::Task("Dependency graph not built".to_string()));
-    }
-
-    let graph = workspace.dep_graph.as_ref().unwrap();
+    let graph = workspace
+        .dep_graph
+        .as_ref()
+        .ok_or_else(|| MartyError::Task("Dependency graph not built".to_string()))?;
 
-    // Create a mapping from project names to node indices
-    let mut name_to_node = HashMap::new();
-    for (node_index, node_weight) in graph.node_indices().zip(graph.node_weights()) {
-        name_to_node.insert(node_weight.clone(), node_index);
+    // Count the dependencies of each project among the projects being grouped
+    let mut ids = Vec::with_capacity(projects.len());
+    for project in projects {
+        let id = workspace.project_id(project).ok_or_else(|| {
+            MartyError::Task(format!("Project '{}' not found in workspace", project))
+        })?;
+        ids.push(id);
     }
+    let selected: HashSet<ProjectId> = ids.iter().copied().collect();
+    // Members of an allowed cycle don't wait for each other
+    let within_allowed_cycle = |a: NodeIndex, b: NodeIndex| {
+        !workspace.allowed_cycles.is_empty() && workspace.in_allowed_cycle(&graph[a], &graph[b])
+    };
+    let mut pending: HashMap<ProjectId, usize> = ids
+        .iter()
+        .map(|&id| {
+            let dependencies = graph
+                .neighbors(id.node())
+                .filter(|dep| selected.contains(&ProjectId::from_node(*dep)))
+                .filter(|dep| !within_allowed_cycle(id.node(), *dep))
+                .count();
+            (id, dependencies)
+        })
+        .collect();
 
-    // Calculate levels using topological sort
+    let name = |id: ProjectId| graph[id.node()].clone();
     let mut levels = Vec::new();
-    let mut visited = std::collections::HashSet::new();
-    let mut current_level = Vec::new();
-
-    // Start with projects that have no dependencies (leaf nodes in reverse topological order)
-    for project in projects {
-        if let Some(&node_index) = name_to_node.get(project) {
-            if graph.neighbors(node_index).count() == 0 {
-                current_level.push(project.clone());
-                visited.insert(node_index);
-            }
+    let mut ready: Vec<ProjectId> = pending
+        .iter()
+        .filter(|(_, &count)| count == 0)
+        .map(|(&id, _)| id)
+        .collect();
+    while !ready.is_empty() {
+        for id in &ready {
+            pending.remove(id);
         }
-    }
-
-    while !current_level.is_empty() {
-        levels.push(current_level.clone());
-        let mut next_level = Vec::new();
-
-        // Find projects that depend on the current level
-        for project in &current_level {
-            if let Some(&node_index) = name_to_node.get(project) {
-                // Find all projects that have this as a dependency (reverse edges)
-                for potential_parent in graph.node_indices() {
-                    if graph.contains_edge(potential_parent, node_index) {
-                        let parent_name = graph.node_weight(potential_parent).unwrap();
-                        if projects.contains(parent_name) && !visited.contains(&potential_parent) {
-                            next_level.push(parent_name.clone());
-                            visited.insert(potential_parent);
-                        }
+        // Dependents of this level become ready once their last dependency is placed
+        let mut next = Vec::new();
+        for id in &ready {
+            for dependent in graph.neighbors_directed(id.node(), Direction::Incoming) {
+                if within_allowed_cycle(dependent, id.node()) {
+                    continue;
+                }
+                if let Some(count) = pending.get_mut(&ProjectId::from_node(dependent)) {
+                    *count -= 1;
+                    if *count == 0 {
+                        next.push(ProjectId::from_node(dependent));
                     }
                 }
             }
         }
+        let mut level: Vec<String> = ready.into_iter().map(name).collect();
+        level.sort();
+        levels.push(level);
+        ready = next;
+    }
 
-        current_level = next_level
-            .into_iter()
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect();
+    if !pending.is_empty() {
+        let mut stuck = pending.keys().map(|&id| name(id)).collect::<Vec<_>>();
+        stuck.sort();
+        return Err(MartyError::Task(format!(
+            "Dependency cycle between projects: {}",
+            stuck.join(", ")
+        )));
     }
 
-    // Reverse to get dependencies first
-    levels.reverse();
     Ok(levels)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::ProjectIndex;
+    use petgraph::graph::DiGraph;
+
+    #[test]
+    fn test_dependencies_run_before_dependents() {
+        // app -> lib -> core, app -> core, tool (no dependencies)
+        let mut graph = DiGraph::new();
+        let app = graph.add_node("app".to_string());
+        let lib = graph.add_node("lib".to_string());
+        let core = graph.add_node("core".to_string());
+        graph.add_node("tool".to_string());
+        graph.add_edge(app, lib, ());
+        graph.add_edge(app, core, ());
+        graph.add_edge(lib, core, ());
+
+        let mut workspace = Workspace {
+            root: std::path::PathBuf::from("."),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: Some(graph),
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        workspace.reindex();
+        let projects = ["app", "lib", "core", "tool"].map(String::from);
+
+        let levels = group_by_dependency_levels(&workspace, &projects).unwrap();
+        assert_eq!(
+            levels,
+            vec![
+                vec!["core".to_string(), "tool".to_string()],
+                vec!["lib".to_string()],
+                vec!["app".to_string()],
+            ]
+        );
+    }
+}
@@ -0,0 +1,593 @@
+//! Execution backends
+//!
+//! An [`Executor`] decides *where* a task's command runs (the local shell, a Docker
+//! container, a remote host, a WASI sandbox) by turning an [`Invocation`] into the process
+//! Marty spawns. Everything around that process (hooks, spawning, exit status handling) is
+//! shared and lives in [`CommandExecutor`](crate::execution::CommandExecutor), so adding a
+//! backend only requires implementing this trait and registering it in [`from_config`].
+//!
+//! [`ExecutionHook`]s run around every command on any backend: they inject environment
+//! variables, capture output to a log and collect artifacts.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::configs::tasks::ExecutorConfig;
+use crate::configs::workspace::Shell;
+use crate::execution::cache;
+use crate::progress::OutputStream;
+use crate::task_logs::{self, LogFile};
+use crate::types::{MartyError, MartyResult};
+
+pub mod docker;
+pub mod local;
+pub mod remote;
+pub mod wasm;
+
+pub use docker::DockerExecutor;
+pub use local::LocalExecutor;
+pub use remote::RemoteExecutor;
+pub use wasm::WasmExecutor;
+
+/// What a task asks to run
+#[derive(Debug, Clone)]
+pub enum Invocation {
+    /// A command line interpreted by a shell
+    Shell(String),
+    /// A program (or script file) with arguments, run without a shell
+    Program { program: String, args: Vec<String> },
+}
+
+impl Invocation {
+    /// Render the invocation as a single command line for `shell`
+    pub fn to_shell_line(&self, shell: Shell) -> String {
+        match self {
+            Invocation::Shell(cmd) => cmd.clone(),
+            Invocation::Program { program, args } => std::iter::once(program)
+                .chain(args)
+                .map(|part| quote_for(shell, part))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Environment a command runs in, prepared by hooks before execution
+#[derive(Debug, Clone)]
+pub struct ExecutionContext {
+    pub workspace_root: PathBuf,
+    pub working_dir: PathBuf,
+    pub targets: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+/// A backend that runs task commands
+pub trait Executor {
+    /// Short name used in messages
+    fn name(&self) -> &'static str;
+
+    /// Shell interpreting [`Invocation::Shell`] command lines
+    fn shell(&self) -> Shell {
+        Shell::Sh
+    }
+
+    /// Build the process that runs `invocation` in `context`
+    fn command(&self, invocation: &Invocation, context: &ExecutionContext) -> MartyResult<Command>;
+}
+
+/// Shared logic that runs before and after every command, regardless of backend
+pub trait ExecutionHook: Send + Sync {
+    fn before(&self, _context: &mut ExecutionContext) -> MartyResult<()> {
+        Ok(())
+    }
+
+    /// Called with every line the command writes, from the threads forwarding its output
+    fn output(&self, _stream: OutputStream, _line: &str) {}
+
+    /// Called once the spawned `command` has finished (or failed to start)
+    fn after(
+        &self,
+        _command: &Command,
+        _context: &ExecutionContext,
+        _success: bool,
+    ) -> MartyResult<()> {
+        Ok(())
+    }
+}
+
+/// Exposes the task targets to commands as `MARTY_TARGET_{i}`
+pub struct TargetEnvHook;
+
+impl ExecutionHook for TargetEnvHook {
+    fn before(&self, context: &mut ExecutionContext) -> MartyResult<()> {
+        for (i, target) in context.targets.iter().enumerate() {
+            context
+                .env
+                .insert(format!("MARTY_TARGET_{}", i), target.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Runs every command in the directory of the project it runs for, instead of the
+/// workspace root
+pub struct ProjectDirHook;
+
+impl ExecutionHook for ProjectDirHook {
+    fn before(&self, context: &mut ExecutionContext) -> MartyResult<()> {
+        if let Some(dir) = context.env.get("MARTY_PROJECT_DIR") {
+            context.working_dir = PathBuf::from(dir);
+        }
+        Ok(())
+    }
+}
+
+/// Adds a fixed set of environment variables to every command
+pub struct EnvHook(pub BTreeMap<String, String>);
+
+impl ExecutionHook for EnvHook {
+    fn before(&self, context: &mut ExecutionContext) -> MartyResult<()> {
+        context
+            .env
+            .extend(self.0.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(())
+    }
+}
+
+/// Sets a task's `env` variables. `${VAR}` in their values is expanded from the variables
+/// already set for the command (such as `MARTY_PROJECT_DIR`), then from Marty's environment.
+pub struct TaskEnvHook(pub BTreeMap<String, String>);
+
+impl ExecutionHook for TaskEnvHook {
+    fn before(&self, context: &mut ExecutionContext) -> MartyResult<()> {
+        let expanded = self
+            .0
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), interpolate(value, &context.env)?)))
+            .collect::<MartyResult<Vec<_>>>()?;
+        context.env.extend(expanded);
+        Ok(())
+    }
+}
+
+/// Copies everything commands write to a task log
+pub struct LogCaptureHook(pub LogFile);
+
+impl ExecutionHook for LogCaptureHook {
+    fn output(&self, _stream: OutputStream, line: &str) {
+        // Logging is best effort and never fails the command
+        if let Ok(mut log) = self.0.lock() {
+            let _ = writeln!(log, "{}", line);
+        }
+    }
+}
+
+/// Copies the files of a project matching a task's `artifacts` globs to
+/// `.marty/artifacts/<project>/<task>/` after each successful command. The files of an
+/// earlier run are removed first, so the directory only holds this run's artifacts.
+pub struct ArtifactHook {
+    project_dir: PathBuf,
+    target: PathBuf,
+    globs: Vec<String>,
+    cleared: AtomicBool,
+}
+
+impl ArtifactHook {
+    pub fn new(
+        workspace_root: &Path,
+        project: &str,
+        project_dir: &Path,
+        task: &str,
+        globs: Vec<String>,
+    ) -> Self {
+        Self {
+            project_dir: project_dir.to_path_buf(),
+            target: artifacts_dir(workspace_root, project, task),
+            globs,
+            cleared: AtomicBool::new(false),
+        }
+    }
+
+    /// Copy the matching files, returning their paths relative to the project directory
+    pub fn collect(&self) -> MartyResult<Vec<String>> {
+        if !self.cleared.swap(true, Ordering::SeqCst) && self.target.exists() {
+            std::fs::remove_dir_all(&self.target)?;
+        }
+        cache::copy_matching(&self.project_dir, &self.target, &self.globs)
+    }
+}
+
+impl ExecutionHook for ArtifactHook {
+    fn after(
+        &self,
+        _command: &Command,
+        _context: &ExecutionContext,
+        success: bool,
+    ) -> MartyResult<()> {
+        if success {
+            self.collect()?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the artifacts of `task` on `project` are collected
+pub fn artifacts_dir(workspace_root: &Path, project: &str, task: &str) -> PathBuf {
+    workspace_root
+        .join(".marty")
+        .join("artifacts")
+        .join(task_logs::file_name(project))
+        .join(task_logs::file_name(task))
+}
+
+/// Replace `${VAR}` with the value from `env`, falling back to the process environment
+pub fn interpolate(input: &str, env: &BTreeMap<String, String>) -> MartyResult<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| MartyError::Task(format!("Unclosed '${{' in '{}'", input)))?;
+        let name = &after[..end];
+
+        let value = env
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+            .ok_or_else(|| {
+                MartyError::Task(format!(
+                    "Variable '{}' referenced in '{}' is not set",
+                    name, input
+                ))
+            })?;
+        output.push_str(&value);
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Create the executor selected by a task's configuration. Local commands use `shell`.
+pub fn from_config(config: Option<&ExecutorConfig>, shell: Shell) -> Box<dyn Executor> {
+    match config {
+        None | Some(ExecutorConfig::Local) => Box::new(LocalExecutor { shell }),
+        Some(ExecutorConfig::Docker { image, options }) => Box::new(DockerExecutor {
+            image: image.clone(),
+            options: options.clone().unwrap_or_default(),
+        }),
+        Some(ExecutorConfig::Remote { host, workdir }) => Box::new(RemoteExecutor {
+            host: host.clone(),
+            workdir: workdir.clone(),
+        }),
+        Some(ExecutorConfig::Wasm { runtime, dirs }) => Box::new(WasmExecutor {
+            runtime: runtime
+                .clone()
+                .unwrap_or_else(|| wasm::DEFAULT_RUNTIME.to_string()),
+            dirs: dirs.clone().unwrap_or_default(),
+        }),
+    }
+}
+
+/// Quote a string for POSIX shells
+pub(crate) fn shell_quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if is_safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Quote a string as a single argument in a command line run by `shell`
+pub(crate) fn quote_for(shell: Shell, value: &str) -> String {
+    match shell {
+        Shell::Sh | Shell::Bash => shell_quote(value),
+        Shell::Cmd => cmd_quote(value),
+        Shell::Powershell | Shell::Pwsh => powershell_quote(value),
+    }
+}
+
+/// Quote a string for cmd: first as the program receiving it splits its command line, then
+/// with `^` before every character cmd itself would interpret, quotes included
+fn cmd_quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./\\:+@".contains(c));
+    if is_safe {
+        return value.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in value.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+
+    let mut escaped = String::with_capacity(quoted.len());
+    for c in quoted.chars() {
+        if "^&|<>()%!\"".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quote a string for PowerShell, where nothing but `'` is special inside single quotes
+fn powershell_quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./\\:".contains(c));
+    if is_safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> ExecutionContext {
+        let mut env = BTreeMap::new();
+        env.insert("MARTY_TARGET_0".to_string(), "app".to_string());
+        ExecutionContext {
+            workspace_root: PathBuf::from("/work/space"),
+            working_dir: PathBuf::from("/work/space"),
+            targets: vec!["app".to_string()],
+            env,
+        }
+    }
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_shell_line_quotes_program_arguments() {
+        let invocation = Invocation::Program {
+            program: "echo".to_string(),
+            args: vec!["hello world".to_string(), "it's".to_string()],
+        };
+        assert_eq!(
+            invocation.to_shell_line(Shell::Sh),
+            "echo 'hello world' 'it'\\''s'"
+        );
+        assert_eq!(
+            invocation.to_shell_line(Shell::Pwsh),
+            "echo 'hello world' 'it''s'"
+        );
+    }
+
+    #[test]
+    fn test_arguments_are_quoted_for_each_shell() {
+        let value = "a b's";
+        assert_eq!(quote_for(Shell::Sh, value), "'a b'\\''s'");
+        assert_eq!(quote_for(Shell::Bash, value), "'a b'\\''s'");
+        assert_eq!(quote_for(Shell::Powershell, value), "'a b''s'");
+        assert_eq!(quote_for(Shell::Pwsh, "$HOME; rm"), "'$HOME; rm'");
+        assert_eq!(quote_for(Shell::Cmd, value), "^\"a b's^\"");
+        assert_eq!(
+            quote_for(Shell::Cmd, r#"say "hi" & %PATH%"#),
+            r#"^"say \^"hi\^" ^& ^%PATH^%^""#
+        );
+        assert_eq!(quote_for(Shell::Cmd, r"C:\dir\"), r"C:\dir\");
+        assert_eq!(quote_for(Shell::Cmd, r"C:\my dir\"), r#"^"C:\my dir\\^""#);
+        for shell in [Shell::Sh, Shell::Cmd, Shell::Pwsh] {
+            assert_eq!(quote_for(shell, "--release"), "--release");
+        }
+    }
+
+    #[test]
+    fn test_docker_executor_mounts_workspace_and_passes_env() {
+        let executor = from_config(
+            Some(&ExecutorConfig::Docker {
+                image: "rust:1".to_string(),
+                options: Some(vec!["--network=none".to_string()]),
+            }),
+            Shell::Sh,
+        );
+        let command = executor
+            .command(&Invocation::Shell("cargo build".to_string()), &context())
+            .unwrap();
+
+        assert_eq!(command.get_program(), "docker");
+        assert_eq!(
+            args(&command),
+            vec![
+                "run",
+                "--rm",
+                "-v",
+                "/work/space:/work/space",
+                "-w",
+                "/work/space",
+                "-e",
+                "MARTY_TARGET_0=app",
+                "--network=none",
+                "rust:1",
+                "sh",
+                "-c",
+                "cargo build",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_env_is_interpolated() {
+        let mut context = context();
+        context.env.insert(
+            "MARTY_PROJECT_DIR".to_string(),
+            "/work/space/app".to_string(),
+        );
+
+        let hook = TaskEnvHook(BTreeMap::from([
+            (
+                "OUT".to_string(),
+                "${MARTY_PROJECT_DIR}/dist/${MARTY_TARGET_0}".to_string(),
+            ),
+            ("PLAIN".to_string(), "$HOME stays for the shell".to_string()),
+        ]));
+        hook.before(&mut context).unwrap();
+        assert_eq!(context.env["OUT"], "/work/space/app/dist/app");
+        assert_eq!(context.env["PLAIN"], "$HOME stays for the shell");
+
+        let missing = TaskEnvHook(BTreeMap::from([(
+            "X".to_string(),
+            "${MARTY_SURELY_UNSET_VARIABLE}".to_string(),
+        )]));
+        assert!(missing.before(&mut context).is_err());
+        assert!(interpolate("${OPEN", &context.env).is_err());
+    }
+
+    #[test]
+    fn test_remote_executor_builds_ssh_command() {
+        let executor = from_config(
+            Some(&ExecutorConfig::Remote {
+                host: "builder".to_string(),
+                workdir: Some("/srv/repo".to_string()),
+            }),
+            Shell::Sh,
+        );
+        let command = executor
+            .command(&Invocation::Shell("make".to_string()), &context())
+            .unwrap();
+
+        assert_eq!(command.get_program(), "ssh");
+        assert_eq!(
+            args(&command),
+            vec![
+                "builder",
+                "cd /srv/repo && env MARTY_TARGET_0=app sh -c make"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wasm_executor_grants_only_listed_dirs() {
+        let executor = from_config(
+            Some(&ExecutorConfig::Wasm {
+                runtime: None,
+                dirs: Some(vec!["shared".to_string()]),
+            }),
+            Shell::Sh,
+        );
+        let command = executor
+            .command(
+                &Invocation::Shell("tool.wasm --check".to_string()),
+                &context(),
+            )
+            .unwrap();
+
+        assert_eq!(command.get_program(), wasm::DEFAULT_RUNTIME);
+        assert_eq!(
+            args(&command),
+            vec![
+                "run",
+                "--dir",
+                "/work/space",
+                "--dir",
+                "/work/space/shared",
+                "--env",
+                "MARTY_TARGET_0=app",
+                "tool.wasm",
+                "--check"
+            ]
+        );
+        assert!(executor
+            .command(&Invocation::Shell(" ".to_string()), &context())
+            .is_err());
+    }
+
+    #[test]
+    fn test_artifact_hook_copies_matching_files_after_success() {
+        let root = tempfile::tempdir().unwrap();
+        let project_dir = root.path().join("app");
+        std::fs::create_dir_all(project_dir.join("dist")).unwrap();
+        std::fs::write(project_dir.join("dist/app.js"), "js").unwrap();
+        std::fs::write(project_dir.join("notes.txt"), "notes").unwrap();
+
+        let hook = ArtifactHook::new(
+            root.path(),
+            "app",
+            &project_dir,
+            "build",
+            vec!["dist/**".to_string()],
+        );
+        let command = Command::new("true");
+        hook.after(&command, &context(), false).unwrap();
+        let target = artifacts_dir(root.path(), "app", "build");
+        assert!(!target.exists());
+
+        hook.after(&command, &context(), true).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(target.join("dist/app.js")).unwrap(),
+            "js"
+        );
+        assert!(!target.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_local_executor_uses_configured_shell() {
+        let shell_args = |shell: Shell| {
+            let command = from_config(None, shell)
+                .command(&Invocation::Shell("echo hi".to_string()), &context())
+                .unwrap();
+            let program = command.get_program().to_string_lossy().to_string();
+            (program, args(&command))
+        };
+
+        assert_eq!(
+            shell_args(Shell::Sh),
+            (
+                "sh".to_string(),
+                vec!["-c".to_string(), "echo hi".to_string()]
+            )
+        );
+        assert_eq!(
+            shell_args(Shell::Pwsh),
+            (
+                "pwsh".to_string(),
+                [
+                    "-NoLogo",
+                    "-NoProfile",
+                    "-NonInteractive",
+                    "-Command",
+                    "echo hi"
+                ]
+                .map(String::from)
+                .to_vec()
+            )
+        );
+        assert_eq!(shell_args(Shell::Cmd).0, "cmd");
+    }
+}
@@ -0,0 +1,50 @@
+//! Run commands inside a Docker container
+
+use std::process::Command;
+
+use super::{ExecutionContext, Executor, Invocation};
+use crate::types::MartyResult;
+
+/// Runs commands in a throwaway container of `image`.
+///
+/// The workspace is mounted at the same path as on the host so that paths in
+/// commands and environment variables stay valid inside the container.
+pub struct DockerExecutor {
+    pub image: String,
+    /// Extra arguments passed to `docker run` before the image name
+    pub options: Vec<String>,
+}
+
+impl Executor for DockerExecutor {
+    fn name(&self) -> &'static str {
+        "docker"
+    }
+
+    fn command(&self, invocation: &Invocation, context: &ExecutionContext) -> MartyResult<Command> {
+        let root = context.workspace_root.display().to_string();
+
+        let mut command = Command::new("docker");
+        command
+            .arg("run")
+            .arg("--rm")
+            .arg("-v")
+            .arg(format!("{}:{}", root, root))
+            .arg("-w")
+            .arg(&context.working_dir);
+        for (key, value) in &context.env {
+            command.arg("-e").arg(format!("{}={}", key, value));
+        }
+        command.args(&self.options).arg(&self.image);
+
+        match invocation {
+            Invocation::Shell(cmd) => {
+                command.arg("sh").arg("-c").arg(cmd);
+            }
+            Invocation::Program { program, args } => {
+                command.arg(program).args(args);
+            }
+        }
+
+        Ok(command)
+    }
+}
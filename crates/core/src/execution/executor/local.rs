@@ -0,0 +1,75 @@
+//! Run commands directly on the host
+
+use std::process::Command;
+
+use super::{ExecutionContext, Executor, Invocation};
+use crate::configs::workspace::Shell;
+use crate::types::MartyResult;
+
+/// Runs commands on the local machine, passing shell commands to the configured shell
+#[derive(Debug, Default)]
+pub struct LocalExecutor {
+    pub shell: Shell,
+}
+
+impl Executor for LocalExecutor {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn shell(&self) -> Shell {
+        self.shell
+    }
+
+    fn command(&self, invocation: &Invocation, context: &ExecutionContext) -> MartyResult<Command> {
+        let mut command = match invocation {
+            Invocation::Shell(cmd) => shell_command(self.shell, cmd),
+            Invocation::Program { program, args } => {
+                let mut command = Command::new(program);
+                command.args(args);
+                command
+            }
+        };
+
+        command.current_dir(&context.working_dir);
+        command.envs(&context.env);
+        Ok(command)
+    }
+}
+
+/// Build the process that runs `line` with `shell`
+pub fn shell_command(shell: Shell, line: &str) -> Command {
+    match shell {
+        Shell::Sh | Shell::Bash => {
+            let mut command = Command::new(if shell == Shell::Sh { "sh" } else { "bash" });
+            command.arg("-c").arg(line);
+            command
+        }
+        Shell::Cmd => {
+            let mut command = Command::new("cmd");
+            command.args(["/D", "/S", "/C"]);
+            // cmd does not parse its arguments like other programs: with /S it runs everything
+            // between the outer quotes verbatim, so the line must not be escaped again
+            #[cfg(windows)]
+            {
+                use std::os::windows::process::CommandExt;
+                command.raw_arg(format!("\"{}\"", line));
+            }
+            #[cfg(not(windows))]
+            command.arg(line);
+            command
+        }
+        Shell::Powershell | Shell::Pwsh => {
+            let program = if shell == Shell::Pwsh {
+                "pwsh"
+            } else {
+                "powershell"
+            };
+            let mut command = Command::new(program);
+            command
+                .args(["-NoLogo", "-NoProfile", "-NonInteractive", "-Command"])
+                .arg(line);
+            command
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Run commands on a remote host over SSH
+
+use std::process::Command;
+
+use super::{shell_quote, ExecutionContext, Executor, Invocation};
+use crate::configs::workspace::Shell;
+use crate::types::MartyResult;
+
+/// Runs commands on `host` via `ssh`.
+///
+/// The remote host is expected to have a checkout of the workspace at `workdir`
+/// (or at the same path as locally when no workdir is configured).
+pub struct RemoteExecutor {
+    pub host: String,
+    pub workdir: Option<String>,
+}
+
+impl Executor for RemoteExecutor {
+    fn name(&self) -> &'static str {
+        "remote"
+    }
+
+    fn command(&self, invocation: &Invocation, context: &ExecutionContext) -> MartyResult<Command> {
+        let workdir = self
+            .workdir
+            .clone()
+            .unwrap_or_else(|| context.working_dir.display().to_string());
+
+        let mut remote_line = format!("cd {} && env", shell_quote(&workdir));
+        for (key, value) in &context.env {
+            remote_line.push(' ');
+            remote_line.push_str(&shell_quote(&format!("{}={}", key, value)));
+        }
+        remote_line.push_str(" sh -c ");
+        remote_line.push_str(&shell_quote(&invocation.to_shell_line(Shell::Sh)));
+
+        let mut command = Command::new("ssh");
+        command.arg(&self.host).arg(remote_line);
+        Ok(command)
+    }
+}
@@ -0,0 +1,55 @@
+//! Run WebAssembly modules in a WASI sandbox
+
+use std::process::Command;
+
+use super::{ExecutionContext, Executor, Invocation};
+use crate::types::{MartyError, MartyResult};
+
+/// Default WASI runtime
+pub const DEFAULT_RUNTIME: &str = "wasmtime";
+
+/// Runs a task's command as a WASI module with `wasmtime run`.
+///
+/// The module only sees the command's working directory, at the same path as on the host,
+/// and the `dirs` it is granted on top. It has no network access and only the environment
+/// variables Marty sets for the command. Shell commands are split on whitespace into the
+/// module and its arguments, as there is no shell inside the sandbox.
+pub struct WasmExecutor {
+    /// Program implementing `run` like the wasmtime CLI
+    pub runtime: String,
+    /// Further directories the module may access, relative to the workspace root
+    pub dirs: Vec<String>,
+}
+
+impl Executor for WasmExecutor {
+    fn name(&self) -> &'static str {
+        "wasm"
+    }
+
+    fn command(&self, invocation: &Invocation, context: &ExecutionContext) -> MartyResult<Command> {
+        let (module, args) = match invocation {
+            Invocation::Shell(line) => {
+                let mut parts = line.split_whitespace().map(str::to_string);
+                let module = parts.next().ok_or_else(|| {
+                    MartyError::Task("The wasm executor needs a module to run".to_string())
+                })?;
+                (module, parts.collect())
+            }
+            Invocation::Program { program, args } => (program.clone(), args.clone()),
+        };
+
+        let mut command = Command::new(&self.runtime);
+        command.arg("run").arg("--dir").arg(&context.working_dir);
+        for dir in &self.dirs {
+            command.arg("--dir").arg(context.workspace_root.join(dir));
+        }
+        for (key, value) in &context.env {
+            command.arg("--env").arg(format!("{}={}", key, value));
+        }
+        command
+            .arg(module)
+            .args(args)
+            .current_dir(&context.working_dir);
+        Ok(command)
+    }
+}
@@ -0,0 +1,183 @@
+//! Notifications sent when a task run finishes
+//!
+//! Each entry under `notifications` in workspace.yml becomes a [`WebhookNotifier`], a
+//! [`RunHook`] that posts the [`RunSummary`] to Slack or a generic webhook. Long local
+//! runs can then report to a channel without a CI system in between.
+
+use reqwest::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use serde_json::json;
+
+use crate::configs::workspace::{NotificationConfig, NotifyOn};
+use crate::execution::runner::{RunHook, RunHookFuture, RunSummary};
+use crate::plugin_cache::expand_env_vars;
+use crate::types::{MartyError, MartyResult};
+
+/// Posts run summaries to a Slack incoming webhook or a generic JSON endpoint
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    config: NotificationConfig,
+    workspace_name: Option<String>,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: NotificationConfig, workspace_name: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+            workspace_name,
+        }
+    }
+
+    fn should_notify(&self, summary: &RunSummary) -> bool {
+        let on = match &self.config {
+            NotificationConfig::Slack { on, .. } | NotificationConfig::Webhook { on, .. } => {
+                on.unwrap_or_default()
+            }
+        };
+        match on {
+            NotifyOn::Always => true,
+            NotifyOn::Success => summary.success,
+            NotifyOn::Failure => !summary.success,
+        }
+    }
+
+    async fn send(&self, summary: &RunSummary) -> MartyResult<()> {
+        let (url, body, headers) = match &self.config {
+            NotificationConfig::Slack { url, .. } => (
+                url,
+                json!({ "text": slack_message(summary, self.workspace_name.as_deref()) }),
+                None,
+            ),
+            NotificationConfig::Webhook { url, headers, .. } => (
+                url,
+                json!({ "workspace": self.workspace_name, "run": summary }),
+                headers.as_ref(),
+            ),
+        };
+
+        let mut request = self
+            .client
+            .post(expand(url)?)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        for (name, value) in headers.into_iter().flatten() {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|e| MartyError::Config(format!("Invalid header '{}': {}", name, e)))?;
+            let value = HeaderValue::try_from(expand(value)?).map_err(|e| {
+                MartyError::Config(format!("Invalid value for header '{}': {}", name, e))
+            })?;
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| MartyError::Task(format!("Failed to send notification: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(MartyError::Task(format!(
+                "Notification endpoint responded with {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl RunHook for WebhookNotifier {
+    fn name(&self) -> &str {
+        match self.config {
+            NotificationConfig::Slack { .. } => "slack notification",
+            NotificationConfig::Webhook { .. } => "webhook notification",
+        }
+    }
+
+    fn on_run_complete<'a>(&'a self, summary: &'a RunSummary) -> RunHookFuture<'a> {
+        Box::pin(async move {
+            if !self.should_notify(summary) {
+                return Ok(());
+            }
+            self.send(summary).await
+        })
+    }
+}
+
+fn expand(value: &str) -> MartyResult<String> {
+    expand_env_vars(value).map_err(|e| MartyError::Config(e.to_string()))
+}
+
+/// One-line summary of a run for chat messages
+fn slack_message(summary: &RunSummary, workspace_name: Option<&str>) -> String {
+    let status = if summary.success {
+        ":white_check_mark: passed"
+    } else {
+        ":x: failed"
+    };
+    let workspace = workspace_name
+        .map(|name| format!(" in {}", name))
+        .unwrap_or_default();
+
+    let mut message = format!(
+        "marty {} {}{} ({}) after {:.1}s: {} succeeded, {} cached, {} failed",
+        summary.task,
+        status,
+        workspace,
+        summary.targets.join(", "),
+        summary.duration.as_secs_f64(),
+        summary.succeeded.len(),
+        summary.cached.len(),
+        summary.failed.len()
+    );
+    if !summary.failed.is_empty() {
+        message.push_str(&format!("\nFailed: {}", summary.failed.join(", ")));
+    }
+    if !summary.blocked.is_empty() {
+        message.push_str(&format!("\nBlocked: {}", summary.blocked.join(", ")));
+    }
+    if !summary.not_run.is_empty() {
+        message.push_str(&format!("\nNot run: {}", summary.not_run.join(", ")));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn summary(success: bool) -> RunSummary {
+        RunSummary {
+            task: "build".to_string(),
+            targets: vec!["app".to_string()],
+            succeeded: vec!["lib".to_string()],
+            cached: Vec::new(),
+            failed: if success {
+                Vec::new()
+            } else {
+                vec!["app".to_string()]
+            },
+            not_run: Vec::new(),
+            blocked: Vec::new(),
+            duration: Duration::from_millis(1500),
+            success,
+            projects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_notify_on_filters_by_outcome() {
+        let notifier = WebhookNotifier::new(
+            NotificationConfig::Slack {
+                url: "http://127.0.0.1:9".to_string(),
+                on: Some(NotifyOn::Failure),
+            },
+            None,
+        );
+        assert!(!notifier.should_notify(&summary(true)));
+        assert!(notifier.should_notify(&summary(false)));
+
+        let message = slack_message(&summary(false), Some("shop"));
+        assert!(message.starts_with("marty build :x: failed in shop (app) after 1.5s"));
+        assert!(message.contains("Failed: app"));
+    }
+}
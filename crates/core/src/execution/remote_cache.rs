@@ -0,0 +1,459 @@
+//! Remote task cache backends
+//!
+//! A [`RemoteCache`] stores cache entries outside the machine so CI runners and developers
+//! can share results. The runner fetches an entry from the remote when it is missing
+//! locally, and uploads new entries through the [`UploadQueue`](super::upload::UploadQueue).
+//! Adding a backend only requires implementing this trait and registering it in
+//! [`from_config`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::{StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+use crate::configs::workspace::{RemoteCacheConfig, S3CacheConfig};
+use crate::types::{MartyError, MartyResult};
+
+/// Future returned by [`RemoteCache`] operations. Errors are plain messages because remote
+/// cache problems are only ever reported as warnings.
+pub type RemoteCacheFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// Storage for cache entries shared between machines
+pub trait RemoteCache: Send + Sync {
+    /// Short name used in messages
+    fn name(&self) -> &'static str;
+
+    /// Fetch an entry, returning `None` when the remote doesn't have it
+    fn get<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> RemoteCacheFuture<'a, Option<Vec<u8>>>;
+
+    /// Store an entry
+    fn put<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+        body: Vec<u8>,
+    ) -> RemoteCacheFuture<'a, ()>;
+}
+
+/// Create the remote cache backend selected in the workspace configuration
+pub fn from_config(config: &RemoteCacheConfig) -> MartyResult<Arc<dyn RemoteCache>> {
+    match (&config.url, &config.s3) {
+        (Some(url), None) => Ok(Arc::new(HttpRemoteCache::new(url))),
+        (None, Some(s3)) => Ok(Arc::new(S3RemoteCache::from_env(s3)?)),
+        (Some(_), Some(_)) => Err(MartyError::Config(
+            "Remote cache must set either 'url' or 's3', not both".to_string(),
+        )),
+        (None, None) => Err(MartyError::Config(
+            "Remote cache must set either 'url' or 's3'".to_string(),
+        )),
+    }
+}
+
+/// Plain HTTP cache: `GET`/`PUT {url}/{namespace}/{key}`
+pub struct HttpRemoteCache {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl HttpRemoteCache {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn url(&self, namespace: &str, key: &str) -> String {
+        format!("{}/{}/{}", self.base_url, namespace, key)
+    }
+}
+
+impl RemoteCache for HttpRemoteCache {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn get<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> RemoteCacheFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .get(self.url(namespace, key))
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            read_response(response).await
+        })
+    }
+
+    fn put<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+        body: Vec<u8>,
+    ) -> RemoteCacheFuture<'a, ()> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .put(self.url(namespace, key))
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            check_status(response.status())
+        })
+    }
+}
+
+/// S3 or an S3-compatible store, authenticated with AWS Signature Version 4.
+///
+/// Credentials are read from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and the optional
+/// `AWS_SESSION_TOKEN`.
+pub struct S3RemoteCache {
+    client: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    prefix: String,
+    credentials: AwsCredentials,
+}
+
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl S3RemoteCache {
+    pub fn from_env(config: &S3CacheConfig) -> MartyResult<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let (Some(access_key_id), Some(secret_access_key)) =
+            (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY"))
+        else {
+            return Err(MartyError::Config(
+                "S3 remote cache needs AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY".to_string(),
+            ));
+        };
+
+        let endpoint = config
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", config.region));
+        let endpoint = Url::parse(&endpoint).map_err(|e| {
+            MartyError::Config(format!("Invalid S3 endpoint '{}': {}", endpoint, e))
+        })?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            bucket: config.bucket.clone(),
+            region: config.region.clone(),
+            prefix: config.prefix.clone().unwrap_or_default(),
+            credentials: AwsCredentials {
+                access_key_id,
+                secret_access_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            },
+        })
+    }
+
+    /// Build a signed request for an object
+    fn request(
+        &self,
+        method: reqwest::Method,
+        namespace: &str,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let object_key = format!("{}{}/{}", self.prefix, namespace, key);
+        let path = format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            uri_encode(&self.bucket),
+            object_key
+                .split('/')
+                .map(uri_encode)
+                .collect::<Vec<_>>()
+                .join("/")
+        );
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(format!("S3 endpoint {} has no host", self.endpoint)),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_secs();
+        let headers = sign_v4(
+            &SigningRequest {
+                method: method.as_str(),
+                host: &host,
+                path: url.path(),
+                payload_hash: &hex(&Sha256::digest(&body)),
+                region: &self.region,
+                service: "s3",
+                timestamp: now,
+            },
+            &self.credentials,
+        );
+
+        let mut request = self.client.request(method, url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        Ok(request)
+    }
+}
+
+impl RemoteCache for S3RemoteCache {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    fn get<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> RemoteCacheFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            let response = self
+                .request(reqwest::Method::GET, namespace, key, Vec::new())?
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            read_response(response).await
+        })
+    }
+
+    fn put<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+        body: Vec<u8>,
+    ) -> RemoteCacheFuture<'a, ()> {
+        Box::pin(async move {
+            let response = self
+                .request(reqwest::Method::PUT, namespace, key, body)?
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            check_status(response.status())
+        })
+    }
+}
+
+async fn read_response(response: reqwest::Response) -> Result<Option<Vec<u8>>, String> {
+    // S3 answers 403 for missing keys when the caller can't list the bucket
+    if matches!(
+        response.status(),
+        StatusCode::NOT_FOUND | StatusCode::FORBIDDEN
+    ) {
+        return Ok(None);
+    }
+    check_status(response.status())?;
+    let body = response.bytes().await.map_err(|e| e.to_string())?;
+    Ok(Some(body.to_vec()))
+}
+
+fn check_status(status: StatusCode) -> Result<(), String> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(format!("server responded with {}", status))
+    }
+}
+
+/// The parts of a request covered by a Signature Version 4 signature
+struct SigningRequest<'a> {
+    method: &'a str,
+    host: &'a str,
+    /// Already URI-encoded path
+    path: &'a str,
+    payload_hash: &'a str,
+    region: &'a str,
+    service: &'a str,
+    /// Seconds since the Unix epoch
+    timestamp: u64,
+}
+
+/// Compute the headers that authenticate a request with AWS Signature Version 4
+fn sign_v4(request: &SigningRequest, credentials: &AwsCredentials) -> Vec<(&'static str, String)> {
+    let amz_date = amz_date(request.timestamp);
+    let date = &amz_date[..8];
+
+    let mut headers = vec![
+        ("host", request.host.to_string()),
+        ("x-amz-content-sha256", request.payload_hash.to_string()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        request.method, request.path, canonical_headers, signed_headers, request.payload_hash
+    );
+
+    let scope = format!(
+        "{}/{}/{}/aws4_request",
+        date, request.region, request.service
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = signing_key(
+        &credentials.secret_access_key,
+        date,
+        request.region,
+        request.service,
+    );
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    // reqwest sets the host header itself
+    headers.retain(|(name, _)| *name != "host");
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let key = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Percent-encode everything except unreserved characters, as SigV4 requires
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Format a Unix timestamp as `YYYYMMDD'T'HHMMSS'Z'`
+fn amz_date(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sigv4_primitives_match_published_vectors() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Signing key example from the AWS Signature Version 4 documentation
+        assert_eq!(
+            hex(&signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(amz_date(1_329_264_000), "20120215T000000Z");
+        assert_eq!(amz_date(1_709_210_096), "20240229T123456Z");
+        assert_eq!(uri_encode("a b/c"), "a%20b%2Fc");
+    }
+
+    #[test]
+    fn test_remote_cache_config_needs_exactly_one_backend() {
+        let config: RemoteCacheConfig = serde_yaml::from_str("enabled: true").unwrap();
+        assert!(from_config(&config).is_err());
+
+        let config: RemoteCacheConfig =
+            serde_yaml::from_str("url: http://cache.local\ns3:\n  bucket: b\n  region: r").unwrap();
+        assert!(from_config(&config).is_err());
+
+        let config: RemoteCacheConfig = serde_yaml::from_str("url: http://cache.local").unwrap();
+        assert_eq!(from_config(&config).unwrap().name(), "http");
+    }
+}
@@ -0,0 +1,286 @@
+//! Resource usage of task commands
+//!
+//! On Unix, commands are reaped with `wait4`, which reports the peak resident set size
+//! and the CPU time of the command and of every process it waited for, such as the
+//! programs a shell runs.
+//!
+//! On Windows, every command is put in a job object right after it is spawned. The job's
+//! accounting covers all processes in it: their total user and kernel time, and the
+//! largest committed memory of any single one (`PeakProcessMemoryUsed`), which stands in
+//! for the resident set size. Processes the command starts before it joins the job are
+//! not counted. Other platforms report no usage.
+
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Resources a task's commands used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Largest resident set size of any single process
+    pub peak_rss_bytes: u64,
+    /// User and system CPU time of all processes together
+    #[serde(
+        serialize_with = "super::runner::serialize_millis",
+        deserialize_with = "deserialize_millis",
+        rename = "cpu_ms"
+    )]
+    pub cpu_time: Duration,
+}
+
+impl ResourceUsage {
+    /// Usage of two commands that ran one after the other
+    pub fn combine(self, other: ResourceUsage) -> ResourceUsage {
+        ResourceUsage {
+            peak_rss_bytes: self.peak_rss_bytes.max(other.peak_rss_bytes),
+            cpu_time: self.cpu_time + other.cpu_time,
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_rusage(rusage: &libc::rusage) -> Self {
+        let time = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+        };
+        // macOS reports bytes, everything else kilobytes
+        let max_rss = rusage.ru_maxrss.max(0) as u64;
+        let peak_rss_bytes = if cfg!(target_os = "macos") {
+            max_rss
+        } else {
+            max_rss * 1024
+        };
+        ResourceUsage {
+            peak_rss_bytes,
+            cpu_time: time(rusage.ru_utime) + time(rusage.ru_stime),
+        }
+    }
+}
+
+fn deserialize_millis<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+/// Adds up the usage of every command run for one project's task, retries and
+/// dependencies included
+#[derive(Debug, Clone, Default)]
+pub struct ResourceMeter(Arc<Mutex<Option<ResourceUsage>>>);
+
+impl ResourceMeter {
+    pub fn add(&self, usage: ResourceUsage) {
+        if let Ok(mut total) = self.0.lock() {
+            *total = Some(total.map_or(usage, |total| total.combine(usage)));
+        }
+    }
+
+    /// Usage so far; `None` if no command reported any
+    pub fn total(&self) -> Option<ResourceUsage> {
+        self.0.lock().ok().and_then(|total| *total)
+    }
+}
+
+/// Start collecting the usage of a just spawned command and of every process it starts
+pub(crate) fn track(_child: &Child) {
+    #[cfg(windows)]
+    job::track(_child);
+}
+
+/// Kill a tracked command together with every process it started. Returns false where
+/// that isn't possible, leaving the caller to kill the command itself. Unix callers signal
+/// the command's process group instead.
+#[cfg(not(unix))]
+pub(crate) fn kill_tree(_child: &Child) -> bool {
+    #[cfg(windows)]
+    return job::terminate(_child);
+    #[cfg(not(windows))]
+    false
+}
+
+/// Wait for a child to exit, or with `block` unset only check whether it has, returning
+/// its exit status and, where the platform reports it, its resource usage
+pub(crate) fn wait_child(
+    child: &mut Child,
+    block: bool,
+) -> io::Result<Option<(ExitStatus, Option<ResourceUsage>)>> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        let flags = if block { 0 } else { libc::WNOHANG };
+        let mut status = 0;
+        // SAFETY: rusage is plain data that wait4 fills in
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            // SAFETY: the pointers are valid for the duration of the call. Reaping the
+            // child here means it must not be waited for through `Child` again.
+            let pid =
+                unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, flags, &mut rusage) };
+            match pid {
+                0 => return Ok(None),
+                -1 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() != io::ErrorKind::Interrupted {
+                        return Err(error);
+                    }
+                }
+                _ => {
+                    return Ok(Some((
+                        ExitStatus::from_raw(status),
+                        Some(ResourceUsage::from_rusage(&rusage)),
+                    )))
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let status = if block {
+            Some(child.wait()?)
+        } else {
+            child.try_wait()?
+        };
+        #[cfg(windows)]
+        return Ok(status.map(|status| (status, job::finish(child))));
+        #[cfg(not(windows))]
+        Ok(status.map(|status| (status, None)))
+    }
+}
+
+#[cfg(windows)]
+mod job {
+    use std::collections::BTreeMap;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicAccountingInformation,
+        JobObjectExtendedLimitInformation, QueryInformationJobObject, TerminateJobObject,
+        JOBOBJECT_BASIC_ACCOUNTING_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    };
+
+    use super::ResourceUsage;
+
+    /// Job objects of the running commands, by process id
+    static JOBS: Mutex<BTreeMap<u32, Job>> = Mutex::new(BTreeMap::new());
+
+    struct Job(HANDLE);
+
+    // SAFETY: job handles can be used and closed from any thread
+    unsafe impl Send for Job {}
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: the handle is owned by this value and closed only here
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    pub(super) fn track(child: &Child) {
+        // SAFETY: a null name and security attributes create an anonymous job
+        let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if handle.is_null() {
+            return;
+        }
+        let job = Job(handle);
+        // SAFETY: both handles are valid while `job` and `child` are alive
+        if unsafe { AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) } == 0 {
+            return;
+        }
+        if let Ok(mut jobs) = JOBS.lock() {
+            jobs.insert(child.id(), job);
+        }
+    }
+
+    pub(super) fn terminate(child: &Child) -> bool {
+        let Ok(jobs) = JOBS.lock() else {
+            return false;
+        };
+        // SAFETY: the job handle stays open while it is in the map
+        jobs.get(&child.id())
+            .is_some_and(|job| unsafe { TerminateJobObject(job.0, 1) } != 0)
+    }
+
+    /// Usage of every process that ran in the exited command's job, which is released
+    pub(super) fn finish(child: &Child) -> Option<ResourceUsage> {
+        let job = JOBS.lock().ok()?.remove(&child.id())?;
+        // SAFETY: both structures are plain data
+        let mut accounting: JOBOBJECT_BASIC_ACCOUNTING_INFORMATION = unsafe { std::mem::zeroed() };
+        let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+        // SAFETY: each buffer is valid for writes of the size passed along with it
+        let queried = unsafe {
+            QueryInformationJobObject(
+                job.0,
+                JobObjectBasicAccountingInformation,
+                &mut accounting as *mut _ as *mut _,
+                std::mem::size_of_val(&accounting) as u32,
+                std::ptr::null_mut(),
+            ) != 0
+                && QueryInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &mut limits as *mut _ as *mut _,
+                    std::mem::size_of_val(&limits) as u32,
+                    std::ptr::null_mut(),
+                ) != 0
+        };
+        if !queried {
+            return None;
+        }
+        // Times are counted in ticks of 100 nanoseconds
+        let ticks = (accounting.TotalUserTime + accounting.TotalKernelTime).max(0) as u64;
+        Some(ResourceUsage {
+            peak_rss_bytes: limits.PeakProcessMemoryUsed as u64,
+            cpu_time: Duration::from_nanos(ticks.saturating_mul(100)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_keeps_peak_and_sums_cpu_time() {
+        let meter = ResourceMeter::default();
+        assert_eq!(meter.total(), None);
+        meter.add(ResourceUsage {
+            peak_rss_bytes: 300,
+            cpu_time: Duration::from_millis(20),
+        });
+        meter.clone().add(ResourceUsage {
+            peak_rss_bytes: 100,
+            cpu_time: Duration::from_millis(5),
+        });
+        assert_eq!(
+            meter.total(),
+            Some(ResourceUsage {
+                peak_rss_bytes: 300,
+                cpu_time: Duration::from_millis(25),
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_usage_of_a_finished_command() {
+        let mut child = std::process::Command::new("sh")
+            .args([
+                "-c",
+                "i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done; exit 3",
+            ])
+            .spawn()
+            .unwrap();
+        let (status, usage) = wait_child(&mut child, true).unwrap().unwrap();
+        assert_eq!(status.code(), Some(3));
+        let usage = usage.unwrap();
+        assert!(usage.peak_rss_bytes > 0);
+        assert!(usage.cpu_time > Duration::ZERO);
+    }
+}
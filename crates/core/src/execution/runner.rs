@@ -1,31 +1,292 @@
 //! High-level task runner
-//! 
+//!
 //! This module provides the main task execution logic that coordinates command execution,
 //! dependency resolution, and parallel execution.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use colored::*;
+use marty_plugin_protocol::MartyPlugin;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::Semaphore;
 
-use crate::configs::tasks::{Command as TaskCommand, TaskConfig};
-use crate::execution::command::CommandExecutor;
+use crate::colors::project_color;
+use crate::configs::tasks::{Command as TaskCommand, MissingOutputs, TaskConfig, TaskDependency};
+use crate::configs::workspace::Shell;
+use crate::execution::cache::{self, TaskCache};
+use crate::execution::command::{CommandExecutor, RetryPolicy};
 use crate::execution::dependencies::group_by_dependency_levels;
-use crate::tasks::get_project_color;
+use crate::execution::executor::{self, ArtifactHook, EnvHook, ProjectDirHook, TaskEnvHook};
+use crate::execution::resources::{ResourceMeter, ResourceUsage};
+use crate::execution::upload::UploadReport;
+use crate::messages::{self, Message};
+use crate::progress::{ProgressEvent, ProgressSink};
+use crate::results::TaskRunResult;
+use crate::run_history::{RecordHook, RunRecord};
+use crate::task_logs::TaskLogs;
 use crate::types::{MartyError, MartyResult};
 use crate::workspace::{get_recursive_dependencies, Workspace};
 
 /// Configuration for the task runner
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct TaskRunnerConfig {
-    #[allow(dead_code)] // Will be used when parallel execution is implemented
+    /// Run the projects of a dependency level at the same time. Only takes effect on a
+    /// multi-thread Tokio runtime; on a current-thread one projects run one by one.
     pub enable_parallel_execution: bool,
+    /// Upper bound on projects running at the same time when parallel execution is
+    /// enabled. Defaults to the number of available CPUs.
+    pub max_concurrency: Option<usize>,
+    /// Keep running projects that do not depend on a failed project, and report all
+    /// failures at the end instead of stopping at the first one
+    pub keep_going: bool,
+    /// When keeping going, still attempt projects depending on a failed project instead
+    /// of blocking them
+    pub run_blocked: bool,
+    /// When keeping going, stop starting projects once this many have failed
+    pub max_failures: Option<usize>,
+    /// When not keeping going, stop the commands still running on other projects once one
+    /// fails instead of letting them finish
+    pub cancel_on_failure: bool,
+    /// Extra environment variables for every command of the run
+    pub env: BTreeMap<String, String>,
+    /// Arguments appended to the command of the run's task on each project, but not to
+    /// the commands of its dependencies
+    pub extra_args: Vec<String>,
+    /// Print the resolved commands of every project instead of running them
+    pub dry_run: bool,
+    /// Run only on the targets, not on the projects they depend on
+    pub only_targets: bool,
+    /// Run commands in each project's directory instead of the workspace root
+    pub in_project_dirs: bool,
+    /// Shell that runs string commands locally
+    pub shell: Shell,
+    /// Receives every [`RunEvent`] and the output of every command
+    pub progress: ProgressSink,
+    /// Save each project's task output to a log file
+    pub task_logs: Option<TaskLogs>,
+    /// Plugins that run the tasks naming them with `plugin:`
+    pub plugins: TaskPlugins,
+}
+
+/// Plugins that run tasks themselves, by key. Cloning shares the same plugins.
+#[derive(Clone, Default)]
+pub struct TaskPlugins(Arc<BTreeMap<String, Arc<dyn MartyPlugin>>>);
+
+impl TaskPlugins {
+    pub fn new(plugins: Vec<Box<dyn MartyPlugin>>) -> Self {
+        Self(Arc::new(
+            plugins
+                .into_iter()
+                .map(|plugin| (plugin.key().to_string(), Arc::from(plugin)))
+                .collect(),
+        ))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&dyn MartyPlugin> {
+        self.0.get(key).map(|plugin| plugin.as_ref())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+impl std::fmt::Debug for TaskPlugins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TaskPlugins")
+            .field(&self.0.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Outcome of a whole task run, handed to [`RunHook`]s once it finishes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSummary {
+    pub task: String,
+    /// Projects the run was started for (dependencies are run as well)
+    pub targets: Vec<String>,
+    pub succeeded: Vec<String>,
+    /// Projects skipped because their cached result was still valid
+    pub cached: Vec<String>,
+    pub failed: Vec<String>,
+    /// Projects never started because an earlier project failed (or, when keeping going,
+    /// because `max_failures` projects failed)
+    pub not_run: Vec<String>,
+    /// Projects not attempted while keeping going because a project they depend on failed
+    /// or was blocked itself
+    pub blocked: Vec<String>,
+    #[serde(serialize_with = "serialize_millis", rename = "duration_ms")]
+    pub duration: Duration,
+    pub success: bool,
+    /// Every project of the run, in the order they finished
+    pub projects: Vec<ProjectRunReport>,
+}
+
+impl RunSummary {
+    /// Add a finished project, handing back its error
+    fn record(
+        &mut self,
+        project: String,
+        duration: Duration,
+        resources: Option<ResourceUsage>,
+        outcome: MartyResult<ProjectOutcome>,
+        cache_enabled: bool,
+    ) -> Option<(String, MartyError)> {
+        let (status, error) = match outcome {
+            Ok(ProjectOutcome::Ran) => {
+                self.succeeded.push(project.clone());
+                (ProjectRunStatus::Succeeded, None)
+            }
+            Ok(ProjectOutcome::Cached) => {
+                self.cached.push(project.clone());
+                (ProjectRunStatus::Cached, None)
+            }
+            Err(e) => {
+                self.failed.push(project.clone());
+                (ProjectRunStatus::Failed, Some(e))
+            }
+        };
+        self.projects.push(ProjectRunReport {
+            project: project.clone(),
+            task: self.task.clone(),
+            duration,
+            status,
+            cache_hit: cache_enabled.then_some(status == ProjectRunStatus::Cached),
+            resources,
+        });
+        error.map(|e| (project, e))
+    }
+
+    fn record_not_run(&mut self, project: String) {
+        self.record_skipped(project, ProjectRunStatus::NotRun);
+    }
+
+    fn record_blocked(&mut self, project: String) {
+        self.record_skipped(project, ProjectRunStatus::Blocked);
+    }
+
+    fn record_skipped(&mut self, project: String, status: ProjectRunStatus) {
+        self.projects.push(ProjectRunReport {
+            project: project.clone(),
+            task: self.task.clone(),
+            duration: Duration::ZERO,
+            status,
+            cache_hit: None,
+            resources: None,
+        });
+        match status {
+            ProjectRunStatus::Blocked => self.blocked.push(project),
+            _ => self.not_run.push(project),
+        }
+    }
+}
+
+/// How the task ended for one project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectRunStatus {
+    Succeeded,
+    Cached,
+    Failed,
+    NotRun,
+    Blocked,
+}
+
+/// Timing and outcome of the task on one project
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRunReport {
+    pub project: String,
+    pub task: String,
+    #[serde(serialize_with = "serialize_millis", rename = "duration_ms")]
+    pub duration: Duration,
+    pub status: ProjectRunStatus,
+    /// Whether the cached result was used; `None` without a cache or when the project never ran
+    pub cache_hit: Option<bool>,
+    /// Peak memory and CPU time of the project's commands; `None` when no command ran or
+    /// the platform doesn't report them
+    pub resources: Option<ResourceUsage>,
+}
+
+pub(crate) fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// Future returned by [`RunHook::on_run_complete`]
+pub type RunHookFuture<'a> = Pin<Box<dyn Future<Output = MartyResult<()>> + Send + 'a>>;
+
+/// Logic that runs once after a whole task run, whether it succeeded or not.
+///
+/// Errors are reported as warnings and never change the outcome of the run.
+pub trait RunHook: Send + Sync {
+    /// Short name used in warnings
+    fn name(&self) -> &str;
+
+    /// Called as the run progresses. Projects of a level may run on separate threads, so
+    /// events of different projects can interleave.
+    fn on_event(&self, _event: &RunEvent) {}
+
+    fn on_run_complete<'a>(&'a self, summary: &'a RunSummary) -> RunHookFuture<'a>;
+}
+
+/// Progress of a task run, reported to [`RunHook::on_event`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    TaskStarted {
+        project: String,
+        task: String,
+    },
+    /// The project's result was taken from the local or remote cache
+    CacheHit {
+        project: String,
+        task: String,
+        remote: bool,
+    },
+    TaskFinished {
+        project: String,
+        task: String,
+        #[serde(serialize_with = "serialize_millis", rename = "duration_ms")]
+        duration: Duration,
+        status: ProjectRunStatus,
+        resources: Option<ResourceUsage>,
+    },
+    Error {
+        project: String,
+        task: String,
+        message: String,
+    },
+}
+
+/// How a single project's task finished
+enum ProjectOutcome {
+    Ran,
+    Cached,
 }
 
 /// High-level task runner that coordinates task execution across projects
 pub struct TaskRunner<'a> {
     workspace: &'a Workspace,
-    #[allow(dead_code)] // Will be used when parallel execution is implemented
     config: TaskRunnerConfig,
+    cache: Option<TaskCache>,
+    run_hooks: Vec<Box<dyn RunHook>>,
+    /// Everything executed during the current run, for `marty repro`
+    record: Arc<Mutex<RunRecord>>,
+    /// `project:task` pairs that already ran, so cross-project dependencies run once
+    completed: Mutex<HashSet<String>>,
+    /// Set once a failure stops the run with `cancel_on_failure`
+    cancel: Arc<AtomicBool>,
+    /// Resources used by each project that ran, until its outcome is recorded
+    usage: Mutex<HashMap<String, ResourceUsage>>,
 }
 
 impl<'a> TaskRunner<'a> {
@@ -33,10 +294,37 @@ impl<'a> TaskRunner<'a> {
         Self {
             workspace,
             config: TaskRunnerConfig::default(),
+            cache: None,
+            run_hooks: Vec::new(),
+            record: Arc::new(Mutex::new(RunRecord::default())),
+            completed: Mutex::new(HashSet::new()),
+            cancel: Arc::new(AtomicBool::new(false)),
+            usage: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Add a hook that runs once the whole task run has finished
+    pub fn with_run_hook(mut self, hook: Box<dyn RunHook>) -> Self {
+        self.run_hooks.push(hook);
+        self
+    }
+
+    pub fn with_config(mut self, config: TaskRunnerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set extra environment variables for every command of the run
+    pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
+        self.config.env = env;
+        self
+    }
 
+    /// Skip tasks whose results are already recorded in the given cache
+    pub fn with_cache(mut self, cache: TaskCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
 
     /// Run a task on targets with proper dependency resolution and parallel execution
     pub async fn run_task_on_targets(
@@ -44,7 +332,7 @@ impl<'a> TaskRunner<'a> {
         task_name: &str,
         targets: &[String],
         all_tasks: &HashMap<String, TaskConfig>,
-    ) -> MartyResult<()> {
+    ) -> MartyResult<TaskRunResult> {
         // Verify the base task exists (either workspace-level or at least one project has it)
         let base_task_exists = all_tasks.contains_key(task_name)
             || all_tasks
@@ -56,21 +344,332 @@ impl<'a> TaskRunner<'a> {
         }
 
         // Get all projects that need this task run on them (targets + their dependencies)
-        let all_projects = get_recursive_dependencies(self.workspace, targets).map_err(MartyError::Task)?;
+        let all_projects = if self.config.only_targets {
+            targets.to_vec()
+        } else {
+            get_recursive_dependencies(self.workspace, targets).map_err(MartyError::Task)?
+        };
+
+        // Group projects by dependency level (topological levels). Persistent tasks never
+        // finish, so all of them start at once.
+        let persistent = !self.config.dry_run && Self::is_persistent(task_name, all_tasks);
+        let levels = match group_by_dependency_levels(self.workspace, &all_projects)? {
+            levels if persistent => vec![levels.into_iter().flatten().collect()],
+            levels => levels,
+        };
+
+        // Projects of a level run on helper threads that drive their futures through the
+        // caller's runtime, which only a multi-thread runtime supports: a current-thread
+        // runtime can't run timers and I/O for other threads, so its levels run one by one
+        let multi_thread = Handle::current().runtime_flavor() == RuntimeFlavor::MultiThread;
+        if persistent && !multi_thread && all_projects.len() > 1 {
+            return Err(MartyError::Task(format!(
+                "Persistent task '{}' runs on several projects at once, which needs a multi-thread Tokio runtime",
+                task_name
+            )));
+        }
 
-        // Group projects by dependency level (topological levels)
-        let levels = group_by_dependency_levels(self.workspace, &all_projects)?;
+        if let Ok(mut record) = self.record.lock() {
+            *record = RunRecord::start(&self.workspace.root, task_name, targets);
+        }
 
         // Execute tasks level by level
+        let started = Instant::now();
+        let mut summary = RunSummary {
+            task: task_name.to_string(),
+            targets: targets.to_vec(),
+            succeeded: Vec::new(),
+            cached: Vec::new(),
+            failed: Vec::new(),
+            not_run: Vec::new(),
+            blocked: Vec::new(),
+            duration: Duration::ZERO,
+            success: true,
+            projects: Vec::new(),
+        };
+        let cache_enabled = self.cache.is_some();
+        let mut errors = Vec::new();
         for level in levels {
-            // Within each level, run tasks sequentially for now
-            // TODO: Implement parallel execution within levels when config.enable_parallel_execution is true
+            let parallel = multi_thread && (self.config.enable_parallel_execution || persistent);
+            if parallel && !self.config.dry_run && level.len() > 1 {
+                // Projects of one level never depend on each other
+                let runnable: Vec<String> = level
+                    .into_iter()
+                    .filter(|project| errors.is_empty() || !self.skip(project, &mut summary))
+                    .collect();
+                let limit = if persistent {
+                    runnable.len()
+                } else {
+                    self.concurrency_limit()
+                };
+                let outcomes = self.run_level_in_parallel(
+                    task_name,
+                    &runnable,
+                    all_tasks,
+                    limit,
+                    summary.failed.len(),
+                );
+                for (project_name, outcome) in runnable.into_iter().zip(outcomes) {
+                    let error = match outcome {
+                        None => {
+                            summary.record_not_run(project_name);
+                            None
+                        }
+                        Some((duration, outcome)) => {
+                            let resources = self.take_usage(&project_name);
+                            summary.record(
+                                project_name,
+                                duration,
+                                resources,
+                                outcome,
+                                cache_enabled,
+                            )
+                        }
+                    };
+                    self.emit_finished(&summary, error.as_ref());
+                    errors.extend(error);
+                }
+                continue;
+            }
             for project_name in level {
-                self.run_task_on_project(task_name, &project_name, all_tasks).await?;
+                if !errors.is_empty() && self.skip(&project_name, &mut summary) {
+                    continue;
+                }
+                let project_started = Instant::now();
+                let outcome = self
+                    .run_task_on_project(task_name, &project_name, all_tasks)
+                    .await;
+                let resources = self.take_usage(&project_name);
+                let error = summary.record(
+                    project_name,
+                    project_started.elapsed(),
+                    resources,
+                    outcome,
+                    cache_enabled,
+                );
+                self.emit_finished(&summary, error.as_ref());
+                errors.extend(error);
             }
         }
+        if self.config.keep_going && !errors.is_empty() {
+            print_failures(&errors, &summary.blocked, &summary.not_run);
+        }
+        if self.config.dry_run {
+            summary.duration = started.elapsed();
+            return match errors.into_iter().next() {
+                Some((_, e)) => Err(e),
+                None => Ok(TaskRunResult::from(summary)),
+            };
+        }
 
-        Ok(())
+        let result = match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0).1),
+            count => Err(MartyError::Task(format!(
+                "Task '{}' failed for {} projects: {}",
+                task_name,
+                count,
+                summary.failed.join(", ")
+            ))),
+        };
+
+        // Let in-flight cache uploads finish even if the run failed
+        if let Some(uploads) = self.cache.as_ref().and_then(TaskCache::uploads) {
+            print_upload_report(&uploads.finish().await);
+        }
+
+        summary.duration = started.elapsed();
+        summary.success = result.is_ok();
+        for hook in &self.run_hooks {
+            if let Err(e) = hook.on_run_complete(&summary).await {
+                eprintln!("Warning: {} hook failed: {}", hook.name(), e);
+            }
+        }
+        self.save_run_record();
+
+        result.map(|()| TaskRunResult::from(summary))
+    }
+
+    fn emit(&self, event: RunEvent) {
+        for hook in &self.run_hooks {
+            hook.on_event(&event);
+        }
+        if self.config.progress.is_enabled() {
+            self.config.progress.emit(ProgressEvent::Run(event));
+        }
+    }
+
+    /// Report the project the summary recorded last, and its error if it failed
+    fn emit_finished(&self, summary: &RunSummary, error: Option<&(String, MartyError)>) {
+        let Some(report) = summary.projects.last() else {
+            return;
+        };
+        if let Some((project, error)) = error {
+            self.emit(RunEvent::Error {
+                project: project.clone(),
+                task: report.task.clone(),
+                message: error.to_string(),
+            });
+        }
+        self.emit(RunEvent::TaskFinished {
+            project: report.project.clone(),
+            task: report.task.clone(),
+            duration: report.duration,
+            status: report.status,
+            resources: report.resources,
+        });
+    }
+
+    /// Resources used by a project's commands, collected by [`Self::run_task_on_project`]
+    fn take_usage(&self, project_name: &str) -> Option<ResourceUsage> {
+        self.usage.lock().ok()?.remove(project_name)
+    }
+
+    /// Whether the task, or any project's override of it, is persistent
+    fn is_persistent(task_name: &str, all_tasks: &HashMap<String, TaskConfig>) -> bool {
+        let suffix = format!(":{}", task_name);
+        all_tasks
+            .iter()
+            .any(|(key, task)| (key == task_name || key.ends_with(&suffix)) && task.is_persistent())
+    }
+
+    /// Projects running at the same time: `max_concurrency`, or the number of CPUs
+    fn concurrency_limit(&self) -> usize {
+        self.config
+            .max_concurrency
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    /// Run the task on every project of a level on its own thread, with a semaphore keeping
+    /// at most `limit` of them busy. Returns the outcomes and durations in the order of
+    /// `projects`; `None` for projects never started, or stopped, because another one
+    /// failed. `failed` counts the failures of earlier levels.
+    fn run_level_in_parallel(
+        &self,
+        task_name: &str,
+        projects: &[String],
+        all_tasks: &HashMap<String, TaskConfig>,
+        limit: usize,
+        failed: usize,
+    ) -> Vec<Option<(Duration, MartyResult<ProjectOutcome>)>> {
+        let permits = Semaphore::new(limit.max(1));
+        let stop = AtomicBool::new(false);
+        let failures = AtomicUsize::new(failed);
+        let runtime = Handle::current();
+
+        let run = || {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = projects
+                    .iter()
+                    .map(|project_name| {
+                        let (permits, stop, failures, runtime) =
+                            (&permits, &stop, &failures, &runtime);
+                        scope.spawn(move || {
+                            let _permit = runtime.block_on(permits.acquire()).ok()?;
+                            if stop.load(Ordering::SeqCst) {
+                                return None;
+                            }
+                            let started = Instant::now();
+                            let outcome = runtime.block_on(self.run_task_on_project(
+                                task_name,
+                                project_name,
+                                all_tasks,
+                            ));
+                            if outcome.is_err() && self.cancel.load(Ordering::SeqCst) {
+                                return None;
+                            }
+                            if outcome.is_err()
+                                && !self.keeps_going(failures.fetch_add(1, Ordering::SeqCst) + 1)
+                            {
+                                stop.store(true, Ordering::SeqCst);
+                                if self.config.cancel_on_failure {
+                                    self.cancel.store(true, Ordering::SeqCst);
+                                }
+                            }
+                            Some((started.elapsed(), outcome))
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .zip(projects)
+                    .map(|(handle, project_name)| {
+                        handle.join().unwrap_or_else(|_| {
+                            Some((
+                                Duration::ZERO,
+                                Err(MartyError::Task(format!(
+                                    "Task '{}' panicked for project '{}'",
+                                    task_name, project_name
+                                ))),
+                            ))
+                        })
+                    })
+                    .collect()
+            })
+        };
+        // Waiting on the threads blocks this worker, so hand its other tasks to the rest of
+        // the runtime
+        tokio::task::block_in_place(run)
+    }
+
+    /// Whether to keep starting projects after `failures` of them failed
+    fn keeps_going(&self, failures: usize) -> bool {
+        self.config.keep_going && self.config.max_failures.is_none_or(|max| failures < max)
+    }
+
+    /// Whether a project must not run after a failure, recording and reporting it as
+    /// skipped if so. Without keep-going (or after `max_failures`) every remaining project
+    /// is not run; with it, those depending on a failed or blocked project are blocked
+    /// unless `run_blocked` is set.
+    fn skip(&self, project_name: &str, summary: &mut RunSummary) -> bool {
+        if !self.keeps_going(summary.failed.len()) {
+            summary.record_not_run(project_name.to_string());
+            self.emit_finished(summary, None);
+            return true;
+        }
+        if self.config.run_blocked {
+            return false;
+        }
+        let blocked = match get_recursive_dependencies(self.workspace, &[project_name.to_string()])
+        {
+            Ok(dependencies) => dependencies.iter().any(|dependency| {
+                summary.failed.contains(dependency) || summary.blocked.contains(dependency)
+            }),
+            Err(_) => true,
+        };
+        if blocked {
+            summary.record_blocked(project_name.to_string());
+            self.emit_finished(summary, None);
+        }
+        blocked
+    }
+
+    /// Persist the record of the current run, pointing at `marty repro` if a command failed.
+    /// Recording is best effort and never fails the run.
+    fn save_run_record(&self) {
+        let Ok(record) = self.record.lock() else {
+            return;
+        };
+        if let Err(e) = record.save(&self.workspace.root) {
+            eprintln!("Warning: Failed to record run: {}", e);
+            return;
+        }
+
+        // Commands stopped by a cancelled run fail after the one that caused it
+        let failed = if self.cancel.load(Ordering::SeqCst) {
+            record.commands.iter().find(|c| !c.success)
+        } else {
+            record.commands.iter().rev().find(|c| !c.success)
+        };
+        if let Some(failed) = failed {
+            eprintln!(
+                "{} marty repro {} {}:{}",
+                "To reproduce the failure, run:".bright_black(),
+                record.id,
+                failed.project,
+                failed.task
+            );
+        }
     }
 
     /// Run a task on a single project
@@ -79,7 +678,7 @@ impl<'a> TaskRunner<'a> {
         task_name: &str,
         project_name: &str,
         all_tasks: &HashMap<String, TaskConfig>,
-    ) -> MartyResult<()> {
+    ) -> MartyResult<ProjectOutcome> {
         // Resolve task config for this specific project (project-level overrides workspace-level)
         let project_task_key = format!("{}:{}", project_name, task_name);
         let (task_config, is_project_override) =
@@ -97,7 +696,7 @@ impl<'a> TaskRunner<'a> {
             };
 
         // Print task execution header with colors
-        let project_color = get_project_color(project_name);
+        let project_color = project_color(project_name);
         let task_source = if is_project_override {
             "project".bright_blue()
         } else {
@@ -111,27 +710,247 @@ impl<'a> TaskRunner<'a> {
             format!("on {}", project_name).color(project_color).bold()
         );
         println!("└─ {} {}", "Source:".bright_black(), task_source);
+        self.emit(RunEvent::TaskStarted {
+            project: project_name.to_string(),
+            task: task_name.to_string(),
+        });
 
-        self.run_task(task_config, &[project_name.to_string()], all_tasks)
+        let cache_key = self.cache_key(project_name, task_config)?;
+        let project_dir = self.project_dir(project_name);
+        if let (Some(cache), Some(key), Some(dir)) = (&self.cache, &cache_key, project_dir) {
+            // A dry run reports hits without restoring outputs or writing fetched entries
+            if cache.contains(key) && (self.config.dry_run || self.restore_outputs(cache, key, dir))
+            {
+                println!("{}", "✓ Cache hit, skipping".green());
+                if !self.config.dry_run {
+                    self.collect_artifacts(project_name, task_config, dir);
+                }
+                self.emit(RunEvent::CacheHit {
+                    project: project_name.to_string(),
+                    task: task_name.to_string(),
+                    remote: false,
+                });
+                self.mark_completed(project_task_key);
+                return Ok(ProjectOutcome::Cached);
+            }
+            if !self.config.dry_run
+                && cache.fetch(key).await
+                && self.restore_outputs(cache, key, dir)
+            {
+                println!("{}", "✓ Remote cache hit, skipping".green());
+                self.collect_artifacts(project_name, task_config, dir);
+                self.emit(RunEvent::CacheHit {
+                    project: project_name.to_string(),
+                    task: task_name.to_string(),
+                    remote: true,
+                });
+                self.mark_completed(project_task_key);
+                return Ok(ProjectOutcome::Cached);
+            }
+        }
+
+        let meter = ResourceMeter::default();
+        let result = self.run_task(
+            task_config,
+            &[project_name.to_string()],
+            all_tasks,
+            &mut vec![project_task_key.clone()],
+            &meter,
+        );
+        if let (Some(usage), Ok(mut collected)) = (meter.total(), self.usage.lock()) {
+            collected.insert(project_name.to_string(), usage);
+        }
+        result?;
+        self.mark_completed(project_task_key);
+
+        if self.config.dry_run {
+            return Ok(ProjectOutcome::Ran);
+        }
+        if let Some(dir) = project_dir {
+            Self::check_outputs(project_name, task_config, dir)?;
+        }
+        if let (Some(cache), Some(key), Some(dir)) = (&self.cache, &cache_key, project_dir) {
+            match cache.record(project_name, dir, task_config, key) {
+                Ok(artifacts) => {
+                    if let Some(uploads) = cache.uploads() {
+                        for artifact in artifacts {
+                            uploads.enqueue(artifact).await;
+                        }
+                    }
+                }
+                Err(e) => eprintln!(
+                    "Warning: failed to record cache entry for {}: {}",
+                    project_name, e
+                ),
+            }
+        }
+
+        Ok(ProjectOutcome::Ran)
+    }
+
+    /// Compute the cache key for a task on a project, if caching is enabled
+    fn cache_key(
+        &self,
+        project_name: &str,
+        task_config: &TaskConfig,
+    ) -> MartyResult<Option<String>> {
+        let Some(cache) = &self.cache else {
+            return Ok(None);
+        };
+        // A long-running process leaves no result worth caching
+        if task_config.is_persistent() {
+            return Ok(None);
+        }
+        let Some(project_dir) = self.project_dir(project_name) else {
+            return Ok(None);
+        };
+        let dependency_dirs = self.workspace.dependency_dirs(project_name);
+        cache
+            .compute_key(project_name, project_dir, &dependency_dirs, task_config)
+            .map(Some)
+    }
+
+    /// Collect a skipped task's artifacts from the outputs its cache hit restored
+    fn collect_artifacts(&self, project_name: &str, task_config: &TaskConfig, dir: &Path) {
+        let Some(globs) = &task_config.artifacts else {
+            return;
+        };
+        let hook = ArtifactHook::new(
+            &self.workspace.root,
+            project_name,
+            dir,
+            &task_config.name,
+            globs.clone(),
+        );
+        if let Err(e) = hook.collect() {
+            eprintln!(
+                "Warning: Failed to collect artifacts of {}: {}",
+                project_name, e
+            );
+        }
+    }
+
+    fn project_dir(&self, project_name: &str) -> Option<&Path> {
+        self.workspace
+            .projects
+            .iter()
+            .find(|p| p.name == project_name)
+            .map(|p| p.project_dir.as_path())
+    }
+
+    /// Make sure a successful run produced the task's declared outputs, before a cache
+    /// entry is recorded for it
+    fn check_outputs(
+        project_name: &str,
+        task_config: &TaskConfig,
+        project_dir: &Path,
+    ) -> MartyResult<()> {
+        let Some(globs) = task_config.outputs.as_deref() else {
+            return Ok(());
+        };
+        let policy = task_config.missing_outputs.unwrap_or_default();
+        if policy == MissingOutputs::Ignore || task_config.is_persistent() {
+            return Ok(());
+        }
+        let missing = cache::missing_outputs(project_dir, globs)?;
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let message = format!(
+            "Task '{}' succeeded on {} without producing its outputs: {}",
+            task_config.name,
+            project_name,
+            missing.join(", ")
+        );
+        if policy == MissingOutputs::Fail {
+            return Err(MartyError::Task(message));
+        }
+        eprintln!("{} {}", "Warning:".yellow(), message);
+        Ok(())
+    }
+
+    /// Restore a cache entry's outputs, treating any problem as a cache miss
+    fn restore_outputs(&self, cache: &TaskCache, key: &str, project_dir: &Path) -> bool {
+        match cache.restore(key, project_dir) {
+            Ok(true) => true,
+            Ok(false) => {
+                println!(
+                    "{}",
+                    "Cached outputs are not available locally, running".yellow()
+                );
+                false
+            }
+            Err(e) => {
+                eprintln!("Warning: failed to restore cached outputs: {}", e);
+                false
+            }
+        }
     }
 
     /// Execute a single task with dependency handling
+    ///
+    /// `stack` holds the `project:task` pairs being resolved, to detect circular dependencies,
+    /// and `meter` collects the resources used by every command, dependencies included
     fn run_task(
         &self,
         task_config: &TaskConfig,
         targets: &[String],
         all_tasks: &HashMap<String, TaskConfig>,
+        stack: &mut Vec<String>,
+        meter: &ResourceMeter,
     ) -> MartyResult<()> {
         // Handle dependencies first
         if let Some(deps) = &task_config.dependencies {
             for dep_name in deps {
-                if let Some(dep_task) = all_tasks.get(dep_name) {
-                    self.run_task(dep_task, targets, all_tasks)?;
-                } else {
-                    return Err(MartyError::Task(format!(
-                        "Dependency '{}' not found for task '{}'",
-                        dep_name, task_config.name
-                    )));
+                match TaskDependency::parse(dep_name) {
+                    TaskDependency::Task(task) => {
+                        let Some(dep_task) = all_tasks.get(task) else {
+                            return Err(MartyError::Task(format!(
+                                "Dependency '{}' not found for task '{}'",
+                                dep_name, task_config.name
+                            )));
+                        };
+                        if self.config.dry_run {
+                            println!("  {} {}", "dependency".bright_black(), dep_name);
+                        }
+                        let key = match targets.first() {
+                            Some(project) => format!("{}:{}", project, task),
+                            None => task.to_string(),
+                        };
+                        self.enter(stack, key)?;
+                        self.run_task(dep_task, targets, all_tasks, stack, meter)?;
+                        stack.pop();
+                    }
+                    TaskDependency::Dependencies(task) => {
+                        let Some(project) = targets.first() else {
+                            continue;
+                        };
+                        // Dependencies without the task have nothing to run
+                        for dependency in self.workspace.direct_dependencies(project) {
+                            if let Some(dep_task) = Self::resolve_task(all_tasks, dependency, task)
+                            {
+                                self.run_project_dependency(
+                                    dependency, dep_task, all_tasks, stack, meter,
+                                )?;
+                            }
+                        }
+                    }
+                    TaskDependency::Project { project, task } => {
+                        if self.workspace.project(project).is_none() {
+                            return Err(MartyError::Task(format!(
+                                "Project '{}' of dependency '{}' not found for task '{}'",
+                                project, dep_name, task_config.name
+                            )));
+                        }
+                        let Some(dep_task) = Self::resolve_task(all_tasks, project, task) else {
+                            return Err(MartyError::Task(format!(
+                                "Dependency '{}' not found for task '{}'",
+                                dep_name, task_config.name
+                            )));
+                        };
+                        self.run_project_dependency(project, dep_task, all_tasks, stack, meter)?;
+                    }
                 }
             }
         }
@@ -140,24 +959,172 @@ impl<'a> TaskRunner<'a> {
         let effective_targets = task_config.override_targets.as_deref().unwrap_or(targets);
 
         // Execute the task based on its configuration
-        let executor = CommandExecutor::new(self.workspace, effective_targets);
-        
-        if let Some(script) = &task_config.script {
-            executor.execute_script(script)?;
-        } else if let Some(command) = &task_config.command {
-            self.execute_task_command(&executor, command)?;
-        } else {
-            return Err(MartyError::Task(format!(
-                "Task '{}' has no script or command to execute",
-                task_config.name
+        let mut executor = CommandExecutor::new(self.workspace, effective_targets)
+            .with_backend(executor::from_config(
+                task_config.executor.as_ref(),
+                self.config.shell,
+            ))
+            .with_policy(RetryPolicy::from_task(task_config))
+            .with_dry_run(self.config.dry_run)
+            .with_persistent(task_config.is_persistent())
+            .with_progress(self.config.progress.clone(), &task_config.name)
+            .with_meter(meter.clone());
+        if self.config.in_project_dirs {
+            executor = executor.with_hook(Box::new(ProjectDirHook));
+        }
+        if self.config.cancel_on_failure {
+            executor = executor.with_cancel(self.cancel.clone());
+        }
+        // Only the task the run was started for has a single entry on the stack
+        if stack.len() == 1 && !self.config.extra_args.is_empty() {
+            executor = executor.with_extra_args(self.config.extra_args.clone());
+        }
+        if let Some(env) = &task_config.env {
+            executor = executor.with_hook(Box::new(TaskEnvHook(env.clone())));
+        }
+        if let (Some(logs), Some(project), false) =
+            (&self.config.task_logs, targets.first(), self.config.dry_run)
+        {
+            match logs.create(project, &task_config.name) {
+                Ok((_, log)) => executor = executor.with_log(log),
+                Err(e) => eprintln!("Warning: Failed to create task log: {}", e),
+            }
+        }
+        if let (Some(globs), Some(project)) = (
+            &task_config.artifacts,
+            targets.first().and_then(|t| self.workspace.project(t)),
+        ) {
+            executor = executor.with_hook(Box::new(ArtifactHook::new(
+                &self.workspace.root,
+                &project.name,
+                &project.project_dir,
+                &task_config.name,
+                globs.clone(),
             )));
         }
+        // Variables passed for the whole run take precedence over the task's own
+        if !self.config.env.is_empty() {
+            executor = executor.with_hook(Box::new(EnvHook(self.config.env.clone())));
+        }
+        if let Some(project) = targets.first() {
+            executor = executor.with_hook(Box::new(RecordHook {
+                record: self.record.clone(),
+                project: project.clone(),
+                task: task_config.name.clone(),
+                toolchain: task_config
+                    .env_probe
+                    .iter()
+                    .flatten()
+                    .map(|probe| {
+                        (
+                            probe.clone(),
+                            cache::run_probe(&self.workspace.root, self.config.shell, probe),
+                        )
+                    })
+                    .collect(),
+            }));
+        }
+
+        let result = task_config
+            .before
+            .iter()
+            .flatten()
+            .try_for_each(|cmd| executor.execute_before_command(cmd))
+            .and_then(|()| {
+                if let Some(key) = &task_config.plugin {
+                    let plugin = self.config.plugins.get(key).ok_or_else(|| {
+                        MartyError::Task(format!(
+                            "Task '{}' runs on plugin '{}', which isn't loaded",
+                            task_config.name, key
+                        ))
+                    })?;
+                    executor.execute_plugin_task(plugin, &task_config.name)
+                } else if let Some(script) = &task_config.script {
+                    executor.execute_script(script)
+                } else if let Some(command) = &task_config.command {
+                    self.execute_task_command(&executor, command)
+                } else {
+                    Err(MartyError::Task(format!(
+                        "Task '{}' has no script, command or plugin to execute",
+                        task_config.name
+                    )))
+                }
+            });
 
+        // Every `after` command runs, whatever happened before; the first failure is reported
+        let mut after_result = Ok(());
+        for cmd in task_config.after.iter().flatten() {
+            let outcome = executor.execute_after_command(cmd);
+            if after_result.is_ok() {
+                after_result = outcome;
+            }
+        }
+        result.and(after_result)
+    }
+
+    /// The task `task` of `project`: its own override, or the workspace-level task
+    fn resolve_task<'t>(
+        all_tasks: &'t HashMap<String, TaskConfig>,
+        project: &str,
+        task: &str,
+    ) -> Option<&'t TaskConfig> {
+        all_tasks
+            .get(&format!("{}:{}", project, task))
+            .or_else(|| all_tasks.get(task))
+    }
+
+    /// Run a task of another project once, unless it already ran during this run
+    fn run_project_dependency(
+        &self,
+        project: &str,
+        task_config: &TaskConfig,
+        all_tasks: &HashMap<String, TaskConfig>,
+        stack: &mut Vec<String>,
+        meter: &ResourceMeter,
+    ) -> MartyResult<()> {
+        let key = format!("{}:{}", project, task_config.name);
+        if self
+            .completed
+            .lock()
+            .is_ok_and(|completed| completed.contains(&key))
+        {
+            return Ok(());
+        }
+        if self.config.dry_run {
+            println!("  {} {}", "dependency".bright_black(), key);
+        }
+        self.enter(stack, key.clone())?;
+        self.run_task(task_config, &[project.to_string()], all_tasks, stack, meter)?;
+        stack.pop();
+        self.mark_completed(key);
+        Ok(())
+    }
+
+    /// Push `key` onto the dependency stack, failing if it is already being resolved
+    fn enter(&self, stack: &mut Vec<String>, key: String) -> MartyResult<()> {
+        if stack.contains(&key) {
+            stack.push(key);
+            return Err(MartyError::Task(format!(
+                "Circular task dependency: {}",
+                stack.join(" -> ")
+            )));
+        }
+        stack.push(key);
         Ok(())
     }
 
+    fn mark_completed(&self, key: String) {
+        if let Ok(mut completed) = self.completed.lock() {
+            completed.insert(key);
+        }
+    }
+
     /// Execute a task command (single or multiple)
-    fn execute_task_command(&self, executor: &CommandExecutor, command: &TaskCommand) -> MartyResult<()> {
+    fn execute_task_command(
+        &self,
+        executor: &CommandExecutor,
+        command: &TaskCommand,
+    ) -> MartyResult<()> {
         match command {
             TaskCommand::Single(cmd) => executor.execute_shell_command(cmd),
             TaskCommand::Multiple(cmds) => {
@@ -169,4 +1136,319 @@ impl<'a> TaskRunner<'a> {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Print every failure of a run that kept going, the projects blocked by them and those
+/// not run after reaching `max_failures`
+fn print_failures(errors: &[(String, MartyError)], blocked: &[String], not_run: &[String]) {
+    eprintln!();
+    eprintln!(
+        "{}",
+        Message::new("summary.failed")
+            .with("count", errors.len())
+            .to_string()
+            .red()
+            .bold()
+    );
+    for (project, error) in errors {
+        eprintln!(
+            "  {} {}",
+            format!("{}:", project).color(project_color(project)),
+            error
+        );
+    }
+    if !blocked.is_empty() {
+        eprintln!(
+            "  {} {}",
+            messages::text("summary.blocked").bright_black(),
+            blocked.join(", ")
+        );
+    }
+    if !not_run.is_empty() {
+        eprintln!(
+            "  {} {}",
+            Message::new("summary.stopped")
+                .with("count", errors.len())
+                .to_string()
+                .bright_black(),
+            not_run.join(", ")
+        );
+    }
+}
+
+/// Print a summary of remote cache uploads. Upload problems are warnings, never errors.
+fn print_upload_report(report: &UploadReport) {
+    if report.is_empty() {
+        return;
+    }
+
+    println!();
+    println!(
+        "{} {} uploaded, {} skipped, {} failed",
+        "Remote cache:".bright_black(),
+        report.uploaded,
+        report.skipped.len(),
+        report.failed.len()
+    );
+    for (key, size) in &report.skipped {
+        println!(
+            "  {} {} ({} bytes exceeds maxArtifactSize)",
+            "skipped".yellow(),
+            key,
+            size
+        );
+    }
+    for (key, error) in &report.failed {
+        eprintln!(
+            "  {} {}: {}",
+            "Warning: upload failed for".yellow(),
+            key,
+            error
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::tasks::parse_tasks_config;
+    use crate::workspace::{build_dependency_graph, InferredProject, Project, ProjectIndex};
+
+    /// Keeps the summary of the last run, which failed runs only hand to hooks
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Option<RunSummary>>>);
+
+    impl Capture {
+        fn summary(&self) -> RunSummary {
+            self.0.lock().unwrap().clone().unwrap()
+        }
+    }
+
+    impl RunHook for Capture {
+        fn name(&self) -> &str {
+            "capture"
+        }
+
+        fn on_run_complete<'a>(&'a self, summary: &'a RunSummary) -> RunHookFuture<'a> {
+            *self.0.lock().unwrap() = Some(summary.clone());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn workspace(root: &Path, dependencies: &[(&str, &[&str])]) -> Workspace {
+        let inferred_projects = dependencies
+            .iter()
+            .map(|(name, deps)| InferredProject {
+                name: name.to_string(),
+                project_dir: root.join(name),
+                discovered_by: "test".to_string(),
+                workspace_dependencies: deps.iter().map(|d| d.to_string()).collect(),
+                kind: None,
+            })
+            .collect();
+        let projects = dependencies
+            .iter()
+            .map(|(name, _)| {
+                std::fs::create_dir_all(root.join(name)).unwrap();
+                Project {
+                    name: name.to_string(),
+                    project_dir: root.join(name),
+                    file_path: None,
+                    dependencies: Vec::new(),
+                }
+            })
+            .collect();
+        let mut workspace = Workspace {
+            root: root.to_path_buf(),
+            projects,
+            inferred_projects,
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        build_dependency_graph(&mut workspace).unwrap();
+        workspace
+    }
+
+    /// `build` marks the project directory as built, except on the `failing` projects
+    fn tasks(failing: &[&str]) -> HashMap<String, TaskConfig> {
+        let config = parse_tasks_config(
+            r#"
+tasks:
+  - name: build
+    command: touch built
+  - name: fail
+    command: exit 1
+"#,
+        )
+        .unwrap();
+        let mut tasks: HashMap<_, _> = config
+            .tasks
+            .into_iter()
+            .map(|task| (task.name.clone(), task))
+            .collect();
+        let fail = tasks.remove("fail").unwrap();
+        for project in failing {
+            tasks.insert(format!("{}:build", project), fail.clone());
+        }
+        tasks
+    }
+
+    async fn run(
+        workspace: &Workspace,
+        targets: &[&str],
+        failing: &[&str],
+        config: TaskRunnerConfig,
+    ) -> (MartyResult<TaskRunResult>, RunSummary) {
+        let capture = Capture::default();
+        let runner = TaskRunner::new(workspace)
+            .with_config(TaskRunnerConfig {
+                in_project_dirs: true,
+                ..config
+            })
+            .with_run_hook(Box::new(capture.clone()));
+        let targets: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+        let result = runner
+            .run_task_on_targets("build", &targets, &tasks(failing))
+            .await;
+        (result, capture.summary())
+    }
+
+    fn built(workspace: &Workspace, project: &str) -> bool {
+        workspace.root.join(project).join("built").exists()
+    }
+
+    #[tokio::test]
+    async fn test_keep_going_runs_independent_projects_and_blocks_dependents() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = workspace(
+            temp.path(),
+            &[("broken", &[]), ("other", &[]), ("app", &["broken"])],
+        );
+
+        let (result, summary) = run(
+            &workspace,
+            &["app", "other"],
+            &["broken"],
+            TaskRunnerConfig {
+                keep_going: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(built(&workspace, "other"));
+        assert!(!built(&workspace, "app"));
+        assert_eq!(summary.failed, vec!["broken"]);
+        assert_eq!(summary.succeeded, vec!["other"]);
+        assert_eq!(summary.blocked, vec!["app"]);
+        let app = summary
+            .projects
+            .iter()
+            .find(|p| p.project == "app")
+            .unwrap();
+        assert_eq!(app.status, ProjectRunStatus::Blocked);
+    }
+
+    #[tokio::test]
+    async fn test_run_blocked_attempts_dependents_of_failed_projects() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = workspace(
+            temp.path(),
+            &[("broken", &[]), ("other", &[]), ("app", &["broken"])],
+        );
+
+        let (result, summary) = run(
+            &workspace,
+            &["app", "other"],
+            &["broken"],
+            TaskRunnerConfig {
+                keep_going: true,
+                run_blocked: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(built(&workspace, "other"));
+        assert!(built(&workspace, "app"));
+        assert_eq!(summary.failed, vec!["broken"]);
+        assert!(summary.blocked.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_max_failures_stops_starting_projects() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = workspace(temp.path(), &[("a", &[]), ("b", &[]), ("c", &[])]);
+        let failing = ["a", "b", "c"];
+
+        for max_failures in [1, 2] {
+            let (result, summary) = run(
+                &workspace,
+                &failing,
+                &failing,
+                TaskRunnerConfig {
+                    keep_going: true,
+                    max_failures: Some(max_failures),
+                    ..Default::default()
+                },
+            )
+            .await;
+            assert!(result.is_err());
+            assert_eq!(summary.failed.len(), max_failures);
+            assert_eq!(summary.not_run.len(), failing.len() - max_failures);
+        }
+
+        // Without keep-going the first failure stops the run
+        let (_, summary) = run(&workspace, &failing, &failing, TaskRunnerConfig::default()).await;
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.not_run.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_max_concurrency_runs_projects_one_at_a_time() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = workspace(temp.path(), &[("a", &[]), ("b", &[]), ("c", &[])]);
+        // mkdir fails while another project holds the directory, so overlapping commands fail
+        let config = parse_tasks_config(
+            r#"
+tasks:
+  - name: build
+    command: mkdir ../running && sleep 0.3 && rmdir ../running
+"#,
+        )
+        .unwrap();
+        let all_tasks: HashMap<_, _> = config
+            .tasks
+            .into_iter()
+            .map(|task| (task.name.clone(), task))
+            .collect();
+        let targets = ["a", "b", "c"].map(String::from);
+
+        let run = |max_concurrency| {
+            TaskRunner::new(&workspace).with_config(TaskRunnerConfig {
+                enable_parallel_execution: true,
+                max_concurrency: Some(max_concurrency),
+                keep_going: true,
+                in_project_dirs: true,
+                ..Default::default()
+            })
+        };
+        let result = run(1)
+            .run_task_on_targets("build", &targets, &all_tasks)
+            .await
+            .unwrap();
+        assert_eq!(result.projects.len(), 3);
+        assert!(result
+            .projects
+            .iter()
+            .all(|project| project.status == ProjectRunStatus::Succeeded));
+
+        // The check does notice projects running at the same time
+        assert!(run(3)
+            .run_task_on_targets("build", &targets, &all_tasks)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Background uploads of task cache artifacts to a remote cache
+//!
+//! Uploads run concurrently with the rest of the task run. The number of uploads in
+//! flight is bounded, so a slow remote applies backpressure instead of piling up work.
+//! Upload failures never fail the run; they are collected into an [`UploadReport`].
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+use crate::configs::workspace::RemoteCacheConfig;
+use crate::execution::remote_cache::RemoteCache;
+
+const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// A cache entry ready to be uploaded
+#[derive(Debug, Clone)]
+pub struct CacheArtifact {
+    pub namespace: String,
+    pub key: String,
+    /// File to upload, or a directory uploaded as a tar archive
+    pub path: PathBuf,
+}
+
+/// Outcome of all uploads performed during a run
+#[derive(Debug, Default)]
+pub struct UploadReport {
+    pub uploaded: usize,
+    /// Artifacts not uploaded because they exceed the size limit, with their size in bytes
+    pub skipped: Vec<(String, u64)>,
+    /// Artifacts whose upload failed, with the error message
+    pub failed: Vec<(String, String)>,
+}
+
+impl UploadReport {
+    pub fn is_empty(&self) -> bool {
+        self.uploaded == 0 && self.skipped.is_empty() && self.failed.is_empty()
+    }
+}
+
+enum UploadOutcome {
+    Uploaded,
+    Skipped(String, u64),
+    Failed(String, String),
+}
+
+/// Bounded queue of uploads to a remote cache
+pub struct UploadQueue {
+    remote: Arc<dyn RemoteCache>,
+    max_artifact_size: Option<u64>,
+    permits: Arc<Semaphore>,
+    pending: Mutex<Vec<JoinHandle<UploadOutcome>>>,
+}
+
+impl UploadQueue {
+    pub fn new(remote: Arc<dyn RemoteCache>, config: &RemoteCacheConfig) -> Self {
+        let concurrency = config
+            .upload_concurrency
+            .unwrap_or(DEFAULT_UPLOAD_CONCURRENCY)
+            .max(1);
+
+        Self {
+            remote,
+            max_artifact_size: config.max_artifact_size,
+            permits: Arc::new(Semaphore::new(concurrency)),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Queue an artifact for upload, waiting while the queue is full
+    pub async fn enqueue(&self, artifact: CacheArtifact) {
+        let Ok(permit) = self.permits.clone().acquire_owned().await else {
+            return;
+        };
+
+        let remote = self.remote.clone();
+        let max_size = self.max_artifact_size;
+
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            upload(remote.as_ref(), &artifact, max_size).await
+        });
+
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push(handle);
+        }
+    }
+
+    /// Wait for all queued uploads to finish
+    pub async fn finish(&self) -> UploadReport {
+        let handles = match self.pending.lock() {
+            Ok(mut pending) => std::mem::take(&mut *pending),
+            Err(_) => Vec::new(),
+        };
+
+        let mut report = UploadReport::default();
+        for handle in handles {
+            match handle.await {
+                Ok(UploadOutcome::Uploaded) => report.uploaded += 1,
+                Ok(UploadOutcome::Skipped(key, size)) => report.skipped.push((key, size)),
+                Ok(UploadOutcome::Failed(key, error)) => report.failed.push((key, error)),
+                Err(e) => report.failed.push((
+                    "<unknown>".to_string(),
+                    format!("upload task panicked: {}", e),
+                )),
+            }
+        }
+        report
+    }
+}
+
+async fn upload(
+    remote: &dyn RemoteCache,
+    artifact: &CacheArtifact,
+    max_size: Option<u64>,
+) -> UploadOutcome {
+    let body = match read_body(&artifact.path).await {
+        Ok(body) => body,
+        Err(e) => return UploadOutcome::Failed(artifact.key.clone(), e.to_string()),
+    };
+
+    let size = body.len() as u64;
+    if max_size.is_some_and(|max| size > max) {
+        return UploadOutcome::Skipped(artifact.key.clone(), size);
+    }
+
+    match remote.put(&artifact.namespace, &artifact.key, body).await {
+        Ok(()) => UploadOutcome::Uploaded,
+        Err(e) => UploadOutcome::Failed(artifact.key.clone(), e),
+    }
+}
+
+/// Contents of a file, or a tar archive of a directory
+async fn read_body(path: &Path) -> std::io::Result<Vec<u8>> {
+    if !tokio::fs::metadata(path).await?.is_dir() {
+        return tokio::fs::read(path).await;
+    }
+    let dir = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut archive = tar::Builder::new(Vec::new());
+        archive.append_dir_all(".", &dir)?;
+        archive.into_inner()
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::remote_cache::HttpRemoteCache;
+
+    #[tokio::test]
+    async fn test_upload_errors_and_size_limit_are_reported() {
+        let temp = tempfile::tempdir().unwrap();
+        let small = temp.path().join("small.json");
+        let large = temp.path().join("large.json");
+        std::fs::write(&small, "{}").unwrap();
+        std::fs::write(&large, "x".repeat(64)).unwrap();
+
+        // Nothing listens on this port, so the upload itself fails
+        let queue = UploadQueue::new(
+            Arc::new(HttpRemoteCache::new("http://127.0.0.1:9/")),
+            &RemoteCacheConfig {
+                url: Some("http://127.0.0.1:9/".to_string()),
+                s3: None,
+                enabled: None,
+                upload: None,
+                upload_concurrency: Some(1),
+                max_artifact_size: Some(16),
+            },
+        );
+
+        for (key, path) in [("small", small), ("large", large)] {
+            queue
+                .enqueue(CacheArtifact {
+                    namespace: "default".to_string(),
+                    key: key.to_string(),
+                    path,
+                })
+                .await;
+        }
+
+        let report = queue.finish().await;
+        assert_eq!(report.uploaded, 0);
+        assert_eq!(report.skipped, vec![("large".to_string(), 64)]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "small");
+    }
+}
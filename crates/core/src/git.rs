@@ -0,0 +1,149 @@
+//! Git helpers
+//!
+//! Thin wrappers around the `git` CLI used for change detection and for
+//! recording which commit a run was executed against.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::types::{MartyError, MartyResult};
+
+/// Run a git command in the workspace root and return its trimmed stdout
+fn run_git(workspace_root: &Path, args: &[&str]) -> MartyResult<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(workspace_root)
+        .output()
+        .map_err(|e| MartyError::Workspace(format!("Failed to execute git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(MartyError::Workspace(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get the commit SHA currently checked out
+pub fn head_sha(workspace_root: &Path) -> MartyResult<String> {
+    run_git(workspace_root, &["rev-parse", "HEAD"])
+}
+
+/// Get the name of the current branch ("HEAD" when detached)
+pub fn current_branch(workspace_root: &Path) -> MartyResult<String> {
+    run_git(workspace_root, &["rev-parse", "--abbrev-ref", "HEAD"])
+}
+
+/// Get all files changed since `base`, relative to the workspace root.
+///
+/// This includes committed changes, uncommitted changes to tracked files and
+/// untracked files that are not ignored.
+pub fn changed_files_since(workspace_root: &Path, base: &str) -> MartyResult<Vec<PathBuf>> {
+    let diff = run_git(workspace_root, &["diff", "--name-only", "--relative", base])?;
+    let untracked = run_git(
+        workspace_root,
+        &["ls-files", "--others", "--exclude-standard"],
+    )?;
+
+    let mut files: Vec<PathBuf> = diff
+        .lines()
+        .chain(untracked.lines())
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    files.sort();
+    files.dedup();
+
+    Ok(files)
+}
+
+/// Get the top-level directory of the repository containing the workspace
+pub fn repository_root(workspace_root: &Path) -> MartyResult<PathBuf> {
+    run_git(workspace_root, &["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// Restrict the working tree to `dirs` (absolute paths) using cone-mode sparse checkout.
+///
+/// Files directly inside the repository root and inside parents of `dirs` are always
+/// kept by git in cone mode.
+pub fn set_sparse_checkout(workspace_root: &Path, dirs: &[PathBuf]) -> MartyResult<()> {
+    let repo_root = repository_root(workspace_root)?;
+    let canonical_root = repo_root.canonicalize()?;
+
+    let mut args = vec![
+        "sparse-checkout".to_string(),
+        "set".to_string(),
+        "--cone".to_string(),
+        "--".to_string(),
+    ];
+    for dir in dirs {
+        let dir = dir.canonicalize()?;
+        let relative = dir.strip_prefix(&canonical_root).map_err(|_| {
+            MartyError::Workspace(format!(
+                "{} is outside of the git repository {}",
+                dir.display(),
+                repo_root.display()
+            ))
+        })?;
+        args.push(relative.to_string_lossy().replace('\\', "/"));
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_git(&repo_root, &args)?;
+    Ok(())
+}
+
+/// A temporary checkout of a ref next to the main working tree, removed when dropped
+pub struct Worktree {
+    repo_root: PathBuf,
+    dir: tempfile::TempDir,
+}
+
+impl Worktree {
+    /// Check out `git_ref` into a new temporary worktree of the repository containing
+    /// the workspace
+    pub fn add(workspace_root: &Path, git_ref: &str) -> MartyResult<Self> {
+        let repo_root = repository_root(workspace_root)?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().to_string_lossy().to_string();
+        run_git(&repo_root, &["worktree", "add", "--detach", &path, git_ref])?;
+        Ok(Self { repo_root, dir })
+    }
+
+    /// Root of the checkout, corresponding to the repository root
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Location of `workspace_root` of the main working tree inside this checkout
+    pub fn workspace_root(&self, workspace_root: &Path) -> MartyResult<PathBuf> {
+        let repo_root = self.repo_root.canonicalize()?;
+        let relative = workspace_root
+            .canonicalize()?
+            .strip_prefix(&repo_root)
+            .map(Path::to_path_buf)
+            .map_err(|_| {
+                MartyError::Workspace(format!(
+                    "{} is outside of the git repository {}",
+                    workspace_root.display(),
+                    repo_root.display()
+                ))
+            })?;
+        Ok(self.path().join(relative))
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let path = self.dir.path().to_string_lossy().to_string();
+        if let Err(e) = run_git(&self.repo_root, &["worktree", "remove", "--force", &path]) {
+            eprintln!(
+                "Warning: Failed to remove temporary worktree {}: {}",
+                path, e
+            );
+        }
+    }
+}
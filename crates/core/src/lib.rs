@@ -14,8 +14,17 @@
 //! - [`task_execution`] - Task execution planning and compatibility checking
 //! - [`tasks`] - Task utilities and color management
 //! - [`configs`] - Configuration parsing for workspace, projects, and tasks
-//! - [`plugin_runtime`] - WASM plugin runtime for workspace providers
+//! - [`change_detection`] - Finding the files changed since a base, with git, Mercurial,
+//!   snapshots or a given list
+//! - [`cycle_breaks`] - Suggested dependencies to remove to break dependency cycles
+//! - [`dependency_rules`] - Per-project allowed/denied dependency rules
+//! - [`discovery_cache`] - Persisted discovery results for fast start-up
+//! - [`plugin_runtime`] - Plugin loading, and the WASM component plugin runtime
+//! - [`plugin_conformance`] - ABI conformance checks for plugin binaries
+//! - [`plugin_diagnostics`] - Problems plugins report during discovery
 //! - [`results`] - Result types for workspace operations
+//! - [`state`] - Persistent run history stored in `.marty/state.json`
+//! - [`git`] - Git helpers for change detection
 //! - [`types`] - Common error types and type aliases
 //!
 //! ## Usage
@@ -30,25 +39,58 @@
 //! # async fn example() -> marty_core::types::MartyResult<()> {
 //! let manager = WorkspaceManager::new(WorkspaceManagerConfig {
 //!     workspace_root: PathBuf::from("."),
+//!     ..Default::default()
 //! }).await?;
 //!
 //! let projects = manager.list_projects(false)?;
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! Applications embedding marty can contribute their own discovery logic without building
+//! a plugin library, by registering a [`MartyPlugin`](marty_plugin_protocol::MartyPlugin)
+//! implementation on the [`WorkspaceManagerBuilder`]:
+//!
+//! ```rust,ignore
+//! let manager = WorkspaceManager::builder(".")
+//!     .register_plugin(Box::new(MyDiscoveryPlugin::default()))
+//!     .build()
+//!     .await?;
+//! ```
 
+pub mod builtin_plugins;
+pub mod bundle;
+pub mod change_detection;
+pub mod colors;
 pub mod configs;
+pub mod cycle_breaks;
+pub mod dependency_rules;
+pub mod discovery_cache;
 pub mod execution;
+pub mod git;
+pub mod messages;
+pub mod plan_diff;
 pub mod platform;
 pub mod plugin_cache;
+pub mod plugin_conformance;
+pub mod plugin_diagnostics;
+pub mod plugin_options;
+pub mod plugin_registry;
+pub mod plugin_runtime;
 pub mod plugin_runtime_dylib;
+pub mod progress;
+pub mod project_configs;
 pub mod results;
+pub mod run_history;
+pub mod state;
 pub mod task_execution;
+pub mod task_logs;
 pub mod tasks;
+pub mod timings;
 pub mod types;
 pub mod workspace;
 pub mod workspace_manager;
 
 // Re-export the main types for easier usage
 pub use types::{MartyError, MartyResult};
-pub use workspace_manager::{WorkspaceManager, WorkspaceManagerConfig};
+pub use workspace_manager::{WorkspaceManager, WorkspaceManagerBuilder, WorkspaceManagerConfig};
@@ -0,0 +1,338 @@
+//! Catalog of user-facing messages
+//!
+//! Messages are identified by stable IDs such as `check.config.no_command`, so tools can
+//! match on them instead of on wording, and rendered from a template in the language
+//! selected by `MARTY_LANG` (e.g. `de` or `de_DE.UTF-8`). Templates refer to parameters as
+//! `{name}`. Messages missing from a translation fall back to English.
+//!
+//! The catalog covers the run summary and the findings of `marty check` so far.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+/// Languages with a catalog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+impl Lang {
+    /// Parse a locale like `de`, `de-AT` or `de_DE.UTF-8`. Unknown languages are `None`.
+    pub fn parse(locale: &str) -> Option<Self> {
+        let language = locale
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Self::En),
+            "de" => Some(Self::De),
+            _ => None,
+        }
+    }
+
+    /// The language selected by `MARTY_LANG`, English when unset or unknown
+    pub fn current() -> Self {
+        static CURRENT: OnceLock<Lang> = OnceLock::new();
+        *CURRENT.get_or_init(|| {
+            std::env::var("MARTY_LANG")
+                .ok()
+                .and_then(|locale| Self::parse(&locale))
+                .unwrap_or_default()
+        })
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::En => EN,
+            Self::De => DE,
+        }
+    }
+
+    /// The template of `id`, falling back to English and then to the ID itself
+    fn template(self, id: &str) -> &str {
+        let find = |catalog: &'static [(&'static str, &'static str)]| {
+            catalog
+                .iter()
+                .find(|(key, _)| *key == id)
+                .map(|(_, template)| *template)
+        };
+        find(self.catalog()).or_else(|| find(EN)).unwrap_or(id)
+    }
+}
+
+/// A message with its parameters, rendered in the current language when displayed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Message {
+    pub id: &'static str,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<&'static str, String>,
+}
+
+impl Message {
+    pub fn new(id: &'static str) -> Self {
+        Self {
+            id,
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Set the parameter `name`
+    pub fn with(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.params.insert(name, value.to_string());
+        self
+    }
+
+    /// The message's text in `lang`
+    pub fn render(&self, lang: Lang) -> String {
+        let template = lang.template(self.id);
+        let mut text = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let placeholder = &rest[start..];
+            match placeholder
+                .find('}')
+                .and_then(|end| Some((self.params.get(&placeholder[1..end])?, end)))
+            {
+                Some((value, end)) => {
+                    text.push_str(value);
+                    rest = &placeholder[end + 1..];
+                }
+                None => {
+                    text.push('{');
+                    rest = &placeholder[1..];
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(Lang::current()))
+    }
+}
+
+/// The text of a message without parameters, in the current language
+pub fn text(id: &'static str) -> String {
+    Message::new(id).to_string()
+}
+
+const EN: &[(&str, &str)] = &[
+    // marty check
+    ("check.error", "{error}"),
+    (
+        "check.config.valid",
+        "workspace.yml, {tasks} task(s) and {projects} project(s) are valid",
+    ),
+    (
+        "check.config.no_command",
+        "Task '{task}' has no command or script",
+    ),
+    (
+        "check.config.missing_script",
+        "Script '{script}' of task '{task}' does not exist",
+    ),
+    (
+        "check.config.unknown_project",
+        "Task '{task}' depends on unknown project '{project}'",
+    ),
+    (
+        "check.config.unknown_task",
+        "Task '{task}' depends on unknown task '{dependency}'",
+    ),
+    (
+        "check.dependency_rules.valid",
+        "{projects} project(s) declare dependency rules",
+    ),
+    (
+        "check.dependency_rules.violation",
+        "{project} -> {dependency} violates its dependency rules",
+    ),
+    ("check.cycles.valid", "no dependency cycles"),
+    ("check.cycles.cycle", "cycle: {cycle}"),
+    (
+        "check.plan.valid",
+        "{projects} project(s) in {levels} level(s)",
+    ),
+    (
+        "check.hint.unused_plugin",
+        "plugin `{plugin}` discovered no projects in the last {runs} runs; is it needed?",
+    ),
+    (
+        "check.hint.disabled_plugin",
+        "plugin {plugin} is turned off with enabled: false; --enable-plugin loads it for one run",
+    ),
+    (
+        "check.hint.allowed_cycle",
+        "dependency cycle {cycle} is allowed by allowedCycles; break it when you can",
+    ),
+    (
+        "check.hint.stale_allowed_cycle",
+        "allowedCycles entry [{cycle}] matches no dependency cycle anymore and can be removed",
+    ),
+    (
+        "check.hint.cycle_break",
+        "moving what {project} uses out of {dependency} ({source} dependency) would break {count} of {total} cycle(s)",
+    ),
+    ("check.passed", "All {count} checks passed"),
+    ("check.failed", "{failed} of {count} check(s) failed"),
+    // Run summary
+    ("summary.project", "Project"),
+    ("summary.task", "Task"),
+    ("summary.duration", "Duration"),
+    ("summary.cpu", "CPU"),
+    ("summary.memory", "Peak memory"),
+    ("summary.status", "Status"),
+    ("summary.cache", "Cache"),
+    ("summary.total", "Total:"),
+    ("summary.status.succeeded", "succeeded"),
+    ("summary.status.cached", "cached"),
+    ("summary.status.failed", "failed"),
+    ("summary.status.not_run", "not run"),
+    ("summary.status.blocked", "blocked"),
+    ("summary.cache.hit", "hit"),
+    ("summary.cache.miss", "miss"),
+    ("summary.success", "All tasks completed successfully!"),
+    ("summary.failed", "✗ {count} project(s) failed:"),
+    ("summary.blocked", "Blocked because a dependency failed:"),
+    ("summary.stopped", "Not run after {count} failure(s):"),
+];
+
+const DE: &[(&str, &str)] = &[
+    (
+        "check.config.valid",
+        "workspace.yml, {tasks} Task(s) und {projects} Projekt(e) sind gültig",
+    ),
+    (
+        "check.config.no_command",
+        "Task '{task}' hat weder Befehl noch Skript",
+    ),
+    (
+        "check.config.missing_script",
+        "Skript '{script}' von Task '{task}' existiert nicht",
+    ),
+    (
+        "check.config.unknown_project",
+        "Task '{task}' hängt vom unbekannten Projekt '{project}' ab",
+    ),
+    (
+        "check.config.unknown_task",
+        "Task '{task}' hängt vom unbekannten Task '{dependency}' ab",
+    ),
+    (
+        "check.dependency_rules.valid",
+        "{projects} Projekt(e) legen Abhängigkeitsregeln fest",
+    ),
+    (
+        "check.dependency_rules.violation",
+        "{project} -> {dependency} verletzt die Abhängigkeitsregeln",
+    ),
+    ("check.cycles.valid", "keine Abhängigkeitszyklen"),
+    ("check.cycles.cycle", "Zyklus: {cycle}"),
+    (
+        "check.plan.valid",
+        "{projects} Projekt(e) in {levels} Ebene(n)",
+    ),
+    (
+        "check.hint.unused_plugin",
+        "Plugin `{plugin}` hat in den letzten {runs} Läufen keine Projekte gefunden; wird es gebraucht?",
+    ),
+    (
+        "check.hint.disabled_plugin",
+        "Plugin {plugin} ist mit enabled: false abgeschaltet; --enable-plugin lädt es für einen Lauf",
+    ),
+    (
+        "check.hint.allowed_cycle",
+        "Abhängigkeitszyklus {cycle} ist über allowedCycles erlaubt; löse ihn auf, sobald es geht",
+    ),
+    (
+        "check.hint.stale_allowed_cycle",
+        "allowedCycles-Eintrag [{cycle}] passt zu keinem Abhängigkeitszyklus mehr und kann entfernt werden",
+    ),
+    (
+        "check.hint.cycle_break",
+        "wenn {project} nichts mehr aus {dependency} nutzt ({source}-Abhängigkeit), lösen sich {count} von {total} Zyklen auf",
+    ),
+    ("check.passed", "Alle {count} Prüfungen bestanden"),
+    (
+        "check.failed",
+        "{failed} von {count} Prüfung(en) fehlgeschlagen",
+    ),
+    ("summary.project", "Projekt"),
+    ("summary.task", "Task"),
+    ("summary.duration", "Dauer"),
+    ("summary.cpu", "CPU"),
+    ("summary.memory", "Speicherspitze"),
+    ("summary.status", "Status"),
+    ("summary.cache", "Cache"),
+    ("summary.total", "Gesamt:"),
+    ("summary.status.succeeded", "erfolgreich"),
+    ("summary.status.cached", "im Cache"),
+    ("summary.status.failed", "fehlgeschlagen"),
+    ("summary.status.not_run", "nicht ausgeführt"),
+    ("summary.status.blocked", "blockiert"),
+    ("summary.cache.hit", "Treffer"),
+    ("summary.cache.miss", "verfehlt"),
+    ("summary.success", "Alle Tasks erfolgreich abgeschlossen!"),
+    ("summary.failed", "✗ {count} Projekt(e) fehlgeschlagen:"),
+    (
+        "summary.blocked",
+        "Blockiert, weil eine Abhängigkeit fehlschlug:",
+    ),
+    (
+        "summary.stopped",
+        "Nach {count} Fehlschlag/Fehlschlägen nicht ausgeführt:",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = template
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_translations_match_the_english_catalog() {
+        for (id, template) in DE {
+            let english = Lang::En.template(id);
+            assert_ne!(english, *id, "'{}' is missing from the English catalog", id);
+            assert_eq!(placeholders(template), placeholders(english), "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_rendering() {
+        let message = Message::new("check.plan.valid")
+            .with("projects", 3)
+            .with("levels", 2);
+        assert_eq!(message.render(Lang::En), "3 project(s) in 2 level(s)");
+        assert_eq!(message.render(Lang::De), "3 Projekt(e) in 2 Ebene(n)");
+
+        // Untranslated messages fall back to English, unknown IDs render as themselves,
+        // and braces without a matching parameter are kept
+        let error = Message::new("check.error").with("error", "bad {yaml}");
+        assert_eq!(error.render(Lang::De), "bad {yaml}");
+        assert_eq!(Message::new("no.such.id").render(Lang::En), "no.such.id");
+
+        assert_eq!(Lang::parse("de_DE.UTF-8"), Some(Lang::De));
+        assert_eq!(Lang::parse("C"), Some(Lang::En));
+        assert_eq!(Lang::parse("fr"), None);
+    }
+}
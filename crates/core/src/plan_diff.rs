@@ -0,0 +1,152 @@
+//! Compare execution plans
+//!
+//! A [`PlanSnapshot`] captures the execution plan of a target for one version of the
+//! workspace (a git ref and/or an alternative workspace.yml). Diffing two snapshots shows
+//! how a change to tasks, project dependencies or configuration reshapes what a run would
+//! do, before actually running anything.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// Which version of the workspace to plan against
+#[derive(Debug, Clone, Default)]
+pub struct PlanSide {
+    /// Git ref to check out; the current working tree when unset
+    pub git_ref: Option<String>,
+    /// workspace.yml to use instead of the one in `.marty/`
+    pub config_file: Option<PathBuf>,
+}
+
+impl PlanSide {
+    /// Human-readable description of the side
+    pub fn label(&self) -> String {
+        let base = self.git_ref.as_deref().unwrap_or("working tree");
+        match &self.config_file {
+            Some(config) => format!("{} with {}", base, config.display()),
+            None => base.to_string(),
+        }
+    }
+}
+
+/// Execution plan of a target for one version of the workspace
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanSnapshot {
+    pub label: String,
+    pub task: String,
+    /// Projects grouped by dependency level, in execution order
+    pub levels: Vec<Vec<String>>,
+    /// Whether each project's task would be a local cache hit, when caching is enabled
+    pub cache_hits: Option<BTreeMap<String, bool>>,
+}
+
+impl PlanSnapshot {
+    /// Dependency level (0-based) of each project in the plan
+    fn project_levels(&self) -> BTreeMap<&str, usize> {
+        self.levels
+            .iter()
+            .enumerate()
+            .flat_map(|(level, projects)| projects.iter().map(move |p| (p.as_str(), level)))
+            .collect()
+    }
+}
+
+/// Differences between two execution plans
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlanDiff {
+    /// Projects only in the second plan, with their level
+    pub added: Vec<(String, usize)>,
+    /// Projects only in the first plan, with their level
+    pub removed: Vec<(String, usize)>,
+    /// Projects running at a different dependency level: (project, before, after)
+    pub moved: Vec<(String, usize, usize)>,
+    /// Projects in both plans whose predicted cache hit differs: (project, before, after)
+    pub cache_changes: Vec<(String, bool, bool)>,
+}
+
+impl PlanDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.cache_changes.is_empty()
+    }
+}
+
+/// Compare the plan `before` with the plan `after`
+pub fn diff_plans(before: &PlanSnapshot, after: &PlanSnapshot) -> PlanDiff {
+    let before_levels = before.project_levels();
+    let after_levels = after.project_levels();
+    let mut diff = PlanDiff::default();
+
+    for (project, &level) in &after_levels {
+        match before_levels.get(project) {
+            None => diff.added.push((project.to_string(), level)),
+            Some(&previous) if previous != level => {
+                diff.moved.push((project.to_string(), previous, level))
+            }
+            Some(_) => {}
+        }
+    }
+    for (project, &level) in &before_levels {
+        if !after_levels.contains_key(project) {
+            diff.removed.push((project.to_string(), level));
+        }
+    }
+
+    if let (Some(before_hits), Some(after_hits)) = (&before.cache_hits, &after.cache_hits) {
+        for (project, &hit) in after_hits {
+            if let Some(&previous) = before_hits.get(project) {
+                if previous != hit {
+                    diff.cache_changes.push((project.clone(), previous, hit));
+                }
+            }
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(levels: &[&[&str]], hits: &[(&str, bool)]) -> PlanSnapshot {
+        PlanSnapshot {
+            label: String::new(),
+            task: "build".to_string(),
+            levels: levels
+                .iter()
+                .map(|level| level.iter().map(|p| p.to_string()).collect())
+                .collect(),
+            cache_hits: Some(hits.iter().map(|(p, h)| (p.to_string(), *h)).collect()),
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_moved_and_cache_changes() {
+        let before = snapshot(
+            &[&["core", "old"], &["app"]],
+            &[("core", true), ("old", true), ("app", true)],
+        );
+        let after = snapshot(
+            &[&["core"], &["lib"], &["app"]],
+            &[("core", false), ("lib", false), ("app", false)],
+        );
+
+        let diff = diff_plans(&before, &after);
+        assert_eq!(diff.added, vec![("lib".to_string(), 1)]);
+        assert_eq!(diff.removed, vec![("old".to_string(), 0)]);
+        assert_eq!(diff.moved, vec![("app".to_string(), 1, 2)]);
+        assert_eq!(
+            diff.cache_changes,
+            vec![
+                ("app".to_string(), true, false),
+                ("core".to_string(), true, false)
+            ]
+        );
+
+        assert!(diff_plans(&after, &after).is_empty());
+    }
+}
@@ -2,6 +2,16 @@
 
 use std::env;
 
+/// Operating systems and architectures plugins are published for
+const SUPPORTED: [(&str, &str); 6] = [
+    ("linux", "x86_64"),
+    ("linux", "aarch64"),
+    ("macos", "x86_64"),
+    ("macos", "aarch64"),
+    ("windows", "x86_64"),
+    ("windows", "aarch64"),
+];
+
 /// Information about the current platform for plugin resolution
 #[derive(Debug, Clone)]
 pub struct PlatformInfo {
@@ -51,6 +61,22 @@ impl PlatformInfo {
         }
     }
 
+    /// Platform info for a Rust target triple, if plugins are published for it
+    pub fn from_target(target: &str) -> Option<Self> {
+        SUPPORTED
+            .iter()
+            .map(|(os, arch)| Self::from_os_arch(os, arch))
+            .find(|platform| platform.target == target)
+    }
+
+    /// Target triples plugins are published for
+    pub fn supported_targets() -> Vec<&'static str> {
+        SUPPORTED
+            .iter()
+            .map(|(os, arch)| Self::from_os_arch(os, arch).target)
+            .collect()
+    }
+
     /// Get the current platform's dynamic library extension
     pub fn current_extension() -> &'static str {
         if cfg!(target_os = "windows") {
@@ -95,6 +121,14 @@ mod tests {
         assert_eq!(platform.extension, "dll");
     }
 
+    #[test]
+    fn test_from_target() {
+        let platform = PlatformInfo::from_target("aarch64-apple-darwin").unwrap();
+        assert_eq!(platform.extension, "dylib");
+        assert!(PlatformInfo::from_target("riscv64gc-unknown-linux-gnu").is_none());
+        assert_eq!(PlatformInfo::supported_targets().len(), 6);
+    }
+
     #[test]
     #[should_panic(expected = "Unsupported platform")]
     fn test_unsupported_platform() {
@@ -1,24 +1,36 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
+use minisign_verify::{PublicKey, Signature};
 use reqwest;
 use sha2::{Digest, Sha256};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 
-use crate::configs::workspace::PluginConfig;
+use crate::builtin_plugins;
+use crate::bundle::{Bundle, BundledBinary, BundledPlugin};
+use crate::configs::workspace::{PluginCacheConfig, PluginConfig, PluginSigningConfig};
 use crate::platform::PlatformInfo;
-use crate::plugin_runtime_dylib::DylibWorkspaceProvider;
+use crate::plugin_runtime::{is_plugin_file, load_plugin, PLUGIN_EXTENSIONS};
 use crate::types::MartyResult;
 use marty_plugin_protocol::MartyPlugin;
 use serde_json::Value;
 
+/// File in the cache directory recording when each entry was last used, as seconds since
+/// the Unix epoch by file name
+const LAST_USED_FILE: &str = "last-used.json";
+
 /// Manages downloading, caching, and loading of dynamic library plugins
 pub struct PluginCache {
+    workspace_root: PathBuf,
     cache_dir: PathBuf,
     client: reqwest::Client,
+    signing: PluginSigningConfig,
+    bundle: Option<Bundle>,
+    limits: PluginCacheConfig,
 }
 
 /// Information about a cached plugin
@@ -26,18 +38,91 @@ pub struct PluginCache {
 pub struct CachedPlugin {
     pub name: String,
     pub path: PathBuf,
+    /// Description of the workspace.yml entry this plugin was resolved from
+    pub source: String,
     pub url: Option<String>,
     pub enabled: bool,
     pub options: Option<serde_json::Value>,
 }
 
+/// Resolution state of a configured plugin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginStatus {
+    /// Downloaded and present in the cache
+    Cached,
+    /// Remote plugin that will be downloaded on the next run
+    NeedsDownload,
+    /// Loaded from a local path
+    Local,
+    /// Compiled into marty
+    Builtin,
+    /// Local path that can't be resolved
+    Missing,
+    /// Turned off with `enabled: false`
+    Disabled,
+}
+
+/// Details of a plugin binary on disk
+#[derive(Debug, Clone)]
+pub struct PluginFileInfo {
+    pub size: u64,
+    pub sha256: String,
+    pub modified: Option<SystemTime>,
+    /// When Marty last loaded the file from the cache. `None` outside of the cache, and
+    /// for entries cached before uses were recorded.
+    pub last_used: Option<SystemTime>,
+}
+
+/// A plugin entry from workspace.yml and what it currently resolves to
+#[derive(Debug, Clone)]
+pub struct PluginStatusEntry {
+    pub source: String,
+    pub status: PluginStatus,
+    pub version: Option<String>,
+    pub path: Option<PathBuf>,
+    pub file: Option<PluginFileInfo>,
+}
+
+/// Configured plugins along with cache entries no configuration refers to
+#[derive(Debug, Clone)]
+pub struct PluginListing {
+    pub plugins: Vec<PluginStatusEntry>,
+    pub prunable: Vec<(PathBuf, PluginFileInfo)>,
+}
+
 impl PluginCache {
     /// Create a new plugin cache instance
     pub fn new(workspace_root: &Path) -> Self {
         let cache_dir = workspace_root.join(".marty").join("cache").join("plugins");
         let client = reqwest::Client::new();
 
-        Self { cache_dir, client }
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            cache_dir,
+            client,
+            signing: PluginSigningConfig::default(),
+            bundle: None,
+            limits: PluginCacheConfig::default(),
+        }
+    }
+
+    /// Remove unreferenced entries past these limits whenever plugins are resolved
+    pub fn with_limits(mut self, limits: PluginCacheConfig) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Verify downloaded plugins against their publishers' signatures
+    pub fn with_signing(mut self, signing: PluginSigningConfig) -> Self {
+        self.signing = signing;
+        self
+    }
+
+    /// Load remote plugins from a bundle created by `marty bundle` instead of downloading
+    /// them
+    pub fn with_bundle(mut self, bundle: Option<Bundle>) -> Self {
+        self.bundle = bundle;
+        self
     }
 
     /// Ensure the cache directory exists
@@ -62,12 +147,23 @@ impl PluginCache {
             crate::types::MartyError::Config(format!("Failed to initialize plugin cache: {}", e))
         })?;
 
+        // Catch missing local plugins before spending time on downloads
+        for config in plugin_configs {
+            if let (Some(path), true) = (&config.path, config.enabled.unwrap_or(true)) {
+                if !builtin_plugins::is_builtin(config) {
+                    resolve_local_plugin_path(path, &self.workspace_root)
+                        .map_err(|e| crate::types::MartyError::Config(e.to_string()))?;
+                }
+            }
+        }
+
         let mut cached_plugins = Vec::new();
 
         for config in plugin_configs {
             let enabled = config.enabled.unwrap_or(true);
 
-            if !enabled {
+            // Compiled-in plugins have nothing to resolve
+            if !enabled || builtin_plugins::is_builtin(config) {
                 continue;
             }
 
@@ -78,37 +174,154 @@ impl PluginCache {
             cached_plugins.push(cached_plugin);
         }
 
+        let used: Vec<&Path> = cached_plugins
+            .iter()
+            .map(|plugin| plugin.path.as_path())
+            .filter(|path| path.starts_with(&self.cache_dir))
+            .collect();
+        if let Err(e) = self.record_use(&used).await {
+            eprintln!("Warning: Failed to record plugin cache use: {}", e);
+        }
+        match self.enforce_limits(plugin_configs) {
+            Ok(evicted) => {
+                for path in evicted {
+                    println!("Removed unused cached plugin: {}", path.display());
+                }
+            }
+            Err(e) => eprintln!("Warning: Failed to trim plugin cache: {}", e),
+        }
+
         Ok(cached_plugins)
     }
 
-    /// Load a dynamic library plugin and extract its name from the MartyPlugin implementation
-    fn load_plugin_and_get_name(&self, dylib_path: &Path) -> Result<String> {
-        let plugin = DylibWorkspaceProvider::from_dylib_with_temp_copy(dylib_path.to_path_buf())?;
+    /// When each cache entry was last used, by file name
+    fn last_used(&self) -> BTreeMap<String, u64> {
+        fs::read_to_string(self.cache_dir.join(LAST_USED_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record that the cache entries at `paths` were used now. Entries that are gone are
+    /// forgotten.
+    async fn record_use(&self, paths: &[&Path]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        // Other marty processes update the file too; don't lose their uses
+        let last_used_path = self.cache_dir.join(LAST_USED_FILE);
+        let _lock = lock_cache_entry(&last_used_path).await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut last_used = self.last_used();
+        for path in paths {
+            if let Some(file) = path.file_name().and_then(|file| file.to_str()) {
+                last_used.insert(file.to_string(), now);
+            }
+        }
+        last_used.retain(|file, _| self.cache_dir.join(file).exists());
+
+        // Another marty process may be reading the file, so replace it in one step
+        let mut temp = tempfile::NamedTempFile::new_in(&self.cache_dir)?;
+        serde_json::to_writer_pretty(&mut temp, &last_used)?;
+        temp.persist(&last_used_path)?;
+        Ok(())
+    }
+
+    /// Remove the entries no plugin configuration refers to that haven't been used for
+    /// `maxAgeDays`, then the least recently used of the rest while the cache is larger
+    /// than `maxSize`. Returns the removed entries.
+    ///
+    /// Runs on every plugin resolution, so it only looks at file metadata.
+    pub fn enforce_limits(&self, plugin_configs: &[PluginConfig]) -> Result<Vec<PathBuf>> {
+        let PluginCacheConfig {
+            max_size,
+            max_age_days,
+        } = self.limits;
+        if max_size.is_none() && max_age_days.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let mut referenced = Vec::new();
+        for config in plugin_configs {
+            referenced.extend(self.resolve_status(config)?.2);
+        }
+        let last_used = self.last_used();
+        // Size and time of last use (or modification) of every cached entry
+        let entries: Vec<(PathBuf, u64, Option<SystemTime>)> = self
+            .list_cached_plugins()?
+            .into_values()
+            .filter_map(|path| {
+                let metadata = fs::metadata(&path).ok()?;
+                let used = path
+                    .file_name()
+                    .and_then(|file| last_used.get(file.to_str()?))
+                    .map(|secs| UNIX_EPOCH + Duration::from_secs(*secs))
+                    .or(metadata.modified().ok());
+                Some((path, metadata.len(), used))
+            })
+            .collect();
+        let mut size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        let mut unreferenced: Vec<_> = entries
+            .into_iter()
+            .filter(|(path, _, _)| !referenced.contains(path))
+            .collect();
+        // Least recently used first
+        unreferenced.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+
+        let mut evicted = Vec::new();
+        let mut unreferenced = unreferenced.into_iter().peekable();
+        if let Some(days) = max_age_days {
+            let cutoff = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+            while let Some((path, entry_size, _)) =
+                unreferenced.next_if(|(_, _, used)| *used < Some(cutoff))
+            {
+                size -= entry_size;
+                evicted.push(path);
+            }
+        }
+        if let Some(max_size) = max_size {
+            while size > max_size {
+                let Some((path, entry_size, _)) = unreferenced.next() else {
+                    break;
+                };
+                size -= entry_size;
+                evicted.push(path);
+            }
+        }
+
+        for path in &evicted {
+            remove_cache_entry(path)?;
+        }
+        Ok(evicted)
+    }
+
+    /// Load a plugin and extract its name from the MartyPlugin implementation
+    fn load_plugin_and_get_name(&self, plugin_path: &Path) -> Result<String> {
+        let plugin = load_plugin(plugin_path, &self.workspace_root)?;
         Ok(plugin.name().to_string())
     }
 
-    /// Load a dynamic library plugin and validate options against its configuration schema
+    /// Load a plugin and validate options against its configuration schema
     fn load_plugin_and_validate_options(
         &self,
-        dylib_path: &Path,
+        plugin_path: &Path,
         options: &Option<Value>,
     ) -> Result<String> {
-        let plugin = DylibWorkspaceProvider::from_dylib_with_temp_copy(dylib_path.to_path_buf())?;
+        let plugin = load_plugin(plugin_path, &self.workspace_root)?;
 
         // Validate options if provided
         if let Some(options_value) = options {
-            self.validate_plugin_options(&plugin, options_value)?;
+            self.validate_plugin_options(plugin.as_ref(), options_value)?;
         }
 
         Ok(plugin.name().to_string())
     }
 
     /// Validate plugin options against the plugin's configuration schema
-    fn validate_plugin_options(
-        &self,
-        plugin: &DylibWorkspaceProvider,
-        options: &Value,
-    ) -> Result<()> {
+    fn validate_plugin_options(&self, plugin: &dyn MartyPlugin, options: &Value) -> Result<()> {
         if let Some(schema_value) = plugin.configuration_options() {
             println!(
                 "Validating options for plugin '{}' against schema",
@@ -192,45 +405,33 @@ impl PluginCache {
         Ok(())
     }
 
-    /// Resolve a single plugin configuration to a cached plugin
-    async fn resolve_plugin(&self, config: &PluginConfig) -> Result<CachedPlugin> {
-        let enabled = config.enabled.unwrap_or(true);
-        let options = config.options.clone();
+    /// The download name and URL for a plugin hosted remotely, or `None` for local plugins
+    fn remote_source(&self, config: &PluginConfig) -> Result<Option<(String, String)>> {
+        self.remote_source_for(config, &PlatformInfo::current())
+    }
 
+    /// [`Self::remote_source`] for the binary built for `platform`
+    fn remote_source_for(
+        &self,
+        config: &PluginConfig,
+        platform: &PlatformInfo,
+    ) -> Result<Option<(String, String)>> {
         // Priority 1: GitHub repository + version (new convention)
         if let (Some(github_repo), Some(version)) = (&config.github_repo, &config.version) {
-            let (url, temp_name) = if let Some(plugin_name) = &config.plugin {
+            if let Some(plugin_name) = &config.plugin {
                 // Monorepo mode: plugin specified separately
-                let url =
-                    self.resolve_github_plugin_url_monorepo(github_repo, plugin_name, version)?;
-                (url, plugin_name.clone())
-            } else {
-                // Separate repo mode: extract plugin name from repository name
-                let url = self.resolve_github_plugin_url(github_repo, version)?;
-                let temp_name = self.extract_plugin_name_from_repo(github_repo)?;
-                (url, temp_name)
-            };
-
-            let cached_path = self.download_and_cache_plugin(&temp_name, &url).await?;
-
-            let plugin_name = self
-                .load_plugin_and_validate_options(&cached_path, &options)
-                .unwrap_or_else(|e| {
-                    eprintln!(
-                        "Warning: Failed to validate plugin options for '{}': {}",
-                        temp_name, e
-                    );
-                    self.load_plugin_and_get_name(&cached_path)
-                        .unwrap_or_else(|_| temp_name.clone())
-                });
-
-            return Ok(CachedPlugin {
-                name: plugin_name,
-                path: cached_path,
-                url: Some(url),
-                enabled,
-                options,
-            });
+                let url = self.resolve_github_plugin_url_monorepo(
+                    github_repo,
+                    plugin_name,
+                    version,
+                    platform,
+                )?;
+                return Ok(Some((plugin_name.clone(), url)));
+            }
+            // Separate repo mode: extract plugin name from repository name
+            let url = self.resolve_github_plugin_url(github_repo, version, platform)?;
+            let temp_name = self.extract_plugin_name_from_repo(github_repo)?;
+            return Ok(Some((temp_name, url)));
         }
 
         // Priority 2: Direct URL (fallback for custom hosting)
@@ -245,8 +446,29 @@ impl PluginCache {
                 .trim_end_matches(".dylib")
                 .trim_end_matches(".dll")
                 .to_string();
+            return Ok(Some((temp_name, url.clone())));
+        }
+
+        Ok(None)
+    }
+
+    /// Resolve a single plugin configuration to a cached plugin
+    async fn resolve_plugin(&self, config: &PluginConfig) -> Result<CachedPlugin> {
+        let enabled = config.enabled.unwrap_or(true);
+        let options = config.options.clone();
 
-            let cached_path = self.download_and_cache_plugin(&temp_name, url).await?;
+        // Priority 1 and 2: GitHub repository + version, or a direct URL
+        if let Some((temp_name, url)) = self.remote_source(config)? {
+            let public_key = self.public_key(config)?;
+            let cached_path = match &self.bundle {
+                Some(bundle) => bundle.plugin_file(&url)?,
+                None => {
+                    self.download_and_cache_plugin(&temp_name, &url, public_key)
+                        .await?
+                }
+            };
+            self.verify_plugin_signature(&cached_path, &url, public_key)
+                .await?;
 
             // Load the plugin, get its name, and validate options
             let plugin_name = self
@@ -263,7 +485,8 @@ impl PluginCache {
             return Ok(CachedPlugin {
                 name: plugin_name,
                 path: cached_path,
-                url: Some(url.clone()),
+                url: Some(url),
+                source: config.source_description(),
                 enabled,
                 options,
             });
@@ -277,26 +500,7 @@ impl PluginCache {
                 .unwrap_or("unnamed")
                 .to_string();
 
-            let path = if path_str == "builtin" {
-                // Handle builtin plugins - look for them in the .marty/cache/plugins directory first
-                let extension = PlatformInfo::current_extension();
-
-                let cache_path = self
-                    .cache_dir
-                    .join(format!("marty-plugin-{}.{}", temp_name, extension));
-                if cache_path.exists() {
-                    cache_path
-                } else {
-                    // Fallback to .marty/plugins directory
-                    self.cache_dir
-                        .parent()
-                        .unwrap()
-                        .join("plugins")
-                        .join(format!("{}.{}", temp_name, extension))
-                }
-            } else {
-                PathBuf::from(path_str)
-            };
+            let path = resolve_local_plugin_path(path_str, &self.workspace_root)?;
 
             // Load the plugin, get its name, and validate options
             let plugin_name = self
@@ -314,6 +518,7 @@ impl PluginCache {
                 name: plugin_name,
                 path,
                 url: None,
+                source: config.source_description(),
                 enabled,
                 options,
             });
@@ -325,11 +530,101 @@ impl PluginCache {
         ))
     }
 
+    /// The key that signs a downloaded plugin. Fails when signatures are required but no
+    /// key is configured for it.
+    fn public_key<'a>(&'a self, config: &'a PluginConfig) -> Result<Option<&'a str>> {
+        let public_key = config
+            .public_key
+            .as_deref()
+            .or(self.signing.public_key.as_deref());
+        if public_key.is_none() && self.signing.require_signed.unwrap_or(false) {
+            anyhow::bail!(
+                "pluginSigning.requireSigned is set, but {} has no publicKey",
+                config.source_description()
+            );
+        }
+        Ok(public_key)
+    }
+
+    /// Check a downloaded plugin against the minisign signature published next to it. The
+    /// signature is kept beside the cache entry, so cached plugins are verified offline.
+    /// An entry that fails is removed, so it is downloaded again next time.
+    async fn verify_plugin_signature(
+        &self,
+        cache_path: &Path,
+        url: &str,
+        public_key: Option<&str>,
+    ) -> Result<()> {
+        let Some(public_key) = public_key else {
+            return Ok(());
+        };
+
+        let signature_path = sidecar_path(cache_path, "minisig");
+        let (signature, downloaded) = match fs::read_to_string(&signature_path) {
+            Ok(signature) => (Some(signature), false),
+            // Bundles carry the signatures of their plugins and are used offline
+            Err(_) if self.bundle.is_some() => (None, false),
+            Err(_) => (self.download_signature(url).await?, true),
+        };
+
+        let bytes = fs::read(cache_path)
+            .with_context(|| format!("Failed to read plugin: {}", cache_path.display()))?;
+        if let Err(e) = check_plugin_signature(&bytes, signature.as_deref(), url, public_key) {
+            // Fetch the plugin and its signature again next time, in case they were
+            // replaced upstream
+            if self.bundle.is_none() {
+                let _ = remove_cache_entry(cache_path);
+            }
+            return Err(e);
+        }
+        if let Some(signature) = signature.filter(|_| downloaded) {
+            fs::write(&signature_path, signature).with_context(|| {
+                format!(
+                    "Failed to record plugin signature: {}",
+                    signature_path.display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Download the signature published at `<url>.minisig`, or `None` if there is none
+    async fn download_signature(&self, url: &str) -> Result<Option<String>> {
+        let signature_url = format!("{}.minisig", url);
+        let response = self
+            .client
+            .get(&signature_url)
+            .send()
+            .await
+            .with_context(|| {
+                format!("Failed to download plugin signature from {}", signature_url)
+            })?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Failed to download plugin signature from {}: HTTP {}",
+                signature_url,
+                response.status()
+            );
+        }
+        let signature = response
+            .text()
+            .await
+            .with_context(|| format!("Failed to read plugin signature from {}", signature_url))?;
+        Ok(Some(signature))
+    }
+
     /// Resolve a GitHub plugin repository and version to a download URL
     /// For separate plugin repositories (e.g., "owner/marty-plugin-cargo")
-    fn resolve_github_plugin_url(&self, repository: &str, version: &str) -> Result<String> {
+    fn resolve_github_plugin_url(
+        &self,
+        repository: &str,
+        version: &str,
+        platform: &PlatformInfo,
+    ) -> Result<String> {
         let plugin_name = self.extract_plugin_name_from_repo(repository)?;
-        let platform = PlatformInfo::current();
 
         // Standard naming convention: marty-plugin-{name}-v{version}-{target}.{ext}
         let filename = format!(
@@ -353,9 +648,8 @@ impl PluginCache {
         repository: &str,
         plugin_name: &str,
         version: &str,
+        platform: &PlatformInfo,
     ) -> Result<String> {
-        let platform = PlatformInfo::current();
-
         // Monorepo naming convention: marty-plugin-{name}-v{version}-{target}.{ext}
         let filename = format!(
             "marty-plugin-{}-v{}-{}.{}",
@@ -396,28 +690,179 @@ impl PluginCache {
         Ok(plugin_name.to_string())
     }
 
-    /// Download a plugin from URL and cache it locally
-    async fn download_and_cache_plugin(&self, name: &str, url: &str) -> Result<PathBuf> {
-        let url_hash = format!("{:x}", Sha256::digest(url.as_bytes()));
-
-        // Determine file extension based on platform
-        let extension = if cfg!(target_os = "windows") {
-            "dll"
-        } else if cfg!(target_os = "macos") {
-            "dylib"
+    /// What a plugin configuration currently resolves to: its status, version and file
+    fn resolve_status(
+        &self,
+        config: &PluginConfig,
+    ) -> Result<(PluginStatus, Option<String>, Option<PathBuf>)> {
+        Ok(if let Some((name, url)) = self.remote_source(config)? {
+            let (status, path) = match &self.bundle {
+                Some(bundle) => match bundle.binary(&url) {
+                    Some(binary) => (PluginStatus::Cached, bundle.root.join(&binary.file)),
+                    None => (PluginStatus::Missing, self.cache_path(&name, &url)),
+                },
+                None => {
+                    let path = self.cache_path(&name, &url);
+                    let status = if path.exists() {
+                        PluginStatus::Cached
+                    } else {
+                        PluginStatus::NeedsDownload
+                    };
+                    (status, path)
+                }
+            };
+            let version = config.version.clone().or_else(|| version_from_url(&url));
+            (status, version, Some(path))
+        } else if builtin_plugins::is_builtin(config) {
+            let compiled_in = config
+                .plugin
+                .as_deref()
+                .is_some_and(|name| builtin_plugins::available().contains(&name));
+            let status = if compiled_in {
+                PluginStatus::Builtin
+            } else {
+                PluginStatus::Missing
+            };
+            (status, None, None)
+        } else if let Some(path_str) = &config.path {
+            match resolve_local_plugin_path(path_str, &self.workspace_root) {
+                Ok(path) if path.is_file() => (PluginStatus::Local, None, Some(path)),
+                Ok(path) => (PluginStatus::Missing, None, Some(path)),
+                Err(_) => (PluginStatus::Missing, None, None),
+            }
         } else {
-            "so"
+            (PluginStatus::Missing, None, None)
+        })
+    }
+
+    /// Report the status of every configured plugin without downloading or loading anything
+    pub fn plugin_listing(&self, plugin_configs: &[PluginConfig]) -> Result<PluginListing> {
+        let mut plugins = Vec::new();
+        let mut referenced = Vec::new();
+        let last_used = self.last_used();
+        let cache_info = |path: &Path| {
+            file_info(path).ok().map(|mut info| {
+                info.last_used = path
+                    .file_name()
+                    .and_then(|file| last_used.get(file.to_str()?))
+                    .map(|secs| UNIX_EPOCH + Duration::from_secs(*secs));
+                info
+            })
         };
 
-        let cache_filename = format!("{}_{}.{}", name, &url_hash[..8], extension);
-        let cache_path = self.cache_dir.join(&cache_filename);
+        for config in plugin_configs {
+            let (status, version, path) = self.resolve_status(config)?;
+
+            // Disabled plugins keep their cache entry so re-enabling them doesn't download again
+            if let Some(path) = &path {
+                referenced.push(path.clone());
+            }
+            let status = if config.enabled.unwrap_or(true) {
+                status
+            } else {
+                PluginStatus::Disabled
+            };
+            let file = path.as_deref().and_then(cache_info);
 
-        // Check if already cached
-        if cache_path.exists() {
+            plugins.push(PluginStatusEntry {
+                source: config.source_description(),
+                status,
+                version,
+                path,
+                file,
+            });
+        }
+
+        let mut prunable = self
+            .list_cached_plugins()?
+            .into_values()
+            .filter(|path| !referenced.contains(path))
+            .filter_map(|path| cache_info(&path).map(|info| (path, info)))
+            .collect::<Vec<_>>();
+        prunable.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(PluginListing { plugins, prunable })
+    }
+
+    /// Delete cache entries that no plugin configuration refers to, including older
+    /// versions of configured plugins. With `dry_run` nothing is deleted.
+    pub fn prune(
+        &self,
+        plugin_configs: &[PluginConfig],
+        dry_run: bool,
+    ) -> Result<Vec<(PathBuf, PluginFileInfo)>> {
+        let prunable = self.plugin_listing(plugin_configs)?.prunable;
+        if !dry_run {
+            for (path, _) in &prunable {
+                remove_cache_entry(path)?;
+            }
+        }
+        Ok(prunable)
+    }
+
+    /// Where a plugin downloaded from `url` is stored in the cache
+    fn cache_path(&self, name: &str, url: &str) -> PathBuf {
+        let url_hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+
+        // Keep the extension of the download, which differs from this platform's for
+        // binaries vendored for other platforms by `marty bundle`
+        let extension = url
+            .rsplit('/')
+            .next()
+            .and_then(|file| file.rsplit_once('.'))
+            .and_then(|(_, extension)| {
+                PLUGIN_EXTENSIONS
+                    .into_iter()
+                    .find(|known| *known == extension)
+            })
+            .unwrap_or_else(PlatformInfo::current_extension);
+
+        self.cache_dir
+            .join(format!("{}_{}.{}", name, &url_hash[..8], extension))
+    }
+
+    /// Download a plugin from URL and cache it locally, checking it against its signature
+    /// first when it is signed with `public_key`
+    async fn download_and_cache_plugin(
+        &self,
+        name: &str,
+        url: &str,
+        public_key: Option<&str>,
+    ) -> Result<PathBuf> {
+        let cache_path = self.cache_path(name, url);
+        let cache_filename = cache_path.file_name().unwrap_or_default().to_string_lossy();
+
+        // Check if already cached. Entries only appear through an atomic rename, so an
+        // existing file is complete unless it was damaged on disk afterwards.
+        if verify_cache_entry(&cache_path)? == CacheEntryState::Valid {
             println!("Using cached plugin: {} ({})", name, cache_filename);
             return Ok(cache_path);
         }
 
+        // Another marty process may be downloading the same plugin
+        let _lock = lock_cache_entry(&cache_path).await?;
+        match verify_cache_entry(&cache_path)? {
+            CacheEntryState::Valid => {
+                println!("Using cached plugin: {} ({})", name, cache_filename);
+                return Ok(cache_path);
+            }
+            CacheEntryState::Corrupt { expected, actual } => {
+                eprintln!(
+                    "Warning: cached plugin {} is corrupted (sha256 {} instead of {}); downloading it again",
+                    cache_path.display(),
+                    actual,
+                    expected
+                );
+                fs::remove_file(&cache_path).with_context(|| {
+                    format!(
+                        "Failed to remove corrupted plugin: {}",
+                        cache_path.display()
+                    )
+                })?;
+            }
+            CacheEntryState::Missing => {}
+        }
+
         println!("Downloading plugin '{}' from {}", name, url);
 
         // Download the plugin
@@ -464,26 +909,141 @@ impl PluginCache {
             return Err(anyhow::anyhow!("Downloaded file from {} is empty", url));
         }
 
-        // Write to cache
-        let mut file = File::create(&cache_path)
-            .await
+        // Write to a temporary file in the cache directory and rename it into place, so
+        // other processes never see a partially written plugin
+        let digest = format!("{:x}", Sha256::digest(&bytes));
+        let temp_file = tempfile::NamedTempFile::new_in(&self.cache_dir)
             .with_context(|| format!("Failed to create cache file: {}", cache_path.display()))?;
+        let (file, temp_path) = temp_file.into_parts();
+        let mut file = File::from_std(file);
 
         file.write_all(&bytes).await.with_context(|| {
             format!("Failed to write plugin to cache: {}", cache_path.display())
         })?;
 
-        file.flush().await.with_context(|| {
+        file.sync_all().await.with_context(|| {
             format!(
                 "Failed to flush plugin cache file: {}",
                 cache_path.display()
             )
         })?;
+        drop(file);
+        // Temporary files are private to the user; cache entries are as readable as before
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o644))?;
+        }
+
+        if file_digest(&temp_path)? != digest {
+            return Err(anyhow::anyhow!(
+                "Plugin written to {} doesn't match the downloaded data",
+                temp_path.display()
+            ));
+        }
+        // Only verified plugins become cache entries
+        if let Some(public_key) = public_key {
+            let signature = self.download_signature(url).await?;
+            check_plugin_signature(&bytes, signature.as_deref(), url, public_key)?;
+            if let Some(signature) = signature {
+                fs::write(sidecar_path(&cache_path, "minisig"), signature).with_context(|| {
+                    format!(
+                        "Failed to record plugin signature: {}",
+                        cache_path.display()
+                    )
+                })?;
+            }
+        }
+        // Record the digest before the entry becomes visible so readers can always verify it
+        fs::write(sidecar_path(&cache_path, "sha256"), &digest)
+            .with_context(|| format!("Failed to record plugin digest: {}", cache_path.display()))?;
+        temp_path.persist(&cache_path).with_context(|| {
+            format!("Failed to move plugin into cache: {}", cache_path.display())
+        })?;
 
         println!("Cached plugin: {} -> {}", name, cache_filename);
         Ok(cache_path)
     }
 
+    /// Download the plugins of every enabled remote entry for each of `platforms` and copy
+    /// them, with their signatures, into `dir/plugins`. Entries with a `url` name a single
+    /// binary, which is vendored as it is. Local and compiled-in plugins aren't vendored.
+    pub async fn vendor_plugins(
+        &self,
+        plugin_configs: &[PluginConfig],
+        platforms: &[PlatformInfo],
+        dir: &Path,
+    ) -> Result<Vec<BundledPlugin>> {
+        self.initialize().await?;
+        let plugins_dir = dir.join("plugins");
+        fs::create_dir_all(&plugins_dir)
+            .with_context(|| format!("Failed to create {}", plugins_dir.display()))?;
+
+        let mut vendored = Vec::new();
+        for config in plugin_configs.iter().filter(|c| c.enabled.unwrap_or(true)) {
+            let per_platform = config.github_repo.is_some() && config.version.is_some();
+            let mut sources = Vec::new();
+            if per_platform {
+                for platform in platforms {
+                    if let Some(source) = self.remote_source_for(config, platform)? {
+                        sources.push((Some(platform.target.to_string()), source));
+                    }
+                }
+            } else if let Some(source) = self.remote_source(config)? {
+                sources.push((None, source));
+            } else {
+                if !builtin_plugins::is_builtin(config) {
+                    println!(
+                        "Not bundling {}: local plugins are loaded from the workspace",
+                        config.source_description()
+                    );
+                }
+                continue;
+            }
+
+            let public_key = self.public_key(config)?;
+            let mut binaries = Vec::new();
+            for (target, (name, url)) in sources {
+                let cached_path = self
+                    .download_and_cache_plugin(&name, &url, public_key)
+                    .await?;
+                self.verify_plugin_signature(&cached_path, &url, public_key)
+                    .await?;
+
+                let file_name = cached_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                fs::copy(&cached_path, plugins_dir.join(&file_name))
+                    .with_context(|| format!("Failed to copy {}", cached_path.display()))?;
+                let signature = sidecar_path(&cached_path, "minisig");
+                if signature.exists() {
+                    fs::copy(
+                        &signature,
+                        sidecar_path(&plugins_dir.join(&file_name), "minisig"),
+                    )
+                    .with_context(|| format!("Failed to copy {}", signature.display()))?;
+                }
+                binaries.push(BundledBinary {
+                    target,
+                    sha256: file_digest(&cached_path)?,
+                    file: format!("plugins/{}", file_name),
+                    url,
+                });
+            }
+            vendored.push(BundledPlugin {
+                source: config.source_description(),
+                version: config
+                    .version
+                    .clone()
+                    .or_else(|| binaries.first().and_then(|b| version_from_url(&b.url))),
+                binaries,
+            });
+        }
+        Ok(vendored)
+    }
+
     /// Get a list of all cached plugins on disk (for cleanup purposes)
     pub fn list_cached_plugins(&self) -> Result<HashMap<String, PathBuf>> {
         let mut plugins = HashMap::new();
@@ -496,13 +1056,7 @@ impl PluginCache {
             let entry = entry?;
             let path = entry.path();
 
-            // Check for dynamic library extensions
-            let is_dylib = path
-                .extension()
-                .map(|e| e == "so" || e == "dylib" || e == "dll")
-                .unwrap_or(false);
-
-            if is_dylib {
+            if is_plugin_file(&path) {
                 if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
                     plugins.insert(filename.to_string(), path);
                 }
@@ -525,15 +1079,599 @@ impl PluginCache {
     }
 }
 
+/// Resolve a `path:` plugin entry to an existing file.
+///
+/// `$VAR` and `${VAR}` are replaced from the environment, a leading `~` expands to the
+/// home directory, and relative paths are taken relative to the workspace root so the
+/// entry works from any subdirectory and on any machine with the same layout.
+pub fn resolve_local_plugin_path(path_str: &str, workspace_root: &Path) -> Result<PathBuf> {
+    let expanded = expand_env_vars(path_str)
+        .with_context(|| format!("Failed to expand plugin path '{}'", path_str))?;
+
+    let path = match expanded.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            let home = std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Plugin path '{}' uses '~' but no home directory is set",
+                        path_str
+                    )
+                })?;
+            PathBuf::from(home).join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(&expanded),
+    };
+    let path = if path.is_relative() {
+        workspace_root.join(path)
+    } else {
+        path
+    };
+
+    if !path.is_file() {
+        let workspace_root = workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| workspace_root.to_path_buf());
+        return Err(anyhow::anyhow!(
+            "Plugin path '{}' does not exist (resolved to {})\n\
+             Relative paths are resolved against the workspace root: {}",
+            path_str,
+            path.display(),
+            workspace_root.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// Replace `$VAR` and `${VAR}` with values from the environment
+pub(crate) fn expand_env_vars(input: &str) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("Unclosed '${{' in '{}'", input))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        if name.is_empty() {
+            // A lone '$' is kept as-is
+            output.push('$');
+            rest = after;
+            continue;
+        }
+        let value = std::env::var(name)
+            .map_err(|_| anyhow::anyhow!("Environment variable '{}' is not set", name))?;
+        output.push_str(&value);
+        rest = remainder;
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CacheEntryState {
+    Missing,
+    Valid,
+    Corrupt { expected: String, actual: String },
+}
+
+/// Check a cached plugin against the digest recorded when it was downloaded.
+///
+/// Entries cached before digests were recorded are trusted once and their digest is
+/// recorded from then on.
+fn verify_cache_entry(cache_path: &Path) -> Result<CacheEntryState> {
+    if !cache_path.exists() {
+        return Ok(CacheEntryState::Missing);
+    }
+
+    let actual = file_digest(cache_path)?;
+    let digest_path = sidecar_path(cache_path, "sha256");
+    match fs::read_to_string(&digest_path) {
+        Ok(expected) if expected.trim() == actual => Ok(CacheEntryState::Valid),
+        Ok(expected) => Ok(CacheEntryState::Corrupt {
+            expected: expected.trim().to_string(),
+            actual,
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            fs::write(&digest_path, &actual).with_context(|| {
+                format!("Failed to record plugin digest: {}", cache_path.display())
+            })?;
+            Ok(CacheEntryState::Valid)
+        }
+        Err(e) => Err(e)
+            .with_context(|| format!("Failed to read plugin digest: {}", digest_path.display())),
+    }
+}
+
+/// Take an exclusive lock on a cache entry, waiting for any other process holding it.
+/// The lock is released when the returned file is dropped.
+async fn lock_cache_entry(cache_path: &Path) -> Result<fs::File> {
+    let lock_path = sidecar_path(cache_path, "lock");
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open lock file: {}", lock_path.display()))?;
+
+    match lock_file.try_lock() {
+        Ok(()) => Ok(lock_file),
+        Err(fs::TryLockError::WouldBlock) => {
+            println!(
+                "Waiting for another marty process to finish writing {}",
+                cache_path.display()
+            );
+            tokio::task::spawn_blocking(move || lock_file.lock().map(|()| lock_file))
+                .await?
+                .with_context(|| format!("Failed to lock {}", lock_path.display()))
+        }
+        Err(fs::TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("Failed to lock {}", lock_path.display()))
+        }
+    }
+}
+
+/// Check `bytes` against a minisign signature (the contents of a `.minisig` file) made
+/// with the secret key of `public_key`
+fn verify_signature(bytes: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let public_key =
+        PublicKey::from_base64(public_key.trim()).context("Invalid minisign public key")?;
+    let signature = Signature::decode(signature).context("Invalid minisign signature")?;
+    public_key
+        .verify(bytes, &signature, false)
+        .context("Signature verification failed")
+}
+
+/// Check a plugin downloaded from `url` against its signature, which is required once a
+/// public key is configured for the plugin
+fn check_plugin_signature(
+    bytes: &[u8],
+    signature: Option<&str>,
+    url: &str,
+    public_key: &str,
+) -> Result<()> {
+    let Some(signature) = signature else {
+        anyhow::bail!(
+            "Plugin from {} is not signed (no {}.minisig), but a publicKey is configured for it; refusing to load it",
+            url,
+            url
+        );
+    };
+    verify_signature(bytes, signature, public_key).with_context(|| {
+        format!(
+            "Plugin from {} doesn't match its signature; refusing to load it",
+            url
+        )
+    })
+}
+
+/// Delete a cache entry along with the files stored next to it
+fn remove_cache_entry(path: &Path) -> Result<()> {
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    let _ = fs::remove_file(sidecar_path(path, "lock"));
+    let _ = fs::remove_file(sidecar_path(path, "sha256"));
+    let _ = fs::remove_file(sidecar_path(path, "minisig"));
+    Ok(())
+}
+
+/// A file stored next to a cache entry, such as `plugin.so.lock`
+fn sidecar_path(cache_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = cache_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Size, digest and modification time of a plugin binary
+fn file_info(path: &Path) -> Result<PluginFileInfo> {
+    let metadata = fs::metadata(path)?;
+    Ok(PluginFileInfo {
+        size: metadata.len(),
+        sha256: file_digest(path)?,
+        modified: metadata.modified().ok(),
+        last_used: None,
+    })
+}
+
+/// Extract the version from a release asset URL such as
+/// `.../marty-plugin-cargo-v0.2.2-x86_64-unknown-linux-gnu.so`
+fn version_from_url(url: &str) -> Option<String> {
+    let filename = url.rsplit('/').next()?;
+    let (_, rest) = filename.split_once("-v")?;
+    let version = rest
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .next()?
+        .trim_end_matches('.');
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Compute the sha256 digest of a plugin binary
+pub fn file_digest(path: &Path) -> Result<String> {
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read plugin {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// Detects plugins that would conflict during discovery: the same binary loaded twice
+/// through different sources, or two plugins reporting the same key.
+#[derive(Default)]
+pub struct PluginConflictChecker {
+    digests: HashMap<String, String>,
+    keys: HashMap<String, String>,
+}
+
+impl PluginConflictChecker {
+    /// Register a plugin binary by digest, failing if the same binary was already registered
+    pub fn register_binary(&mut self, digest: &str, source: &str) -> MartyResult<()> {
+        if let Some(existing) = self.digests.get(digest) {
+            return Err(crate::types::MartyError::Config(format!(
+                "The same plugin binary is configured twice: {} and {}",
+                existing, source
+            )));
+        }
+        self.digests.insert(digest.to_string(), source.to_string());
+        Ok(())
+    }
+
+    /// Register a loaded plugin's key, failing if another plugin already uses it
+    pub fn register_key(&mut self, key: &str, source: &str) -> MartyResult<()> {
+        if let Some(existing) = self.keys.get(key) {
+            return Err(crate::types::MartyError::Config(format!(
+                "Plugins from {} and {} both use the key '{}'",
+                existing, source, key
+            )));
+        }
+        self.keys.insert(key.to_string(), source.to_string());
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_plugin_listing_flags_unreferenced_cache_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = PluginCache::new(root.path());
+        std::fs::create_dir_all(&cache.cache_dir).unwrap();
+
+        let url = "https://example.com/marty-plugin-foo-v1.4.0-x86_64-unknown-linux-gnu.so";
+        let cached = cache.cache_path("marty-plugin-foo-v1.4.0-x86_64-unknown-linux-gnu", url);
+        std::fs::write(&cached, b"foo").unwrap();
+        let stale = cache.cache_dir.join("old_12345678.so");
+        std::fs::write(&stale, b"old").unwrap();
+
+        let configs: Vec<PluginConfig> = serde_yaml::from_str(&format!(
+            "- url: {}\n- url: https://example.com/bar.so\n- path: missing.so\n",
+            url
+        ))
+        .unwrap();
+        let listing = cache.plugin_listing(&configs).unwrap();
+
+        let statuses = listing.plugins.iter().map(|p| p.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                PluginStatus::Cached,
+                PluginStatus::NeedsDownload,
+                PluginStatus::Missing
+            ]
+        );
+        assert_eq!(listing.plugins[0].version.as_deref(), Some("1.4.0"));
+        assert_eq!(listing.plugins[0].file.as_ref().unwrap().size, 3);
+        assert_eq!(listing.prunable.len(), 1);
+        assert_eq!(listing.prunable[0].0, stale);
+
+        let pruned = cache.prune(&configs, true).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert!(stale.exists());
+        cache.prune(&configs, false).unwrap();
+        assert!(!stale.exists());
+        assert!(cached.exists());
+    }
+
+    #[test]
+    fn test_limits_evict_least_recently_used_unreferenced_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = PluginCache::new(root.path());
+        std::fs::create_dir_all(&cache.cache_dir).unwrap();
+
+        let url = "https://example.com/foo.so";
+        let configs: Vec<PluginConfig> =
+            serde_yaml::from_str(&format!("- url: {}\n", url)).unwrap();
+        let referenced = cache.cache_path("foo", url);
+        std::fs::write(&referenced, [0; 400]).unwrap();
+        let older = cache.cache_dir.join("older_12345678.so");
+        let newer = cache.cache_dir.join("newer_12345678.so");
+        let ancient = cache.cache_dir.join("ancient_12345678.so");
+        for path in [&older, &newer, &ancient] {
+            std::fs::write(path, [0; 200]).unwrap();
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let days = |n: u64| now - n * 24 * 60 * 60;
+        let last_used = BTreeMap::from([
+            ("older_12345678.so".to_string(), days(3)),
+            ("newer_12345678.so".to_string(), days(1)),
+            ("ancient_12345678.so".to_string(), days(40)),
+        ]);
+        std::fs::write(
+            cache.cache_dir.join(LAST_USED_FILE),
+            serde_json::to_string(&last_used).unwrap(),
+        )
+        .unwrap();
+
+        let listing = cache.plugin_listing(&configs).unwrap();
+        assert!(listing
+            .prunable
+            .iter()
+            .all(|(_, file)| file.last_used.is_some()));
+
+        // Without limits the cache is left alone
+        assert!(cache.enforce_limits(&configs).unwrap().is_empty());
+
+        let cache = cache.with_limits(PluginCacheConfig {
+            max_size: Some(700),
+            max_age_days: Some(30),
+        });
+        let evicted = cache.enforce_limits(&configs).unwrap();
+        assert_eq!(evicted, vec![ancient.clone(), older.clone()]);
+        assert!(referenced.exists() && newer.exists());
+        assert!(!ancient.exists() && !older.exists());
+
+        // Referenced entries stay even when the cache is still too large
+        let cache = cache.with_limits(PluginCacheConfig {
+            max_size: Some(1),
+            max_age_days: None,
+        });
+        cache.enforce_limits(&configs).unwrap();
+        assert!(referenced.exists() && !newer.exists());
+    }
+
+    #[test]
+    fn test_cache_entries_are_verified_against_recorded_digest() {
+        let root = tempfile::tempdir().unwrap();
+        let entry = root.path().join("plugin_12345678.so");
+        assert_eq!(
+            verify_cache_entry(&entry).unwrap(),
+            CacheEntryState::Missing
+        );
+
+        // Entries without a recorded digest are adopted
+        fs::write(&entry, b"plugin").unwrap();
+        assert_eq!(verify_cache_entry(&entry).unwrap(), CacheEntryState::Valid);
+        assert!(sidecar_path(&entry, "sha256").exists());
+
+        fs::write(&entry, b"plug").unwrap();
+        assert!(matches!(
+            verify_cache_entry(&entry).unwrap(),
+            CacheEntryState::Corrupt { .. }
+        ));
+    }
+
+    // Test vector of the minisign-verify crate: the signature of the bytes `test`
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+
+    #[tokio::test]
+    async fn test_downloaded_plugins_are_checked_against_their_signature() {
+        assert!(verify_signature(b"test", SIGNATURE, PUBLIC_KEY).is_ok());
+        assert!(verify_signature(b"tset", SIGNATURE, PUBLIC_KEY).is_err());
+        assert!(verify_signature(b"test", SIGNATURE, "not a key").is_err());
+
+        let root = tempfile::tempdir().unwrap();
+        let signing: PluginSigningConfig =
+            serde_yaml::from_str(&format!("publicKey: {}\nrequireSigned: true\n", PUBLIC_KEY))
+                .unwrap();
+        let cache = PluginCache::new(root.path()).with_signing(signing);
+        let configs: Vec<PluginConfig> = serde_yaml::from_str(
+            "- url: https://example.com/foo.so\n- url: https://example.com/bar.so\n  publicKey: RWQother\n",
+        )
+        .unwrap();
+        assert_eq!(cache.public_key(&configs[0]).unwrap(), Some(PUBLIC_KEY));
+        assert_eq!(cache.public_key(&configs[1]).unwrap(), Some("RWQother"));
+
+        // A cached plugin is verified against the signature stored next to it
+        let entry = root.path().join("foo_12345678.so");
+        fs::write(&entry, b"test").unwrap();
+        fs::write(sidecar_path(&entry, "minisig"), SIGNATURE).unwrap();
+        let url = "https://example.com/foo.so";
+        cache
+            .verify_plugin_signature(&entry, url, Some(PUBLIC_KEY))
+            .await
+            .unwrap();
+
+        fs::write(&entry, b"tampered").unwrap();
+        let error = cache
+            .verify_plugin_signature(&entry, url, Some(PUBLIC_KEY))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("doesn't match its signature"));
+        assert!(!sidecar_path(&entry, "minisig").exists());
+        assert!(!entry.exists());
+
+        // With a key configured, a missing signature is an error even without requireSigned
+        let error = check_plugin_signature(b"test", None, url, PUBLIC_KEY).unwrap_err();
+        assert!(error.to_string().contains("is not signed"));
+
+        // Without a key, requiring signatures refuses remote plugins
+        let cache = PluginCache::new(root.path()).with_signing(PluginSigningConfig {
+            public_key: None,
+            require_signed: Some(true),
+        });
+        assert!(cache.public_key(&configs[0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_vendored_plugins_resolve_from_the_bundle() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = PluginCache::new(root.path());
+        fs::create_dir_all(&cache.cache_dir).unwrap();
+        let configs: Vec<PluginConfig> = serde_yaml::from_str(
+            "- githubRepo: codyspate/marty\n  plugin: helm\n  version: 0.1.0\n- url: https://example.com/foo.so\n- path: builtin\n  plugin: cargo\n",
+        )
+        .unwrap();
+
+        // Cached downloads are vendored without going to the network
+        let platforms = ["x86_64-unknown-linux-gnu", "aarch64-apple-darwin"]
+            .map(|target| PlatformInfo::from_target(target).unwrap());
+        for platform in &platforms {
+            let (name, url) = cache
+                .remote_source_for(&configs[0], platform)
+                .unwrap()
+                .unwrap();
+            fs::write(cache.cache_path(&name, &url), platform.target).unwrap();
+        }
+        let foo = cache.cache_path("foo", "https://example.com/foo.so");
+        fs::write(&foo, b"foo").unwrap();
+
+        let dir = root.path().join("bundle");
+        let plugins = cache
+            .vendor_plugins(&configs, &platforms, &dir)
+            .await
+            .unwrap();
+        assert_eq!(plugins.len(), 2);
+        let helm = &plugins[0];
+        assert_eq!(helm.version.as_deref(), Some("0.1.0"));
+        let targets: Vec<_> = helm.binaries.iter().map(|b| b.target.as_deref()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                Some("x86_64-unknown-linux-gnu"),
+                Some("aarch64-apple-darwin")
+            ]
+        );
+        assert!(helm.binaries[1].file.ends_with(".dylib"));
+        assert_eq!(plugins[1].binaries[0].target, None);
+
+        // With the bundle, plugins are loaded from it even once the cache is gone
+        fs::remove_dir_all(&cache.cache_dir).unwrap();
+        let bundle = Bundle {
+            root: dir.clone(),
+            lock: crate::bundle::BundleLock {
+                plugins,
+                ..Default::default()
+            },
+        };
+        let cache = PluginCache::new(root.path()).with_bundle(Some(bundle));
+        let resolved = cache.resolve_plugins(&configs[1..]).await.unwrap();
+        assert_eq!(
+            resolved[0].path,
+            dir.join("plugins").join(foo.file_name().unwrap())
+        );
+        let listing = cache.plugin_listing(&configs).unwrap();
+        assert_eq!(listing.plugins[1].status, PluginStatus::Cached);
+    }
+
+    #[tokio::test]
+    async fn test_cache_entry_lock_is_exclusive() {
+        let root = tempfile::tempdir().unwrap();
+        let entry = root.path().join("plugin_12345678.so");
+
+        let lock = lock_cache_entry(&entry).await.unwrap();
+        let other = fs::File::open(sidecar_path(&entry, "lock")).unwrap();
+        assert!(matches!(
+            other.try_lock(),
+            Err(fs::TryLockError::WouldBlock)
+        ));
+
+        drop(lock);
+        assert!(other.try_lock().is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_uses_are_recorded_under_the_lock() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = PluginCache::new(root.path());
+        std::fs::create_dir_all(&cache.cache_dir).unwrap();
+        let entry = cache.cache_dir.join("plugin_12345678.so");
+        std::fs::write(&entry, b"").unwrap();
+
+        // Another process holding the lock keeps the update waiting
+        let lock = lock_cache_entry(&cache.cache_dir.join(LAST_USED_FILE))
+            .await
+            .unwrap();
+        let recording = tokio::spawn(async move {
+            cache.record_use(&[entry.as_path()]).await.unwrap();
+            cache
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!recording.is_finished());
+
+        drop(lock);
+        let cache = recording.await.unwrap();
+        assert!(cache.last_used().contains_key("plugin_12345678.so"));
+    }
+
+    #[test]
+    fn test_local_plugin_paths_resolve_against_workspace_root() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("plugins")).unwrap();
+        std::fs::write(root.path().join("plugins/libfoo.so"), b"").unwrap();
+
+        let resolved = resolve_local_plugin_path("plugins/libfoo.so", root.path()).unwrap();
+        assert_eq!(resolved, root.path().join("plugins/libfoo.so"));
+
+        std::env::set_var("MARTY_TEST_PLUGIN_DIR", "plugins");
+        let resolved =
+            resolve_local_plugin_path("${MARTY_TEST_PLUGIN_DIR}/libfoo.so", root.path()).unwrap();
+        assert_eq!(resolved, root.path().join("plugins/libfoo.so"));
+
+        let err = resolve_local_plugin_path("plugins/missing.so", root.path()).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+        assert!(resolve_local_plugin_path("$MARTY_TEST_UNSET_VAR/x.so", root.path()).is_err());
+    }
+
+    #[test]
+    fn test_plugin_conflicts_name_both_sources() {
+        let mut checker = PluginConflictChecker::default();
+        checker.register_binary("abc", "path ./a.so").unwrap();
+        checker.register_key("cargo", "path ./a.so").unwrap();
+        checker
+            .register_binary("def", "url https://x/b.so")
+            .unwrap();
+
+        let err = checker
+            .register_binary("abc", "githubRepo o/marty-plugin-a v1")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("path ./a.so") && err.contains("githubRepo o/marty-plugin-a v1"));
+
+        let err = checker
+            .register_key("cargo", "url https://x/b.so")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("path ./a.so") && err.contains("url https://x/b.so"));
+        assert!(err.contains("'cargo'"));
+    }
+
     #[test]
     fn test_extract_plugin_name_from_repo() {
         let cache = PluginCache {
+            workspace_root: PathBuf::from("/tmp"),
             cache_dir: PathBuf::from("/tmp"),
             client: reqwest::Client::new(),
+            signing: PluginSigningConfig::default(),
+            bundle: None,
+            limits: PluginCacheConfig::default(),
         };
 
         // Valid repository names
@@ -569,12 +1707,20 @@ mod tests {
     #[test]
     fn test_resolve_github_plugin_url_linux() {
         let cache = PluginCache {
+            workspace_root: PathBuf::from("/tmp"),
             cache_dir: PathBuf::from("/tmp"),
             client: reqwest::Client::new(),
+            signing: PluginSigningConfig::default(),
+            bundle: None,
+            limits: PluginCacheConfig::default(),
         };
 
         let url = cache
-            .resolve_github_plugin_url("codyspate/marty-plugin-cargo", "0.2.0")
+            .resolve_github_plugin_url(
+                "codyspate/marty-plugin-cargo",
+                "0.2.0",
+                &PlatformInfo::current(),
+            )
             .unwrap();
 
         // URL should contain the repository, version, and platform info
@@ -608,12 +1754,16 @@ mod tests {
     #[test]
     fn test_resolve_github_plugin_url_format() {
         let cache = PluginCache {
+            workspace_root: PathBuf::from("/tmp"),
             cache_dir: PathBuf::from("/tmp"),
             client: reqwest::Client::new(),
+            signing: PluginSigningConfig::default(),
+            bundle: None,
+            limits: PluginCacheConfig::default(),
         };
 
         let url = cache
-            .resolve_github_plugin_url("user/marty-plugin-test", "1.0.0")
+            .resolve_github_plugin_url("user/marty-plugin-test", "1.0.0", &PlatformInfo::current())
             .unwrap();
 
         // Verify URL structure
@@ -626,12 +1776,21 @@ mod tests {
     #[test]
     fn test_resolve_github_plugin_url_monorepo() {
         let cache = PluginCache {
+            workspace_root: PathBuf::from("/tmp"),
             cache_dir: PathBuf::from("/tmp"),
             client: reqwest::Client::new(),
+            signing: PluginSigningConfig::default(),
+            bundle: None,
+            limits: PluginCacheConfig::default(),
         };
 
         let url = cache
-            .resolve_github_plugin_url_monorepo("codyspate/marty", "typescript", "0.2.2")
+            .resolve_github_plugin_url_monorepo(
+                "codyspate/marty",
+                "typescript",
+                "0.2.2",
+                &PlatformInfo::current(),
+            )
             .unwrap();
 
         // URL should contain the repository, plugin name, and version
@@ -0,0 +1,626 @@
+//! Behavioral conformance checks for plugin dynamic libraries
+//!
+//! [`run_conformance`] loads a plugin binary and exercises its C ABI directly: symbol
+//! presence, metadata consistency, null and invalid UTF-8 inputs, large inputs, the shape
+//! of returned JSON, and memory growth over many calls. Each check produces a
+//! [`ConformanceCheck`] so callers can render a pass/fail report.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+
+use libloading::Library;
+use marty_plugin_protocol::dylib::{methods, CallError, ABI_VERSION};
+use marty_plugin_protocol::{InferredProjectMessage, PluginKey, WorkspaceMutation};
+use serde_json::Value;
+
+use crate::types::{MartyError, MartyResult};
+
+type StringFn = unsafe extern "C" fn() -> *const c_char;
+type TypeFn = unsafe extern "C" fn() -> u8;
+type FileFoundFn = unsafe extern "C" fn(*const c_char, *const c_char) -> *const c_char;
+type ScanCompleteFn = unsafe extern "C" fn(*const c_char) -> *const c_char;
+type CleanupFn = unsafe extern "C" fn(*const c_char);
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type CallFn = unsafe extern "C" fn(*const u8, usize, *const u8, usize, *mut u8, usize) -> isize;
+
+const REQUIRED_SYMBOLS: &[&str] = &[
+    "plugin_name",
+    "plugin_key",
+    "plugin_type",
+    "plugin_include_globs",
+    "plugin_on_file_found",
+    "plugin_cleanup_string",
+];
+const OPTIONAL_SYMBOLS: &[&str] = &[
+    "plugin_exclude_globs",
+    "plugin_config_options",
+    "plugin_on_scan_complete",
+    "plugin_abi_version",
+    "plugin_call",
+];
+
+/// Calls made per function when checking for memory growth
+const LEAK_CHECK_ITERATIONS: usize = 20_000;
+/// Resident memory growth over the leak check that is reported as a likely leak
+const LEAK_THRESHOLD_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConformanceStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Outcome of a single conformance check
+#[derive(Debug, Clone)]
+pub struct ConformanceCheck {
+    pub name: String,
+    pub status: ConformanceStatus,
+    pub detail: String,
+}
+
+/// All checks run against a plugin binary
+#[derive(Debug, Clone)]
+pub struct ConformanceReport {
+    pub path: PathBuf,
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl ConformanceReport {
+    /// Whether no check failed (warnings are allowed)
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != ConformanceStatus::Fail)
+    }
+
+    fn record(&mut self, name: &str, status: ConformanceStatus, detail: impl Into<String>) {
+        self.checks.push(ConformanceCheck {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    fn pass(&mut self, name: &str, detail: impl Into<String>) {
+        self.record(name, ConformanceStatus::Pass, detail);
+    }
+
+    fn warn(&mut self, name: &str, detail: impl Into<String>) {
+        self.record(name, ConformanceStatus::Warn, detail);
+    }
+
+    fn fail(&mut self, name: &str, detail: impl Into<String>) {
+        self.record(name, ConformanceStatus::Fail, detail);
+    }
+}
+
+/// Raw access to a plugin's exported functions.
+///
+/// Every string returned by the plugin is copied and handed back to
+/// `plugin_cleanup_string` before the copy is inspected.
+struct PluginLibrary {
+    library: Library,
+}
+
+impl PluginLibrary {
+    fn has(&self, symbol: &str) -> bool {
+        unsafe {
+            self.library
+                .get::<unsafe extern "C" fn()>(symbol.as_bytes())
+        }
+        .is_ok()
+    }
+
+    /// Copy and free a string returned by the plugin. `Ok(None)` means a null pointer.
+    fn take_string(&self, ptr: *const c_char) -> Result<Option<String>, String> {
+        if ptr.is_null() {
+            return Ok(None);
+        }
+        let copy = unsafe { CStr::from_ptr(ptr) }.to_bytes().to_vec();
+        if let Ok(cleanup) = unsafe { self.library.get::<CleanupFn>(b"plugin_cleanup_string") } {
+            unsafe { cleanup(ptr) };
+        }
+        String::from_utf8(copy)
+            .map(Some)
+            .map_err(|_| "returned invalid UTF-8".to_string())
+    }
+
+    fn call_string(&self, symbol: &str) -> Result<Option<String>, String> {
+        let func = unsafe { self.library.get::<StringFn>(symbol.as_bytes()) }
+            .map_err(|_| format!("{} is not exported", symbol))?;
+        self.take_string(unsafe { func() })
+    }
+
+    fn call_type(&self) -> Result<u8, String> {
+        let func = unsafe { self.library.get::<TypeFn>(b"plugin_type") }
+            .map_err(|_| "plugin_type is not exported".to_string())?;
+        Ok(unsafe { func() })
+    }
+
+    fn call_file_found(
+        &self,
+        path: Option<&[u8]>,
+        contents: Option<&[u8]>,
+    ) -> Result<Option<String>, String> {
+        let func = unsafe { self.library.get::<FileFoundFn>(b"plugin_on_file_found") }
+            .map_err(|_| "plugin_on_file_found is not exported".to_string())?;
+        let path = path
+            .map(CString::new)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let contents = contents
+            .map(CString::new)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let ptr = unsafe {
+            func(
+                path.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()),
+                contents.as_ref().map_or(std::ptr::null(), |c| c.as_ptr()),
+            )
+        };
+        self.take_string(ptr)
+    }
+
+    fn call_scan_complete(&self, workspace_json: Option<&str>) -> Result<Option<String>, String> {
+        let func = unsafe {
+            self.library
+                .get::<ScanCompleteFn>(b"plugin_on_scan_complete")
+        }
+        .map_err(|_| "plugin_on_scan_complete is not exported".to_string())?;
+        let workspace = workspace_json
+            .map(CString::new)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let ptr = unsafe { func(workspace.as_ref().map_or(std::ptr::null(), |w| w.as_ptr())) };
+        self.take_string(ptr)
+    }
+
+    /// Call `plugin_call` with an output buffer of `capacity` bytes, returning the raw
+    /// result and the bytes written (when the response fit)
+    fn call_buffer(
+        &self,
+        method: &str,
+        input: &[u8],
+        capacity: usize,
+    ) -> Result<(isize, Vec<u8>), String> {
+        let func = unsafe { self.library.get::<CallFn>(b"plugin_call") }
+            .map_err(|_| "plugin_call is not exported".to_string())?;
+        let mut buffer = vec![0u8; capacity];
+        let result = unsafe {
+            func(
+                method.as_ptr(),
+                method.len(),
+                input.as_ptr(),
+                input.len(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            )
+        };
+        if let Ok(len) = usize::try_from(result) {
+            buffer.truncate(len);
+        }
+        Ok((result, buffer))
+    }
+}
+
+/// Run every conformance check against the plugin at `path`
+pub fn run_conformance(path: &Path) -> MartyResult<ConformanceReport> {
+    let library = unsafe { Library::new(path) }.map_err(|e| {
+        MartyError::Config(format!("Failed to load plugin {}: {}", path.display(), e))
+    })?;
+    let plugin = PluginLibrary { library };
+
+    let mut report = ConformanceReport {
+        path: path.to_path_buf(),
+        checks: Vec::new(),
+    };
+
+    check_symbols(&plugin, &mut report);
+    if !report.passed() {
+        // Calling into a plugin with missing required symbols only produces noise
+        return Ok(report);
+    }
+
+    let key = check_metadata(&plugin, &mut report);
+    let include_globs = check_json_functions(&plugin, &mut report);
+    check_file_found(&plugin, &mut report, key.as_deref(), &include_globs)?;
+    check_scan_complete(&plugin, &mut report);
+    check_buffer_abi(&plugin, &mut report);
+    check_memory_growth(&plugin, &mut report);
+
+    Ok(report)
+}
+
+fn check_symbols(plugin: &PluginLibrary, report: &mut ConformanceReport) {
+    let missing = REQUIRED_SYMBOLS
+        .iter()
+        .filter(|symbol| !plugin.has(symbol))
+        .copied()
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        report.pass("required symbols", "all required functions are exported");
+    } else {
+        report.fail(
+            "required symbols",
+            format!("missing: {}", missing.join(", ")),
+        );
+    }
+
+    let missing = OPTIONAL_SYMBOLS
+        .iter()
+        .filter(|symbol| !plugin.has(symbol))
+        .copied()
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        report.pass("optional symbols", "all optional functions are exported");
+    } else {
+        report.warn(
+            "optional symbols",
+            format!(
+                "not exported: {} (rebuild against the latest marty_plugin_protocol)",
+                missing.join(", ")
+            ),
+        );
+    }
+}
+
+/// Check name, key and type, returning the key when it is valid
+fn check_metadata(plugin: &PluginLibrary, report: &mut ConformanceReport) -> Option<String> {
+    match plugin.call_string("plugin_name") {
+        Ok(Some(name)) if !name.trim().is_empty() => report.pass("name", name),
+        Ok(Some(_)) => report.fail("name", "plugin_name returned an empty string"),
+        Ok(None) => report.fail("name", "plugin_name returned null"),
+        Err(e) => report.fail("name", format!("plugin_name {}", e)),
+    }
+
+    let key = match plugin.call_string("plugin_key") {
+        Ok(Some(key)) => match PluginKey::new(key.clone()) {
+            Ok(_) if !key.is_empty() => {
+                report.pass("key", key.clone());
+                Some(key)
+            }
+            Ok(_) => {
+                report.fail("key", "plugin_key returned an empty string");
+                None
+            }
+            Err(e) => {
+                report.fail("key", e);
+                None
+            }
+        },
+        Ok(None) => {
+            report.fail("key", "plugin_key returned null");
+            None
+        }
+        Err(e) => {
+            report.fail("key", format!("plugin_key {}", e));
+            None
+        }
+    };
+
+    match plugin.call_type() {
+        Ok(value @ 0..=2) => report.pass("type", format!("plugin_type returned {}", value)),
+        Ok(value) => report.fail(
+            "type",
+            format!("plugin_type returned unknown value {}", value),
+        ),
+        Err(e) => report.fail("type", e),
+    }
+
+    // Metadata must not change between calls
+    let stable = (0..10).all(|_| {
+        plugin.call_string("plugin_name").ok().flatten().is_some()
+            && plugin.call_string("plugin_key").ok().flatten() == key
+            && plugin.call_type().ok().is_some_and(|value| value <= 2)
+    });
+    if stable {
+        report.pass(
+            "metadata consistency",
+            "name, key and type are stable across calls",
+        );
+    } else {
+        report.fail(
+            "metadata consistency",
+            "name, key or type changed between calls",
+        );
+    }
+
+    key
+}
+
+/// Check the JSON returning functions, returning the include globs
+fn check_json_functions(plugin: &PluginLibrary, report: &mut ConformanceReport) -> Vec<String> {
+    let mut include_globs = Vec::new();
+
+    for (symbol, required) in [
+        ("plugin_include_globs", true),
+        ("plugin_exclude_globs", false),
+    ] {
+        if !plugin.has(symbol) {
+            continue;
+        }
+        match plugin.call_string(symbol) {
+            Ok(Some(json)) => match serde_json::from_str::<Vec<String>>(&json) {
+                Ok(globs) => {
+                    let invalid = globs
+                        .iter()
+                        .filter(|glob| globset::Glob::new(glob).is_err())
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if invalid.is_empty() {
+                        report.pass(symbol, format!("{} glob(s)", globs.len()));
+                    } else {
+                        report.fail(symbol, format!("invalid globs: {}", invalid.join(", ")));
+                    }
+                    if symbol == "plugin_include_globs" {
+                        include_globs = globs;
+                    }
+                }
+                Err(e) => report.fail(symbol, format!("not a JSON array of strings: {}", e)),
+            },
+            Ok(None) if required => report.warn(symbol, "returned null; every file is scanned"),
+            Ok(None) => report.pass(symbol, "returned null"),
+            Err(e) => report.fail(symbol, e),
+        }
+    }
+
+    if plugin.has("plugin_config_options") {
+        match plugin.call_string("plugin_config_options") {
+            Ok(Some(json)) => match serde_json::from_str::<Value>(&json) {
+                Ok(Value::Object(_)) => report.pass("plugin_config_options", "JSON schema object"),
+                Ok(_) => report.fail("plugin_config_options", "not a JSON object"),
+                Err(e) => report.fail("plugin_config_options", format!("invalid JSON: {}", e)),
+            },
+            Ok(None) => report.pass("plugin_config_options", "no options"),
+            Err(e) => report.fail("plugin_config_options", e),
+        }
+    }
+
+    include_globs
+}
+
+/// A `plugin_on_file_found` result is acceptable when it is "no project" or a valid project
+fn validate_file_found(result: Result<Option<String>, String>) -> Result<(), String> {
+    match result? {
+        None => Ok(()),
+        Some(json) if json.trim().is_empty() || json.trim() == "null" => Ok(()),
+        Some(json) => serde_json::from_str::<InferredProjectMessage>(&json)
+            .map(|_| ())
+            .map_err(|e| format!("returned an invalid project: {}", e)),
+    }
+}
+
+fn check_file_found(
+    plugin: &PluginLibrary,
+    report: &mut ConformanceReport,
+    key: Option<&str>,
+    include_globs: &[String],
+) -> MartyResult<()> {
+    let name = "null inputs";
+    match validate_file_found(plugin.call_file_found(None, None)) {
+        Ok(()) => report.pass(name, "null path and contents are handled"),
+        Err(e) => report.fail(name, e),
+    }
+
+    let name = "invalid UTF-8";
+    match validate_file_found(
+        plugin.call_file_found(Some(b"/tmp/\xff\xfe/package.json"), Some(b"\xc3\x28")),
+    ) {
+        Ok(()) => report.pass(name, "invalid UTF-8 path and contents are handled"),
+        Err(e) => report.fail(name, e),
+    }
+
+    let name = "missing file";
+    match validate_file_found(
+        plugin.call_file_found(Some(b"/nonexistent/marty/conformance"), Some(b"")),
+    ) {
+        Ok(()) => report.pass(name, "paths that don't exist are handled"),
+        Err(e) => report.fail(name, e),
+    }
+
+    let name = "large input";
+    let long_path = format!("/{}/file", "a".repeat(64 * 1024));
+    let large_contents = "x".repeat(4 * 1024 * 1024);
+    match validate_file_found(
+        plugin.call_file_found(Some(long_path.as_bytes()), Some(large_contents.as_bytes())),
+    ) {
+        Ok(()) => report.pass(name, "64 KiB path and 4 MiB contents are handled"),
+        Err(e) => report.fail(name, e),
+    }
+
+    // Feed an empty file named like each literal include glob and check returned projects
+    let temp = tempfile::tempdir()?;
+    let name = "returned projects";
+    let mut problems = Vec::new();
+    let mut projects = 0;
+    for file_name in include_globs
+        .iter()
+        .filter_map(|glob| glob.rsplit('/').next())
+        .filter(|file| !file.is_empty() && !file.contains(['*', '?', '[', '{']))
+    {
+        let project_dir = temp.path().join("project");
+        let file = project_dir.join(file_name);
+        std::fs::create_dir_all(&project_dir)?;
+        std::fs::write(&file, "")?;
+
+        let path = file.to_string_lossy().into_owned();
+        match plugin.call_file_found(Some(path.as_bytes()), Some(b"")) {
+            Ok(Some(json)) if json.trim() != "null" && !json.trim().is_empty() => {
+                match serde_json::from_str::<InferredProjectMessage>(&json) {
+                    Ok(project) => {
+                        projects += 1;
+                        if key.is_some_and(|key| project.discovered_by != key) {
+                            problems.push(format!(
+                                "discovered_by '{}' doesn't match key '{}'",
+                                project.discovered_by,
+                                key.unwrap_or_default()
+                            ));
+                        }
+                        if project.name.is_empty() {
+                            problems.push(format!("empty project name for {}", file_name));
+                        }
+                    }
+                    Err(e) => problems.push(format!("invalid project for {}: {}", file_name, e)),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(format!("{}: {}", file_name, e)),
+        }
+    }
+    if !problems.is_empty() {
+        report.fail(name, problems.join("; "));
+    } else if projects == 0 {
+        report.pass(name, "no project returned for empty manifests");
+    } else {
+        report.pass(
+            name,
+            format!("{} project(s) with consistent fields", projects),
+        );
+    }
+
+    Ok(())
+}
+
+fn check_scan_complete(plugin: &PluginLibrary, report: &mut ConformanceReport) {
+    if !plugin.has("plugin_on_scan_complete") {
+        return;
+    }
+
+    let name = "plugin_on_scan_complete";
+    let workspace = r#"{"root":"/tmp","projects":[],"inferred_projects":[]}"#;
+    let results = [
+        plugin.call_scan_complete(Some(workspace)),
+        plugin.call_scan_complete(None),
+    ];
+    let problem = results.into_iter().find_map(|result| match result {
+        Ok(None) => None,
+        Ok(Some(json)) => serde_json::from_str::<Vec<WorkspaceMutation>>(&json)
+            .err()
+            .map(|e| format!("not a JSON array of mutations: {}", e)),
+        Err(e) => Some(e),
+    });
+    match problem {
+        None => report.pass(
+            name,
+            "returns mutations for an empty workspace and handles null",
+        ),
+        Some(problem) => report.fail(name, problem),
+    }
+}
+
+fn check_buffer_abi(plugin: &PluginLibrary, report: &mut ConformanceReport) {
+    if !plugin.has("plugin_call") {
+        return;
+    }
+
+    let name = "plugin_call";
+    let version = unsafe { plugin.library.get::<AbiVersionFn>(b"plugin_abi_version") }
+        .map(|version_fn| unsafe { version_fn() })
+        .ok();
+    if version != Some(ABI_VERSION) {
+        report.fail(
+            name,
+            format!(
+                "plugin_abi_version must return {} when plugin_call is exported",
+                ABI_VERSION
+            ),
+        );
+        return;
+    }
+
+    let mut problems = Vec::new();
+    match plugin.call_buffer(methods::NAME, &[], 0) {
+        Ok((needed, _)) if needed > 0 => {
+            match plugin.call_buffer(methods::NAME, &[], needed as usize) {
+                Ok((len, bytes)) if len == needed => {
+                    let legacy = plugin.call_string("plugin_name").ok().flatten();
+                    if serde_json::from_slice::<String>(&bytes).ok() != legacy {
+                        problems.push("name doesn't match plugin_name".to_string());
+                    }
+                }
+                Ok((len, _)) => problems.push(format!(
+                    "name needed {} bytes but {} were returned on retry",
+                    needed, len
+                )),
+                Err(e) => problems.push(e),
+            }
+        }
+        Ok((result, _)) => problems.push(format!(
+            "a zero-capacity buffer must return the response size, got {}",
+            result
+        )),
+        Err(e) => problems.push(e),
+    }
+
+    match plugin.call_buffer("marty/conformance/unknown", &[], 64) {
+        Ok((result, _)) if result == CallError::UnknownMethod.code() => {}
+        Ok((result, _)) => problems.push(format!(
+            "unknown methods must return {}, got {}",
+            CallError::UnknownMethod.code(),
+            result
+        )),
+        Err(e) => problems.push(e),
+    }
+
+    match plugin.call_buffer(methods::ON_FILE_FOUND, b"not json", 64) {
+        Ok((result, _)) if result == CallError::InvalidInput.code() => {}
+        Ok((result, _)) => problems.push(format!(
+            "malformed input must return {}, got {}",
+            CallError::InvalidInput.code(),
+            result
+        )),
+        Err(e) => problems.push(e),
+    }
+
+    if problems.is_empty() {
+        report.pass(
+            name,
+            "sizes responses, matches legacy metadata and rejects bad calls",
+        );
+    } else {
+        report.fail(name, problems.join("; "));
+    }
+}
+
+/// Resident set size of this process, where the platform exposes it cheaply
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(pages * 4096)
+}
+
+fn check_memory_growth(plugin: &PluginLibrary, report: &mut ConformanceReport) {
+    let name = "memory growth";
+    let exercise = |iterations: usize| {
+        for _ in 0..iterations {
+            let _ = plugin.call_string("plugin_name");
+            let _ = plugin.call_string("plugin_key");
+            let _ = plugin.call_string("plugin_include_globs");
+            let _ = plugin.call_file_found(Some(b"/nonexistent/marty/conformance"), Some(b""));
+            let _ = plugin.call_buffer(methods::INCLUDE_GLOBS, &[], 1024);
+        }
+    };
+
+    // Warm up so one-time allocations don't count as growth
+    exercise(100);
+    let Some(before) = resident_memory() else {
+        report.warn(name, "memory usage can't be measured on this platform");
+        return;
+    };
+    exercise(LEAK_CHECK_ITERATIONS);
+    let after = resident_memory().unwrap_or(before);
+
+    let growth = after.saturating_sub(before);
+    let detail = format!(
+        "{} KiB growth over {} calls per function",
+        growth / 1024,
+        LEAK_CHECK_ITERATIONS
+    );
+    if growth > LEAK_THRESHOLD_BYTES {
+        report.warn(name, format!("{}; returned strings may be leaking", detail));
+    } else {
+        report.pass(name, detail);
+    }
+}
@@ -0,0 +1,160 @@
+//! Problems plugins report during discovery
+//!
+//! Plugins collect [`Diagnostic`]s while they are offered files and hand them over through
+//! `WorkspaceProvider::take_diagnostics()` once discovery is finished. Marty attributes each
+//! one to the project its file belongs to, prints it to stderr and, for the JSON log,
+//! reports it as a `plugin_diagnostic` event.
+
+use std::fmt;
+use std::path::Path;
+
+use colored::*;
+use marty_plugin_protocol::{Diagnostic, DiagnosticLevel};
+use serde::Serialize;
+
+use crate::workspace::{find_owning_project, Workspace};
+
+/// A diagnostic with the plugin that reported it and the project it is about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PluginDiagnostic {
+    pub plugin: String,
+    /// The project owning the diagnostic's file, tracked or only inferred
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+}
+
+impl PluginDiagnostic {
+    /// Attribute `diagnostic` to a project of `workspace`. The file is shown relative to the
+    /// workspace root from then on.
+    pub fn new(workspace: &Workspace, plugin: &str, mut diagnostic: Diagnostic) -> Self {
+        let file = diagnostic
+            .file
+            .as_ref()
+            .map(|file| workspace.root.join(file));
+        let project = file
+            .as_deref()
+            .and_then(|file| owning_project(workspace, file));
+        if let Some(relative) = file
+            .as_deref()
+            .and_then(|file| file.strip_prefix(&workspace.root).ok())
+        {
+            diagnostic.file = Some(relative.display().to_string());
+        }
+
+        Self {
+            plugin: plugin.to_string(),
+            project,
+            diagnostic,
+        }
+    }
+}
+
+/// The deepest project containing `file`, preferring projects with a marty.yml
+fn owning_project(workspace: &Workspace, file: &Path) -> Option<String> {
+    if let Some(project) = find_owning_project(workspace, file) {
+        return Some(project.name.clone());
+    }
+    workspace
+        .inferred_projects
+        .iter()
+        .filter(|project| file.starts_with(&project.project_dir))
+        .max_by_key(|project| project.project_dir.components().count())
+        .map(|project| project.name.clone())
+}
+
+impl fmt::Display for PluginDiagnostic {
+    /// `warning[cargo] api: crates/api/Cargo.toml:3:5: message`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = self.diagnostic.level.to_string();
+        let level = match self.diagnostic.level {
+            DiagnosticLevel::Error => level.red().bold(),
+            DiagnosticLevel::Warning => level.yellow().bold(),
+            DiagnosticLevel::Info => level.cyan(),
+        };
+        write!(f, "{}[{}]", level, self.plugin)?;
+        if let Some(project) = &self.project {
+            write!(f, " {}", project)?;
+        }
+        write!(f, ":")?;
+        if let Some(file) = &self.diagnostic.file {
+            write!(f, " {}", file)?;
+            if let Some(span) = &self.diagnostic.span {
+                write!(f, ":{}:{}", span.start_line, span.start_column)?;
+            }
+            write!(f, ":")?;
+        }
+        write!(f, " {}", self.diagnostic.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::{InferredProject, Project, ProjectIndex};
+    use marty_plugin_protocol::Span;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_diagnostics_are_attributed_to_the_deepest_project() {
+        let root = PathBuf::from("/ws");
+        let inferred = |name: &str, dir: &str| InferredProject {
+            name: name.to_string(),
+            project_dir: root.join(dir),
+            discovered_by: "cargo".to_string(),
+            workspace_dependencies: Vec::new(),
+            kind: None,
+        };
+        let projects = vec![Project {
+            name: "api".to_string(),
+            project_dir: root.join("crates/api"),
+            file_path: None,
+            dependencies: Vec::new(),
+        }];
+        let inferred_projects = vec![
+            inferred("api", "crates/api"),
+            inferred("tools", "tools"),
+            inferred("codegen", "tools/codegen"),
+        ];
+        let workspace = Workspace {
+            index: ProjectIndex::new(["api"], &projects, &inferred_projects),
+            root: root.clone(),
+            projects,
+            inferred_projects,
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+        };
+
+        let diagnostic = |file: &str| {
+            PluginDiagnostic::new(
+                &workspace,
+                "cargo",
+                Diagnostic::warning("missing name").with_file(file),
+            )
+        };
+        let api = diagnostic("/ws/crates/api/Cargo.toml");
+        assert_eq!(api.project.as_deref(), Some("api"));
+        assert_eq!(
+            api.diagnostic.file.as_deref(),
+            Some("crates/api/Cargo.toml")
+        );
+        assert_eq!(
+            diagnostic("tools/codegen/Cargo.toml").project.as_deref(),
+            Some("codegen")
+        );
+        assert_eq!(diagnostic("/ws/Cargo.toml").project, None);
+
+        let located = PluginDiagnostic {
+            diagnostic: api.diagnostic.clone().with_span(Span::line(3)),
+            ..api
+        };
+        assert_eq!(
+            located.to_string(),
+            "warning[cargo] api: crates/api/Cargo.toml:3:1: missing name"
+        );
+        let plain = PluginDiagnostic::new(&workspace, "cargo", Diagnostic::error("no manifest"));
+        assert_eq!(plain.to_string(), "error[cargo]: no manifest");
+    }
+}
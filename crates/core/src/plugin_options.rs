@@ -0,0 +1,239 @@
+//! Readable documentation for plugin options
+//!
+//! Plugins describe the `options` they accept in workspace.yml with a JSON schema returned
+//! from `configuration_options()`. This module flattens that schema into one entry per
+//! option and builds an example `options` block, so the documentation always matches the
+//! plugin binary that is actually in use. Tasks a plugin suggests are rendered as a task
+//! file the same way.
+
+use marty_plugin_protocol::SuggestedTask;
+use serde_json::{Map, Value};
+use serde_yaml::Mapping;
+
+use crate::types::MartyResult;
+
+/// A single option accepted by a plugin
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionDoc {
+    /// Dotted path of the option, e.g. `compilerOptions.strict`
+    pub path: String,
+    /// Human-readable type, e.g. `array of string`
+    pub type_name: String,
+    pub required: bool,
+    pub default: Option<Value>,
+    pub description: Option<String>,
+    /// Allowed values, when the schema restricts them
+    pub allowed: Vec<Value>,
+}
+
+/// Flatten an object schema into its options, nested objects included
+pub fn describe_options(schema: &Value) -> Vec<OptionDoc> {
+    let mut docs = Vec::new();
+    collect_options(schema, "", &mut docs);
+    docs
+}
+
+fn collect_options(schema: &Value, prefix: &str, docs: &mut Vec<OptionDoc>) {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut names: Vec<&String> = properties.keys().collect();
+    names.sort();
+    for name in names {
+        let property = &properties[name];
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+
+        docs.push(OptionDoc {
+            path: path.clone(),
+            type_name: type_name(property),
+            required: required.contains(&name.as_str()),
+            default: property.get("default").cloned(),
+            description: property
+                .get("description")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            allowed: property
+                .get("enum")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+        });
+        collect_options(property, &path, docs);
+    }
+}
+
+/// Describe the type of a schema, following array item types
+fn type_name(schema: &Value) -> String {
+    let base = match schema.get("type") {
+        Some(Value::String(name)) => name.clone(),
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" | "),
+        _ if schema.get("enum").is_some() => "enum".to_string(),
+        _ => "any".to_string(),
+    };
+
+    match schema.get("items") {
+        Some(items) if base == "array" => format!("array of {}", type_name(items)),
+        _ => base,
+    }
+}
+
+/// Example `options` value for a plugin, using defaults where the schema provides them
+pub fn example_options(schema: &Value) -> Value {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Value::Object(Map::new());
+    };
+
+    properties
+        .iter()
+        .map(|(name, property)| (name.clone(), example_value(property)))
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+/// Example `options:` block for a plugin entry in workspace.yml, as YAML
+pub fn example_yaml(schema: &Value) -> MartyResult<String> {
+    let mut block = Map::new();
+    block.insert("options".to_string(), example_options(schema));
+    Ok(serde_yaml::to_string(&block)?)
+}
+
+fn example_value(schema: &Value) -> Value {
+    if let Some(default) = schema.get("default") {
+        return default.clone();
+    }
+    if let Some(first) = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|values| values.first())
+    {
+        return first.clone();
+    }
+
+    let type_name = match schema.get("type") {
+        Some(Value::String(name)) => name.as_str(),
+        Some(Value::Array(names)) => names.iter().find_map(Value::as_str).unwrap_or("null"),
+        _ => "null",
+    };
+    match type_name {
+        "object" => example_options(schema),
+        "array" => Value::Array(Vec::new()),
+        "string" => Value::String(String::new()),
+        "boolean" => Value::Bool(false),
+        "integer" | "number" => Value::from(0),
+        _ => Value::Null,
+    }
+}
+
+/// A task file defining the `tasks` a plugin suggests, bound to projects tagged with the
+/// plugin's key
+pub fn suggested_tasks_yaml(key: &str, tasks: &[SuggestedTask]) -> MartyResult<String> {
+    // A YAML mapping keeps the keys in the order a person would write them
+    let tasks: Vec<Mapping> = tasks
+        .iter()
+        .map(|task| {
+            let mut entry = Mapping::new();
+            entry.insert("name".into(), task.name.as_str().into());
+            if let Some(description) = &task.description {
+                entry.insert("description".into(), description.as_str().into());
+            }
+            entry.insert("command".into(), task.command.as_str().into());
+            if !task.dependencies.is_empty() {
+                entry.insert("dependencies".into(), task.dependencies.clone().into());
+            }
+            entry
+        })
+        .collect();
+    let mut file = Mapping::new();
+    file.insert("tags".into(), vec![key.to_string()].into());
+    file.insert("tasks".into(), tasks.into());
+    Ok(serde_yaml::to_string(&file)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_describes_nested_options_and_builds_example() {
+        let schema = json!({
+            "type": "object",
+            "required": ["mode"],
+            "properties": {
+                "includes": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Extra globs",
+                    "default": ["libs/**"]
+                },
+                "mode": { "enum": ["fast", "full"] },
+                "compiler": {
+                    "type": "object",
+                    "properties": { "strict": { "type": "boolean" } }
+                }
+            }
+        });
+
+        let docs = describe_options(&schema);
+        let paths: Vec<&str> = docs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["compiler", "compiler.strict", "includes", "mode"]);
+        assert_eq!(docs[2].type_name, "array of string");
+        assert_eq!(docs[2].description.as_deref(), Some("Extra globs"));
+        assert!(docs[3].required);
+        assert_eq!(docs[3].type_name, "enum");
+        assert_eq!(docs[3].allowed, vec![json!("fast"), json!("full")]);
+
+        assert_eq!(
+            example_options(&schema),
+            json!({
+                "includes": ["libs/**"],
+                "mode": "fast",
+                "compiler": { "strict": false }
+            })
+        );
+        assert!(example_yaml(&schema)
+            .unwrap()
+            .starts_with("options:\n  compiler:\n    strict: false\n"));
+    }
+
+    #[test]
+    fn test_suggested_tasks_become_a_task_file() {
+        let tasks = [
+            SuggestedTask {
+                name: "lint".to_string(),
+                command: "helm lint .".to_string(),
+                description: Some("Lint the chart".to_string()),
+                dependencies: vec![],
+            },
+            SuggestedTask {
+                name: "package".to_string(),
+                command: "helm package .".to_string(),
+                description: None,
+                dependencies: vec!["lint".to_string()],
+            },
+        ];
+        let yaml = suggested_tasks_yaml("helm", &tasks).unwrap();
+        let config = crate::configs::tasks::parse_tasks_config(&yaml).unwrap();
+        assert_eq!(config.tags, Some(vec!["helm".to_string()]));
+        assert_eq!(config.tasks.len(), 2);
+        assert_eq!(
+            config.tasks[0].description.as_deref(),
+            Some("Lint the chart")
+        );
+        assert_eq!(config.tasks[1].dependencies, Some(vec!["lint".to_string()]));
+    }
+}
@@ -0,0 +1,321 @@
+//! Plugin registry index
+//!
+//! A registry is a JSON file, usually hosted on GitHub, listing plugins by name with the
+//! repository their releases are published in and the versions available.
+//! `marty plugin search` looks plugins up in it, and `marty plugin add` resolves a plugin's
+//! latest version and adds its entry to workspace.yml. Workspaces can point
+//! `pluginRegistry` at their own index.
+
+use std::cmp::Ordering;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::configs::workspace::{parse_workspace_config, PluginConfig};
+
+/// Index of the plugins maintained in the Marty repository
+pub const DEFAULT_REGISTRY_URL: &str =
+    "https://raw.githubusercontent.com/codyspate/marty/main/plugins/registry.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryIndex {
+    pub plugins: Vec<RegistryPlugin>,
+}
+
+/// A plugin listed in a registry
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryPlugin {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Repository the plugin's releases are published in, as "owner/repo"
+    pub github_repo: String,
+    /// Name of the plugin in a repository that releases several
+    pub plugin: Option<String>,
+    pub versions: Vec<String>,
+}
+
+impl RegistryIndex {
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Invalid plugin registry index")
+    }
+
+    /// Download the index at `url`
+    pub async fn fetch(client: &reqwest::Client, url: &str) -> Result<Self> {
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch plugin registry {}", url))?;
+        if !response.status().is_success() {
+            bail!(
+                "Failed to fetch plugin registry {}: HTTP {}",
+                url,
+                response.status()
+            );
+        }
+        let body = response
+            .text()
+            .await
+            .with_context(|| format!("Failed to read plugin registry {}", url))?;
+        Self::parse(&body).with_context(|| format!("Failed to read plugin registry {}", url))
+    }
+
+    /// Plugins whose name or description contains `term`, ignoring case, ordered by name
+    pub fn search(&self, term: &str) -> Vec<&RegistryPlugin> {
+        let term = term.to_lowercase();
+        let mut found: Vec<_> = self
+            .plugins
+            .iter()
+            .filter(|plugin| {
+                plugin.name.to_lowercase().contains(&term)
+                    || plugin.description.to_lowercase().contains(&term)
+            })
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        found
+    }
+
+    pub fn find(&self, name: &str) -> Option<&RegistryPlugin> {
+        self.plugins.iter().find(|plugin| plugin.name == name)
+    }
+}
+
+impl RegistryPlugin {
+    /// The highest version listed, pre-releases only if there is nothing else
+    pub fn latest_version(&self) -> Option<&str> {
+        self.versions
+            .iter()
+            .filter(|version| !version.contains('-'))
+            .max_by(|a, b| compare_versions(a, b))
+            .or_else(|| self.versions.iter().max_by(|a, b| compare_versions(a, b)))
+            .map(String::as_str)
+    }
+
+    /// The workspace.yml entry for one of the plugin's versions
+    pub fn plugin_config(&self, version: &str) -> PluginConfig {
+        PluginConfig {
+            github_repo: Some(self.github_repo.clone()),
+            plugin: self.plugin.clone(),
+            version: Some(version.to_string()),
+            url: None,
+            path: None,
+            public_key: None,
+            writable: None,
+            enabled: None,
+            options: None,
+        }
+    }
+}
+
+/// Orders `1.10.0` after `1.9.0`, and a pre-release like `1.0.0-rc.1` before `1.0.0`
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn parts(version: &str) -> (Vec<u64>, Option<&str>) {
+        let version = version.trim_start_matches('v');
+        let (release, pre) = match version.split_once('-') {
+            Some((release, pre)) => (release, Some(pre)),
+            None => (version, None),
+        };
+        let numbers = release
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        (numbers, pre)
+    }
+
+    let (a_numbers, a_pre) = parts(a);
+    let (b_numbers, b_pre) = parts(b);
+    a_numbers
+        .cmp(&b_numbers)
+        .then_with(|| match (a_pre, b_pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        })
+}
+
+/// Add `entry` to the `plugins` list of a workspace.yml, keeping the rest of the file (and
+/// its comments) as it is
+pub fn add_plugin_entry(workspace_yaml: &str, entry: &PluginConfig) -> Result<String> {
+    let config = parse_workspace_config(workspace_yaml)?;
+    let existing = config.plugins.unwrap_or_default();
+    if let Some(configured) = existing
+        .iter()
+        .find(|plugin| plugin.github_repo == entry.github_repo && plugin.plugin == entry.plugin)
+    {
+        bail!(
+            "The plugin is already configured in workspace.yml ({})",
+            configured.source_description()
+        );
+    }
+
+    let lines: Vec<&str> = workspace_yaml.lines().collect();
+    let updated = match lines.iter().position(|line| is_plugins_key(line)) {
+        None => {
+            let mut updated = workspace_yaml.trim_end().to_string();
+            if !updated.is_empty() {
+                updated.push('\n');
+            }
+            updated.push_str("plugins:\n");
+            updated.push_str(&entry_lines(entry, "  "));
+            updated
+        }
+        Some(key) if lines[key].trim_end().ends_with("[]") => {
+            let mut updated: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            updated[key] = "plugins:".to_string();
+            updated.insert(key + 1, entry_lines(entry, "  ").trim_end().to_string());
+            updated.join("\n") + "\n"
+        }
+        Some(key) => {
+            // The list ends at the next line that starts another top-level key
+            let end = lines[key + 1..]
+                .iter()
+                .position(|line| {
+                    !line.trim().is_empty()
+                        && !line.starts_with(char::is_whitespace)
+                        && !line.starts_with('-')
+                        && !line.starts_with('#')
+                })
+                .map_or(lines.len(), |offset| key + 1 + offset);
+            let last = (key + 1..end)
+                .rev()
+                .find(|&i| !lines[i].trim().is_empty() && !lines[i].trim_start().starts_with('#'))
+                .unwrap_or(key);
+            let indent = lines[key + 1..end]
+                .iter()
+                .find(|line| line.trim_start().starts_with('-'))
+                .map(|line| &line[..line.len() - line.trim_start().len()])
+                .unwrap_or("  ");
+
+            let mut updated: Vec<String> = lines[..=last].iter().map(|l| l.to_string()).collect();
+            updated.push(entry_lines(entry, indent).trim_end().to_string());
+            updated.extend(lines[last + 1..].iter().map(|l| l.to_string()));
+            updated.join("\n") + "\n"
+        }
+    };
+
+    // Only hand back a file that still parses, with exactly the new entry added
+    let plugins = parse_workspace_config(&updated)
+        .ok()
+        .and_then(|config| config.plugins)
+        .unwrap_or_default();
+    if plugins.len() != existing.len() + 1 {
+        bail!("Could not add the plugin to workspace.yml automatically; add this entry to `plugins` instead:\n{}", entry_lines(entry, "  "));
+    }
+    Ok(updated)
+}
+
+fn is_plugins_key(line: &str) -> bool {
+    line.strip_prefix("plugins:")
+        .map(|rest| {
+            let rest = rest.trim();
+            rest.is_empty() || rest.starts_with('#') || rest == "[]"
+        })
+        .unwrap_or(false)
+}
+
+/// The entry as a YAML list item, each line prefixed by `indent`
+fn entry_lines(entry: &PluginConfig, indent: &str) -> String {
+    let mut fields = Vec::new();
+    if let Some(repo) = &entry.github_repo {
+        fields.push(format!("githubRepo: \"{}\"", repo));
+    }
+    if let Some(plugin) = &entry.plugin {
+        fields.push(format!("plugin: \"{}\"", plugin));
+    }
+    if let Some(version) = &entry.version {
+        fields.push(format!("version: \"{}\"", version));
+    }
+
+    let mut lines = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        let marker = if i == 0 { "- " } else { "  " };
+        lines.push_str(&format!("{}{}{}\n", indent, marker, field));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> RegistryIndex {
+        RegistryIndex::parse(
+            r#"{
+                "plugins": [
+                    {
+                        "name": "helm",
+                        "description": "Discovers Helm charts",
+                        "githubRepo": "codyspate/marty",
+                        "plugin": "helm",
+                        "versions": ["0.9.0", "0.10.0", "0.11.0-rc.1"]
+                    },
+                    {
+                        "name": "cargo",
+                        "description": "Discovers Rust crates",
+                        "githubRepo": "acme/marty-plugin-cargo",
+                        "versions": ["1.0.0"]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_search_and_latest_version() {
+        let index = index();
+        let names = |term| {
+            index
+                .search(term)
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("CHARTS"), vec!["helm"]);
+        assert_eq!(names("discovers"), vec!["cargo", "helm"]);
+        assert!(names("python").is_empty());
+
+        assert_eq!(index.find("helm").unwrap().latest_version(), Some("0.10.0"));
+        assert!(index.find("pnpm").is_none());
+    }
+
+    #[test]
+    fn test_bundled_registry_is_valid() {
+        let index = RegistryIndex::parse(include_str!("../../../plugins/registry.json")).unwrap();
+        for plugin in &index.plugins {
+            assert!(plugin.latest_version().is_some(), "{}", plugin.name);
+        }
+    }
+
+    #[test]
+    fn test_add_plugin_entry_keeps_the_rest_of_the_file() {
+        let index = index();
+        let helm = index.find("helm").unwrap().plugin_config("0.10.0");
+
+        let yaml = "name: demo\n# Plugins\nplugins:\n    - path: \"plugins/local.so\" # ours\n\n# Caching\ncache:\n  enabled: true\n";
+        let updated = add_plugin_entry(yaml, &helm).unwrap();
+        assert_eq!(
+            updated,
+            "name: demo\n# Plugins\nplugins:\n    - path: \"plugins/local.so\" # ours\n    - githubRepo: \"codyspate/marty\"\n      plugin: \"helm\"\n      version: \"0.10.0\"\n\n# Caching\ncache:\n  enabled: true\n"
+        );
+        assert!(add_plugin_entry(&updated, &helm)
+            .unwrap_err()
+            .to_string()
+            .contains("already configured"));
+
+        let cargo = index.find("cargo").unwrap().plugin_config("1.0.0");
+        let updated = add_plugin_entry("name: demo\nplugins: []\n", &cargo).unwrap();
+        assert_eq!(
+            updated,
+            "name: demo\nplugins:\n  - githubRepo: \"acme/marty-plugin-cargo\"\n    version: \"1.0.0\"\n"
+        );
+        let updated = add_plugin_entry("name: demo\n", &cargo).unwrap();
+        assert!(updated.ends_with(
+            "plugins:\n  - githubRepo: \"acme/marty-plugin-cargo\"\n    version: \"1.0.0\"\n"
+        ));
+    }
+}
@@ -0,0 +1,716 @@
+//! Plugin loading, and the runtime for WebAssembly component plugins
+//!
+//! Plugins are dynamic libraries (see [`plugin_runtime_dylib`](crate::plugin_runtime_dylib))
+//! or, for users who prefer a sandbox, `.wasm` components built against the world in
+//! [`marty_plugin_protocol::wasm::WIT`]. Components run in wasmtime with read access to
+//! the workspace directory only, within the limits of a [`WasmSandbox`], and speak the
+//! same JSON methods as dynamic libraries. Loading components needs the `wasm-plugins`
+//! feature.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use marty_plugin_protocol::MartyPlugin;
+
+use crate::plugin_runtime_dylib::DylibWorkspaceProvider;
+
+/// File extensions of the plugins Marty loads
+pub const PLUGIN_EXTENSIONS: [&str; 4] = ["so", "dylib", "dll", "wasm"];
+
+/// Whether `path` looks like a plugin Marty can load
+pub fn is_plugin_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| PLUGIN_EXTENSIONS.contains(&extension))
+}
+
+/// What a WebAssembly plugin may do. Dynamic libraries aren't sandboxed.
+#[derive(Debug, Clone, Copy)]
+pub struct WasmSandbox {
+    /// Let the plugin write files under the workspace root, not just read them
+    pub writable: bool,
+    /// Most linear memory the plugin may allocate, in bytes
+    pub max_memory: usize,
+    /// Longest a single call into the plugin may run before it is interrupted
+    pub call_timeout: Duration,
+}
+
+impl Default for WasmSandbox {
+    fn default() -> Self {
+        Self {
+            writable: false,
+            max_memory: 512 * 1024 * 1024,
+            call_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Load the plugin at `path`: a WebAssembly component if it ends in `.wasm`, which may
+/// only read files under `workspace_root`, or else a dynamic library
+pub fn load_plugin(path: &Path, workspace_root: &Path) -> Result<Box<dyn MartyPlugin>> {
+    load_plugin_with(path, workspace_root, &WasmSandbox::default())
+}
+
+/// Like [`load_plugin`], running a WebAssembly component in `sandbox`
+pub fn load_plugin_with(
+    path: &Path,
+    workspace_root: &Path,
+    sandbox: &WasmSandbox,
+) -> Result<Box<dyn MartyPlugin>> {
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "wasm")
+    {
+        return load_component(path, workspace_root, sandbox);
+    }
+    let provider = DylibWorkspaceProvider::from_dylib_with_temp_copy(path.to_path_buf())?;
+    Ok(Box::new(provider))
+}
+
+#[cfg(feature = "wasm-plugins")]
+fn load_component(
+    path: &Path,
+    workspace_root: &Path,
+    sandbox: &WasmSandbox,
+) -> Result<Box<dyn MartyPlugin>> {
+    let provider = wasm::WasmWorkspaceProvider::from_component(path, workspace_root, sandbox)?;
+    Ok(Box::new(provider))
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+fn load_component(
+    path: &Path,
+    _workspace_root: &Path,
+    _sandbox: &WasmSandbox,
+) -> Result<Box<dyn MartyPlugin>> {
+    anyhow::bail!(
+        "{} is a WebAssembly plugin, but this build of Marty was built without the \
+         wasm-plugins feature",
+        path.display()
+    )
+}
+
+#[cfg(feature = "wasm-plugins")]
+pub use wasm::WasmWorkspaceProvider;
+
+#[cfg(feature = "wasm-plugins")]
+mod wasm {
+    use std::path::{Path, PathBuf};
+    use std::sync::{mpsc, OnceLock};
+    use std::time::Duration;
+
+    use anyhow::{Context, Result};
+    use marty_plugin_protocol::dylib::{
+        methods, CallError, FileFoundResponse, ProjectCreatedRequest, RunTaskRequest, ABI_VERSION,
+    };
+    use marty_plugin_protocol::{
+        Diagnostic, FilesFound, InferredProject, InferredProjectMessage, MartyPlugin, PluginError,
+        PluginType, Project, ProjectMessage, SuggestedTask, TaskOutcome, Workspace,
+        WorkspaceMutation, WorkspaceProvider,
+    };
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::Value;
+    use wasmtime::component::{Component, Linker, ResourceTable, TypedFunc};
+    use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder};
+    use wasmtime_wasi::{DirPerms, FilePerms, IoView, WasiCtx, WasiCtxBuilder, WasiView};
+
+    use super::WasmSandbox;
+
+    type CallFn = TypedFunc<(String, Vec<u8>), (Result<Vec<u8>, i32>,)>;
+
+    /// How often the shared engine's epoch advances, the granularity of call timeouts
+    const EPOCH_TICK: Duration = Duration::from_millis(100);
+
+    /// The engine every component runs on, with a thread advancing its epoch so calls
+    /// running past their deadline are interrupted
+    fn engine() -> Result<Engine> {
+        static ENGINE: OnceLock<Engine> = OnceLock::new();
+        if let Some(engine) = ENGINE.get() {
+            return Ok(engine.clone());
+        }
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+        Ok(ENGINE
+            .get_or_init(|| {
+                let ticking = engine.clone();
+                let _ = std::thread::Builder::new()
+                    .name("plugin epochs".to_string())
+                    .spawn(move || loop {
+                        std::thread::sleep(EPOCH_TICK);
+                        ticking.increment_epoch();
+                    });
+                engine
+            })
+            .clone())
+    }
+
+    /// A method call sent to the thread running a component
+    struct Call {
+        method: &'static str,
+        input: Vec<u8>,
+        reply: mpsc::Sender<Result<Vec<u8>>>,
+    }
+
+    /// What a component may access through WASI
+    struct Sandbox {
+        ctx: WasiCtx,
+        table: ResourceTable,
+        limits: StoreLimits,
+    }
+
+    impl IoView for Sandbox {
+        fn table(&mut self) -> &mut ResourceTable {
+            &mut self.table
+        }
+    }
+
+    impl WasiView for Sandbox {
+        fn ctx(&mut self) -> &mut WasiCtx {
+            &mut self.ctx
+        }
+    }
+
+    /// An instantiated component, owned by the thread that runs it
+    struct Instance {
+        store: Store<Sandbox>,
+        call: CallFn,
+        /// Epoch ticks each call may run for
+        deadline: u64,
+    }
+
+    impl Instance {
+        fn new(path: &Path, workspace_root: &Path, sandbox: &WasmSandbox) -> Result<Self> {
+            let engine = engine()?;
+            let component = Component::from_file(&engine, path)
+                .with_context(|| format!("Failed to compile plugin: {}", path.display()))?;
+            let mut linker = Linker::new(&engine);
+            wasmtime_wasi::add_to_linker_sync(&mut linker)?;
+
+            let (dir_perms, file_perms) = if sandbox.writable {
+                (DirPerms::all(), FilePerms::all())
+            } else {
+                (DirPerms::READ, FilePerms::READ)
+            };
+            let root = workspace_root.to_string_lossy();
+            let ctx = WasiCtxBuilder::new()
+                .inherit_stderr()
+                .preopened_dir(workspace_root, &root, dir_perms, file_perms)
+                .with_context(|| format!("Failed to open {} for the plugin", root))?
+                .build();
+            let mut store = Store::new(
+                &engine,
+                Sandbox {
+                    ctx,
+                    table: ResourceTable::new(),
+                    limits: StoreLimitsBuilder::new()
+                        .memory_size(sandbox.max_memory)
+                        .build(),
+                },
+            );
+            store.limiter(|sandbox| &mut sandbox.limits);
+            let deadline = (sandbox.call_timeout.as_millis() / EPOCH_TICK.as_millis()).max(1);
+            let deadline = u64::try_from(deadline).unwrap_or(u64::MAX);
+            store.set_epoch_deadline(deadline);
+
+            let instance = linker
+                .instantiate(&mut store, &component)
+                .context("Failed to instantiate plugin")?;
+            let abi_version = instance
+                .get_typed_func::<(), (u32,)>(&mut store, "abi-version")
+                .context("Plugin doesn't export abi-version")?;
+            store.set_epoch_deadline(deadline);
+            let (version,) = abi_version.call(&mut store, ())?;
+            abi_version.post_return(&mut store)?;
+            if version != ABI_VERSION {
+                anyhow::bail!(
+                    "the plugin uses plugin ABI version {}, but this version of Marty loads \
+                     WebAssembly plugins of version {}",
+                    version,
+                    ABI_VERSION
+                );
+            }
+            let call = instance
+                .get_typed_func(&mut store, "call")
+                .context("Plugin doesn't export call")?;
+            Ok(Self {
+                store,
+                call,
+                deadline,
+            })
+        }
+
+        fn call(&mut self, method: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+            self.store.set_epoch_deadline(self.deadline);
+            let (response,) = self
+                .call
+                .call(&mut self.store, (method.to_string(), input))?;
+            self.call.post_return(&mut self.store)?;
+            response.map_err(|code| match CallError::from_code(code as isize) {
+                Some(error) => {
+                    anyhow::Error::new(error).context(format!("Plugin call '{}' failed", method))
+                }
+                None => anyhow::anyhow!("Plugin call '{}' failed with code {}", method, code),
+            })
+        }
+    }
+
+    /// A plugin built as a WebAssembly component.
+    ///
+    /// The component runs on a thread of its own, which keeps WASI's blocking calls out of
+    /// the tokio runtime Marty runs in.
+    pub struct WasmWorkspaceProvider {
+        name: String,
+        key: String,
+        plugin_type: PluginType,
+        description: Option<String>,
+        version: Option<String>,
+        homepage: Option<String>,
+        calls: mpsc::Sender<Call>,
+    }
+
+    impl WasmWorkspaceProvider {
+        /// Load a component that may access files under `workspace_root` as `sandbox` allows
+        pub fn from_component(
+            path: &Path,
+            workspace_root: &Path,
+            sandbox: &WasmSandbox,
+        ) -> Result<Self> {
+            let (calls, requests) = mpsc::channel::<Call>();
+            let (ready, loaded) = mpsc::channel();
+            let (path, workspace_root): (PathBuf, PathBuf) = (path.into(), workspace_root.into());
+            let sandbox = *sandbox;
+            std::thread::Builder::new()
+                .name(format!("plugin {}", path.display()))
+                .spawn(move || {
+                    let mut instance = match Instance::new(&path, &workspace_root, &sandbox) {
+                        Ok(instance) => instance,
+                        Err(e) => {
+                            let _ = ready.send(Err(e));
+                            return;
+                        }
+                    };
+                    let _ = ready.send(Ok(()));
+                    // Runs until the provider, and with it the sender, is dropped
+                    for call in requests {
+                        let _ = call.reply.send(instance.call(call.method, call.input));
+                    }
+                })
+                .context("Failed to start plugin thread")?;
+            loaded
+                .recv()
+                .context("Plugin thread stopped while loading")??;
+
+            let mut provider = Self {
+                name: String::new(),
+                key: String::new(),
+                plugin_type: PluginType::Primary,
+                description: None,
+                version: None,
+                homepage: None,
+                calls,
+            };
+            provider.name = provider.call(methods::NAME, &())?;
+            provider.key = provider.call(methods::KEY, &())?;
+            provider.plugin_type = match provider.call::<u8>(methods::TYPE, &())? {
+                0 => PluginType::Primary,
+                1 => PluginType::Supplemental,
+                2 => PluginType::Hook,
+                value => anyhow::bail!("Invalid plugin type value: {}", value),
+            };
+            // Plugins built before they could describe themselves don't know these
+            provider.description = provider
+                .call_if_supported::<Option<String>>(methods::DESCRIPTION, &())?
+                .flatten();
+            provider.version = provider
+                .call_if_supported::<Option<String>>(methods::VERSION, &())?
+                .flatten();
+            provider.homepage = provider
+                .call_if_supported::<Option<String>>(methods::HOMEPAGE, &())?
+                .flatten();
+            Ok(provider)
+        }
+
+        /// Call `method` with `input` as JSON and parse the JSON response
+        fn call<T: DeserializeOwned>(
+            &self,
+            method: &'static str,
+            input: &impl Serialize,
+        ) -> Result<T> {
+            let (reply, response) = mpsc::channel();
+            self.calls
+                .send(Call {
+                    method,
+                    input: serde_json::to_vec(input)?,
+                    reply,
+                })
+                .map_err(|_| anyhow::anyhow!("plugin thread stopped"))?;
+            let response = response
+                .recv()
+                .map_err(|_| anyhow::anyhow!("plugin thread stopped"))??;
+            Ok(serde_json::from_slice(&response)?)
+        }
+
+        /// Like [`Self::call`], but `None` if the plugin doesn't know the method
+        fn call_if_supported<T: DeserializeOwned>(
+            &self,
+            method: &'static str,
+            input: &impl Serialize,
+        ) -> Result<Option<T>> {
+            match self.call(method, input) {
+                Err(e) if e.downcast_ref::<CallError>() == Some(&CallError::UnknownMethod) => {
+                    Ok(None)
+                }
+                outcome => outcome.map(Some),
+            }
+        }
+    }
+
+    /// The borrowed form of [`FileFoundRequest`](marty_plugin_protocol::dylib::FileFoundRequest)
+    #[derive(Serialize)]
+    struct FileFoundInput<'a> {
+        path: &'a str,
+        workspace: &'a Workspace,
+    }
+
+    /// The borrowed form of [`InitializeRequest`](marty_plugin_protocol::dylib::InitializeRequest)
+    #[derive(Serialize)]
+    struct InitializeInput<'a> {
+        workspace_root: std::borrow::Cow<'a, str>,
+        options: &'a Value,
+    }
+
+    /// The borrowed form of [`FilesFoundRequest`](marty_plugin_protocol::dylib::FilesFoundRequest)
+    #[derive(Serialize)]
+    struct FilesFoundInput<'a> {
+        paths: Vec<std::borrow::Cow<'a, str>>,
+        workspace: &'a Workspace,
+    }
+
+    impl WorkspaceProvider for WasmWorkspaceProvider {
+        fn include_path_globs(&self) -> Vec<String> {
+            self.call(methods::INCLUDE_GLOBS, &()).unwrap_or_default()
+        }
+
+        fn exclude_path_globs(&self) -> Vec<String> {
+            self.call(methods::EXCLUDE_GLOBS, &()).unwrap_or_default()
+        }
+
+        fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+            let path = path.to_string_lossy();
+            let input = FileFoundInput {
+                path: &path,
+                workspace,
+            };
+            self.call::<Option<InferredProjectMessage>>(methods::ON_FILE_FOUND, &input)
+                .ok()?
+                .map(InferredProject::from)
+        }
+
+        fn on_file_found_messages(
+            &self,
+            workspace: &Workspace,
+            path: &Path,
+        ) -> Result<Vec<ProjectMessage>, PluginError> {
+            let file = path.to_string_lossy();
+            let input = FileFoundInput {
+                path: &file,
+                workspace,
+            };
+            if let Ok(Some(response)) =
+                self.call_if_supported::<FileFoundResponse>(methods::ON_FILE_FOUND_RESULT, &input)
+            {
+                return response.into();
+            }
+            match self.call_if_supported(methods::ON_FILE_FOUND_MESSAGES, &input) {
+                Ok(Some(messages)) => Ok(messages),
+                _ => Ok(self
+                    .on_file_found(workspace, path)
+                    .into_iter()
+                    .map(ProjectMessage::from)
+                    .collect()),
+            }
+        }
+
+        fn on_files_found(&self, workspace: &Workspace, paths: &[PathBuf]) -> Option<FilesFound> {
+            let input = FilesFoundInput {
+                paths: paths.iter().map(|path| path.to_string_lossy()).collect(),
+                workspace,
+            };
+            self.call_if_supported::<Option<FilesFound>>(methods::ON_FILES_FOUND, &input)
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Warning: Plugin '{}' failed to process its files at once, offering them one at a time: {}",
+                        self.name, e
+                    );
+                    None
+                })
+                .flatten()
+        }
+
+        fn on_scan_complete(&self, workspace: &Workspace) -> Vec<WorkspaceMutation> {
+            self.call(methods::ON_SCAN_COMPLETE, workspace)
+                .unwrap_or_default()
+        }
+
+        fn take_diagnostics(&self) -> Vec<Diagnostic> {
+            self.call_if_supported(methods::TAKE_DIAGNOSTICS, &())
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Warning: Failed to get diagnostics of plugin '{}': {}",
+                        self.name, e
+                    );
+                    None
+                })
+                .unwrap_or_default()
+        }
+    }
+
+    impl MartyPlugin for WasmWorkspaceProvider {
+        fn plugin_type(&self) -> PluginType {
+            self.plugin_type
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn key(&self) -> &str {
+            &self.key
+        }
+
+        fn description(&self) -> Option<&str> {
+            self.description.as_deref()
+        }
+
+        fn version(&self) -> Option<&str> {
+            self.version.as_deref()
+        }
+
+        fn homepage(&self) -> Option<&str> {
+            self.homepage.as_deref()
+        }
+
+        fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+            self
+        }
+
+        fn configuration_options(&self) -> Option<Value> {
+            self.call(methods::CONFIG_OPTIONS, &()).unwrap_or_default()
+        }
+
+        fn configure(&self, options: &Value) {
+            if let Err(e) = self.call_if_supported::<()>(methods::CONFIGURE, options) {
+                eprintln!("Warning: Failed to configure plugin '{}': {}", self.name, e);
+            }
+        }
+
+        fn initialize(&self, workspace_root: &Path, options: &Value) {
+            let input = InitializeInput {
+                workspace_root: workspace_root.to_string_lossy(),
+                options,
+            };
+            match self.call_if_supported::<()>(methods::INITIALIZE, &input) {
+                // Components built before the lifecycle existed only take their options
+                Ok(None) if !options.is_null() => self.configure(options),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Failed to initialize plugin '{}': {}",
+                        self.name, e
+                    )
+                }
+            }
+        }
+
+        fn shutdown(&self) {
+            if let Err(e) = self.call_if_supported::<()>(methods::SHUTDOWN, &()) {
+                eprintln!("Warning: Failed to shut down plugin '{}': {}", self.name, e);
+            }
+        }
+
+        fn suggested_tasks(&self) -> Vec<SuggestedTask> {
+            self.call_if_supported(methods::SUGGESTED_TASKS, &())
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        }
+
+        fn default_tasks(&self) -> Vec<SuggestedTask> {
+            self.call_if_supported(methods::DEFAULT_TASKS, &())
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Warning: Failed to get default tasks of plugin '{}': {}",
+                        self.name, e
+                    );
+                    None
+                })
+                .unwrap_or_default()
+        }
+
+        fn post_discovery(&self, workspace: &Workspace) -> Vec<String> {
+            self.call_if_supported(methods::POST_DISCOVERY, workspace)
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Warning: Plugin '{}' failed after discovery: {}",
+                        self.name, e
+                    );
+                    None
+                })
+                .unwrap_or_default()
+        }
+
+        fn on_project_created(&self, workspace: &Workspace, project_dir: &Path) -> Vec<String> {
+            let request = ProjectCreatedRequest {
+                project_dir: project_dir.to_string_lossy().to_string(),
+                workspace: workspace.clone(),
+            };
+            self.call_if_supported(methods::ON_PROJECT_CREATED, &request)
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Warning: Plugin '{}' failed to register a new project: {}",
+                        self.name, e
+                    );
+                    None
+                })
+                .unwrap_or_default()
+        }
+
+        fn run_task(&self, task: &str, project: &Project, workspace: &Workspace) -> TaskOutcome {
+            let request = RunTaskRequest {
+                task: task.to_string(),
+                project: project.clone(),
+                workspace: workspace.clone(),
+            };
+            self.call(methods::RUN_TASK, &request).unwrap_or_else(|e| {
+                TaskOutcome::failure(format!(
+                    "Plugin '{}' failed to run task '{}': {}",
+                    self.name, task, e
+                ))
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// A component answering the metadata methods, in the text format wasmtime also loads
+        const NOTES_COMPONENT: &str = r#"
+(component
+  (core module $m
+    (memory (export "memory") 1)
+    (global $heap (mut i32) (i32.const 1024))
+    (data (i32.const 0) "\"Wasm notes\"")
+    (data (i32.const 16) "\"notes\"")
+    (data (i32.const 32) "1")
+    (data (i32.const 48) "[\"**/NOTES\"]")
+    (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr (global.get $heap))
+      (global.set $heap (i32.add (global.get $heap) (local.get 3)))
+      (local.get $ptr))
+    (func $respond (param $tag i32) (param $first i32) (param $second i32) (result i32)
+      (i32.store8 (i32.const 512) (local.get $tag))
+      (i32.store (i32.const 516) (local.get $first))
+      (i32.store (i32.const 520) (local.get $second))
+      (i32.const 512))
+    (func (export "abi-version") (result i32) (i32.const 2))
+    (func (export "call") (param $method i32) (param i32 i32 i32) (result i32)
+      (local $first i32)
+      (local.set $first (i32.load8_u (local.get $method)))
+      (if (i32.eq (local.get $first) (i32.const 110))
+        (then (return (call $respond (i32.const 0) (i32.const 0) (i32.const 12)))))
+      (if (i32.eq (local.get $first) (i32.const 107))
+        (then (return (call $respond (i32.const 0) (i32.const 16) (i32.const 7)))))
+      (if (i32.eq (local.get $first) (i32.const 116))
+        (then (return (call $respond (i32.const 0) (i32.const 32) (i32.const 1)))))
+      (if (i32.eq (local.get $first) (i32.const 105))
+        (then (return (call $respond (i32.const 0) (i32.const 48) (i32.const 12)))))
+      (call $respond (i32.const 1) (i32.const -1) (i32.const 0)))
+    (func (export "cabi_post_call") (param i32)))
+  (core instance $i (instantiate $m))
+  (func (export "abi-version") (result u32) (canon lift (core func $i "abi-version")))
+  (func (export "call") (param "method" string) (param "input" (list u8))
+    (result (result (list u8) (error s32)))
+    (canon lift (core func $i "call") (memory (core memory $i "memory"))
+      (realloc (core func $i "cabi_realloc")) (post-return (core func $i "cabi_post_call")))))
+"#;
+
+        #[test]
+        fn test_components_load_from_wasm_files() {
+            let temp = tempfile::tempdir().unwrap();
+            let path = temp.path().join("notes.wasm");
+            std::fs::write(&path, NOTES_COMPONENT).unwrap();
+
+            let plugin = crate::plugin_runtime::load_plugin(&path, temp.path()).unwrap();
+            assert_eq!(plugin.name(), "Wasm notes");
+            assert_eq!(plugin.key(), "notes");
+            assert_eq!(plugin.plugin_type(), PluginType::Supplemental);
+            let provider = plugin.workspace_provider();
+            assert_eq!(provider.include_path_globs(), vec!["**/NOTES"]);
+            // Methods the component rejects fall back like they do for older dylibs
+            assert!(provider.exclude_path_globs().is_empty());
+            assert!(plugin.default_tasks().is_empty());
+            assert!(
+                !plugin
+                    .run_task("stamp", &project(temp.path()), &workspace(temp.path()))
+                    .success
+            );
+        }
+
+        #[test]
+        fn test_components_are_held_to_the_sandbox_limits() {
+            let temp = tempfile::tempdir().unwrap();
+            let path = temp.path().join("spin.wasm");
+            // Every call spins forever
+            let spinning = NOTES_COMPONENT.replace(
+                "(local $first i32)",
+                "(local $first i32)\n      (loop $spin (br $spin))",
+            );
+            std::fs::write(&path, spinning).unwrap();
+            let sandbox = WasmSandbox {
+                call_timeout: Duration::from_millis(200),
+                ..WasmSandbox::default()
+            };
+            let started = std::time::Instant::now();
+            let Err(error) = crate::plugin_runtime::load_plugin_with(&path, temp.path(), &sandbox)
+            else {
+                panic!("a spinning plugin loaded");
+            };
+            assert!(format!("{:?}", error).contains("interrupt"), "{:?}", error);
+            assert!(started.elapsed() < Duration::from_secs(10));
+
+            // One page of memory is already too much
+            std::fs::write(&path, NOTES_COMPONENT).unwrap();
+            let sandbox = WasmSandbox {
+                max_memory: 1024,
+                ..WasmSandbox::default()
+            };
+            let Err(error) = crate::plugin_runtime::load_plugin_with(&path, temp.path(), &sandbox)
+            else {
+                panic!("a plugin over its memory limit loaded");
+            };
+            assert!(format!("{:?}", error).contains("memory"), "{:?}", error);
+        }
+
+        fn project(root: &Path) -> Project {
+            Project {
+                name: "app".to_string(),
+                project_dir: root.to_path_buf(),
+                file_path: None,
+                dependencies: Vec::new(),
+            }
+        }
+
+        fn workspace(root: &Path) -> Workspace {
+            Workspace {
+                root: root.to_path_buf(),
+                projects: Vec::new(),
+                inferred_projects: Vec::new(),
+            }
+        }
+    }
+}
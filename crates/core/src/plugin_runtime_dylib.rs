@@ -5,25 +5,116 @@ use std::sync::Mutex;
 
 use anyhow::{Context, Result};
 use libloading::{Library, Symbol};
+use marty_plugin_protocol::dylib::{
+    methods, CallError, FileFoundResponse, ProjectCreatedRequest, RunTaskRequest, ABI_VERSION,
+    MIN_ABI_VERSION,
+};
 use marty_plugin_protocol::{
-    InferredProject, MartyPlugin, PluginType, Workspace, WorkspaceProvider,
+    Diagnostic, FilesFound, InferredProject, InferredProjectMessage, MartyPlugin, PluginError,
+    PluginType, Project, ProjectMessage, SuggestedTask, TaskOutcome, Workspace, WorkspaceMutation,
+    WorkspaceProvider,
 };
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
 
 /// Plugin function signatures for the C ABI interface
-type PluginNameFn = unsafe extern "C" fn() -> *const c_char;
-type PluginKeyFn = unsafe extern "C" fn() -> *const c_char;
+type PluginAbiVersionFn = unsafe extern "C" fn() -> u32;
+type PluginCallFn =
+    unsafe extern "C" fn(*const u8, usize, *const u8, usize, *mut u8, usize) -> isize;
+type PluginStringFn = unsafe extern "C" fn() -> *const c_char;
 type PluginTypeFn = unsafe extern "C" fn() -> u8;
-type PluginConfigOptionsFn = unsafe extern "C" fn() -> *const c_char;
 type PluginOnFileFoundFn = extern "C" fn(*const c_char, *const c_char) -> *const c_char;
+type PluginOnScanCompleteFn = extern "C" fn(*const c_char) -> *const c_char;
 type PluginCleanupStringFn = extern "C" fn(*const c_char);
 
+/// Initial size of the response buffer passed to `plugin_call`
+const INITIAL_RESPONSE_CAPACITY: usize = 16 * 1024;
+
+/// Refuse plugins built against a plugin ABI this version of Marty can't talk to
+fn check_abi_version(abi_version: u32) -> Result<()> {
+    if abi_version > ABI_VERSION {
+        anyhow::bail!(
+            "the plugin uses plugin ABI version {}, but this version of Marty supports up to \
+             version {}. Update Marty, or use an older release of the plugin",
+            abi_version,
+            ABI_VERSION
+        );
+    }
+    if abi_version < MIN_ABI_VERSION {
+        anyhow::bail!(
+            "the plugin uses plugin ABI version {}, but this version of Marty needs at least \
+             version {}. Update the plugin",
+            abi_version,
+            MIN_ABI_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// The borrowed form of [`FileFoundRequest`](marty_plugin_protocol::dylib::FileFoundRequest),
+/// so the workspace isn't copied for every file
+#[derive(Serialize)]
+struct FileFoundInput<'a> {
+    path: &'a str,
+    workspace: &'a Workspace,
+}
+
+/// The borrowed form of [`FilesFoundRequest`](marty_plugin_protocol::dylib::FilesFoundRequest)
+#[derive(Serialize)]
+struct FilesFoundInput<'a> {
+    paths: Vec<std::borrow::Cow<'a, str>>,
+    workspace: &'a Workspace,
+}
+
+/// The borrowed form of [`InitializeRequest`](marty_plugin_protocol::dylib::InitializeRequest)
+#[derive(Serialize)]
+struct InitializeInput<'a> {
+    workspace_root: std::borrow::Cow<'a, str>,
+    options: &'a Value,
+}
+
+/// A string allocated by a legacy plugin. It is handed back to `plugin_cleanup_string`
+/// when dropped, so early returns while parsing can't leak it.
+struct PluginString<'lib> {
+    library: &'lib Library,
+    ptr: *const c_char,
+}
+
+impl<'lib> PluginString<'lib> {
+    fn new(library: &'lib Library, ptr: *const c_char) -> Option<Self> {
+        (!ptr.is_null()).then_some(Self { library, ptr })
+    }
+
+    fn to_str(&self) -> Result<&str> {
+        unsafe { CStr::from_ptr(self.ptr) }
+            .to_str()
+            .context("Plugin returned invalid UTF-8")
+    }
+}
+
+impl Drop for PluginString<'_> {
+    fn drop(&mut self) {
+        if let Ok(cleanup_fn) = unsafe {
+            self.library
+                .get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
+        } {
+            cleanup_fn(self.ptr);
+        }
+    }
+}
+
 /// A workspace provider that loads and interacts with dynamic library plugins
 pub struct DylibWorkspaceProvider {
     name: String,
     key: String,
     plugin_type: PluginType,
+    description: Option<String>,
+    version: Option<String>,
+    homepage: Option<String>,
     library: Library,
+    /// Whether the plugin exports the buffer-based `plugin_call`
+    buffer_abi: bool,
     _temp_dir: Option<tempfile::TempDir>, // Hold onto temp dir to prevent cleanup
     call_lock: Mutex<()>,                 // Prevent concurrent calls to the same plugin
 }
@@ -39,6 +130,100 @@ impl DylibWorkspaceProvider {
         &self.key
     }
 
+    /// Get the JSON schema of the options this plugin accepts
+    pub fn configuration_options(&self) -> Option<Value> {
+        self.call_json_function(methods::CONFIG_OPTIONS, b"plugin_config_options")
+            .unwrap_or_default()
+    }
+
+    /// Pass the plugin its options from workspace.yml. Plugins built before options were
+    /// delivered ignore them.
+    pub fn configure(&self, options: &Value) -> Result<()> {
+        self.call_optional(methods::CONFIGURE, &serde_json::to_vec(options)?, || Ok(()))
+    }
+
+    /// Set the plugin up for the session. Plugins built before the lifecycle existed only
+    /// receive their options, if there are any.
+    pub fn initialize(&self, workspace_root: &Path, options: &Value) -> Result<()> {
+        let input = serde_json::to_vec(&InitializeInput {
+            workspace_root: workspace_root.to_string_lossy(),
+            options,
+        })?;
+        self.call_optional(methods::INITIALIZE, &input, || {
+            if options.is_null() {
+                Ok(())
+            } else {
+                self.configure(options)
+            }
+        })
+    }
+
+    /// Tell the plugin Marty is done with it. Plugins built before the lifecycle existed
+    /// have nothing to release.
+    pub fn shutdown(&self) -> Result<()> {
+        self.call_optional(methods::SHUTDOWN, &[], || Ok(()))
+    }
+
+    /// Get the tasks the plugin suggests for its projects. Plugins built before suggestions
+    /// existed have none.
+    pub fn suggested_tasks(&self) -> Result<Vec<SuggestedTask>> {
+        self.call_optional(methods::SUGGESTED_TASKS, &[], || Ok(Vec::new()))
+    }
+
+    /// Get the tasks the plugin defines for its projects. Plugins built before default
+    /// tasks existed define none.
+    pub fn default_tasks(&self) -> Result<Vec<SuggestedTask>> {
+        self.call_optional(methods::DEFAULT_TASKS, &[], || Ok(Vec::new()))
+    }
+
+    /// Offer the plugin all its files at once. `None` if it handles files one at a time,
+    /// which plugins built before batches existed all do.
+    pub fn on_files_found(
+        &self,
+        workspace: &Workspace,
+        paths: &[PathBuf],
+    ) -> Result<Option<FilesFound>> {
+        let input = serde_json::to_vec(&FilesFoundInput {
+            paths: paths.iter().map(|path| path.to_string_lossy()).collect(),
+            workspace,
+        })?;
+        self.call_optional(methods::ON_FILES_FOUND, &input, || Ok(None))
+    }
+
+    /// Get the diagnostics the plugin collected while scanning. Plugins built before
+    /// diagnostics existed report none.
+    pub fn take_diagnostics(&self) -> Result<Vec<Diagnostic>> {
+        self.call_optional(methods::TAKE_DIAGNOSTICS, &[], || Ok(Vec::new()))
+    }
+
+    /// Hand the plugin the discovered workspace and get its notes about what it changed.
+    /// Plugins built before this phase existed do nothing.
+    pub fn post_discovery(&self, workspace: &Workspace) -> Result<Vec<String>> {
+        self.call_optional(
+            methods::POST_DISCOVERY,
+            &serde_json::to_vec(workspace)?,
+            || Ok(Vec::new()),
+        )
+    }
+
+    /// Tell the plugin about a newly created project and get its notes about what it
+    /// changed. Plugins built before this hook existed do nothing.
+    pub fn on_project_created(&self, request: &ProjectCreatedRequest) -> Result<Vec<String>> {
+        self.call_optional(
+            methods::ON_PROJECT_CREATED,
+            &serde_json::to_vec(request)?,
+            || Ok(Vec::new()),
+        )
+    }
+
+    /// Run one of the plugin's own tasks on a project. Plugins built before plugins could
+    /// run tasks refuse.
+    pub fn run_task(&self, request: &RunTaskRequest) -> Result<TaskOutcome> {
+        self.call_optional(methods::RUN_TASK, &serde_json::to_vec(request)?, || {
+            anyhow::bail!("the plugin is too old to run tasks")
+        })
+    }
+
     /// Load a plugin from a dynamic library file
     pub fn from_dylib(dylib_path: PathBuf) -> Result<Self> {
         // Load the dynamic library
@@ -48,19 +233,7 @@ impl DylibWorkspaceProvider {
             })?
         };
 
-        // Get plugin metadata
-        let name = Self::get_plugin_name(&library)?;
-        let key = Self::get_plugin_key(&library)?;
-        let plugin_type = Self::get_plugin_type(&library)?;
-
-        Ok(Self {
-            name,
-            key,
-            plugin_type,
-            library,
-            _temp_dir: None,
-            call_lock: Mutex::new(()),
-        })
+        Self::from_library(library, None)
     }
 
     /// Load a plugin from a dynamic library, creating a temporary copy if needed
@@ -85,147 +258,220 @@ impl DylibWorkspaceProvider {
             })?
         };
 
-        // Get plugin metadata
-        let name = Self::get_plugin_name(&library)?;
-        let key = Self::get_plugin_key(&library)?;
-        let plugin_type = Self::get_plugin_type(&library)?;
-
-        Ok(Self {
-            name,
-            key,
-            plugin_type,
+        Self::from_library(library, Some(temp_dir)) // Keep temp dir alive
+    }
+
+    fn from_library(library: Library, temp_dir: Option<tempfile::TempDir>) -> Result<Self> {
+        let abi_version =
+            unsafe { library.get::<Symbol<PluginAbiVersionFn>>(b"plugin_abi_version") }
+                .map(|version_fn| unsafe { version_fn() })
+                .unwrap_or(1);
+        check_abi_version(abi_version)?;
+
+        let mut provider = Self {
+            name: String::new(),
+            key: String::new(),
+            plugin_type: PluginType::Primary,
+            description: None,
+            version: None,
+            homepage: None,
             library,
-            _temp_dir: Some(temp_dir), // Keep temp dir alive
+            buffer_abi: abi_version >= ABI_VERSION,
+            _temp_dir: temp_dir,
             call_lock: Mutex::new(()),
-        })
-    }
+        };
 
-    /// Extract plugin name from the library
-    fn get_plugin_name(library: &Library) -> Result<String> {
-        unsafe {
-            let name_fn: Symbol<PluginNameFn> = library
-                .get(b"plugin_name")
-                .context("Plugin missing plugin_name function")?;
+        // Get plugin metadata
+        provider.name = provider
+            .call(methods::NAME, b"plugin_name", None)?
+            .ok_or_else(|| anyhow::anyhow!("Plugin name function returned null"))?;
+        provider.key = provider
+            .call(methods::KEY, b"plugin_key", None)?
+            .ok_or_else(|| anyhow::anyhow!("Plugin key function returned null"))?;
+        provider.plugin_type = match provider.plugin_type_value()? {
+            0 => PluginType::Primary,
+            1 => PluginType::Supplemental,
+            2 => PluginType::Hook,
+            value => return Err(anyhow::anyhow!("Invalid plugin type value: {}", value)),
+        };
+        provider.description = provider.display_metadata(methods::DESCRIPTION)?;
+        provider.version = provider.display_metadata(methods::VERSION)?;
+        provider.homepage = provider.display_metadata(methods::HOMEPAGE)?;
 
-            let name_ptr = name_fn();
-            if name_ptr.is_null() {
-                return Err(anyhow::anyhow!("Plugin name function returned null"));
-            }
+        Ok(provider)
+    }
 
-            let name_cstr = CStr::from_ptr(name_ptr);
-            let name = name_cstr
-                .to_str()
-                .context("Plugin name contains invalid UTF-8")?
-                .to_string();
-
-            // Clean up the string if cleanup function exists
-            if let Ok(cleanup_fn) =
-                library.get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
-            {
-                cleanup_fn(name_ptr);
-            }
+    /// A description, version or homepage the plugin reports about itself. Plugins built
+    /// before they could report them have none.
+    fn display_metadata(&self, method: &str) -> Result<Option<String>> {
+        self.call_optional(method, &[], || Ok(None))
+    }
 
-            Ok(name)
+    fn plugin_type_value(&self) -> Result<u8> {
+        if self.buffer_abi {
+            return self
+                .call_buffer(methods::TYPE, &[])
+                .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?));
         }
-    }
 
-    /// Extract plugin key from the library
-    fn get_plugin_key(library: &Library) -> Result<String> {
         unsafe {
-            let key_fn: Symbol<PluginKeyFn> = library
-                .get(b"plugin_key")
-                .context("Plugin missing plugin_key function")?;
+            let type_fn: Symbol<PluginTypeFn> = self
+                .library
+                .get(b"plugin_type")
+                .context("Plugin missing plugin_type function")?;
+            Ok(type_fn())
+        }
+    }
 
-            let key_ptr = key_fn();
-            if key_ptr.is_null() {
-                return Err(anyhow::anyhow!("Plugin key function returned null"));
-            }
+    /// Call `method` and parse its JSON response.
+    ///
+    /// Plugins built against the buffer ABI are called through `plugin_call`. Older plugins
+    /// are called through `legacy_symbol`, which takes `legacy_input` as a C string if given.
+    /// A null JSON response or a missing legacy function is returned as `None`.
+    fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        legacy_symbol: &[u8],
+        legacy_input: Option<&CStr>,
+    ) -> Result<Option<T>> {
+        let _guard = self
+            .call_lock
+            .lock()
+            .map_err(|_| anyhow::anyhow!("plugin call mutex poisoned"))?;
+
+        if self.buffer_abi {
+            let input = legacy_input.map(CStr::to_bytes).unwrap_or_default();
+            let response = self.call_buffer(method, input)?;
+            return Ok(serde_json::from_slice(&response)?);
+        }
 
-            let key_cstr = CStr::from_ptr(key_ptr);
-            let key = key_cstr
-                .to_str()
-                .context("Plugin key contains invalid UTF-8")?
-                .to_string();
-
-            // Clean up the string if cleanup function exists
-            if let Ok(cleanup_fn) =
-                library.get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
-            {
-                cleanup_fn(key_ptr);
+        let result_ptr = unsafe {
+            match legacy_input {
+                None => match self.library.get::<Symbol<PluginStringFn>>(legacy_symbol) {
+                    Ok(func) => func(),
+                    Err(_) => return Ok(None),
+                },
+                Some(input) => match self
+                    .library
+                    .get::<Symbol<PluginOnScanCompleteFn>>(legacy_symbol)
+                {
+                    Ok(func) => func(input.as_ptr()),
+                    Err(_) => return Ok(None),
+                },
             }
+        };
+        let Some(result) = PluginString::new(&self.library, result_ptr) else {
+            return Ok(None);
+        };
+        let result_str = result.to_str()?;
 
-            Ok(key)
+        // Legacy name and key functions return plain strings rather than JSON
+        if method == methods::NAME || method == methods::KEY {
+            let value = Value::String(result_str.to_string());
+            return Ok(Some(serde_json::from_value(value)?));
+        }
+        if result_str.trim().is_empty() {
+            return Ok(None);
         }
+        serde_json::from_str(result_str)
+            .with_context(|| format!("Plugin function returned invalid JSON: {}", result_str))
     }
 
-    /// Extract plugin type from the library
-    fn get_plugin_type(library: &Library) -> Result<PluginType> {
-        unsafe {
-            let type_fn: Symbol<PluginTypeFn> = library
-                .get(b"plugin_type")
-                .context("Plugin missing plugin_type function")?;
+    /// Call an `on_file_found` method through `plugin_call`, with the projects found so far
+    fn call_file_found<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        workspace: &Workspace,
+        path: &Path,
+    ) -> Result<Option<T>> {
+        let path = path.to_string_lossy();
+        let input = serde_json::to_vec(&FileFoundInput {
+            path: &path,
+            workspace,
+        })?;
+        let _guard = self
+            .call_lock
+            .lock()
+            .map_err(|_| anyhow::anyhow!("plugin call mutex poisoned"))?;
+        Ok(serde_json::from_slice(&self.call_buffer(method, &input)?)?)
+    }
 
-            let type_value = type_fn();
-            match type_value {
-                0 => Ok(PluginType::Primary),
-                1 => Ok(PluginType::Supplemental),
-                2 => Ok(PluginType::Hook),
-                _ => Err(anyhow::anyhow!("Invalid plugin type value: {}", type_value)),
+    /// Call `plugin_call`, growing the response buffer if the first one is too small
+    /// Call a method that older plugins may not support, through `plugin_call`. Plugins
+    /// without the buffer ABI, or that don't know the method, get `fallback` instead.
+    fn call_optional<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        input: &[u8],
+        fallback: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if !self.buffer_abi {
+            return fallback();
+        }
+        // The fallback may call the plugin again, so the lock is released first
+        let outcome = {
+            let _guard = self
+                .call_lock
+                .lock()
+                .map_err(|_| anyhow::anyhow!("plugin call mutex poisoned"))?;
+            self.call_buffer(method, input)
+        };
+        match outcome {
+            Err(e) if e.downcast_ref::<CallError>() == Some(&CallError::UnknownMethod) => {
+                fallback()
             }
+            outcome => Ok(serde_json::from_slice(&outcome?)?),
         }
     }
 
-    /// Call a plugin function that returns a JSON string
-    fn call_json_function(&self, function_name: &[u8]) -> Result<Option<Value>> {
-        let _guard = self.call_lock.lock().expect("plugin call mutex poisoned");
-
-        unsafe {
-            let func: Symbol<PluginConfigOptionsFn> = match self.library.get(function_name) {
-                Ok(f) => f,
-                Err(_) => return Ok(None), // Function not found, return None
+    fn call_buffer(&self, method: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let call_fn: Symbol<PluginCallFn> = unsafe { self.library.get(b"plugin_call") }
+            .context("Plugin missing plugin_call function")?;
+
+        let mut buffer = Vec::with_capacity(INITIAL_RESPONSE_CAPACITY);
+        for _ in 0..2 {
+            let written = unsafe {
+                call_fn(
+                    method.as_ptr(),
+                    method.len(),
+                    input.as_ptr(),
+                    input.len(),
+                    buffer.as_mut_ptr(),
+                    buffer.capacity(),
+                )
             };
-
-            let result_ptr = func();
-            if result_ptr.is_null() {
-                return Ok(None);
-            }
-
-            let result_cstr = CStr::from_ptr(result_ptr);
-            let result_str = result_cstr
-                .to_str()
-                .context("Plugin function returned invalid UTF-8")?;
-
-            if result_str.is_empty() {
-                // Clean up and return None for empty results
-                if let Ok(cleanup_fn) = self
-                    .library
-                    .get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
-                {
-                    cleanup_fn(result_ptr);
-                }
-                return Ok(None);
+            let len =
+                usize::try_from(written).map_err(|_| match CallError::from_code(written) {
+                    Some(error) => anyhow::Error::new(error)
+                        .context(format!("Plugin call '{}' failed", method)),
+                    None => {
+                        anyhow::anyhow!("Plugin call '{}' failed with code {}", method, written)
+                    }
+                })?;
+
+            if len <= buffer.capacity() {
+                // The plugin wrote exactly `len` bytes into the buffer
+                unsafe { buffer.set_len(len) };
+                return Ok(buffer);
             }
+            buffer.reserve_exact(len);
+        }
 
-            let value = serde_json::from_str(result_str).with_context(|| {
-                format!("Plugin function returned invalid JSON: {}", result_str)
-            })?;
-
-            // Clean up the string if cleanup function exists
-            if let Ok(cleanup_fn) = self
-                .library
-                .get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
-            {
-                cleanup_fn(result_ptr);
-            }
+        Err(anyhow::anyhow!(
+            "Plugin call '{}' returned a different response size on retry",
+            method
+        ))
+    }
 
-            Ok(Some(value))
-        }
+    /// Call a plugin function that returns a JSON string
+    fn call_json_function(&self, method: &str, legacy_symbol: &[u8]) -> Result<Option<Value>> {
+        self.call(method, legacy_symbol, None)
     }
 }
 
 impl WorkspaceProvider for DylibWorkspaceProvider {
     fn include_path_globs(&self) -> Vec<String> {
-        match self.call_json_function(b"plugin_include_globs") {
+        match self.call_json_function(methods::INCLUDE_GLOBS, b"plugin_include_globs") {
             Ok(Some(Value::Array(arr))) => arr
                 .into_iter()
                 .filter_map(|v| v.as_str().map(|s| s.to_string()))
@@ -235,7 +481,7 @@ impl WorkspaceProvider for DylibWorkspaceProvider {
     }
 
     fn exclude_path_globs(&self) -> Vec<String> {
-        match self.call_json_function(b"plugin_exclude_globs") {
+        match self.call_json_function(methods::EXCLUDE_GLOBS, b"plugin_exclude_globs") {
             Ok(Some(Value::Array(arr))) => arr
                 .into_iter()
                 .filter_map(|v| v.as_str().map(|s| s.to_string()))
@@ -244,68 +490,114 @@ impl WorkspaceProvider for DylibWorkspaceProvider {
         }
     }
 
-    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
-        let _guard = self.call_lock.lock().expect("plugin call mutex poisoned");
+    fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let message: Option<InferredProjectMessage> = if self.buffer_abi {
+            self.call_file_found(methods::ON_FILE_FOUND, workspace, path)
+                .ok()?
+        } else {
+            let path_cstr = CString::new(path.to_string_lossy().as_ref()).ok()?;
+            self.legacy_on_file_found(&path_cstr, path)
+        };
 
-        let contents = std::fs::read_to_string(path).ok()?;
-        let path_str = path.to_string_lossy();
+        message.map(InferredProject::from)
+    }
 
-        let path_cstr = CString::new(path_str.as_ref()).ok()?;
-        let contents_cstr = CString::new(contents).ok()?;
+    fn on_file_found_messages(
+        &self,
+        workspace: &Workspace,
+        path: &Path,
+    ) -> Result<Vec<ProjectMessage>, PluginError> {
+        if self.buffer_abi {
+            // Plugins built before errors could be reported reject the method
+            if let Ok(Some(response)) = self.call_file_found::<FileFoundResponse>(
+                methods::ON_FILE_FOUND_RESULT,
+                workspace,
+                path,
+            ) {
+                return response.into();
+            }
+            let messages = self.call_file_found::<Vec<ProjectMessage>>(
+                methods::ON_FILE_FOUND_MESSAGES,
+                workspace,
+                path,
+            );
+            // Plugins built before project messages existed reject the method
+            if let Ok(messages) = messages {
+                return Ok(messages.unwrap_or_default());
+            }
+        }
 
-        let func: Symbol<PluginOnFileFoundFn> =
-            unsafe { self.library.get(b"plugin_on_file_found").ok()? };
+        Ok(self
+            .on_file_found(workspace, path)
+            .into_iter()
+            .map(ProjectMessage::from)
+            .collect())
+    }
 
-        let result_ptr = func(path_cstr.as_ptr(), contents_cstr.as_ptr());
-        if result_ptr.is_null() {
-            return None;
-        }
+    fn on_files_found(&self, workspace: &Workspace, paths: &[PathBuf]) -> Option<FilesFound> {
+        DylibWorkspaceProvider::on_files_found(self, workspace, paths).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Plugin '{}' failed to process its files at once, offering them one at a time: {}",
+                self.name, e
+            );
+            None
+        })
+    }
 
-        let result_str = unsafe {
-            let result_cstr = CStr::from_ptr(result_ptr);
-            result_cstr.to_str().ok()?
+    fn on_scan_complete(&self, workspace: &Workspace) -> Vec<WorkspaceMutation> {
+        let Some(workspace_cstr) = serde_json::to_string(workspace)
+            .ok()
+            .and_then(|json| CString::new(json).ok())
+        else {
+            return Vec::new();
         };
 
-        if result_str.trim().is_empty() || result_str == "null" {
-            // Clean up and return None
-            if let Ok(cleanup_fn) = unsafe {
-                self.library
-                    .get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
-            } {
-                cleanup_fn(result_ptr);
-            }
-            return None;
-        }
+        // Plugins built before this hook existed don't export it
+        self.call(
+            methods::ON_SCAN_COMPLETE,
+            b"plugin_on_scan_complete",
+            Some(&workspace_cstr),
+        )
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+    }
 
-        let value: Value = serde_json::from_str(result_str).ok()?;
-        if value.is_null() {
-            // Clean up and return None
-            if let Ok(cleanup_fn) = unsafe {
-                self.library
-                    .get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
-            } {
-                cleanup_fn(result_ptr);
-            }
-            return None;
-        }
+    fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        DylibWorkspaceProvider::take_diagnostics(self).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Failed to get diagnostics of plugin '{}': {}",
+                self.name, e
+            );
+            Vec::new()
+        })
+    }
+}
 
-        let message: marty_plugin_protocol::InferredProjectMessage =
-            serde_json::from_value(value).ok()?;
+impl DylibWorkspaceProvider {
+    /// `plugin_on_file_found` also takes the file contents, unlike the other legacy functions
+    fn legacy_on_file_found(
+        &self,
+        path_cstr: &CStr,
+        path: &Path,
+    ) -> Option<InferredProjectMessage> {
+        let _guard = self.call_lock.lock().ok()?;
 
-        // Clean up the string if cleanup function exists
-        if let Ok(cleanup_fn) = unsafe {
-            self.library
-                .get::<Symbol<PluginCleanupStringFn>>(b"plugin_cleanup_string")
-        } {
-            cleanup_fn(result_ptr);
-        }
+        let contents = std::fs::read_to_string(path).ok()?;
+        let contents_cstr = CString::new(contents).ok()?;
 
-        Some(InferredProject {
-            name: message.name,
-            project_dir: PathBuf::from(message.project_dir),
-            discovered_by: message.discovered_by,
-            workspace_dependencies: message.workspace_dependencies,
-        })
+        let func: Symbol<PluginOnFileFoundFn> =
+            unsafe { self.library.get(b"plugin_on_file_found").ok()? };
+
+        let result = PluginString::new(
+            &self.library,
+            func(path_cstr.as_ptr(), contents_cstr.as_ptr()),
+        )?;
+        let result_str = result.to_str().ok()?;
+        if result_str.trim().is_empty() {
+            return None;
+        }
+        serde_json::from_str(result_str).ok()
     }
 }
 
@@ -322,12 +614,126 @@ impl MartyPlugin for DylibWorkspaceProvider {
         &self.key
     }
 
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        self.homepage.as_deref()
+    }
+
     fn workspace_provider(&self) -> &dyn WorkspaceProvider {
         self
     }
 
     fn configuration_options(&self) -> Option<serde_json::Value> {
-        self.call_json_function(b"plugin_config_options")
-            .unwrap_or_default()
+        DylibWorkspaceProvider::configuration_options(self)
+    }
+
+    fn configure(&self, options: &Value) {
+        if let Err(e) = DylibWorkspaceProvider::configure(self, options) {
+            eprintln!("Warning: Failed to configure plugin '{}': {}", self.name, e);
+        }
+    }
+
+    fn initialize(&self, workspace_root: &Path, options: &Value) {
+        if let Err(e) = DylibWorkspaceProvider::initialize(self, workspace_root, options) {
+            eprintln!(
+                "Warning: Failed to initialize plugin '{}': {}",
+                self.name, e
+            );
+        }
+    }
+
+    fn shutdown(&self) {
+        if let Err(e) = DylibWorkspaceProvider::shutdown(self) {
+            eprintln!("Warning: Failed to shut down plugin '{}': {}", self.name, e);
+        }
+    }
+
+    fn suggested_tasks(&self) -> Vec<SuggestedTask> {
+        DylibWorkspaceProvider::suggested_tasks(self).unwrap_or_default()
+    }
+
+    fn default_tasks(&self) -> Vec<SuggestedTask> {
+        DylibWorkspaceProvider::default_tasks(self).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Failed to get default tasks of plugin '{}': {}",
+                self.name, e
+            );
+            Vec::new()
+        })
+    }
+
+    fn post_discovery(&self, workspace: &Workspace) -> Vec<String> {
+        DylibWorkspaceProvider::post_discovery(self, workspace).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Plugin '{}' failed after discovery: {}",
+                self.name, e
+            );
+            Vec::new()
+        })
+    }
+
+    fn on_project_created(&self, workspace: &Workspace, project_dir: &Path) -> Vec<String> {
+        let request = ProjectCreatedRequest {
+            project_dir: project_dir.to_string_lossy().to_string(),
+            workspace: workspace.clone(),
+        };
+        DylibWorkspaceProvider::on_project_created(self, &request).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: Plugin '{}' failed to register a new project: {}",
+                self.name, e
+            );
+            Vec::new()
+        })
+    }
+
+    fn run_task(&self, task: &str, project: &Project, workspace: &Workspace) -> TaskOutcome {
+        let request = RunTaskRequest {
+            task: task.to_string(),
+            project: project.clone(),
+            workspace: workspace.clone(),
+        };
+        DylibWorkspaceProvider::run_task(self, &request).unwrap_or_else(|e| {
+            TaskOutcome::failure(format!(
+                "Plugin '{}' failed to run task '{}': {}",
+                self.name, task, e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abi_versions_outside_the_supported_range_are_refused() {
+        for version in MIN_ABI_VERSION..=ABI_VERSION {
+            assert!(check_abi_version(version).is_ok());
+        }
+
+        let error = check_abi_version(ABI_VERSION + 1).unwrap_err().to_string();
+        assert!(error.contains("Update Marty"), "{}", error);
+        assert!(
+            error.contains(&format!("up to version {}", ABI_VERSION)),
+            "{}",
+            error
+        );
+
+        let error = check_abi_version(MIN_ABI_VERSION - 1)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Update the plugin"), "{}", error);
+        assert!(
+            error.contains(&format!("at least version {}", MIN_ABI_VERSION)),
+            "{}",
+            error
+        );
     }
 }
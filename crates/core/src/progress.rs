@@ -0,0 +1,143 @@
+//! Typed progress events for frontends
+//!
+//! Discovery and task runs report what they are doing as [`ProgressEvent`]s delivered to
+//! a [`ProgressSink`], so a frontend such as the CLI's JSON log or a long-running server
+//! can present progress without parsing printed output. The sink is set through
+//! [`WorkspaceManagerConfig::progress`](crate::workspace_manager::WorkspaceManagerConfig)
+//! and is a no-op by default.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::execution::runner::{serialize_millis, RunEvent};
+use crate::plugin_diagnostics::PluginDiagnostic;
+
+/// Something discovery or a task run did
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    DiscoveryStarted {
+        root: PathBuf,
+    },
+    /// A plugin was offered a file matching its include globs
+    PluginMatchedFile {
+        plugin: String,
+        path: PathBuf,
+    },
+    ProjectDiscovered {
+        name: String,
+        path: PathBuf,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        discovered_by: Option<String>,
+    },
+    DiscoveryFinished {
+        projects: usize,
+        /// The projects were read from the discovery cache instead of scanning
+        cached: bool,
+        #[serde(serialize_with = "serialize_millis", rename = "duration_ms")]
+        duration: Duration,
+    },
+    /// A problem a plugin reported while discovering projects
+    PluginDiagnostic(PluginDiagnostic),
+    /// One line a task's command wrote
+    TaskOutputChunk {
+        project: String,
+        task: String,
+        stream: OutputStream,
+        text: String,
+    },
+    /// Task starts, cache hits, finishes and errors
+    #[serde(untagged)]
+    Run(RunEvent),
+}
+
+/// Which output of a command a chunk was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+type Callback = dyn Fn(&ProgressEvent) + Send + Sync;
+
+/// Receiver of [`ProgressEvent`]s. Cloning shares the same destination.
+#[derive(Clone, Default)]
+pub struct ProgressSink(Option<Arc<Callback>>);
+
+impl ProgressSink {
+    /// Call `callback` with every event, from whichever thread produced it
+    pub fn new(callback: impl Fn(&ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(callback)))
+    }
+
+    /// A sink sending every event into a channel
+    pub fn channel() -> (Self, Receiver<ProgressEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let sink = Self::new(move |event| {
+            // A dropped receiver just means nobody listens anymore
+            let _ = sender.send(event.clone());
+        });
+        (sink, receiver)
+    }
+
+    /// Whether anyone listens, so producers can skip building events nobody receives
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn emit(&self, event: ProgressEvent) {
+        if let Some(callback) = &self.0 {
+            callback(&event);
+        }
+    }
+}
+
+impl fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ProgressSink")
+            .field(&self.is_enabled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_reach_the_channel_with_flat_tags() {
+        let (sink, receiver) = ProgressSink::channel();
+        sink.emit(ProgressEvent::PluginMatchedFile {
+            plugin: "cargo".to_string(),
+            path: PathBuf::from("app/Cargo.toml"),
+        });
+        sink.emit(ProgressEvent::Run(RunEvent::TaskStarted {
+            project: "app".to_string(),
+            task: "build".to_string(),
+        }));
+        drop(sink);
+
+        let lines: Vec<String> = receiver
+            .iter()
+            .map(|event| serde_json::to_string(&event).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"event":"plugin_matched_file","plugin":"cargo","path":"app/Cargo.toml"}"#,
+                r#"{"event":"task_started","project":"app","task":"build"}"#,
+            ]
+        );
+
+        // Without a listener emitting does nothing
+        ProgressSink::default().emit(ProgressEvent::DiscoveryStarted {
+            root: PathBuf::from("."),
+        });
+    }
+}
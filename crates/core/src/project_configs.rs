@@ -0,0 +1,166 @@
+//! The parsed marty.yml files of a workspace's projects
+//!
+//! Planning a task consults every project's marty.yml several times: for the task map, for
+//! tag and kind compatibility and to find project-level tasks. [`ProjectConfigs`] reads
+//! each file once so those checks share the results. With `planning.parallelConfigLoading`
+//! in workspace.yml the files are read and parsed on a few threads at once, which shortens
+//! planning in workspaces with thousands of projects.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::configs::project::{parse_project_config, ProjectConfig};
+use crate::types::{MartyError, MartyResult};
+use crate::workspace::{Project, Workspace};
+
+/// Most threads reading project configs at once
+const MAX_THREADS: usize = 8;
+
+/// The marty.yml of every project that has one, by project name
+pub struct ProjectConfigs {
+    /// Read or parse errors are kept as messages and reported by [`ProjectConfigs::get`]
+    configs: HashMap<String, Result<ProjectConfig, String>>,
+}
+
+impl ProjectConfigs {
+    /// Read the configs of all of `workspace`'s projects, on several threads if `parallel`
+    pub fn load(workspace: &Workspace, parallel: bool) -> Self {
+        let threads = if parallel {
+            std::thread::available_parallelism().map_or(1, |n| n.get().min(MAX_THREADS))
+        } else {
+            1
+        };
+        let read_all = |projects: &[Project]| {
+            projects
+                .iter()
+                .filter_map(|project| {
+                    let config = read_config(&project.project_dir).transpose()?;
+                    Some((project.name.clone(), config))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let configs = if threads <= 1 || workspace.projects.len() < 2 {
+            read_all(&workspace.projects)
+        } else {
+            let chunk_size = workspace.projects.len().div_ceil(threads);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = workspace
+                    .projects
+                    .chunks(chunk_size)
+                    .map(|projects| scope.spawn(move || read_all(projects)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| {
+                        handle
+                            .join()
+                            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                    })
+                    .collect()
+            })
+        };
+
+        Self {
+            configs: configs.into_iter().collect(),
+        }
+    }
+
+    /// The config of a project; `None` if it has no marty.yml
+    pub fn get(&self, project: &str) -> MartyResult<Option<&ProjectConfig>> {
+        match self.configs.get(project) {
+            Some(Ok(config)) => Ok(Some(config)),
+            Some(Err(message)) => Err(MartyError::Task(message.clone())),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The marty.yml in `project_dir`, if there is one
+pub fn read_project_config(project_dir: &Path) -> MartyResult<Option<ProjectConfig>> {
+    read_config(project_dir).map_err(MartyError::Task)
+}
+
+fn read_config(project_dir: &Path) -> Result<Option<ProjectConfig>, String> {
+    let project_config_path = project_dir.join("marty.yml");
+    if !project_config_path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&project_config_path).map_err(|e| {
+        format!(
+            "Failed to read project config {}: {}",
+            project_config_path.display(),
+            e
+        )
+    })?;
+
+    let project_config = parse_project_config(&content).map_err(|e| {
+        format!(
+            "Failed to parse project config {}: {}",
+            project_config_path.display(),
+            e
+        )
+    })?;
+
+    Ok(Some(project_config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::ProjectIndex;
+
+    #[test]
+    fn test_parallel_loading_matches_sequential() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let projects = (0..40)
+            .map(|i| {
+                let project_dir = root.join(format!("p{}", i));
+                std::fs::create_dir_all(&project_dir).unwrap();
+                match i % 4 {
+                    0 => {}
+                    1 => std::fs::write(project_dir.join("marty.yml"), "tags: [\n").unwrap(),
+                    _ => std::fs::write(project_dir.join("marty.yml"), format!("tags: [t{}]\n", i))
+                        .unwrap(),
+                }
+                Project {
+                    name: format!("p{}", i),
+                    project_dir,
+                    file_path: None,
+                    dependencies: Vec::new(),
+                }
+            })
+            .collect();
+        let workspace = Workspace {
+            root: root.to_path_buf(),
+            projects,
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+
+        let sequential = ProjectConfigs::load(&workspace, false);
+        let parallel = ProjectConfigs::load(&workspace, true);
+        for i in 0..40 {
+            let name = format!("p{}", i);
+            let tags = |configs: &ProjectConfigs| {
+                configs
+                    .get(&name)
+                    .map(|config| config.and_then(|c| c.tags.clone()))
+                    .map_err(|e| e.to_string())
+            };
+            assert_eq!(tags(&sequential), tags(&parallel), "{}", name);
+            match i % 4 {
+                0 => assert_eq!(tags(&parallel), Ok(None)),
+                1 => assert!(tags(&parallel)
+                    .unwrap_err()
+                    .starts_with("Task error: Failed to parse")),
+                _ => assert_eq!(tags(&parallel), Ok(Some(vec![format!("t{}", i)]))),
+            }
+        }
+    }
+}
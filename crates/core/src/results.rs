@@ -1,31 +1,71 @@
 //! Result types for workspace operations
-//! 
+//!
 //! This module contains all result types returned by workspace manager operations,
 //! providing a centralized location for output structures.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
+use std::time::Duration;
 
-use colored::Color;
+use serde::Serialize;
 
+use crate::colors::ProjectColor;
+use crate::configs::tasks::TaskConfig;
+use crate::dependency_rules::DependencyViolation;
+use crate::execution::cache::{CacheNamespace, CacheNamespaceStats};
+use crate::execution::resources::ResourceUsage;
+use crate::execution::runner::{ProjectRunReport, ProjectRunStatus, RunSummary};
+use crate::messages::Message;
 use crate::task_execution::TaskExecutionPlan;
-use crate::workspace::{InferredProject, Project};
+use crate::types::MartyError;
+use crate::workspace::{InferredProject, Project, ProjectKind};
 
 /// Information about a tracked project with its configuration
 #[derive(Debug, Clone)]
 pub struct ProjectInfo {
     pub name: String,
     pub path: PathBuf,
+    pub kind: Option<ProjectKind>,
     pub tags: Vec<String>,
     pub has_config: bool,
 }
 
+/// One project's record in `marty export metadata`, for dashboards built outside Marty
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectMetadata {
+    /// Name of the workspace from workspace.yml, to tell repositories apart
+    pub workspace: Option<String>,
+    pub name: String,
+    /// Project directory relative to the workspace root
+    pub path: PathBuf,
+    pub kind: Option<ProjectKind>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub owners: Vec<String>,
+    /// Key of the plugin that discovered the project
+    pub plugin: Option<String>,
+    pub dependencies: Vec<String>,
+    /// How each task ended the last time it ran on the project, by task name
+    pub tasks: Vec<ProjectTaskStats>,
+}
+
+/// The last run of a task on a project
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectTaskStats {
+    pub task: String,
+    pub last_status: ProjectRunStatus,
+    /// What the last successful run used
+    #[serde(flatten)]
+    pub resources: Option<ResourceUsage>,
+}
+
 /// Information about an inferred project discovered by plugins
 #[derive(Debug, Clone)]
 pub struct InferredProjectInfo {
     pub name: String,
     pub path: PathBuf,
     pub discovered_by: String,
+    pub kind: Option<ProjectKind>,
     pub is_tracked: bool,
 }
 
@@ -34,7 +74,8 @@ pub struct InferredProjectInfo {
 pub struct ProjectListResult {
     pub explicit_projects: Vec<ProjectInfo>,
     pub inferred_projects: Vec<InferredProjectInfo>,
-    pub project_colors: HashMap<String, Color>,
+    /// Color of every project, as shown in terminal output
+    pub project_colors: BTreeMap<String, ProjectColor>,
 }
 
 /// Result of getting the dependency graph
@@ -44,11 +85,54 @@ pub struct DependencyGraphResult {
     pub cycles: Vec<Vec<String>>,
 }
 
+/// Chains of dependencies from one project to another
+#[derive(Debug)]
+pub struct DependencyPathsResult {
+    /// Each chain of projects, starting with the dependent project
+    pub paths: Vec<Vec<String>>,
+    /// Plugin that reported each edge of the chains, keyed by project and dependency
+    pub edge_sources: BTreeMap<(String, String), String>,
+}
+
+/// Result of collapsing the dependency graph into directory groups
+#[derive(Debug)]
+pub struct GroupedDependencyGraphResult {
+    /// Projects in each directory group
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Groups each group depends on, excluding dependencies within the same group
+    pub edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Result of checking project dependency rules
+#[derive(Debug)]
+pub struct DependencyLintResult {
+    /// Number of projects that declare dependency rules
+    pub checked_projects: usize,
+    pub violations: Vec<DependencyViolation>,
+}
+
+/// Mapping of workspace files to the project that owns them
+#[derive(Debug)]
+pub struct FileOwnershipResult {
+    /// Workspace-relative file path to owning project (`None` for files outside any project)
+    pub files: BTreeMap<PathBuf, Option<String>>,
+}
+
 /// Result of task execution planning
 #[derive(Debug)]
 pub struct TaskPlanResult {
     pub plan: TaskExecutionPlan,
-    pub project_colors: HashMap<String, Color>,
+    pub project_colors: BTreeMap<String, ProjectColor>,
+}
+
+/// Result of describing a task
+#[derive(Debug)]
+pub struct TaskDescriptionResult {
+    pub name: String,
+    /// Workspace-level definition from `.marty/tasks`, if any
+    pub workspace_task: Option<TaskConfig>,
+    /// Projects that define their own version of the task in marty.yml
+    pub project_overrides: Vec<(String, TaskConfig)>,
 }
 
 impl From<Project> for ProjectInfo {
@@ -56,7 +140,8 @@ impl From<Project> for ProjectInfo {
         Self {
             name: project.name,
             path: project.project_dir,
-            tags: Vec::new(), // Will be populated by caller with config data
+            kind: None,        // Will be populated by caller
+            tags: Vec::new(),  // Will be populated by caller with config data
             has_config: false, // Will be populated by caller
         }
     }
@@ -68,7 +153,108 @@ impl From<InferredProject> for InferredProjectInfo {
             name: project.name,
             path: project.project_dir,
             discovered_by: project.discovered_by,
+            kind: project.kind,
             is_tracked: false, // Will be populated by caller
         }
     }
-}
\ No newline at end of file
+}
+
+/// Result of running a task, with the timing and outcome of every project
+#[derive(Debug, Clone, Default)]
+pub struct TaskRunResult {
+    pub task: String,
+    /// Projects in the order they finished
+    pub projects: Vec<ProjectRunReport>,
+    pub duration: Duration,
+}
+
+impl TaskRunResult {
+    /// A run that had nothing to do
+    pub fn empty(task: &str) -> Self {
+        Self {
+            task: task.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<RunSummary> for TaskRunResult {
+    fn from(summary: RunSummary) -> Self {
+        Self {
+            task: summary.task,
+            projects: summary.projects,
+            duration: summary.duration,
+        }
+    }
+}
+
+/// Result of inspecting the task cache
+#[derive(Debug)]
+pub struct CacheStatsResult {
+    pub enabled: bool,
+    /// Namespace that runs in the current environment would use
+    pub current_namespace: CacheNamespace,
+    pub namespaces: Vec<CacheNamespaceStats>,
+}
+
+/// Outcome of one check run by `marty check`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+}
+
+/// A single workspace check with the problems it found (or a short summary when it passed)
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    /// `findings` rendered in the current language
+    pub details: Vec<String>,
+    /// The problems, or the summary, with their stable message IDs
+    pub findings: Vec<Message>,
+}
+
+/// Result of running all workspace checks
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceCheckResult {
+    pub checks: Vec<WorkspaceCheck>,
+    /// Suggestions for tidying up the workspace, which never fail the check
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<Message>,
+}
+
+impl WorkspaceCheckResult {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status == CheckStatus::Pass)
+    }
+
+    pub(crate) fn record(&mut self, name: &str, problems: Vec<Message>, summary: Message) {
+        let (status, findings) = if problems.is_empty() {
+            (CheckStatus::Pass, vec![summary])
+        } else {
+            (CheckStatus::Fail, problems)
+        };
+        self.checks.push(WorkspaceCheck {
+            name: name.to_string(),
+            status,
+            details: findings.iter().map(Message::to_string).collect(),
+            findings,
+        });
+    }
+
+    /// Record a check that could not run at all
+    pub(crate) fn fail(&mut self, name: &str, error: &MartyError) {
+        let finding = Message::new("check.error").with("error", error);
+        self.checks.push(WorkspaceCheck {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            details: vec![finding.to_string()],
+            findings: vec![finding],
+        });
+    }
+}
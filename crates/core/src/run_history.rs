@@ -0,0 +1,411 @@
+//! Records of past task runs
+//!
+//! Every task run writes a record to `.marty/runs/<run-id>.json` describing exactly what
+//! was executed: the spawned programs with their arguments, working directory and
+//! environment, the inherited environment, the commit and the output of each task's
+//! `envProbe` commands. `marty repro` turns a record back into a shell script so a
+//! failure seen elsewhere (typically CI) can be reproduced locally.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::configs::workspace::Shell;
+use crate::execution::cache::run_probe;
+use crate::execution::executor::{shell_quote, ExecutionContext, ExecutionHook};
+use crate::git;
+use crate::types::{MartyError, MartyResult};
+
+/// Number of run records kept per workspace; older records are removed
+const MAX_RECORDS: usize = 50;
+
+/// Fragments of variable names whose values are never written to a record
+const SECRET_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "KEY",
+    "CREDENTIAL",
+    "AUTH",
+    "COOKIE",
+];
+
+/// Variables maintained by the shell itself, which are meaningless to replay
+const SHELL_STATE_VARS: &[&str] = &["PWD", "OLDPWD", "SHLVL", "_"];
+
+/// Everything executed during one task run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub id: String,
+    pub task: String,
+    pub targets: Vec<String>,
+    /// Seconds since the Unix epoch
+    pub started_at: u64,
+    pub git_sha: Option<String>,
+    pub git_branch: Option<String>,
+    /// Variables inherited from the environment Marty was started in. Values of variables
+    /// that look like secrets are not recorded.
+    pub inherited_env: BTreeMap<String, Option<String>>,
+    pub commands: Vec<CommandRecord>,
+}
+
+/// A single process spawned for a project's task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandRecord {
+    pub project: String,
+    pub task: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    /// Variables set by Marty for this command. Values of variables that look like secrets
+    /// are not recorded.
+    pub env: BTreeMap<String, Option<String>>,
+    /// Output of the task's `envProbe` commands when it ran
+    pub toolchain: BTreeMap<String, String>,
+    pub success: bool,
+}
+
+impl CommandRecord {
+    /// Render the command as a shell line
+    pub fn shell_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|part| shell_quote(part))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl RunRecord {
+    /// Start a record for a run of `task` on `targets`
+    pub fn start(workspace_root: &Path, task: &str, targets: &[String]) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            id: format!("{}-{}", now, std::process::id()),
+            task: task.to_string(),
+            targets: targets.to_vec(),
+            started_at: now,
+            git_sha: git::head_sha(workspace_root).ok(),
+            git_branch: git::current_branch(workspace_root).ok(),
+            inherited_env: std::env::vars()
+                .filter(|(name, _)| !SHELL_STATE_VARS.contains(&name.as_str()))
+                .map(|(name, value)| {
+                    let value = (!is_secret(&name)).then_some(value);
+                    (name, value)
+                })
+                .collect(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Directory holding all run records
+    pub fn dir(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".marty").join("runs")
+    }
+
+    /// Write the record, removing the oldest records beyond the retention limit
+    pub fn save(&self, workspace_root: &Path) -> MartyResult<()> {
+        let dir = Self::dir(workspace_root);
+        std::fs::create_dir_all(&dir)?;
+
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| MartyError::Workspace(format!("Failed to serialize run record: {}", e)))?;
+        std::fs::write(dir.join(format!("{}.json", self.id)), content)?;
+
+        let ids = Self::list(workspace_root)?;
+        for id in ids.iter().take(ids.len().saturating_sub(MAX_RECORDS)) {
+            std::fs::remove_file(dir.join(format!("{}.json", id)))?;
+        }
+        Ok(())
+    }
+
+    /// Ids of all recorded runs, oldest first
+    pub fn list(workspace_root: &Path) -> MartyResult<Vec<String>> {
+        let dir = Self::dir(workspace_root);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut ids = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(stem.to_string());
+                }
+            }
+        }
+        // Ids start with the start time, so sorting by it orders runs chronologically
+        ids.sort_by_key(|id| {
+            let (time, pid) = id.split_once('-').unwrap_or((id, ""));
+            (time.parse::<u64>().unwrap_or_default(), pid.to_string())
+        });
+        Ok(ids)
+    }
+
+    /// Load a run record by id, or the most recent one for `latest`
+    pub fn load(workspace_root: &Path, id: &str) -> MartyResult<Self> {
+        let id = if id == "latest" {
+            Self::list(workspace_root)?
+                .pop()
+                .ok_or_else(|| MartyError::Workspace("No runs have been recorded".to_string()))?
+        } else {
+            id.to_string()
+        };
+
+        let path = Self::dir(workspace_root).join(format!("{}.json", id));
+        if !path.exists() {
+            return Err(MartyError::Workspace(format!(
+                "Run '{}' not found in {}",
+                id,
+                Self::dir(workspace_root).display()
+            )));
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            MartyError::Workspace(format!(
+                "Failed to parse run record {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Commands run for `task` on `project`, in execution order
+    pub fn commands_for(&self, project: &str, task: &str) -> Vec<&CommandRecord> {
+        self.commands
+            .iter()
+            .filter(|c| c.project == project && c.task == task)
+            .collect()
+    }
+
+    /// Build a POSIX shell script re-running `task` on `project` in the recorded environment
+    pub fn repro_script(&self, project: &str, task: &str) -> MartyResult<String> {
+        let commands = self.commands_for(project, task);
+        if commands.is_empty() {
+            return Err(MartyError::Task(format!(
+                "Run '{}' did not execute '{}:{}'",
+                self.id, project, task
+            )));
+        }
+
+        let mut script = String::from("#!/bin/sh\n");
+        script.push_str(&format!(
+            "# Reproduces {}:{} from marty run {}\n",
+            project, task, self.id
+        ));
+        if let Some(sha) = &self.git_sha {
+            script.push_str(&format!(
+                "# Recorded at commit {} on branch {}\n",
+                sha,
+                self.git_branch.as_deref().unwrap_or("(unknown)")
+            ));
+        }
+        for (probe, output) in &commands[0].toolchain {
+            script.push_str(&format!("# {} => {}\n", probe, output.replace('\n', " ")));
+        }
+        script.push_str("set -e\n\n");
+
+        for (name, value) in &self.inherited_env {
+            match value {
+                Some(value) => {
+                    script.push_str(&format!("export {}={}\n", name, shell_quote(value)))
+                }
+                None => {
+                    script.push_str(&format!("# {} was set; its value was not recorded\n", name))
+                }
+            }
+        }
+
+        for command in commands {
+            script.push('\n');
+            script.push('(');
+            if let Some(cwd) = &command.cwd {
+                script.push_str(&format!(
+                    "cd {} && ",
+                    shell_quote(&cwd.display().to_string())
+                ));
+            }
+            for (name, value) in &command.env {
+                match value {
+                    Some(value) => script.push_str(&format!("{}={} ", name, shell_quote(value))),
+                    // Taken from the environment the script runs in
+                    None => script.push_str(&format!("{}=\"${}\" ", name, name)),
+                }
+            }
+            script.push_str(&command.shell_line());
+            script.push_str(")\n");
+        }
+
+        Ok(script)
+    }
+
+    /// Describe how the current checkout and toolchain differ from when `task` ran on
+    /// `project` in this run, probing the toolchain in `shell`
+    pub fn drift(
+        &self,
+        workspace_root: &Path,
+        shell: Shell,
+        project: &str,
+        task: &str,
+    ) -> Vec<String> {
+        let mut drift = Vec::new();
+        if let (Some(recorded), Ok(current)) = (&self.git_sha, git::head_sha(workspace_root)) {
+            if *recorded != current {
+                drift.push(format!("commit was {}, now {}", recorded, current));
+            }
+        }
+
+        if let Some(command) = self.commands_for(project, task).first() {
+            for (probe, recorded) in &command.toolchain {
+                let current = run_probe(workspace_root, shell, probe);
+                if *recorded != current {
+                    drift.push(format!("`{}` was {}, now {}", probe, recorded, current));
+                }
+            }
+        }
+        drift
+    }
+}
+
+fn is_secret(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// Adds every command a task spawns to a shared run record
+pub struct RecordHook {
+    pub record: Arc<Mutex<RunRecord>>,
+    pub project: String,
+    pub task: String,
+    pub toolchain: BTreeMap<String, String>,
+}
+
+impl ExecutionHook for RecordHook {
+    fn after(
+        &self,
+        command: &Command,
+        _context: &ExecutionContext,
+        success: bool,
+    ) -> MartyResult<()> {
+        let entry = CommandRecord {
+            project: self.project.clone(),
+            task: self.task.clone(),
+            program: command.get_program().to_string_lossy().to_string(),
+            args: command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            cwd: command
+                .get_current_dir()
+                .map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())),
+            env: command
+                .get_envs()
+                .filter_map(|(name, value)| {
+                    let name = name.to_string_lossy().to_string();
+                    let value = value?.to_string_lossy().to_string();
+                    let value = (!is_secret(&name)).then_some(value);
+                    Some((name, value))
+                })
+                .collect(),
+            toolchain: self.toolchain.clone(),
+            success,
+        };
+
+        if let Ok(mut record) = self.record.lock() {
+            record.commands.push(entry);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repro_script_replays_recorded_commands() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut record = RunRecord {
+            id: "100-1".to_string(),
+            task: "build".to_string(),
+            inherited_env: BTreeMap::from([
+                ("PATH".to_string(), Some("/usr/bin".to_string())),
+                ("NPM_TOKEN".to_string(), None),
+            ]),
+            ..RunRecord::default()
+        };
+        record.commands.push(CommandRecord {
+            project: "app".to_string(),
+            task: "build".to_string(),
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), "make all".to_string()],
+            cwd: Some(PathBuf::from("/work")),
+            env: BTreeMap::from([("MARTY_TARGET_0".to_string(), Some("app".to_string()))]),
+            toolchain: BTreeMap::new(),
+            success: false,
+        });
+        record.save(temp.path()).unwrap();
+
+        let loaded = RunRecord::load(temp.path(), "latest").unwrap();
+        let script = loaded.repro_script("app", "build").unwrap();
+        assert!(script.contains("export PATH=/usr/bin\n"));
+        assert!(script.contains("# NPM_TOKEN was set; its value was not recorded\n"));
+        assert!(script.contains("(cd /work && MARTY_TARGET_0=app sh -c 'make all')\n"));
+
+        assert!(loaded.repro_script("app", "test").is_err());
+        assert!(RunRecord::load(temp.path(), "200-1").is_err());
+        assert!(is_secret("aws_secret_access_key"));
+    }
+
+    #[test]
+    fn test_secret_task_env_values_are_not_recorded() {
+        let temp = tempfile::tempdir().unwrap();
+        let record = Arc::new(Mutex::new(RunRecord {
+            id: "100-1".to_string(),
+            ..RunRecord::default()
+        }));
+        let hook = RecordHook {
+            record: record.clone(),
+            project: "app".to_string(),
+            task: "publish".to_string(),
+            toolchain: BTreeMap::new(),
+        };
+        let mut command = Command::new("npm");
+        command
+            .arg("publish")
+            .env("NPM_TOKEN", "s3cr3t")
+            .env("MARTY_TARGET_0", "app");
+        let context = ExecutionContext {
+            working_dir: temp.path().to_path_buf(),
+            workspace_root: temp.path().to_path_buf(),
+            targets: vec!["app".to_string()],
+            env: BTreeMap::new(),
+        };
+        hook.after(&command, &context, true).unwrap();
+
+        let record = record.lock().unwrap().clone();
+        let env = &record.commands[0].env;
+        assert_eq!(env["NPM_TOKEN"], None);
+        assert_eq!(env["MARTY_TARGET_0"].as_deref(), Some("app"));
+
+        record.save(temp.path()).unwrap();
+        let saved = std::fs::read_to_string(RunRecord::dir(temp.path()).join("100-1.json"));
+        assert!(!saved.unwrap().contains("s3cr3t"));
+        let script = record.repro_script("app", "publish").unwrap();
+        assert!(!script.contains("s3cr3t"), "{}", script);
+        assert!(script.contains("NPM_TOKEN=\"$NPM_TOKEN\" "), "{}", script);
+    }
+}
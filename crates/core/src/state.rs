@@ -0,0 +1,263 @@
+//! Persistent workspace state
+//!
+//! Marty keeps a small amount of run history in `.marty/state.json` so that later
+//! invocations can make decisions based on previous runs, such as only re-running
+//! projects changed since the last fully successful run of a task, or only the projects
+//! that failed last time. It also counts how often each plugin discovered nothing, to
+//! point out plugins a workspace no longer needs, and the resources each task used on each
+//! project the last time it succeeded, to size CI runners and notice builds getting
+//! more expensive.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::execution::resources::ResourceUsage;
+use crate::execution::runner::{ProjectRunStatus, RunHook, RunHookFuture, RunSummary};
+use crate::types::{MartyError, MartyResult};
+
+/// Consecutive discoveries without a single project after which a plugin is reported as
+/// possibly unused
+pub const UNUSED_PLUGIN_RUNS: u32 = 3;
+
+/// Run history persisted between invocations
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceState {
+    /// Commit SHA of the last fully successful run, keyed by task name and then branch
+    #[serde(default)]
+    pub last_success: BTreeMap<String, BTreeMap<String, String>>,
+    /// How each project ended the last time a task ran on it, keyed by task name and then
+    /// project
+    #[serde(default)]
+    pub last_results: BTreeMap<String, BTreeMap<String, ProjectRunStatus>>,
+    /// Number of consecutive discoveries in which a plugin found no projects, keyed by
+    /// plugin key
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub idle_plugins: BTreeMap<String, u32>,
+    /// Peak memory and CPU time of the last successful run of a task on a project, keyed
+    /// by task name and then project
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resource_usage: BTreeMap<String, BTreeMap<String, ResourceUsage>>,
+}
+
+impl WorkspaceState {
+    /// Location of the state file for a workspace
+    pub fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".marty").join("state.json")
+    }
+
+    /// Load the state file, returning an empty state if none has been written yet
+    pub fn load(workspace_root: &Path) -> MartyResult<Self> {
+        let path = Self::path(workspace_root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            MartyError::Workspace(format!(
+                "Failed to parse workspace state {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the state file
+    pub fn save(&self, workspace_root: &Path) -> MartyResult<()> {
+        let path = Self::path(workspace_root);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(self).map_err(|e| {
+            MartyError::Workspace(format!("Failed to serialize workspace state: {}", e))
+        })?;
+        std::fs::write(&path, content)?;
+        Ok(())
+    }
+
+    /// Get the commit of the last fully successful run of a task on a branch
+    pub fn last_success(&self, task_name: &str, branch: &str) -> Option<&str> {
+        self.last_success
+            .get(task_name)
+            .and_then(|branches| branches.get(branch))
+            .map(String::as_str)
+    }
+
+    /// Record a fully successful run of a task on a branch
+    pub fn record_success(&mut self, task_name: &str, branch: &str, sha: &str) {
+        self.last_success
+            .entry(task_name.to_string())
+            .or_default()
+            .insert(branch.to_string(), sha.to_string());
+    }
+
+    /// Record how each project of a run ended, and what the projects it succeeded on used.
+    /// Projects the run did not include keep their earlier result.
+    pub fn record_results(&mut self, summary: &RunSummary) {
+        let results = self.last_results.entry(summary.task.clone()).or_default();
+        for report in &summary.projects {
+            results.insert(report.project.clone(), report.status);
+        }
+        for report in &summary.projects {
+            if let (ProjectRunStatus::Succeeded, Some(resources)) =
+                (report.status, report.resources)
+            {
+                self.resource_usage
+                    .entry(summary.task.clone())
+                    .or_default()
+                    .insert(report.project.clone(), resources);
+            }
+        }
+    }
+
+    /// Record how many projects each loaded plugin discovered. Plugins that are no longer
+    /// loaded are forgotten.
+    pub fn record_discovery(&mut self, discovered: &BTreeMap<String, usize>) {
+        self.idle_plugins
+            .retain(|key, _| discovered.contains_key(key));
+        for (key, &count) in discovered {
+            if count == 0 {
+                *self.idle_plugins.entry(key.clone()).or_default() += 1;
+            } else {
+                self.idle_plugins.remove(key);
+            }
+        }
+    }
+
+    /// Plugins that discovered no projects in the last [`UNUSED_PLUGIN_RUNS`] discoveries
+    pub fn unused_plugins(&self) -> Vec<&str> {
+        self.idle_plugins
+            .iter()
+            .filter(|(_, &runs)| runs >= UNUSED_PLUGIN_RUNS)
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// Projects that did not finish the task the last time it ran on them: those that
+    /// failed, were blocked by a failure or never started. `None` if the task never ran.
+    pub fn unfinished(&self, task_name: &str) -> Option<Vec<String>> {
+        let results = self.last_results.get(task_name)?;
+        Some(
+            results
+                .iter()
+                .filter(|(_, status)| {
+                    matches!(
+                        status,
+                        ProjectRunStatus::Failed
+                            | ProjectRunStatus::Blocked
+                            | ProjectRunStatus::NotRun
+                    )
+                })
+                .map(|(project, _)| project.clone())
+                .collect(),
+        )
+    }
+}
+
+/// Saves the outcome of every project of a run to the state file, for `--failed`
+pub(crate) struct ResultsRecorder {
+    pub workspace_root: PathBuf,
+}
+
+impl RunHook for ResultsRecorder {
+    fn name(&self) -> &str {
+        "run state"
+    }
+
+    fn on_run_complete<'a>(&'a self, summary: &'a RunSummary) -> RunHookFuture<'a> {
+        Box::pin(async move {
+            let mut state = WorkspaceState::load(&self.workspace_root)?;
+            state.record_results(summary);
+            state.save(&self.workspace_root)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::runner::ProjectRunReport;
+    use std::time::Duration;
+
+    fn summary(results: &[(&str, ProjectRunStatus)]) -> RunSummary {
+        RunSummary {
+            task: "test".to_string(),
+            projects: results
+                .iter()
+                .map(|(project, status)| ProjectRunReport {
+                    project: project.to_string(),
+                    task: "test".to_string(),
+                    duration: Duration::ZERO,
+                    status: *status,
+                    cache_hit: None,
+                    resources: (*status == ProjectRunStatus::Succeeded).then_some(ResourceUsage {
+                        peak_rss_bytes: 1024,
+                        cpu_time: Duration::from_millis(10),
+                    }),
+                })
+                .collect(),
+            ..RunSummary::default()
+        }
+    }
+
+    #[test]
+    fn test_results_of_later_runs_replace_only_their_projects() {
+        let mut state = WorkspaceState::default();
+        assert_eq!(state.unfinished("test"), None);
+
+        state.record_results(&summary(&[
+            ("lib", ProjectRunStatus::Succeeded),
+            ("app", ProjectRunStatus::Failed),
+            ("cli", ProjectRunStatus::Blocked),
+            ("web", ProjectRunStatus::NotRun),
+        ]));
+        assert_eq!(
+            state.unfinished("test"),
+            Some(vec![
+                "app".to_string(),
+                "cli".to_string(),
+                "web".to_string()
+            ])
+        );
+
+        state.record_results(&summary(&[
+            ("app", ProjectRunStatus::Succeeded),
+            ("cli", ProjectRunStatus::Cached),
+        ]));
+        assert_eq!(state.unfinished("test"), Some(vec!["web".to_string()]));
+        assert_eq!(state.unfinished("build"), None);
+
+        // Only successful runs measure what a task costs
+        let usage = &state.resource_usage["test"];
+        assert_eq!(usage.keys().collect::<Vec<_>>(), vec!["app", "lib"]);
+        assert_eq!(usage["app"].cpu_time, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_plugins_without_projects_become_unused() {
+        let mut state = WorkspaceState::default();
+        let discovered = |counts: &[(&str, usize)]| {
+            counts
+                .iter()
+                .map(|(key, count)| (key.to_string(), *count))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        for _ in 0..UNUSED_PLUGIN_RUNS - 1 {
+            state.record_discovery(&discovered(&[("cargo", 4), ("dotnet", 0), ("helm", 0)]));
+        }
+        assert!(state.unused_plugins().is_empty());
+
+        // A plugin that finds something starts over; one that is removed is forgotten
+        state.record_discovery(&discovered(&[("cargo", 4), ("dotnet", 0), ("helm", 1)]));
+        assert_eq!(state.unused_plugins(), vec!["dotnet"]);
+        assert!(!state.idle_plugins.contains_key("helm"));
+
+        state.record_discovery(&discovered(&[("cargo", 4)]));
+        assert!(state.idle_plugins.is_empty());
+    }
+}
@@ -1,9 +1,7 @@
-use std::fs;
-
-use crate::configs::project::{parse_project_config, ProjectConfig};
 use crate::configs::tasks::TasksFileConfig;
+use crate::project_configs::{read_project_config, ProjectConfigs};
 use crate::types::{MartyError, MartyResult};
-use crate::workspace::{get_recursive_dependencies, Workspace};
+use crate::workspace::{get_recursive_dependencies, ProjectKind, Workspace};
 
 /// Result of resolving which projects should execute a task
 #[derive(Debug, Clone)]
@@ -13,50 +11,48 @@ pub struct TaskExecutionPlan {
     pub project_filter: Option<String>,
 }
 
-/// Check if a project is compatible with a task based on tags
+/// Check if a project is compatible with a task based on tags and kinds
 pub fn is_project_compatible_with_task(
     workspace: &Workspace,
+    project_configs: &ProjectConfigs,
     project_name: &str,
     tasks_file_config: &TasksFileConfig,
 ) -> MartyResult<bool> {
-    // If task file has no tags, it's compatible with all projects
+    // If task file has no tags or kinds, it's compatible with all projects
     let task_file_tags = tasks_file_config.tags.clone().unwrap_or_default();
-    if task_file_tags.is_empty() {
+    let task_file_kinds = tasks_file_config.kinds.clone().unwrap_or_default();
+    if task_file_tags.is_empty() && task_file_kinds.is_empty() {
         return Ok(true);
     }
 
     // Find the project
-    let project = workspace.projects.iter().find(|p| p.name == project_name);
-    let project = match project {
-        Some(p) => p,
-        None => return Ok(false), // Project doesn't exist
-    };
+    if workspace.project(project_name).is_none() {
+        return Ok(false); // Project doesn't exist
+    }
+
+    let project_config = project_configs.get(project_name)?;
 
-    // Read project config
-    let project_config_path = project.project_dir.join("marty.yml");
-    if !project_config_path.exists() {
-        // If no project config, assume it's compatible (no tags means no restrictions)
+    // A project without a kind is not compatible with tasks bound to kinds
+    if !task_file_kinds.is_empty() {
+        let kind = project_config
+            .and_then(|c| c.kind)
+            .or_else(|| workspace.inferred_kind(project_name));
+        if !kind.is_some_and(|kind| task_file_kinds.contains(&kind)) {
+            return Ok(false);
+        }
+    }
+
+    if task_file_tags.is_empty() {
         return Ok(true);
     }
 
-    let content = fs::read_to_string(&project_config_path).map_err(|e| {
-        MartyError::Task(format!(
-            "Failed to read project config {}: {}",
-            project_config_path.display(),
-            e
-        ))
-    })?;
-
-    let project_config: ProjectConfig = parse_project_config(&content).map_err(|e| {
-        MartyError::Task(format!(
-            "Failed to parse project config {}: {}",
-            project_config_path.display(),
-            e
-        ))
-    })?;
+    // If no project config, assume it's compatible (no tags means no restrictions)
+    let Some(project_config) = project_config else {
+        return Ok(true);
+    };
 
     // Get project tags, defaulting to empty if not specified
-    let project_tags = project_config.tags.unwrap_or_default();
+    let project_tags = project_config.tags.as_deref().unwrap_or_default();
 
     // If project has no tags, it's not compatible with tagged tasks
     if project_tags.is_empty() {
@@ -73,9 +69,19 @@ pub fn is_project_compatible_with_task(
     Ok(has_matching_tag)
 }
 
+/// The kind of a project: the one set in its marty.yml, or else the one plugins inferred
+pub fn project_kind(workspace: &Workspace, project_name: &str) -> MartyResult<Option<ProjectKind>> {
+    let configured = match workspace.project(project_name) {
+        Some(project) => read_project_config(&project.project_dir)?.and_then(|c| c.kind),
+        None => None,
+    };
+    Ok(configured.or_else(|| workspace.inferred_kind(project_name)))
+}
+
 /// Check if a task exists either at workspace level or in any project
 pub fn task_exists(
     workspace: &Workspace,
+    project_configs: &ProjectConfigs,
     config: &TasksFileConfig,
     task_name: &str,
     project_filter: Option<&str>,
@@ -87,12 +93,12 @@ pub fn task_exists(
 
     // If we have a specific project filter, only check that project
     if let Some(project_name) = project_filter {
-        return check_task_in_project(workspace, project_name, task_name);
+        return check_task_in_project(workspace, project_configs, project_name, task_name);
     }
 
     // Check all projects for the task
     for project in &workspace.projects {
-        if check_task_in_project(workspace, &project.name, task_name)? {
+        if check_task_in_project(workspace, project_configs, &project.name, task_name)? {
             return Ok(true);
         }
     }
@@ -103,49 +109,44 @@ pub fn task_exists(
 /// Check if a specific project has a task
 fn check_task_in_project(
     workspace: &Workspace,
+    project_configs: &ProjectConfigs,
     project_name: &str,
     task_name: &str,
 ) -> MartyResult<bool> {
-    let project = workspace
-        .projects
-        .iter()
-        .find(|p| p.name == project_name)
-        .ok_or_else(|| MartyError::Task(format!("Project '{}' not found", project_name)))?;
+    if workspace.project(project_name).is_none() {
+        return Err(MartyError::Task(format!(
+            "Project '{}' not found",
+            project_name
+        )));
+    }
 
-    let project_config_path = project.project_dir.join("marty.yml");
-    if !project_config_path.exists() {
+    let Some(project_config) = project_configs.get(project_name)? else {
         return Ok(false);
-    }
+    };
 
-    let content = fs::read_to_string(&project_config_path).map_err(|e| {
-        MartyError::Task(format!(
-            "Failed to read project config {}: {}",
-            project_config_path.display(),
-            e
-        ))
-    })?;
-
-    let project_config: TasksFileConfig = crate::configs::tasks::parse_tasks_config(&content)
-        .map_err(|e| {
-            MartyError::Task(format!(
-                "Failed to parse project config {}: {}",
-                project_config_path.display(),
-                e
-            ))
-        })?;
-
-    Ok(project_config.tasks.iter().any(|t| t.name == task_name))
+    Ok(project_config
+        .tasks
+        .iter()
+        .flatten()
+        .any(|t| t.name == task_name))
 }
 
 /// Resolve which projects should execute a task, including dependency resolution and tag filtering
 pub fn resolve_task_execution_plan(
     workspace: &Workspace,
+    project_configs: &ProjectConfigs,
     config: &TasksFileConfig,
     task_name: &str,
     project_filter: Option<&str>,
 ) -> MartyResult<TaskExecutionPlan> {
     // Verify the task exists
-    if !task_exists(workspace, config, task_name, project_filter)? {
+    if !task_exists(
+        workspace,
+        project_configs,
+        config,
+        task_name,
+        project_filter,
+    )? {
         return Err(MartyError::Task(format!("Task '{}' not found", task_name)));
     }
 
@@ -153,8 +154,11 @@ pub fn resolve_task_execution_plan(
     let initial_targets = match project_filter {
         Some(project_name) => {
             // Check if the specific project exists
-            if !workspace.projects.iter().any(|p| p.name == project_name) {
-                return Err(MartyError::Task(format!("Project '{}' not found", project_name)));
+            if workspace.project(project_name).is_none() {
+                return Err(MartyError::Task(format!(
+                    "Project '{}' not found",
+                    project_name
+                )));
             }
             vec![project_name.to_string()]
         }
@@ -171,7 +175,7 @@ pub fn resolve_task_execution_plan(
     // Filter projects to only those compatible with the task based on tags
     let mut compatible_projects = Vec::new();
     for project_name in &all_projects_with_deps {
-        if is_project_compatible_with_task(workspace, project_name, config)? {
+        if is_project_compatible_with_task(workspace, project_configs, project_name, config)? {
             compatible_projects.push(project_name.clone());
         }
     }
@@ -180,9 +184,8 @@ pub fn resolve_task_execution_plan(
     if let Some(target_project) = project_filter {
         if !compatible_projects.contains(&target_project.to_string()) {
             return Err(MartyError::Task(format!(
-                "Project '{}' is not compatible with task '{}' (tag mismatch)",
-                target_project,
-                task_name
+                "Project '{}' is not compatible with task '{}' (tag or kind mismatch)",
+                target_project, task_name
             )));
         }
     }
@@ -192,4 +195,4 @@ pub fn resolve_task_execution_plan(
         compatible_projects,
         project_filter: project_filter.map(|s| s.to_string()),
     })
-}
\ No newline at end of file
+}
@@ -0,0 +1,146 @@
+//! Captured output of task runs
+//!
+//! With `logs.enabled` set in workspace.yml, everything a project's task writes is also
+//! saved to `.marty/logs/<project>/<task>-<timestamp>.log`, the timestamp being
+//! milliseconds since the Unix epoch. `marty logs` shows the most recent one. Only the
+//! newest logs of each project and task are kept.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::types::{MartyError, MartyResult};
+
+/// Logs kept per project and task unless workspace.yml says otherwise
+pub const DEFAULT_KEEP: usize = 10;
+
+/// An open log file, shared by the threads forwarding a command's output
+pub type LogFile = Arc<Mutex<File>>;
+
+/// Where task logs are written and how many are kept
+#[derive(Debug, Clone)]
+pub struct TaskLogs {
+    dir: PathBuf,
+    keep: usize,
+}
+
+impl TaskLogs {
+    pub fn new(workspace_root: &Path, keep: usize) -> Self {
+        Self {
+            dir: Self::dir(workspace_root),
+            keep: keep.max(1),
+        }
+    }
+
+    /// Directory holding the logs of every project
+    pub fn dir(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".marty").join("logs")
+    }
+
+    /// Open a new log for a run of `task` on `project`, removing the oldest logs of the
+    /// pair beyond the retention limit
+    pub fn create(&self, project: &str, task: &str) -> MartyResult<(PathBuf, LogFile)> {
+        let dir = self.dir.join(file_name(project));
+        std::fs::create_dir_all(&dir)?;
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("{}-{}.log", file_name(task), millis));
+        let file = File::create(&path)?;
+
+        let logs = list(&dir, Some(task))?;
+        for (_, old) in logs.iter().take(logs.len().saturating_sub(self.keep)) {
+            std::fs::remove_file(old)?;
+        }
+        Ok((path, Arc::new(Mutex::new(file))))
+    }
+}
+
+/// The most recent log of `project`, for `task` or any task
+pub fn latest(workspace_root: &Path, project: &str, task: Option<&str>) -> MartyResult<PathBuf> {
+    let dir = TaskLogs::dir(workspace_root).join(file_name(project));
+    list(&dir, task)?
+        .pop()
+        .map(|(_, path)| path)
+        .ok_or_else(|| {
+            let run = match task {
+                Some(task) => format!("'{}' on '{}'", task, project),
+                None => format!("any task on '{}'", project),
+            };
+            MartyError::Workspace(format!(
+                "No logs of {} in {}; set `logs.enabled` in workspace.yml to capture them",
+                run,
+                dir.display()
+            ))
+        })
+}
+
+/// Logs in `dir`, optionally only those of `task`, oldest first with their timestamps
+fn list(dir: &Path, task: Option<&str>) -> MartyResult<Vec<(u128, PathBuf)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let task = task.map(file_name);
+
+    let mut logs = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "log") {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        // Task names may contain dashes, the timestamp never does
+        let Some((name, millis)) = stem.rsplit_once('-') else {
+            continue;
+        };
+        let Ok(millis) = millis.parse::<u128>() else {
+            continue;
+        };
+        if task.as_deref().is_none_or(|task| task == name) {
+            logs.push((millis, path));
+        }
+    }
+    logs.sort();
+    Ok(logs)
+}
+
+/// A project or task name usable as a single path component
+pub(crate) fn file_name(name: &str) -> String {
+    name.replace(['/', '\\', ':'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_log_and_retention() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let logs = TaskLogs::new(root, 2);
+
+        let mut paths = Vec::new();
+        for _ in 0..3 {
+            let (path, _) = logs.create("@acme/ui", "build-prod").unwrap();
+            paths.push(path);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let (test_log, _) = logs.create("@acme/ui", "test").unwrap();
+
+        // Only the two newest logs of a task are kept, other tasks are untouched
+        assert!(!paths[0].exists());
+        assert!(paths[1].exists() && paths[2].exists());
+        assert_eq!(
+            latest(root, "@acme/ui", Some("build-prod")).unwrap(),
+            paths[2]
+        );
+        assert_eq!(latest(root, "@acme/ui", None).unwrap(), test_log);
+        assert!(latest(root, "@acme/ui", Some("lint")).is_err());
+        assert!(latest(root, "other", None).is_err());
+    }
+}
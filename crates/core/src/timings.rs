@@ -0,0 +1,145 @@
+//! Where a command spends its time
+//!
+//! A [`Timings`] handle set through
+//! [`WorkspaceManagerConfig::timings`](crate::workspace_manager::WorkspaceManagerConfig)
+//! adds up the time spent in each [`Phase`] of a command, so `marty --timings` can show a
+//! breakdown that makes slow commands diagnosable from a user report. It records nothing
+//! by default.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::execution::runner::serialize_millis;
+
+/// A part of a command's work, in the order commands go through them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Reading workspace.yml and the task files
+    ConfigLoading,
+    /// Downloading, verifying and loading plugins
+    PluginResolution,
+    /// Walking the workspace with the plugins, or reading the discovery cache
+    Traversal,
+    GraphBuilding,
+    /// Working out which projects a task runs on, and in which order
+    Planning,
+    /// Running task commands
+    Execution,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::ConfigLoading => "Config loading",
+            Phase::PluginResolution => "Plugin resolution",
+            Phase::Traversal => "Traversal",
+            Phase::GraphBuilding => "Graph building",
+            Phase::Planning => "Planning",
+            Phase::Execution => "Execution",
+        }
+    }
+}
+
+/// Time spent in one phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PhaseTiming {
+    pub phase: Phase,
+    #[serde(serialize_with = "serialize_millis", rename = "duration_ms")]
+    pub duration: Duration,
+}
+
+/// Adds up the time spent per phase. Cloning shares the same totals.
+#[derive(Debug, Clone, Default)]
+pub struct Timings(Option<Arc<Mutex<Vec<PhaseTiming>>>>);
+
+impl Timings {
+    /// A handle that records, unlike the default one
+    pub fn enabled() -> Self {
+        Self(Some(Arc::default()))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn record(&self, phase: Phase, duration: Duration) {
+        let Some(Ok(mut phases)) = self.0.as_ref().map(|phases| phases.lock()) else {
+            return;
+        };
+        match phases.iter_mut().find(|timing| timing.phase == phase) {
+            Some(timing) => timing.duration += duration,
+            None => phases.push(PhaseTiming { phase, duration }),
+        }
+    }
+
+    /// Time `phase` until the returned guard is dropped
+    pub fn start(&self, phase: Phase) -> PhaseGuard<'_> {
+        PhaseGuard {
+            timings: self,
+            phase,
+            started: Instant::now(),
+        }
+    }
+
+    /// Run `f` as part of `phase`
+    pub fn time<T>(&self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let _guard = self.start(phase);
+        f()
+    }
+
+    /// The phases time was spent in, in phase order
+    pub fn phases(&self) -> Vec<PhaseTiming> {
+        let mut phases = self
+            .0
+            .as_ref()
+            .and_then(|phases| phases.lock().ok().map(|phases| phases.clone()))
+            .unwrap_or_default();
+        phases.sort_by_key(|timing| timing.phase);
+        phases
+    }
+}
+
+/// Records the time since it was created when dropped
+pub struct PhaseGuard<'a> {
+    timings: &'a Timings,
+    phase: Phase,
+    started: Instant,
+}
+
+impl Drop for PhaseGuard<'_> {
+    fn drop(&mut self) {
+        self.timings.record(self.phase, self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_add_up_in_phase_order() {
+        let timings = Timings::enabled();
+        timings.record(Phase::Execution, Duration::from_millis(30));
+        timings
+            .clone()
+            .record(Phase::ConfigLoading, Duration::from_millis(5));
+        timings.record(Phase::Execution, Duration::from_millis(12));
+        let planned = timings.time(Phase::Planning, || 7);
+        assert_eq!(planned, 7);
+
+        let phases = timings.phases();
+        let order: Vec<_> = phases.iter().map(|timing| timing.phase).collect();
+        assert_eq!(
+            order,
+            vec![Phase::ConfigLoading, Phase::Planning, Phase::Execution]
+        );
+        assert_eq!(phases[2].duration, Duration::from_millis(42));
+
+        let disabled = Timings::default();
+        disabled.record(Phase::Execution, Duration::from_millis(30));
+        assert!(disabled.phases().is_empty());
+    }
+}
@@ -1,11 +1,15 @@
 use globset::{Glob, GlobSetBuilder};
+use marty_plugin_protocol::Diagnostic;
 use petgraph::algo::kosaraju_scc;
 use petgraph::prelude::*;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 // Re-export types from plugin_protocol for convenience
-pub use marty_plugin_protocol::{InferredProject, Project, WorkspaceProvider};
+pub use marty_plugin_protocol::{
+    InferredProject, Project, ProjectKind, ProjectMessage, WorkspaceMutation, WorkspaceProvider,
+};
 
 /// Extended workspace structure with dependency graph information
 #[derive(Debug)]
@@ -15,6 +19,211 @@ pub struct Workspace {
     pub inferred_projects: Vec<InferredProject>,
     pub dep_graph: Option<petgraph::Graph<String, ()>>,
     pub dependency_cycles: Vec<Vec<String>>,
+    /// Cycles `allowedCycles` in workspace.yml accepts, moved out of `dependency_cycles`.
+    /// Planning doesn't fail on them, and their members don't wait for each other.
+    pub allowed_cycles: Vec<Vec<String>>,
+    /// Lookups by project name and directory, rebuilt with the dependency graph
+    pub index: ProjectIndex,
+}
+
+/// Interned project name: the project's node in the dependency graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProjectId(u32);
+
+impl ProjectId {
+    pub fn node(self) -> NodeIndex {
+        NodeIndex::new(self.0 as usize)
+    }
+
+    pub fn from_node(node: NodeIndex) -> Self {
+        Self(node.index() as u32)
+    }
+}
+
+/// Hash indexes over the projects of a workspace, so large workspaces avoid linear searches.
+///
+/// Names are interned once; [`ProjectId`]s are assigned in the order names were first seen,
+/// which is also the node order of the dependency graph. Projects or inferred projects with
+/// the same name resolve to the first one, as a linear search would.
+#[derive(Debug, Default)]
+pub struct ProjectIndex {
+    names: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, ProjectId>,
+    /// Position in `Workspace::projects`, by id
+    projects: Vec<Option<usize>>,
+    /// Position in `Workspace::inferred_projects` of the first inference of each name
+    inferred: HashMap<Arc<str>, usize>,
+    /// Position in `Workspace::projects`, by project directory
+    dirs: HashMap<PathBuf, usize>,
+}
+
+impl ProjectIndex {
+    /// Index `projects` and `inferred_projects`, interning `names` in order
+    pub fn new<'n>(
+        names: impl IntoIterator<Item = &'n str>,
+        projects: &[Project],
+        inferred_projects: &[InferredProject],
+    ) -> Self {
+        let mut index = Self::default();
+        for name in names {
+            index.intern(name);
+        }
+        index.projects = vec![None; index.names.len()];
+        for (position, project) in projects.iter().enumerate() {
+            if let Some(id) = index.id(&project.name) {
+                index.projects[id.0 as usize].get_or_insert(position);
+            }
+            index
+                .dirs
+                .entry(project.project_dir.clone())
+                .or_insert(position);
+        }
+        for (position, project) in inferred_projects.iter().enumerate() {
+            let name = match index.ids.get_key_value(project.name.as_str()) {
+                Some((name, _)) => name.clone(),
+                None => Arc::from(project.name.as_str()),
+            };
+            index.inferred.entry(name).or_insert(position);
+        }
+        index
+    }
+
+    fn intern(&mut self, name: &str) -> ProjectId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = ProjectId(self.names.len() as u32);
+        let name: Arc<str> = Arc::from(name);
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        id
+    }
+
+    pub fn id(&self, name: &str) -> Option<ProjectId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: ProjectId) -> Option<&str> {
+        self.names.get(id.0 as usize).map(|name| &**name)
+    }
+
+    /// Every interned name, in id order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(|name| &**name)
+    }
+}
+
+impl Workspace {
+    /// Rebuild [`Workspace::index`] from the projects and the dependency graph
+    pub fn reindex(&mut self) {
+        self.index = match &self.dep_graph {
+            Some(graph) => ProjectIndex::new(
+                graph.node_weights().map(String::as_str),
+                &self.projects,
+                &self.inferred_projects,
+            ),
+            None => ProjectIndex::new(
+                self.projects.iter().map(|p| p.name.as_str()),
+                &self.projects,
+                &self.inferred_projects,
+            ),
+        };
+    }
+
+    pub fn project_id(&self, name: &str) -> Option<ProjectId> {
+        self.index.id(name)
+    }
+
+    /// The project named `name`
+    pub fn project(&self, name: &str) -> Option<&Project> {
+        let id = self.index.id(name)?;
+        let position = (*self.index.projects.get(id.0 as usize)?)?;
+        self.projects.get(position)
+    }
+
+    /// The first project inferred under `name`
+    pub fn inferred_project(&self, name: &str) -> Option<&InferredProject> {
+        let position = *self.index.inferred.get(name)?;
+        self.inferred_projects.get(position)
+    }
+
+    /// The kind plugins inferred for the project named `name`: from an inference of the same
+    /// name, or else from one in the tracked project's directory, as marty.yml may rename it
+    pub fn inferred_kind(&self, name: &str) -> Option<ProjectKind> {
+        if let Some(kind) = self.inferred_project(name).and_then(|p| p.kind) {
+            return Some(kind);
+        }
+        let dir = &self.project(name)?.project_dir;
+        self.inferred_projects
+            .iter()
+            .filter(|p| &p.project_dir == dir)
+            .find_map(|p| p.kind)
+    }
+
+    /// The project whose directory is exactly `dir`
+    pub fn project_by_dir(&self, dir: &Path) -> Option<&Project> {
+        let position = *self.index.dirs.get(dir)?;
+        self.projects.get(position)
+    }
+
+    /// Projects `name` depends on directly, without their own dependencies
+    pub fn direct_dependencies(&self, name: &str) -> Vec<&str> {
+        let (Some(graph), Some(id)) = (&self.dep_graph, self.index.id(name)) else {
+            return Vec::new();
+        };
+        let mut dependencies: Vec<&str> = graph
+            .neighbors(id.node())
+            .filter_map(|node| self.index.name(ProjectId::from_node(node)))
+            .collect();
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        dependencies
+    }
+
+    /// Projects `name` depends on directly or through other projects, sorted by name.
+    /// Unlike [`get_recursive_dependencies`], dependency cycles are not an error.
+    pub fn transitive_dependencies(&self, name: &str) -> Vec<&str> {
+        let mut seen = BTreeSet::new();
+        let mut queue = self.direct_dependencies(name);
+        while let Some(dependency) = queue.pop() {
+            if dependency != name && seen.insert(dependency) {
+                queue.extend(self.direct_dependencies(dependency));
+            }
+        }
+        seen.into_iter().collect()
+    }
+
+    /// Directories of the [transitive dependencies](Self::transitive_dependencies) of `name`
+    pub fn dependency_dirs(&self, name: &str) -> Vec<&Path> {
+        self.transitive_dependencies(name)
+            .into_iter()
+            .filter_map(|dependency| self.project(dependency))
+            .map(|project| project.project_dir.as_path())
+            .collect()
+    }
+
+    /// Move the dependency cycles listed in `allowed` to [`Workspace::allowed_cycles`]. A
+    /// cycle only matches an entry with exactly its members, so a cycle that grows is
+    /// reported again.
+    pub fn allow_cycles(&mut self, allowed: &[Vec<String>]) {
+        let (allowed_cycles, cycles) = std::mem::take(&mut self.dependency_cycles)
+            .into_iter()
+            .partition(|cycle: &Vec<String>| allowed.iter().any(|entry| same_cycle(cycle, entry)));
+        self.dependency_cycles = cycles;
+        self.allowed_cycles = allowed_cycles;
+    }
+
+    /// Whether `a` and `b` depend on each other through an allowed cycle
+    pub fn in_allowed_cycle(&self, a: &str, b: &str) -> bool {
+        self.allowed_cycles
+            .iter()
+            .any(|cycle| cycle.iter().any(|p| p == a) && cycle.iter().any(|p| p == b))
+    }
+}
+
+/// Whether two lists name the same projects, in any order
+pub fn same_cycle(a: &[String], b: &[String]) -> bool {
+    a.iter().collect::<BTreeSet<_>>() == b.iter().collect::<BTreeSet<_>>()
 }
 
 impl From<&Workspace> for marty_plugin_protocol::Workspace {
@@ -30,7 +239,23 @@ impl From<&Workspace> for marty_plugin_protocol::Workspace {
 const DEFAULT_INCLUDE_GLOBS: &[&str] = &["**"];
 const DEFAULT_EXCLUDE_GLOBS: &[&str] = &["**/.git/**", "**/target/**", "**/node_modules/**"];
 
-pub fn traverse_workspace(caller: &dyn WorkspaceProvider, workspace: &mut Workspace) {
+/// Offer the plugin every file matching its globs, all at once if it takes them that way,
+/// and apply what it reports. Files the plugin couldn't process are returned as error
+/// diagnostics.
+pub fn traverse_workspace(
+    caller: &dyn WorkspaceProvider,
+    workspace: &mut Workspace,
+) -> Vec<Diagnostic> {
+    traverse_workspace_with(caller, workspace, &mut |_| {})
+}
+
+/// Like [`traverse_workspace`], calling `visit` with every directory scanned and every file
+/// offered to the plugin
+pub fn traverse_workspace_with(
+    caller: &dyn WorkspaceProvider,
+    workspace: &mut Workspace,
+    visit: &mut dyn FnMut(&Path),
+) -> Vec<Diagnostic> {
     let include_globs = caller.include_path_globs();
     let exclude_globs = caller.exclude_path_globs();
 
@@ -69,10 +294,20 @@ pub fn traverse_workspace(caller: &dyn WorkspaceProvider, workspace: &mut Worksp
     }
     let exclude_set = exclude_builder.build().unwrap_or_default();
 
+    // Plugins see the projects found so far; keep them in the plugin's view for the whole
+    // scan instead of copying them for every file
+    let mut plugin_workspace = marty_plugin_protocol::Workspace {
+        root: workspace.root.clone(),
+        projects: std::mem::take(&mut workspace.projects),
+        inferred_projects: std::mem::take(&mut workspace.inferred_projects),
+    };
+
+    let mut files = Vec::new();
     let mut queue = VecDeque::new();
     queue.push_back(workspace.root.clone());
 
     while let Some(current_dir) = queue.pop_front() {
+        visit(&current_dir);
         if let Ok(entries) = std::fs::read_dir(&current_dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
@@ -91,62 +326,307 @@ pub fn traverse_workspace(caller: &dyn WorkspaceProvider, workspace: &mut Worksp
                 }
 
                 if path.is_file() {
-                    let plugin_workspace = marty_plugin_protocol::Workspace::from(&*workspace);
-                    if let Some(project) = caller.on_file_found(&plugin_workspace, &path) {
-                        let manifest_path = project.project_dir.join("marty.yml");
-
-                        if manifest_path.exists() {
-                            workspace.projects.push(Project {
-                                name: project.name.clone(),
-                                project_dir: project.project_dir.clone(),
-                                file_path: Some(manifest_path),
-                                dependencies: Vec::new(),
-                            });
-                        }
-
-                        workspace.inferred_projects.push(project);
-                    }
+                    visit(&path);
+                    files.push(path);
                 } else if path.is_dir() {
                     queue.push_back(path);
                 }
             }
         }
     }
+
+    let mut diagnostics = Vec::new();
+    if let Some(found) = caller.on_files_found(&plugin_workspace, &files) {
+        apply_messages(&mut plugin_workspace, found.messages);
+        diagnostics = found.diagnostics;
+    } else {
+        for path in files {
+            match caller.on_file_found_messages(&plugin_workspace, &path) {
+                Ok(messages) => apply_messages(&mut plugin_workspace, messages),
+                Err(error) => diagnostics.push(error.into_diagnostic(&path)),
+            }
+        }
+    }
+
+    workspace.projects = plugin_workspace.projects;
+    workspace.inferred_projects = plugin_workspace.inferred_projects;
+    diagnostics
 }
 
-/// Build the dependency graph from the projects in the workspace
-pub fn build_dependency_graph(workspace: &mut Workspace) -> Result<(), String> {
-    let mut graph = DiGraph::<String, ()>::new();
-    let mut node_indices = HashMap::new();
+/// Apply the messages a plugin reported for a file during the scan, in order
+pub fn apply_project_messages(workspace: &mut Workspace, messages: Vec<ProjectMessage>) {
+    let mut plugin_workspace = marty_plugin_protocol::Workspace {
+        root: workspace.root.clone(),
+        projects: std::mem::take(&mut workspace.projects),
+        inferred_projects: std::mem::take(&mut workspace.inferred_projects),
+    };
+    apply_messages(&mut plugin_workspace, messages);
+    workspace.projects = plugin_workspace.projects;
+    workspace.inferred_projects = plugin_workspace.inferred_projects;
+}
 
-    // Add all projects as nodes
-    for project in &workspace.projects {
-        let node_index = graph.add_node(project.name.clone());
-        node_indices.insert(project.name.clone(), node_index);
+fn apply_messages(workspace: &mut marty_plugin_protocol::Workspace, messages: Vec<ProjectMessage>) {
+    for message in messages {
+        match message {
+            ProjectMessage::AddProject { project } => add_project(workspace, project.into()),
+            ProjectMessage::UpdateProject { project } => {
+                let project = InferredProject::from(project);
+                remove_project(workspace, &project.name, Some(&project.discovered_by));
+                add_project(workspace, project);
+            }
+            ProjectMessage::RemoveProject {
+                name,
+                discovered_by,
+            } => remove_project(workspace, &name, discovered_by.as_deref()),
+            ProjectMessage::AddDependencyEdge { from, to } => {
+                let mut found = false;
+                for project in workspace
+                    .inferred_projects
+                    .iter_mut()
+                    .filter(|p| p.name == from)
+                {
+                    found = true;
+                    if !project.workspace_dependencies.contains(&to) {
+                        project.workspace_dependencies.push(to.clone());
+                    }
+                }
+                if !found {
+                    eprintln!(
+                        "Warning: dependency edge from unknown project '{}' to '{}'",
+                        from, to
+                    );
+                }
+            }
+        }
     }
+}
 
-    // Add edges for dependencies
-    for project in &workspace.projects {
-        let from_node = node_indices[&project.name];
-        let inferred_project = workspace
+/// Record an inferred project, tracking it as a project when it has a marty.yml.
+///
+/// Projects outside the workspace root are rejected with a warning. Accepted project
+/// directories are stored as the root joined with their normalized workspace-relative path,
+/// so every project has a single spelling no matter how the plugin reported it.
+fn add_project(workspace: &mut marty_plugin_protocol::Workspace, mut project: InferredProject) {
+    let Some(relative) = workspace_relative_path(&workspace.root, &project.project_dir) else {
+        eprintln!(
+            "Warning: ignoring project '{}' from plugin '{}': {} is outside the workspace root {}",
+            project.name,
+            project.discovered_by,
+            project.project_dir.display(),
+            workspace.root.display()
+        );
+        return;
+    };
+    project.project_dir = if relative.as_os_str().is_empty() {
+        workspace.root.clone()
+    } else {
+        workspace.root.join(relative)
+    };
+
+    let manifest_path = project.project_dir.join("marty.yml");
+    if manifest_path.exists() {
+        workspace.projects.push(Project {
+            name: project.name.clone(),
+            project_dir: project.project_dir.clone(),
+            file_path: Some(manifest_path),
+            dependencies: Vec::new(),
+        });
+    }
+    workspace.inferred_projects.push(project);
+}
+
+/// `path` relative to the workspace root, with `.` and `..` resolved, or `None` when it
+/// lies outside the root. Relative paths are taken relative to the root.
+pub fn workspace_relative_path(root: &Path, path: &Path) -> Option<PathBuf> {
+    let root = normalize_path(&std::path::absolute(root).ok()?)?;
+    let path = normalize_path(&std::path::absolute(root.join(path)).ok()?)?;
+    if let Ok(relative) = path.strip_prefix(&root) {
+        return Some(relative.to_path_buf());
+    }
+    // The same directory reached through a symlink (e.g. /tmp and /private/tmp)
+    let canonical_root = root.canonicalize().ok()?;
+    let canonical_path = path.canonicalize().ok()?;
+    canonical_path
+        .strip_prefix(&canonical_root)
+        .ok()
+        .map(Path::to_path_buf)
+}
+
+/// Resolve `.` and `..` components lexically. `None` if `..` climbs above the root.
+fn normalize_path(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    Some(normalized)
+}
+
+/// Drop inferred projects named `name` (only those from `discovered_by`, when given), and
+/// stop tracking the project once no inferred project of that name is left
+fn remove_project(
+    workspace: &mut marty_plugin_protocol::Workspace,
+    name: &str,
+    discovered_by: Option<&str>,
+) {
+    workspace
+        .inferred_projects
+        .retain(|p| p.name != name || discovered_by.is_some_and(|key| p.discovered_by != key));
+    if !workspace.inferred_projects.iter().any(|p| p.name == name) {
+        workspace.projects.retain(|p| p.name != name);
+    }
+}
+
+/// Apply the mutations a plugin returned from `on_scan_complete` to the inferred projects.
+///
+/// A mutation applies to every inferred project with the given name. Mutations that name
+/// no known project are skipped with a warning.
+pub fn apply_workspace_mutations(
+    workspace: &mut Workspace,
+    plugin_key: &str,
+    mutations: Vec<WorkspaceMutation>,
+) {
+    for mutation in mutations {
+        let project_name = match &mutation {
+            WorkspaceMutation::SetDependencies { project, .. }
+            | WorkspaceMutation::AddDependency { project, .. }
+            | WorkspaceMutation::RemoveDependency { project, .. }
+            | WorkspaceMutation::RemoveProject { project, .. } => project.clone(),
+        };
+        if !workspace
             .inferred_projects
             .iter()
-            .find(|p| p.name == project.name);
-        if inferred_project.is_none() {
+            .any(|p| p.name == project_name)
+        {
+            eprintln!(
+                "Warning: plugin '{}' referenced unknown project '{}'",
+                plugin_key, project_name
+            );
+            continue;
+        }
+
+        if let WorkspaceMutation::RemoveProject { discovered_by, .. } = &mutation {
+            workspace.inferred_projects.retain(|p| {
+                p.name != project_name
+                    || discovered_by
+                        .as_ref()
+                        .is_some_and(|key| &p.discovered_by != key)
+            });
             continue;
         }
-        for dep in &inferred_project.unwrap().workspace_dependencies {
-            if let Some(&to_node) = node_indices.get(dep) {
+
+        for project in workspace
+            .inferred_projects
+            .iter_mut()
+            .filter(|p| p.name == project_name)
+        {
+            let dependencies = &mut project.workspace_dependencies;
+            match &mutation {
+                WorkspaceMutation::SetDependencies {
+                    dependencies: new, ..
+                } => *dependencies = new.clone(),
+                WorkspaceMutation::AddDependency { dependency, .. } => {
+                    if !dependencies.contains(dependency) {
+                        dependencies.push(dependency.clone());
+                    }
+                }
+                WorkspaceMutation::RemoveDependency { dependency, .. } => {
+                    dependencies.retain(|d| d != dependency)
+                }
+                WorkspaceMutation::RemoveProject { .. } => {}
+            }
+        }
+    }
+}
+
+/// Keep only the projects whose directory, relative to the workspace root, matches one of
+/// `patterns`, and rebuild the dependency graph. A pattern ending in `/**` also matches the
+/// directory itself. Dependencies on projects outside the view are dropped.
+pub fn retain_view(workspace: &mut Workspace, patterns: &[String]) -> Result<(), String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| e.to_string())?);
+        if let Some(dir) = pattern.strip_suffix("/**") {
+            builder.add(Glob::new(dir).map_err(|e| e.to_string())?);
+        }
+    }
+    let set = builder.build().map_err(|e| e.to_string())?;
+    let root = workspace.root.clone();
+    let in_view = |dir: &Path| set.is_match(dir.strip_prefix(&root).unwrap_or(dir));
+
+    let mut removed = HashSet::new();
+    workspace.projects.retain(|p| {
+        let keep = in_view(&p.project_dir);
+        if !keep {
+            removed.insert(p.name.clone());
+        }
+        keep
+    });
+    workspace.inferred_projects.retain(|p| {
+        let keep = in_view(&p.project_dir);
+        if !keep {
+            removed.insert(p.name.clone());
+        }
+        keep
+    });
+    for name in workspace
+        .projects
+        .iter()
+        .map(|p| &p.name)
+        .chain(workspace.inferred_projects.iter().map(|p| &p.name))
+    {
+        removed.remove(name);
+    }
+    for project in &mut workspace.inferred_projects {
+        project
+            .workspace_dependencies
+            .retain(|dependency| !removed.contains(dependency));
+    }
+
+    build_dependency_graph(workspace)
+}
+
+/// Build the dependency graph from the projects in the workspace, and index the projects
+pub fn build_dependency_graph(workspace: &mut Workspace) -> Result<(), String> {
+    let index = ProjectIndex::new(
+        workspace.projects.iter().map(|p| p.name.as_str()),
+        &workspace.projects,
+        &workspace.inferred_projects,
+    );
+
+    // Add all projects as nodes, in id order
+    let mut graph = DiGraph::<String, ()>::with_capacity(index.names.len(), 0);
+    for name in index.names() {
+        graph.add_node(name.to_string());
+    }
+    workspace.index = index;
+
+    // Add edges for dependencies
+    let mut edges = Vec::new();
+    for (id, name) in workspace.index.names.iter().enumerate() {
+        let Some(inferred_project) = workspace.inferred_project(name) else {
+            continue;
+        };
+        for dep in &inferred_project.workspace_dependencies {
+            if let Some(to) = workspace.project_id(dep) {
                 // Add edge: project -> dependency (dependency comes first)
-                graph.add_edge(from_node, to_node, ());
+                edges.push((NodeIndex::new(id), to.node()));
             } else {
                 return Err(format!(
                     "Project '{}' depends on '{}' which was not found",
-                    project.name, dep
+                    name, dep
                 ));
             }
         }
     }
+    graph.extend_with_edges(edges);
 
     // Detect cycles using strongly connected components
     let mut cycles: Vec<Vec<String>> = kosaraju_scc(&graph)
@@ -192,17 +672,11 @@ pub fn get_recursive_dependencies(
     let mut result = Vec::new();
     let mut stack = Vec::new();
 
-    // Create a reverse mapping from project names to node indices
-    let mut name_to_node = HashMap::new();
-    for (node_index, node_weight) in graph.node_indices().zip(graph.node_weights()) {
-        name_to_node.insert(node_weight.clone(), node_index);
-    }
-
     // Resolve targets to node indices and prime traversal structures
     let mut start_nodes = Vec::new();
     for target in targets {
-        if let Some(&node_index) = name_to_node.get(target) {
-            start_nodes.push(node_index);
+        if let Some(id) = workspace.project_id(target) {
+            start_nodes.push(id.node());
         } else {
             return Err(format!(
                 "Target project '{}' not found in workspace",
@@ -226,15 +700,16 @@ pub fn get_recursive_dependencies(
 
     // If cycles exist that involve reachable nodes, report them
     if !workspace.dependency_cycles.is_empty() {
-        let reachable_names: HashSet<String> = reachable_nodes
-            .iter()
-            .map(|node| graph[*node].clone())
-            .collect();
-
         let mut relevant_cycles: Vec<Vec<String>> = workspace
             .dependency_cycles
             .iter()
-            .filter(|cycle| cycle.iter().any(|name| reachable_names.contains(name)))
+            .filter(|cycle| {
+                cycle.iter().any(|name| {
+                    workspace
+                        .project_id(name)
+                        .is_some_and(|id| reachable_nodes.contains(&id.node()))
+                })
+            })
             .cloned()
             .collect();
 
@@ -284,9 +759,239 @@ pub fn get_recursive_dependencies(
     Ok(result)
 }
 
+/// Chains of dependencies leading from `from` to `to`, each starting with `from`. Only a
+/// shortest chain unless `all` is set, in which case every chain without repeated projects
+/// is returned, shortest first. Empty when `from` doesn't depend on `to`.
+pub fn dependency_paths(
+    workspace: &Workspace,
+    from: &str,
+    to: &str,
+    all: bool,
+) -> Result<Vec<Vec<String>>, String> {
+    let graph = workspace.dep_graph.as_ref().ok_or_else(|| {
+        "Dependency graph not built. Call build_dependency_graph first.".to_string()
+    })?;
+    let node = |name: &str| {
+        workspace
+            .project_id(name)
+            .map(ProjectId::node)
+            .ok_or_else(|| format!("Project '{}' not found in workspace", name))
+    };
+    let (start, end) = (node(from)?, node(to)?);
+    let names = |path: Vec<NodeIndex>| path.into_iter().map(|n| graph[n].clone()).collect();
+
+    if all {
+        let mut paths: Vec<Vec<String>> =
+            petgraph::algo::all_simple_paths::<Vec<_>, _>(graph, start, end, 0, None)
+                .map(names)
+                .collect();
+        paths.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        return Ok(paths);
+    }
+
+    // Breadth-first, visiting dependencies in name order so the chain is stable
+    let mut previous: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(current) = queue.pop_front() {
+        if current == end && current != start {
+            break;
+        }
+        let mut dependencies: Vec<NodeIndex> = graph.neighbors(current).collect();
+        dependencies.sort_by(|a, b| graph[*a].cmp(&graph[*b]));
+        for dependency in dependencies {
+            if dependency != start && !previous.contains_key(&dependency) {
+                previous.insert(dependency, current);
+                queue.push_back(dependency);
+            }
+        }
+    }
+    if start == end || !previous.contains_key(&end) {
+        return Ok(Vec::new());
+    }
+
+    let mut path = vec![end];
+    while let Some(&before) = previous.get(path.last().unwrap()) {
+        path.push(before);
+    }
+    path.reverse();
+    Ok(vec![names(path)])
+}
+
+/// Plugin that reported `project`'s dependency on `dependency`
+pub fn dependency_source<'a>(
+    workspace: &'a Workspace,
+    project: &str,
+    dependency: &str,
+) -> Option<&'a str> {
+    workspace
+        .inferred_projects
+        .iter()
+        .find(|p| p.name == project && p.workspace_dependencies.iter().any(|d| d == dependency))
+        .map(|p| p.discovered_by.as_str())
+}
+
+/// Find the project that owns a path relative to the workspace root.
+///
+/// When projects are nested, the project with the deepest matching directory wins.
+pub fn find_owning_project<'a>(workspace: &'a Workspace, path: &Path) -> Option<&'a Project> {
+    let absolute_path = workspace.root.join(path);
+    absolute_path
+        .ancestors()
+        .find_map(|dir| workspace.project_by_dir(dir))
+}
+
+/// List every file in the workspace as a path relative to the root, sorted.
+///
+/// The default excludes (`.git`, `target`, `node_modules`), Marty's own cache and any
+/// `extra_excludes` patterns are skipped.
+pub fn list_workspace_files(workspace: &Workspace, extra_excludes: &[String]) -> Vec<PathBuf> {
+    let mut exclude_builder = GlobSetBuilder::new();
+    for pattern in DEFAULT_EXCLUDE_GLOBS
+        .iter()
+        .copied()
+        .chain(["**/.marty/cache/**"])
+        .chain(extra_excludes.iter().map(String::as_str))
+    {
+        if let Ok(glob) = Glob::new(pattern) {
+            exclude_builder.add(glob);
+        }
+    }
+    let exclude_set = exclude_builder.build().unwrap_or_default();
+
+    let mut files = Vec::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(workspace.root.clone());
+
+    while let Some(current_dir) = queue.pop_front() {
+        let Ok(entries) = std::fs::read_dir(&current_dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let relative_path = path.strip_prefix(&workspace.root).unwrap_or(&path);
+
+            if path.is_dir() {
+                // Don't descend into directories whose contents are all excluded
+                if !exclude_set.is_match(relative_path.join("_")) {
+                    queue.push_back(path);
+                }
+            } else if path.is_file() && !exclude_set.is_match(relative_path) {
+                files.push(relative_path.to_path_buf());
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Get the given projects together with every project that transitively depends on them
+pub fn get_reverse_dependencies(
+    workspace: &Workspace,
+    projects: &[String],
+) -> Result<Vec<String>, String> {
+    let graph = workspace.dep_graph.as_ref().ok_or_else(|| {
+        "Dependency graph not built. Call build_dependency_graph first.".to_string()
+    })?;
+
+    let mut queue = VecDeque::new();
+    for project in projects {
+        let id = workspace
+            .project_id(project)
+            .ok_or_else(|| format!("Project '{}' not found in workspace", project))?;
+        queue.push_back(id.node());
+    }
+
+    let mut visited = HashSet::new();
+    let mut result = Vec::new();
+    while let Some(node_index) = queue.pop_front() {
+        if !visited.insert(node_index) {
+            continue;
+        }
+        result.push(graph[node_index].clone());
+
+        // Edges point from a project to its dependencies, so dependents are incoming neighbors
+        for dependent in graph.neighbors_directed(node_index, Direction::Incoming) {
+            queue.push_back(dependent);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Directory group a project belongs to: the first `depth` components of its directory
+/// relative to the workspace root (e.g. "apps" for `apps/web` at depth 1).
+///
+/// Projects at the workspace root (or outside it) are grouped under ".".
+pub fn directory_group(workspace: &Workspace, project: &Project, depth: usize) -> String {
+    let Ok(relative) = project.project_dir.strip_prefix(&workspace.root) else {
+        return ".".to_string();
+    };
+
+    let components: Vec<String> = relative
+        .components()
+        .take(depth)
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    if components.is_empty() {
+        ".".to_string()
+    } else {
+        components.join("/")
+    }
+}
+
+/// Collapse the dependency graph into directory groups.
+///
+/// Returns the projects in each group and, for each group, the other groups it depends on.
+/// Edges between projects of the same group are dropped.
+#[allow(clippy::type_complexity)]
+pub fn group_dependencies_by_directory(
+    workspace: &Workspace,
+    depth: usize,
+) -> Result<
+    (
+        BTreeMap<String, Vec<String>>,
+        BTreeMap<String, BTreeSet<String>>,
+    ),
+    String,
+> {
+    let graph = workspace.dep_graph.as_ref().ok_or_else(|| {
+        "Dependency graph not built. Call build_dependency_graph first.".to_string()
+    })?;
+
+    let project_groups: HashMap<&str, String> = workspace
+        .projects
+        .iter()
+        .map(|p| (p.name.as_str(), directory_group(workspace, p, depth)))
+        .collect();
+
+    let mut members: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for project in &workspace.projects {
+        members
+            .entry(project_groups[project.name.as_str()].clone())
+            .or_default()
+            .push(project.name.clone());
+    }
+
+    let mut edges: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for edge in graph.edge_references() {
+        let from = project_groups.get(graph[edge.source()].as_str());
+        let to = project_groups.get(graph[edge.target()].as_str());
+        if let (Some(from), Some(to)) = (from, to) {
+            if from != to {
+                edges.entry(from.clone()).or_default().insert(to.clone());
+            }
+        }
+    }
+
+    Ok((members, edges))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use marty_plugin_protocol::InferredProjectMessage;
 
     struct TestCaller;
 
@@ -320,6 +1025,7 @@ mod tests {
                         project_dir: parent.to_path_buf(),
                         workspace_dependencies: dependencies,
                         discovered_by: "test".to_string(),
+                        kind: None,
                     })
                 } else {
                     None
@@ -328,6 +1034,22 @@ mod tests {
                 None
             }
         }
+
+        fn try_on_file_found(
+            &self,
+            workspace: &marty_plugin_protocol::Workspace,
+            path: &std::path::Path,
+        ) -> Result<Option<InferredProject>, marty_plugin_protocol::PluginError> {
+            let contents = std::fs::read_to_string(path).unwrap_or_default();
+            match (1..)
+                .zip(contents.lines())
+                .find(|(_, line)| *line == "invalid")
+            {
+                Some((line, _)) => Err(marty_plugin_protocol::PluginError::new("invalid line")
+                    .with_span(marty_plugin_protocol::Span::line(line))),
+                None => Ok(self.on_file_found(workspace, path)),
+            }
+        }
     }
 
     #[test]
@@ -343,6 +1065,8 @@ mod tests {
             inferred_projects: Vec::new(),
             dep_graph: None,
             dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
         };
         let caller = TestCaller;
 
@@ -378,6 +1102,8 @@ mod tests {
             inferred_projects: Vec::new(),
             dep_graph: None,
             dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
         };
         let caller = TestCaller;
 
@@ -389,6 +1115,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_traverse_workspace_reports_files_the_plugin_cannot_process() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        for (dir, config) in [("good", "dep=broken\n"), ("broken", "dep=good\ninvalid\n")] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("project_config.txt"), config).unwrap();
+        }
+        let mut workspace = Workspace {
+            root: root.clone(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+
+        let errors = traverse_workspace(&TestCaller, &mut workspace);
+
+        let names: Vec<_> = workspace
+            .inferred_projects
+            .iter()
+            .map(|p| &p.name)
+            .collect();
+        assert_eq!(names, ["good"]);
+        let broken = root.join("broken/project_config.txt");
+        assert_eq!(
+            errors,
+            [Diagnostic::error("invalid line")
+                .with_file(&broken)
+                .with_span(marty_plugin_protocol::Span::line(2))]
+        );
+    }
+
+    /// Resolves every project against all the others, which needs the whole batch
+    struct BatchCaller;
+
+    impl WorkspaceProvider for BatchCaller {
+        fn include_path_globs(&self) -> Vec<String> {
+            vec!["**/project_config.txt".to_string()]
+        }
+
+        fn on_file_found(
+            &self,
+            _workspace: &marty_plugin_protocol::Workspace,
+            _path: &std::path::Path,
+        ) -> Option<InferredProject> {
+            unreachable!("files are handed over in one batch")
+        }
+
+        fn on_files_found(
+            &self,
+            _workspace: &marty_plugin_protocol::Workspace,
+            paths: &[PathBuf],
+        ) -> Option<marty_plugin_protocol::FilesFound> {
+            let dirs: Vec<&Path> = paths.iter().filter_map(|path| path.parent()).collect();
+            let name = |dir: &Path| dir.file_name().unwrap().to_string_lossy().into_owned();
+            let messages = dirs
+                .iter()
+                .map(|dir| {
+                    InferredProject {
+                        name: name(dir),
+                        project_dir: dir.to_path_buf(),
+                        discovered_by: "batch".to_string(),
+                        workspace_dependencies: dirs
+                            .iter()
+                            .filter(|other| other != &dir)
+                            .map(|other| name(other))
+                            .collect(),
+                        kind: None,
+                    }
+                    .into()
+                })
+                .collect();
+            Some(marty_plugin_protocol::FilesFound {
+                messages,
+                diagnostics: vec![Diagnostic::info(format!("{} manifests", paths.len()))],
+            })
+        }
+    }
+
+    #[test]
+    fn test_traverse_workspace_hands_batching_plugins_every_file_at_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        for dir in ["a", "nested/b"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("project_config.txt"), "").unwrap();
+        }
+        let mut workspace = Workspace {
+            root,
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+
+        let diagnostics = traverse_workspace(&BatchCaller, &mut workspace);
+
+        let projects: Vec<_> = workspace
+            .inferred_projects
+            .iter()
+            .map(|p| (p.name.as_str(), p.workspace_dependencies.clone()))
+            .collect();
+        assert_eq!(
+            projects,
+            [("a", vec!["b".to_string()]), ("b", vec!["a".to_string()])]
+        );
+        assert_eq!(diagnostics, [Diagnostic::info("2 manifests")]);
+    }
+
     // Additional test for directory with non-matching files
     #[test]
     fn test_traverse_workspace_no_projects() {
@@ -402,6 +1242,8 @@ mod tests {
             inferred_projects: Vec::new(),
             dep_graph: None,
             dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
         };
         let caller = TestCaller;
 
@@ -435,6 +1277,8 @@ mod tests {
             inferred_projects: Vec::new(),
             dep_graph: None,
             dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
         };
         let caller = TestCaller;
 
@@ -467,4 +1311,512 @@ mod tests {
             "Cycle should be reported in message"
         );
     }
+
+    #[test]
+    fn test_allowed_cycles_are_planned_without_waiting() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+
+        for (name, deps) in [("a", "dep=b\n"), ("b", "dep=a\n"), ("c", "dep=a\n")] {
+            let project_dir = root.join(name);
+            std::fs::create_dir_all(&project_dir).unwrap();
+            std::fs::write(project_dir.join("project_config.txt"), deps).unwrap();
+            std::fs::write(project_dir.join("marty.yml"), format!("name: {}\n", name)).unwrap();
+        }
+
+        let mut workspace = Workspace {
+            root,
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        traverse_workspace(&TestCaller, &mut workspace);
+        build_dependency_graph(&mut workspace).unwrap();
+
+        // An entry naming more projects than the cycle doesn't match it
+        workspace.allow_cycles(&[vec!["a".to_string(), "b".to_string(), "c".to_string()]]);
+        assert_eq!(workspace.dependency_cycles.len(), 1);
+        assert!(workspace.allowed_cycles.is_empty());
+
+        workspace.allow_cycles(&[vec!["b".to_string(), "a".to_string()]]);
+        assert!(workspace.dependency_cycles.is_empty());
+        assert_eq!(
+            workspace.allowed_cycles,
+            vec![vec!["a".to_string(), "b".to_string()]]
+        );
+        assert!(workspace.in_allowed_cycle("b", "a"));
+        assert!(!workspace.in_allowed_cycle("a", "c"));
+
+        let mut dependencies = get_recursive_dependencies(&workspace, &["c".to_string()])
+            .expect("Allowed cycles shouldn't prevent dependency resolution");
+        dependencies.sort();
+        assert_eq!(dependencies, vec!["a", "b", "c"]);
+
+        let levels =
+            crate::execution::dependencies::group_by_dependency_levels(&workspace, &dependencies)
+                .unwrap();
+        assert_eq!(
+            levels,
+            vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["c".to_string()]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_owning_project_and_reverse_dependencies() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+
+        // lib <- app, and "tool" nested inside app with no dependents
+        let lib = root.join("lib");
+        let app = root.join("app");
+        let tool = app.join("tool");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::create_dir_all(&tool).unwrap();
+
+        std::fs::write(lib.join("project_config.txt"), "").unwrap();
+        std::fs::write(app.join("project_config.txt"), "dep=lib\n").unwrap();
+        std::fs::write(tool.join("project_config.txt"), "").unwrap();
+        for dir in [&lib, &app, &tool] {
+            std::fs::write(dir.join("marty.yml"), "").unwrap();
+        }
+
+        let mut workspace = Workspace {
+            root,
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        traverse_workspace(&TestCaller, &mut workspace);
+        build_dependency_graph(&mut workspace).unwrap();
+
+        let owner =
+            |path: &str| find_owning_project(&workspace, Path::new(path)).map(|p| p.name.clone());
+        assert_eq!(owner("app/src/main.rs").as_deref(), Some("app"));
+        assert_eq!(owner("app/tool/run.sh").as_deref(), Some("tool"));
+        assert_eq!(owner("README.md"), None);
+
+        // Ids are the graph nodes of the interned names
+        let app_id = workspace.project_id("app").unwrap();
+        assert_eq!(workspace.dep_graph.as_ref().unwrap()[app_id.node()], "app");
+        assert_eq!(workspace.index.name(app_id), Some("app"));
+        assert_eq!(workspace.project("tool").unwrap().project_dir, tool);
+        assert_eq!(workspace.inferred_project("lib").unwrap().name, "lib");
+        assert!(workspace.project("missing").is_none());
+
+        let mut affected = get_reverse_dependencies(&workspace, &["lib".to_string()]).unwrap();
+        affected.sort();
+        assert_eq!(affected, vec!["app".to_string(), "lib".to_string()]);
+
+        let affected = get_reverse_dependencies(&workspace, &["tool".to_string()]).unwrap();
+        assert_eq!(affected, vec!["tool".to_string()]);
+    }
+
+    #[test]
+    fn test_dependency_paths() {
+        let root = PathBuf::from("/ws");
+        let project = |name: &str, discovered_by: &str, deps: &[&str]| InferredProject {
+            name: name.to_string(),
+            project_dir: root.join(name),
+            discovered_by: discovered_by.to_string(),
+            workspace_dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            kind: None,
+        };
+        let inferred_projects = vec![
+            project("app", "cargo", &["web", "legacy-adapter", "api"]),
+            project("web", "cargo", &["api"]),
+            project("api", "cargo", &["legacy"]),
+            project("legacy-adapter", "pnpm", &["legacy"]),
+            project("legacy", "cargo", &[]),
+        ];
+        let mut workspace = Workspace {
+            root: root.clone(),
+            projects: inferred_projects
+                .iter()
+                .map(|p| Project {
+                    name: p.name.clone(),
+                    project_dir: p.project_dir.clone(),
+                    file_path: None,
+                    dependencies: Vec::new(),
+                })
+                .collect(),
+            inferred_projects,
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        build_dependency_graph(&mut workspace).unwrap();
+
+        // Of the two shortest chains, the one through the first name in order
+        assert_eq!(
+            dependency_paths(&workspace, "app", "legacy", false).unwrap(),
+            vec![vec!["app", "api", "legacy"]]
+        );
+        assert_eq!(
+            dependency_paths(&workspace, "app", "legacy", true).unwrap(),
+            vec![
+                vec!["app", "api", "legacy"],
+                vec!["app", "legacy-adapter", "legacy"],
+                vec!["app", "web", "api", "legacy"],
+            ]
+        );
+        assert!(dependency_paths(&workspace, "legacy", "app", false)
+            .unwrap()
+            .is_empty());
+        assert!(dependency_paths(&workspace, "app", "app", true)
+            .unwrap()
+            .is_empty());
+        assert!(dependency_paths(&workspace, "app", "missing", false).is_err());
+
+        assert_eq!(
+            dependency_source(&workspace, "legacy-adapter", "legacy"),
+            Some("pnpm")
+        );
+        assert_eq!(dependency_source(&workspace, "legacy", "app"), None);
+    }
+
+    #[test]
+    fn test_group_dependencies_by_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+
+        let projects = [
+            ("apps/web", "dep=ui\n"),
+            ("apps/admin", "dep=ui\n"),
+            ("libs/ui", "dep=core\n"),
+            ("libs/core", ""),
+        ];
+        for (dir, config) in projects {
+            let dir = root.join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("project_config.txt"), config).unwrap();
+            std::fs::write(dir.join("marty.yml"), "").unwrap();
+        }
+
+        let mut workspace = Workspace {
+            root,
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        traverse_workspace(&TestCaller, &mut workspace);
+        build_dependency_graph(&mut workspace).unwrap();
+
+        let (mut members, edges) = group_dependencies_by_directory(&workspace, 1).unwrap();
+        members.values_mut().for_each(|m| m.sort());
+        assert_eq!(
+            members["apps"],
+            vec!["admin".to_string(), "web".to_string()]
+        );
+        assert_eq!(members["libs"], vec!["core".to_string(), "ui".to_string()]);
+
+        // Only the inter-group edge remains; libs/ui -> libs/core is internal to "libs"
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges["apps"], BTreeSet::from(["libs".to_string()]));
+
+        let (members, _) = group_dependencies_by_directory(&workspace, 2).unwrap();
+        assert!(members.contains_key("apps/web"));
+    }
+
+    #[test]
+    fn test_list_workspace_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        for file in [
+            "README.md",
+            "app/src/main.rs",
+            "app/target/debug/app",
+            ".marty/workspace.yml",
+            ".marty/cache/tasks/x.json",
+            "docs/generated/api.html",
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let workspace = Workspace {
+            root,
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+
+        let files = list_workspace_files(&workspace, &["docs/generated/**".to_string()]);
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from(".marty/workspace.yml"),
+                PathBuf::from("README.md"),
+                PathBuf::from("app/src/main.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retain_view() {
+        let root = PathBuf::from("/ws");
+        let dirs = [
+            ("payments", "apps/payments"),
+            ("payments-api", "apps/payments/api"),
+            ("money", "libs/money"),
+            ("auth", "libs/auth"),
+            ("shop", "apps/shop"),
+        ];
+        let deps: HashMap<&str, Vec<&str>> = HashMap::from([
+            ("payments", vec!["money", "auth"]),
+            ("payments-api", vec!["payments"]),
+            ("shop", vec!["money"]),
+        ]);
+        let mut workspace = Workspace {
+            root: root.clone(),
+            projects: dirs
+                .iter()
+                .map(|(name, dir)| Project {
+                    name: name.to_string(),
+                    project_dir: root.join(dir),
+                    file_path: None,
+                    dependencies: Vec::new(),
+                })
+                .collect(),
+            inferred_projects: dirs
+                .iter()
+                .map(|(name, dir)| InferredProject {
+                    name: name.to_string(),
+                    project_dir: root.join(dir),
+                    discovered_by: "cargo".to_string(),
+                    workspace_dependencies: deps
+                        .get(name)
+                        .map(|d| d.iter().map(|d| d.to_string()).collect())
+                        .unwrap_or_default(),
+                    kind: None,
+                })
+                .collect(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+        build_dependency_graph(&mut workspace).unwrap();
+
+        retain_view(
+            &mut workspace,
+            &["apps/payments/**".to_string(), "libs/money".to_string()],
+        )
+        .unwrap();
+        let names: Vec<&str> = workspace.projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["payments", "payments-api", "money"]);
+        assert_eq!(
+            workspace
+                .inferred_project("payments")
+                .unwrap()
+                .workspace_dependencies,
+            vec!["money".to_string()]
+        );
+        let mut closure =
+            get_recursive_dependencies(&workspace, &["payments-api".to_string()]).unwrap();
+        closure.sort();
+        assert_eq!(closure, ["money", "payments", "payments-api"]);
+
+        assert!(retain_view(&mut workspace, &["apps/[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_apply_workspace_mutations() {
+        let project = |name: &str, discovered_by: &str, deps: &[&str]| InferredProject {
+            name: name.to_string(),
+            project_dir: PathBuf::from(name),
+            discovered_by: discovered_by.to_string(),
+            workspace_dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            kind: None,
+        };
+        let mut workspace = Workspace {
+            root: PathBuf::from("."),
+            projects: Vec::new(),
+            inferred_projects: vec![
+                project("web", "pnpm", &["ui-alias"]),
+                project("ui", "pnpm", &[]),
+                project("ui", "typescript", &[]),
+            ],
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+
+        apply_workspace_mutations(
+            &mut workspace,
+            "pnpm",
+            vec![
+                WorkspaceMutation::RemoveDependency {
+                    project: "web".to_string(),
+                    dependency: "ui-alias".to_string(),
+                },
+                WorkspaceMutation::AddDependency {
+                    project: "web".to_string(),
+                    dependency: "ui".to_string(),
+                },
+                WorkspaceMutation::RemoveProject {
+                    project: "ui".to_string(),
+                    discovered_by: Some("typescript".to_string()),
+                },
+                WorkspaceMutation::AddDependency {
+                    project: "missing".to_string(),
+                    dependency: "ui".to_string(),
+                },
+            ],
+        );
+
+        let summary = workspace
+            .inferred_projects
+            .iter()
+            .map(|p| {
+                (
+                    p.name.as_str(),
+                    p.discovered_by.as_str(),
+                    p.workspace_dependencies.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("web", "pnpm", vec!["ui".to_string()]),
+                ("ui", "pnpm", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_project_messages_corrects_earlier_inferences() {
+        let temp = tempfile::tempdir().unwrap();
+        let web_dir = temp.path().join("web");
+        std::fs::create_dir_all(&web_dir).unwrap();
+        std::fs::write(web_dir.join("marty.yml"), "name: web\n").unwrap();
+
+        let message = |name: &str, dir: &Path| InferredProjectMessage {
+            name: name.to_string(),
+            project_dir: dir.display().to_string(),
+            discovered_by: "pnpm".to_string(),
+            workspace_dependencies: Vec::new(),
+            kind: None,
+        };
+        let mut workspace = Workspace {
+            root: temp.path().to_path_buf(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+
+        apply_project_messages(
+            &mut workspace,
+            vec![
+                ProjectMessage::AddProject {
+                    project: message("web", temp.path()),
+                },
+                ProjectMessage::AddProject {
+                    project: message("packages", temp.path()),
+                },
+            ],
+        );
+        // A later file shows where "web" really lives and that "packages" is no project
+        apply_project_messages(
+            &mut workspace,
+            vec![
+                ProjectMessage::UpdateProject {
+                    project: message("web", &web_dir),
+                },
+                ProjectMessage::RemoveProject {
+                    name: "packages".to_string(),
+                    discovered_by: None,
+                },
+                ProjectMessage::AddDependencyEdge {
+                    from: "web".to_string(),
+                    to: "ui".to_string(),
+                },
+            ],
+        );
+
+        assert_eq!(workspace.inferred_projects.len(), 1);
+        assert_eq!(workspace.inferred_projects[0].project_dir, web_dir);
+        assert_eq!(
+            workspace.inferred_projects[0].workspace_dependencies,
+            vec!["ui".to_string()]
+        );
+        // The corrected location has a marty.yml, so the project is now tracked
+        assert_eq!(workspace.projects.len(), 1);
+        assert_eq!(workspace.projects[0].project_dir, web_dir);
+    }
+
+    #[test]
+    fn test_projects_outside_the_root_are_rejected() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("repo");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let message = |name: &str, dir: PathBuf| ProjectMessage::AddProject {
+            project: InferredProjectMessage {
+                name: name.to_string(),
+                project_dir: dir.display().to_string(),
+                discovered_by: "cargo".to_string(),
+                workspace_dependencies: Vec::new(),
+                kind: None,
+            },
+        };
+        let mut workspace = Workspace {
+            root: root.clone(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+            dep_graph: None,
+            dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
+        };
+
+        apply_project_messages(
+            &mut workspace,
+            vec![
+                message("sibling", temp.path().join("other")),
+                message("escape", root.join("libs/../../other")),
+                message("relative", PathBuf::from("libs/./core/../api")),
+                message("nested", root.join("libs/ui")),
+            ],
+        );
+
+        let dirs = workspace
+            .inferred_projects
+            .iter()
+            .map(|p| (p.name.as_str(), p.project_dir.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dirs,
+            vec![
+                ("relative", root.join("libs/api")),
+                ("nested", root.join("libs/ui")),
+            ]
+        );
+        assert_eq!(
+            workspace_relative_path(&root, &root.join("libs/ui")),
+            Some(PathBuf::from("libs/ui"))
+        );
+        assert_eq!(workspace_relative_path(&root, &root), Some(PathBuf::new()));
+    }
 }
@@ -19,6 +19,7 @@
 //! # async fn example() -> marty_core::types::MartyResult<()> {
 //! let manager = WorkspaceManager::new(WorkspaceManagerConfig {
 //!     workspace_root: PathBuf::from("."),
+//!     ..Default::default()
 //! }).await?;
 //!
 //! // List all projects
@@ -33,67 +34,386 @@
 //! # }
 //! ```
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
+use sha2::{Digest, Sha256};
+
+use crate::builtin_plugins;
+use crate::bundle::Bundle;
+use crate::change_detection::{change_detector, ChangeDetector, SnapshotChangeDetector};
+use crate::colors::project_colors;
 use crate::configs::{
-    project::{parse_project_config, ProjectConfig},
-    tasks::{parse_tasks_config, TaskConfig, TasksFileConfig},
-    workspace::{parse_workspace_config, WorkspaceConfig},
+    tasks::{
+        parse_tasks_config, Command, ErrorPolicy, TaskConfig, TaskDependency, TasksFileConfig,
+    },
+    workspace::{parse_workspace_config, ChangeDetection, PluginConfig, Shell, WorkspaceConfig},
+};
+use crate::cycle_breaks::{suggest_cycle_breaks, CycleBreakPlan};
+use crate::dependency_rules::DependencyRules;
+use crate::discovery_cache::{DiscoveryCache, ScanStamps};
+use crate::execution::cache::{CacheNamespace, TaskCache};
+use crate::execution::command::CommandExecutor;
+use crate::execution::dependencies::group_by_dependency_levels;
+use crate::execution::executor::LocalExecutor;
+use crate::execution::notify::WebhookNotifier;
+use crate::execution::remote_cache;
+use crate::execution::runner::{RunHook, TaskPlugins, TaskRunnerConfig};
+use crate::execution::upload::UploadQueue;
+use crate::git;
+use crate::messages::Message;
+use crate::plan_diff::{PlanSide, PlanSnapshot};
+use crate::plugin_cache::{file_digest, PluginCache, PluginConflictChecker};
+use crate::plugin_diagnostics::PluginDiagnostic;
+use crate::plugin_runtime::{self, WasmSandbox};
+use crate::progress::{ProgressEvent, ProgressSink};
+use crate::project_configs::ProjectConfigs;
+use crate::results::{
+    CacheStatsResult, DependencyGraphResult, DependencyLintResult, DependencyPathsResult,
+    FileOwnershipResult, GroupedDependencyGraphResult, InferredProjectInfo, ProjectInfo,
+    ProjectListResult, ProjectMetadata, ProjectTaskStats, TaskDescriptionResult, TaskRunResult,
+    WorkspaceCheckResult,
 };
-use crate::plugin_cache::PluginCache;
-use crate::plugin_runtime_dylib::DylibWorkspaceProvider;
-use crate::results::{DependencyGraphResult, InferredProjectInfo, ProjectInfo, ProjectListResult};
-use crate::task_execution::{resolve_task_execution_plan, TaskExecutionPlan};
+use crate::state::{ResultsRecorder, WorkspaceState, UNUSED_PLUGIN_RUNS};
+use crate::task_execution::{project_kind, resolve_task_execution_plan, TaskExecutionPlan};
+use crate::task_logs::{self, TaskLogs};
 use crate::tasks::run_task_on_targets;
+use crate::timings::{Phase, Timings};
 use crate::types::{MartyError, MartyResult};
-use crate::workspace::{build_dependency_graph, traverse_workspace, Workspace};
-use marty_plugin_protocol::{InferredProject, MartyPlugin, WorkspaceProvider};
+use crate::workspace::{
+    apply_workspace_mutations, build_dependency_graph, dependency_paths, dependency_source,
+    find_owning_project, get_recursive_dependencies, get_reverse_dependencies,
+    group_dependencies_by_directory, list_workspace_files, retain_view, same_cycle,
+    traverse_workspace_with, ProjectIndex, Workspace,
+};
+use marty_plugin_protocol::{
+    InferredProject, MartyPlugin, ProjectKind, ProjectMessage, SuggestedTask, WorkspaceMutation,
+    WorkspaceProvider,
+};
+
+/// Setup task run by `bootstrap` when workspace.yml doesn't name one
+const DEFAULT_BOOTSTRAP_TASK: &str = "setup";
+
+/// Name under which `exec` reports its ad-hoc command
+const EXEC_TASK: &str = "exec";
 
 /// High-level workspace manager that encapsulates all workspace operations
 pub struct WorkspaceManager {
     pub workspace: Workspace,
     pub task_configs: TasksFileConfig,
     pub workspace_config: WorkspaceConfig,
+    /// In-process plugins registered through the builder, kept for re-discovery
+    embedded_plugins: Vec<Arc<dyn MartyPlugin>>,
+    /// Tasks plugins define for the projects they discover, by plugin key
+    plugin_tasks: BTreeMap<String, Vec<SuggestedTask>>,
+    /// The plugins discovery ran with, for tasks they run themselves. `None` when the
+    /// projects came from the discovery cache; the plugins are loaded on demand then.
+    task_plugins: Option<TaskPlugins>,
+    /// Number of projects each plugin discovered, recorded by commands that write the run
+    /// history. Empty when the projects came from the discovery cache.
+    plugin_usage: BTreeMap<String, usize>,
+    plugin_selection: PluginSelection,
+    progress: ProgressSink,
+    timings: Timings,
+    /// Finds the changed files for the affected planner
+    change_detector: Arc<dyn ChangeDetector>,
 }
 
 /// Configuration for initializing a workspace manager
+#[derive(Debug, Clone, Default)]
 pub struct WorkspaceManagerConfig {
     pub workspace_root: PathBuf,
+    /// Temporary adjustments to the set of plugins configured in workspace.yml
+    pub plugins: PluginSelection,
+    /// Receives discovery and task run progress
+    pub progress: ProgressSink,
+    /// Only work with the projects of this view from workspace.yml
+    pub view: Option<String>,
+    /// Adds up the time spent in each phase of the command
+    pub timings: Timings,
+    /// How changed files are found, instead of `changeDetection` from workspace.yml
+    pub change_detection: Option<ChangeDetection>,
+}
+
+/// Builds a [`WorkspaceManager`], optionally with plugins compiled into the embedding
+/// application rather than loaded from dynamic libraries
+pub struct WorkspaceManagerBuilder {
+    config: WorkspaceManagerConfig,
+    plugins: Vec<Arc<dyn MartyPlugin>>,
+}
+
+impl WorkspaceManagerBuilder {
+    /// Temporarily skip or restrict the plugins configured in workspace.yml
+    pub fn plugin_selection(mut self, selection: PluginSelection) -> Self {
+        self.config.plugins = selection;
+        self
+    }
+
+    /// Report discovery and task run progress to `progress`
+    pub fn progress(mut self, progress: ProgressSink) -> Self {
+        self.config.progress = progress;
+        self
+    }
+
+    /// Add up the time spent in each phase of the command in `timings`
+    pub fn timings(mut self, timings: Timings) -> Self {
+        self.config.timings = timings;
+        self
+    }
+
+    /// Find changed files with `change_detection` instead of what workspace.yml sets
+    pub fn change_detection(mut self, change_detection: ChangeDetection) -> Self {
+        self.config.change_detection = Some(change_detection);
+        self
+    }
+
+    /// Only work with the projects of a view defined in workspace.yml
+    pub fn view(mut self, view: impl Into<String>) -> Self {
+        self.config.view = Some(view.into());
+        self
+    }
+
+    /// Add an in-process plugin. Registered plugins run discovery after the plugins from
+    /// workspace.yml and `.marty/plugins`, in registration order, and are subject to the
+    /// workspace includes/excludes and the plugin selection like any other plugin.
+    pub fn register_plugin(mut self, plugin: Box<dyn MartyPlugin>) -> Self {
+        self.plugins.push(Arc::from(plugin));
+        self
+    }
+
+    pub async fn build(self) -> MartyResult<WorkspaceManager> {
+        WorkspaceManager::initialize(self.config, self.plugins).await
+    }
+}
+
+/// Restricts which plugins are loaded, without editing workspace.yml.
+///
+/// Plugins are matched by key (e.g. `cargo`) or by the name they were resolved under.
+#[derive(Debug, Clone, Default)]
+pub struct PluginSelection {
+    /// Plugins to skip
+    pub disabled: Vec<String>,
+    /// When non-empty, only these plugins are loaded
+    pub only: Vec<String>,
+    /// Plugins turned off with `enabled: false` in workspace.yml to load anyway
+    pub enabled: Vec<String>,
+}
+
+impl PluginSelection {
+    /// Whether a plugin known by any of `names` is turned on despite `enabled: false`
+    pub fn enables(&self, names: &[&str]) -> bool {
+        self.enabled
+            .iter()
+            .any(|selector| names.contains(&selector.as_str()))
+    }
+
+    /// Whether a disabled workspace.yml entry could be one of the plugins to turn on. Its
+    /// key is only known once it is loaded, so this goes by the plugin name, repository,
+    /// URL or path of the entry.
+    fn may_enable(&self, config: &PluginConfig) -> bool {
+        let source = config.source_description();
+        self.enabled.iter().any(|selector| {
+            config.plugin.as_deref() == Some(selector.as_str())
+                || source.contains(selector.as_str())
+        })
+    }
+
+    /// Whether a plugin known by any of `names` should be loaded
+    pub fn allows(&self, names: &[&str]) -> bool {
+        let matches = |selector: &String| names.contains(&selector.as_str());
+        if self.disabled.iter().any(matches) {
+            return false;
+        }
+        self.only.is_empty() || self.only.iter().any(matches)
+    }
+
+    /// Selectors that didn't match any of the available plugins
+    fn unmatched<'a>(&'a self, available: &HashSet<String>) -> Vec<&'a str> {
+        self.disabled
+            .iter()
+            .chain(&self.only)
+            .chain(&self.enabled)
+            .filter(|selector| !available.contains(*selector))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Options controlling how a task run selects and executes projects
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Only run projects affected by changes since the last fully successful run
+    /// of the task on the current branch (plus their dependents)
+    pub since_last_success: bool,
+    /// Only run projects that failed, were blocked or never started the last time the task
+    /// ran on them (plus their dependents)
+    pub failed: bool,
+    /// Only run if files inside this project changed since `affected_since`, and then only
+    /// this project and its dependents
+    pub changed_only_within: Option<String>,
+    /// Only run projects affected by changes to the working tree (plus their dependents)
+    pub affected: bool,
+    /// With `affected` or `changed_only_within`, the base the changes are found against: a
+    /// git ref, Mercurial revision or snapshot name, depending on the change detection
+    /// (defaults to the revision the working tree is based on, so uncommitted changes)
+    pub affected_since: Option<String>,
+    /// Only run these of the task's compatible projects (and what they depend on),
+    /// e.g. the ones picked with `marty run --interactive`
+    pub projects: Option<Vec<String>>,
+    /// Extra environment variables for every command of the run
+    pub env: BTreeMap<String, String>,
+    /// Arguments appended to the task's command, e.g. `--release` for
+    /// `marty run build -- --release`
+    pub args: Vec<String>,
+    /// Keep running projects that do not depend on a failed project
+    pub keep_going: bool,
+    /// When keeping going, also attempt projects whose dependencies failed
+    pub run_blocked: bool,
+    /// When keeping going, stop after this many projects failed
+    pub max_failures: Option<usize>,
+    /// Print the resolved commands instead of running them
+    pub dry_run: bool,
+    /// Run independent projects at the same time, at most this many at once
+    pub jobs: Option<usize>,
 }
 
 impl WorkspaceManager {
     /// Initialize a new workspace manager from the given workspace root
     pub async fn new(config: WorkspaceManagerConfig) -> MartyResult<Self> {
-        // Load workspace configuration
-        let workspace_config = Self::load_workspace_config(&config.workspace_root)?;
+        Self::initialize(config, Vec::new()).await
+    }
+
+    /// Start building a workspace manager, e.g. to register in-process plugins
+    pub fn builder(workspace_root: impl Into<PathBuf>) -> WorkspaceManagerBuilder {
+        WorkspaceManagerBuilder {
+            config: WorkspaceManagerConfig {
+                workspace_root: workspace_root.into(),
+                ..WorkspaceManagerConfig::default()
+            },
+            plugins: Vec::new(),
+        }
+    }
 
-        // Load and merge task configurations
-        let task_configs = Self::load_task_configs(&config.workspace_root)?;
+    async fn initialize(
+        config: WorkspaceManagerConfig,
+        embedded_plugins: Vec<Arc<dyn MartyPlugin>>,
+    ) -> MartyResult<Self> {
+        let timings = config.timings;
+        let (workspace_config, task_configs) = timings.time(Phase::ConfigLoading, || {
+            // Load workspace configuration
+            let workspace_config = Self::load_workspace_config(&config.workspace_root)?;
+
+            // Load and merge task configurations
+            let task_configs = Self::load_task_configs(&config.workspace_root, &workspace_config)?;
+            Ok::<_, MartyError>((workspace_config, task_configs))
+        })?;
 
         // Load workspace providers and initialize workspace
-        let workspace =
-            Self::initialize_workspace(config.workspace_root, &workspace_config).await?;
+        let (mut workspace, plugin_tasks, task_plugins) = Self::initialize_workspace(
+            config.workspace_root,
+            &workspace_config,
+            &config.plugins,
+            &embedded_plugins,
+            &config.progress,
+            &timings,
+        )
+        .await?;
+        let plugin_usage = Self::plugin_usage(&workspace, task_plugins.as_ref());
+        if let Some(view) = &config.view {
+            Self::apply_view(&mut workspace, &workspace_config, view)?;
+        }
+        Self::allow_cycles(&mut workspace, &workspace_config);
+        let change_detector = Self::change_detector(&workspace_config, config.change_detection);
 
         Ok(Self {
             workspace,
             task_configs,
             workspace_config,
+            embedded_plugins,
+            plugin_tasks,
+            task_plugins,
+            plugin_usage,
+            plugin_selection: config.plugins,
+            progress: config.progress,
+            timings,
+            change_detector,
         })
     }
 
+    /// Initialize a workspace manager that discovers projects with the given providers
+    /// instead of the plugins configured in workspace.yml.
+    ///
+    /// This lets plugin authors run their plugin in-process against fixture workspaces.
+    /// The providers are used as given: Marty doesn't initialize or shut them down, so
+    /// tests of plugins needing that call `initialize()` themselves.
+    pub fn with_providers(
+        workspace_root: PathBuf,
+        providers: Vec<Box<dyn MartyPlugin>>,
+    ) -> MartyResult<Self> {
+        let workspace_config = Self::load_workspace_config(&workspace_root)?;
+        let task_configs = Self::load_task_configs(&workspace_root, &workspace_config)?;
+        let mut workspace = Self::discover_workspace(
+            workspace_root,
+            &providers,
+            None,
+            &ProgressSink::default(),
+            &Timings::default(),
+        )?;
+        Self::allow_cycles(&mut workspace, &workspace_config);
+        let change_detector = Self::change_detector(&workspace_config, None);
+        let plugin_tasks = Self::plugin_tasks(&providers);
+        let task_plugins = TaskPlugins::new(providers);
+
+        Ok(Self {
+            plugin_usage: Self::plugin_usage(&workspace, Some(&task_plugins)),
+            workspace,
+            task_configs,
+            workspace_config,
+            embedded_plugins: Vec::new(),
+            plugin_tasks,
+            task_plugins: Some(task_plugins),
+            plugin_selection: PluginSelection::default(),
+            progress: ProgressSink::default(),
+            timings: Timings::default(),
+            change_detector,
+        })
+    }
+
+    /// The change detector selected by `selected`, or else by workspace.yml
+    fn change_detector(
+        workspace_config: &WorkspaceConfig,
+        selected: Option<ChangeDetection>,
+    ) -> Arc<dyn ChangeDetector> {
+        let kind = selected
+            .or(workspace_config.change_detection)
+            .unwrap_or_default();
+        change_detector(
+            kind,
+            workspace_config.excludes.as_deref().unwrap_or_default(),
+        )
+    }
+
     /// List all projects in the workspace
     pub fn list_projects(&self, include_inferred: bool) -> MartyResult<ProjectListResult> {
+        let project_configs = self.project_configs();
         let tracked_projects = self
             .workspace
             .projects
             .iter()
             .map(|p| {
-                let project_config = self.load_project_config(&p.project_dir);
+                let project_config = project_configs.get(&p.name).ok().flatten();
                 ProjectInfo {
                     name: p.name.clone(),
                     path: p.project_dir.clone(),
-                    tags: project_config.ok().and_then(|c| c.tags).unwrap_or_default(),
+                    kind: project_config
+                        .as_ref()
+                        .and_then(|c| c.kind)
+                        .or_else(|| self.workspace.inferred_kind(&p.name)),
+                    tags: project_config
+                        .and_then(|c| c.tags.clone())
+                        .unwrap_or_default(),
                     has_config: p.project_dir.join("marty.yml").exists(),
                 }
             })
@@ -107,6 +427,7 @@ impl WorkspaceManager {
                     name: p.name.clone(),
                     path: p.project_dir.clone(),
                     discovered_by: p.discovered_by.clone(),
+                    kind: p.kind,
                     is_tracked: self
                         .workspace
                         .projects
@@ -118,61 +439,1271 @@ impl WorkspaceManager {
             Vec::new()
         };
 
-        Ok(ProjectListResult {
-            explicit_projects: tracked_projects,
-            inferred_projects,
-            project_colors: self.get_project_colors(),
-        })
+        Ok(ProjectListResult {
+            explicit_projects: tracked_projects,
+            inferred_projects,
+            project_colors: project_colors(
+                self.workspace
+                    .projects
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .chain(
+                        self.workspace
+                            .inferred_projects
+                            .iter()
+                            .map(|p| p.name.as_str()),
+                    ),
+            ),
+        })
+    }
+
+    /// The kind of a project: the one set in its marty.yml, or else the one plugins inferred
+    pub fn project_kind(&self, name: &str) -> MartyResult<Option<ProjectKind>> {
+        project_kind(&self.workspace, name)
+    }
+
+    /// Get execution plan for a task
+    pub fn get_execution_plan(&self, target: &str) -> MartyResult<TaskExecutionPlan> {
+        self.timings
+            .time(Phase::Planning, || self.resolve_execution_plan(target))
+    }
+
+    fn resolve_execution_plan(&self, target: &str) -> MartyResult<TaskExecutionPlan> {
+        let (project_filter, task_name) = Self::parse_target(target)?;
+        let workspace_task = self.task_configs.tasks.iter().any(|t| t.name == task_name);
+        let plugin_task = self
+            .plugin_tasks
+            .values()
+            .flatten()
+            .any(|task| task.name == task_name);
+        let project_configs = self.project_configs();
+        if plugin_task && !workspace_task {
+            return self.resolve_project_task_plan(
+                &project_configs,
+                &task_name,
+                project_filter.as_deref(),
+            );
+        }
+        resolve_task_execution_plan(
+            &self.workspace,
+            &project_configs,
+            &self.task_configs,
+            &task_name,
+            project_filter.as_deref(),
+        )
+    }
+
+    /// Plan a task without a workspace-level definition from the projects that define it
+    /// themselves or through a plugin's default tasks. Other projects are left out.
+    fn resolve_project_task_plan(
+        &self,
+        project_configs: &ProjectConfigs,
+        task_name: &str,
+        project_filter: Option<&str>,
+    ) -> MartyResult<TaskExecutionPlan> {
+        let task_map = self.task_map(project_configs)?;
+        let has_task = |project: &str| task_map.contains_key(&format!("{}:{}", project, task_name));
+        let initial_targets = match project_filter {
+            Some(project) => {
+                if self.workspace.project(project).is_none() {
+                    return Err(MartyError::Task(format!("Project '{}' not found", project)));
+                }
+                if !has_task(project) {
+                    return Err(MartyError::Task(format!(
+                        "Task '{}' not found for project '{}'",
+                        task_name, project
+                    )));
+                }
+                vec![project.to_string()]
+            }
+            None => self
+                .workspace
+                .projects
+                .iter()
+                .map(|project| project.name.clone())
+                .filter(|project| has_task(project))
+                .collect(),
+        };
+        let compatible_projects = get_recursive_dependencies(&self.workspace, &initial_targets)
+            .map_err(|e| MartyError::Task(format!("Failed to resolve dependencies: {}", e)))?
+            .into_iter()
+            .filter(|project| has_task(project))
+            .collect();
+        Ok(TaskExecutionPlan {
+            task_name: task_name.to_string(),
+            compatible_projects,
+            project_filter: project_filter.map(String::from),
+        })
+    }
+
+    /// The task definition each project of a plan runs, with project overrides applied
+    pub fn plan_task_configs(
+        &self,
+        plan: &TaskExecutionPlan,
+    ) -> MartyResult<BTreeMap<String, TaskConfig>> {
+        let mut task_map = self.build_task_map()?;
+        let mut configs = BTreeMap::new();
+        for project in &plan.compatible_projects {
+            let task_config = task_map
+                .remove(&format!("{}:{}", project, plan.task_name))
+                .or_else(|| task_map.get(&plan.task_name).cloned());
+            if let Some(task_config) = task_config {
+                configs.insert(project.clone(), task_config);
+            }
+        }
+        Ok(configs)
+    }
+
+    /// Execute a task on the workspace, returning the outcome and timing of every project
+    pub async fn run_task(&self, target: &str) -> MartyResult<TaskRunResult> {
+        self.run_task_with_options(target, &RunOptions::default())
+            .await
+    }
+
+    /// Execute a task on the workspace with the given run options
+    pub async fn run_task_with_options(
+        &self,
+        target: &str,
+        options: &RunOptions,
+    ) -> MartyResult<TaskRunResult> {
+        self.run_task_with_hooks(target, options, Vec::new()).await
+    }
+
+    /// Execute a task like [`Self::run_task_with_options`], additionally calling `hooks`
+    /// once the run finishes
+    pub async fn run_task_with_hooks(
+        &self,
+        target: &str,
+        options: &RunOptions,
+        hooks: Vec<Box<dyn RunHook>>,
+    ) -> MartyResult<TaskRunResult> {
+        let planning = self.timings.start(Phase::Planning);
+        let mut execution_plan = self.resolve_execution_plan(target)?;
+
+        if execution_plan.compatible_projects.is_empty() {
+            return Err(MartyError::Task(format!(
+                "No compatible projects found for task '{}'",
+                execution_plan.task_name
+            )));
+        }
+
+        if options.since_last_success {
+            if let Some(affected) =
+                self.projects_changed_since_last_success(&execution_plan.task_name)?
+            {
+                execution_plan
+                    .compatible_projects
+                    .retain(|project| affected.contains(project));
+
+                if execution_plan.compatible_projects.is_empty() {
+                    println!(
+                        "No projects changed since the last successful run of '{}'",
+                        execution_plan.task_name
+                    );
+                    return Ok(TaskRunResult::empty(&execution_plan.task_name));
+                }
+            }
+        }
+
+        if options.failed {
+            let state = WorkspaceState::load(&self.workspace.root)?;
+            let unfinished = state
+                .unfinished(&execution_plan.task_name)
+                .unwrap_or_default();
+            let affected: HashSet<String> = get_reverse_dependencies(&self.workspace, &unfinished)
+                .map_err(MartyError::Workspace)?
+                .into_iter()
+                .collect();
+            execution_plan
+                .compatible_projects
+                .retain(|project| affected.contains(project));
+
+            if execution_plan.compatible_projects.is_empty() {
+                println!(
+                    "No projects failed the last run of '{}'",
+                    execution_plan.task_name
+                );
+                return Ok(TaskRunResult::empty(&execution_plan.task_name));
+            }
+        }
+
+        if let Some(project) = &options.changed_only_within {
+            let base = options.affected_since.as_deref();
+            let affected = self.dependents_if_changed(project, base)?;
+            execution_plan
+                .compatible_projects
+                .retain(|name| affected.contains(name));
+
+            if execution_plan.compatible_projects.is_empty() {
+                println!(
+                    "No changes in '{}' since {} affecting '{}'",
+                    project,
+                    base.unwrap_or("the checked-out revision"),
+                    execution_plan.task_name
+                );
+                return Ok(TaskRunResult::empty(&execution_plan.task_name));
+            }
+        }
+
+        if options.affected {
+            let base = options.affected_since.as_deref();
+            let affected = self.affected_projects(base)?;
+            execution_plan
+                .compatible_projects
+                .retain(|project| affected.contains(project));
+
+            if execution_plan.compatible_projects.is_empty() {
+                match base {
+                    Some(base) => println!(
+                        "No projects affected by changes since '{}' have task '{}'",
+                        base, execution_plan.task_name
+                    ),
+                    None => println!(
+                        "No projects affected by changes have task '{}'",
+                        execution_plan.task_name
+                    ),
+                }
+                return Ok(TaskRunResult::empty(&execution_plan.task_name));
+            }
+        }
+
+        if let Some(projects) = &options.projects {
+            execution_plan
+                .compatible_projects
+                .retain(|project| projects.contains(project));
+
+            if execution_plan.compatible_projects.is_empty() {
+                println!("No projects selected for '{}'", execution_plan.task_name);
+                return Ok(TaskRunResult::empty(&execution_plan.task_name));
+            }
+        }
+
+        self.warn_allowed_cycles(&execution_plan.compatible_projects);
+        let task_map = self.build_task_map()?;
+        // Command line flags take precedence over the task's error policy
+        let policy = task_map
+            .get(&execution_plan.task_name)
+            .and_then(|task| task.on_error);
+        let keep_going = options.keep_going
+            || matches!(policy, Some(ErrorPolicy::Continue | ErrorPolicy::Isolate));
+        let cache = self.task_cache()?;
+        drop(planning);
+        let plugins = self.task_plugins(&task_map).await?;
+
+        let _execution = self.timings.start(Phase::Execution);
+        let result = run_task_on_targets(
+            &execution_plan.task_name,
+            &execution_plan.compatible_projects,
+            &self.workspace,
+            &task_map,
+            cache.map(|cache| {
+                cache
+                    .with_extra_env(options.env.clone())
+                    .with_extra_args(options.args.clone())
+            }),
+            self.run_hooks()
+                .into_iter()
+                .chain((!options.dry_run).then(|| {
+                    Box::new(ResultsRecorder {
+                        workspace_root: self.workspace.root.clone(),
+                    }) as Box<dyn RunHook>
+                }))
+                .chain(hooks)
+                .collect(),
+            TaskRunnerConfig {
+                keep_going,
+                extra_args: options.args.clone(),
+                run_blocked: options.run_blocked || policy == Some(ErrorPolicy::Isolate),
+                max_failures: options.max_failures,
+                cancel_on_failure: !keep_going && policy == Some(ErrorPolicy::FailFast),
+                env: options.env.clone(),
+                dry_run: options.dry_run,
+                shell: self.shell(),
+                enable_parallel_execution: options.jobs.is_some_and(|jobs| jobs > 1),
+                max_concurrency: options.jobs,
+                progress: self.progress.clone(),
+                task_logs: self.task_logs(),
+                plugins,
+                ..TaskRunnerConfig::default()
+            },
+        )
+        .await?;
+
+        // Only a run of the task across the whole workspace counts as fully green
+        if execution_plan.project_filter.is_none() && !options.dry_run {
+            self.record_successful_run(&execution_plan.task_name);
+        }
+        if !options.dry_run {
+            self.record_plugin_usage();
+        }
+
+        Ok(result)
+    }
+
+    /// Run a shell command in the directory of every project, or of `projects` only, in
+    /// dependency order without defining a task. The run is reported as task `exec` and
+    /// never cached.
+    pub async fn exec(
+        &self,
+        command: &str,
+        projects: &[String],
+        options: &RunOptions,
+        hooks: Vec<Box<dyn RunHook>>,
+    ) -> MartyResult<TaskRunResult> {
+        for name in projects {
+            if self.workspace.project(name).is_none() {
+                return Err(MartyError::Project(format!("Project '{}' not found", name)));
+            }
+        }
+        let targets = match projects {
+            [] => self
+                .workspace
+                .projects
+                .iter()
+                .map(|project| project.name.clone())
+                .collect(),
+            projects => projects.to_vec(),
+        };
+
+        let task = TaskConfig {
+            name: EXEC_TASK.to_string(),
+            command: Some(Command::Single(command.to_string())),
+            ..TaskConfig::default()
+        };
+        let _execution = self.timings.start(Phase::Execution);
+        run_task_on_targets(
+            EXEC_TASK,
+            &targets,
+            &self.workspace,
+            &HashMap::from([(EXEC_TASK.to_string(), task)]),
+            None,
+            self.run_hooks().into_iter().chain(hooks).collect(),
+            TaskRunnerConfig {
+                keep_going: options.keep_going,
+                env: options.env.clone(),
+                dry_run: options.dry_run,
+                shell: self.shell(),
+                enable_parallel_execution: options.jobs.is_some_and(|jobs| jobs > 1),
+                max_concurrency: options.jobs,
+                only_targets: true,
+                in_project_dirs: true,
+                progress: self.progress.clone(),
+                ..TaskRunnerConfig::default()
+            },
+        )
+        .await
+    }
+
+    /// Set up a fresh checkout: run the workspace-level `before` commands, then each project's
+    /// setup task in dependency order. Setup results are always cached so re-running is cheap.
+    pub async fn bootstrap(&self) -> MartyResult<()> {
+        let config = self.workspace_config.bootstrap.clone().unwrap_or_default();
+        let task_name = config.task.as_deref().unwrap_or(DEFAULT_BOOTSTRAP_TASK);
+
+        for command in config.before.iter().flatten() {
+            CommandExecutor::new(&self.workspace, &[])
+                .with_backend(Box::new(LocalExecutor {
+                    shell: self.shell(),
+                }))
+                .execute_shell_command(command)?;
+        }
+
+        let task_map = self.build_task_map()?;
+        let suffix = format!(":{}", task_name);
+        if !task_map
+            .keys()
+            .any(|key| key == task_name || key.ends_with(&suffix))
+        {
+            println!("No '{}' task defined, nothing to bootstrap", task_name);
+            return Ok(());
+        }
+
+        // Without a workspace-level setup task, only projects declaring their own are set up
+        let mut execution_plan = self.get_execution_plan(task_name)?;
+        if !task_map.contains_key(task_name) {
+            execution_plan
+                .compatible_projects
+                .retain(|project| task_map.contains_key(&format!("{}{}", project, suffix)));
+        }
+
+        let cache = match self.task_cache()? {
+            Some(cache) => cache,
+            None => TaskCache::new(&self.workspace.root, self.cache_namespace()?)
+                .with_shell(self.shell()),
+        };
+
+        let plugins = self.task_plugins(&task_map).await?;
+        let _execution = self.timings.start(Phase::Execution);
+        run_task_on_targets(
+            task_name,
+            &execution_plan.compatible_projects,
+            &self.workspace,
+            &task_map,
+            Some(cache),
+            self.run_hooks(),
+            TaskRunnerConfig {
+                shell: self.shell(),
+                progress: self.progress.clone(),
+                task_logs: self.task_logs(),
+                plugins,
+                ..TaskRunnerConfig::default()
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Let the plugins register a project just created in `project_dir`, e.g. by a
+    /// generator, with their tools so the next discovery finds it. Returns the notes about
+    /// what each plugin changed, with the plugin's key.
+    ///
+    /// `project_dir` is relative to the workspace root, or absolute, and must be an existing
+    /// directory inside the workspace.
+    pub async fn project_created(&self, project_dir: &Path) -> MartyResult<Vec<(String, String)>> {
+        let joined = self.workspace.root.join(project_dir);
+        let project_dir = joined.canonicalize().map_err(|e| {
+            MartyError::Path(format!(
+                "Project directory {} is not accessible: {}",
+                joined.display(),
+                e
+            ))
+        })?;
+        let root = self.workspace.root.canonicalize()?;
+        if project_dir == root || !project_dir.starts_with(&root) || !project_dir.is_dir() {
+            return Err(MartyError::Path(format!(
+                "{} is not a directory inside the workspace",
+                joined.display()
+            )));
+        }
+        // Plugins see the directory the way they see the workspace root
+        let project_dir = self
+            .workspace
+            .root
+            .join(project_dir.strip_prefix(&root).unwrap());
+        let providers = {
+            let _resolving = self.timings.start(Phase::PluginResolution);
+            Self::load_workspace_providers(
+                &self.workspace.root,
+                &self.workspace_config,
+                &self.plugin_selection,
+                &self.embedded_plugins,
+            )
+            .await?
+        };
+
+        let plugin_workspace = marty_plugin_protocol::Workspace::from(&self.workspace);
+        let mut notes = Vec::new();
+        for plugin in &providers {
+            for note in plugin.on_project_created(&plugin_workspace, &project_dir) {
+                notes.push((plugin.key().to_string(), note));
+            }
+        }
+        Ok(notes)
+    }
+
+    /// Point out the allowed dependency cycles among `projects`, which run in no particular
+    /// order relative to each other
+    fn warn_allowed_cycles(&self, projects: &[String]) {
+        for cycle in &self.workspace.allowed_cycles {
+            if cycle.iter().any(|project| projects.contains(project)) {
+                eprintln!(
+                    "Warning: dependency cycle {} is allowed by allowedCycles; its projects don't wait for each other",
+                    cycle.join(" -> ")
+                );
+            }
+        }
+    }
+
+    /// The plugins to run tasks with, if any task of `task_map` names one. Plugins are
+    /// loaded now if discovery didn't need them.
+    async fn task_plugins(
+        &self,
+        task_map: &HashMap<String, TaskConfig>,
+    ) -> MartyResult<TaskPlugins> {
+        if !task_map.values().any(|task| task.plugin.is_some()) {
+            return Ok(TaskPlugins::default());
+        }
+        if let Some(plugins) = &self.task_plugins {
+            return Ok(plugins.clone());
+        }
+        let _resolving = self.timings.start(Phase::PluginResolution);
+        let providers = Self::load_workspace_providers(
+            &self.workspace.root,
+            &self.workspace_config,
+            &self.plugin_selection,
+            &self.embedded_plugins,
+        )
+        .await?;
+        Ok(TaskPlugins::new(providers))
+    }
+
+    /// Shell for string commands, from workspace.yml or the platform default
+    pub fn shell(&self) -> Shell {
+        self.workspace_config.shell.unwrap_or_default()
+    }
+
+    /// Where task output is saved, if workspace.yml enables it
+    fn task_logs(&self) -> Option<TaskLogs> {
+        let config = self.workspace_config.logs.as_ref()?;
+        config.enabled.unwrap_or(false).then(|| {
+            TaskLogs::new(
+                &self.workspace.root,
+                config.keep.unwrap_or(task_logs::DEFAULT_KEEP),
+            )
+        })
+    }
+
+    /// Hooks run after every task run, built from the `notifications` configuration
+    fn run_hooks(&self) -> Vec<Box<dyn RunHook>> {
+        self.workspace_config
+            .notifications
+            .iter()
+            .flatten()
+            .map(|config| {
+                Box::new(WebhookNotifier::new(
+                    config.clone(),
+                    self.workspace_config.name.clone(),
+                )) as Box<dyn RunHook>
+            })
+            .collect()
+    }
+
+    /// Get the projects affected by changes since the last fully successful run of a task
+    /// on the current branch, including their dependents. Runs are recorded by commit, so
+    /// this always asks git, whatever the change detection.
+    ///
+    /// Returns `None` when no successful run has been recorded yet, meaning every project
+    /// should be considered affected.
+    pub fn projects_changed_since_last_success(
+        &self,
+        task_name: &str,
+    ) -> MartyResult<Option<HashSet<String>>> {
+        let root = &self.workspace.root;
+        let state = WorkspaceState::load(root)?;
+        let branch = git::current_branch(root)?;
+
+        let Some(last_green) = state.last_success(task_name, &branch) else {
+            return Ok(None);
+        };
+
+        let changed_files = git::changed_files_since(root, last_green)?;
+        Ok(Some(self.projects_affected_by(&changed_files)?))
+    }
+
+    /// Get the projects affected by changes between `base` and the working tree, as found
+    /// by the change detector: the projects owning a changed file, plus every project
+    /// depending on them. Without a base, the detector's default is used.
+    pub fn affected_projects(&self, base: Option<&str>) -> MartyResult<HashSet<String>> {
+        let changed_files = self.change_detector.changed_files(&self.workspace, base)?;
+        self.projects_affected_by(&changed_files)
+    }
+
+    /// Record the hashes of the workspace files as snapshot `name`, for `snapshot` change
+    /// detection. Returns the number of files recorded.
+    pub fn save_snapshot(&self, name: &str) -> MartyResult<usize> {
+        let excludes = self.workspace_config.excludes.clone().unwrap_or_default();
+        SnapshotChangeDetector::new(excludes).save(&self.workspace, name)
+    }
+
+    /// The projects owning any of `changed_files`, plus every project depending on them
+    fn projects_affected_by(&self, changed_files: &[PathBuf]) -> MartyResult<HashSet<String>> {
+        let mut changed_projects: Vec<String> = changed_files
+            .iter()
+            .filter_map(|file| find_owning_project(&self.workspace, file))
+            .map(|project| project.name.clone())
+            .collect();
+        changed_projects.sort();
+        changed_projects.dedup();
+
+        let affected = get_reverse_dependencies(&self.workspace, &changed_projects)
+            .map_err(MartyError::Workspace)?;
+        Ok(affected.into_iter().collect())
+    }
+
+    /// Get `project` and every project depending on it, if any file owned by `project`
+    /// changed between `base` and the working tree, as found by the change detector.
+    /// Without a base, the detector's default is used, which for git and Mercurial means
+    /// uncommitted changes. Returns an empty set when the project is unchanged.
+    pub fn dependents_if_changed(
+        &self,
+        project: &str,
+        base: Option<&str>,
+    ) -> MartyResult<HashSet<String>> {
+        if self.workspace.project(project).is_none() {
+            return Err(MartyError::Project(format!(
+                "Project '{}' not found in workspace",
+                project
+            )));
+        }
+
+        let changed_files = self.change_detector.changed_files(&self.workspace, base)?;
+        let changed = changed_files.iter().any(|file| {
+            find_owning_project(&self.workspace, file).is_some_and(|owner| owner.name == project)
+        });
+        if !changed {
+            return Ok(HashSet::new());
+        }
+
+        let affected = get_reverse_dependencies(&self.workspace, &[project.to_string()])
+            .map_err(MartyError::Workspace)?;
+        Ok(affected.into_iter().collect())
+    }
+
+    /// Record the current commit as the last fully successful run of a task.
+    ///
+    /// This is best effort: outside of a git repository there is nothing to record.
+    fn record_successful_run(&self, task_name: &str) {
+        let root = &self.workspace.root;
+        let (Ok(branch), Ok(sha)) = (git::current_branch(root), git::head_sha(root)) else {
+            return;
+        };
+
+        let result = WorkspaceState::load(root).and_then(|mut state| {
+            state.record_success(task_name, &branch, &sha);
+            state.save(root)
+        });
+
+        if let Err(e) = result {
+            eprintln!("Warning: Failed to record run history: {}", e);
+        }
+    }
+
+    /// Get the task cache for the current environment, if caching is enabled
+    pub fn task_cache(&self) -> MartyResult<Option<TaskCache>> {
+        let Some(cache_config) = &self.workspace_config.cache else {
+            return Ok(None);
+        };
+        if !cache_config.enabled.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let namespace = self.cache_namespace()?;
+        let mut cache = TaskCache::new(&self.workspace.root, namespace).with_shell(self.shell());
+        if let Some(config) = cache_config
+            .remote
+            .as_ref()
+            .filter(|r| r.enabled.unwrap_or(true))
+        {
+            match remote_cache::from_config(config) {
+                Ok(remote) => {
+                    if config.upload.unwrap_or(true) {
+                        cache = cache.with_uploads(UploadQueue::new(remote.clone(), config));
+                    }
+                    cache = cache.with_remote(remote);
+                }
+                Err(e) => eprintln!("Warning: Remote cache disabled: {}", e),
+            }
+        }
+        Ok(Some(cache))
+    }
+
+    /// Compute the execution plan of `target` for another version of the workspace: a git
+    /// ref (checked out into a temporary worktree) and/or an alternative workspace.yml.
+    ///
+    /// Plugins are resolved from this workspace, so local plugin builds and the plugin
+    /// cache are reused. Cache hits are predicted against this workspace's local cache.
+    pub async fn plan_snapshot(&self, target: &str, side: &PlanSide) -> MartyResult<PlanSnapshot> {
+        let worktree = side
+            .git_ref
+            .as_deref()
+            .map(|git_ref| git::Worktree::add(&self.workspace.root, git_ref))
+            .transpose()?;
+        let root = match &worktree {
+            Some(worktree) => worktree.workspace_root(&self.workspace.root)?,
+            None => self.workspace.root.clone(),
+        };
+
+        let workspace_config = match &side.config_file {
+            Some(path) => Self::load_workspace_config_file(path)?,
+            None => Self::load_workspace_config(&root)?,
+        };
+        let providers = Self::load_workspace_providers(
+            &self.workspace.root,
+            &workspace_config,
+            &PluginSelection::default(),
+            &self.embedded_plugins,
+        )
+        .await?;
+        let mut workspace = Self::discover_workspace(
+            root.clone(),
+            &providers,
+            None,
+            &ProgressSink::default(),
+            &Timings::default(),
+        )?;
+        Self::allow_cycles(&mut workspace, &workspace_config);
+        let manager = Self {
+            task_configs: Self::load_task_configs(&root, &workspace_config)?,
+            workspace,
+            workspace_config,
+            embedded_plugins: self.embedded_plugins.clone(),
+            plugin_tasks: Self::plugin_tasks(&providers),
+            task_plugins: Some(TaskPlugins::new(providers)),
+            plugin_usage: BTreeMap::new(),
+            plugin_selection: PluginSelection::default(),
+            progress: ProgressSink::default(),
+            timings: Timings::default(),
+            change_detector: self.change_detector.clone(),
+        };
+
+        let plan = manager.get_execution_plan(target)?;
+        let levels = group_by_dependency_levels(&manager.workspace, &plan.compatible_projects)?;
+        let cache_hits = self
+            .prediction_cache(&manager.workspace_config)?
+            .map(|cache| manager.predict_cache_hits(&plan, &cache))
+            .transpose()?;
+
+        Ok(PlanSnapshot {
+            label: side.label(),
+            task: plan.task_name,
+            levels,
+            cache_hits,
+        })
+    }
+
+    /// Whether each project of a plan would be skipped because of a local cache hit
+    fn predict_cache_hits(
+        &self,
+        plan: &TaskExecutionPlan,
+        cache: &TaskCache,
+    ) -> MartyResult<BTreeMap<String, bool>> {
+        let task_configs = self.plan_task_configs(plan)?;
+        let mut hits = BTreeMap::new();
+        for project in &self.workspace.projects {
+            let Some(task_config) = task_configs.get(&project.name) else {
+                continue;
+            };
+            let key = cache.compute_key(
+                &project.name,
+                &project.project_dir,
+                &self.workspace.dependency_dirs(&project.name),
+                task_config,
+            )?;
+            hits.insert(project.name.clone(), cache.contains(&key));
+        }
+        Ok(hits)
+    }
+
+    /// Local cache used to predict hits for a version of the workspace configured by
+    /// `config`, if that configuration enables caching. The namespace is resolved in this
+    /// working tree so that its branch applies to every compared version.
+    fn prediction_cache(&self, config: &WorkspaceConfig) -> MartyResult<Option<TaskCache>> {
+        let Some(cache_config) = config.cache.as_ref().filter(|c| c.enabled.unwrap_or(false))
+        else {
+            return Ok(None);
+        };
+        let shell = config.shell.unwrap_or_default();
+        let namespace = CacheNamespace::resolve(
+            &self.workspace.root,
+            &cache_config.namespace.clone().unwrap_or_default(),
+            shell,
+        )?;
+        Ok(Some(
+            TaskCache::new(&self.workspace.root, namespace).with_shell(shell),
+        ))
+    }
+
+    /// Get statistics about the task cache grouped by namespace
+    pub fn get_cache_stats(&self) -> MartyResult<CacheStatsResult> {
+        Ok(CacheStatsResult {
+            enabled: self
+                .workspace_config
+                .cache
+                .as_ref()
+                .and_then(|c| c.enabled)
+                .unwrap_or(false),
+            current_namespace: self.cache_namespace()?,
+            namespaces: TaskCache::stats(&self.workspace.root)?,
+        })
+    }
+
+    /// Remove all cached task results and the persisted discovery results
+    pub fn clear_cache(&self) -> MartyResult<()> {
+        TaskCache::clear(&self.workspace.root)?;
+        DiscoveryCache::clear(&self.workspace.root)
+    }
+
+    fn cache_namespace(&self) -> MartyResult<CacheNamespace> {
+        let namespace_config = self
+            .workspace_config
+            .cache
+            .as_ref()
+            .and_then(|c| c.namespace.clone())
+            .unwrap_or_default();
+        CacheNamespace::resolve(&self.workspace.root, &namespace_config, self.shell())
+    }
+
+    /// Describe a task: its workspace-level definition and any project overrides
+    pub fn describe_task(&self, task_name: &str) -> MartyResult<TaskDescriptionResult> {
+        let task_map = self.build_task_map()?;
+        let suffix = format!(":{}", task_name);
+
+        let mut project_overrides: Vec<(String, TaskConfig)> = task_map
+            .iter()
+            .filter_map(|(key, task)| {
+                key.strip_suffix(&suffix)
+                    .map(|project| (project.to_string(), task.clone()))
+            })
+            .collect();
+        project_overrides.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let workspace_task = task_map.get(task_name).cloned();
+        if workspace_task.is_none() && project_overrides.is_empty() {
+            return Err(MartyError::Task(format!("Task '{}' not found", task_name)));
+        }
+
+        Ok(TaskDescriptionResult {
+            name: task_name.to_string(),
+            workspace_task,
+            project_overrides,
+        })
+    }
+
+    /// Get dependency graph information
+    pub fn get_dependency_graph(&self) -> MartyResult<DependencyGraphResult> {
+        Ok(DependencyGraphResult {
+            graph: self.workspace.dep_graph.clone(),
+            cycles: self.workspace.dependency_cycles.clone(),
+        })
+    }
+
+    /// Suggest the fewest dependencies to remove to break each dependency cycle
+    pub fn suggest_cycle_breaks(&self) -> Vec<CycleBreakPlan> {
+        suggest_cycle_breaks(&self.workspace)
+    }
+
+    /// Get the dependency graph collapsed into directory groups `depth` levels deep
+    pub fn get_grouped_dependency_graph(
+        &self,
+        depth: usize,
+    ) -> MartyResult<GroupedDependencyGraphResult> {
+        let (groups, edges) = group_dependencies_by_directory(&self.workspace, depth)
+            .map_err(MartyError::Workspace)?;
+        Ok(GroupedDependencyGraphResult { groups, edges })
+    }
+
+    /// Explain how `from` ends up depending on `to`: a shortest chain of dependencies, or
+    /// every chain when `all` is set
+    pub fn dependency_paths(
+        &self,
+        from: &str,
+        to: &str,
+        all: bool,
+    ) -> MartyResult<DependencyPathsResult> {
+        let paths =
+            dependency_paths(&self.workspace, from, to, all).map_err(MartyError::Project)?;
+        let mut edge_sources = BTreeMap::new();
+        for path in &paths {
+            for edge in path.windows(2) {
+                if let Some(source) = dependency_source(&self.workspace, &edge[0], &edge[1]) {
+                    edge_sources.insert((edge[0].clone(), edge[1].clone()), source.to_string());
+                }
+            }
+        }
+        Ok(DependencyPathsResult {
+            paths,
+            edge_sources,
+        })
+    }
+
+    /// Check every project's direct dependencies against its allowed/denied dependency rules
+    pub fn lint_dependencies(&self) -> MartyResult<DependencyLintResult> {
+        let graph = self
+            .workspace
+            .dep_graph
+            .as_ref()
+            .ok_or_else(|| MartyError::Workspace("Dependency graph not built".to_string()))?;
+
+        let project_configs = self.project_configs();
+        let mut checked_projects = 0;
+        let mut violations = Vec::new();
+        for node in graph.node_indices() {
+            let name = &graph[node];
+            let Some(config) = project_configs.get(name)? else {
+                continue;
+            };
+            let Some(rules) = DependencyRules::from_config(config).map_err(|e| {
+                MartyError::Project(format!("Invalid dependency rules for '{}': {}", name, e))
+            })?
+            else {
+                continue;
+            };
+
+            let mut dependencies: Vec<String> = graph
+                .neighbors(node)
+                .map(|dep| graph[dep].clone())
+                .collect();
+            dependencies.sort();
+
+            checked_projects += 1;
+            violations.extend(rules.check(name, &dependencies, |dependency| {
+                self.project_kind(dependency).ok().flatten()
+            }));
+        }
+
+        violations.sort_by(|a, b| (&a.project, &a.dependency).cmp(&(&b.project, &b.dependency)));
+        Ok(DependencyLintResult {
+            checked_projects,
+            violations,
+        })
+    }
+
+    /// Run the checks meant for CI gates: configuration validation, dependency rules, cycle
+    /// detection and a dry plan of each task in `tasks` (every workspace-level task when
+    /// empty). Problems are collected into the result rather than returned as errors.
+    pub fn check(&self, tasks: &[String]) -> MartyResult<WorkspaceCheckResult> {
+        let mut result = WorkspaceCheckResult {
+            checks: Vec::new(),
+            hints: Vec::new(),
+        };
+
+        let problems = self.validate_configs();
+        let summary = Message::new("check.config.valid")
+            .with("tasks", self.task_configs.tasks.len())
+            .with("projects", self.workspace.projects.len());
+        let configs_valid = problems.is_empty();
+        result.record("config", problems, summary);
+
+        match self.lint_dependencies() {
+            Ok(lint) => {
+                let problems = lint
+                    .violations
+                    .iter()
+                    .map(|v| {
+                        Message::new("check.dependency_rules.violation")
+                            .with("project", &v.project)
+                            .with("dependency", &v.dependency)
+                    })
+                    .collect();
+                let summary = Message::new("check.dependency_rules.valid")
+                    .with("projects", lint.checked_projects);
+                result.record("dependency-rules", problems, summary);
+            }
+            Err(e) => result.fail("dependency-rules", &e),
+        }
+
+        let problems = self
+            .workspace
+            .dependency_cycles
+            .iter()
+            .map(|cycle| Message::new("check.cycles.cycle").with("cycle", cycle.join(" -> ")))
+            .collect();
+        result.record("cycles", problems, Message::new("check.cycles.valid"));
+        for plan in suggest_cycle_breaks(&self.workspace) {
+            result.hints.extend(plan.breaks.iter().map(|cut| {
+                Message::new("check.hint.cycle_break")
+                    .with("project", &cut.project)
+                    .with("dependency", &cut.dependency)
+                    .with("source", cut.source.as_deref().unwrap_or("unknown"))
+                    .with("count", cut.cycles)
+                    .with("total", plan.cycles)
+            }));
+        }
+        result
+            .hints
+            .extend(self.workspace.allowed_cycles.iter().map(|cycle| {
+                Message::new("check.hint.allowed_cycle").with("cycle", cycle.join(" -> "))
+            }));
+        result.hints.extend(
+            self.workspace_config
+                .allowed_cycles
+                .iter()
+                .flatten()
+                .filter(|entry| {
+                    !self
+                        .workspace
+                        .allowed_cycles
+                        .iter()
+                        .any(|cycle| same_cycle(cycle, entry))
+                })
+                .map(|entry| {
+                    Message::new("check.hint.stale_allowed_cycle").with("cycle", entry.join(", "))
+                }),
+        );
+
+        let tasks = if tasks.is_empty() {
+            let mut names: Vec<String> = self
+                .task_configs
+                .tasks
+                .iter()
+                .map(|t| t.name.clone())
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        } else {
+            tasks.to_vec()
+        };
+        // Planning needs every project config, which the config check reported on already
+        if configs_valid {
+            for task in &tasks {
+                let name = format!("plan:{}", task);
+                match self.plan_levels(task) {
+                    Ok(levels) => {
+                        let summary = Message::new("check.plan.valid")
+                            .with("projects", levels.iter().map(Vec::len).sum::<usize>())
+                            .with("levels", levels.len());
+                        result.record(&name, Vec::new(), summary);
+                    }
+                    Err(e) => result.fail(&name, &e),
+                }
+            }
+        }
+
+        self.record_plugin_usage();
+        match WorkspaceState::load(&self.workspace.root) {
+            Ok(state) => result
+                .hints
+                .extend(state.unused_plugins().into_iter().map(|key| {
+                    Message::new("check.hint.unused_plugin")
+                        .with("plugin", key)
+                        .with("runs", UNUSED_PLUGIN_RUNS)
+                })),
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+        result.hints.extend(
+            self.workspace_config
+                .plugins
+                .iter()
+                .flatten()
+                .filter(|config| !config.enabled.unwrap_or(true))
+                .map(|config| {
+                    Message::new("check.hint.disabled_plugin")
+                        .with("plugin", config.source_description())
+                }),
+        );
+
+        Ok(result)
+    }
+
+    /// Problems in project configs and task definitions
+    fn validate_configs(&self) -> Vec<Message> {
+        let project_configs = self.project_configs();
+        let mut problems = Vec::new();
+        for project in &self.workspace.projects {
+            if let Err(e) = project_configs.get(&project.name) {
+                problems.push(Message::new("check.error").with("error", e));
+            }
+        }
+        if !problems.is_empty() {
+            return problems;
+        }
+
+        let task_map = match self.task_map(&project_configs) {
+            Ok(task_map) => task_map,
+            Err(e) => return vec![Message::new("check.error").with("error", e)],
+        };
+        let mut keys: Vec<&String> = task_map.keys().collect();
+        keys.sort();
+        for key in keys {
+            let task = &task_map[key];
+            match (&task.command, &task.script) {
+                (None, None) if task.plugin.is_none() => {
+                    problems.push(Message::new("check.config.no_command").with("task", key))
+                }
+                (_, Some(script)) if !self.workspace.root.join(script).exists() => problems.push(
+                    Message::new("check.config.missing_script")
+                        .with("script", script)
+                        .with("task", key),
+                ),
+                _ => {}
+            }
+            for dependency in task.dependencies.iter().flatten() {
+                let known = match TaskDependency::parse(dependency) {
+                    TaskDependency::Task(name) => task_map.contains_key(name),
+                    TaskDependency::Dependencies(name) => {
+                        let suffix = format!(":{}", name);
+                        task_map
+                            .keys()
+                            .any(|key| key == name || key.ends_with(&suffix))
+                    }
+                    TaskDependency::Project { project, task } => {
+                        if self.workspace.project(project).is_none() {
+                            problems.push(
+                                Message::new("check.config.unknown_project")
+                                    .with("task", key)
+                                    .with("project", project),
+                            );
+                            continue;
+                        }
+                        task_map.contains_key(dependency) || task_map.contains_key(task)
+                    }
+                };
+                if !known {
+                    problems.push(
+                        Message::new("check.config.unknown_task")
+                            .with("task", key)
+                            .with("dependency", dependency),
+                    );
+                }
+            }
+        }
+        problems
+    }
+
+    /// Projects a run of `target` would execute, grouped by dependency level
+    fn plan_levels(&self, target: &str) -> MartyResult<Vec<Vec<String>>> {
+        let suffix = format!(":{}", target);
+        if !self
+            .build_task_map()?
+            .keys()
+            .any(|key| key == target || key.ends_with(&suffix))
+        {
+            return Err(MartyError::Task(format!("Task '{}' not found", target)));
+        }
+
+        let plan = self.get_execution_plan(target)?;
+        let projects = get_recursive_dependencies(&self.workspace, &plan.compatible_projects)
+            .map_err(MartyError::Task)?;
+        group_by_dependency_levels(&self.workspace, &projects)
     }
 
-    /// Get execution plan for a task
-    pub fn get_execution_plan(&self, target: &str) -> MartyResult<TaskExecutionPlan> {
-        let (project_filter, task_name) = Self::parse_target(target)?;
-        resolve_task_execution_plan(
-            &self.workspace,
-            &self.task_configs,
-            &task_name,
-            project_filter.as_deref(),
-        )
+    /// One record per project with its configuration, dependencies and how its tasks last
+    /// ended according to `.marty/state.json`, for `marty export metadata`
+    pub fn project_metadata(&self) -> MartyResult<Vec<ProjectMetadata>> {
+        let project_configs = self.project_configs();
+        let state = WorkspaceState::load(&self.workspace.root)?;
+        self.workspace
+            .projects
+            .iter()
+            .map(|project| {
+                let config = project_configs.get(&project.name)?;
+                let tasks = state
+                    .last_results
+                    .iter()
+                    .filter_map(|(task, results)| {
+                        Some(ProjectTaskStats {
+                            task: task.clone(),
+                            last_status: *results.get(&project.name)?,
+                            resources: state
+                                .resource_usage
+                                .get(task)
+                                .and_then(|usage| usage.get(&project.name))
+                                .copied(),
+                        })
+                    })
+                    .collect();
+                Ok(ProjectMetadata {
+                    workspace: self.workspace_config.name.clone(),
+                    name: project.name.clone(),
+                    path: project
+                        .project_dir
+                        .strip_prefix(&self.workspace.root)
+                        .unwrap_or(&project.project_dir)
+                        .to_path_buf(),
+                    kind: config
+                        .and_then(|c| c.kind)
+                        .or_else(|| self.workspace.inferred_kind(&project.name)),
+                    description: config.and_then(|c| c.description.clone()),
+                    tags: config.and_then(|c| c.tags.clone()).unwrap_or_default(),
+                    owners: config.and_then(|c| c.owners.clone()).unwrap_or_default(),
+                    plugin: self
+                        .workspace
+                        .inferred_project(&project.name)
+                        .map(|p| p.discovered_by.clone()),
+                    dependencies: self
+                        .workspace
+                        .direct_dependencies(&project.name)
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                    tasks,
+                })
+            })
+            .collect()
     }
 
-    /// Execute a task on the workspace
-    pub async fn run_task(&self, target: &str) -> MartyResult<()> {
-        let execution_plan = self.get_execution_plan(target)?;
+    /// Map every workspace file to its owning project (the one with the nearest project
+    /// directory, so files in nested projects belong to the nested project)
+    pub fn get_file_ownership(&self) -> MartyResult<FileOwnershipResult> {
+        let excludes = self.workspace_config.excludes.clone().unwrap_or_default();
+        let files = list_workspace_files(&self.workspace, &excludes)
+            .into_iter()
+            .map(|file| {
+                let owner = find_owning_project(&self.workspace, &file).map(|p| p.name.clone());
+                (file, owner)
+            })
+            .collect();
+
+        Ok(FileOwnershipResult { files })
+    }
 
-        if execution_plan.compatible_projects.is_empty() {
-            return Err(MartyError::Task(format!(
-                "No compatible projects found for task '{}'",
-                execution_plan.task_name
+    /// List the workspace-relative paths of the files owned by a project
+    pub fn get_project_files(&self, project_name: &str) -> MartyResult<Vec<PathBuf>> {
+        if !self
+            .workspace
+            .projects
+            .iter()
+            .any(|p| p.name == project_name)
+        {
+            return Err(MartyError::Project(format!(
+                "Project '{}' not found",
+                project_name
             )));
         }
 
-        let task_map = self.build_task_map()?;
+        Ok(self
+            .get_file_ownership()?
+            .files
+            .into_iter()
+            .filter(|(_, owner)| owner.as_deref() == Some(project_name))
+            .map(|(file, _)| file)
+            .collect())
+    }
 
-        run_task_on_targets(
-            &execution_plan.task_name,
-            &execution_plan.compatible_projects,
-            &self.workspace,
-            &task_map,
-        )
-        .await?;
+    /// Compute the minimal set of directories needed to work on `projects`: their directories,
+    /// the directories of their transitive dependencies and the workspace configuration.
+    ///
+    /// Directories nested inside another selected directory are omitted.
+    pub fn get_sparse_checkout_dirs(&self, projects: &[String]) -> MartyResult<Vec<PathBuf>> {
+        let required =
+            get_recursive_dependencies(&self.workspace, projects).map_err(MartyError::Project)?;
+
+        let mut dirs = vec![self.workspace.root.join(".marty")];
+        for name in &required {
+            let project = self
+                .workspace
+                .projects
+                .iter()
+                .find(|p| &p.name == name)
+                .ok_or_else(|| MartyError::Project(format!("Project '{}' not found", name)))?;
+
+            if project.project_dir == self.workspace.root {
+                return Err(MartyError::Project(format!(
+                    "Project '{}' is located at the workspace root, so a sparse checkout would include everything",
+                    name
+                )));
+            }
+            dirs.push(project.project_dir.clone());
+        }
 
-        Ok(())
+        dirs.sort();
+        dirs.dedup();
+        let mut minimal: Vec<PathBuf> = Vec::new();
+        for dir in dirs {
+            if !minimal.iter().any(|parent| dir.starts_with(parent)) {
+                minimal.push(dir);
+            }
+        }
+
+        Ok(minimal)
     }
 
-    /// Get dependency graph information
-    pub fn get_dependency_graph(&self) -> MartyResult<DependencyGraphResult> {
-        Ok(DependencyGraphResult {
-            graph: self.workspace.dep_graph.clone(),
-            cycles: self.workspace.dependency_cycles.clone(),
-        })
+    /// Configure git sparse checkout so only the directories needed for `projects` are materialized
+    pub fn apply_sparse_checkout(&self, projects: &[String]) -> MartyResult<Vec<PathBuf>> {
+        let dirs = self.get_sparse_checkout_dirs(projects)?;
+        git::set_sparse_checkout(&self.workspace.root, &dirs)?;
+        Ok(dirs)
     }
 
     // Private helper methods
 
     fn load_workspace_config(workspace_root: &Path) -> MartyResult<WorkspaceConfig> {
-        let workspace_config_path = workspace_root.join(".marty").join("workspace.yml");
-        let content = std::fs::read_to_string(&workspace_config_path).map_err(|e| {
+        Self::load_workspace_config_file(&workspace_root.join(".marty").join("workspace.yml"))
+    }
+
+    fn load_workspace_config_file(workspace_config_path: &Path) -> MartyResult<WorkspaceConfig> {
+        let content = std::fs::read_to_string(workspace_config_path).map_err(|e| {
             MartyError::Task(format!(
                 "Failed to read workspace config {}: {}",
                 workspace_config_path.display(),
@@ -189,7 +1720,12 @@ impl WorkspaceManager {
         })
     }
 
-    fn load_task_configs(workspace_root: &Path) -> MartyResult<TasksFileConfig> {
+    /// Load and merge the task files in `.marty/tasks/` with the tasks defined inline in
+    /// workspace.yml. A task may only be defined in one of the two places.
+    fn load_task_configs(
+        workspace_root: &Path,
+        workspace_config: &WorkspaceConfig,
+    ) -> MartyResult<TasksFileConfig> {
         let tasks_dir = workspace_root.join(".marty").join("tasks");
         let mut task_configs = Vec::new();
 
@@ -220,19 +1756,44 @@ impl WorkspaceManager {
                             e
                         ))
                     })?;
-                    task_configs.push(config);
+                    task_configs.push((path, config));
                 }
             }
         }
 
+        let inline_tasks = workspace_config.tasks.as_deref().unwrap_or_default();
+        let mut inline_names = HashSet::new();
+        for task in inline_tasks {
+            if !inline_names.insert(task.name.as_str()) {
+                return Err(MartyError::Config(format!(
+                    "Task '{}' is defined more than once in workspace.yml",
+                    task.name
+                )));
+            }
+        }
+        for (path, config) in &task_configs {
+            if let Some(task) = config
+                .tasks
+                .iter()
+                .find(|task| inline_names.contains(task.name.as_str()))
+            {
+                return Err(MartyError::Config(format!(
+                    "Task '{}' is defined both in workspace.yml and in {}",
+                    task.name,
+                    path.display()
+                )));
+            }
+        }
+
         // Merge all task configs
         let mut all_tasks = Vec::new();
         let mut name = None;
         let mut description = None;
         let mut targets = None;
         let mut all_tags = Vec::new();
+        let mut all_kinds = Vec::new();
 
-        for config in &task_configs {
+        for (_, config) in &task_configs {
             all_tasks.extend(config.tasks.clone());
             if config.name.is_some() {
                 name = config.name.clone();
@@ -246,11 +1807,16 @@ impl WorkspaceManager {
             if let Some(tags) = &config.tags {
                 all_tags.extend(tags.clone());
             }
+            all_kinds.extend(config.kinds.iter().flatten().copied());
         }
 
+        all_tasks.extend(inline_tasks.iter().cloned());
+
         // Remove duplicate tags
         all_tags.sort();
         all_tags.dedup();
+        all_kinds.sort();
+        all_kinds.dedup();
 
         Ok(TasksFileConfig {
             name,
@@ -262,16 +1828,250 @@ impl WorkspaceManager {
             } else {
                 Some(all_tags)
             },
+            kinds: if all_kinds.is_empty() {
+                None
+            } else {
+                Some(all_kinds)
+            },
+            // Each file's variables and error policy were already applied to its own tasks
+            env: None,
+            on_error: None,
         })
     }
 
     async fn initialize_workspace(
         workspace_root: PathBuf,
         workspace_config: &WorkspaceConfig,
-    ) -> MartyResult<Workspace> {
+        selection: &PluginSelection,
+        embedded_plugins: &[Arc<dyn MartyPlugin>],
+        progress: &ProgressSink,
+        timings: &Timings,
+    ) -> MartyResult<(
+        Workspace,
+        BTreeMap<String, Vec<SuggestedTask>>,
+        Option<TaskPlugins>,
+    )> {
+        let started = Instant::now();
+        progress.emit(ProgressEvent::DiscoveryStarted {
+            root: workspace_root.clone(),
+        });
+
+        let discovery_cache = workspace_config
+            .discovery
+            .as_ref()
+            .and_then(|d| d.cache)
+            .unwrap_or(false)
+            .then(|| {
+                let key = Self::discovery_key(
+                    &workspace_root,
+                    workspace_config,
+                    selection,
+                    embedded_plugins,
+                )?;
+                Ok::<_, MartyError>(DiscoveryCache::new(&workspace_root, key))
+            })
+            .transpose()?;
+        let cached = timings.time(Phase::Traversal, || {
+            discovery_cache
+                .as_ref()
+                .and_then(|cache| cache.load(&workspace_root))
+        });
+        if let Some((workspace, plugin_tasks)) = cached {
+            Self::report_discovered(&workspace, true, started, progress);
+            return Ok((workspace, plugin_tasks, None));
+        }
+
         // Load workspace providers
-        let providers = Self::load_workspace_providers(&workspace_root, workspace_config).await?;
+        let resolving = timings.start(Phase::PluginResolution);
+        let providers = Self::load_workspace_providers(
+            &workspace_root,
+            workspace_config,
+            selection,
+            embedded_plugins,
+        )
+        .await?;
+        drop(resolving);
+        let plugin_tasks = Self::plugin_tasks(&providers);
+
+        let workspace = match discovery_cache {
+            Some(cache) => {
+                let mut stamps = ScanStamps::default();
+                Self::record_plugin_stamps(&workspace_root, workspace_config, &mut stamps);
+                let workspace = Self::discover_workspace(
+                    workspace_root,
+                    &providers,
+                    Some(&mut stamps),
+                    progress,
+                    timings,
+                )?;
+                if let Err(e) = cache.store(&workspace, &plugin_tasks, stamps) {
+                    eprintln!("Warning: failed to write discovery cache: {}", e);
+                }
+                workspace
+            }
+            None => Self::discover_workspace(workspace_root, &providers, None, progress, timings)?,
+        };
+        Self::report_discovered(&workspace, false, started, progress);
+        Ok((workspace, plugin_tasks, Some(TaskPlugins::new(providers))))
+    }
+
+    /// The default tasks of every plugin that defines any, by plugin key
+    fn plugin_tasks(providers: &[Box<dyn MartyPlugin>]) -> BTreeMap<String, Vec<SuggestedTask>> {
+        providers
+            .iter()
+            .map(|plugin| (plugin.key().to_string(), plugin.default_tasks()))
+            .filter(|(_, tasks)| !tasks.is_empty())
+            .collect()
+    }
+
+    /// Count the projects each plugin discovered, so `marty check` can point out plugins
+    /// that keep finding nothing
+    fn plugin_usage(
+        workspace: &Workspace,
+        plugins: Option<&TaskPlugins>,
+    ) -> BTreeMap<String, usize> {
+        plugins
+            .into_iter()
+            .flat_map(TaskPlugins::keys)
+            .map(|key| {
+                let count = workspace
+                    .inferred_projects
+                    .iter()
+                    .filter(|project| project.discovered_by == key)
+                    .count();
+                (key.to_string(), count)
+            })
+            .collect()
+    }
+
+    /// Add the projects each plugin discovered to the run history. Best effort, like the
+    /// rest of the run history; only commands that write it anyway call this.
+    fn record_plugin_usage(&self) {
+        if self.plugin_usage.is_empty() {
+            return;
+        }
+        let root = &self.workspace.root;
+        let result = WorkspaceState::load(root).and_then(|mut state| {
+            state.record_discovery(&self.plugin_usage);
+            state.save(root)
+        });
+        if let Err(e) = result {
+            eprintln!("Warning: Failed to record plugin usage: {}", e);
+        }
+    }
+
+    /// Drop the projects outside a view, after discovery so the discovery cache is shared
+    /// by every view
+    fn apply_view(
+        workspace: &mut Workspace,
+        workspace_config: &WorkspaceConfig,
+        view: &str,
+    ) -> MartyResult<()> {
+        let views = workspace_config.views.clone().unwrap_or_default();
+        let Some(patterns) = views.get(view) else {
+            let known: Vec<&str> = views.keys().map(String::as_str).collect();
+            return Err(MartyError::Config(if known.is_empty() {
+                format!("Unknown view '{}': workspace.yml defines no views", view)
+            } else {
+                format!(
+                    "Unknown view '{}' (workspace.yml defines: {})",
+                    view,
+                    known.join(", ")
+                )
+            }));
+        };
+        retain_view(workspace, patterns)
+            .map_err(|e| MartyError::Config(format!("Invalid view '{}': {}", view, e)))
+    }
+
+    /// Accept the dependency cycles listed in `allowedCycles`
+    fn allow_cycles(workspace: &mut Workspace, workspace_config: &WorkspaceConfig) {
+        if let Some(allowed) = &workspace_config.allowed_cycles {
+            workspace.allow_cycles(allowed);
+        }
+    }
+
+    /// Report every project of a freshly loaded workspace, then the end of discovery
+    fn report_discovered(
+        workspace: &Workspace,
+        cached: bool,
+        started: Instant,
+        progress: &ProgressSink,
+    ) {
+        if !progress.is_enabled() {
+            return;
+        }
+        for project in &workspace.projects {
+            progress.emit(ProgressEvent::ProjectDiscovered {
+                name: project.name.clone(),
+                path: project.project_dir.clone(),
+                discovered_by: workspace
+                    .inferred_project(&project.name)
+                    .map(|inferred| inferred.discovered_by.clone()),
+            });
+        }
+        progress.emit(ProgressEvent::DiscoveryFinished {
+            projects: workspace.projects.len(),
+            cached,
+            duration: started.elapsed(),
+        });
+    }
+
+    /// Identifies everything besides the scanned files that discovery results depend on
+    fn discovery_key(
+        workspace_root: &Path,
+        workspace_config: &WorkspaceConfig,
+        selection: &PluginSelection,
+        embedded_plugins: &[Arc<dyn MartyPlugin>],
+    ) -> MartyResult<String> {
+        let config = serde_json::to_string(workspace_config).map_err(|e| {
+            MartyError::Config(format!("Failed to serialize workspace config: {}", e))
+        })?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(workspace_root.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(config.as_bytes());
+        hasher.update([0]);
+        hasher.update(format!("{:?}", selection).as_bytes());
+        for plugin in embedded_plugins {
+            hasher.update([0]);
+            hasher.update(plugin.key().as_bytes());
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Track the plugin binaries that are not identified by workspace.yml alone
+    fn record_plugin_stamps(
+        workspace_root: &Path,
+        workspace_config: &WorkspaceConfig,
+        stamps: &mut ScanStamps,
+    ) {
+        for plugin in workspace_config.plugins.iter().flatten() {
+            if let Some(path) = &plugin.path {
+                stamps.record(&workspace_root.join(path));
+            }
+        }
+
+        let plugins_dir = workspace_root.join(".marty").join("plugins");
+        stamps.record(&plugins_dir);
+        for entry in std::fs::read_dir(&plugins_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            stamps.record(&entry.path());
+        }
+    }
 
+    fn discover_workspace(
+        workspace_root: PathBuf,
+        providers: &[Box<dyn MartyPlugin>],
+        mut stamps: Option<&mut ScanStamps>,
+        progress: &ProgressSink,
+        timings: &Timings,
+    ) -> MartyResult<Workspace> {
+        let traversal = timings.start(Phase::Traversal);
         // Initialize workspace
         let mut workspace = Workspace {
             root: workspace_root,
@@ -279,23 +2079,82 @@ impl WorkspaceManager {
             inferred_projects: Vec::new(),
             dep_graph: None,
             dependency_cycles: Vec::new(),
+            allowed_cycles: Vec::new(),
+            index: ProjectIndex::default(),
         };
 
         // Discover projects using plugins
-        for plugin in &providers {
-            traverse_workspace(plugin.workspace_provider(), &mut workspace);
+        // Marty's own state changes on every run and never affects discovery
+        let marty_dir = workspace.root.join(".marty");
+        let mut diagnostics = Vec::new();
+        for plugin in providers {
+            let errors =
+                traverse_workspace_with(plugin.workspace_provider(), &mut workspace, &mut |path| {
+                    if let Some(stamps) = stamps.as_deref_mut() {
+                        if !path.starts_with(&marty_dir) {
+                            stamps.record(path);
+                        }
+                    }
+                    if progress.is_enabled() && path.is_file() {
+                        progress.emit(ProgressEvent::PluginMatchedFile {
+                            plugin: plugin.key().to_string(),
+                            path: path.to_path_buf(),
+                        });
+                    }
+                });
+            diagnostics.extend(errors.into_iter().map(|error| (plugin.key(), error)));
+        }
+
+        // Let plugins reason about the complete scan, in load order
+        for plugin in providers {
+            let plugin_workspace = marty_plugin_protocol::Workspace::from(&workspace);
+            let mutations = plugin
+                .workspace_provider()
+                .on_scan_complete(&plugin_workspace);
+            apply_workspace_mutations(&mut workspace, plugin.key(), mutations);
+        }
+
+        // Discovery is settled; plugins may now sync tool configuration with it
+        let plugin_workspace = marty_plugin_protocol::Workspace::from(&workspace);
+        for plugin in providers {
+            for note in plugin.post_discovery(&plugin_workspace) {
+                eprintln!("{}: {}", plugin.key(), note);
+            }
+        }
+
+        for plugin in providers {
+            let taken = plugin.workspace_provider().take_diagnostics();
+            diagnostics.extend(
+                taken
+                    .into_iter()
+                    .map(|diagnostic| (plugin.key(), diagnostic)),
+            );
         }
 
+        drop(traversal);
+
         // Build dependency graph
-        build_dependency_graph(&mut workspace)
+        timings
+            .time(Phase::GraphBuilding, || {
+                build_dependency_graph(&mut workspace)
+            })
             .map_err(|e| MartyError::Task(format!("Failed to build dependency graph: {}", e)))?;
 
+        // Projects can only be told apart by directory once the graph is built
+        for (plugin, diagnostic) in diagnostics {
+            let diagnostic = PluginDiagnostic::new(&workspace, plugin, diagnostic);
+            eprintln!("{}", diagnostic);
+            progress.emit(ProgressEvent::PluginDiagnostic(diagnostic));
+        }
+
         Ok(workspace)
     }
 
     async fn load_workspace_providers(
         workspace_root: &Path,
         workspace_config: &WorkspaceConfig,
+        selection: &PluginSelection,
+        embedded_plugins: &[Arc<dyn MartyPlugin>],
     ) -> MartyResult<Vec<Box<dyn MartyPlugin>>> {
         let workspace_includes = workspace_config
             .includes
@@ -309,32 +2168,104 @@ impl WorkspaceManager {
             .unwrap_or_default();
 
         // Create plugin cache and resolve plugins
-        let plugin_cache = PluginCache::new(workspace_root);
+        let bundle = Bundle::locate().map_err(|e| MartyError::Config(format!("{:#}", e)))?;
+        let plugin_cache = PluginCache::new(workspace_root)
+            .with_signing(workspace_config.plugin_signing.clone().unwrap_or_default())
+            .with_bundle(bundle)
+            .with_limits(workspace_config.plugin_cache.clone().unwrap_or_default());
 
-        let plugin_configs = workspace_config
+        let mut plugin_configs = workspace_config
             .plugins
             .as_ref()
             .cloned()
             .unwrap_or_default();
+        // Entries turned on for this run only; each is dropped again once loaded unless its
+        // key or name was asked for
+        let mut enabled_for_run = HashSet::new();
+        for (position, config) in plugin_configs.iter_mut().enumerate() {
+            if !config.enabled.unwrap_or(true) && selection.may_enable(config) {
+                config.enabled = Some(true);
+                enabled_for_run.insert(position);
+            }
+        }
 
-        let cached_plugins = plugin_cache.resolve_plugins(&plugin_configs).await?;
+        let mut cached_plugins = plugin_cache
+            .resolve_plugins(&plugin_configs)
+            .await?
+            .into_iter();
 
-        // Load dynamic library providers from cached plugins
+        // Load providers from cached plugins (dynamic libraries or WASM components), and
+        // compiled-in plugins.
+        // Providers run discovery in this order: configured plugins in workspace.yml order,
+        // then any extra plugins in .marty/plugins sorted by file name, then plugins
+        // registered in-process.
         let mut providers = Vec::new();
         let mut loaded_plugin_names = std::collections::HashSet::new();
+        let mut conflicts = PluginConflictChecker::default();
+        let mut available_plugins = HashSet::new();
 
-        for cached_plugin in cached_plugins {
-            loaded_plugin_names.insert(cached_plugin.name.clone());
-            match DylibWorkspaceProvider::from_dylib_with_temp_copy(cached_plugin.path.clone()) {
+        for (position, config) in plugin_configs
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.enabled.unwrap_or(true))
+        {
+            let enabled_for_run = enabled_for_run.contains(&position);
+            if builtin_plugins::is_builtin(config) {
+                let plugin = builtin_plugins::resolve(config)?;
+                if enabled_for_run && !selection.enables(&[plugin.key(), plugin.name()]) {
+                    continue;
+                }
+                loaded_plugin_names.insert(plugin.name().to_string());
+                available_plugins.insert(plugin.key().to_string());
+                if !selection.allows(&[plugin.key()]) {
+                    continue;
+                }
+                conflicts.register_key(plugin.key(), &config.source_description())?;
+                providers.push(Box::new(ConfigurableWorkspaceProvider::new(
+                    Arc::from(plugin),
+                    workspace_root,
+                    config.options.as_ref(),
+                    workspace_includes.clone(),
+                    workspace_excludes.clone(),
+                )) as Box<dyn MartyPlugin>);
+                continue;
+            }
+            // Every other enabled entry resolved to one cached plugin, in order
+            let Some(cached_plugin) = cached_plugins.next() else {
+                break;
+            };
+            let sandbox = WasmSandbox {
+                writable: config.writable.unwrap_or(false),
+                ..WasmSandbox::default()
+            };
+            match plugin_runtime::load_plugin_with(&cached_plugin.path, workspace_root, &sandbox) {
                 Ok(provider) => {
+                    if enabled_for_run && !selection.enables(&[&cached_plugin.name, provider.key()])
+                    {
+                        continue;
+                    }
+                    loaded_plugin_names.insert(cached_plugin.name.clone());
+                    available_plugins.insert(cached_plugin.name.clone());
+                    available_plugins.insert(provider.key().to_string());
+                    if !selection.allows(&[&cached_plugin.name, provider.key()]) {
+                        continue;
+                    }
+                    if let Ok(digest) = file_digest(&cached_plugin.path) {
+                        conflicts.register_binary(&digest, &cached_plugin.source)?;
+                    }
+                    conflicts.register_key(provider.key(), &cached_plugin.source)?;
                     let configurable_provider = ConfigurableWorkspaceProvider::new(
-                        Box::new(provider) as Box<dyn MartyPlugin>,
+                        Arc::from(provider),
+                        workspace_root,
+                        cached_plugin.options.as_ref(),
                         workspace_includes.clone(),
                         workspace_excludes.clone(),
                     );
                     providers.push(Box::new(configurable_provider) as Box<dyn MartyPlugin>);
                 }
                 Err(e) => {
+                    loaded_plugin_names.insert(cached_plugin.name.clone());
+                    available_plugins.insert(cached_plugin.name.clone());
                     eprintln!("Failed to load plugin '{}': {}", cached_plugin.name, e);
                 }
             }
@@ -344,23 +2275,66 @@ impl WorkspaceManager {
         let all_plugins = Self::load_plugins_from_directory(workspace_root)
             .map_err(|e| MartyError::Task(format!("Failed to load workspace providers: {}", e)))?;
 
-        let additional_providers = all_plugins
-            .into_iter()
-            .filter(|p| !loaded_plugin_names.contains(p.name())) // Only load plugins not already configured
-            .map(|p| {
-                Box::new(ConfigurableWorkspaceProvider::new(
-                    Box::new(p) as Box<dyn MartyPlugin>,
-                    workspace_includes.clone(),
-                    workspace_excludes.clone(),
-                )) as Box<dyn MartyPlugin>
-            })
-            .collect::<Vec<_>>();
-        providers.extend(additional_providers);
+        for (path, plugin) in all_plugins {
+            // Only load plugins not already configured
+            if loaded_plugin_names.contains(plugin.name()) {
+                continue;
+            }
+            available_plugins.insert(plugin.key().to_string());
+            if !selection.allows(&[plugin.key()]) {
+                continue;
+            }
+            let source = format!("plugin directory {}", path.display());
+            conflicts.register_key(plugin.key(), &source)?;
+            providers.push(Box::new(ConfigurableWorkspaceProvider::new(
+                Arc::from(plugin),
+                workspace_root,
+                None,
+                workspace_includes.clone(),
+                workspace_excludes.clone(),
+            )) as Box<dyn MartyPlugin>);
+        }
+
+        for plugin in embedded_plugins {
+            available_plugins.insert(plugin.key().to_string());
+            if !selection.allows(&[plugin.key()]) {
+                continue;
+            }
+            let source = format!("embedded plugin {}", plugin.name());
+            conflicts.register_key(plugin.key(), &source)?;
+            providers.push(Box::new(ConfigurableWorkspaceProvider::new(
+                plugin.clone(),
+                workspace_root,
+                None,
+                workspace_includes.clone(),
+                workspace_excludes.clone(),
+            )) as Box<dyn MartyPlugin>);
+        }
+
+        let unmatched = selection.unmatched(&available_plugins);
+        if !unmatched.is_empty() {
+            let mut available = available_plugins.into_iter().collect::<Vec<_>>();
+            available.sort();
+            return Err(MartyError::Config(format!(
+                "Unknown plugin(s) in plugin selection: {} (available: {})",
+                unmatched.join(", "),
+                available.join(", ")
+            )));
+        }
 
         Ok(providers)
     }
 
     fn build_task_map(&self) -> MartyResult<HashMap<String, TaskConfig>> {
+        self.task_map(&self.project_configs())
+    }
+
+    /// Workspace-level tasks by name, and project tasks and plugin default tasks by
+    /// `project:task`
+    fn task_map(
+        &self,
+        project_configs: &ProjectConfigs,
+    ) -> MartyResult<HashMap<String, TaskConfig>> {
         let mut task_map = HashMap::new();
 
         // Add all workspace-level tasks to the map
@@ -370,12 +2344,9 @@ impl WorkspaceManager {
 
         // Add project-level tasks with project-specific keys
         for project in &self.workspace.projects {
-            let project_config_path = project.project_dir.join("marty.yml");
-            if !project_config_path.exists() {
+            let Some(project_config) = project_configs.get(&project.name)? else {
                 continue;
-            }
-
-            let project_config = self.load_project_config(&project.project_dir)?;
+            };
 
             // Add project-specific tasks with keys like "project_name:task_name"
             if let Some(tasks) = &project_config.tasks {
@@ -386,26 +2357,46 @@ impl WorkspaceManager {
             }
         }
 
+        // Plugin default tasks apply to the projects a plugin discovered, unless the
+        // workspace or the project defines a task of the same name
+        for inferred in &self.workspace.inferred_projects {
+            let Some(tasks) = self.plugin_tasks.get(&inferred.discovered_by) else {
+                continue;
+            };
+            if self.workspace.project(&inferred.name).is_none() {
+                continue;
+            }
+            for task in tasks {
+                let project_task_key = format!("{}:{}", inferred.name, task.name);
+                if task_map.contains_key(&task.name) || task_map.contains_key(&project_task_key) {
+                    continue;
+                }
+                task_map.insert(
+                    project_task_key,
+                    TaskConfig {
+                        name: task.name.clone(),
+                        description: task.description.clone(),
+                        command: Some(Command::Single(task.command.clone())),
+                        dependencies: (!task.dependencies.is_empty())
+                            .then(|| task.dependencies.clone()),
+                        ..TaskConfig::default()
+                    },
+                );
+            }
+        }
+
         Ok(task_map)
     }
 
-    fn load_project_config(&self, project_dir: &Path) -> MartyResult<ProjectConfig> {
-        let project_config_path = project_dir.join("marty.yml");
-        let content = std::fs::read_to_string(&project_config_path).map_err(|e| {
-            MartyError::Task(format!(
-                "Failed to read project config {}: {}",
-                project_config_path.display(),
-                e
-            ))
-        })?;
-
-        parse_project_config(&content).map_err(|e| {
-            MartyError::Task(format!(
-                "Failed to parse project config {}: {}",
-                project_config_path.display(),
-                e
-            ))
-        })
+    /// The marty.yml of every project, read on several threads when workspace.yml enables it
+    fn project_configs(&self) -> ProjectConfigs {
+        let parallel = self
+            .workspace_config
+            .planning
+            .as_ref()
+            .and_then(|planning| planning.parallel_config_loading)
+            .unwrap_or(false);
+        ProjectConfigs::load(&self.workspace, parallel)
     }
 
     fn parse_target(target: &str) -> MartyResult<(Option<String>, String)> {
@@ -416,87 +2407,52 @@ impl WorkspaceManager {
         }
     }
 
-    /// Generate consistent color mapping for projects
-    fn get_project_colors(&self) -> HashMap<String, colored::Color> {
-        let mut colors = HashMap::new();
-        let available_colors = [
-            colored::Color::Red,
-            colored::Color::Green,
-            colored::Color::Yellow,
-            colored::Color::Blue,
-            colored::Color::Magenta,
-            colored::Color::Cyan,
-            colored::Color::White,
-            colored::Color::BrightRed,
-            colored::Color::BrightGreen,
-            colored::Color::BrightYellow,
-            colored::Color::BrightBlue,
-            colored::Color::BrightMagenta,
-            colored::Color::BrightCyan,
-        ];
-
-        let mut all_projects: Vec<String> = Vec::new();
-        all_projects.extend(self.workspace.projects.iter().map(|p| p.name.clone()));
-        all_projects.extend(
-            self.workspace
-                .inferred_projects
-                .iter()
-                .map(|p| p.name.clone()),
-        );
-        all_projects.sort();
-
-        for (i, project) in all_projects.iter().enumerate() {
-            let color = available_colors[i % available_colors.len()];
-            colors.insert(project.clone(), color);
-        }
-
-        colors
-    }
-
-    /// Scan the .marty/plugins directory for dynamic library files and return providers for each
+    /// Load plugins from `.marty/plugins`, sorted by file name so discovery order is stable
     fn load_plugins_from_directory(
         workspace_root: &Path,
-    ) -> anyhow::Result<Vec<DylibWorkspaceProvider>> {
+    ) -> anyhow::Result<Vec<(PathBuf, Box<dyn MartyPlugin>)>> {
         let plugins_dir = workspace_root.join(".marty/plugins");
         if !plugins_dir.exists() {
             return Ok(Vec::new());
         }
 
-        let mut providers = Vec::new();
+        let mut paths = Vec::new();
         for entry in std::fs::read_dir(&plugins_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            // Check for dynamic library extensions
-            let is_dylib = path
-                .extension()
-                .map(|e| e == "so" || e == "dylib" || e == "dll")
-                .unwrap_or(false);
-
-            if is_dylib {
-                match DylibWorkspaceProvider::from_dylib_with_temp_copy(path.clone()) {
-                    Ok(provider) => providers.push(provider),
-                    Err(e) => eprintln!("Failed to load plugin '{}': {}", path.display(), e),
-                }
+            let path = entry?.path();
+            if plugin_runtime::is_plugin_file(&path) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut providers = Vec::new();
+        for path in paths {
+            match plugin_runtime::load_plugin(&path, workspace_root) {
+                Ok(provider) => providers.push((path, provider)),
+                Err(e) => eprintln!("Failed to load plugin '{}': {}", path.display(), e),
             }
         }
         Ok(providers)
     }
 }
 
-/// Wrapper that combines workspace config includes with plugin includes
+/// Wrapper that combines workspace config includes with plugin includes. The plugin is
+/// initialized with its options when wrapped and shut down when the wrapper is dropped.
 struct ConfigurableWorkspaceProvider {
-    inner: Box<dyn MartyPlugin>,
+    inner: Arc<dyn MartyPlugin>,
     workspace_includes: Vec<String>,
     workspace_excludes: Vec<String>,
 }
 
 impl ConfigurableWorkspaceProvider {
     fn new(
-        inner: Box<dyn MartyPlugin>,
+        inner: Arc<dyn MartyPlugin>,
+        workspace_root: &Path,
+        options: Option<&serde_json::Value>,
         workspace_includes: Vec<String>,
         workspace_excludes: Vec<String>,
     ) -> Self {
+        inner.initialize(workspace_root, options.unwrap_or(&serde_json::Value::Null));
         Self {
             inner,
             workspace_includes,
@@ -505,6 +2461,12 @@ impl ConfigurableWorkspaceProvider {
     }
 }
 
+impl Drop for ConfigurableWorkspaceProvider {
+    fn drop(&mut self) {
+        self.inner.shutdown();
+    }
+}
+
 impl MartyPlugin for ConfigurableWorkspaceProvider {
     fn plugin_type(&self) -> marty_plugin_protocol::PluginType {
         self.inner.plugin_type()
@@ -518,6 +2480,18 @@ impl MartyPlugin for ConfigurableWorkspaceProvider {
         self.inner.key()
     }
 
+    fn description(&self) -> Option<&str> {
+        self.inner.description()
+    }
+
+    fn version(&self) -> Option<&str> {
+        self.inner.version()
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        self.inner.homepage()
+    }
+
     fn workspace_provider(&self) -> &dyn WorkspaceProvider {
         self
     }
@@ -525,6 +2499,35 @@ impl MartyPlugin for ConfigurableWorkspaceProvider {
     fn configuration_options(&self) -> Option<serde_json::Value> {
         self.inner.configuration_options()
     }
+
+    fn suggested_tasks(&self) -> Vec<marty_plugin_protocol::SuggestedTask> {
+        self.inner.suggested_tasks()
+    }
+
+    fn default_tasks(&self) -> Vec<marty_plugin_protocol::SuggestedTask> {
+        self.inner.default_tasks()
+    }
+
+    fn post_discovery(&self, workspace: &marty_plugin_protocol::Workspace) -> Vec<String> {
+        self.inner.post_discovery(workspace)
+    }
+
+    fn on_project_created(
+        &self,
+        workspace: &marty_plugin_protocol::Workspace,
+        project_dir: &Path,
+    ) -> Vec<String> {
+        self.inner.on_project_created(workspace, project_dir)
+    }
+
+    fn run_task(
+        &self,
+        task: &str,
+        project: &marty_plugin_protocol::Project,
+        workspace: &marty_plugin_protocol::Workspace,
+    ) -> marty_plugin_protocol::TaskOutcome {
+        self.inner.run_task(task, project, workspace)
+    }
 }
 
 impl WorkspaceProvider for ConfigurableWorkspaceProvider {
@@ -560,4 +2563,45 @@ impl WorkspaceProvider for ConfigurableWorkspaceProvider {
             .workspace_provider()
             .on_file_found(workspace, path)
     }
+
+    fn try_on_file_found(
+        &self,
+        workspace: &marty_plugin_protocol::Workspace,
+        path: &Path,
+    ) -> Result<Option<InferredProject>, marty_plugin_protocol::PluginError> {
+        self.inner
+            .workspace_provider()
+            .try_on_file_found(workspace, path)
+    }
+
+    fn on_file_found_messages(
+        &self,
+        workspace: &marty_plugin_protocol::Workspace,
+        path: &Path,
+    ) -> Result<Vec<ProjectMessage>, marty_plugin_protocol::PluginError> {
+        self.inner
+            .workspace_provider()
+            .on_file_found_messages(workspace, path)
+    }
+
+    fn on_files_found(
+        &self,
+        workspace: &marty_plugin_protocol::Workspace,
+        paths: &[PathBuf],
+    ) -> Option<marty_plugin_protocol::FilesFound> {
+        self.inner
+            .workspace_provider()
+            .on_files_found(workspace, paths)
+    }
+
+    fn on_scan_complete(
+        &self,
+        workspace: &marty_plugin_protocol::Workspace,
+    ) -> Vec<WorkspaceMutation> {
+        self.inner.workspace_provider().on_scan_complete(workspace)
+    }
+
+    fn take_diagnostics(&self) -> Vec<marty_plugin_protocol::Diagnostic> {
+        self.inner.workspace_provider().take_diagnostics()
+    }
 }
@@ -0,0 +1,923 @@
+//! Golden tests over the fixture workspaces in `examples/fixtures`.
+//!
+//! Each fixture directory holds a workspace, a `plans.txt` listing the targets to plan
+//! (one per line) and an `expected.txt` with the rendered discovery, graph and plan output.
+//!
+//! By default the fixtures are scanned with the builtin cargo and pnpm plugins, when their
+//! features are enabled, and a small in-process provider following the conventions of the
+//! example python plugin. Without the builtin features, in-process providers stand in for
+//! the cargo and pnpm plugins as well. To run the
+//! fixtures against real plugin binaries instead, point `MARTY_FIXTURE_PLUGINS` at them
+//! (absolute paths, separated like `PATH`):
+//!
+//! ```text
+//! MARTY_FIXTURE_PLUGINS=$PWD/plugins/cargo/target/debug/libmarty_plugin_cargo.so:... \
+//!     cargo test -p marty_core --test golden_fixtures
+//! ```
+//!
+//! Set `MARTY_UPDATE_GOLDEN=1` to rewrite `expected.txt` after an intended change.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use marty_core::builtin_plugins;
+use marty_core::execution::group_by_dependency_levels;
+use marty_core::plugin_runtime_dylib::DylibWorkspaceProvider;
+use marty_core::progress::{ProgressEvent, ProgressSink};
+use marty_core::workspace_manager::RunOptions;
+use marty_core::WorkspaceManager;
+use marty_plugin_protocol::{
+    Diagnostic, InferredProject, MartyPlugin, PluginType, Project, Span, SuggestedTask,
+    TaskOutcome, Workspace, WorkspaceProvider,
+};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/fixtures")
+}
+
+/// Copy a fixture workspace into a temporary directory, so nothing Marty writes under
+/// `.marty` ends up in the repository
+fn copy_fixture(fixture: &Path) -> (tempfile::TempDir, PathBuf) {
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            let target = to.join(entry.file_name());
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &target);
+            } else {
+                std::fs::copy(entry.path(), target).unwrap();
+            }
+        }
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    copy_dir(fixture, &root);
+    (temp, root)
+}
+
+fn providers() -> Vec<Box<dyn MartyPlugin>> {
+    match std::env::var_os("MARTY_FIXTURE_PLUGINS") {
+        Some(paths) => std::env::split_paths(&paths)
+            .map(|path| {
+                let provider = DylibWorkspaceProvider::from_dylib(path.clone())
+                    .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+                Box::new(provider) as Box<dyn MartyPlugin>
+            })
+            .collect(),
+        None => vec![
+            builtin_plugins::create("cargo").unwrap_or_else(|| Box::new(ManifestPlugin::Cargo)),
+            builtin_plugins::create("pnpm").unwrap_or_else(|| Box::new(ManifestPlugin::Pnpm)),
+            Box::new(ManifestPlugin::Python),
+        ],
+    }
+}
+
+fn render(manager: &WorkspaceManager, root: &Path, targets: &[String]) -> String {
+    let workspace = &manager.workspace;
+    let mut out = String::new();
+
+    let mut projects = workspace
+        .inferred_projects
+        .iter()
+        .map(|p| {
+            let dir = p.project_dir.strip_prefix(root).unwrap_or(&p.project_dir);
+            format!("  {} [{}] {}", p.name, p.discovered_by, dir.display())
+        })
+        .collect::<Vec<_>>();
+    projects.sort();
+    writeln!(out, "projects:").unwrap();
+    for line in projects {
+        writeln!(out, "{}", line).unwrap();
+    }
+
+    let graph = workspace.dep_graph.as_ref().unwrap();
+    let mut edges = graph
+        .raw_edges()
+        .iter()
+        .map(|e| format!("  {} -> {}", graph[e.source()], graph[e.target()]))
+        .collect::<Vec<_>>();
+    edges.sort();
+    writeln!(out, "dependencies:").unwrap();
+    for line in edges {
+        writeln!(out, "{}", line).unwrap();
+    }
+    writeln!(out, "cycles: {:?}", workspace.dependency_cycles).unwrap();
+
+    for target in targets {
+        writeln!(out, "plan {}:", target).unwrap();
+        match manager.get_execution_plan(target) {
+            Ok(plan) => {
+                let levels =
+                    group_by_dependency_levels(workspace, &plan.compatible_projects).unwrap();
+                for (i, mut level) in levels.into_iter().enumerate() {
+                    level.sort();
+                    writeln!(out, "  {}: {}", i + 1, level.join(", ")).unwrap();
+                }
+            }
+            Err(e) => writeln!(out, "  error: {}", e).unwrap(),
+        }
+    }
+
+    out
+}
+
+#[test]
+fn test_fixture_workspaces_match_golden_output() {
+    let mut fixtures = std::fs::read_dir(fixtures_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("expected.txt").exists())
+        .collect::<Vec<_>>();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures found");
+
+    for fixture in fixtures {
+        let (_temp, root) = copy_fixture(&fixture);
+        let targets = std::fs::read_to_string(root.join("plans.txt"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        let manager = WorkspaceManager::with_providers(root.clone(), providers()).unwrap();
+        let actual = render(&manager, &root, &targets);
+        // Planning is read-only: it must not touch the run history
+        assert!(!root.join(".marty/state.json").exists());
+
+        let expected_path = fixture.join("expected.txt");
+        if std::env::var_os("MARTY_UPDATE_GOLDEN").is_some() {
+            std::fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&expected_path).unwrap();
+        assert_eq!(
+            actual,
+            expected,
+            "golden output mismatch for {}",
+            fixture.display()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_registered_plugins_discover_like_providers() {
+    let (_temp, root) = copy_fixture(&fixtures_dir().join("mixed"));
+
+    let registered = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(ManifestPlugin::Cargo))
+        .register_plugin(Box::new(ManifestPlugin::Pnpm))
+        .register_plugin(Box::new(ManifestPlugin::Python))
+        .build()
+        .await
+        .unwrap();
+    let direct = WorkspaceManager::with_providers(
+        root.clone(),
+        vec![
+            Box::new(ManifestPlugin::Cargo),
+            Box::new(ManifestPlugin::Pnpm),
+            Box::new(ManifestPlugin::Python),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(render(&registered, &root, &[]), render(&direct, &root, &[]));
+}
+
+#[test]
+fn test_plugin_default_tasks_yield_to_workspace_tasks() {
+    let (_temp, root) = copy_fixture(&fixtures_dir().join("mixed"));
+    let manager = WorkspaceManager::with_providers(root, providers()).unwrap();
+
+    let build = manager.get_execution_plan("build").unwrap();
+    let configs = manager.plan_task_configs(&build).unwrap();
+    assert_eq!(configs["mixed-core"].script.as_deref(), Some("echo build"));
+
+    let test = manager.get_execution_plan("test").unwrap();
+    let configs = manager.plan_task_configs(&test).unwrap();
+    assert_eq!(
+        configs.keys().collect::<Vec<_>>(),
+        vec!["mixed-cli", "mixed-core"]
+    );
+}
+
+#[test]
+fn test_describe_task_shows_docs_and_project_overrides() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty/tasks")).unwrap();
+    std::fs::write(root.join(".marty/workspace.yml"), "name: docs\n").unwrap();
+    std::fs::write(
+        root.join(".marty/tasks/build.yml"),
+        "tasks:\n  - name: build\n    description: Build it\n    docs: |\n      # Build\n\n      Run `marty run build`.\n    script: echo build\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("a")).unwrap();
+    std::fs::write(
+        root.join("a/marty.yml"),
+        "name: a\ntasks:\n  - name: build\n    script: echo a\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("b")).unwrap();
+    std::fs::write(root.join("b/marty.yml"), "name: b\n").unwrap();
+
+    let manager = WorkspaceManager::with_providers(root, vec![Box::new(NotesPlugin)]).unwrap();
+    let description = manager.describe_task("build").unwrap();
+    let workspace_task = description.workspace_task.unwrap();
+    assert_eq!(workspace_task.description.as_deref(), Some("Build it"));
+    assert_eq!(
+        workspace_task.docs.as_deref(),
+        Some("# Build\n\nRun `marty run build`.\n")
+    );
+    let overrides = description
+        .project_overrides
+        .iter()
+        .map(|(project, task)| (project.as_str(), task.script.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(overrides, vec![("a", Some("echo a"))]);
+
+    let error = manager.describe_task("missing").unwrap_err().to_string();
+    assert!(error.contains("Task 'missing' not found"), "{}", error);
+}
+
+#[test]
+fn test_inline_workspace_tasks_merge_with_task_files() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty/tasks")).unwrap();
+    std::fs::write(
+        root.join(".marty/tasks/build.yml"),
+        "tasks:\n  - name: build\n    script: echo build\n",
+    )
+    .unwrap();
+    let with_inline = |tasks: &str| {
+        std::fs::write(
+            root.join(".marty/workspace.yml"),
+            format!("name: inline\ntasks:\n{}", tasks),
+        )
+        .unwrap();
+        WorkspaceManager::with_providers(root.clone(), vec![Box::new(NotesPlugin)])
+    };
+
+    let manager = with_inline("  - name: lint\n    script: echo lint\n").unwrap();
+    for (task, script) in [("build", "echo build"), ("lint", "echo lint")] {
+        let description = manager.describe_task(task).unwrap();
+        assert_eq!(
+            description.workspace_task.unwrap().script.as_deref(),
+            Some(script)
+        );
+    }
+
+    let error = with_inline("  - name: lint\n    script: a\n  - name: lint\n    script: b\n")
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("Task 'lint' is defined more than once in workspace.yml"),
+        "{}",
+        error
+    );
+
+    let error = with_inline("  - name: build\n    script: echo inline\n")
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("Task 'build' is defined both in workspace.yml and in")
+            && error.contains("build.yml"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_plugins_run_tasks_naming_them() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty/tasks")).unwrap();
+    std::fs::write(root.join(".marty/workspace.yml"), "name: notes\n").unwrap();
+    std::fs::write(
+        root.join(".marty/tasks/notes.yml"),
+        "tasks:\n  - name: stamp\n    plugin: notes\n  - name: other\n    plugin: missing\n",
+    )
+    .unwrap();
+    for name in ["a", "b"] {
+        std::fs::create_dir_all(root.join(name)).unwrap();
+        std::fs::write(
+            root.join(name).join("marty.yml"),
+            format!("name: {}\n", name),
+        )
+        .unwrap();
+    }
+
+    let manager = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(NotesPlugin))
+        .build()
+        .await
+        .unwrap();
+    let result = manager.run_task("stamp").await.unwrap();
+    assert_eq!(result.projects.len(), 2);
+    for name in ["a", "b"] {
+        assert_eq!(
+            std::fs::read_to_string(root.join(name).join("STAMP")).unwrap(),
+            name
+        );
+    }
+
+    let error = manager.run_task("other").await.unwrap_err().to_string();
+    assert!(error.contains("plugin 'missing'"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_plugins_register_created_projects() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty")).unwrap();
+    std::fs::write(root.join(".marty/workspace.yml"), "name: notes\n").unwrap();
+    std::fs::create_dir_all(root.join("a")).unwrap();
+    std::fs::write(root.join("a/marty.yml"), "name: a\n").unwrap();
+
+    let build = || {
+        WorkspaceManager::builder(root.clone())
+            .register_plugin(Box::new(NotesPlugin))
+            .build()
+    };
+    let manager = build().await.unwrap();
+    std::fs::create_dir_all(root.join("apps/b")).unwrap();
+    let notes = manager.project_created(Path::new("apps/b")).await.unwrap();
+    assert_eq!(
+        notes,
+        vec![("notes".to_string(), "registered b".to_string())]
+    );
+    for outside in ["..", "apps/../..", "/"] {
+        let error = manager
+            .project_created(Path::new(outside))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("not a directory inside the workspace"),
+            "{}",
+            error
+        );
+    }
+    assert!(manager.project_created(Path::new("apps/c")).await.is_err());
+
+    let manager = build().await.unwrap();
+    let mut names: Vec<_> = manager
+        .workspace
+        .inferred_projects
+        .iter()
+        .map(|project| project.name.as_str())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["a", "b"]);
+}
+
+// `#[tokio::test]` uses a current-thread runtime, which can't drive projects on other
+// threads, so the levels run one project at a time
+#[tokio::test]
+async fn test_parallel_runs_finish_on_a_current_thread_runtime() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty/tasks")).unwrap();
+    std::fs::write(root.join(".marty/workspace.yml"), "name: notes\n").unwrap();
+    std::fs::write(
+        root.join(".marty/tasks/notes.yml"),
+        "tasks:\n  - name: stamp\n    plugin: notes\n  - name: serve\n    command: \"true\"\n    persistent: true\n",
+    )
+    .unwrap();
+    for name in ["a", "b", "c"] {
+        std::fs::create_dir_all(root.join(name)).unwrap();
+        std::fs::write(
+            root.join(name).join("marty.yml"),
+            format!("name: {}\n", name),
+        )
+        .unwrap();
+    }
+
+    let manager = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(NotesPlugin))
+        .build()
+        .await
+        .unwrap();
+    let options = RunOptions {
+        jobs: Some(3),
+        ..RunOptions::default()
+    };
+    let result = manager
+        .run_task_with_options("stamp", &options)
+        .await
+        .unwrap();
+    assert_eq!(result.projects.len(), 3);
+    assert!(root.join("c/STAMP").exists());
+
+    let error = manager.run_task("serve").await.unwrap_err().to_string();
+    assert!(error.contains("multi-thread"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_project_metadata_includes_last_task_results() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty")).unwrap();
+    std::fs::write(root.join(".marty/workspace.yml"), "name: notes\n").unwrap();
+    std::fs::write(
+        root.join(".marty/state.json"),
+        r#"{"lastResults": {"build": {"a": "succeeded"}, "test": {"a": "failed", "b": "cached"}},
+            "resourceUsage": {"build": {"a": {"peak_rss_bytes": 1024, "cpu_ms": 1500}}}}"#,
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("apps/a")).unwrap();
+    std::fs::write(
+        root.join("apps/a/marty.yml"),
+        "name: a\ntags: [web]\nowners: [\"@payments\"]\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("b")).unwrap();
+    std::fs::write(root.join("b/marty.yml"), "name: b\n").unwrap();
+
+    let manager = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(NotesPlugin))
+        .build()
+        .await
+        .unwrap();
+    let mut records = manager.project_metadata().unwrap();
+    records.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let a = serde_json::to_value(&records[0]).unwrap();
+    assert_eq!(
+        a,
+        serde_json::json!({
+            "workspace": "notes",
+            "name": "a",
+            "path": "apps/a",
+            "kind": null,
+            "description": null,
+            "tags": ["web"],
+            "owners": ["@payments"],
+            "plugin": "notes",
+            "dependencies": [],
+            "tasks": [
+                {"task": "build", "last_status": "succeeded", "peak_rss_bytes": 1024, "cpu_ms": 1500},
+                {"task": "test", "last_status": "failed"}
+            ]
+        })
+    );
+    assert_eq!(records[1].owners, Vec::<String>::new());
+    assert_eq!(records[1].tasks.len(), 1);
+}
+
+#[tokio::test]
+async fn test_snapshot_change_detection_finds_affected_projects() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty")).unwrap();
+    std::fs::write(
+        root.join(".marty/workspace.yml"),
+        "name: notes\nchangeDetection: snapshot\n",
+    )
+    .unwrap();
+    for (name, contents) in [
+        ("a", "name: a\n"),
+        ("b", "name: b\ndependencies: [a]\n"),
+        ("c", "name: c\n"),
+    ] {
+        std::fs::create_dir_all(root.join(name)).unwrap();
+        std::fs::write(root.join(name).join("marty.yml"), contents).unwrap();
+    }
+
+    let manager = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(NotesPlugin))
+        .build()
+        .await
+        .unwrap();
+    assert!(manager.affected_projects(None).is_err());
+    manager.save_snapshot("latest").unwrap();
+    assert!(manager.affected_projects(None).unwrap().is_empty());
+
+    std::fs::write(root.join("a/lib.rs"), "").unwrap();
+    let mut affected: Vec<String> = manager
+        .affected_projects(Some("latest"))
+        .unwrap()
+        .into_iter()
+        .collect();
+    affected.sort();
+    assert_eq!(affected, vec!["a", "b"]);
+}
+
+#[tokio::test]
+async fn test_changed_only_within_compares_against_the_base() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty/tasks")).unwrap();
+    std::fs::write(
+        root.join(".marty/workspace.yml"),
+        "name: notes\nchangeDetection: snapshot\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join(".marty/tasks/build.yml"),
+        "tasks:\n  - name: build\n    command: echo build\n",
+    )
+    .unwrap();
+    for (name, contents) in [
+        ("a", "name: a\n"),
+        ("b", "name: b\ndependencies: [a]\n"),
+        ("c", "name: c\n"),
+    ] {
+        std::fs::create_dir_all(root.join(name)).unwrap();
+        std::fs::write(root.join(name).join("marty.yml"), contents).unwrap();
+    }
+
+    let manager = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(NotesPlugin))
+        .build()
+        .await
+        .unwrap();
+    manager.save_snapshot("before").unwrap();
+    std::fs::write(root.join("a/lib.rs"), "").unwrap();
+
+    let ran = |project: &str| {
+        let options = RunOptions {
+            changed_only_within: Some(project.to_string()),
+            affected_since: Some("before".to_string()),
+            dry_run: true,
+            ..RunOptions::default()
+        };
+        let manager = &manager;
+        async move {
+            let result = manager
+                .run_task_with_options("build", &options)
+                .await
+                .unwrap();
+            let mut projects: Vec<String> =
+                result.projects.into_iter().map(|p| p.project).collect();
+            projects.sort();
+            projects
+        }
+    };
+    assert_eq!(ran("a").await, vec!["a", "b"]);
+    assert!(ran("c").await.is_empty());
+}
+
+#[tokio::test]
+async fn test_registered_plugins_are_initialized_and_shut_down() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty")).unwrap();
+    std::fs::write(root.join(".marty/workspace.yml"), "name: notes\n").unwrap();
+    for name in ["a", "b"] {
+        std::fs::create_dir_all(root.join(name)).unwrap();
+        std::fs::write(
+            root.join(name).join("marty.yml"),
+            format!("name: {}\n", name),
+        )
+        .unwrap();
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let manager = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(LifecyclePlugin {
+            events: events.clone(),
+        }))
+        .build()
+        .await
+        .unwrap();
+    drop(manager);
+
+    let mut events = events.lock().unwrap().clone();
+    // Files are offered in traversal order, which depends on the file system
+    events[1..3].sort();
+    assert_eq!(
+        events,
+        vec![
+            format!("initialize {}", root.display()),
+            "a/marty.yml".to_string(),
+            "b/marty.yml".to_string(),
+            "shutdown".to_string(),
+        ]
+    );
+}
+
+/// Records the lifecycle calls and files it is offered
+struct LifecyclePlugin {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl MartyPlugin for LifecyclePlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "Lifecycle"
+    }
+
+    fn key(&self) -> &str {
+        "lifecycle"
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        self
+    }
+
+    fn initialize(&self, workspace_root: &Path, options: &serde_json::Value) {
+        assert!(options.is_null());
+        let event = format!("initialize {}", workspace_root.display());
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn shutdown(&self) {
+        self.events.lock().unwrap().push("shutdown".to_string());
+    }
+}
+
+impl WorkspaceProvider for LifecyclePlugin {
+    fn include_path_globs(&self) -> Vec<String> {
+        vec!["**/marty.yml".to_string()]
+    }
+
+    fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let relative = path.strip_prefix(&workspace.root).unwrap_or(path);
+        let event = relative.display().to_string();
+        self.events.lock().unwrap().push(event);
+        None
+    }
+}
+
+/// Discovers a project for every marty.yml, with the `dependencies` it lists, and
+/// implements the `stamp` task
+struct NotesPlugin;
+
+impl MartyPlugin for NotesPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "Notes"
+    }
+
+    fn key(&self) -> &str {
+        "notes"
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        self
+    }
+
+    fn run_task(&self, task: &str, project: &Project, _workspace: &Workspace) -> TaskOutcome {
+        if task != "stamp" {
+            return TaskOutcome::failure(format!("no task '{}'", task));
+        }
+        match std::fs::write(project.project_dir.join("STAMP"), &project.name) {
+            Ok(()) => TaskOutcome::success(vec![format!("stamped {}", project.name)]),
+            Err(e) => TaskOutcome::failure(e.to_string()),
+        }
+    }
+
+    // Registers new projects by giving them the marty.yml discovery looks for
+    fn on_project_created(&self, _workspace: &Workspace, project_dir: &Path) -> Vec<String> {
+        let config = project_dir.join("marty.yml");
+        let Some(name) = project_dir.file_name().and_then(|name| name.to_str()) else {
+            return Vec::new();
+        };
+        if config.exists() || std::fs::write(&config, format!("name: {}\n", name)).is_err() {
+            return Vec::new();
+        }
+        vec![format!("registered {}", name)]
+    }
+}
+
+impl WorkspaceProvider for NotesPlugin {
+    fn include_path_globs(&self) -> Vec<String> {
+        vec!["**/marty.yml".to_string()]
+    }
+
+    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let project_dir = path.parent()?.to_path_buf();
+        let config: serde_yaml::Value =
+            serde_yaml::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+        let workspace_dependencies = config["dependencies"]
+            .as_sequence()
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| dependency.as_str().map(str::to_string))
+            .collect();
+        Some(InferredProject {
+            name: project_dir.file_name()?.to_str()?.to_string(),
+            project_dir,
+            discovered_by: "notes".to_string(),
+            workspace_dependencies,
+            kind: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_plugin_diagnostics_are_reported_with_their_project() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".marty")).unwrap();
+    std::fs::write(root.join(".marty/workspace.yml"), "name: lint\n").unwrap();
+    for (dir, contents) in [
+        ("apps/web", "name: web\n"),
+        ("libs/ui", "name: ui\n# TODO\n"),
+    ] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+        std::fs::write(root.join(dir).join("marty.yml"), contents).unwrap();
+    }
+
+    let (progress, events) = ProgressSink::channel();
+    let manager = WorkspaceManager::builder(root.clone())
+        .register_plugin(Box::new(TodoPlugin::default()))
+        .progress(progress)
+        .build()
+        .await
+        .unwrap();
+    drop(manager);
+
+    let diagnostics: Vec<_> = events
+        .try_iter()
+        .filter_map(|event| match event {
+            ProgressEvent::PluginDiagnostic(diagnostic) => Some(diagnostic),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        serde_json::to_value(&diagnostics[0]).unwrap(),
+        serde_json::json!({
+            "plugin": "todo",
+            "project": "ui",
+            "level": "warning",
+            "message": "unresolved TODO",
+            "file": "libs/ui/marty.yml",
+            "span": {"start_line": 2, "start_column": 1, "end_line": 2, "end_column": 1}
+        })
+    );
+}
+
+/// Discovers a project for every marty.yml and warns about the TODOs in them
+#[derive(Default)]
+struct TodoPlugin {
+    diagnostics: Mutex<Vec<Diagnostic>>,
+}
+
+impl MartyPlugin for TodoPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "Todo"
+    }
+
+    fn key(&self) -> &str {
+        "todo"
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        self
+    }
+}
+
+impl WorkspaceProvider for TodoPlugin {
+    fn include_path_globs(&self) -> Vec<String> {
+        vec!["**/marty.yml".to_string()]
+    }
+
+    fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        for (line, _) in (1..)
+            .zip(contents.lines())
+            .filter(|(_, l)| l.contains("TODO"))
+        {
+            self.diagnostics.lock().unwrap().push(
+                Diagnostic::warning("unresolved TODO")
+                    .with_file(path)
+                    .with_span(Span::line(line)),
+            );
+        }
+        NotesPlugin.on_file_found(workspace, path)
+    }
+
+    fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.diagnostics.lock().unwrap())
+    }
+}
+
+/// Minimal providers mirroring the conventions of the bundled plugins
+enum ManifestPlugin {
+    Cargo,
+    Pnpm,
+    Python,
+}
+
+impl MartyPlugin for ManifestPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        self.key()
+    }
+
+    fn key(&self) -> &str {
+        match self {
+            ManifestPlugin::Cargo => "cargo",
+            ManifestPlugin::Pnpm => "pnpm",
+            ManifestPlugin::Python => "python-plugin",
+        }
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        self
+    }
+
+    fn default_tasks(&self) -> Vec<SuggestedTask> {
+        let ManifestPlugin::Cargo = self else {
+            return Vec::new();
+        };
+        ["build", "test"]
+            .into_iter()
+            .map(|name| SuggestedTask {
+                name: name.to_string(),
+                command: format!("cargo {}", name),
+                description: None,
+                dependencies: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+impl WorkspaceProvider for ManifestPlugin {
+    fn include_path_globs(&self) -> Vec<String> {
+        let file = match self {
+            ManifestPlugin::Cargo => "Cargo.toml",
+            ManifestPlugin::Pnpm => "package.json",
+            ManifestPlugin::Python => "requirements.txt",
+        };
+        vec![format!("**/{}", file)]
+    }
+
+    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let project_dir = path.parent()?.to_path_buf();
+        let contents = std::fs::read_to_string(path).ok()?;
+
+        let (name, workspace_dependencies) = match self {
+            ManifestPlugin::Cargo => {
+                let manifest: toml::Value = toml::from_str(&contents).ok()?;
+                let name = manifest.get("package")?.get("name")?.as_str()?.to_string();
+                let deps = manifest
+                    .get("dependencies")
+                    .and_then(|d| d.as_table())
+                    .map(|table| {
+                        table
+                            .iter()
+                            .filter(|(_, spec)| spec.get("path").is_some())
+                            .map(|(dep, _)| dep.clone())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (name, deps)
+            }
+            ManifestPlugin::Pnpm => {
+                let manifest: serde_json::Value = serde_json::from_str(&contents).ok()?;
+                let name = manifest.get("name")?.as_str()?.to_string();
+                let deps = manifest
+                    .get("dependencies")
+                    .and_then(|d| d.as_object())
+                    .map(|deps| {
+                        deps.iter()
+                            .filter(|(_, version)| {
+                                version
+                                    .as_str()
+                                    .is_some_and(|v| v.starts_with("workspace:"))
+                            })
+                            .map(|(dep, _)| dep.clone())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (name, deps)
+            }
+            ManifestPlugin::Python => {
+                let name = project_dir.file_name()?.to_str()?.to_string();
+                (name, Vec::new())
+            }
+        };
+
+        Some(InferredProject {
+            name,
+            project_dir,
+            discovered_by: self.key().to_string(),
+            workspace_dependencies,
+            kind: None,
+        })
+    }
+}
@@ -11,6 +11,384 @@
 //! ## Usage
 //!
 //! Use the [`export_plugin!`] macro to export your plugin with all necessary C ABI functions.
+//!
+//! ## String Exchange
+//!
+//! Marty talks to plugins through `plugin_call`, which writes each response into a buffer
+//! owned by Marty, so no memory allocated by the plugin ever crosses the library boundary.
+//! The per-function exports that return plugin-allocated C strings (freed through
+//! `plugin_cleanup_string`) are still generated for older versions of Marty.
+
+use serde::{Deserialize, Serialize};
+
+use crate::traits::MartyPlugin;
+use crate::types::{PluginType, Project, Workspace};
+use crate::{InferredProjectMessage, PluginError, ProjectMessage};
+
+/// Version of the buffer-based ABI, reported by `plugin_abi_version`. Bumped on changes
+/// that break plugins built against an earlier version, and the other way around.
+pub const ABI_VERSION: u32 = 2;
+
+/// Oldest ABI version Marty still loads. Plugins without `plugin_abi_version` are
+/// version 1, which uses the legacy string exports.
+pub const MIN_ABI_VERSION: u32 = 1;
+
+/// Method names accepted by `plugin_call`
+pub mod methods {
+    pub const NAME: &str = "name";
+    pub const KEY: &str = "key";
+    pub const TYPE: &str = "type";
+    pub const INCLUDE_GLOBS: &str = "include_globs";
+    pub const EXCLUDE_GLOBS: &str = "exclude_globs";
+    pub const CONFIG_OPTIONS: &str = "config_options";
+    /// Older plugins don't support it.
+    pub const DESCRIPTION: &str = "description";
+    /// Older plugins don't support it.
+    pub const VERSION: &str = "version";
+    /// Older plugins don't support it.
+    pub const HOMEPAGE: &str = "homepage";
+    /// Input: the plugin's JSON options from workspace.yml. Older plugins don't support it.
+    pub const CONFIGURE: &str = "configure";
+    /// Input: a JSON [`InitializeRequest`](super::InitializeRequest). Older plugins don't
+    /// support it; callers fall back to [`CONFIGURE`].
+    pub const INITIALIZE: &str = "initialize";
+    /// Older plugins don't support it.
+    pub const SHUTDOWN: &str = "shutdown";
+    /// Input: a JSON [`FileFoundRequest`](super::FileFoundRequest)
+    pub const ON_FILE_FOUND: &str = "on_file_found";
+    /// Input: a JSON [`FileFoundRequest`](super::FileFoundRequest). Older plugins don't
+    /// support it; callers fall back to [`ON_FILE_FOUND`]. Errors are reported as no
+    /// messages.
+    pub const ON_FILE_FOUND_MESSAGES: &str = "on_file_found_messages";
+    /// Input: a JSON [`FileFoundRequest`](super::FileFoundRequest), output a JSON
+    /// [`FileFoundResponse`](super::FileFoundResponse). Older plugins don't support it;
+    /// callers fall back to [`ON_FILE_FOUND_MESSAGES`].
+    pub const ON_FILE_FOUND_RESULT: &str = "on_file_found_result";
+    /// Input: a JSON [`FilesFoundRequest`](super::FilesFoundRequest), output a JSON
+    /// [`FilesFound`](crate::FilesFound), or `null` if the plugin handles files one at a
+    /// time. Older plugins don't support it; callers fall back to the per-file methods.
+    pub const ON_FILES_FOUND: &str = "on_files_found";
+    /// Input: the JSON [`Workspace`](crate::Workspace)
+    pub const ON_SCAN_COMPLETE: &str = "on_scan_complete";
+    /// Older plugins don't support it.
+    pub const TAKE_DIAGNOSTICS: &str = "take_diagnostics";
+    /// Older plugins don't support it.
+    pub const SUGGESTED_TASKS: &str = "suggested_tasks";
+    /// Older plugins don't support it.
+    pub const DEFAULT_TASKS: &str = "default_tasks";
+    /// Input: the JSON [`Workspace`](crate::Workspace). Older plugins don't support it.
+    pub const POST_DISCOVERY: &str = "post_discovery";
+    /// Input: a JSON [`ProjectCreatedRequest`](super::ProjectCreatedRequest). Older plugins
+    /// don't support it.
+    pub const ON_PROJECT_CREATED: &str = "on_project_created";
+    /// Input: a JSON [`RunTaskRequest`](super::RunTaskRequest). Older plugins don't
+    /// support it.
+    pub const RUN_TASK: &str = "run_task";
+}
+
+/// Input of the `initialize` method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitializeRequest {
+    pub workspace_root: String,
+    /// The plugin's options from workspace.yml, `null` if it has none
+    #[serde(default)]
+    pub options: serde_json::Value,
+}
+
+/// Input of the `on_file_found` and `on_file_found_messages` methods
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileFoundRequest {
+    pub path: String,
+    /// The projects discovered so far. Older versions of Marty don't send it, and plugins
+    /// then see an empty workspace rooted at ".".
+    #[serde(default)]
+    pub workspace: Option<Workspace>,
+}
+
+/// Input of the `on_files_found` method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilesFoundRequest {
+    pub paths: Vec<String>,
+    /// The projects discovered before the plugin's scan
+    pub workspace: Workspace,
+}
+
+/// Output of the `on_file_found_result` method: the messages for a file, or why the plugin
+/// couldn't process it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFoundResponse {
+    Messages(Vec<ProjectMessage>),
+    Error(PluginError),
+}
+
+impl From<Result<Vec<ProjectMessage>, PluginError>> for FileFoundResponse {
+    fn from(result: Result<Vec<ProjectMessage>, PluginError>) -> Self {
+        match result {
+            Ok(messages) => FileFoundResponse::Messages(messages),
+            Err(error) => FileFoundResponse::Error(error),
+        }
+    }
+}
+
+impl From<FileFoundResponse> for Result<Vec<ProjectMessage>, PluginError> {
+    fn from(response: FileFoundResponse) -> Self {
+        match response {
+            FileFoundResponse::Messages(messages) => Ok(messages),
+            FileFoundResponse::Error(error) => Err(error),
+        }
+    }
+}
+
+/// Input of the `on_project_created` method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectCreatedRequest {
+    pub project_dir: String,
+    pub workspace: Workspace,
+}
+
+/// Input of the `run_task` method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunTaskRequest {
+    pub task: String,
+    pub project: Project,
+    pub workspace: Workspace,
+}
+
+/// Errors `plugin_call` reports as negative return values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallError {
+    UnknownMethod,
+    InvalidInput,
+    Serialization,
+}
+
+impl CallError {
+    /// The value returned by `plugin_call` for this error
+    pub const fn code(self) -> isize {
+        match self {
+            CallError::UnknownMethod => -1,
+            CallError::InvalidInput => -2,
+            CallError::Serialization => -3,
+        }
+    }
+
+    /// Map a negative `plugin_call` return value back to an error
+    pub const fn from_code(code: isize) -> Option<Self> {
+        match code {
+            -1 => Some(CallError::UnknownMethod),
+            -2 => Some(CallError::InvalidInput),
+            -3 => Some(CallError::Serialization),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for CallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::UnknownMethod => write!(f, "unknown method"),
+            CallError::InvalidInput => write!(f, "invalid input"),
+            CallError::Serialization => write!(f, "failed to serialize response"),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// Run `method` on `plugin` and return the JSON response.
+///
+/// This is the safe core of `plugin_call`; the generated export only converts pointers
+/// to slices and copies the response into the caller's buffer.
+pub fn dispatch(
+    plugin: &dyn MartyPlugin,
+    method: &str,
+    input: &[u8],
+) -> Result<Vec<u8>, CallError> {
+    let provider = plugin.workspace_provider();
+    let response = match method {
+        methods::NAME => serde_json::to_vec(plugin.name()),
+        methods::KEY => serde_json::to_vec(plugin.key()),
+        methods::TYPE => serde_json::to_vec(&match plugin.plugin_type() {
+            PluginType::Primary => 0u8,
+            PluginType::Supplemental => 1,
+            PluginType::Hook => 2,
+        }),
+        methods::INCLUDE_GLOBS => serde_json::to_vec(&provider.include_path_globs()),
+        methods::EXCLUDE_GLOBS => serde_json::to_vec(&provider.exclude_path_globs()),
+        methods::CONFIG_OPTIONS => serde_json::to_vec(&plugin.configuration_options()),
+        methods::DESCRIPTION => serde_json::to_vec(&plugin.description()),
+        methods::VERSION => serde_json::to_vec(&plugin.version()),
+        methods::HOMEPAGE => serde_json::to_vec(&plugin.homepage()),
+        methods::CONFIGURE => {
+            let options: serde_json::Value =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            plugin.configure(&options);
+            serde_json::to_vec(&())
+        }
+        methods::INITIALIZE => {
+            let request: InitializeRequest =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            plugin.initialize(
+                std::path::Path::new(&request.workspace_root),
+                &request.options,
+            );
+            serde_json::to_vec(&())
+        }
+        methods::SHUTDOWN => {
+            plugin.shutdown();
+            serde_json::to_vec(&())
+        }
+        methods::ON_FILE_FOUND => {
+            let request: FileFoundRequest =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            let workspace = request.workspace.unwrap_or_else(minimal_workspace);
+            let project = provider
+                .on_file_found(&workspace, std::path::Path::new(&request.path))
+                .map(InferredProjectMessage::from);
+            serde_json::to_vec(&project)
+        }
+        methods::ON_FILE_FOUND_MESSAGES => {
+            let request: FileFoundRequest =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            let workspace = request.workspace.unwrap_or_else(minimal_workspace);
+            let path = std::path::Path::new(&request.path);
+            let messages = provider
+                .on_file_found_messages(&workspace, path)
+                .unwrap_or_default();
+            serde_json::to_vec(&messages)
+        }
+        methods::ON_FILE_FOUND_RESULT => {
+            let request: FileFoundRequest =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            let workspace = request.workspace.unwrap_or_else(minimal_workspace);
+            let path = std::path::Path::new(&request.path);
+            let result = provider.on_file_found_messages(&workspace, path);
+            serde_json::to_vec(&FileFoundResponse::from(result))
+        }
+        methods::ON_FILES_FOUND => {
+            let request: FilesFoundRequest =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            let paths: Vec<std::path::PathBuf> =
+                request.paths.iter().map(std::path::PathBuf::from).collect();
+            serde_json::to_vec(&provider.on_files_found(&request.workspace, &paths))
+        }
+        methods::ON_SCAN_COMPLETE => {
+            let workspace: Workspace =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            serde_json::to_vec(&provider.on_scan_complete(&workspace))
+        }
+        methods::TAKE_DIAGNOSTICS => serde_json::to_vec(&provider.take_diagnostics()),
+        methods::SUGGESTED_TASKS => serde_json::to_vec(&plugin.suggested_tasks()),
+        methods::DEFAULT_TASKS => serde_json::to_vec(&plugin.default_tasks()),
+        methods::POST_DISCOVERY => {
+            let workspace: Workspace =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            serde_json::to_vec(&plugin.post_discovery(&workspace))
+        }
+        methods::ON_PROJECT_CREATED => {
+            let request: ProjectCreatedRequest =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            serde_json::to_vec(&plugin.on_project_created(
+                &request.workspace,
+                std::path::Path::new(&request.project_dir),
+            ))
+        }
+        methods::RUN_TASK => {
+            let request: RunTaskRequest =
+                serde_json::from_slice(input).map_err(|_| CallError::InvalidInput)?;
+            serde_json::to_vec(&plugin.run_task(
+                &request.task,
+                &request.project,
+                &request.workspace,
+            ))
+        }
+        _ => return Err(CallError::UnknownMethod),
+    };
+    response.map_err(|_| CallError::Serialization)
+}
+
+/// A response that didn't fit in the caller's buffer, kept for the retry
+struct PendingResponse {
+    method: String,
+    input: Vec<u8>,
+    response: Vec<u8>,
+}
+
+thread_local! {
+    static PENDING_RESPONSE: std::cell::RefCell<Option<PendingResponse>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Run the `plugin_call` export: dispatch `method` to `plugin` and copy the response into
+/// the caller's buffer.
+///
+/// Returns the response length, or a negative [`CallError`] code. A response that doesn't
+/// fit is kept until the next call on the same thread, which gets it instead of running
+/// the method again if it asks for the same method and input. Callers retrying with a
+/// larger buffer thus don't run a task or drain diagnostics twice.
+///
+/// # Safety
+///
+/// `method_ptr` and `input_ptr` must be null or valid for reads of `method_len` and
+/// `input_len` bytes, and `out_ptr` null or valid for writes of `out_cap` bytes.
+pub unsafe fn call(
+    plugin: &dyn MartyPlugin,
+    method_ptr: *const u8,
+    method_len: usize,
+    input_ptr: *const u8,
+    input_len: usize,
+    out_ptr: *mut u8,
+    out_cap: usize,
+) -> isize {
+    // Whatever the outcome, a kept response is only ever offered to the very next call
+    let pending = PENDING_RESPONSE.with(|pending| pending.borrow_mut().take());
+
+    if method_ptr.is_null() {
+        return CallError::InvalidInput.code();
+    }
+    let method = std::slice::from_raw_parts(method_ptr, method_len);
+    let method = match std::str::from_utf8(method) {
+        Ok(method) => method,
+        Err(_) => return CallError::InvalidInput.code(),
+    };
+    let input: &[u8] = if input_ptr.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(input_ptr, input_len)
+    };
+
+    let response = match pending {
+        Some(pending) if pending.method == method && pending.input == input => pending.response,
+        _ => match dispatch(plugin, method, input) {
+            Ok(response) => response,
+            Err(e) => return e.code(),
+        },
+    };
+
+    let len = response.len();
+    if !out_ptr.is_null() && len <= out_cap {
+        std::ptr::copy_nonoverlapping(response.as_ptr(), out_ptr, len);
+    } else {
+        PENDING_RESPONSE.with(|pending| {
+            *pending.borrow_mut() = Some(PendingResponse {
+                method: method.to_string(),
+                input: input.to_vec(),
+                response,
+            })
+        });
+    }
+    len as isize
+}
+
+/// The workspace plugins see while scanning when Marty doesn't send one
+fn minimal_workspace() -> Workspace {
+    Workspace {
+        root: std::path::PathBuf::from("."),
+        projects: Vec::new(),
+        inferred_projects: Vec::new(),
+    }
+}
 
 /// Macro to export your plugin with a C ABI interface for dynamic library loading.
 ///
@@ -24,12 +402,25 @@
 /// - Be `Send + Sync` (automatically satisfied for most structs)
 ///
 /// **Generated Functions**: The macro creates these C ABI exports:
+/// - `plugin_abi_version()` - Returns [`ABI_VERSION`]
+/// - `plugin_call()` - Runs a method (see [`methods`]) and writes the JSON response into
+///   a caller-provided buffer. Returns the response length, which may exceed the buffer
+///   capacity (nothing is written then, and the caller retries with a larger buffer,
+///   which gets the same response without running the method again), or a negative
+///   [`CallError`] code.
+///
+/// Built for `wasm32`, it also exports the plugin as a WebAssembly component; see
+/// [`wasm`](crate::wasm).
+///
+/// Legacy exports returning plugin-allocated strings, kept for older hosts:
 /// - `plugin_name()` - Returns the plugin's display name
 /// - `plugin_key()` - Returns the plugin's unique identifier
 /// - `plugin_type()` - Returns the plugin type (Primary/Supplemental/Hook)
 /// - `plugin_include_globs()` - Returns file inclusion patterns
 /// - `plugin_exclude_globs()` - Returns file exclusion patterns
 /// - `plugin_on_file_found()` - Handles file discovery events
+/// - `plugin_on_scan_complete()` - Receives the scanned workspace as JSON and returns
+///   a JSON array of workspace mutations
 /// - `plugin_cleanup_string()` - Manages memory for returned strings
 /// - `plugin_config_options()` - Returns JSON schema configuration
 ///
@@ -111,9 +502,10 @@
 ///
 /// # Memory Management
 ///
-/// The macro handles C string memory management automatically:
-/// - Strings returned to Marty are allocated with `CString::into_raw()`
-/// - Marty calls `plugin_cleanup_string()` to free the memory safely
+/// The macro handles memory management automatically:
+/// - `plugin_call()` only writes into memory owned by Marty
+/// - Legacy exports allocate strings with `CString::into_raw()`, which Marty hands back
+///   to `plugin_cleanup_string()`
 /// - Plugins should never manually free strings returned to Marty
 ///
 /// # Error Handling  
@@ -170,6 +562,28 @@ macro_rules! export_plugin {
 
         static PLUGIN: $plugin_type = <$plugin_type>::new();
 
+        #[no_mangle]
+        pub extern "C" fn plugin_abi_version() -> u32 {
+            $crate::dylib::ABI_VERSION
+        }
+
+        /// # Safety
+        ///
+        /// See [`call`]($crate::dylib::call).
+        #[no_mangle]
+        pub unsafe extern "C" fn plugin_call(
+            method_ptr: *const u8,
+            method_len: usize,
+            input_ptr: *const u8,
+            input_len: usize,
+            out_ptr: *mut u8,
+            out_cap: usize,
+        ) -> isize {
+            $crate::dylib::call(
+                &PLUGIN, method_ptr, method_len, input_ptr, input_len, out_ptr, out_cap,
+            )
+        }
+
         #[no_mangle]
         pub extern "C" fn plugin_name() -> *const c_char {
             let name = PLUGIN.name();
@@ -235,10 +649,11 @@ macro_rules! export_plugin {
             }
         }
 
-        /// Safe wrapper for handling plugin file found logic
+        /// Safe wrapper for handling plugin file found logic. The second argument carries
+        /// the JSON workspace; older versions of Marty pass the file contents there instead.
         fn handle_file_found_safe(
             path_ptr: *const c_char,
-            _contents_ptr: *const c_char,
+            workspace_ptr: *const c_char,
         ) -> Option<*const c_char> {
             // Validate pointers before any unsafe operations
             if path_ptr.is_null() {
@@ -256,12 +671,16 @@ macro_rules! export_plugin {
 
             let path = std::path::Path::new(path_str);
 
-            // Create a minimal workspace context for the plugin
-            let workspace = $crate::Workspace {
-                root: std::path::PathBuf::from("."),
-                projects: Vec::new(),
-                inferred_projects: Vec::new(),
-            };
+            // Fall back to a minimal workspace context when no workspace was passed
+            let workspace = (!workspace_ptr.is_null())
+                .then(|| unsafe { CStr::from_ptr(workspace_ptr) })
+                .and_then(|json| json.to_str().ok())
+                .and_then(|json| serde_json::from_str::<$crate::Workspace>(json).ok())
+                .unwrap_or_else(|| $crate::Workspace {
+                    root: std::path::PathBuf::from("."),
+                    projects: Vec::new(),
+                    inferred_projects: Vec::new(),
+                });
 
             match PLUGIN.workspace_provider().on_file_found(&workspace, path) {
                 Some(project) => {
@@ -284,9 +703,38 @@ macro_rules! export_plugin {
         #[no_mangle]
         pub extern "C" fn plugin_on_file_found(
             path_ptr: *const c_char,
-            contents_ptr: *const c_char,
+            workspace_ptr: *const c_char,
         ) -> *const c_char {
-            handle_file_found_safe(path_ptr, contents_ptr).unwrap_or_else(std::ptr::null)
+            handle_file_found_safe(path_ptr, workspace_ptr).unwrap_or_else(std::ptr::null)
+        }
+
+        /// Safe wrapper for handling the post-scan hook
+        fn handle_scan_complete_safe(workspace_ptr: *const c_char) -> Option<*const c_char> {
+            if workspace_ptr.is_null() {
+                return None;
+            }
+
+            let workspace_str = unsafe {
+                match CStr::from_ptr(workspace_ptr).to_str() {
+                    Ok(s) => s,
+                    Err(_) => return None,
+                }
+            };
+
+            let workspace: $crate::Workspace = serde_json::from_str(workspace_str).ok()?;
+            let mutations = PLUGIN.workspace_provider().on_scan_complete(&workspace);
+            match serde_json::to_string(&mutations) {
+                Ok(json) => match CString::new(json) {
+                    Ok(cstr) => Some(cstr.into_raw()),
+                    Err(_) => None,
+                },
+                Err(_) => None,
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn plugin_on_scan_complete(workspace_ptr: *const c_char) -> *const c_char {
+            handle_scan_complete_safe(workspace_ptr).unwrap_or_else(std::ptr::null)
         }
 
         /// Safe wrapper for cleaning up plugin-allocated strings
@@ -302,7 +750,321 @@ macro_rules! export_plugin {
         pub extern "C" fn plugin_cleanup_string(ptr: *const c_char) {
             cleanup_string_safe(ptr);
         }
+
+        // The component exports, see the `wasm` module
+        #[cfg(target_arch = "wasm32")]
+        const _: () = {
+            #[export_name = "abi-version"]
+            pub extern "C" fn component_abi_version() -> u32 {
+                $crate::dylib::ABI_VERSION
+            }
+
+            #[export_name = "call"]
+            pub unsafe extern "C" fn component_call(
+                method_ptr: *mut u8,
+                method_len: usize,
+                input_ptr: *mut u8,
+                input_len: usize,
+            ) -> *mut $crate::wasm::CallResult {
+                $crate::wasm::call(&PLUGIN, method_ptr, method_len, input_ptr, input_len)
+            }
+
+            #[export_name = "cabi_post_call"]
+            pub unsafe extern "C" fn component_post_call(result: *mut $crate::wasm::CallResult) {
+                $crate::wasm::post_call(result)
+            }
+
+            #[export_name = "cabi_realloc"]
+            pub unsafe extern "C" fn component_realloc(
+                old_ptr: *mut u8,
+                old_len: usize,
+                align: usize,
+                new_len: usize,
+            ) -> *mut u8 {
+                $crate::wasm::cabi_realloc(old_ptr, old_len, align, new_len)
+            }
+        };
     };
 }
 
 pub use export_plugin;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InferredProject, TaskOutcome, WorkspaceProvider};
+    use std::path::Path;
+
+    struct TestPlugin;
+
+    impl WorkspaceProvider for TestPlugin {
+        fn include_path_globs(&self) -> Vec<String> {
+            vec!["**/test.json".to_string()]
+        }
+
+        // Depends on every project found before, to show what the plugin was sent
+        fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+            Some(InferredProject {
+                name: path.parent()?.file_name()?.to_str()?.to_string(),
+                project_dir: path.parent()?.to_path_buf(),
+                discovered_by: "test".to_string(),
+                workspace_dependencies: workspace
+                    .inferred_projects
+                    .iter()
+                    .map(|p| p.name.clone())
+                    .collect(),
+                kind: None,
+            })
+        }
+
+        fn try_on_file_found(
+            &self,
+            workspace: &Workspace,
+            path: &Path,
+        ) -> Result<Option<InferredProject>, PluginError> {
+            match path.file_name().and_then(|name| name.to_str()) {
+                Some("broken.json") => Err(PluginError::new("unexpected end of file")),
+                _ => Ok(self.on_file_found(workspace, path)),
+            }
+        }
+    }
+
+    impl MartyPlugin for TestPlugin {
+        fn plugin_type(&self) -> PluginType {
+            PluginType::Supplemental
+        }
+
+        fn name(&self) -> &str {
+            "Test Plugin"
+        }
+
+        fn version(&self) -> Option<&str> {
+            Some("1.2.0")
+        }
+
+        fn key(&self) -> &str {
+            "test"
+        }
+
+        fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+            self
+        }
+    }
+
+    #[test]
+    fn test_dispatch_returns_json_responses() {
+        let call = |method: &str, input: &[u8]| dispatch(&TestPlugin, method, input);
+
+        assert_eq!(call(methods::NAME, b"").unwrap(), br#""Test Plugin""#);
+        assert_eq!(call(methods::TYPE, b"").unwrap(), b"1");
+        assert_eq!(call(methods::CONFIG_OPTIONS, b"").unwrap(), b"null");
+        assert_eq!(call(methods::DESCRIPTION, b"").unwrap(), b"null");
+        assert_eq!(call(methods::VERSION, b"").unwrap(), br#""1.2.0""#);
+        assert_eq!(call(methods::EXCLUDE_GLOBS, b"").unwrap(), b"[]");
+        assert_eq!(call(methods::CONFIGURE, br#"{"a":1}"#).unwrap(), b"null");
+        assert_eq!(
+            call(methods::INITIALIZE, br#"{"workspace_root":"/ws"}"#).unwrap(),
+            b"null"
+        );
+        assert_eq!(call(methods::SHUTDOWN, b"").unwrap(), b"null");
+        assert_eq!(call(methods::SUGGESTED_TASKS, b"").unwrap(), b"[]");
+        assert_eq!(call(methods::DEFAULT_TASKS, b"").unwrap(), b"[]");
+        assert_eq!(call(methods::TAKE_DIAGNOSTICS, b"").unwrap(), b"[]");
+        // Plugins that don't batch have their files offered one at a time
+        assert_eq!(
+            call(
+                methods::ON_FILES_FOUND,
+                br#"{"paths":["/ws/app/test.json"],"workspace":{"root":"/ws"}}"#
+            )
+            .unwrap(),
+            b"null"
+        );
+        assert_eq!(
+            call(methods::POST_DISCOVERY, br#"{"root":"/ws"}"#).unwrap(),
+            b"[]"
+        );
+        assert_eq!(
+            call(
+                methods::ON_PROJECT_CREATED,
+                br#"{"project_dir":"/ws/new","workspace":{"root":"/ws"}}"#
+            )
+            .unwrap(),
+            b"[]"
+        );
+        let outcome = call(
+            methods::RUN_TASK,
+            br#"{"task":"sync","project":{"name":"app","project_dir":"/ws/app","file_path":null},"workspace":{"root":"/ws"}}"#,
+        )
+        .unwrap();
+        let outcome: TaskOutcome = serde_json::from_slice(&outcome).unwrap();
+        assert!(!outcome.success);
+        assert_eq!(
+            outcome.output,
+            vec!["Test Plugin doesn't implement task 'sync'"]
+        );
+
+        let found = call(methods::ON_FILE_FOUND, br#"{"path":"/ws/app/test.json"}"#).unwrap();
+        let message: InferredProjectMessage = serde_json::from_slice(&found).unwrap();
+        assert_eq!(message.name, "app");
+        assert!(message.workspace_dependencies.is_empty());
+
+        let found = call(
+            methods::ON_FILE_FOUND,
+            br#"{"path":"/ws/app/test.json","workspace":{"root":"/ws","inferred_projects":[
+                {"name":"lib","project_dir":"/ws/lib","discovered_by":"test"}
+            ]}}"#,
+        )
+        .unwrap();
+        let message: InferredProjectMessage = serde_json::from_slice(&found).unwrap();
+        assert_eq!(message.workspace_dependencies, vec!["lib".to_string()]);
+
+        let found = call(
+            methods::ON_FILE_FOUND_MESSAGES,
+            br#"{"path":"/ws/app/test.json"}"#,
+        )
+        .unwrap();
+        let messages: Vec<ProjectMessage> = serde_json::from_slice(&found).unwrap();
+        assert!(matches!(
+            messages.as_slice(),
+            [ProjectMessage::AddProject { project }] if project.name == "app"
+        ));
+
+        let found = call(
+            methods::ON_FILE_FOUND_RESULT,
+            br#"{"path":"/ws/app/test.json"}"#,
+        )
+        .unwrap();
+        let response: FileFoundResponse = serde_json::from_slice(&found).unwrap();
+        assert!(matches!(
+            Result::from(response).as_deref(),
+            Ok([ProjectMessage::AddProject { project }]) if project.name == "app"
+        ));
+        let found = call(
+            methods::ON_FILE_FOUND_RESULT,
+            br#"{"path":"/ws/app/broken.json"}"#,
+        )
+        .unwrap();
+        assert_eq!(found, br#"{"error":{"message":"unexpected end of file"}}"#);
+        // Older hosts only see that there is no project
+        let found = call(
+            methods::ON_FILE_FOUND_MESSAGES,
+            br#"{"path":"/ws/app/broken.json"}"#,
+        )
+        .unwrap();
+        assert_eq!(found, b"[]");
+
+        assert_eq!(
+            call(methods::ON_FILE_FOUND, b"not json"),
+            Err(CallError::InvalidInput)
+        );
+        assert_eq!(call("bogus", b""), Err(CallError::UnknownMethod));
+        assert_eq!(
+            CallError::from_code(CallError::Serialization.code()),
+            Some(CallError::Serialization)
+        );
+    }
+
+    /// Runs tasks with an output larger than the buffer Marty starts with
+    struct CountingPlugin {
+        runs: std::sync::atomic::AtomicUsize,
+    }
+
+    impl WorkspaceProvider for CountingPlugin {
+        fn include_path_globs(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn on_file_found(&self, _workspace: &Workspace, _path: &Path) -> Option<InferredProject> {
+            None
+        }
+    }
+
+    impl MartyPlugin for CountingPlugin {
+        fn plugin_type(&self) -> PluginType {
+            PluginType::Hook
+        }
+
+        fn name(&self) -> &str {
+            "Counting Plugin"
+        }
+
+        fn key(&self) -> &str {
+            "counting"
+        }
+
+        fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+            self
+        }
+
+        fn run_task(&self, _task: &str, _project: &Project, _workspace: &Workspace) -> TaskOutcome {
+            let run = self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            TaskOutcome::success(vec![format!("run {}", run), "x".repeat(32 * 1024)])
+        }
+    }
+
+    #[test]
+    fn test_call_keeps_a_response_that_does_not_fit_for_the_retry() {
+        let plugin = CountingPlugin {
+            runs: std::sync::atomic::AtomicUsize::new(0),
+        };
+        let input = br#"{"task":"gen","project":{"name":"app","project_dir":"/ws/app","file_path":null},"workspace":{"root":"/ws"}}"#;
+        let run = |method: &str, buffer: &mut Vec<u8>| {
+            let len = unsafe {
+                call(
+                    &plugin,
+                    method.as_ptr(),
+                    method.len(),
+                    input.as_ptr(),
+                    input.len(),
+                    buffer.as_mut_ptr(),
+                    buffer.capacity(),
+                )
+            };
+            usize::try_from(len).unwrap()
+        };
+
+        let mut buffer = Vec::with_capacity(16 * 1024);
+        let len = run(methods::RUN_TASK, &mut buffer);
+        assert!(len > buffer.capacity());
+        buffer.reserve_exact(len);
+        assert_eq!(run(methods::RUN_TASK, &mut buffer), len);
+        unsafe { buffer.set_len(len) };
+        let outcome: TaskOutcome = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(outcome.output[0], "run 1");
+        assert_eq!(plugin.runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // The kept response is only handed to the call right after, if it asks for it
+        let mut small = Vec::with_capacity(16);
+        run(methods::RUN_TASK, &mut small);
+        run(methods::NAME, &mut buffer);
+        run(methods::RUN_TASK, &mut buffer);
+        assert_eq!(plugin.runs.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_diagnostics_serialize_with_optional_location() {
+        let diagnostics = vec![
+            crate::Diagnostic::warning("missing name")
+                .with_file("/ws/app/package.json")
+                .with_span(crate::Span::line(3)),
+            crate::Diagnostic::info("nothing to do"),
+        ];
+        let json = serde_json::to_value(&diagnostics).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "level": "warning",
+                    "message": "missing name",
+                    "file": "/ws/app/package.json",
+                    "span": {"start_line": 3, "start_column": 1, "end_line": 3, "end_column": 1}
+                },
+                {"level": "info", "message": "nothing to do"}
+            ])
+        );
+        assert_eq!(
+            serde_json::from_value::<Vec<crate::Diagnostic>>(json).unwrap(),
+            diagnostics
+        );
+    }
+}
@@ -100,6 +100,7 @@
 //!             project_dir,
 //!             discovered_by: "my-framework".to_string(),
 //!             workspace_dependencies,
+//!             kind: None,
 //!         })
 //!     }
 //! }
@@ -155,6 +156,7 @@
 //! - [`traits`] - Plugin traits (`MartyPlugin`, `WorkspaceProvider`)
 //! - [`message`] - Serializable message types for FFI communication
 //! - [`dylib`] - Dynamic library export macro and C ABI interface
+//! - [`wasm`] - WebAssembly component exports, for plugins Marty runs sandboxed
 //!
 //! ## Key Concepts
 //!
@@ -173,7 +175,16 @@
 //! 1. **Scanning**: Marty walks the workspace using your `include_path_globs()` patterns
 //! 2. **Filtering**: Files matching `exclude_path_globs()` are skipped  
 //! 3. **Detection**: `on_file_found()` is called for each matching file
-//! 4. **Project Creation**: If a project is detected, an `InferredProject` is returned
+//! 4. **Project Creation**: If a project is detected, an `InferredProject` is returned.
+//!    Plugins that need to revise earlier results return [`ProjectMessage`]s from
+//!    `on_file_found_messages()` instead, and files that are broken rather than not a
+//!    project are reported as a [`PluginError`] from `try_on_file_found()`. Plugins
+//!    that would rather see every matching file at once implement `on_files_found()`
+//!    and report [`FilesFound`] for the whole batch
+//! 5. **Finalization**: Once every plugin has scanned, `on_scan_complete()` may return
+//!    [`WorkspaceMutation`]s that adjust the discovered projects
+//! 6. **Diagnostics**: Problems a plugin ran into along the way are collected from
+//!    `take_diagnostics()` as [`Diagnostic`]s and shown with their project
 //!
 //! ## See Also
 //!
@@ -187,9 +198,15 @@ mod traits;
 mod types;
 
 // Re-export everything at the crate root for backward compatibility
-pub use message::InferredProjectMessage;
-pub use traits::{MartyPlugin, WorkspaceProvider};
-pub use types::{InferredProject, PluginKey, PluginType, Project, Workspace};
+pub use message::{FilesFound, InferredProjectMessage, ProjectMessage};
+pub use traits::{plugin_options, MartyPlugin, WorkspaceProvider};
+pub use types::{
+    Diagnostic, DiagnosticLevel, InferredProject, PluginError, PluginKey, PluginType, Project,
+    ProjectKind, Span, SuggestedTask, TaskOutcome, Workspace, WorkspaceMutation,
+};
 
 // Dynamic library exports
 pub mod dylib;
+
+// WebAssembly component exports
+pub mod wasm;
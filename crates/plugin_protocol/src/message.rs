@@ -2,8 +2,12 @@
 //!
 //! This module contains types used for cross-boundary communication between
 //! Marty and plugins, especially for FFI/dynamic library interfaces.
+//!
+//! During discovery a plugin reports what it learned from a file as a list of
+//! [`ProjectMessage`]s, so a later file can correct what an earlier one suggested.
+//! Plugins handling all their files in one call report them together in [`FilesFound`].
 
-use crate::types::InferredProject;
+use crate::types::{Diagnostic, InferredProject, ProjectKind};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -29,6 +33,7 @@ use std::path::PathBuf;
 ///     project_dir: PathBuf::from("/workspace/project"),
 ///     discovered_by: "pnpm".to_string(),
 ///     workspace_dependencies: vec![],
+///     kind: None,
 /// };
 /// let message: InferredProjectMessage = project.into();
 ///
@@ -48,6 +53,10 @@ pub struct InferredProjectMessage {
 
     /// List of workspace project dependencies.
     pub workspace_dependencies: Vec<String>,
+
+    /// What the project produces, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ProjectKind>,
 }
 
 impl InferredProjectMessage {
@@ -76,8 +85,16 @@ impl InferredProjectMessage {
             project_dir: project_dir.into(),
             discovered_by: discovered_by.into(),
             workspace_dependencies,
+            kind: None,
         }
     }
+
+    /// Set what the project produces.
+    #[must_use]
+    pub fn with_kind(mut self, kind: ProjectKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
 }
 
 impl From<InferredProject> for InferredProjectMessage {
@@ -87,10 +104,76 @@ impl From<InferredProject> for InferredProjectMessage {
             project_dir: project.project_dir.display().to_string(),
             discovered_by: project.discovered_by,
             workspace_dependencies: project.workspace_dependencies,
+            kind: project.kind,
         }
     }
 }
 
+/// A change to the discovered projects, reported while the workspace is scanned.
+///
+/// **Purpose**: `on_file_found()` can only add one project per file. Returning messages
+/// from `on_file_found_messages()` instead lets a plugin revise earlier inferences when a
+/// later file contradicts them, e.g. a workspace manifest that turns a directory previously
+/// seen as a project into a plain folder.
+///
+/// Messages are applied in order, as soon as the file is processed. Projects are referenced
+/// by name.
+///
+/// ```rust
+/// # use marty_plugin_protocol::{InferredProjectMessage, ProjectMessage};
+/// let messages = vec![
+///     ProjectMessage::RemoveProject {
+///         name: "packages".to_string(),
+///         discovered_by: Some("pnpm".to_string()),
+///     },
+///     ProjectMessage::AddProject {
+///         project: InferredProjectMessage::new("web", "/workspace/packages/web", "pnpm", vec![]),
+///     },
+///     ProjectMessage::AddDependencyEdge {
+///         from: "web".to_string(),
+///         to: "ui".to_string(),
+///     },
+/// ];
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProjectMessage {
+    /// Add a newly discovered project
+    AddProject { project: InferredProjectMessage },
+    /// Replace the project with the same name discovered by the same plugin, or add it if
+    /// there is none
+    UpdateProject { project: InferredProjectMessage },
+    /// Drop a previously discovered project.
+    ///
+    /// When `discovered_by` is set, only entries discovered by that plugin are removed.
+    RemoveProject {
+        name: String,
+        #[serde(default)]
+        discovered_by: Option<String>,
+    },
+    /// Make the already discovered project `from` depend on the workspace project `to`
+    AddDependencyEdge { from: String, to: String },
+}
+
+impl From<InferredProject> for ProjectMessage {
+    fn from(project: InferredProject) -> Self {
+        Self::AddProject {
+            project: project.into(),
+        }
+    }
+}
+
+/// What a plugin learned from a whole batch of files, returned by `on_files_found()`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FilesFound {
+    /// Changes to apply, in order, as if reported file by file
+    #[serde(default)]
+    pub messages: Vec<ProjectMessage>,
+    /// Files the plugin couldn't process, and anything else worth reporting
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
 impl From<InferredProjectMessage> for InferredProject {
     fn from(message: InferredProjectMessage) -> Self {
         Self {
@@ -98,6 +181,7 @@ impl From<InferredProjectMessage> for InferredProject {
             project_dir: PathBuf::from(message.project_dir),
             discovered_by: message.discovered_by,
             workspace_dependencies: message.workspace_dependencies,
+            kind: message.kind,
         }
     }
 }
@@ -3,10 +3,18 @@
 //! This module defines the two main traits that plugins must implement:
 //! - [`MartyPlugin`] - Main plugin interface with metadata and configuration
 //! - [`WorkspaceProvider`] - Project discovery and scanning logic
+//!
+//! [`plugin_options`] reads the options handed to [`MartyPlugin::configure`] into a typed
+//! config.
 
-use crate::types::{InferredProject, PluginType, Workspace};
+use crate::message::{FilesFound, ProjectMessage};
+use crate::types::{
+    Diagnostic, InferredProject, PluginError, PluginType, Project, SuggestedTask, TaskOutcome,
+    Workspace, WorkspaceMutation,
+};
+use serde::de::DeserializeOwned;
 use serde_json::Value as JsonValue;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// The workspace provider trait that plugins implement to discover projects.
 ///
@@ -126,7 +134,8 @@ pub trait WorkspaceProvider {
     /// **Performance Guidelines**:
     /// - Use filename/path checks before reading file contents
     /// - Return `None` quickly for files you can't process
-    /// - Handle parse errors gracefully (return `None`, don't panic)
+    /// - Handle parse errors gracefully (return `None`, don't panic), or report them from
+    ///   `try_on_file_found()`
     /// - Minimize file I/O operations
     ///
     /// # Arguments
@@ -174,6 +183,7 @@ pub trait WorkspaceProvider {
     ///         project_dir,
     ///         discovered_by: "my-plugin".to_string(),
     ///         workspace_dependencies,
+    ///         kind: None,
     ///     })
     /// }
     /// # }
@@ -187,6 +197,222 @@ pub trait WorkspaceProvider {
     /// - Impact analysis (which projects are affected by changes)
     /// - Generating dependency graphs
     fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject>;
+
+    /// Called instead of `on_file_found()`, to also report files that are broken rather
+    /// than not a project.
+    ///
+    /// **Purpose**: A manifest that doesn't parse is most likely a project the user expects
+    /// to see. Returning a [`PluginError`] instead of `Ok(None)` makes Marty report the file
+    /// as an error, instead of leaving the project out without a word.
+    ///
+    /// The default implementation returns whatever `on_file_found()` returns.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{InferredProject, PluginError, Span, Workspace, WorkspaceProvider};
+    /// # use std::path::Path;
+    /// # struct MyProvider;
+    /// # impl WorkspaceProvider for MyProvider {
+    /// # fn include_path_globs(&self) -> Vec<String> { vec![] }
+    /// fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+    ///     self.try_on_file_found(workspace, path).ok().flatten()
+    /// }
+    ///
+    /// fn try_on_file_found(
+    ///     &self,
+    ///     _workspace: &Workspace,
+    ///     path: &Path,
+    /// ) -> Result<Option<InferredProject>, PluginError> {
+    ///     if path.file_name().is_none_or(|name| name != "my-config.json") {
+    ///         return Ok(None);
+    ///     }
+    ///     let contents = std::fs::read_to_string(path)
+    ///         .map_err(|e| PluginError::new(format!("Failed to read: {}", e)))?;
+    ///     let config: serde_json::Value = serde_json::from_str(&contents).map_err(|e| {
+    ///         PluginError::new(e.to_string()).with_span(Span::line(e.line() as u32))
+    ///     })?;
+    ///     // ...
+    /// #   let _ = config;
+    /// #   Ok(None)
+    /// }
+    /// # }
+    /// ```
+    fn try_on_file_found(
+        &self,
+        workspace: &Workspace,
+        path: &Path,
+    ) -> Result<Option<InferredProject>, PluginError> {
+        Ok(self.on_file_found(workspace, path))
+    }
+
+    /// Called instead of `try_on_file_found()` to report any number of changes for a file.
+    ///
+    /// **Purpose**: Correct earlier inferences when a later file contradicts them, by
+    /// updating or removing projects reported before, or add dependency edges that only a
+    /// later file reveals. Messages are applied in order as soon as the file is processed.
+    /// An error is reported for the file and changes nothing.
+    ///
+    /// The default implementation adds the project returned by `try_on_file_found()`, if
+    /// any.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{InferredProject, PluginError, ProjectMessage, Workspace, WorkspaceProvider};
+    /// # use std::path::Path;
+    /// # struct MyProvider;
+    /// # impl WorkspaceProvider for MyProvider {
+    /// # fn include_path_globs(&self) -> Vec<String> { vec![] }
+    /// # fn on_file_found(&self, _: &Workspace, _: &Path) -> Option<InferredProject> { None }
+    /// fn on_file_found_messages(
+    ///     &self,
+    ///     workspace: &Workspace,
+    ///     path: &Path,
+    /// ) -> Result<Vec<ProjectMessage>, PluginError> {
+    ///     // A nested workspace manifest means its directory is not a project of its own
+    ///     if path.file_name().is_some_and(|name| name == "workspace.json") {
+    ///         let dir = path.parent().unwrap();
+    ///         return Ok(workspace
+    ///             .inferred_projects
+    ///             .iter()
+    ///             .filter(|p| p.project_dir == dir)
+    ///             .map(|p| ProjectMessage::RemoveProject {
+    ///                 name: p.name.clone(),
+    ///                 discovered_by: Some("my-plugin".to_string()),
+    ///             })
+    ///             .collect());
+    ///     }
+    ///     let project = self.try_on_file_found(workspace, path)?;
+    ///     Ok(project.into_iter().map(Into::into).collect())
+    /// }
+    /// # }
+    /// ```
+    fn on_file_found_messages(
+        &self,
+        workspace: &Workspace,
+        path: &Path,
+    ) -> Result<Vec<ProjectMessage>, PluginError> {
+        let project = self.try_on_file_found(workspace, path)?;
+        Ok(project.into_iter().map(ProjectMessage::from).collect())
+    }
+
+    /// Called with every file matching the plugin's globs at once, before any of them is
+    /// offered one at a time.
+    ///
+    /// **Purpose**: One call per scan instead of one per file saves crossing into the
+    /// plugin library thousands of times in large workspaces, and lets the plugin resolve
+    /// files against each other, like a Cargo workspace manifest and its members, without
+    /// depending on the order they are found in. The messages are applied in order once the
+    /// call returns; files the plugin couldn't process are reported as error diagnostics
+    /// with their file.
+    ///
+    /// Return `None` (the default) to have each file offered to `on_file_found_messages()`
+    /// instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{FilesFound, InferredProject, PluginError, Workspace, WorkspaceProvider};
+    /// # use std::path::{Path, PathBuf};
+    /// # struct MyProvider;
+    /// # impl WorkspaceProvider for MyProvider {
+    /// # fn include_path_globs(&self) -> Vec<String> { vec![] }
+    /// # fn on_file_found(&self, _: &Workspace, _: &Path) -> Option<InferredProject> { None }
+    /// fn on_files_found(&self, workspace: &Workspace, paths: &[PathBuf]) -> Option<FilesFound> {
+    ///     let mut found = FilesFound::default();
+    ///     for path in paths {
+    ///         match self.try_on_file_found(workspace, path) {
+    ///             Ok(project) => found.messages.extend(project.map(Into::into)),
+    ///             Err(error) => found.diagnostics.push(error.into_diagnostic(path)),
+    ///         }
+    ///     }
+    ///     Some(found)
+    /// }
+    /// # }
+    /// ```
+    fn on_files_found(&self, _workspace: &Workspace, _paths: &[PathBuf]) -> Option<FilesFound> {
+        None
+    }
+
+    /// Called once after every plugin has finished scanning the workspace.
+    ///
+    /// **Purpose**: Do cross-file reasoning that needs the complete set of projects, such as
+    /// resolving aliased dependencies by package name or dropping duplicates, instead of
+    /// guessing from a single file in `on_file_found()`.
+    ///
+    /// Plugins are called in load order, and each sees the mutations applied by the plugins
+    /// before it. The default implementation changes nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{WorkspaceProvider, Workspace, WorkspaceMutation};
+    /// # struct MyProvider;
+    /// # impl WorkspaceProvider for MyProvider {
+    /// # fn include_path_globs(&self) -> Vec<String> { vec![] }
+    /// # fn on_file_found(&self, _: &Workspace, _: &std::path::Path) -> Option<marty_plugin_protocol::InferredProject> { None }
+    /// fn on_scan_complete(&self, workspace: &Workspace) -> Vec<WorkspaceMutation> {
+    ///     // Drop dependencies on names that turned out not to be workspace projects
+    ///     let mut mutations = Vec::new();
+    ///     for project in &workspace.inferred_projects {
+    ///         for dependency in &project.workspace_dependencies {
+    ///             if !workspace.inferred_projects.iter().any(|p| &p.name == dependency) {
+    ///                 mutations.push(WorkspaceMutation::RemoveDependency {
+    ///                     project: project.name.clone(),
+    ///                     dependency: dependency.clone(),
+    ///                 });
+    ///             }
+    ///         }
+    ///     }
+    ///     mutations
+    /// }
+    /// # }
+    /// ```
+    fn on_scan_complete(&self, _workspace: &Workspace) -> Vec<WorkspaceMutation> {
+        Vec::new()
+    }
+
+    /// Hand over the diagnostics collected since the last call.
+    ///
+    /// **Purpose**: Report problems found in `on_file_found()`, like a manifest that doesn't
+    /// parse, without printing them. Marty calls it once discovery is finished, after
+    /// `post_discovery()`, and shows each diagnostic with the project its file belongs to.
+    /// Diagnostics returned here should not be returned again. The default has none.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{Diagnostic, InferredProject, Workspace, WorkspaceProvider};
+    /// # use std::path::Path;
+    /// # use std::sync::Mutex;
+    /// struct MyProvider {
+    ///     diagnostics: Mutex<Vec<Diagnostic>>,
+    /// }
+    ///
+    /// impl WorkspaceProvider for MyProvider {
+    /// # fn include_path_globs(&self) -> Vec<String> { vec![] }
+    ///     fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+    ///         let Ok(contents) = std::fs::read_to_string(path) else {
+    ///             self.diagnostics
+    ///                 .lock()
+    ///                 .unwrap()
+    ///                 .push(Diagnostic::error("Failed to read manifest").with_file(path));
+    ///             return None;
+    ///         };
+    ///         // ...
+    /// #       let _ = contents;
+    /// #       None
+    ///     }
+    ///
+    ///     fn take_diagnostics(&self) -> Vec<Diagnostic> {
+    ///         std::mem::take(&mut *self.diagnostics.lock().unwrap())
+    ///     }
+    /// }
+    /// ```
+    fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        Vec::new()
+    }
 }
 
 /// The main plugin trait that defines plugin metadata and capabilities.
@@ -233,7 +459,9 @@ pub trait WorkspaceProvider {
 ///     }
 /// }
 /// ```
-pub trait MartyPlugin {
+///
+/// Marty shares plugins between threads, so they must be `Send + Sync`.
+pub trait MartyPlugin: Send + Sync {
     /// Return the type of this plugin.
     ///
     /// **Purpose**: Explicitly declares the plugin's role and capabilities.
@@ -346,6 +574,52 @@ pub trait MartyPlugin {
     /// ```
     fn key(&self) -> &str;
 
+    /// Return a one-line description of what the plugin does.
+    ///
+    /// **Purpose**: Shown by `marty plugin list` and `marty plugin options`, so users can
+    /// tell what an installed plugin is for without looking it up. The default is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::MartyPlugin;
+    /// # struct MyPlugin;
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Primary }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn description(&self) -> Option<&str> {
+    ///     Some("Discovers Rust crates from Cargo workspaces")
+    /// }
+    ///
+    /// // The version and repository from the plugin's Cargo.toml
+    /// fn version(&self) -> Option<&str> {
+    ///     Some(env!("CARGO_PKG_VERSION"))
+    /// }
+    ///
+    /// fn homepage(&self) -> Option<&str> {
+    ///     Some("https://github.com/codyspate/marty")
+    /// }
+    /// # }
+    /// ```
+    fn description(&self) -> Option<&str> {
+        None
+    }
+
+    /// Return the version of the plugin, usually `env!("CARGO_PKG_VERSION")`.
+    ///
+    /// Shown next to the plugin so the version actually installed is visible, whatever the
+    /// file it was loaded from is called. The default is `None`.
+    fn version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Return where to find the plugin's documentation or source. The default is `None`.
+    fn homepage(&self) -> Option<&str> {
+        None
+    }
+
     /// Return the workspace provider implementation for this plugin.
     ///
     /// **Purpose**: Connects your plugin to the workspace scanning and project
@@ -431,4 +705,285 @@ pub trait MartyPlugin {
     fn configuration_options(&self) -> Option<JsonValue> {
         None
     }
+
+    /// Receive the `options` set for this plugin in workspace.yml.
+    ///
+    /// **Purpose**: Apply user configuration before scanning starts. Marty calls this once,
+    /// after validating the options against `configuration_options()`, and only when the
+    /// plugin entry has options. Plugins keep the values in a field with interior
+    /// mutability, since the plugin is shared. [`plugin_options`] reads them into a
+    /// config struct.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::MartyPlugin;
+    /// # use serde_json::Value;
+    /// # use std::sync::atomic::{AtomicBool, Ordering};
+    /// # struct MyPlugin { verbose: AtomicBool }
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Primary }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn configure(&self, options: &Value) {
+    ///     let verbose = options.get("verbose").and_then(Value::as_bool);
+    ///     self.verbose.store(verbose.unwrap_or(false), Ordering::Relaxed);
+    /// }
+    /// # }
+    /// ```
+    fn configure(&self, _options: &JsonValue) {}
+
+    /// Set up state kept for the whole session, once before scanning starts.
+    ///
+    /// **Purpose**: Load what many calls share only once, e.g. the workspace's root
+    /// manifest or a lockfile every `on_file_found()` call would otherwise parse again.
+    /// Marty calls this right after loading the plugin, with the workspace root and the
+    /// plugin's options from workspace.yml (`null` without options), and calls
+    /// [`shutdown()`](Self::shutdown) once it is done with the plugin.
+    ///
+    /// The default passes the options on to [`configure()`](Self::configure), so plugins
+    /// implementing this should do the same or read their options here.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::MartyPlugin;
+    /// # use serde_json::Value;
+    /// # use std::path::Path;
+    /// # use std::sync::Mutex;
+    /// # struct MyPlugin { lockfile: Mutex<Option<String>> }
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Primary }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn initialize(&self, workspace_root: &Path, options: &Value) {
+    ///     let lockfile = std::fs::read_to_string(workspace_root.join("Cargo.lock")).ok();
+    ///     *self.lockfile.lock().unwrap() = lockfile;
+    ///     if !options.is_null() {
+    ///         self.configure(options);
+    ///     }
+    /// }
+    ///
+    /// fn shutdown(&self) {
+    ///     self.lockfile.lock().unwrap().take();
+    /// }
+    /// # }
+    /// ```
+    fn initialize(&self, _workspace_root: &Path, options: &JsonValue) {
+        if !options.is_null() {
+            self.configure(options);
+        }
+    }
+
+    /// Release what [`initialize()`](Self::initialize) set up, e.g. flush a cache to disk.
+    ///
+    /// Marty calls this once it is done with the plugin, before unloading it. Nothing is
+    /// called on the plugin afterwards until it is initialized again.
+    fn shutdown(&self) {}
+
+    /// Return tasks worth defining for the projects this plugin discovers.
+    ///
+    /// **Purpose**: Help users get started with a technology, e.g. `lint` and `package` for
+    /// Helm charts. Marty doesn't run these on its own; `marty plugin options` prints them
+    /// as a task file to copy into the workspace. The default suggests nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{MartyPlugin, SuggestedTask};
+    /// # struct MyPlugin;
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Primary }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn suggested_tasks(&self) -> Vec<SuggestedTask> {
+    ///     vec![SuggestedTask {
+    ///         name: "test".to_string(),
+    ///         command: "mytool test".to_string(),
+    ///         description: Some("Run the project's tests".to_string()),
+    ///         dependencies: vec!["^build".to_string()],
+    ///     }]
+    /// }
+    /// # }
+    /// ```
+    fn suggested_tasks(&self) -> Vec<SuggestedTask> {
+        Vec::new()
+    }
+
+    /// Return tasks Marty defines for every project this plugin discovers.
+    ///
+    /// **Purpose**: Make a workspace usable without task files, e.g. `build` and `test`
+    /// running `cargo build` and `cargo test` in each crate. Unlike
+    /// [`suggested_tasks()`](Self::suggested_tasks), these run as they are. A task of the
+    /// same name in the workspace's task files or in a project's marty.yml takes
+    /// precedence. The default defines nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{MartyPlugin, SuggestedTask};
+    /// # struct MyPlugin;
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Primary }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn default_tasks(&self) -> Vec<SuggestedTask> {
+    ///     vec![SuggestedTask {
+    ///         name: "build".to_string(),
+    ///         command: "mytool build".to_string(),
+    ///         description: Some("Build the project".to_string()),
+    ///         dependencies: vec![],
+    ///     }]
+    /// }
+    /// # }
+    /// ```
+    fn default_tasks(&self) -> Vec<SuggestedTask> {
+        Vec::new()
+    }
+
+    /// Called once discovery is finished, with every project and dependency known.
+    ///
+    /// **Purpose**: Let plugins act on the final workspace, typically Supplemental plugins
+    /// keeping tool configuration in sync with the dependency graph, like the TypeScript
+    /// plugin rewriting `references` in tsconfig.json. Marty calls it on every plugin in
+    /// load order, after all `on_scan_complete()` mutations are applied.
+    ///
+    /// Returns notes for the user about what the plugin changed, e.g. the files it rewrote;
+    /// Marty prints them. The default does nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{MartyPlugin, Workspace};
+    /// # struct MyPlugin;
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Supplemental }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn post_discovery(&self, workspace: &Workspace) -> Vec<String> {
+    ///     let mut notes = Vec::new();
+    ///     for project in &workspace.inferred_projects {
+    ///         let config = project.project_dir.join("deps.txt");
+    ///         let contents = project.workspace_dependencies.join("\n");
+    ///         if std::fs::read_to_string(&config).ok().as_deref() != Some(contents.as_str())
+    ///             && std::fs::write(&config, &contents).is_ok()
+    ///         {
+    ///             notes.push(format!("Updated {}", config.display()));
+    ///         }
+    ///     }
+    ///     notes
+    /// }
+    /// # }
+    /// ```
+    fn post_discovery(&self, _workspace: &Workspace) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Called after a new project was created in the workspace, e.g. generated from a
+    /// template.
+    ///
+    /// **Purpose**: Register the project with the tool the plugin handles so discovery finds
+    /// it right away, like the Cargo plugin adding it to the `[workspace] members` of the
+    /// root Cargo.toml. `project_dir` is the new project's directory and `workspace` the
+    /// workspace discovered before it existed. Marty calls it on every plugin in load order.
+    ///
+    /// Returns notes for the user about what the plugin changed; Marty prints them. The
+    /// default does nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{MartyPlugin, Workspace};
+    /// # use std::path::Path;
+    /// # struct MyPlugin;
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Primary }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn on_project_created(&self, workspace: &Workspace, project_dir: &Path) -> Vec<String> {
+    ///     let list = workspace.root.join("projects.txt");
+    ///     let Ok(relative) = project_dir.strip_prefix(&workspace.root) else {
+    ///         return Vec::new();
+    ///     };
+    ///     let mut contents = std::fs::read_to_string(&list).unwrap_or_default();
+    ///     contents.push_str(&format!("{}\n", relative.display()));
+    ///     match std::fs::write(&list, contents) {
+    ///         Ok(()) => vec![format!("Added {} to projects.txt", relative.display())],
+    ///         Err(_) => Vec::new(),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    fn on_project_created(&self, _workspace: &Workspace, _project_dir: &Path) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Run a task on one project natively, instead of Marty running a shell command.
+    ///
+    /// **Purpose**: Implement tasks that are easier to do with the plugin's own knowledge
+    /// of the workspace, like the TypeScript plugin's `sync-references`. Marty calls it
+    /// for tasks that name the plugin with `plugin: <key>` in a task file, once per
+    /// project, possibly from several threads at once. `workspace` is the discovered
+    /// workspace. The default fails, since the plugin implements no tasks.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::{MartyPlugin, Project, TaskOutcome, Workspace};
+    /// # struct MyPlugin;
+    /// # impl MartyPlugin for MyPlugin {
+    /// # fn plugin_type(&self) -> marty_plugin_protocol::PluginType { marty_plugin_protocol::PluginType::Supplemental }
+    /// # fn name(&self) -> &str { "" }
+    /// # fn key(&self) -> &str { "" }
+    /// # fn workspace_provider(&self) -> &dyn marty_plugin_protocol::WorkspaceProvider { todo!() }
+    /// fn run_task(&self, task: &str, project: &Project, _workspace: &Workspace) -> TaskOutcome {
+    ///     match task {
+    ///         "write-deps" => {
+    ///             let path = project.project_dir.join("deps.txt");
+    ///             match std::fs::write(&path, project.dependencies.join("\n")) {
+    ///                 Ok(()) => TaskOutcome::success(vec![format!("Wrote {}", path.display())]),
+    ///                 Err(e) => TaskOutcome::failure(format!("Failed to write {}: {}", path.display(), e)),
+    ///             }
+    ///         }
+    ///         _ => TaskOutcome::failure(format!("Unknown task '{}'", task)),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    fn run_task(&self, task: &str, _project: &Project, _workspace: &Workspace) -> TaskOutcome {
+        TaskOutcome::failure(format!("{} doesn't implement task '{}'", self.name(), task))
+    }
+}
+
+/// Read the options passed to [`MartyPlugin::configure`] into the plugin's config type.
+///
+/// Marty validates options against `configuration_options()` before handing them over,
+/// so an error here means the schema and the type disagree.
+///
+/// # Example
+///
+/// ```rust
+/// use marty_plugin_protocol::plugin_options;
+/// use serde::Deserialize;
+/// use serde_json::json;
+///
+/// #[derive(Default, Deserialize)]
+/// #[serde(rename_all = "camelCase")]
+/// struct MyConfig {
+///     #[serde(default)]
+///     project_per_target: bool,
+/// }
+///
+/// let config: MyConfig = plugin_options(&json!({ "projectPerTarget": true })).unwrap();
+/// assert!(config.project_per_target);
+/// assert!(plugin_options::<MyConfig>(&json!({ "projectPerTarget": "yes" })).is_err());
+/// ```
+pub fn plugin_options<T: DeserializeOwned>(options: &JsonValue) -> Result<T, serde_json::Error> {
+    T::deserialize(options)
 }
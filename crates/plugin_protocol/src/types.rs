@@ -4,7 +4,10 @@
 //! - [`PluginType`] - Defines plugin capabilities (Primary, Supplemental, Hook)
 //! - [`Project`] - Explicit projects with marty.yml configuration
 //! - [`InferredProject`] - Projects discovered automatically by plugins
+//! - [`ProjectKind`] - What a project produces: an application, a library, ...
 //! - [`Workspace`] - The workspace context containing all projects
+//! - [`SuggestedTask`] - A task a plugin recommends for the projects it discovers
+//! - [`TaskOutcome`] - The result of a task a plugin runs itself
 //! - [`PluginKey`] - Type-safe plugin identifier
 
 use serde::{Deserialize, Serialize};
@@ -155,6 +158,7 @@ pub struct Project {
 ///         project_dir,
 ///         discovered_by: "my-plugin".to_string(),
 ///         workspace_dependencies,
+///         kind: None,
 ///     })
 /// }
 /// # }
@@ -173,7 +177,8 @@ pub struct InferredProject {
     ///
     /// This is typically the parent directory of the configuration file that was detected.
     /// For example, if you found `/workspace/packages/lib-a/package.json`, this would be
-    /// `/workspace/packages/lib-a`.
+    /// `/workspace/packages/lib-a`. Projects outside the workspace root are ignored with a
+    /// warning.
     pub project_dir: PathBuf,
 
     /// The plugin key that discovered this project.
@@ -221,10 +226,75 @@ pub struct InferredProject {
     ///         "utils".to_string(),           // ✅ Another workspace project
     ///         // NOT "express", "lodash", etc. - those are external dependencies
     ///     ],
+    ///     kind: None,
     /// };
     /// ```
     #[serde(default)]
     pub workspace_dependencies: Vec<String>,
+
+    /// What the project produces, if the plugin can tell.
+    ///
+    /// **Examples**:
+    /// - For Cargo: `Tool` for `xtask`, `Application` for crates with a binary target,
+    ///   `Library` otherwise
+    /// - For NPM/PNPM: `Tool` for packages with a `bin`, `Application` for other private
+    ///   packages, `Library` for published ones
+    ///
+    /// Leave it `None` when unsure; a `kind` in the project's marty.yml takes precedence.
+    #[serde(default)]
+    pub kind: Option<ProjectKind>,
+}
+
+/// Classification of a project, used to filter listings and graphs, in dependency rules
+/// (`kind:library`) and to bind task files to kinds of projects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectKind {
+    /// Something deployed or run by users, like a service, web app or binary
+    Application,
+    /// Code consumed by other projects
+    Library,
+    /// Developer tooling, like a CLI, code generator or build script
+    Tool,
+    /// A project holding only tests, like an end-to-end suite
+    Test,
+}
+
+impl ProjectKind {
+    pub const ALL: [Self; 4] = [Self::Application, Self::Library, Self::Tool, Self::Test];
+
+    /// The lowercase name used in configuration files
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Application => "application",
+            Self::Library => "library",
+            Self::Tool => "tool",
+            Self::Test => "test",
+        }
+    }
+}
+
+impl std::fmt::Display for ProjectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ProjectKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown project kind '{}', expected one of: application, library, tool, test",
+                    s
+                )
+            })
+    }
 }
 
 /// Represents the entire workspace context provided to plugins during discovery.
@@ -280,6 +350,292 @@ pub struct Workspace {
     pub inferred_projects: Vec<InferredProject>,
 }
 
+/// A change a plugin requests after the whole workspace has been scanned.
+///
+/// **Purpose**: `on_file_found()` only sees one file at a time, so some facts can only be
+/// settled once every project is known, e.g. resolving a PNPM alias like
+/// `"ui": "workspace:@acme/design-system@*"` to the project named `@acme/design-system`.
+/// Plugins return these from [`WorkspaceProvider::on_scan_complete`](crate::WorkspaceProvider::on_scan_complete)
+/// and Marty applies them to the inferred projects before building the dependency graph.
+///
+/// Projects are referenced by name. Mutations naming unknown projects are ignored with a warning.
+///
+/// **Example**:
+/// ```rust
+/// # use marty_plugin_protocol::WorkspaceMutation;
+/// let mutation = WorkspaceMutation::AddDependency {
+///     project: "web".to_string(),
+///     dependency: "@acme/design-system".to_string(),
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkspaceMutation {
+    /// Replace the workspace dependencies of a project
+    SetDependencies {
+        project: String,
+        dependencies: Vec<String>,
+    },
+    /// Add a workspace dependency to a project
+    AddDependency { project: String, dependency: String },
+    /// Remove a workspace dependency from a project
+    RemoveDependency { project: String, dependency: String },
+    /// Drop an inferred project, e.g. one discovered twice.
+    ///
+    /// When `discovered_by` is set, only entries discovered by that plugin are removed.
+    RemoveProject {
+        project: String,
+        #[serde(default)]
+        discovered_by: Option<String>,
+    },
+}
+
+/// A task a plugin recommends for the projects it discovers, such as `lint` for charts.
+///
+/// **Purpose**: Marty never adds tasks on its own; `marty plugin options` shows the
+/// suggestions as a task file the user can copy into `.marty/tasks/`. The same type
+/// describes the tasks a plugin defines outright through `MartyPlugin::default_tasks`.
+///
+/// **Example**:
+/// ```rust
+/// # use marty_plugin_protocol::SuggestedTask;
+/// let task = SuggestedTask {
+///     name: "lint".to_string(),
+///     command: "helm lint \"$MARTY_PROJECT_DIR\"".to_string(),
+///     description: Some("Check the chart for problems".to_string()),
+///     dependencies: vec![],
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuggestedTask {
+    pub name: String,
+    /// Shell command run in the project's directory
+    pub command: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Task dependencies, in the same syntax as a task file's `dependencies`
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+/// The result of a task a plugin runs itself through `MartyPlugin::run_task`.
+///
+/// **Example**:
+/// ```rust
+/// # use marty_plugin_protocol::TaskOutcome;
+/// let outcome = TaskOutcome::success(vec!["Updated tsconfig.json".to_string()]);
+/// assert!(outcome.success);
+/// assert!(!TaskOutcome::failure("tsconfig.json is missing").success);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskOutcome {
+    pub success: bool,
+    /// Lines Marty prints as the task's output
+    #[serde(default)]
+    pub output: Vec<String>,
+}
+
+impl TaskOutcome {
+    pub fn success(output: Vec<String>) -> Self {
+        Self {
+            success: true,
+            output,
+        }
+    }
+
+    /// A failed run, with `message` explaining why
+    pub fn failure(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            output: vec![message.into()],
+        }
+    }
+}
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Info,
+}
+
+impl std::fmt::Display for DiagnosticLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticLevel::Error => write!(f, "error"),
+            DiagnosticLevel::Warning => write!(f, "warning"),
+            DiagnosticLevel::Info => write!(f, "info"),
+        }
+    }
+}
+
+/// A range of a file, with 1-based lines and columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+impl Span {
+    /// A whole line
+    pub fn line(line: u32) -> Self {
+        Self {
+            start_line: line,
+            start_column: 1,
+            end_line: line,
+            end_column: 1,
+        }
+    }
+
+    /// The span of the bytes `range` of `text`, as parsers report error locations
+    ///
+    /// ```rust
+    /// # use marty_plugin_protocol::Span;
+    /// let span = Span::from_byte_range("[package]\nname = 1\n", 17..18);
+    /// assert_eq!((span.start_line, span.start_column), (2, 8));
+    /// ```
+    pub fn from_byte_range(text: &str, range: std::ops::Range<usize>) -> Self {
+        let position = |offset: usize| {
+            let before = &text[..text.floor_char_boundary(offset)];
+            let line = before.matches('\n').count() as u32 + 1;
+            let column = before
+                .rsplit('\n')
+                .next()
+                .map_or(0, |line| line.chars().count()) as u32
+                + 1;
+            (line, column)
+        };
+        let (start_line, start_column) = position(range.start);
+        let (end_line, end_column) = position(range.end);
+        Self {
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+/// A problem a plugin noticed while scanning, such as a manifest it couldn't parse.
+///
+/// **Purpose**: Plugins hand diagnostics to Marty through `WorkspaceProvider::take_diagnostics`
+/// instead of printing them, so Marty can show them with the project they belong to, or
+/// emit them as JSON events.
+///
+/// **Example**:
+/// ```rust
+/// # use marty_plugin_protocol::{Diagnostic, DiagnosticLevel, Span};
+/// let diagnostic = Diagnostic::warning("`name` is missing, using the directory name")
+///     .with_file("/workspace/packages/web/package.json")
+///     .with_span(Span::line(1));
+/// assert_eq!(diagnostic.level, DiagnosticLevel::Warning);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub level: DiagnosticLevel,
+    pub message: String,
+    /// The file the diagnostic is about, absolute or relative to the workspace root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Where in `file` the problem is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(level: DiagnosticLevel, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            message: message.into(),
+            file: None,
+            span: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(DiagnosticLevel::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(DiagnosticLevel::Warning, message)
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(DiagnosticLevel::Info, message)
+    }
+
+    /// Set the file the diagnostic is about
+    #[must_use]
+    pub fn with_file(mut self, file: impl AsRef<std::path::Path>) -> Self {
+        self.file = Some(file.as_ref().display().to_string());
+        self
+    }
+
+    /// Set where in the file the problem is
+    #[must_use]
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+}
+
+/// Why a plugin couldn't process a file it was offered, such as a manifest that doesn't parse.
+///
+/// **Purpose**: Returned from `WorkspaceProvider::try_on_file_found` to tell a broken file
+/// apart from one that simply isn't a project. Marty reports it as an error about the file
+/// and goes on scanning.
+///
+/// **Example**:
+/// ```rust
+/// # use marty_plugin_protocol::{PluginError, Span};
+/// let error = PluginError::new("expected `=` after the key").with_span(Span::line(4));
+/// assert_eq!(error.to_string(), "expected `=` after the key");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginError {
+    pub message: String,
+    /// Where in the file the problem is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+}
+
+impl PluginError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Set where in the file the problem is
+    #[must_use]
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// The error as a diagnostic about `file`
+    pub fn into_diagnostic(self, file: &std::path::Path) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.message).with_file(file);
+        match self.span {
+            Some(span) => diagnostic.with_span(span),
+            None => diagnostic,
+        }
+    }
+}
+
+impl std::fmt::Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for PluginError {}
+
 /// Type-safe identifier for plugins.
 ///
 /// **Purpose**: Ensures plugin keys don't contain whitespace or invalid characters.
@@ -0,0 +1,222 @@
+//! WebAssembly component plugin interface.
+//!
+//! Built for `wasm32`, [`export_plugin!`](crate::dylib::export_plugin) also exports the
+//! plugin as a WebAssembly component implementing the world in [`WIT`], so the same crate
+//! can ship as a `.wasm` plugin that Marty runs sandboxed in wasmtime. The component's
+//! `call` export runs the same [`methods`](crate::dylib::methods) as `plugin_call` does for
+//! dynamic libraries, with the arguments and the JSON response passed through the
+//! component model's canonical ABI.
+//!
+//! ## Building a Component
+//!
+//! Build the crate for WASI, then wrap the module into a component with
+//! [wasm-tools](https://github.com/bytecodealliance/wasm-tools), using the `wit` directory
+//! of this crate and the WASI reactor adapter published with wasmtime releases:
+//!
+//! ```bash
+//! cargo build --lib --release --target wasm32-wasip1
+//! wasm-tools component embed wit/plugin.wit --world plugin \
+//!     target/wasm32-wasip1/release/my_plugin.wasm -o my_plugin.embed.wasm
+//! wasm-tools component new my_plugin.embed.wasm \
+//!     --adapt wasi_snapshot_preview1.reactor.wasm -o my_plugin.wasm
+//! ```
+//!
+//! ## Sandbox
+//!
+//! Components can only read and write files under the workspace root, which they see at
+//! the same path as on the host. They have no network access, environment variables or
+//! other directories, and what they print to stderr is shown to the user.
+
+use std::alloc::{self, Layout};
+
+use crate::dylib::{dispatch, CallError};
+use crate::traits::MartyPlugin;
+
+/// The WIT world `.wasm` plugins implement
+pub const WIT: &str = include_str!("../wit/plugin.wit");
+
+/// The result of `call` in guest memory, laid out as the canonical ABI lowers
+/// `result<list<u8>, s32>` on `wasm32`
+#[repr(C)]
+pub struct CallResult {
+    /// 0 for a response, 1 for an error
+    tag: u8,
+    /// Pointer to the response, or the [`CallError`] code
+    first: usize,
+    /// Length of the response
+    second: usize,
+}
+
+/// Run the `call` export: dispatch `method` to `plugin` and return the result, which stays
+/// allocated until the host hands it to [`post_call`].
+///
+/// # Safety
+///
+/// The arguments must have been allocated through [`cabi_realloc`] with alignment 1 and
+/// the given lengths. They are freed here.
+pub unsafe fn call(
+    plugin: &dyn MartyPlugin,
+    method_ptr: *mut u8,
+    method_len: usize,
+    input_ptr: *mut u8,
+    input_len: usize,
+) -> *mut CallResult {
+    let method = Vec::from_raw_parts(method_ptr, method_len, method_len);
+    let input = Vec::from_raw_parts(input_ptr, input_len, input_len);
+    let response = match std::str::from_utf8(&method) {
+        Ok(method) => dispatch(plugin, method, &input),
+        Err(_) => Err(CallError::InvalidInput),
+    };
+
+    let result = match response {
+        Ok(response) => {
+            let len = response.len();
+            let ptr = Box::into_raw(response.into_boxed_slice());
+            CallResult {
+                tag: 0,
+                first: ptr as *mut u8 as usize,
+                second: len,
+            }
+        }
+        Err(e) => CallResult {
+            tag: 1,
+            first: e.code() as i32 as u32 as usize,
+            second: 0,
+        },
+    };
+    Box::into_raw(Box::new(result))
+}
+
+/// Free a result returned by [`call`] once the host has read it
+///
+/// # Safety
+///
+/// `result` must come from [`call`] and not have been freed yet.
+pub unsafe fn post_call(result: *mut CallResult) {
+    let result = Box::from_raw(result);
+    if result.tag == 0 {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            result.first as *mut u8,
+            result.second,
+        )));
+    }
+}
+
+/// Allocate or grow memory for the host, as the canonical ABI's `cabi_realloc`
+///
+/// # Safety
+///
+/// `old_ptr` must be null or come from an earlier call with `old_len` and `align`, and
+/// `align` must be a power of two.
+pub unsafe fn cabi_realloc(
+    old_ptr: *mut u8,
+    old_len: usize,
+    align: usize,
+    new_len: usize,
+) -> *mut u8 {
+    if new_len == 0 {
+        // A dangling, aligned pointer, as for empty vectors
+        return align as *mut u8;
+    }
+    let layout;
+    let ptr = if old_len == 0 {
+        layout = Layout::from_size_align_unchecked(new_len, align);
+        alloc::alloc(layout)
+    } else {
+        layout = Layout::from_size_align_unchecked(old_len, align);
+        alloc::realloc(old_ptr, layout, new_len)
+    };
+    if ptr.is_null() {
+        alloc::handle_alloc_error(layout);
+    }
+    ptr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PluginType, WorkspaceProvider};
+
+    struct TestPlugin;
+
+    impl WorkspaceProvider for TestPlugin {
+        fn include_path_globs(&self) -> Vec<String> {
+            vec!["**/test.json".to_string()]
+        }
+
+        fn on_file_found(
+            &self,
+            _workspace: &crate::Workspace,
+            _path: &std::path::Path,
+        ) -> Option<crate::InferredProject> {
+            None
+        }
+    }
+
+    impl MartyPlugin for TestPlugin {
+        fn plugin_type(&self) -> PluginType {
+            PluginType::Primary
+        }
+
+        fn name(&self) -> &str {
+            "Test"
+        }
+
+        fn key(&self) -> &str {
+            "test"
+        }
+
+        fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+            self
+        }
+    }
+
+    /// Call `method` the way the host does: copy the arguments into memory from
+    /// `cabi_realloc`, read the result and free it
+    fn call_like_host(method: &str, input: &[u8]) -> Result<Vec<u8>, isize> {
+        unsafe {
+            let copy = |bytes: &[u8]| {
+                let ptr = cabi_realloc(std::ptr::null_mut(), 0, 1, bytes.len());
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+                ptr
+            };
+            let result = call(
+                &TestPlugin,
+                copy(method.as_bytes()),
+                method.len(),
+                copy(input),
+                input.len(),
+            );
+            let outcome = match (*result).tag {
+                0 => Ok(
+                    std::slice::from_raw_parts((*result).first as *const u8, (*result).second)
+                        .to_vec(),
+                ),
+                _ => Err((*result).first as u32 as i32 as isize),
+            };
+            post_call(result);
+            outcome
+        }
+    }
+
+    #[test]
+    fn test_call_uses_the_dylib_methods() {
+        assert_eq!(call_like_host("key", b"").unwrap(), b"\"test\"");
+        assert_eq!(
+            call_like_host("include_globs", b"").unwrap(),
+            b"[\"**/test.json\"]"
+        );
+        assert_eq!(
+            call_like_host("on_file_found", b"{\"path\":\"a/test.json\"}").unwrap(),
+            b"null"
+        );
+        assert_eq!(
+            call_like_host("on_scan_complete", b"not json"),
+            Err(CallError::InvalidInput.code())
+        );
+        assert_eq!(
+            call_like_host("unknown", b""),
+            Err(CallError::UnknownMethod.code())
+        );
+    }
+}
@@ -5,7 +5,7 @@
 //! projects in the same workspace (for task ordering), not external packages.
 
 use marty_plugin_protocol::{
-    dylib::export_plugin, InferredProject, MartyPlugin, Workspace, WorkspaceProvider,
+    dylib::export_plugin, InferredProject, MartyPlugin, PluginType, Workspace, WorkspaceProvider,
 };
 use serde_json::{json, Value as JsonValue};
 use std::path::Path;
@@ -74,11 +74,16 @@ impl WorkspaceProvider for PythonWorkspaceProvider {
             project_dir,
             discovered_by: "python-plugin".to_string(),
             workspace_dependencies,
+            kind: None,
         })
     }
 }
 
 impl MartyPlugin for PythonPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
     fn name(&self) -> &str {
         "Python Requirements Plugin"
     }
@@ -87,6 +92,18 @@ impl MartyPlugin for PythonPlugin {
         "python"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("Example plugin discovering Python projects from requirements.txt")
+    }
+
+    fn version(&self) -> Option<&str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        Some("https://github.com/codyspate/marty")
+    }
+
     fn workspace_provider(&self) -> &dyn WorkspaceProvider {
         &PythonWorkspaceProvider
     }
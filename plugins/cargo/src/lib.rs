@@ -1,28 +1,38 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use marty_plugin_protocol::{
-    dylib::export_plugin, InferredProject, InferredProjectMessage, MartyPlugin, PluginType,
-    Workspace, WorkspaceProvider,
+    InferredProject, InferredProjectMessage, MartyPlugin, PluginError, PluginType, ProjectKind,
+    ProjectMessage, Span, SuggestedTask, Workspace, WorkspaceProvider,
 };
 use serde_json::{json, Value as JsonValue};
 use toml::Value;
 
 /// Main Cargo plugin struct
-pub struct CargoPlugin;
+pub struct CargoPlugin {
+    provider: CargoWorkspaceProvider,
+}
 
 /// Workspace provider for Cargo projects  
-pub struct CargoWorkspaceProvider;
+pub struct CargoWorkspaceProvider {
+    /// `projectPerTarget`: one project per binary target of packages with several
+    project_per_target: AtomicBool,
+}
 
 impl Default for CargoPlugin {
     fn default() -> Self {
-        Self
+        Self::new()
     }
 }
 
 impl CargoPlugin {
     pub const fn new() -> Self {
-        Self
+        Self {
+            provider: CargoWorkspaceProvider {
+                project_per_target: AtomicBool::new(false),
+            },
+        }
     }
 }
 
@@ -35,21 +45,67 @@ impl WorkspaceProvider for CargoWorkspaceProvider {
         vec!["**/target/**".to_string()]
     }
 
-    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
-        if path.file_name()?.to_str()? != "Cargo.toml" {
-            return None;
+    fn on_file_found(&self, workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        self.try_on_file_found(workspace, path).ok().flatten()
+    }
+
+    fn try_on_file_found(
+        &self,
+        _workspace: &Workspace,
+        path: &Path,
+    ) -> Result<Option<InferredProject>, PluginError> {
+        if path.file_name().and_then(|name| name.to_str()) != Some("Cargo.toml") {
+            return Ok(None);
         }
 
-        let contents = std::fs::read_to_string(path).ok()?;
-        let message = process_manifest(path, &contents)?;
+        let contents = read_manifest(path)?;
+        let Some(message) = process_manifest(path, &contents) else {
+            return Ok(None);
+        };
 
-        Some(InferredProject {
+        Ok(Some(InferredProject {
             name: message.name,
             project_dir: std::path::PathBuf::from(message.project_dir),
             discovered_by: message.discovered_by,
             workspace_dependencies: message.workspace_dependencies,
-        })
+            kind: message.kind,
+        }))
     }
+
+    fn on_file_found_messages(
+        &self,
+        workspace: &Workspace,
+        path: &Path,
+    ) -> Result<Vec<ProjectMessage>, PluginError> {
+        if self.project_per_target.load(Ordering::Relaxed)
+            && path.file_name().and_then(|name| name.to_str()) == Some("Cargo.toml")
+        {
+            let contents = read_manifest(path)?;
+            if let Some(messages) = process_manifest_per_target(path, &contents) {
+                return Ok(messages
+                    .into_iter()
+                    .map(|project| ProjectMessage::AddProject { project })
+                    .collect());
+            }
+        }
+
+        let project = self.try_on_file_found(workspace, path)?;
+        Ok(project.into_iter().map(ProjectMessage::from).collect())
+    }
+}
+
+/// Read a manifest, failing for files that can't be read or aren't valid TOML
+fn read_manifest(path: &Path) -> Result<String, PluginError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| PluginError::new(format!("Failed to read manifest: {}", e)))?;
+    if let Err(e) = toml::from_str::<Value>(&contents) {
+        let error = PluginError::new(format!("Invalid Cargo.toml: {}", e.message()));
+        return Err(match e.span() {
+            Some(span) => error.with_span(Span::from_byte_range(&contents, span)),
+            None => error,
+        });
+    }
+    Ok(contents)
 }
 
 impl MartyPlugin for CargoPlugin {
@@ -65,8 +121,27 @@ impl MartyPlugin for CargoPlugin {
         "cargo"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("Discovers Rust crates and their path dependencies from Cargo.toml")
+    }
+
+    fn version(&self) -> Option<&str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        Some("https://github.com/codyspate/marty")
+    }
+
     fn workspace_provider(&self) -> &dyn WorkspaceProvider {
-        &CargoWorkspaceProvider
+        &self.provider
+    }
+
+    fn configure(&self, options: &JsonValue) {
+        let per_target = options.get("projectPerTarget").and_then(JsonValue::as_bool);
+        self.provider
+            .project_per_target
+            .store(per_target.unwrap_or(false), Ordering::Relaxed);
     }
 
     fn configuration_options(&self) -> Option<JsonValue> {
@@ -88,15 +163,189 @@ impl MartyPlugin for CargoPlugin {
                         "type": "string"
                     },
                     "default": []
+                },
+                "projectPerTarget": {
+                    "type": "boolean",
+                    "description": "Infer one project per binary target, named <package>-<bin>, for packages with several binaries",
+                    "default": false
                 }
             },
             "additionalProperties": false
         }))
     }
+
+    fn on_project_created(&self, workspace: &Workspace, project_dir: &Path) -> Vec<String> {
+        if !project_dir.join("Cargo.toml").is_file() {
+            return Vec::new();
+        }
+        let Some(member) = relative_member(&workspace.root, project_dir) else {
+            return Vec::new();
+        };
+        let manifest = workspace.root.join("Cargo.toml");
+        let Some(updated) = std::fs::read_to_string(&manifest)
+            .ok()
+            .and_then(|contents| add_workspace_member(&contents, &member))
+        else {
+            return Vec::new();
+        };
+        match std::fs::write(&manifest, updated) {
+            Ok(()) => vec![format!(
+                "Added {} to the workspace members in Cargo.toml",
+                member
+            )],
+            Err(e) => vec![format!("Failed to add {} to Cargo.toml: {}", member, e)],
+        }
+    }
+
+    fn default_tasks(&self) -> Vec<SuggestedTask> {
+        let task = |name: &str, command: &str, description: &str| SuggestedTask {
+            name: name.to_string(),
+            command: format!(
+                r#"{} --manifest-path "$MARTY_PROJECT_DIR/Cargo.toml""#,
+                command
+            ),
+            description: Some(description.to_string()),
+            dependencies: Vec::new(),
+        };
+        vec![
+            task("build", "cargo build", "Build the crate"),
+            task("test", "cargo test", "Run the crate's tests"),
+            task("lint", "cargo clippy", "Check the crate with Clippy"),
+        ]
+    }
 }
 
 // Export the plugin using the dynamic library interface
-export_plugin!(CargoPlugin);
+#[cfg(feature = "dylib")]
+marty_plugin_protocol::dylib::export_plugin!(CargoPlugin);
+
+/// `project_dir` relative to the workspace root, with `/` separators
+fn relative_member(root: &Path, project_dir: &Path) -> Option<String> {
+    let relative = project_dir.strip_prefix(root).ok()?;
+    let parts: Option<Vec<&str>> = relative
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect();
+    let member = parts?.join("/");
+    (!member.is_empty()).then_some(member)
+}
+
+/// Add `member` to the `members` array of a root manifest's `[workspace]`, keeping the
+/// manifest's layout. `None` if there is no such array, or it or `exclude` already
+/// covers the member.
+fn add_workspace_member(contents: &str, member: &str) -> Option<String> {
+    let manifest: Value = toml::from_str(contents).ok()?;
+    let workspace = manifest.get("workspace")?;
+    workspace.get("members")?.as_array()?;
+    let covered = |key: &str| {
+        workspace
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .any(|pattern| matches_member_pattern(pattern, member))
+    };
+    if covered("members") || covered("exclude") {
+        return None;
+    }
+
+    // Find the `members = [` of the [workspace] table
+    let mut table = "";
+    let mut offset = 0;
+    let mut open = None;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && !trimmed.starts_with("[[") {
+            table = trimmed.trim_matches(|c| c == '[' || c == ']').trim();
+        } else if table == "workspace" {
+            if let Some(value) = trimmed.strip_prefix("members") {
+                if value.trim_start().starts_with('=') {
+                    open = line.find('[').map(|i| offset + i);
+                    break;
+                }
+            }
+        }
+        offset += line.len();
+    }
+    let open = open?;
+    let close = open + contents[open..].find(']')?;
+
+    let items = contents[open + 1..close].trim_end();
+    let items_end = open + 1 + items.len();
+    let trailing_comma = items.ends_with(',');
+    let quoted = Value::String(member.to_string()).to_string();
+    let insertion = if !contents[open..close].contains('\n') {
+        match (items.trim().is_empty(), trailing_comma) {
+            (true, _) => quoted,
+            (false, true) => format!(" {}", quoted),
+            (false, false) => format!(", {}", quoted),
+        }
+    } else {
+        let last_line = items.rsplit('\n').next().unwrap_or_default();
+        let indent = match last_line.trim() {
+            "" | "[" => "    ",
+            _ => &last_line[..last_line.len() - last_line.trim_start().len()],
+        };
+        let separator = if trailing_comma || items.trim().is_empty() {
+            ""
+        } else {
+            ","
+        };
+        let comma = if trailing_comma { "," } else { "" };
+        format!("{}\n{}{}{}", separator, indent, quoted, comma)
+    };
+    let updated = format!(
+        "{}{}{}",
+        &contents[..items_end],
+        insertion,
+        &contents[items_end..]
+    );
+
+    // Only write manifests that still parse and now list the member
+    let check: Value = toml::from_str(&updated).ok()?;
+    let listed = check["workspace"]["members"]
+        .as_array()?
+        .iter()
+        .any(|m| m.as_str() == Some(member));
+    listed.then_some(updated)
+}
+
+/// Whether `path` matches a workspace member pattern like `crates/*`
+fn matches_member_pattern(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .split('/')
+        .collect();
+    let path: Vec<&str> = path.split('/').collect();
+    matches_segments(&pattern, &path)
+}
+
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((&"**", rest)), _) => {
+            matches_segments(rest, path)
+                || (!path.is_empty() && matches_segments(pattern, &path[1..]))
+        }
+        (Some((segment_pattern, pattern_rest)), Some((segment, path_rest))) => {
+            matches_segment(segment_pattern, segment) && matches_segments(pattern_rest, path_rest)
+        }
+        _ => false,
+    }
+}
+
+fn matches_segment(pattern: &str, segment: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == segment,
+        Some((prefix, rest)) => segment.strip_prefix(prefix).is_some_and(|tail| {
+            (0..=tail.len())
+                .filter(|&i| tail.is_char_boundary(i))
+                .any(|i| matches_segment(rest, &tail[i..]))
+        }),
+    }
+}
 
 pub fn process_manifest(
     manifest_path: &Path,
@@ -109,17 +358,117 @@ pub fn process_manifest(
     let project_dir = manifest_path.parent()?.to_path_buf();
     let manifest = parse_manifest(manifest_contents, &project_dir)?;
 
-    Some(InferredProjectMessage::new(
+    let message = InferredProjectMessage::new(
         manifest.package_name,
         project_dir.display().to_string(),
         "cargo",
         manifest.workspace_dependencies,
-    ))
+    );
+    Some(match manifest.kind {
+        Some(kind) => message.with_kind(kind),
+        None => message,
+    })
+}
+
+/// The projects of a package with several binary targets when every target is its own
+/// project, or `None` for other manifests.
+///
+/// Each binary becomes an application named `<package>-<bin>`. A library target keeps the
+/// package's name, and the binaries depend on it.
+pub fn process_manifest_per_target(
+    manifest_path: &Path,
+    manifest_contents: &str,
+) -> Option<Vec<InferredProjectMessage>> {
+    let project_dir = manifest_path.parent()?.to_path_buf();
+    let manifest_value: Value = toml::from_str(manifest_contents).ok()?;
+    let bins = binary_targets(&manifest_value, &project_dir);
+    if bins.len() < 2 {
+        return None;
+    }
+    let manifest = parse_manifest(manifest_contents, &project_dir)?;
+
+    let mut projects = Vec::new();
+    let mut bin_dependencies = manifest.workspace_dependencies.clone();
+    if has_library(&manifest_value, &project_dir) {
+        projects.push(
+            InferredProjectMessage::new(
+                manifest.package_name.clone(),
+                project_dir.display().to_string(),
+                "cargo",
+                manifest.workspace_dependencies,
+            )
+            .with_kind(ProjectKind::Library),
+        );
+        bin_dependencies.push(manifest.package_name.clone());
+    }
+    for bin in bins {
+        projects.push(
+            InferredProjectMessage::new(
+                format!("{}-{}", manifest.package_name, bin),
+                project_dir.display().to_string(),
+                "cargo",
+                bin_dependencies.clone(),
+            )
+            .with_kind(ProjectKind::Application),
+        );
+    }
+    Some(projects)
+}
+
+/// Names of the package's binary targets: `[[bin]]` entries and, unless `autobins` is off,
+/// `src/main.rs` (named after the package) and `src/bin/*.rs` or `src/bin/*/main.rs`
+fn binary_targets(manifest: &Value, project_dir: &Path) -> BTreeSet<String> {
+    let mut bins = BTreeSet::new();
+    let Some(package) = manifest.get("package") else {
+        return bins;
+    };
+    for bin in manifest
+        .get("bin")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let Some(name) = bin.get("name").and_then(Value::as_str) {
+            bins.insert(name.to_string());
+        }
+    }
+
+    if package.get("autobins").and_then(Value::as_bool) == Some(false) {
+        return bins;
+    }
+    if project_dir.join("src/main.rs").is_file() {
+        if let Some(name) = package.get("name").and_then(Value::as_str) {
+            bins.insert(name.to_string());
+        }
+    }
+    for entry in std::fs::read_dir(project_dir.join("src/bin"))
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let path = entry.path();
+        let name = if path.extension().is_some_and(|ext| ext == "rs") {
+            path.file_stem()
+        } else if path.join("main.rs").is_file() {
+            path.file_name()
+        } else {
+            None
+        };
+        if let Some(name) = name.and_then(|name| name.to_str()) {
+            bins.insert(name.to_string());
+        }
+    }
+    bins
+}
+
+fn has_library(manifest: &Value, project_dir: &Path) -> bool {
+    manifest.get("lib").is_some() || project_dir.join("src/lib.rs").is_file()
 }
 
 struct ParsedManifest {
     package_name: String,
     workspace_dependencies: Vec<String>,
+    kind: Option<ProjectKind>,
 }
 
 fn parse_manifest(manifest_contents: &str, project_dir: &Path) -> Option<ParsedManifest> {
@@ -139,10 +488,26 @@ fn parse_manifest(manifest_contents: &str, project_dir: &Path) -> Option<ParsedM
         })?;
 
     let workspace_dependencies = collect_workspace_dependencies(&manifest_value, project_dir);
+    let kind = classify(&manifest_value, &package_name, project_dir);
 
     Some(ParsedManifest {
         package_name,
         workspace_dependencies,
+        kind,
+    })
+}
+
+/// `xtask` crates are tools, crates with a binary target applications and other packages
+/// libraries. Virtual workspace manifests have no kind.
+fn classify(manifest: &Value, package_name: &str, project_dir: &Path) -> Option<ProjectKind> {
+    manifest.get("package")?;
+    if package_name == "xtask" {
+        return Some(ProjectKind::Tool);
+    }
+    Some(if !binary_targets(manifest, project_dir).is_empty() {
+        ProjectKind::Application
+    } else {
+        ProjectKind::Library
     })
 }
 
@@ -189,6 +554,51 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn adds_created_projects_to_workspace_members() {
+        let multiline = "[workspace]\nmembers = [\n    \"crates/core\",\n    \"tools/gen\"\n]\n";
+        assert_eq!(
+            add_workspace_member(multiline, "tools/lint").unwrap(),
+            "[workspace]\nmembers = [\n    \"crates/core\",\n    \"tools/gen\",\n    \"tools/lint\"\n]\n"
+        );
+        assert_eq!(
+            add_workspace_member("[workspace]\nmembers = [\"a\"]\n", "b").unwrap(),
+            "[workspace]\nmembers = [\"a\", \"b\"]\n"
+        );
+
+        // Members already covered by a pattern or excluded stay as they are
+        let globbed = "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"scratch\"]\n";
+        assert!(add_workspace_member(globbed, "crates/new").is_none());
+        assert!(add_workspace_member(globbed, "scratch").is_none());
+        assert!(add_workspace_member("[package]\nname = \"solo\"\n", "lib").is_none());
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        std::fs::create_dir_all(root.join("libs/new")).unwrap();
+        let workspace = Workspace {
+            root: root.to_path_buf(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+        };
+        let plugin = CargoPlugin::new();
+        assert!(plugin
+            .on_project_created(&workspace, &root.join("libs/new"))
+            .is_empty());
+
+        std::fs::write(
+            root.join("libs/new/Cargo.toml"),
+            "[package]\nname = \"new\"\n",
+        )
+        .unwrap();
+        let notes = plugin.on_project_created(&workspace, &root.join("libs/new"));
+        assert_eq!(notes.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(root.join("Cargo.toml")).unwrap(),
+            "[workspace]\nmembers = [\"libs/new\"]\n"
+        );
+    }
+
     #[test]
     fn extracts_path_dependencies_and_package_name() {
         let temp_dir = tempdir().expect("tempdir should be created");
@@ -228,4 +638,138 @@ lib-crate = { path = "../lib" }
             vec!["lib-crate".to_string()]
         );
     }
+
+    #[test]
+    fn reports_manifests_that_are_not_valid_toml() {
+        let temp_dir = tempdir().expect("tempdir should be created");
+        let manifest = temp_dir.path().join("Cargo.toml");
+        std::fs::write(&manifest, "[package]\nname = \"app\nversion = \"0.1.0\"\n").unwrap();
+        let workspace = Workspace {
+            root: temp_dir.path().to_path_buf(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+        };
+
+        let provider = CargoPlugin::new().provider;
+        let error = provider
+            .try_on_file_found(&workspace, &manifest)
+            .expect_err("broken manifest should be reported");
+        assert!(error.message.starts_with("Invalid Cargo.toml"));
+        assert_eq!(error.span.map(|span| span.start_line), Some(2));
+        assert!(provider.on_file_found(&workspace, &manifest).is_none());
+    }
+
+    #[test]
+    fn classifies_binaries_libraries_and_tools() {
+        let temp_dir = tempdir().expect("tempdir should be created");
+        let root = temp_dir.path();
+        let kind = |name: &str, manifest: &str, files: &[&str]| {
+            let dir = root.join(name);
+            for file in files {
+                let path = dir.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, "").unwrap();
+            }
+            std::fs::create_dir_all(&dir).unwrap();
+            process_manifest(&dir.join("Cargo.toml"), manifest).and_then(|message| message.kind)
+        };
+
+        let package = "[package]\nname = \"pkg\"\n";
+        assert_eq!(
+            kind("server", package, &["src/main.rs", "src/lib.rs"]),
+            Some(ProjectKind::Application)
+        );
+        assert_eq!(
+            kind(
+                "cli",
+                &format!("{}[[bin]]\nname = \"cli\"\npath = \"cli.rs\"\n", package),
+                &[]
+            ),
+            Some(ProjectKind::Application)
+        );
+        assert_eq!(
+            kind("lib", package, &["src/lib.rs"]),
+            Some(ProjectKind::Library)
+        );
+        assert_eq!(
+            kind("xtask", "[package]\nname = \"xtask\"\n", &["src/main.rs"]),
+            Some(ProjectKind::Tool)
+        );
+        assert_eq!(kind("root", "[workspace]\nmembers = [\"*\"]\n", &[]), None);
+    }
+
+    #[test]
+    fn splits_binary_targets_into_projects() {
+        let temp_dir = tempdir().expect("tempdir should be created");
+        let dir = temp_dir.path().join("services");
+        for file in [
+            "src/lib.rs",
+            "src/main.rs",
+            "src/bin/worker.rs",
+            "src/bin/api/main.rs",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let manifest = r#"
+[package]
+name = "services"
+
+[[bin]]
+name = "cron"
+path = "cron.rs"
+
+[dependencies]
+shared = { path = "../shared" }
+"#;
+
+        let projects = process_manifest_per_target(&dir.join("Cargo.toml"), manifest)
+            .expect("package has several binaries");
+        let summary: Vec<_> = projects
+            .iter()
+            .map(|p| (p.name.as_str(), p.kind, p.workspace_dependencies.clone()))
+            .collect();
+        let bin_deps = vec!["shared".to_string(), "services".to_string()];
+        assert_eq!(
+            summary,
+            [
+                (
+                    "services",
+                    Some(ProjectKind::Library),
+                    vec!["shared".to_string()]
+                ),
+                (
+                    "services-api",
+                    Some(ProjectKind::Application),
+                    bin_deps.clone()
+                ),
+                (
+                    "services-cron",
+                    Some(ProjectKind::Application),
+                    bin_deps.clone()
+                ),
+                (
+                    "services-services",
+                    Some(ProjectKind::Application),
+                    bin_deps.clone()
+                ),
+                ("services-worker", Some(ProjectKind::Application), bin_deps),
+            ]
+        );
+
+        let single = "[package]\nname = \"single\"\nautobins = false\n";
+        assert!(process_manifest_per_target(&dir.join("Cargo.toml"), single).is_none());
+    }
+
+    #[test]
+    fn defines_build_test_and_lint_tasks() {
+        let tasks = CargoPlugin::new().default_tasks();
+        let names: Vec<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "test", "lint"]);
+        assert_eq!(
+            tasks[0].command,
+            r#"cargo build --manifest-path "$MARTY_PROJECT_DIR/Cargo.toml""#
+        );
+    }
 }
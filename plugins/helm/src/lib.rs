@@ -0,0 +1,269 @@
+use std::path::Path;
+
+use marty_plugin_protocol::{
+    dylib::export_plugin, InferredProject, MartyPlugin, PluginType, ProjectKind, SuggestedTask,
+    Workspace, WorkspaceProvider,
+};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+const PLUGIN_KEY: &str = "helm";
+
+#[derive(Debug, Deserialize)]
+struct ChartYaml {
+    name: String,
+    /// `application` (the default) or `library`
+    #[serde(default, rename = "type")]
+    chart_type: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<ChartDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartDependency {
+    name: String,
+    /// A chart repository URL, an `@alias` of one, or `file://<path>` for a local chart
+    #[serde(default)]
+    repository: Option<String>,
+}
+
+/// Main Helm plugin struct
+pub struct HelmPlugin;
+
+/// Workspace provider for Helm charts
+pub struct HelmWorkspaceProvider;
+
+impl Default for HelmPlugin {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl HelmPlugin {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl WorkspaceProvider for HelmWorkspaceProvider {
+    fn include_path_globs(&self) -> Vec<String> {
+        vec!["**/Chart.yaml".to_string()]
+    }
+
+    fn exclude_path_globs(&self) -> Vec<String> {
+        ignore_path_globs()
+    }
+
+    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        if path.file_name()? != "Chart.yaml" {
+            return None;
+        }
+        let contents = std::fs::read_to_string(path).ok()?;
+        process_chart_yaml(path, &contents)
+    }
+}
+
+impl MartyPlugin for HelmPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "Helm Chart Plugin"
+    }
+
+    fn key(&self) -> &str {
+        PLUGIN_KEY
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Discovers Helm charts and their file:// dependencies")
+    }
+
+    fn version(&self) -> Option<&str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        Some("https://github.com/codyspate/marty")
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        &HelmWorkspaceProvider
+    }
+
+    fn configuration_options(&self) -> Option<JsonValue> {
+        Some(json!({
+            "type": "object",
+            "description": "Discovers Helm charts from Chart.yaml files, and makes them depend on the local charts they pull in through file:// repositories.",
+            "properties": {
+                "includes": {
+                    "type": "array",
+                    "description": "Additional glob patterns to include in scanning",
+                    "items": {
+                        "type": "string"
+                    },
+                    "default": []
+                },
+                "excludes": {
+                    "type": "array",
+                    "description": "Additional glob patterns to exclude from scanning",
+                    "items": {
+                        "type": "string"
+                    },
+                    "default": []
+                }
+            },
+            "additionalProperties": false
+        }))
+    }
+
+    fn suggested_tasks(&self) -> Vec<SuggestedTask> {
+        vec![
+            SuggestedTask {
+                name: "lint".to_string(),
+                command: r#"helm lint "$MARTY_PROJECT_DIR""#.to_string(),
+                description: Some("Check the chart for possible issues".to_string()),
+                dependencies: Vec::new(),
+            },
+            SuggestedTask {
+                name: "template".to_string(),
+                command: r#"helm template "$MARTY_PROJECT_DIR""#.to_string(),
+                description: Some("Render the chart's manifests locally".to_string()),
+                dependencies: Vec::new(),
+            },
+            SuggestedTask {
+                name: "package".to_string(),
+                command: concat!(
+                    r#"helm package "$MARTY_PROJECT_DIR" --dependency-update"#,
+                    r#" --destination "$MARTY_PROJECT_DIR/dist""#
+                )
+                .to_string(),
+                description: Some(
+                    "Package the chart and its local dependencies into dist/".to_string(),
+                ),
+                dependencies: vec!["lint".to_string(), "^package".to_string()],
+            },
+        ]
+    }
+}
+
+// Export the plugin using the dynamic library interface
+export_plugin!(HelmPlugin);
+
+pub fn ignore_path_globs() -> Vec<String> {
+    vec![
+        "**/node_modules/**".to_string(),
+        "**/.git/**".to_string(),
+        "**/target/**".to_string(),
+    ]
+}
+
+/// A project for the chart of a Chart.yaml. Library charts are libraries, every other
+/// chart is an application.
+pub fn process_chart_yaml(manifest_path: &Path, contents: &str) -> Option<InferredProject> {
+    let chart: ChartYaml = serde_yaml::from_str(contents).ok()?;
+    let project_dir = manifest_path.parent()?.to_path_buf();
+    let kind = match chart.chart_type.as_deref() {
+        Some("library") => ProjectKind::Library,
+        _ => ProjectKind::Application,
+    };
+    let workspace_dependencies = local_dependencies(&project_dir, &chart.dependencies);
+
+    Some(InferredProject {
+        name: chart.name,
+        project_dir,
+        discovered_by: PLUGIN_KEY.to_string(),
+        workspace_dependencies,
+        kind: Some(kind),
+    })
+}
+
+/// Names of the charts pulled in through `file://` repositories. The chart at the path
+/// decides the name; a dependency whose Chart.yaml can't be read keeps its declared name.
+fn local_dependencies(chart_dir: &Path, dependencies: &[ChartDependency]) -> Vec<String> {
+    let mut names: Vec<String> = dependencies
+        .iter()
+        .filter_map(|dependency| {
+            let path = dependency.repository.as_deref()?.strip_prefix("file://")?;
+            let name = std::fs::read_to_string(chart_dir.join(path).join("Chart.yaml"))
+                .ok()
+                .and_then(|contents| serde_yaml::from_str::<ChartYaml>(&contents).ok())
+                .map(|chart| chart.name)
+                .unwrap_or_else(|| dependency.name.clone());
+            Some(name)
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn discovers_charts_and_local_dependencies() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let write = |path: &str, contents: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        write(
+            "charts/common/Chart.yaml",
+            "apiVersion: v2\nname: acme-common\ntype: library\nversion: 0.1.0\n",
+        );
+        write(
+            "charts/web/Chart.yaml",
+            r#"apiVersion: v2
+name: web
+version: 1.2.0
+dependencies:
+  - name: acme-common
+    version: 0.1.0
+    repository: "file://../common"
+  - name: worker
+    version: 0.1.0
+    repository: file://../worker
+  - name: postgresql
+    version: 15.5.0
+    repository: https://charts.bitnami.com/bitnami
+  - name: redis
+    version: 19.0.0
+    repository: "@bitnami"
+"#,
+        );
+
+        let common = root.join("charts/common/Chart.yaml");
+        let project = process_chart_yaml(&common, &fs::read_to_string(&common).unwrap()).unwrap();
+        assert_eq!(project.name, "acme-common");
+        assert_eq!(project.kind, Some(ProjectKind::Library));
+        assert!(project.workspace_dependencies.is_empty());
+
+        let web = root.join("charts/web/Chart.yaml");
+        let project = process_chart_yaml(&web, &fs::read_to_string(&web).unwrap()).unwrap();
+        assert_eq!(project.name, "web");
+        assert_eq!(project.project_dir, root.join("charts/web"));
+        assert_eq!(project.kind, Some(ProjectKind::Application));
+        // The worker chart doesn't exist (yet), so it keeps its declared name
+        assert_eq!(
+            project.workspace_dependencies,
+            vec!["acme-common", "worker"]
+        );
+
+        assert!(process_chart_yaml(&web, "not: [a chart").is_none());
+    }
+
+    #[test]
+    fn suggests_chart_tasks() {
+        let tasks = HelmPlugin::new().suggested_tasks();
+        let names: Vec<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["lint", "template", "package"]);
+        assert!(tasks[2].dependencies.contains(&"^package".to_string()));
+    }
+}
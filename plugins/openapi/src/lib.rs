@@ -0,0 +1,294 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use marty_plugin_protocol::{
+    dylib::export_plugin, plugin_options, InferredProject, MartyPlugin, PluginType, ProjectKind,
+    Workspace, WorkspaceMutation, WorkspaceProvider,
+};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+const PLUGIN_KEY: &str = "openapi";
+
+/// Options of the OpenAPI plugin in workspace.yml
+#[derive(Debug, Default, Deserialize)]
+pub struct OpenApiPluginConfig {
+    #[serde(default)]
+    pub mappings: Vec<SpecMapping>,
+}
+
+/// Projects generated from a spec, which must be regenerated and rebuilt when it changes
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpecMapping {
+    /// Name of the spec project
+    pub spec: String,
+    /// Names of the client and server projects generated from it
+    pub projects: Vec<String>,
+}
+
+/// Main OpenAPI plugin struct
+pub struct OpenApiPlugin {
+    provider: OpenApiWorkspaceProvider,
+}
+
+/// Workspace provider for OpenAPI specs
+pub struct OpenApiWorkspaceProvider {
+    /// `mappings`: the projects generated from each spec
+    mappings: Mutex<Vec<SpecMapping>>,
+}
+
+impl Default for OpenApiPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenApiPlugin {
+    pub const fn new() -> Self {
+        Self {
+            provider: OpenApiWorkspaceProvider {
+                mappings: Mutex::new(Vec::new()),
+            },
+        }
+    }
+}
+
+impl WorkspaceProvider for OpenApiWorkspaceProvider {
+    fn include_path_globs(&self) -> Vec<String> {
+        ["openapi", "*.openapi", "swagger"]
+            .iter()
+            .flat_map(|stem| {
+                ["yaml", "yml", "json"]
+                    .iter()
+                    .map(move |extension| format!("**/{}.{}", stem, extension))
+            })
+            .collect()
+    }
+
+    fn exclude_path_globs(&self) -> Vec<String> {
+        ignore_path_globs()
+    }
+
+    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        process_spec(path, &contents)
+    }
+
+    fn on_scan_complete(&self, workspace: &Workspace) -> Vec<WorkspaceMutation> {
+        let mappings = self.mappings.lock().map(|m| m.clone()).unwrap_or_default();
+        mapping_dependencies(workspace, &mappings)
+    }
+}
+
+impl MartyPlugin for OpenApiPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "OpenAPI Spec Plugin"
+    }
+
+    fn key(&self) -> &str {
+        PLUGIN_KEY
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Ties projects generated from OpenAPI specs to the spec they come from")
+    }
+
+    fn version(&self) -> Option<&str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        Some("https://github.com/codyspate/marty")
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        &self.provider
+    }
+
+    fn configure(&self, options: &JsonValue) {
+        let config = plugin_options::<OpenApiPluginConfig>(options).unwrap_or_default();
+        if let Ok(mut mappings) = self.provider.mappings.lock() {
+            *mappings = config.mappings;
+        }
+    }
+
+    fn configuration_options(&self) -> Option<JsonValue> {
+        Some(json!({
+            "type": "object",
+            "description": "Discovers OpenAPI and Swagger specs (openapi.yaml, <name>.openapi.yaml, swagger.json, ...) as library projects, and makes the projects generated from a spec depend on it.",
+            "properties": {
+                "mappings": {
+                    "type": "array",
+                    "description": "The client and server projects generated from each spec",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "spec": {
+                                "type": "string",
+                                "description": "Name of the spec project"
+                            },
+                            "projects": {
+                                "type": "array",
+                                "description": "Projects generated from the spec",
+                                "items": {
+                                    "type": "string"
+                                }
+                            }
+                        },
+                        "required": ["spec", "projects"],
+                        "additionalProperties": false
+                    },
+                    "default": []
+                },
+                "includes": {
+                    "type": "array",
+                    "description": "Additional glob patterns to include in scanning",
+                    "items": {
+                        "type": "string"
+                    },
+                    "default": []
+                },
+                "excludes": {
+                    "type": "array",
+                    "description": "Additional glob patterns to exclude from scanning",
+                    "items": {
+                        "type": "string"
+                    },
+                    "default": []
+                }
+            },
+            "additionalProperties": false
+        }))
+    }
+}
+
+// Export the plugin using the dynamic library interface
+export_plugin!(OpenApiPlugin);
+
+pub fn ignore_path_globs() -> Vec<String> {
+    vec![
+        "**/node_modules/**".to_string(),
+        "**/.git/**".to_string(),
+        "**/target/**".to_string(),
+    ]
+}
+
+/// A library project for a spec file, named `<name>` for `<name>.openapi.yaml` and after
+/// its directory otherwise. Files without a top-level `openapi` or `swagger` version
+/// aren't specs.
+pub fn process_spec(path: &Path, contents: &str) -> Option<InferredProject> {
+    // YAML is a superset of JSON, so one parser reads both
+    let document: serde_yaml::Value = serde_yaml::from_str(contents).ok()?;
+    if document.get("openapi").is_none() && document.get("swagger").is_none() {
+        return None;
+    }
+
+    let project_dir = path.parent()?.to_path_buf();
+    let file_name = path.file_name()?.to_str()?;
+    let name = match file_name.split_once(".openapi.") {
+        Some((name, _)) if !name.is_empty() => name.to_string(),
+        _ => project_dir.file_name()?.to_str()?.to_string(),
+    };
+    Some(InferredProject {
+        name,
+        project_dir,
+        discovered_by: PLUGIN_KEY.to_string(),
+        workspace_dependencies: Vec::new(),
+        kind: Some(ProjectKind::Library),
+    })
+}
+
+/// Dependencies of the generated projects on their spec. Mappings naming a spec this
+/// plugin didn't discover are skipped with a warning.
+pub fn mapping_dependencies(
+    workspace: &Workspace,
+    mappings: &[SpecMapping],
+) -> Vec<WorkspaceMutation> {
+    let mut mutations = Vec::new();
+    for mapping in mappings {
+        if !workspace
+            .inferred_projects
+            .iter()
+            .any(|p| p.discovered_by == PLUGIN_KEY && p.name == mapping.spec)
+        {
+            eprintln!(
+                "Warning: OpenAPI mapping names unknown spec '{}'",
+                mapping.spec
+            );
+            continue;
+        }
+        for project in &mapping.projects {
+            mutations.push(WorkspaceMutation::AddDependency {
+                project: project.clone(),
+                dependency: mapping.spec.clone(),
+            });
+        }
+    }
+    mutations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovers_specs() {
+        let spec = "openapi: 3.1.0\ninfo:\n  title: Orders\n  version: 1.0.0\npaths: {}\n";
+        let project = process_spec(Path::new("/repo/api/orders/openapi.yaml"), spec).unwrap();
+        assert_eq!(project.name, "orders");
+        assert_eq!(project.project_dir, Path::new("/repo/api/orders"));
+        assert_eq!(project.kind, Some(ProjectKind::Library));
+
+        let swagger = r#"{"swagger": "2.0", "info": {"title": "Billing"}}"#;
+        let project = process_spec(Path::new("/repo/api/billing.openapi.json"), swagger).unwrap();
+        assert_eq!(project.name, "billing");
+
+        // A config file that happens to share the name
+        assert!(process_spec(Path::new("/repo/tools/openapi.yaml"), "generator: go\n").is_none());
+    }
+
+    #[test]
+    fn links_generated_projects_to_their_spec() {
+        let project = |name: &str, discovered_by: &str| InferredProject {
+            name: name.to_string(),
+            project_dir: Path::new("/repo").join(name),
+            discovered_by: discovered_by.to_string(),
+            workspace_dependencies: Vec::new(),
+            kind: None,
+        };
+        let workspace = Workspace {
+            root: Path::new("/repo").to_path_buf(),
+            projects: vec![],
+            inferred_projects: vec![
+                project("orders", PLUGIN_KEY),
+                project("orders-client", "pnpm"),
+                project("orders-server", "cargo"),
+            ],
+        };
+        let config: OpenApiPluginConfig = serde_json::from_value(json!({
+            "mappings": [
+                { "spec": "orders", "projects": ["orders-client", "orders-server"] },
+                { "spec": "orders-client", "projects": ["orders"] }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            mapping_dependencies(&workspace, &config.mappings),
+            vec![
+                WorkspaceMutation::AddDependency {
+                    project: "orders-client".to_string(),
+                    dependency: "orders".to_string(),
+                },
+                WorkspaceMutation::AddDependency {
+                    project: "orders-server".to_string(),
+                    dependency: "orders".to_string(),
+                },
+            ]
+        );
+    }
+}
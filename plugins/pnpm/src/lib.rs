@@ -1,9 +1,9 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use marty_plugin_protocol::{
-    dylib::export_plugin, InferredProject, InferredProjectMessage, MartyPlugin, PluginType,
-    Workspace, WorkspaceProvider,
+    InferredProject, InferredProjectMessage, MartyPlugin, PluginType, ProjectKind, Workspace,
+    WorkspaceProvider,
 };
 use serde::Deserialize;
 use serde_json::{json, Value as JsonValue};
@@ -13,6 +13,10 @@ struct PackageJson {
     #[serde(default)]
     name: Option<String>,
     #[serde(default)]
+    private: bool,
+    #[serde(default)]
+    bin: Option<JsonValue>,
+    #[serde(default)]
     dependencies: serde_json::Map<String, serde_json::Value>,
     #[serde(default, rename = "devDependencies")]
     dev_dependencies: serde_json::Map<String, serde_json::Value>,
@@ -20,6 +24,17 @@ struct PackageJson {
     optional_dependencies: serde_json::Map<String, serde_json::Value>,
     #[serde(default, rename = "peerDependencies")]
     peer_dependencies: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    resolutions: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pnpm: PnpmSettings,
+}
+
+/// The `pnpm` field of a package.json
+#[derive(Debug, Default, Deserialize)]
+struct PnpmSettings {
+    #[serde(default)]
+    overrides: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Main PNPM plugin struct
@@ -66,6 +81,7 @@ impl WorkspaceProvider for PnpmWorkspaceProvider {
             project_dir: std::path::PathBuf::from(message.project_dir),
             discovered_by: message.discovered_by,
             workspace_dependencies: message.workspace_dependencies,
+            kind: message.kind,
         })
     }
 }
@@ -83,10 +99,48 @@ impl MartyPlugin for PnpmPlugin {
         "pnpm"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("Discovers PNPM workspace packages and their workspace dependencies")
+    }
+
+    fn version(&self) -> Option<&str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        Some("https://github.com/codyspate/marty")
+    }
+
     fn workspace_provider(&self) -> &dyn WorkspaceProvider {
         &PnpmWorkspaceProvider
     }
 
+    fn on_project_created(&self, workspace: &Workspace, project_dir: &Path) -> Vec<String> {
+        if !project_dir.join("package.json").is_file() {
+            return Vec::new();
+        }
+        let Some(package) = relative_package(&workspace.root, project_dir) else {
+            return Vec::new();
+        };
+        let config = workspace.root.join("pnpm-workspace.yaml");
+        let Some(updated) = std::fs::read_to_string(&config)
+            .ok()
+            .and_then(|contents| add_workspace_package(&contents, &package))
+        else {
+            return Vec::new();
+        };
+        match std::fs::write(&config, updated) {
+            Ok(()) => vec![format!(
+                "Added {} to the packages in pnpm-workspace.yaml",
+                package
+            )],
+            Err(e) => vec![format!(
+                "Failed to add {} to pnpm-workspace.yaml: {}",
+                package, e
+            )],
+        }
+    }
+
     fn configuration_options(&self) -> Option<JsonValue> {
         Some(json!({
             "type": "object",
@@ -114,7 +168,122 @@ impl MartyPlugin for PnpmPlugin {
 }
 
 // Export the plugin using the dynamic library interface
-export_plugin!(PnpmPlugin);
+#[cfg(feature = "dylib")]
+marty_plugin_protocol::dylib::export_plugin!(PnpmPlugin);
+
+/// `project_dir` relative to the workspace root, with `/` separators
+fn relative_package(root: &Path, project_dir: &Path) -> Option<String> {
+    let relative = project_dir.strip_prefix(root).ok()?;
+    let parts: Option<Vec<&str>> = relative
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect();
+    let package = parts?.join("/");
+    (!package.is_empty()).then_some(package)
+}
+
+/// Add `package` to the `packages` list of a pnpm-workspace.yaml, after its last entry.
+/// `None` if there is no block-style list, or one of its patterns already matches.
+fn add_workspace_package(contents: &str, package: &str) -> Option<String> {
+    let mut in_packages = false;
+    let mut found = false;
+    let mut patterns = Vec::new();
+    // End of the last entry, and its indentation
+    let mut last_entry: Option<(usize, &str)> = None;
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let indented = line.starts_with(char::is_whitespace);
+        if !indented && trimmed.starts_with("packages:") {
+            if !trimmed["packages:".len()..].trim().is_empty() {
+                // Flow-style lists like `packages: [...]` are left alone
+                return None;
+            }
+            in_packages = true;
+            found = true;
+        } else if in_packages {
+            if let Some(entry) = trimmed.strip_prefix('-') {
+                patterns.push(
+                    entry
+                        .trim()
+                        .trim_matches(|c| c == '\'' || c == '"')
+                        .to_string(),
+                );
+                let indent = &line[..line.len() - line.trim_start().len()];
+                last_entry = Some((offset + line.trim_end().len(), indent));
+            } else if !trimmed.is_empty() && !trimmed.starts_with('#') && !indented {
+                in_packages = false;
+            }
+        }
+        offset += line.len();
+    }
+    if !found {
+        return None;
+    }
+
+    let mut included = false;
+    for pattern in &patterns {
+        match pattern.strip_prefix('!') {
+            Some(excluded) if matches_package_pattern(excluded, package) => return None,
+            Some(_) => {}
+            None => included |= matches_package_pattern(pattern, package),
+        }
+    }
+    if included {
+        return None;
+    }
+
+    let (end, indent) = match last_entry {
+        Some(entry) => entry,
+        None => {
+            let end = contents.find("packages:")? + "packages:".len();
+            (end, "  ")
+        }
+    };
+    Some(format!(
+        "{}\n{}- '{}'{}",
+        &contents[..end],
+        indent,
+        package,
+        &contents[end..]
+    ))
+}
+
+/// Whether `path` matches a pnpm workspace pattern like `packages/*` or `apps/**`
+fn matches_package_pattern(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .split('/')
+        .collect();
+    let path: Vec<&str> = path.split('/').collect();
+    matches_segments(&pattern, &path)
+}
+
+fn matches_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((&"**", rest)), _) => {
+            matches_segments(rest, path)
+                || (!path.is_empty() && matches_segments(pattern, &path[1..]))
+        }
+        (Some((segment_pattern, pattern_rest)), Some((segment, path_rest))) => {
+            matches_segment(segment_pattern, segment) && matches_segments(pattern_rest, path_rest)
+        }
+        _ => false,
+    }
+}
+
+fn matches_segment(pattern: &str, segment: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == segment,
+        Some((prefix, rest)) => segment.strip_prefix(prefix).is_some_and(|tail| {
+            (0..=tail.len())
+                .filter(|&i| tail.is_char_boundary(i))
+                .any(|i| matches_segment(rest, &tail[i..]))
+        }),
+    }
+}
 
 pub fn ignore_path_globs() -> Vec<String> {
     vec![
@@ -144,16 +313,35 @@ pub fn process_package_json(
 
     let dependencies = gather_workspace_dependencies(&manifest);
 
-    Some(InferredProjectMessage::new(
-        name,
-        project_dir.display().to_string(),
-        "pnpm",
-        dependencies,
-    ))
+    Some(
+        InferredProjectMessage::new(
+            name,
+            project_dir.display().to_string(),
+            "pnpm",
+            dependencies,
+        )
+        .with_kind(classify(&manifest)),
+    )
+}
+
+/// Packages with a `bin` entry are tools, other private packages applications and
+/// publishable packages libraries.
+fn classify(manifest: &PackageJson) -> ProjectKind {
+    if manifest.bin.is_some() {
+        ProjectKind::Tool
+    } else if manifest.private {
+        ProjectKind::Application
+    } else {
+        ProjectKind::Library
+    }
 }
 
+/// Names of the workspace packages a manifest depends on: dependencies declared with a
+/// local protocol, or overridden with one through `pnpm.overrides` or `resolutions`.
+/// Bundled dependencies must also be declared in `dependencies`, so a bundled workspace
+/// package is found through its spec there.
 fn gather_workspace_dependencies(manifest: &PackageJson) -> Vec<String> {
-    let mut names = HashSet::new();
+    let mut specs: HashMap<&str, &str> = HashMap::new();
     for map in [
         &manifest.dependencies,
         &manifest.dev_dependencies,
@@ -162,23 +350,112 @@ fn gather_workspace_dependencies(manifest: &PackageJson) -> Vec<String> {
     ] {
         for (dep_name, dep_value) in map {
             if let Some(value_str) = dep_value.as_str() {
-                if value_str.starts_with("workspace:") || value_str.starts_with("file:") {
-                    names.insert(dep_name.clone());
+                specs.insert(dep_name, value_str);
+            }
+        }
+    }
+
+    let mut names = HashSet::new();
+    // Overrides replace the version of a package anywhere in the dependency tree, so a
+    // local one links the workspace package in even if no manifest names it directly
+    for map in [&manifest.pnpm.overrides, &manifest.resolutions] {
+        for (selector, value) in map {
+            if let Some(value_str) = value.as_str() {
+                let dep_name = overridden_package(selector);
+                if is_local_spec(value_str) {
+                    names.insert(dep_name.to_string());
                 }
+                specs.insert(dep_name, value_str);
             }
         }
     }
 
+    names.extend(
+        specs
+            .iter()
+            .filter(|(_, spec)| is_local_spec(spec))
+            .map(|(dep_name, _)| dep_name.to_string()),
+    );
+
     let mut result: Vec<String> = names.into_iter().collect();
     result.sort_unstable();
     result
 }
 
+/// Whether a version spec points into the workspace rather than to the registry
+fn is_local_spec(spec: &str) -> bool {
+    ["workspace:", "file:", "link:"]
+        .iter()
+        .any(|protocol| spec.starts_with(protocol))
+}
+
+/// The package an override selector applies to, e.g. `shared` for pnpm's
+/// `app@1>shared@^2` or yarn's `**/@scope/app/shared`
+fn overridden_package(selector: &str) -> &str {
+    let selector = selector.rsplit('>').next().unwrap_or(selector);
+    // Yarn selectors are paths; keep the scope of the last segment
+    let segments: Vec<&str> = selector.split('/').collect();
+    let name = match segments.as_slice() {
+        [.., scope, name] if scope.starts_with('@') => {
+            &selector[selector.len() - scope.len() - name.len() - 1..]
+        }
+        [.., name] => name,
+        [] => selector,
+    };
+    // Drop the version range, which follows an `@` that isn't the scope's
+    match name.get(1..).and_then(|rest| rest.find('@')) {
+        Some(i) => &name[..i + 1],
+        None => name,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn adds_created_projects_to_workspace_packages() {
+        let config = "packages:\n  - 'apps/*'\n  - tools/gen\n\ncatalog:\n  react: ^18\n";
+        assert_eq!(
+            add_workspace_package(config, "libs/ui").unwrap(),
+            "packages:\n  - 'apps/*'\n  - tools/gen\n  - 'libs/ui'\n\ncatalog:\n  react: ^18\n"
+        );
+        assert_eq!(
+            add_workspace_package("packages:\n", "web").unwrap(),
+            "packages:\n  - 'web'\n"
+        );
+
+        // Packages already matched or excluded stay as they are
+        let globbed = "packages:\n  - 'packages/**'\n  - '!**/test/**'\n";
+        assert!(add_workspace_package(globbed, "packages/a/b").is_none());
+        assert!(add_workspace_package(globbed, "apps/test/e2e").is_none());
+        assert!(add_workspace_package("packages: ['*']\n", "web").is_none());
+        assert!(add_workspace_package("catalog: {}\n", "web").is_none());
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(
+            root.join("pnpm-workspace.yaml"),
+            "packages:\n  - 'apps/*'\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join("libs/ui")).unwrap();
+        std::fs::write(root.join("libs/ui/package.json"), r#"{"name": "ui"}"#).unwrap();
+        let workspace = Workspace {
+            root: root.to_path_buf(),
+            projects: Vec::new(),
+            inferred_projects: Vec::new(),
+        };
+
+        let notes = PnpmPlugin.on_project_created(&workspace, &root.join("libs/ui"));
+        assert_eq!(notes.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(root.join("pnpm-workspace.yaml")).unwrap(),
+            "packages:\n  - 'apps/*'\n  - 'libs/ui'\n"
+        );
+    }
+
     #[test]
     fn extracts_workspace_dependencies() {
         let temp_dir = tempdir().unwrap();
@@ -205,5 +482,56 @@ mod tests {
         assert_eq!(message.discovered_by, "pnpm");
         assert_eq!(message.project_dir, project_dir.display().to_string());
         assert_eq!(message.workspace_dependencies, vec!["builder", "shared"]);
+        assert_eq!(message.kind, Some(ProjectKind::Library));
+    }
+
+    #[test]
+    fn classifies_private_packages_and_binaries() {
+        let path = Path::new("/repo/pkg/package.json");
+        let kind = |manifest: &str| process_package_json(path, manifest).and_then(|m| m.kind);
+
+        assert_eq!(
+            kind(r#"{"name": "web", "private": true}"#),
+            Some(ProjectKind::Application)
+        );
+        assert_eq!(
+            kind(r#"{"name": "cli", "private": true, "bin": {"cli": "bin.js"}}"#),
+            Some(ProjectKind::Tool)
+        );
+    }
+
+    #[test]
+    fn follows_overrides_and_resolutions() {
+        let manifest = r#"
+{
+  "name": "web-app",
+  "dependencies": {
+    "shared": "^1.0.0",
+    "linked": "link:../linked",
+    "react": "18.2.0",
+    "utils": "workspace:*"
+  },
+  "bundledDependencies": ["shared", "react"],
+  "resolutions": {
+    "**/@scope/ui": "workspace:*",
+    "lodash": "4.17.21"
+  },
+  "pnpm": {
+    "overrides": {
+      "shared": "workspace:^",
+      "utils": "2.0.0",
+      "react@18>scheduler@^0.23": "file:../scheduler"
+    }
+  }
+}
+"#;
+
+        let message = process_package_json(Path::new("/repo/web-app/package.json"), manifest)
+            .expect("should produce inferred project");
+
+        assert_eq!(
+            message.workspace_dependencies,
+            vec!["@scope/ui", "linked", "scheduler", "shared"]
+        );
     }
 }
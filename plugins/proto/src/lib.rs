@@ -0,0 +1,422 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use marty_plugin_protocol::{
+    dylib::export_plugin, InferredProject, MartyPlugin, PluginError, PluginType, ProjectKind,
+    ProjectMessage, Workspace, WorkspaceMutation, WorkspaceProvider,
+};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+const PLUGIN_KEY: &str = "proto";
+
+#[derive(Debug, Default, Deserialize)]
+struct BufYaml {
+    #[serde(default)]
+    version: Option<String>,
+    /// Name of a v1 module, e.g. `buf.build/acme/payments`
+    #[serde(default)]
+    name: Option<String>,
+    /// Modules of a v2 workspace
+    #[serde(default)]
+    modules: Vec<BufModule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BufModule {
+    path: String,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Main Protobuf plugin struct
+pub struct ProtoPlugin;
+
+/// Workspace provider for Protobuf projects
+pub struct ProtoWorkspaceProvider;
+
+impl Default for ProtoPlugin {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl ProtoPlugin {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl WorkspaceProvider for ProtoWorkspaceProvider {
+    fn include_path_globs(&self) -> Vec<String> {
+        vec!["**/buf.yaml".to_string(), "**/*.proto".to_string()]
+    }
+
+    fn exclude_path_globs(&self) -> Vec<String> {
+        ignore_path_globs()
+    }
+
+    fn on_file_found(&self, _workspace: &Workspace, path: &Path) -> Option<InferredProject> {
+        discover(path).into_iter().next()
+    }
+
+    // A v2 buf.yaml declares any number of modules, and every .proto file of a directory
+    // reports the same project again, which replacing it makes harmless
+    fn on_file_found_messages(
+        &self,
+        _workspace: &Workspace,
+        path: &Path,
+    ) -> Result<Vec<ProjectMessage>, PluginError> {
+        let is_proto = path.extension().is_some_and(|ext| ext == "proto");
+        Ok(discover(path)
+            .into_iter()
+            .map(|project| match is_proto {
+                true => ProjectMessage::UpdateProject {
+                    project: project.into(),
+                },
+                false => ProjectMessage::AddProject {
+                    project: project.into(),
+                },
+            })
+            .collect())
+    }
+
+    fn on_scan_complete(&self, workspace: &Workspace) -> Vec<WorkspaceMutation> {
+        import_dependencies(workspace)
+            .into_iter()
+            .map(|(project, dependency)| WorkspaceMutation::AddDependency {
+                project,
+                dependency,
+            })
+            .collect()
+    }
+}
+
+impl MartyPlugin for ProtoPlugin {
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Primary
+    }
+
+    fn name(&self) -> &str {
+        "Protobuf Plugin"
+    }
+
+    fn key(&self) -> &str {
+        PLUGIN_KEY
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Discovers Protobuf modules from buf.yaml and .proto imports")
+    }
+
+    fn version(&self) -> Option<&str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        Some("https://github.com/codyspate/marty")
+    }
+
+    fn workspace_provider(&self) -> &dyn WorkspaceProvider {
+        &ProtoWorkspaceProvider
+    }
+
+    fn configuration_options(&self) -> Option<JsonValue> {
+        Some(json!({
+            "type": "object",
+            "description": "Discovers buf modules and directories of .proto files, and makes them depend on the projects owning the files they import.",
+            "properties": {
+                "includes": {
+                    "type": "array",
+                    "description": "Additional glob patterns to include in scanning",
+                    "items": {
+                        "type": "string"
+                    },
+                    "default": []
+                },
+                "excludes": {
+                    "type": "array",
+                    "description": "Additional glob patterns to exclude from scanning",
+                    "items": {
+                        "type": "string"
+                    },
+                    "default": []
+                }
+            },
+            "additionalProperties": false
+        }))
+    }
+}
+
+// Export the plugin using the dynamic library interface
+export_plugin!(ProtoPlugin);
+
+pub fn ignore_path_globs() -> Vec<String> {
+    vec![
+        "**/node_modules/**".to_string(),
+        "**/.git/**".to_string(),
+        "**/target/**".to_string(),
+    ]
+}
+
+/// The projects a buf.yaml or .proto file reveals
+fn discover(path: &Path) -> Vec<InferredProject> {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some("buf.yaml") => std::fs::read_to_string(path)
+            .map(|contents| process_buf_yaml(path, &contents))
+            .unwrap_or_default(),
+        Some(name) if name.ends_with(".proto") => process_proto_file(path).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The projects a buf.yaml declares: its own directory for a v1 module, or every module
+/// of a v2 workspace
+pub fn process_buf_yaml(manifest_path: &Path, contents: &str) -> Vec<InferredProject> {
+    let Ok(config) = serde_yaml::from_str::<BufYaml>(contents) else {
+        return Vec::new();
+    };
+    let Some(dir) = manifest_path.parent() else {
+        return Vec::new();
+    };
+
+    if config.version.as_deref() == Some("v2") {
+        return config
+            .modules
+            .iter()
+            .filter_map(|module| project(dir.join(&module.path), module.name.as_deref()))
+            .collect();
+    }
+    project(dir.to_path_buf(), config.name.as_deref())
+        .into_iter()
+        .collect()
+}
+
+/// A project for the directory of a .proto file outside any buf module. The search for a
+/// buf.yaml stops at the workspace root, the directory holding `.marty`.
+pub fn process_proto_file(path: &Path) -> Option<InferredProject> {
+    let dir = path.parent()?;
+    for ancestor in dir.ancestors() {
+        if ancestor.join("buf.yaml").is_file() {
+            return None;
+        }
+        if ancestor.join(".marty").is_dir() {
+            break;
+        }
+    }
+    project(dir.to_path_buf(), None)
+}
+
+/// A library project for `dir`, named after the last segment of the buf module name or
+/// else the directory
+fn project(dir: PathBuf, module_name: Option<&str>) -> Option<InferredProject> {
+    let name = match module_name.and_then(|name| name.rsplit('/').next()) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => dir.file_name()?.to_str()?.to_string(),
+    };
+    Some(InferredProject {
+        name,
+        project_dir: dir,
+        discovered_by: PLUGIN_KEY.to_string(),
+        workspace_dependencies: Vec::new(),
+        kind: Some(ProjectKind::Library),
+    })
+}
+
+/// Dependencies between Protobuf projects implied by import statements, as
+/// `(project, dependency)` pairs. An import resolves against the directory of every
+/// Protobuf project and the workspace root, and the project owning the first file it
+/// finds is the dependency.
+pub fn import_dependencies(workspace: &Workspace) -> BTreeSet<(String, String)> {
+    let projects: Vec<&InferredProject> = workspace
+        .inferred_projects
+        .iter()
+        .filter(|p| p.discovered_by == PLUGIN_KEY)
+        .collect();
+    let roots: Vec<&Path> = projects
+        .iter()
+        .map(|p| p.project_dir.as_path())
+        .chain([workspace.root.as_path()])
+        .collect();
+
+    let mut dependencies = BTreeSet::new();
+    for project in &projects {
+        let mut files = Vec::new();
+        proto_files(&project.project_dir, &mut files);
+        for file in files {
+            // Files of a nested project belong to that one
+            if owner(&projects, &file) != Some(project.name.as_str()) {
+                continue;
+            }
+            let Ok(contents) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            for import in parse_imports(&contents) {
+                let Some(target) = roots
+                    .iter()
+                    .map(|root| root.join(&import))
+                    .find(|candidate| candidate.is_file())
+                else {
+                    continue;
+                };
+                if let Some(dependency) = owner(&projects, &target) {
+                    if dependency != project.name {
+                        dependencies.insert((project.name.clone(), dependency.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    dependencies
+}
+
+/// The project with the deepest directory containing `path`
+fn owner<'a>(projects: &[&'a InferredProject], path: &Path) -> Option<&'a str> {
+    projects
+        .iter()
+        .filter(|p| path.starts_with(&p.project_dir))
+        .max_by_key(|p| p.project_dir.components().count())
+        .map(|p| p.name.as_str())
+}
+
+/// Every .proto file below `dir`, skipping directories of installed dependencies
+fn proto_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            let name = entry.file_name();
+            if !matches!(name.to_str(), Some("node_modules" | ".git" | "target")) {
+                proto_files(&path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "proto") {
+            files.push(path);
+        }
+    }
+}
+
+/// The paths of a .proto file's `import`, `import public` and `import weak` statements
+pub fn parse_imports(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("import")?;
+            let rest = rest.trim_start();
+            let rest = rest
+                .strip_prefix("public")
+                .or_else(|| rest.strip_prefix("weak"))
+                .unwrap_or(rest)
+                .trim_start();
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let path = &rest[1..];
+            Some(path[..path.find(quote)?].to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_import_statements() {
+        let contents = r#"
+syntax = "proto3";
+package acme.orders;
+
+import "common/money.proto";
+import public 'common/ids.proto';
+import weak "legacy/old.proto";
+// import "commented/out.proto";
+importer = "not an import";
+"#;
+        assert_eq!(
+            parse_imports(contents),
+            vec!["common/money.proto", "common/ids.proto", "legacy/old.proto"]
+        );
+    }
+
+    #[test]
+    fn discovers_modules_and_links_imports() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let write = |path: &str, contents: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        write(
+            "common/buf.yaml",
+            "version: v1\nname: buf.build/acme/common\n",
+        );
+        write("common/acme/money.proto", "syntax = \"proto3\";\n");
+        write(
+            "orders/buf.yaml",
+            "version: v2\nmodules:\n  - path: proto\n  - path: internal\n    name: buf.build/acme/orders-internal\n",
+        );
+        write(
+            "orders/proto/orders.proto",
+            "import \"acme/money.proto\";\nimport \"google/protobuf/timestamp.proto\";\n",
+        );
+        write("orders/internal/audit.proto", "import \"orders.proto\";\n");
+        write(
+            "legacy/events.proto",
+            "import \"common/acme/money.proto\";\n",
+        );
+        write("legacy/schema.proto", "syntax = \"proto2\";\n");
+
+        let mut workspace = Workspace {
+            root: root.to_path_buf(),
+            projects: vec![],
+            inferred_projects: vec![],
+        };
+        for file in [
+            "common/buf.yaml",
+            "common/acme/money.proto",
+            "orders/buf.yaml",
+            "orders/proto/orders.proto",
+            "legacy/events.proto",
+            "legacy/schema.proto",
+        ] {
+            let messages = ProtoWorkspaceProvider
+                .on_file_found_messages(&workspace, &root.join(file))
+                .unwrap();
+            for message in messages {
+                match message {
+                    ProjectMessage::AddProject { project } => {
+                        workspace.inferred_projects.push(project.into())
+                    }
+                    ProjectMessage::UpdateProject { project } => {
+                        workspace
+                            .inferred_projects
+                            .retain(|p| p.name != project.name);
+                        workspace.inferred_projects.push(project.into());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let names: Vec<&str> = workspace
+            .inferred_projects
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["common", "proto", "orders-internal", "legacy"]);
+
+        let dependencies: Vec<_> = import_dependencies(&workspace).into_iter().collect();
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            dependencies,
+            vec![
+                pair("legacy", "common"),
+                pair("orders-internal", "proto"),
+                pair("proto", "common"),
+            ]
+        );
+    }
+}
@@ -1,9 +1,12 @@
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 use marty_plugin_protocol::{
-    dylib::export_plugin, InferredProject, InferredProjectMessage, MartyPlugin, PluginType,
-    Workspace, WorkspaceProvider,
+    plugin_options, InferredProject, InferredProjectMessage, MartyPlugin, PluginType, Project,
+    TaskOutcome, Workspace, WorkspaceMutation, WorkspaceProvider,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
@@ -22,21 +25,47 @@ struct TsReference {
     path: Option<String>,
 }
 
+/// The `compilerOptions` of a tsconfig.json that path aliases depend on
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct TsCompilerOptions {
+    base_url: Option<String>,
+    #[serde(default)]
+    paths: HashMap<String, Vec<String>>,
+}
+
+/// Task the plugin runs itself: bring a project's tsconfig.json references in line with
+/// its workspace dependencies, whether or not `auto_project_references` is on
+pub const SYNC_REFERENCES_TASK: &str = "sync-references";
+
 /// Main TypeScript plugin struct
-pub struct TypeScriptPlugin;
+pub struct TypeScriptPlugin {
+    provider: TypeScriptWorkspaceProvider,
+    /// Options from workspace.yml, for keeping project references in sync after discovery
+    options: Mutex<Option<JsonValue>>,
+}
 
 /// Workspace provider for TypeScript projects  
-pub struct TypeScriptWorkspaceProvider;
+#[derive(Default)]
+pub struct TypeScriptWorkspaceProvider {
+    /// `path_alias_dependencies`: infer dependencies from `compilerOptions.paths`
+    path_alias_dependencies: AtomicBool,
+}
 
 impl Default for TypeScriptPlugin {
     fn default() -> Self {
-        Self
+        Self::new()
     }
 }
 
 impl TypeScriptPlugin {
     pub const fn new() -> Self {
-        Self
+        Self {
+            provider: TypeScriptWorkspaceProvider {
+                path_alias_dependencies: AtomicBool::new(false),
+            },
+            options: Mutex::new(None),
+        }
     }
 }
 
@@ -57,6 +86,19 @@ impl WorkspaceProvider for TypeScriptWorkspaceProvider {
         // This plugin only provides TypeScript-specific enhancements like project references
         None
     }
+
+    fn on_scan_complete(&self, workspace: &Workspace) -> Vec<WorkspaceMutation> {
+        if !self.path_alias_dependencies.load(Ordering::Relaxed) {
+            return Vec::new();
+        }
+        path_alias_dependencies(workspace)
+            .into_iter()
+            .map(|(project, dependency)| WorkspaceMutation::AddDependency {
+                project,
+                dependency,
+            })
+            .collect()
+    }
 }
 
 impl MartyPlugin for TypeScriptPlugin {
@@ -72,8 +114,48 @@ impl MartyPlugin for TypeScriptPlugin {
         "typescript"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("Keeps TypeScript project references in line with workspace dependencies")
+    }
+
+    fn version(&self) -> Option<&str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn homepage(&self) -> Option<&str> {
+        Some("https://github.com/codyspate/marty")
+    }
+
     fn workspace_provider(&self) -> &dyn WorkspaceProvider {
-        &TypeScriptWorkspaceProvider
+        &self.provider
+    }
+
+    fn configure(&self, options: &JsonValue) {
+        let config = plugin_options::<TypeScriptPluginConfig>(options).unwrap_or_default();
+        self.provider
+            .path_alias_dependencies
+            .store(config.path_alias_dependencies, Ordering::Relaxed);
+        if let Ok(mut stored) = self.options.lock() {
+            *stored = Some(options.clone());
+        }
+    }
+
+    // Rewrite tsconfig.json references once other plugins have settled the dependencies
+    fn post_discovery(&self, workspace: &Workspace) -> Vec<String> {
+        let options = self.options.lock().ok().and_then(|options| options.clone());
+        update_workspace_project_references(workspace, options.as_ref())
+            .unwrap_or_else(|e| vec![format!("Failed to update project references: {}", e)])
+    }
+
+    fn run_task(&self, task: &str, project: &Project, workspace: &Workspace) -> TaskOutcome {
+        if task != SYNC_REFERENCES_TASK {
+            return TaskOutcome::failure(format!("The TypeScript plugin has no task '{}'", task));
+        }
+        let options = self.options.lock().ok().and_then(|options| options.clone());
+        let config = options
+            .and_then(|options| plugin_options::<TypeScriptPluginConfig>(&options).ok())
+            .unwrap_or_default();
+        sync_project_references(project, workspace, &config.reference_path_style)
     }
 
     fn configuration_options(&self) -> Option<JsonValue> {
@@ -91,6 +173,11 @@ impl MartyPlugin for TypeScriptPlugin {
                     "description": "Style for generating project reference paths",
                     "enum": ["relative", "tsconfig"],
                     "default": "relative"
+                },
+                "path_alias_dependencies": {
+                    "type": "boolean",
+                    "description": "Infer workspace dependencies from compilerOptions.paths aliases whose targets lie inside other projects' directories",
+                    "default": false
                 }
             },
             "additionalProperties": false
@@ -99,7 +186,8 @@ impl MartyPlugin for TypeScriptPlugin {
 }
 
 // Export the plugin using the dynamic library interface
-export_plugin!(TypeScriptPlugin);
+#[cfg(feature = "dylib")]
+marty_plugin_protocol::dylib::export_plugin!(TypeScriptPlugin);
 
 pub fn ignore_path_globs() -> Vec<String> {
     vec![
@@ -139,6 +227,82 @@ pub fn update_project_references(
     workspace_dependencies: &[String],
     workspace: &Workspace,
     reference_path_style: &str,
+) -> anyhow::Result<bool> {
+    write_project_references(
+        tsconfig_path,
+        workspace_dependencies,
+        &project_dirs(workspace),
+        reference_path_style,
+    )
+}
+
+/// Directory of every project by name. Explicit projects win over inferred ones, and the
+/// first project of a name over later ones.
+fn project_dirs(workspace: &Workspace) -> HashMap<&str, &Path> {
+    let mut dirs = HashMap::new();
+    for (name, dir) in workspace
+        .projects
+        .iter()
+        .map(|p| (&p.name, &p.project_dir))
+        .chain(
+            workspace
+                .inferred_projects
+                .iter()
+                .map(|p| (&p.name, &p.project_dir)),
+        )
+    {
+        dirs.entry(name.as_str()).or_insert(dir.as_path());
+    }
+    dirs
+}
+
+/// Rewrite the references of `project`'s tsconfig.json, for the `sync-references` task
+fn sync_project_references(
+    project: &Project,
+    workspace: &Workspace,
+    reference_path_style: &str,
+) -> TaskOutcome {
+    let tsconfig_path = project.project_dir.join("tsconfig.json");
+    if !tsconfig_path.exists() {
+        return TaskOutcome::success(vec![format!(
+            "No tsconfig.json in {}, nothing to sync",
+            project.project_dir.display()
+        )]);
+    }
+
+    // Dependencies found by other plugins, or those declared in marty.yml
+    let dependencies = workspace
+        .inferred_projects
+        .iter()
+        .find(|p| p.name == project.name)
+        .map_or(&project.dependencies, |p| &p.workspace_dependencies);
+    match write_project_references(
+        &tsconfig_path,
+        dependencies,
+        &project_dirs(workspace),
+        reference_path_style,
+    ) {
+        Ok(true) => TaskOutcome::success(vec![format!(
+            "Updated project references in {}",
+            tsconfig_path.display()
+        )]),
+        Ok(false) => TaskOutcome::success(vec![format!(
+            "Project references in {} are up to date",
+            tsconfig_path.display()
+        )]),
+        Err(e) => TaskOutcome::failure(format!(
+            "Failed to update project references in {}: {}",
+            tsconfig_path.display(),
+            e
+        )),
+    }
+}
+
+fn write_project_references(
+    tsconfig_path: &Path,
+    workspace_dependencies: &[String],
+    project_dirs: &HashMap<&str, &Path>,
+    reference_path_style: &str,
 ) -> anyhow::Result<bool> {
     // Read and parse existing tsconfig.json
     let contents = fs::read_to_string(tsconfig_path)?;
@@ -148,21 +312,7 @@ pub fn update_project_references(
     let mut new_references = Vec::new();
 
     for dep_name in workspace_dependencies {
-        // Find the dependency project in the workspace
-        let dep_project = workspace
-            .projects
-            .iter()
-            .find(|p| &p.name == dep_name)
-            .map(|p| &p.project_dir)
-            .or_else(|| {
-                workspace
-                    .inferred_projects
-                    .iter()
-                    .find(|p| &p.name == dep_name)
-                    .map(|p| &p.project_dir)
-            });
-
-        if let Some(dep_dir) = dep_project {
+        if let Some(dep_dir) = project_dirs.get(dep_name.as_str()) {
             let current_dir = tsconfig_path.parent().unwrap_or_else(|| Path::new("."));
 
             // Calculate relative path to the dependency
@@ -217,6 +367,66 @@ pub fn update_project_references(
     Ok(true) // Changes were made
 }
 
+/// Dependencies that the `compilerOptions.paths` aliases of every project's tsconfig.json
+/// imply, as `(project, dependency)` pairs: an alias target inside another project's
+/// directory makes the project depend on that one. Targets resolve against `baseUrl`, or
+/// the tsconfig.json's directory without one.
+pub fn path_alias_dependencies(workspace: &Workspace) -> BTreeSet<(String, String)> {
+    let project_dirs = project_dirs(workspace);
+    let mut dependencies = BTreeSet::new();
+    for project in &workspace.inferred_projects {
+        let Some(options) = read_compiler_options(&project.project_dir.join("tsconfig.json"))
+        else {
+            continue;
+        };
+        let base = match &options.base_url {
+            Some(base_url) => project.project_dir.join(base_url),
+            None => project.project_dir.clone(),
+        };
+        for target in options.paths.values().flatten() {
+            // Only the fixed part of a wildcard target says where it points
+            let fixed = target.split('*').next().unwrap_or(target);
+            let path = normalize(&base.join(fixed));
+            if let Some(owner) = owning_project(&project_dirs, &path) {
+                if owner != project.name {
+                    dependencies.insert((project.name.clone(), owner.to_string()));
+                }
+            }
+        }
+    }
+    dependencies
+}
+
+fn read_compiler_options(tsconfig_path: &Path) -> Option<TsCompilerOptions> {
+    let contents = fs::read_to_string(tsconfig_path).ok()?;
+    let config: TsConfig = serde_json::from_str(&contents).ok()?;
+    serde_json::from_value(config.other.get("compilerOptions")?.clone()).ok()
+}
+
+/// The project with the deepest directory containing `path`
+fn owning_project<'a>(project_dirs: &HashMap<&'a str, &Path>, path: &Path) -> Option<&'a str> {
+    project_dirs
+        .iter()
+        .filter(|(_, dir)| path.starts_with(normalize(dir)))
+        .max_by_key(|(_, dir)| dir.components().count())
+        .map(|(name, _)| *name)
+}
+
+/// Resolve `.` and `..` in a path without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
 /// Configuration options for the TypeScript plugin
 #[derive(Debug, Deserialize, Default)]
 pub struct TypeScriptPluginConfig {
@@ -224,6 +434,8 @@ pub struct TypeScriptPluginConfig {
     pub auto_project_references: bool,
     #[serde(default = "default_reference_path_style")]
     pub reference_path_style: String,
+    #[serde(default)]
+    pub path_alias_dependencies: bool,
 }
 
 fn default_reference_path_style() -> String {
@@ -252,6 +464,7 @@ pub fn update_workspace_project_references(
     }
 
     let mut updated_projects = Vec::new();
+    let project_dirs = project_dirs(workspace);
 
     // Process ALL inferred projects (from any plugin) that have a tsconfig.json file
     // This allows TypeScript plugin to enhance projects discovered by PNPM, NPM, etc.
@@ -260,10 +473,10 @@ pub fn update_workspace_project_references(
 
         // Only process if tsconfig.json exists and project has workspace dependencies
         if tsconfig_path.exists() && !project.workspace_dependencies.is_empty() {
-            match update_project_references(
+            match write_project_references(
                 &tsconfig_path,
                 &project.workspace_dependencies,
-                workspace,
+                &project_dirs,
                 &config.reference_path_style,
             ) {
                 Ok(true) => {
@@ -298,7 +511,7 @@ mod tests {
     #[test]
     fn typescript_plugin_does_not_discover_projects() {
         // TypeScript plugin no longer discovers projects - that's the job of PNPM/NPM plugins
-        let provider = TypeScriptWorkspaceProvider;
+        let provider = TypeScriptWorkspaceProvider::default();
 
         // Should return empty include patterns
         assert_eq!(provider.include_path_globs(), Vec::<String>::new());
@@ -356,12 +569,14 @@ mod tests {
                     project_dir: shared_dir.clone(),
                     discovered_by: "pnpm".to_string(),
                     workspace_dependencies: vec![],
+                    kind: None,
                 },
                 InferredProject {
                     name: "ui".to_string(),
                     project_dir: ui_dir.clone(),
                     discovered_by: "pnpm".to_string(),
                     workspace_dependencies: vec!["shared".to_string()],
+                    kind: None,
                 },
             ],
         };
@@ -407,6 +622,7 @@ mod tests {
                 project_dir: shared_dir.clone(),
                 discovered_by: "pnpm".to_string(),
                 workspace_dependencies: vec![],
+                kind: None,
             }],
         };
 
@@ -429,6 +645,186 @@ mod tests {
         );
     }
 
+    #[test]
+    fn infers_dependencies_from_path_aliases() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let project = |name: &str| {
+            let dir = root.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            InferredProject {
+                name: name.replace('/', "-"),
+                project_dir: dir,
+                discovered_by: "pnpm".to_string(),
+                workspace_dependencies: vec![],
+                kind: None,
+            }
+        };
+        let workspace = Workspace {
+            root: root.to_path_buf(),
+            projects: vec![],
+            inferred_projects: vec![
+                project("web"),
+                project("libs"),
+                project("libs/ui"),
+                project("api"),
+            ],
+        };
+        fs::write(
+            root.join("web/tsconfig.json"),
+            r#"{
+  "compilerOptions": {
+    "baseUrl": "..",
+    "paths": {
+      "@/*": ["web/src/*"],
+      "@ui/*": ["libs/ui/src/*"],
+      "@common": ["./libs/common.ts"],
+      "react": ["node_modules/react"]
+    }
+  }
+}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("api/tsconfig.json"),
+            r#"{"compilerOptions": {"paths": {"@web/*": ["../web/src/*"]}}}"#,
+        )
+        .unwrap();
+
+        let dependencies: Vec<_> = path_alias_dependencies(&workspace).into_iter().collect();
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            dependencies,
+            vec![
+                pair("api", "web"),
+                pair("web", "libs"),
+                pair("web", "libs-ui")
+            ]
+        );
+
+        let plugin = TypeScriptPlugin::new();
+        assert!(plugin
+            .workspace_provider()
+            .on_scan_complete(&workspace)
+            .is_empty());
+        plugin.configure(&json!({"path_alias_dependencies": true}));
+        assert_eq!(
+            plugin
+                .workspace_provider()
+                .on_scan_complete(&workspace)
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn post_discovery_updates_references_when_enabled() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        for dir in ["web", "shared"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let tsconfig = root.join("web/tsconfig.json");
+        fs::write(&tsconfig, r#"{"compilerOptions": {"composite": true}}"#).unwrap();
+
+        let project = |name: &str, dependencies: Vec<String>| InferredProject {
+            name: name.to_string(),
+            project_dir: root.join(name),
+            discovered_by: "pnpm".to_string(),
+            workspace_dependencies: dependencies,
+            kind: None,
+        };
+        let workspace = Workspace {
+            root: root.to_path_buf(),
+            projects: vec![],
+            inferred_projects: vec![
+                project("web", vec!["shared".to_string()]),
+                project("shared", vec![]),
+            ],
+        };
+
+        let plugin = TypeScriptPlugin::new();
+        assert!(plugin.post_discovery(&workspace).is_empty());
+        let config: TsConfig =
+            serde_json::from_str(&fs::read_to_string(&tsconfig).unwrap()).unwrap();
+        assert!(config.references.is_empty());
+
+        plugin.configure(&json!({ "auto_project_references": true }));
+        let notes = plugin.post_discovery(&workspace);
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("web"));
+        let config: TsConfig =
+            serde_json::from_str(&fs::read_to_string(&tsconfig).unwrap()).unwrap();
+        assert_eq!(config.references[0].path.as_deref(), Some("../shared"));
+
+        // Already up to date
+        assert!(plugin.post_discovery(&workspace).is_empty());
+    }
+
+    #[test]
+    fn sync_references_task_updates_one_project() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        for dir in ["web", "shared", "docs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let tsconfig = root.join("web/tsconfig.json");
+        fs::write(&tsconfig, r#"{"compilerOptions": {"composite": true}}"#).unwrap();
+
+        let workspace = Workspace {
+            root: root.to_path_buf(),
+            projects: vec![],
+            inferred_projects: vec![
+                InferredProject {
+                    name: "web".to_string(),
+                    project_dir: root.join("web"),
+                    discovered_by: "pnpm".to_string(),
+                    workspace_dependencies: vec!["shared".to_string()],
+                    kind: None,
+                },
+                InferredProject {
+                    name: "shared".to_string(),
+                    project_dir: root.join("shared"),
+                    discovered_by: "pnpm".to_string(),
+                    workspace_dependencies: vec![],
+                    kind: None,
+                },
+            ],
+        };
+        let project = |name: &str| Project {
+            name: name.to_string(),
+            project_dir: root.join(name),
+            file_path: None,
+            dependencies: vec![],
+        };
+
+        // Runs even though automatic updates are off
+        let plugin = TypeScriptPlugin::new();
+        plugin.configure(&json!({ "reference_path_style": "tsconfig" }));
+        let outcome = plugin.run_task(SYNC_REFERENCES_TASK, &project("web"), &workspace);
+        assert!(outcome.success, "{:?}", outcome.output);
+        assert!(outcome.output[0].starts_with("Updated"));
+        let config: TsConfig =
+            serde_json::from_str(&fs::read_to_string(&tsconfig).unwrap()).unwrap();
+        assert_eq!(
+            config.references[0].path.as_deref(),
+            Some("../shared/tsconfig.json")
+        );
+
+        let outcome = plugin.run_task(SYNC_REFERENCES_TASK, &project("web"), &workspace);
+        assert!(outcome.output[0].ends_with("are up to date"));
+        assert!(
+            plugin
+                .run_task(SYNC_REFERENCES_TASK, &project("docs"), &workspace)
+                .success
+        );
+        assert!(
+            !plugin
+                .run_task("build", &project("web"), &workspace)
+                .success
+        );
+    }
+
     #[test]
     fn configuration_parsing() {
         let config_json = json!({